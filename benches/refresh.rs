@@ -0,0 +1,73 @@
+// Benchmarks for the work behind every refresh: listing interfaces, parsing
+// scan output and drawing a frame. Run with `cargo bench`; the interface
+// benchmark reads the machine it runs on, the others are self-contained.
+use criterion::{criterion_group, criterion_main, Criterion};
+use lantern::app::App;
+use lantern::iwd::parse_iw_scan_output;
+use lantern::network::{parse_wifi_scan_results, NetworkManager};
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use std::hint::black_box;
+use tokio::runtime::Runtime;
+
+// A crowded scan: the captured samples repeated until there are ~100 BSSes
+fn crowded_scan() -> String {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus/iw-scan");
+    let mut samples: Vec<String> = std::fs::read_dir(dir)
+        .expect("corpus should be readable")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect();
+    samples.sort();
+
+    let mut scan = String::new();
+    while scan.matches("\nBSS ").count() < 100 {
+        for sample in &samples {
+            scan.push('\n');
+            scan.push_str(sample);
+        }
+    }
+    scan
+}
+
+fn get_interfaces(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("get_interfaces");
+    group.sample_size(20); // Shells out several times per interface
+
+    let system = NetworkManager::new();
+    group.bench_function("system", |b| {
+        b.iter(|| runtime.block_on(system.get_interfaces()).unwrap())
+    });
+    let demo = NetworkManager::demo();
+    group.bench_function("demo", |b| {
+        b.iter(|| runtime.block_on(demo.get_interfaces()).unwrap())
+    });
+    group.finish();
+}
+
+fn scan_parsing(c: &mut Criterion) {
+    let scan = crowded_scan();
+    let mut group = c.benchmark_group("scan_parsing");
+    group.bench_function("network", |b| {
+        b.iter(|| parse_wifi_scan_results(black_box(&scan)))
+    });
+    group.bench_function("iwd", |b| b.iter(|| parse_iw_scan_output(black_box(&scan))));
+    group.finish();
+}
+
+fn draw(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut app = runtime.block_on(App::demo()).unwrap();
+    let mut terminal = Terminal::new(TestBackend::new(160, 48)).unwrap();
+
+    c.bench_function("draw", |b| {
+        b.iter(|| {
+            terminal.draw(|f| lantern::ui::draw(f, &mut app)).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, get_interfaces, scan_parsing, draw);
+criterion_main!(benches);
@@ -0,0 +1,423 @@
+// src/agentx.rs - Read-only AgentX (RFC 2741) subagent: answers Get, GetNext and GetBulk from a snapshot
+use anyhow::{anyhow, Result};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+pub type Oid = Vec<u32>;
+
+/// Objects by OID, sorted so GetNext is a search
+pub type Mib = Vec<(Oid, Value)>;
+
+const HEADER_LEN: usize = 20;
+const VERSION: u8 = 1;
+
+// Flags
+const NON_DEFAULT_CONTEXT: u8 = 0x08;
+const NETWORK_BYTE_ORDER: u8 = 0x10;
+
+// PDU types
+const OPEN: u8 = 1;
+const CLOSE: u8 = 2;
+const REGISTER: u8 = 3;
+const GET: u8 = 5;
+const GET_NEXT: u8 = 6;
+const GET_BULK: u8 = 7;
+const TEST_SET: u8 = 8;
+const COMMIT_SET: u8 = 9;
+const UNDO_SET: u8 = 10;
+const CLEANUP_SET: u8 = 11;
+const RESPONSE: u8 = 18;
+
+// res.error
+const NOT_WRITABLE: u16 = 17;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i32),
+    OctetString(String),
+    Gauge32(u32),
+    Counter64(u64),
+    NoSuchObject,
+    EndOfMibView,
+}
+
+impl Value {
+    fn tag(&self) -> u16 {
+        match self {
+            Value::Integer(_) => 2,
+            Value::OctetString(_) => 4,
+            Value::Gauge32(_) => 66,
+            Value::Counter64(_) => 70,
+            Value::NoSuchObject => 128,
+            Value::EndOfMibView => 130,
+        }
+    }
+}
+
+/// "1.3.6.1.4.1.8072" as sub-identifiers.
+pub fn parse_oid(text: &str) -> Result<Oid> {
+    let oid: Oid = text
+        .trim()
+        .trim_start_matches('.')
+        .split('.')
+        .map(|part| part.parse::<u32>())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| anyhow!("'{}' is not an OID", text.trim()))?;
+    if oid.len() < 2 {
+        return Err(anyhow!("'{}' is too short for an OID", text.trim()));
+    }
+    Ok(oid)
+}
+
+// PDUs are written in network byte order, and say so in their flags
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn oid(&mut self, oid: &[u32], include: bool) {
+        self.u8(oid.len() as u8);
+        self.u8(0); // No prefix compression
+        self.u8(include as u8);
+        self.u8(0);
+        for &sub in oid {
+            self.u32(sub);
+        }
+    }
+
+    fn octets(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.0.extend_from_slice(bytes);
+        // Padded to a multiple of four
+        self.0.resize(self.0.len() + (4 - bytes.len() % 4) % 4, 0);
+    }
+
+    fn varbind(&mut self, name: &[u32], value: &Value) {
+        self.u16(value.tag());
+        self.u16(0);
+        self.oid(name, false);
+        match value {
+            Value::Integer(value) => self.u32(*value as u32),
+            Value::OctetString(text) => self.octets(text.as_bytes()),
+            Value::Gauge32(value) => self.u32(*value),
+            Value::Counter64(value) => self.0.extend_from_slice(&value.to_be_bytes()),
+            Value::NoSuchObject | Value::EndOfMibView => {}
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        if self.bytes.len() < len {
+            return Err(anyhow!("truncated AgentX PDU"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes: [u8; 2] = self.take(2)?.try_into()?;
+        Ok(match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes: [u8; 4] = self.take(4)?.try_into()?;
+        Ok(match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    }
+
+    fn oid(&mut self) -> Result<(Oid, bool)> {
+        let count = self.u8()?;
+        let prefix = self.u8()?;
+        let include = self.u8()? != 0;
+        self.u8()?;
+        // A prefix abbreviates 1.3.6.1.<prefix>
+        let mut oid = match prefix {
+            0 => Vec::new(),
+            prefix => vec![1, 3, 6, 1, prefix as u32],
+        };
+        for _ in 0..count {
+            oid.push(self.u32()?);
+        }
+        Ok((oid, include))
+    }
+
+    fn octets(&mut self) -> Result<()> {
+        let len = self.u32()? as usize;
+        self.take(len + (4 - len % 4) % 4)?;
+        Ok(())
+    }
+
+    /// Start (with its include flag) and end of each range until the end
+    fn search_ranges(&mut self) -> Result<Vec<(Oid, bool, Oid)>> {
+        let mut ranges = Vec::new();
+        while !self.bytes.is_empty() {
+            let (start, include) = self.oid()?;
+            let (end, _) = self.oid()?;
+            ranges.push((start, include, end));
+        }
+        Ok(ranges)
+    }
+}
+
+fn pdu(kind: u8, session: u32, transaction: u32, packet: u32, payload: &[u8]) -> Vec<u8> {
+    let mut pdu = Writer(Vec::with_capacity(HEADER_LEN + payload.len()));
+    pdu.u8(VERSION);
+    pdu.u8(kind);
+    pdu.u8(NETWORK_BYTE_ORDER);
+    pdu.u8(0);
+    pdu.u32(session);
+    pdu.u32(transaction);
+    pdu.u32(packet);
+    pdu.u32(payload.len() as u32);
+    pdu.0.extend_from_slice(payload);
+    pdu.0
+}
+
+/// The object at `oid`, for Get
+fn get(mib: &Mib, oid: &[u32]) -> Value {
+    mib.binary_search_by(|(name, _)| name.as_slice().cmp(oid))
+        .map(|index| mib[index].1.clone())
+        .unwrap_or(Value::NoSuchObject)
+}
+
+/// The first object after `start` (or at it, with `include`) and before
+/// `end` when that isn't empty, for GetNext
+fn next(mib: &Mib, start: &[u32], include: bool, end: &[u32]) -> Option<(Oid, Value)> {
+    let index = mib.partition_point(|(name, _)| match include {
+        true => name.as_slice() < start,
+        false => name.as_slice() <= start,
+    });
+    mib.get(index)
+        .filter(|(name, _)| end.is_empty() || name.as_slice() < end)
+        .cloned()
+}
+
+/// The varbinds answering a Get, GetNext or GetBulk payload.
+fn answer(kind: u8, payload: &mut Reader, mib: &Mib) -> Result<Vec<(Oid, Value)>> {
+    let (non_repeaters, repetitions) = match kind {
+        GET_BULK => (payload.u16()? as usize, payload.u16()? as usize),
+        _ => (0, 0),
+    };
+    let ranges = payload.search_ranges()?;
+    if kind == GET {
+        return Ok(ranges
+            .into_iter()
+            .map(|(start, _, _)| {
+                let value = get(mib, &start);
+                (start, value)
+            })
+            .collect());
+    }
+
+    let mut varbinds = Vec::new();
+    let step = |(start, include, end): &mut (Oid, bool, Oid)| match next(mib, start, *include, end)
+    {
+        Some((name, value)) => {
+            (*start, *include) = (name.clone(), false);
+            (name, value)
+        }
+        None => (start.clone(), Value::EndOfMibView),
+    };
+    let split = match kind {
+        GET_BULK => non_repeaters.min(ranges.len()),
+        _ => ranges.len(),
+    };
+    let (mut once, mut repeated) = (ranges[..split].to_vec(), ranges[split..].to_vec());
+    varbinds.extend(once.iter_mut().map(step));
+    // A row per repetition, each repeater once in it
+    for _ in 0..repetitions {
+        if repeated.is_empty() {
+            break;
+        }
+        let row: Vec<(Oid, Value)> = repeated.iter_mut().map(step).collect();
+        let finished = row.iter().all(|(_, value)| *value == Value::EndOfMibView);
+        varbinds.extend(row);
+        if finished {
+            break;
+        }
+    }
+    Ok(varbinds)
+}
+
+async fn read_pdu(stream: &mut UnixStream) -> Result<(u8, u8, [u32; 3], Vec<u8>)> {
+    let mut header = [0u8; HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+    let flags = header[2];
+    let mut reader = Reader {
+        bytes: &header[4..],
+        big_endian: flags & NETWORK_BYTE_ORDER != 0,
+    };
+    let ids = [reader.u32()?, reader.u32()?, reader.u32()?];
+    let mut payload = vec![0u8; reader.u32()? as usize];
+    stream.read_exact(&mut payload).await?;
+    Ok((header[1], flags, ids, payload))
+}
+
+async fn expect_response(stream: &mut UnixStream, what: &str) -> Result<u32> {
+    let (kind, flags, [session, ..], payload) = read_pdu(stream).await?;
+    let mut reader = Reader {
+        bytes: &payload,
+        big_endian: flags & NETWORK_BYTE_ORDER != 0,
+    };
+    reader.u32()?; // sysUpTime
+    let error = reader.u16()?;
+    if kind != RESPONSE || error != 0 {
+        return Err(anyhow!("master agent refused {} (error {})", what, error));
+    }
+    Ok(session)
+}
+
+/// One session with the master agent at `socket`: register `subtree` and
+/// answer from `mib` until the master goes away.
+pub async fn serve(socket: &str, subtree: &[u32], mib: &Arc<Mutex<Mib>>) -> Result<()> {
+    let mut stream = UnixStream::connect(socket).await?;
+    let started = Instant::now();
+
+    let mut open = Writer(Vec::new());
+    open.u8(0); // Default timeout
+    open.0.extend_from_slice(&[0; 3]);
+    open.oid(&[], false);
+    open.octets(b"lantern");
+    stream.write_all(&pdu(OPEN, 0, 0, 1, &open.0)).await?;
+    let session = expect_response(&mut stream, "the session").await?;
+
+    let mut register = Writer(Vec::new());
+    register.u8(0);
+    register.u8(127); // Default priority
+    register.u8(0);
+    register.u8(0);
+    register.oid(subtree, false);
+    stream
+        .write_all(&pdu(REGISTER, session, 0, 2, &register.0))
+        .await?;
+    expect_response(&mut stream, "the subtree").await?;
+
+    loop {
+        let (kind, flags, [_, transaction, packet], payload) = read_pdu(&mut stream).await?;
+        let mut reader = Reader {
+            bytes: &payload,
+            big_endian: flags & NETWORK_BYTE_ORDER != 0,
+        };
+        if flags & NON_DEFAULT_CONTEXT != 0 {
+            reader.octets()?;
+        }
+        let (error, varbinds) = match kind {
+            GET | GET_NEXT | GET_BULK => {
+                let mib = mib.lock().unwrap_or_else(|e| e.into_inner());
+                (0, answer(kind, &mut reader, &mib)?)
+            }
+            TEST_SET => (NOT_WRITABLE, Vec::new()),
+            COMMIT_SET | UNDO_SET => (0, Vec::new()),
+            CLEANUP_SET => continue,
+            CLOSE => return Err(anyhow!("master agent closed the session")),
+            _ => continue,
+        };
+
+        let mut response = Writer(Vec::new());
+        // In hundredths of a second, as sysUpTime is
+        response.u32((started.elapsed().as_millis() / 10) as u32);
+        response.u16(error);
+        response.u16(0);
+        for (name, value) in &varbinds {
+            response.varbind(name, value);
+        }
+        stream
+            .write_all(&pdu(RESPONSE, session, transaction, packet, &response.0))
+            .await?;
+    }
+}
+
+/// Keep a session with the master agent, reconnecting when it restarts or
+/// isn't running yet. Never returns.
+pub async fn run(socket: String, subtree: Oid, mib: Arc<Mutex<Mib>>) {
+    loop {
+        let _ = serve(&socket, &subtree, &mib).await;
+        tokio::time::sleep(Duration::from_secs(15)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walks_the_snapshot() {
+        let mib: Mib = vec![
+            (vec![1, 3, 6, 1, 4, 1, 9, 1, 1], Value::Integer(1)),
+            (
+                vec![1, 3, 6, 1, 4, 1, 9, 1, 2],
+                Value::OctetString("eth0".to_string()),
+            ),
+            (vec![1, 3, 6, 1, 4, 1, 9, 2, 1], Value::Counter64(42)),
+        ];
+        assert_eq!(
+            parse_oid(".1.3.6.1.4.1.9").unwrap(),
+            vec![1, 3, 6, 1, 4, 1, 9]
+        );
+        assert!(parse_oid("1.3.x").is_err());
+
+        // A GetNext request as net-snmp sends it: prefix-compressed, little endian
+        let mut request = Vec::new();
+        request.extend_from_slice(&[3, 4, 0, 0]); // 1.3.6.1.4 + three sub-identifiers
+        for sub in [1u32, 9, 1] {
+            request.extend_from_slice(&sub.to_le_bytes());
+        }
+        request.extend_from_slice(&[0, 0, 0, 0]); // No end
+        let mut reader = Reader {
+            bytes: &request,
+            big_endian: false,
+        };
+        assert_eq!(
+            answer(GET_NEXT, &mut reader, &mib).unwrap(),
+            vec![(vec![1, 3, 6, 1, 4, 1, 9, 1, 1], Value::Integer(1))]
+        );
+
+        // GetBulk from the start of the subtree runs off the end
+        let mut request = vec![0, 0, 0, 5];
+        let mut range = Writer(Vec::new());
+        range.oid(&[1, 3, 6, 1, 4, 1, 9, 1, 2], true);
+        range.oid(&[], false);
+        request.extend_from_slice(&range.0);
+        let mut reader = Reader {
+            bytes: &request,
+            big_endian: true,
+        };
+        let varbinds = answer(GET_BULK, &mut reader, &mib).unwrap();
+        assert_eq!(varbinds.len(), 3);
+        assert_eq!(varbinds[1].1, Value::Counter64(42));
+        assert_eq!(varbinds[2].1, Value::EndOfMibView);
+
+        assert_eq!(get(&mib, &[1, 3, 6, 1, 4, 1, 9, 2]), Value::NoSuchObject);
+        let mut bind = Writer(Vec::new());
+        bind.varbind(&[1, 3], &Value::OctetString("abcde".to_string()));
+        assert_eq!(bind.0.len(), 4 + 4 + 8 + 4 + 8);
+    }
+}
@@ -0,0 +1,431 @@
+// src/alerts.rs - Critical events sent out by mail, Telegram or webhook while the daemon watches
+use crate::certs::CertExpiry;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `[alerts]`: which events are critical, and where to send them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertSettings {
+    /// Minutes without internet before it is reported; 0 turns the check off
+    pub wan_down_mins: u64,
+    /// Seconds between internet checks
+    pub wan_check_secs: u64,
+    /// Report links that start flapping
+    pub link_flapping: bool,
+    /// Report a scheduled speed test downloading slower than this; 0: off
+    pub speed_below_mbps: f64,
+    /// Days before a saved profile's certificate expires to start warning,
+    /// in the TUI as well; 0: off
+    pub cert_expiry_days: u64,
+    pub channels: Vec<AlertChannel>,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            wan_down_mins: 5,
+            wan_check_secs: 30,
+            link_flapping: true,
+            speed_below_mbps: 0.0,
+            cert_expiry_days: 30,
+            channels: Vec::new(),
+        }
+    }
+}
+
+impl AlertSettings {
+    /// Nothing is checked without somewhere to send it
+    pub fn enabled(&self) -> bool {
+        !self.channels.is_empty()
+    }
+
+    pub fn wan_check_interval(&self) -> Duration {
+        Duration::from_secs(self.wan_check_secs.max(5))
+    }
+}
+
+/// One `[[alerts.channels]]` entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AlertChannel {
+    /// A mail through an SMTP server, e.g. url = "smtps://smtp.example.com:465"
+    Smtp {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+    /// A message from a bot made with @BotFather to a chat it is in
+    Telegram { bot_token: String, chat_id: String },
+    /// A JSON POST of `{"title", "message", "host"}`; ntfy, Gotify, Slack
+    /// relays and the like
+    Webhook { url: String },
+}
+
+impl AlertChannel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertChannel::Smtp { .. } => "SMTP",
+            AlertChannel::Telegram { .. } => "Telegram",
+            AlertChannel::Webhook { .. } => "webhook",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub title: String,
+    pub message: String,
+}
+
+impl Alert {
+    fn new(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            title: format!("[{}] {}", hostname(), title.into()),
+            message: message.into(),
+        }
+    }
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|_| "lantern".to_string())
+}
+
+/// Turns what the daemon sees into alerts, each once per episode
+#[derive(Debug, Default)]
+pub struct AlertMonitor {
+    wan_down_since: Option<Instant>,
+    wan_alerted: bool,
+    flapping: HashSet<String>,
+    certificates: HashSet<String>,
+}
+
+impl AlertMonitor {
+    /// The result of an internet check at `now`
+    pub fn wan(&mut self, settings: &AlertSettings, up: bool, now: Instant) -> Option<Alert> {
+        if settings.wan_down_mins == 0 {
+            return None;
+        }
+        if up {
+            let since = self.wan_down_since.take()?;
+            if !std::mem::take(&mut self.wan_alerted) {
+                return None; // A blip too short to have been reported
+            }
+            return Some(Alert::new(
+                "Internet is back",
+                format!(
+                    "The internet connection is back after {}.",
+                    crate::locale::duration(now.duration_since(since))
+                ),
+            ));
+        }
+        let since = *self.wan_down_since.get_or_insert(now);
+        let limit = Duration::from_secs(settings.wan_down_mins * 60);
+        if self.wan_alerted || now.duration_since(since) < limit {
+            return None;
+        }
+        self.wan_alerted = true;
+        Some(Alert::new(
+            "Internet down",
+            format!(
+                "The internet connection has been down for {}.",
+                crate::locale::duration(limit)
+            ),
+        ))
+    }
+
+    /// The links flapping right now; each is reported when it starts
+    pub fn flapping(&mut self, settings: &AlertSettings, flapping: &[String]) -> Vec<Alert> {
+        if !settings.link_flapping {
+            return Vec::new();
+        }
+        self.flapping.retain(|name| flapping.contains(name));
+        flapping
+            .iter()
+            .filter(|name| self.flapping.insert(name.to_string()))
+            .map(|name| {
+                Alert::new(
+                    format!("{} is flapping", name),
+                    format!(
+                        "{}'s carrier keeps going up and down: check the cable and switch port.",
+                        name
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    /// Certificates close to expiry; each is reported once, and again if it
+    /// is replaced by one that comes close too
+    pub fn certificates(
+        &mut self,
+        expiring: &[CertExpiry],
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Vec<Alert> {
+        let keys: Vec<String> = expiring
+            .iter()
+            .map(|expiry| format!("{} {}", expiry.path, expiry.not_after))
+            .collect();
+        self.certificates.retain(|key| keys.contains(key));
+        expiring
+            .iter()
+            .zip(keys)
+            .filter(|(_, key)| self.certificates.insert(key.clone()))
+            .map(|(expiry, _)| {
+                let title = if expiry.not_after <= now {
+                    format!("{} certificate expired", expiry.ssid)
+                } else {
+                    format!("{} certificate expiring", expiry.ssid)
+                };
+                Alert::new(
+                    title,
+                    format!(
+                        "{}: renew it before you get locked out.",
+                        expiry.describe(now)
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    /// A finished speed test
+    pub fn speed(&self, settings: &AlertSettings, download_mbps: Option<f64>) -> Option<Alert> {
+        let limit = settings.speed_below_mbps;
+        let download = download_mbps.filter(|download| limit > 0.0 && *download < limit)?;
+        Some(Alert::new(
+            "Slow connection",
+            format!(
+                "A speed test downloaded at {:.1} Mbit/s, below the {:.0} Mbit/s alert level.",
+                download, limit
+            ),
+        ))
+    }
+}
+
+/// A curl config line, quoted and escaped as curl's config files want
+pub(crate) fn config_line(key: &str, value: &str) -> String {
+    format!(
+        "{} = \"{}\"\n",
+        key,
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    )
+}
+
+/// Run curl with its options read from stdin, so tokens and passwords stay
+/// out of the process list
+pub(crate) async fn curl(config: String) -> Result<()> {
+    let mut child = Command::new("/usr/bin/curl")
+        .args(["-s", "-S", "--fail", "-o", "/dev/null", "-K", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to run curl")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(config.as_bytes()).await?;
+    }
+    let output = tokio::time::timeout(SEND_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow!("Timed out"))??;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// The mail as curl uploads it: headers, a blank line, the text
+pub fn mail(from: &str, to: &[String], alert: &Alert) -> String {
+    let date = chrono::Local::now().to_rfc2822();
+    format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+        from,
+        to.join(", "),
+        alert.title,
+        date,
+        alert.message
+    )
+}
+
+/// A mail to upload, in a file only we can read
+fn write_mail(path: &Path, content: &str) -> std::io::Result<()> {
+    let _ = fs::remove_file(path); // Left behind by a send that was killed
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(content.as_bytes())
+}
+
+pub async fn send(channel: &AlertChannel, alert: &Alert) -> Result<()> {
+    match channel {
+        AlertChannel::Webhook { url } => {
+            let body = serde_json::json!({
+                "title": alert.title,
+                "message": alert.message,
+                "host": hostname(),
+            });
+            let mut config = config_line("url", url);
+            config.push_str(&config_line("header", "Content-Type: application/json"));
+            config.push_str(&config_line("data", &body.to_string()));
+            curl(config).await
+        }
+        AlertChannel::Telegram { bot_token, chat_id } => {
+            let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
+            let mut config = config_line("url", &url);
+            config.push_str(&config_line(
+                "data-urlencode",
+                &format!("chat_id={}", chat_id),
+            ));
+            config.push_str(&config_line(
+                "data-urlencode",
+                &format!("text={}\n{}", alert.title, alert.message),
+            ));
+            curl(config).await
+        }
+        AlertChannel::Smtp {
+            url,
+            username,
+            password,
+            from,
+            to,
+        } => {
+            let path = std::env::temp_dir().join(format!("lantern-alert-{}", std::process::id()));
+            write_mail(&path, &mail(from, to, alert))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            let mut config = config_line("url", url);
+            config.push_str("ssl-reqd\n"); // STARTTLS on smtp://, or no mail at all
+            config.push_str(&config_line("mail-from", from));
+            for recipient in to {
+                config.push_str(&config_line("mail-rcpt", recipient));
+            }
+            if let Some(username) = username {
+                let password = password.as_deref().unwrap_or_default();
+                config.push_str(&config_line("user", &format!("{}:{}", username, password)));
+            }
+            config.push_str(&config_line("upload-file", &path.to_string_lossy()));
+            let result = curl(config).await;
+            let _ = fs::remove_file(&path);
+            result
+        }
+    }
+}
+
+/// Send `alert` through every channel; the errors of those that failed
+pub async fn send_all(channels: &[AlertChannel], alert: &Alert) -> Vec<String> {
+    let mut errors = Vec::new();
+    for channel in channels {
+        if let Err(e) = send(channel, alert).await {
+            errors.push(format!("{}: {:#}", channel.name(), e));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_each_episode_once() {
+        let settings = AlertSettings::default();
+        let mut monitor = AlertMonitor::default();
+        let start = Instant::now();
+        let at = |mins: u64| start + Duration::from_secs(mins * 60);
+
+        assert_eq!(monitor.wan(&settings, true, at(0)), None);
+        assert_eq!(monitor.wan(&settings, false, at(1)), None);
+        // Back within the limit: nothing to report either way
+        assert_eq!(monitor.wan(&settings, true, at(2)), None);
+        assert_eq!(monitor.wan(&settings, false, at(3)), None);
+        let down = monitor.wan(&settings, false, at(8)).unwrap();
+        assert!(down.title.ends_with("Internet down"));
+        assert_eq!(monitor.wan(&settings, false, at(9)), None);
+        let back = monitor.wan(&settings, true, at(13)).unwrap();
+        assert!(back.message.contains("after 10 min"));
+
+        let flapping = vec!["eth0".to_string()];
+        assert_eq!(monitor.flapping(&settings, &flapping).len(), 1);
+        assert!(monitor.flapping(&settings, &flapping).is_empty());
+        monitor.flapping(&settings, &[]);
+        assert_eq!(monitor.flapping(&settings, &flapping).len(), 1);
+
+        let slow = AlertSettings {
+            speed_below_mbps: 100.0,
+            ..AlertSettings::default()
+        };
+        assert!(monitor.speed(&slow, Some(42.0)).is_some());
+        assert!(monitor.speed(&slow, Some(420.0)).is_none());
+        assert!(monitor.speed(&settings, Some(42.0)).is_none());
+
+        let now = chrono::Utc::now();
+        let expiring = vec![CertExpiry {
+            ssid: "corp".to_string(),
+            role: "client certificate",
+            path: "/etc/certs/me.pem".to_string(),
+            not_after: now + chrono::Duration::days(10),
+        }];
+        let alert = &monitor.certificates(&expiring, now)[0];
+        assert!(alert.title.ends_with("corp certificate expiring"));
+        assert!(monitor.certificates(&expiring, now).is_empty());
+    }
+
+    #[test]
+    fn reads_channels_from_config() {
+        let settings: AlertSettings = toml::from_str(
+            r#"
+            wan_down_mins = 10
+
+            [[channels]]
+            type = "telegram"
+            bot_token = "123:abc"
+            chat_id = "42"
+
+            [[channels]]
+            type = "webhook"
+            url = "https://ntfy.sh/lantern"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(settings.wan_down_mins, 10);
+        assert_eq!(
+            settings.channels,
+            vec![
+                AlertChannel::Telegram {
+                    bot_token: "123:abc".to_string(),
+                    chat_id: "42".to_string(),
+                },
+                AlertChannel::Webhook {
+                    url: "https://ntfy.sh/lantern".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            config_line("data", r#"{"title":"a \"b\""}"#),
+            "data = \"{\\\"title\\\":\\\"a \\\\\\\"b\\\\\\\"\\\"}\"\n"
+        );
+    }
+}
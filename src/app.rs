@@ -2,23 +2,319 @@
 #![allow(dead_code)] // Many methods are for future features or CLI mode
 #![allow(clippy::useless_format)] // Format strings may contain dynamic content in future
 #![allow(clippy::unnecessary_map_or)] // Code clarity over micro-optimizations
-use crate::config::{Config, WifiProfile};
+use crate::alerts::{Alert, AlertMonitor};
+use crate::baseline::Baselines;
+use crate::certs::CertExpiry;
+use crate::config::{Config, NetworkExtras, Profile, TrustedNetworks, VpnPolicy, WifiProfile};
+use crate::containers::ContainerLink;
+use crate::dhcpprobe::DhcpReport;
+use crate::dnsleak::LeakReport;
+use crate::doctor::Finding;
+use crate::events::{EventKind, EventTimeline};
+use crate::gai::{GaiState, Preference};
+use crate::groups::{GroupTotals, Rates};
+use crate::hotspot::{ClientTracker, DhcpReservation, Station, AUTO_CHANNEL};
+use crate::httpcheck::{Binding, HttpCheck};
+use crate::jobs::{Job, JobId, JobQueue, JobState, Outcome};
+use crate::link_kind::LinkKind;
+use crate::lock::OperationLock;
+use crate::macconflict::Conflict;
+use crate::modal::{Modal, ModalStack};
+use crate::mqtt::Publisher;
+use crate::neighbors::StaticNeighbor;
 use crate::network::{
-    DetailedWifiInfo, EnterpriseAuthMethod, EnterpriseCredentials, Interface, NetworkManager,
-    Phase2AuthMethod, WifiCredentials, WifiNetwork, WifiSecurity,
+    DetailedWifiInfo, EndpointChange, EnterpriseAuthMethod, EnterpriseCredentials, HotspotConfig,
+    Interface, InterfaceStats, NetworkManager, Phase2AuthMethod, Route, VpnAutoConnect, WifiBand,
+    WifiCredentials, WifiNetwork, WifiSecurity, WireGuardStatus,
 };
+use crate::networkd::MatchedFiles;
+use crate::pacing::Pacer;
+use crate::packets::{PacketView, Sample};
+use crate::palette::PaletteAction;
+use crate::pending::{PendingChange, Revert};
+use crate::perf::{Measure, Timings};
+use crate::portfwd::{PortForward, Protocol};
+use crate::pxe::{PxeConfig, PxeMode, PxeServer, PxeSettings};
+use crate::qr::WifiQrPayload;
+use crate::queues::QueueStats;
+use crate::rawedit::RawEditor;
+use crate::reachability::ReachabilityReport;
+use crate::redraw::{Region, Regions, STATUS_DURATION_SECS};
+use crate::resolved::GlobalDns;
+use crate::rfmon::{Capture, RfSession, Sighting};
+use crate::routing::RoutingStatus;
+use crate::scan_cache::ScanCache;
+use crate::snmp::{Agent, SnmpJob};
+use crate::speedtest::SpeedResult;
+use crate::survey::{GpsStatus, Survey};
 use crate::systemd::SystemdNetworkConfig;
+use crate::takeover::Foreign;
+use crate::tcp::{TcpHealth, TcpSnapshot};
+use crate::troubleshoot::Diagnosis;
+use crate::trust::Uplink;
+use crate::wifi_events::{FrameEvent, SeenFrameEvent};
 use anyhow::Result;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
 use tui_input::backend::crossterm::EventHandler;
 use tui_input::Input;
 
-#[derive(Clone)]
+// 802.11 events kept for the WiFi diagnostics dialog
+const MAX_FRAME_EVENTS: usize = 50;
+
+// Range the diagnostics dialog steps the transmit power through, in dBm;
+// the driver still refuses what the regulatory domain doesn't allow
+const MIN_TX_POWER: i32 = 1;
+const MAX_TX_POWER: i32 = 30;
+
+/// How long `i` blinks a port LED
+const IDENTIFY_SECONDS: u64 = 15;
+
+// Certificates expire by the day; the daemon rereads them this often
+const CERT_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Results of background tasks, applied to the App by `App::apply`
+#[derive(Debug)]
+pub enum AppEvent {
+    Stats(HashMap<String, InterfaceStats>), // Keyed by interface name
+    Interfaces(Vec<Interface>),
+    WifiInfo(Vec<Interface>),
+    WifiScan(String, std::result::Result<Vec<WifiNetwork>, String>),
+    Routes(String, Vec<Route>),
+    WireGuard(String, Option<WireGuardStatus>),
+    Queues(String, QueueStats),
+    Routing(RoutingStatus),
+    Tcp(TcpSnapshot),
+    AutoConnected {
+        ssid: String,
+        interface: String,
+    },
+    EndpointsReresolved(Vec<EndpointChange>),
+    VpnPolicyApplied {
+        applied: Vec<(String, bool)>,  // Tunnels brought up (true) or down
+        failed: Vec<(String, String)>, // Tunnels that couldn't be, with the error
+    },
+    Trust(HashMap<String, bool>),
+    FirewallFailed(String), // The untrusted-network firewall couldn't be applied
+    Timed(Measure, Duration), // How long a background refresh took
+    Containers(HashMap<String, ContainerLink>), // Keyed by host veth name
+    TaskFailed {
+        task: &'static str,
+        error: String,
+    }, // Error or panic in a background task
+    HotspotStations(std::result::Result<Vec<Station>, String>),
+    PersistentHotspot(Option<(HotspotConfig, String)>), // With its unit's state
+    SurveyScan(std::result::Result<Vec<WifiNetwork>, String>),
+    SurveyGps(GpsStatus),
+    RfSighting(Sighting),
+    RfCaptureEnded(Option<String>), // The error that stopped it, if any
+    Packets(Sample),
+    PacketCaptureEnded(Option<String>), // The error that stopped it, if any
+    Identified(String, std::result::Result<(), String>),
+    DnsLeak(LeakReport),
+    DhcpProbe(DhcpReport),
+    Reachability(String, std::result::Result<ReachabilityReport, String>),
+    Troubleshoot(String, std::result::Result<Diagnosis, String>),
+    HttpCheck(std::result::Result<HttpCheck, String>),
+    JobFinished(JobId, std::result::Result<Outcome, String>),
+    AddressOrder(std::result::Result<Vec<String>, String>),
+    SpeedTest(SpeedResult),
+    WanChecked(bool), // Whether the internet answered, for alerts
+    StaticNeighbors(String, std::result::Result<Vec<StaticNeighbor>, String>),
+    NetworkdFiles(String, std::result::Result<MatchedFiles, String>),
+    ForeignManagers(HashMap<String, Foreign>), // Keyed by interface
+    Frame(FrameEvent), // 802.11 disconnect, deauth or beacon loss from nl80211
+    MacConflicts(Vec<Conflict>),
+    LinkChanged, // Netlink saw a link appear, vanish or change state
+}
+
+impl AppEvent {
+    /// The parts of the screen showing what this event changes
+    pub fn regions(&self) -> &'static [Region] {
+        match self {
+            AppEvent::Tcp(_) => &[Region::Tcp],
+            AppEvent::Timed(..) => &[Region::Overlay],
+            AppEvent::Stats(_)
+            | AppEvent::WifiInfo(_)
+            | AppEvent::Containers(_)
+            | AppEvent::ForeignManagers(_)
+            | AppEvent::Trust(_)
+            | AppEvent::Frame(_) => &[Region::Interfaces, Region::Details],
+            // A new interface may come with a hotplug prompt
+            AppEvent::Interfaces(_) => &[Region::Interfaces, Region::Details, Region::Overlay],
+            AppEvent::Routes(..) | AppEvent::WireGuard(..) | AppEvent::Queues(..) => {
+                &[Region::Details]
+            }
+            AppEvent::EndpointsReresolved(_) => &[Region::Details],
+            AppEvent::VpnPolicyApplied { .. }
+            | AppEvent::FirewallFailed(_)
+            | AppEvent::MacConflicts(_) => &[Region::Interfaces, Region::Details, Region::Footer],
+            AppEvent::AutoConnected { .. } | AppEvent::TaskFailed { .. } => &[Region::Footer],
+            AppEvent::Identified(..) => &[Region::Interfaces, Region::Footer],
+            AppEvent::JobFinished(..) => &[Region::Interfaces, Region::Footer, Region::Overlay],
+            AppEvent::PersistentHotspot(_) => &[Region::Footer, Region::Overlay],
+            AppEvent::WifiScan(..)
+            | AppEvent::Routing(_)
+            | AppEvent::HotspotStations(_)
+            | AppEvent::SurveyScan(_)
+            | AppEvent::SurveyGps(_)
+            | AppEvent::RfSighting(_)
+            | AppEvent::RfCaptureEnded(_)
+            | AppEvent::Packets(_)
+            | AppEvent::PacketCaptureEnded(_)
+            | AppEvent::DnsLeak(_)
+            | AppEvent::DhcpProbe(_)
+            | AppEvent::Reachability(..)
+            | AppEvent::Troubleshoot(..)
+            | AppEvent::HttpCheck(_)
+            | AppEvent::AddressOrder(_)
+            | AppEvent::SpeedTest(_)
+            | AppEvent::StaticNeighbors(..)
+            | AppEvent::NetworkdFiles(..) => &[Region::Overlay],
+            // Only the daemon checks, and has no screen
+            AppEvent::WanChecked(_) => &[],
+            // Only the pace in the header, until the refresh it asks for
+            AppEvent::LinkChanged => &[Region::Header],
+        }
+    }
+
+    // Snapshots where only the newest matters when several arrive at once
+    fn superseded_by(&self, newer: &AppEvent) -> bool {
+        matches!(
+            (self, newer),
+            (AppEvent::Interfaces(_), AppEvent::Interfaces(_))
+                | (AppEvent::Containers(_), AppEvent::Containers(_))
+                | (AppEvent::ForeignManagers(_), AppEvent::ForeignManagers(_))
+                | (AppEvent::Trust(_), AppEvent::Trust(_))
+                | (AppEvent::LinkChanged, AppEvent::LinkChanged)
+        )
+    }
+}
+
+/// Drop events a later one in the same batch replaces, so a burst of link
+/// changes costs one refresh and one frame instead of one each. Everything
+/// else keeps its order.
+pub fn coalesce(events: Vec<AppEvent>) -> Vec<AppEvent> {
+    let mut kept: Vec<AppEvent> = Vec::with_capacity(events.len());
+    for event in events {
+        kept.retain(|older| !older.superseded_by(&event));
+        kept.push(event);
+    }
+    kept
+}
+
+/// Tabs of the right-hand detail pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetailTab {
+    Overview,
+    Ipv6,
+    Wifi,
+    WireGuard,
+    Routes,
+    Queues,
+    Logs,
+}
+
+impl DetailTab {
+    pub const ALL: [DetailTab; 7] = [
+        DetailTab::Overview,
+        DetailTab::Ipv6,
+        DetailTab::Wifi,
+        DetailTab::WireGuard,
+        DetailTab::Routes,
+        DetailTab::Queues,
+        DetailTab::Logs,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            DetailTab::Overview => "Overview",
+            DetailTab::Ipv6 => "IPv6",
+            DetailTab::Wifi => "WiFi",
+            DetailTab::WireGuard => "WireGuard",
+            DetailTab::Routes => "Routes",
+            DetailTab::Queues => "Queues",
+            DetailTab::Logs => "Logs",
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|&tab| tab == self).unwrap_or(0)
+    }
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+/// Detail tab contents fetched in the background for the selected interface
+pub struct TabData<T> {
+    pub value: T,
+    pub interface: Option<String>, // Interface the value was fetched for
+    pub loading: bool,
+    fetched_at: Instant,
+}
+
+impl<T: Default> TabData<T> {
+    /// Refetch when the selection changed or the value is older than 5s.
+    fn due(&self, interface: &str) -> bool {
+        !self.loading
+            && (self.interface.as_deref() != Some(interface)
+                || self.fetched_at.elapsed() > Duration::from_secs(5))
+    }
+
+    /// Refetch on the next tick, keeping the old value on screen meanwhile
+    fn expire(&mut self) {
+        self.fetched_at = Instant::now()
+            .checked_sub(Duration::from_secs(6))
+            .unwrap_or(self.fetched_at);
+    }
+
+    pub fn start(&mut self, interface: &str) {
+        if self.interface.as_deref() != Some(interface) {
+            self.value = T::default();
+            self.interface = Some(interface.to_string());
+        }
+        self.loading = true;
+        self.fetched_at = Instant::now();
+    }
+
+    /// Store a result unless the selection moved on while it was fetched.
+    fn finish(&mut self, interface: &str, value: T) {
+        self.loading = false;
+        if self.interface.as_deref() == Some(interface) {
+            self.value = value;
+        }
+    }
+}
+
+impl<T: Default> Default for TabData<T> {
+    fn default() -> Self {
+        Self {
+            value: T::default(),
+            interface: None,
+            loading: false,
+            fetched_at: Instant::now(),
+        }
+    }
+}
+
 pub struct App {
     pub interfaces: Vec<Interface>,
+    pub hidden_interfaces: usize, // Ignored ones left out of `interfaces`
+    pub show_ignored: bool,
     pub selected_index: usize,
-    pub show_details: bool,
-    pub show_edit_dialog: bool,
+    pub detail_tab: DetailTab,
+    pub routes: TabData<Vec<Route>>,
+    pub wireguard: TabData<Option<WireGuardStatus>>,
+    pub queues: TabData<QueueStats>,
+    pub modals: ModalStack, // Dialogs open over the main screen, innermost last
     pub network_manager: NetworkManager,
     pub systemd_config: SystemdNetworkConfig,
     pub config: Config,
@@ -26,8 +322,15 @@ pub struct App {
     pub last_interface_refresh: Instant,
     pub last_wifi_update: Instant,
     pub last_auto_connect_check: Instant,
+    pub last_endpoint_resolve: Instant,
+    pub last_policy_check: Option<Instant>, // None until the first check at startup
+    pub network_trust: HashMap<String, bool>,
+    pub firewall_failed: Option<String>, // Why untrusted networks aren't firewalled, when they should be
     pub status_message: Option<(String, Instant)>,
-    pub needs_redraw: bool,
+    pub events: EventTimeline,
+    pub tcp: TcpHealth,
+    pub needs_redraw: bool, // Everything, e.g. after a key press
+    pub redraw: Regions,
 
     // Edit dialog state
     pub edit_interface: Option<Interface>,
@@ -36,18 +339,24 @@ pub struct App {
     pub gateway_input: Input,
     pub dns_input: Input,
     pub active_input: usize,
+    pub revert_input: Input, // Minutes until the edit reverts; empty keeps it
+    pub link_responders: crate::resolved::LinkResponders, // 4: LLMNR, 5: mDNS
+    pub link_responders_now: crate::resolved::LinkResponders, // As resolved reports them
+    pub link_local_fallback: bool, // 6: IPv4LL when DHCP gets no answer
+    link_local_fallback_was: bool,
 
     // WiFi state
-    pub show_wifi_dialog: bool,
-    pub show_wifi_loading_dialog: bool,
     pub wifi_scan_pending: bool,
     pub wifi_networks: Vec<WifiNetwork>,
     pub selected_wifi_index: usize,
     pub wifi_scanning: bool,
     pub last_wifi_scan: Instant,
+    pub scan_cache: ScanCache,
+    pub wifi_cached_since: Option<SystemTime>, // Set while showing cached results
+    pub wifi_show_details: bool,               // Security details under the selected network
+    pub wifi_expanded: HashSet<String>,        // SSIDs whose access points are listed
 
     // WiFi connection dialog state
-    pub show_wifi_connect_dialog: bool,
     pub selected_wifi_network: Option<WifiNetwork>,
     pub wifi_password_input: Input,
     pub wifi_use_dhcp: bool,
@@ -58,7 +367,6 @@ pub struct App {
     pub wifi_hidden_ssid: bool,
 
     // Enterprise WiFi dialog state
-    pub show_wifi_enterprise_dialog: bool,
     pub enterprise_auth_method: EnterpriseAuthMethod,
     pub enterprise_phase2_auth: Option<Phase2AuthMethod>,
     pub enterprise_username_input: Input,
@@ -71,58 +379,348 @@ pub struct App {
     pub enterprise_active_input: usize,
 
     // Hotspot dialog state
-    pub show_hotspot_dialog: bool,
     pub hotspot_ssid_input: Input,
     pub hotspot_password_input: Input,
     pub hotspot_channel: u32,
     pub hotspot_active_input: usize,
+    pub active_hotspot: Option<HotspotConfig>,
+    pub hotspot_clients: ClientTracker,
+    pub hotspot_clients_error: Option<String>,
+    pub selected_hotspot_client: usize,
+    last_hotspot_clients_fetch: Option<Instant>,
+    // systemd's state of a hotspot kept across reboots, checked every 30s
+    pub hotspot_unit_state: Option<String>,
+    last_hotspot_unit_check: Option<Instant>,
+    // USB gadget links sharing this machine's connection, with their address
+    pub usb_shares: HashMap<String, String>,
+
+    // DHCP reservations editor, opened over the hotspot clients view
+    pub selected_reservation: usize,
+    pub reservation_mac_input: Input,
+    pub reservation_ip_input: Input,
+    pub reservation_hostname_input: Input,
+    pub reservation_active_input: usize, // 0: MAC, 1: IP, 2: hostname
+    reservation_editing: Option<usize>,  // Index of the reservation the form replaces
+
+    // Port forwarding editor, opened over the hotspot clients view
+    pub selected_port_forward: usize,
+    pub port_forward_protocol: Protocol,
+    pub port_forward_external_input: Input,
+    pub port_forward_ip_input: Input,
+    pub port_forward_internal_input: Input,
+    pub port_forward_active_input: usize, // 0: protocol, 1: external port, 2: IP, 3: internal port, 4: revert
+    pub port_forward_revert_input: Input,
+    port_forward_editing: Option<usize>, // Index of the forward the form replaces
+
+    // Temporary PXE boot server on the selected interface
+    pub pxe_mode: PxeMode,
+    pub pxe_root_input: Input,
+    pub pxe_bios_input: Input,
+    pub pxe_efi_input: Input,
+    pub pxe_active_input: usize, // 0: mode, 1: TFTP root, 2: BIOS boot file, 3: UEFI boot file
+    pub pxe_server: Option<PxeServer>,
+    pxe_logged: usize, // Log lines there were when the dialog was last marked
+
+    // systemd-resolved's global settings, as lantern's drop-in sets them
+    pub global_dns_effective: GlobalDns, // Every resolved.conf file together
+    pub global_dns_input: Input,
+    pub global_fallback_dns_input: Input,
+    pub global_domains_input: Input,
+    pub global_dns_over_tls: Option<String>,
+    pub global_llmnr: Option<String>,
+    pub global_multicast_dns: Option<String>,
+    pub global_dns_active_input: usize, // 0: DNS, 1: fallback, 2: domains, 3: DoT, 4: LLMNR, 5: mDNS
+
+    // Temporary changes waiting to be kept, re-read every second
+    pub pending: Vec<PendingChange>,
+    last_pending_check: Option<Instant>,
+
+    // Permanent neighbor (static ARP/NDP) editor for one interface
+    pub neighbor_interface: String,
+    pub queue_tuning_interface: String,
+    pub queue_rx_ring_input: Input,
+    pub queue_tx_ring_input: Input,
+    pub queue_rss_input: Input,
+    pub queue_tuning_active_input: usize, // 0: RX ring, 1: TX ring, 2: RSS queues
+    pub static_neighbors: Option<Vec<StaticNeighbor>>, // None until listed
+    pub static_neighbors_error: Option<String>,
+    pub selected_neighbor: usize,
+
+    // Changes queued to run in the background, and the jobs panel's selection
+    pub jobs: JobQueue,
+    pub selected_job: usize,
+
+    // Values of the selected interface `y` offers to copy: label, value
+    pub copy_choices: Vec<(String, String)>,
+    pub selected_copy: usize,
+    last_report_path: Option<String>,
+    screenshot: Option<crate::screenshot::Format>, // Taken from the next frame
+    pub neighbor_address_input: Input,
+    pub neighbor_mac_input: Input,
+    pub neighbor_active_input: usize, // 0: address, 1: MAC
+    neighbor_editing: Option<String>, // Address of the entry the form replaces
+    neighbors_stale: bool,
+
+    // The .link/.network/.netdev files networkd applies to one interface
+    pub networkd_files_interface: String,
+    pub networkd_files: Option<MatchedFiles>, // None until read
+    pub networkd_files_error: Option<String>,
+    pub selected_networkd_file: usize,
+    networkd_files_stale: bool,
+    pub raw_editor: Option<RawEditor>, // i on one of lantern's own files
+
+    // Doctor: problems in the networkd files already on the system
+    pub doctor: Option<std::result::Result<Vec<Finding>, String>>,
+    pub selected_finding: usize,
+
+    // Share network (QR code) dialog state
+    pub share_payload: Option<WifiQrPayload>,
+
+    // Routing daemon (FRR/BIRD) status dialog, refreshed while open
+    pub routing: Option<RoutingStatus>,
+    routing_loading: bool,
+    last_routing_fetch: Option<Instant>,
+
+    // DNS leak test of one interface, run once per opening or `r`
+    pub dns_leak: Option<LeakReport>,
+    pub dns_leak_interface: String,
+    dns_leak_running: bool,
+
+    // DHCPDISCOVER broadcast on one interface, run like the DNS leak test
+    pub dhcp_probe: Option<DhcpReport>,
+    pub dhcp_probe_interface: String,
+    dhcp_probe_running: bool,
+
+    // IPv4/IPv6 reachability test of one interface, run like the DNS leak test
+    pub reachability: Option<std::result::Result<ReachabilityReport, String>>,
+    pub reachability_interface: String,
+    reachability_running: bool,
+
+    // The "why is my network broken?" walk up the layers, run the same way
+    pub troubleshoot: Option<std::result::Result<Diagnosis, String>>,
+    pub troubleshoot_interface: String,
+    troubleshoot_running: bool,
+
+    // HTTP(S) GET through the selected interface or one of its addresses
+    pub http_check: Option<std::result::Result<HttpCheck, String>>,
+    pub http_check_input: Input,
+    pub http_check_bindings: Vec<Binding>,
+    pub http_check_binding: usize,
+    http_check_queued: bool, // Enter was pressed; the main loop starts it
+    http_check_running: bool,
+
+    // Interface whose port LED blinks, and whether ethtool has been started
+    pub identifying: Option<String>,
+    identify_running: bool,
+
+    // gai.conf's IPv4/IPv6 preference: as saved, as chosen in the dialog,
+    // and the order a sample name resolves in
+    pub address_preference: GaiState,
+    pub address_preference_choice: Preference,
+    pub address_order: Option<std::result::Result<Vec<String>, String>>,
+    address_order_running: bool,
+
+    // Speed tests: asked for in the panel, or on the daemon's schedule
+    pub speed_history: Vec<SpeedResult>,
+    pub speed_test_running: bool,
+    speed_test_requested: bool,
+    last_scheduled_speed_test: Option<Instant>,
+    speed_test_manual: bool, // The running test was asked for, not scheduled
+
+    // Alerts the daemon sends out: what it has seen, and what waits to go
+    alerts: AlertMonitor,
+    pending_alerts: Vec<Alert>,
+    last_wan_check: Option<Instant>,
+    wan_checking: bool,
+    // Enterprise certificates running out, read at startup and hourly after
+    pub cert_expiries: Vec<CertExpiry>,
+    last_cert_check: Option<Instant>,
+
+    // WiFi site survey panel: scans on a timer, tagged with gpsd's position
+    pub survey: Option<Survey>,
+    survey_scanning: bool,
+    last_survey_scan: Option<Instant>,
+    survey_gps_polling: bool,
+    last_survey_gps: Option<Instant>,
+
+    // RF monitor: the interface it is offered for, then the running session
+    pub rf_interface: String,
+    pub rf_session: Option<RfSession>,
+
+    // Live packet summary (v) and the capture behind it, while open
+    pub packet_view: Option<PacketView>,
+
+    // Hotplugged NIC with a matching saved profile
+    pub hotplug_offer: Option<HotplugOffer>,
+    pub interface_refresh_requested: bool,
 
     // WiFi diagnostics dialog state
-    pub show_wifi_diagnostics_dialog: bool,
     pub wifi_diagnostics_data: Option<DetailedWifiInfo>,
+    pub frame_events: VecDeque<SeenFrameEvent>, // Oldest first, all interfaces
+
+    // Containers behind host veths, when [containers] is enabled
+    pub containers: HashMap<String, ContainerLink>,
+    last_container_refresh: Option<Instant>,
+
+    // Interfaces NetworkManager, dhcpcd, netctl or wicked run, and adopting them
+    pub foreign_managers: HashMap<String, Foreign>,
+    last_foreign_check: Option<Instant>,
+    pub adopt_interface: String,
+
+    // Duplicate and cloned MACs in the neighbor table, checked every minute
+    pub mac_conflicts: Vec<Conflict>,
+    last_mac_check: Option<Instant>,
+
+    // Telemetry for an MQTT broker, when [mqtt] is enabled
+    mqtt: Option<Publisher>,
+    mqtt_error: Option<String>, // Last one shown, so it isn't repeated every publish
+    last_mqtt_publish: Option<Instant>,
+    // Line protocol pushed to InfluxDB or VictoriaMetrics, when [influx] is enabled
+    influx: Option<crate::influx::Pusher>,
+    influx_error: Option<String>,
+    last_influx_push: Option<Instant>,
+
+    // AgentX subagent answering SNMP polls, when [snmp] is enabled (daemon only)
+    snmp: Option<Agent>,
+    last_snmp_refresh: Option<Instant>,
+
+    // Command palette (Ctrl+P); a picked key is handled as if pressed
+    pub palette_input: Input,
+    pub palette_selected: usize,
+    pub queued_key: Option<crossterm::event::KeyEvent>,
+
+    // Debug overlay with frame and refresh timings (F12)
+    pub show_perf_overlay: bool,
+    pub timings: Timings,
+
+    // Adaptive refresh rates and the pause toggle (f)
+    pub pacer: Pacer,
+
+    // Byte rates behind the [[groups]] rows
+    pub rates: Rates,
+
+    // Counters zeroed with z, per interface
+    pub baselines: Baselines,
+
+    // --safe: auto-connect, background checks and startup writes are off
+    pub safe_mode: bool,
 }
 
 impl App {
-    pub async fn new() -> Result<Self> {
+    /// `safe` is `--safe`: nothing runs or gets written unless asked for,
+    /// not even the overdue reverts
+    pub async fn new(safe: bool) -> Result<Self> {
+        let started = Instant::now();
+        // Before anything is read: these may rewrite config.toml too
+        let overdue = if safe {
+            Vec::new()
+        } else {
+            crate::pending::revert_overdue().await
+        };
         let network_manager = NetworkManager::new();
         let interfaces = network_manager.get_interfaces().await?;
-        let config = Config::load().unwrap_or_else(|_| Config {
-            profiles: Vec::new(),
-            wifi_profiles: Vec::new(),
-        });
+        let config = Config::load().unwrap_or_default();
+
+        let mut app = Self::with_interfaces(network_manager, interfaces, config);
+        if !overdue.is_empty() {
+            let failed = overdue.iter().filter(|result| result.is_err()).count();
+            app.status_message = Some((
+                format!(
+                    "Reverted {} overdue temporary change(s){}",
+                    overdue.len() - failed,
+                    if failed > 0 {
+                        format!(", {} failed", failed)
+                    } else {
+                        String::new()
+                    }
+                ),
+                Instant::now(),
+            ));
+        }
+        app.check_certificates();
+        if let (None, Some(first)) = (&app.status_message, app.cert_expiries.first()) {
+            app.status_message = Some((first.describe(chrono::Utc::now()), Instant::now()));
+        }
+        if safe {
+            app.safe_mode = true;
+            app.status_message = Some((
+                "Safe mode: no auto-connect, background checks or automatic writes".to_string(),
+                Instant::now(),
+            ));
+        }
+        app.timings.record(Measure::Startup, started.elapsed());
+        Ok(app)
+    }
+
+    /// An app over the simulated network, starting from default settings.
+    pub async fn demo() -> Result<Self> {
+        let network_manager = NetworkManager::demo();
+        let interfaces = network_manager.get_interfaces().await?;
 
-        Ok(Self {
+        Ok(Self::with_interfaces(
+            network_manager,
+            interfaces,
+            Config::default(),
+        ))
+    }
+
+    pub fn with_interfaces(
+        network_manager: NetworkManager,
+        interfaces: Vec<Interface>,
+        config: Config,
+    ) -> Self {
+        let (ignored, interfaces): (Vec<Interface>, Vec<Interface>) = interfaces
+            .into_iter()
+            .partition(|iface| config.interfaces.is_ignored(&iface.name));
+        Self {
             interfaces,
+            hidden_interfaces: ignored.len(),
+            show_ignored: false,
             selected_index: 0,
-            show_details: false,
-            show_edit_dialog: false,
+            detail_tab: DetailTab::Overview,
+            routes: TabData::default(),
+            wireguard: TabData::default(),
+            queues: TabData::default(),
+            modals: ModalStack::default(),
             network_manager,
             systemd_config: SystemdNetworkConfig::new(),
-            config,
             last_refresh: Instant::now(),
             last_interface_refresh: Instant::now(),
             last_wifi_update: Instant::now(),
             last_auto_connect_check: Instant::now(),
+            last_endpoint_resolve: Instant::now(),
+            last_policy_check: None,
+            network_trust: HashMap::new(),
+            firewall_failed: None,
             status_message: None,
+            events: EventTimeline::default(),
+            tcp: TcpHealth::default(),
             needs_redraw: true,
+            redraw: Regions::default(),
             edit_interface: None,
             use_dhcp: false,
             ip_input: Input::default(),
             gateway_input: Input::default(),
             dns_input: Input::default(),
             active_input: 0,
+            revert_input: Input::default(),
+            link_responders: Default::default(),
+            link_responders_now: Default::default(),
+            link_local_fallback: false,
+            link_local_fallback_was: false,
 
             // WiFi initialization
-            show_wifi_dialog: false,
-            show_wifi_loading_dialog: false,
             wifi_scan_pending: false,
             wifi_networks: Vec::new(),
             selected_wifi_index: 0,
             wifi_scanning: false,
             last_wifi_scan: Instant::now() - Duration::from_secs(60), // Force initial scan
+            scan_cache: ScanCache::load(),
+            wifi_cached_since: None,
+            wifi_show_details: false,
+            wifi_expanded: HashSet::new(),
 
             // WiFi connection dialog initialization
-            show_wifi_connect_dialog: false,
             selected_wifi_network: None,
             wifi_password_input: Input::default(),
             wifi_use_dhcp: true,
@@ -133,7 +731,6 @@ impl App {
             wifi_hidden_ssid: false,
 
             // Enterprise WiFi initialization
-            show_wifi_enterprise_dialog: false,
             enterprise_auth_method: EnterpriseAuthMethod::PEAP,
             enterprise_phase2_auth: Some(Phase2AuthMethod::MSCHAPV2),
             enterprise_username_input: Input::default(),
@@ -146,94 +743,974 @@ impl App {
             enterprise_active_input: 0,
 
             // Hotspot initialization
-            show_hotspot_dialog: false,
             hotspot_ssid_input: Input::default().with_value("Lantern-Hotspot".to_string()),
-            hotspot_password_input: Input::default().with_value("password123".to_string()),
+            hotspot_password_input: Input::default()
+                .with_value(crate::password::generate_passphrase(4).unwrap_or_default()),
             hotspot_channel: 6,
             hotspot_active_input: 0,
+            active_hotspot: None,
+            hotspot_clients: ClientTracker::default(),
+            hotspot_clients_error: None,
+            selected_hotspot_client: 0,
+            last_hotspot_clients_fetch: None,
+            hotspot_unit_state: None,
+            last_hotspot_unit_check: None,
+            usb_shares: crate::usbshare::shares(),
+
+            selected_reservation: 0,
+            reservation_mac_input: Input::default(),
+            reservation_ip_input: Input::default(),
+            reservation_hostname_input: Input::default(),
+            reservation_active_input: 0,
+            reservation_editing: None,
+            selected_port_forward: 0,
+            port_forward_protocol: Protocol::default(),
+            port_forward_external_input: Input::default(),
+            port_forward_ip_input: Input::default(),
+            port_forward_internal_input: Input::default(),
+            port_forward_active_input: 0,
+            port_forward_revert_input: Input::default(),
+            port_forward_editing: None,
+            pxe_mode: PxeMode::default(),
+            pxe_root_input: Input::default(),
+            pxe_bios_input: Input::default(),
+            pxe_efi_input: Input::default(),
+            pxe_active_input: 0,
+            pxe_server: None,
+            pxe_logged: 0,
+            global_dns_effective: GlobalDns::default(),
+            global_dns_input: Input::default(),
+            global_fallback_dns_input: Input::default(),
+            global_domains_input: Input::default(),
+            global_dns_over_tls: None,
+            global_llmnr: None,
+            global_multicast_dns: None,
+            global_dns_active_input: 0,
+            pending: Vec::new(),
+            last_pending_check: None,
+
+            neighbor_interface: String::new(),
+            queue_tuning_interface: String::new(),
+            queue_rx_ring_input: Input::default(),
+            queue_tx_ring_input: Input::default(),
+            queue_rss_input: Input::default(),
+            queue_tuning_active_input: 0,
+            static_neighbors: None,
+            static_neighbors_error: None,
+            selected_neighbor: 0,
+            jobs: JobQueue::default(),
+            selected_job: 0,
+            copy_choices: Vec::new(),
+            selected_copy: 0,
+            last_report_path: None,
+            screenshot: None,
+            neighbor_address_input: Input::default(),
+            neighbor_mac_input: Input::default(),
+            neighbor_active_input: 0,
+            neighbor_editing: None,
+            neighbors_stale: false,
+            networkd_files_interface: String::new(),
+            networkd_files: None,
+            networkd_files_error: None,
+            selected_networkd_file: 0,
+            raw_editor: None,
+            networkd_files_stale: false,
+            doctor: None,
+            selected_finding: 0,
+
+            // Share network initialization
+            routing: None,
+            routing_loading: false,
+            last_routing_fetch: None,
+            dns_leak: None,
+            dns_leak_interface: String::new(),
+            dns_leak_running: false,
+            dhcp_probe: None,
+            dhcp_probe_interface: String::new(),
+            dhcp_probe_running: false,
+            reachability: None,
+            reachability_interface: String::new(),
+            reachability_running: false,
+            troubleshoot: None,
+            troubleshoot_interface: String::new(),
+            troubleshoot_running: false,
+            http_check: None,
+            http_check_input: Input::new(crate::httpcheck::DEFAULT_URL.to_string()),
+            http_check_bindings: Vec::new(),
+            http_check_binding: 0,
+            http_check_queued: false,
+            http_check_running: false,
+            identifying: None,
+            identify_running: false,
+            address_preference: GaiState::default(),
+            address_preference_choice: Preference::Ipv6,
+            address_order: None,
+            address_order_running: false,
+            speed_history: Vec::new(),
+            speed_test_running: false,
+            speed_test_requested: false,
+            last_scheduled_speed_test: None,
+            speed_test_manual: false,
+            alerts: AlertMonitor::default(),
+            pending_alerts: Vec::new(),
+            last_wan_check: None,
+            cert_expiries: Vec::new(),
+            last_cert_check: None,
+            wan_checking: false,
+            survey: None,
+            rf_interface: String::new(),
+            rf_session: None,
+            packet_view: None,
+            survey_scanning: false,
+            last_survey_scan: None,
+            survey_gps_polling: false,
+            last_survey_gps: None,
+            share_payload: None,
+            hotplug_offer: None,
+            interface_refresh_requested: false,
 
             // WiFi diagnostics initialization
-            show_wifi_diagnostics_dialog: false,
             wifi_diagnostics_data: None,
-        })
+            frame_events: VecDeque::new(),
+            containers: HashMap::new(),
+            last_container_refresh: None,
+            foreign_managers: HashMap::new(),
+            last_foreign_check: None,
+            mac_conflicts: Vec::new(),
+            last_mac_check: None,
+            safe_mode: false,
+            adopt_interface: String::new(),
+            mqtt: None,
+            mqtt_error: None,
+            last_mqtt_publish: None,
+            influx: None,
+            influx_error: None,
+            last_influx_push: None,
+            snmp: None,
+            last_snmp_refresh: None,
+            palette_input: Input::default(),
+            palette_selected: 0,
+            queued_key: None,
+            show_perf_overlay: false,
+            timings: Timings::default(),
+            pacer: Pacer::new(&config.refresh),
+            rates: Rates::default(),
+            baselines: Baselines::default(),
+            config,
+        }
+    }
+
+    /// Apply the result of a background task. All state changes coming from
+    /// spawned tasks go through here, so the tasks never hold the App itself.
+    pub fn apply(&mut self, event: AppEvent) {
+        // Timings only show in the perf overlay
+        if !matches!(event, AppEvent::Timed(..)) || self.show_perf_overlay {
+            self.redraw.mark(event.regions());
+        }
+        match event {
+            AppEvent::Tcp(snapshot) => self.tcp.record(snapshot),
+            AppEvent::Timed(measure, duration) => self.timings.record(measure, duration),
+            AppEvent::Containers(containers) => self.containers = containers,
+            AppEvent::ForeignManagers(foreign) => self.foreign_managers = foreign,
+            AppEvent::MacConflicts(conflicts) => self.apply_mac_conflicts(conflicts),
+            AppEvent::TaskFailed { task, error } => {
+                self.jobs.panicked(task, &error);
+                self.status_message = Some((format!("{} failed: {}", task, error), Instant::now()));
+            }
+            AppEvent::JobFinished(id, result) => {
+                if !self.jobs.finish(id, &result) {
+                    return;
+                }
+                let message = match result {
+                    Ok(Outcome::HotspotStarted(config, message)) => {
+                        self.active_hotspot = Some(config);
+                        self.open_hotspot_summary();
+                        message
+                    }
+                    Ok(Outcome::HotspotPersisted(config, message)) => {
+                        if config.is_none() {
+                            self.close_hotspot_summary();
+                        }
+                        self.active_hotspot = config;
+                        self.hotspot_unit_state = None;
+                        self.last_hotspot_unit_check = None;
+                        message
+                    }
+                    Ok(Outcome::UsbShared(interface, address, message)) => {
+                        match address {
+                            Some(address) => self.usb_shares.insert(interface, address),
+                            None => self.usb_shares.remove(&interface),
+                        };
+                        message
+                    }
+                    Ok(outcome) => outcome.message().to_string(),
+                    Err(e) => format!("{} failed: {} (J: jobs)", self.job_description(id), e),
+                };
+                self.status_message = Some((message, Instant::now()));
+                self.interface_refresh_requested = true;
+            }
+            AppEvent::Stats(mut stats) => {
+                // Update stats only (preserve other interface data). Interfaces
+                // may have come or gone since the task took its list of names
+                for interface in &mut self.interfaces {
+                    if let Some(stats) = stats.remove(&interface.name) {
+                        interface.stats = stats;
+                    }
+                }
+                self.rates.record(&self.interfaces, Instant::now());
+                self.baselines.observe(&self.interfaces);
+            }
+            AppEvent::Interfaces(interfaces) => self.set_interfaces(interfaces),
+            AppEvent::WifiInfo(updated_interfaces) => {
+                for updated in updated_interfaces {
+                    if let Some(interface) =
+                        self.interfaces.iter_mut().find(|i| i.name == updated.name)
+                    {
+                        let ssid = updated
+                            .wifi_info
+                            .as_ref()
+                            .and_then(|w| w.current_network.as_ref())
+                            .map(|n| n.ssid.clone());
+                        self.events.record_wifi(&updated.name, ssid);
+                        interface.wifi_info = updated.wifi_info;
+                    }
+                }
+            }
+            AppEvent::WifiScan(interface_name, result) => {
+                // Ignore results that arrive after the dialog was closed
+                if !self.modals.is_open(Modal::Wifi) || self.wifi_cached_since.is_none() {
+                    return;
+                }
+                match result {
+                    Ok(networks) => self.apply_wifi_scan(&interface_name, networks),
+                    Err(e) => self.wifi_scan_failed(&e),
+                }
+            }
+            AppEvent::SurveyScan(result) => {
+                self.survey_scanning = false;
+                // The interval runs from the end of a scan, so slow ones don't pile up
+                self.last_survey_scan = Some(Instant::now());
+                let Some(survey) = &mut self.survey else {
+                    return;
+                };
+                match result {
+                    Ok(networks) => {
+                        let timestamp = chrono::Local::now()
+                            .to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+                        survey.add_scan(&networks, &timestamp);
+                    }
+                    Err(e) => survey.error = Some(e),
+                }
+            }
+            AppEvent::DnsLeak(report) => {
+                self.dns_leak_running = false;
+                if self.modals.is_open(Modal::DnsLeak)
+                    && report.interface == self.dns_leak_interface
+                {
+                    self.dns_leak = Some(report);
+                }
+            }
+            AppEvent::DhcpProbe(report) => {
+                self.dhcp_probe_running = false;
+                if self.modals.is_open(Modal::DhcpProbe)
+                    && report.interface == self.dhcp_probe_interface
+                {
+                    self.status_message = Some((report.summary(), Instant::now()));
+                    self.dhcp_probe = Some(report);
+                }
+            }
+            AppEvent::HttpCheck(result) => {
+                self.http_check_running = false;
+                if self.modals.is_open(Modal::HttpCheck) {
+                    if let Ok(check) = &result {
+                        self.status_message = Some((check.summary(), Instant::now()));
+                    }
+                    self.http_check = Some(result);
+                }
+            }
+            AppEvent::Reachability(interface, result) => {
+                self.reachability_running = false;
+                if self.modals.is_open(Modal::Reachability)
+                    && interface == self.reachability_interface
+                {
+                    if let Ok(report) = &result {
+                        self.status_message = Some((report.summary(), Instant::now()));
+                    }
+                    self.reachability = Some(result);
+                }
+            }
+            AppEvent::Troubleshoot(interface, result) => {
+                self.troubleshoot_running = false;
+                if self.modals.is_open(Modal::Troubleshoot)
+                    && interface == self.troubleshoot_interface
+                {
+                    if let Ok(diagnosis) = &result {
+                        self.status_message = Some((diagnosis.summary(), Instant::now()));
+                    }
+                    self.troubleshoot = Some(result);
+                }
+            }
+            AppEvent::Identified(interface, result) => {
+                self.identifying = None;
+                self.identify_running = false;
+                let message = match result {
+                    Ok(()) => format!("Stopped blinking {}", interface),
+                    Err(e) => e,
+                };
+                self.status_message = Some((message, Instant::now()));
+            }
+            AppEvent::AddressOrder(result) => {
+                self.address_order_running = false;
+                if self.modals.is_open(Modal::AddressPreference) {
+                    self.address_order = Some(result);
+                }
+            }
+            AppEvent::SpeedTest(result) => {
+                self.speed_test_running = false;
+                if !self.network_manager.is_demo() {
+                    if let Err(e) =
+                        crate::speedtest::record(&result, self.config.speedtest.keep_days)
+                    {
+                        self.status_message =
+                            Some((format!("Speed test not saved: {:#}", e), Instant::now()));
+                    }
+                }
+                let summary = result.summary_in(|mbps| self.config.display.mbps(mbps));
+                let message = match &result.error {
+                    Some(error) => format!("Speed test: {} ({})", summary, error),
+                    None => format!("Speed test: {}", summary),
+                };
+                self.status_message = Some((message, Instant::now()));
+                if !self.speed_test_manual && self.config.alerts.enabled() {
+                    let alert = self.alerts.speed(&self.config.alerts, result.download_mbps);
+                    self.pending_alerts.extend(alert);
+                }
+                self.speed_history.push(result);
+            }
+            AppEvent::WanChecked(up) => {
+                self.wan_checking = false;
+                let alert = self.alerts.wan(&self.config.alerts, up, Instant::now());
+                self.pending_alerts.extend(alert);
+            }
+            AppEvent::RfSighting(sighting) => {
+                if let Some(session) = &mut self.rf_session {
+                    session.survey.record(sighting, Instant::now());
+                }
+            }
+            AppEvent::RfCaptureEnded(error) => {
+                if let Some(session) = &mut self.rf_session {
+                    session.capture = Capture::Stopped(error);
+                }
+            }
+            AppEvent::Packets(sample) => {
+                if let Some(view) = &mut self.packet_view {
+                    view.record(sample);
+                }
+            }
+            AppEvent::PacketCaptureEnded(error) => {
+                if let Some(view) = &mut self.packet_view {
+                    view.capture = Capture::Stopped(error);
+                }
+            }
+            AppEvent::SurveyGps(status) => {
+                self.survey_gps_polling = false;
+                if let Some(survey) = &mut self.survey {
+                    survey.gps = status;
+                }
+            }
+            AppEvent::Routes(interface_name, routes) => {
+                self.routes.finish(&interface_name, routes);
+            }
+            AppEvent::WireGuard(interface_name, status) => {
+                self.wireguard.finish(&interface_name, status);
+            }
+            AppEvent::Routing(status) => {
+                self.routing_loading = false;
+                if self.modals.is_open(Modal::Routing) {
+                    self.routing = Some(status);
+                }
+            }
+            AppEvent::Queues(interface_name, stats) => {
+                self.queues.finish(&interface_name, stats);
+            }
+            AppEvent::AutoConnected { ssid, interface } => {
+                self.config.update_wifi_connection(&ssid, &interface);
+                let _ = self.config.save(); // Save updated connection time
+                self.status_message = Some((format!("Auto-connected to {}", ssid), Instant::now()));
+            }
+            AppEvent::Trust(trust) => {
+                // A failure of this pass follows as its own event
+                self.network_trust = trust;
+                self.firewall_failed = None;
+            }
+            AppEvent::FirewallFailed(error) => {
+                self.status_message = Some((
+                    format!("Untrusted network firewall not applied: {}", error),
+                    Instant::now(),
+                ));
+                self.firewall_failed = Some(error);
+            }
+            AppEvent::HotspotStations(result) => {
+                if !self.modals.is_open(Modal::HotspotClients)
+                    && !self.modals.is_open(Modal::HotspotSummary)
+                {
+                    return;
+                }
+                match result {
+                    Ok(stations) => {
+                        self.hotspot_clients.update(stations, Instant::now());
+                        self.hotspot_clients_error = None;
+                        self.selected_hotspot_client = self
+                            .selected_hotspot_client
+                            .min(self.hotspot_clients.clients.len().saturating_sub(1));
+                    }
+                    Err(e) => self.hotspot_clients_error = Some(e),
+                }
+            }
+            AppEvent::PersistentHotspot(found) => self.apply_persistent_hotspot(found),
+            AppEvent::StaticNeighbors(interface, result) => {
+                if !self.modals.is_open(Modal::Neighbors) || interface != self.neighbor_interface {
+                    return;
+                }
+                match result {
+                    Ok(neighbors) => {
+                        self.selected_neighbor = self
+                            .selected_neighbor
+                            .min(neighbors.len().saturating_sub(1));
+                        self.static_neighbors = Some(neighbors);
+                        self.static_neighbors_error = None;
+                    }
+                    Err(e) => self.static_neighbors_error = Some(e),
+                }
+            }
+            AppEvent::NetworkdFiles(interface, result) => {
+                if !self.modals.is_open(Modal::NetworkdFiles)
+                    || interface != self.networkd_files_interface
+                {
+                    return;
+                }
+                match result {
+                    Ok(files) => {
+                        self.selected_networkd_file = self
+                            .selected_networkd_file
+                            .min(files.files.len().saturating_sub(1));
+                        self.networkd_files = Some(files);
+                        self.networkd_files_error = None;
+                    }
+                    Err(e) => self.networkd_files_error = Some(e),
+                }
+            }
+            AppEvent::Frame(event) => {
+                if event.is_drop() {
+                    self.events.push(
+                        event.interface.clone(),
+                        EventKind::WifiDropped,
+                        event.summary(),
+                    );
+                }
+                self.frame_events.push_back(SeenFrameEvent {
+                    timestamp: chrono::Local::now(),
+                    event,
+                });
+                while self.frame_events.len() > MAX_FRAME_EVENTS {
+                    self.frame_events.pop_front();
+                }
+            }
+            AppEvent::LinkChanged => {
+                self.interface_refresh_requested = true;
+                self.pacer.link_changed(Instant::now());
+            }
+            AppEvent::VpnPolicyApplied { applied, failed } => {
+                for (interface, up) in applied {
+                    let message = format!(
+                        "{} {} by auto-connect policy",
+                        interface,
+                        if up { "brought up" } else { "taken down" }
+                    );
+                    self.status_message = Some((message.clone(), Instant::now()));
+                    self.events
+                        .push(interface, EventKind::StateChanged, message);
+                }
+                // Last, so the footer shows a failure over a success
+                for (interface, error) in failed {
+                    let message =
+                        format!("Auto-connect policy for {} failed: {}", interface, error);
+                    self.status_message = Some((message.clone(), Instant::now()));
+                    self.events
+                        .push(interface, EventKind::StateChanged, message);
+                }
+            }
+            AppEvent::EndpointsReresolved(changes) => {
+                for change in changes {
+                    let message = format!("{} re-resolved to {}", change.endpoint, change.address);
+                    self.events
+                        .push(change.interface, EventKind::EndpointChanged, message);
+                }
+            }
+        }
+    }
+
+    /// Replace the interface list, recording what changed in the timeline.
+    pub fn set_interfaces(&mut self, mut interfaces: Vec<Interface>) {
+        // A refresh only says an interface is wireless; keep what the last
+        // WiFi info update found while it stays up
+        for iface in interfaces.iter_mut().filter(|iface| iface.state == "UP") {
+            let previous = self
+                .interfaces
+                .iter()
+                .find(|old| old.name == iface.name && old.state == "UP");
+            if let (Some(wifi_info), Some(previous)) = (&mut iface.wifi_info, previous) {
+                if let Some(known) = &previous.wifi_info {
+                    *wifi_info = known.clone();
+                }
+            }
+        }
+        // Ignored interfaces don't make it into the timeline or hotplug offers
+        let managed = |list: &[Interface]| -> Vec<Interface> {
+            list.iter()
+                .filter(|iface| !self.config.interfaces.is_ignored(&iface.name))
+                .cloned()
+                .collect()
+        };
+        let (before, after) = (managed(&self.interfaces), managed(&interfaces));
+        self.events.record_changes(&before, &after);
+        // Physical NICs that weren't there before; nothing counts at startup
+        let plugged: Vec<Interface> = after
+            .iter()
+            .filter(|iface| iface.hardware.is_some())
+            .filter(|iface| !before.is_empty() && !before.iter().any(|o| o.name == iface.name))
+            .cloned()
+            .collect();
+
+        // Keep the selection on the same interface as others come and go
+        let selected = self
+            .get_selected_interface()
+            .map(|iface| iface.name.clone());
+        self.hidden_interfaces = interfaces.len() - after.len();
+        self.interfaces = if self.show_ignored { interfaces } else { after };
+        if let Some(index) =
+            selected.and_then(|name| self.interfaces.iter().position(|iface| iface.name == name))
+        {
+            self.selected_index = index;
+        }
+        self.selected_index = self
+            .selected_index
+            .min(self.interfaces.len().saturating_sub(1));
+        self.baselines.observe(&self.interfaces);
+        for iface in plugged {
+            self.hotplugged(&iface);
+        }
+    }
+
+    fn hotplugged(&mut self, iface: &Interface) {
+        let hardware = iface
+            .hardware
+            .as_ref()
+            .map(|h| h.summary())
+            .unwrap_or_default();
+        let profile = self
+            .config
+            .profiles
+            .iter()
+            .find(|profile| profile.matches_interface(&iface.name))
+            .cloned();
+
+        match profile {
+            Some(profile) => {
+                self.hotplug_offer = Some(HotplugOffer {
+                    interface: iface.name.clone(),
+                    hardware,
+                    profile,
+                    auto: self.config.hotplug.auto_apply && !self.safe_mode,
+                });
+                self.show_hotplug_offer();
+            }
+            None => {
+                self.status_message = Some((
+                    format!("{} plugged in: {}", iface.name, hardware),
+                    Instant::now(),
+                ));
+            }
+        }
+    }
+
+    /// Put the prompt for a hotplugged NIC on screen, though not over a text
+    /// field: a `y` or Enter meant for it would apply the profile. It waits
+    /// for that dialog to close.
+    pub fn show_hotplug_offer(&mut self) {
+        let prompt = self.hotplug_offer.as_ref().is_some_and(|offer| !offer.auto);
+        if prompt && !self.modals.top().is_some_and(Modal::takes_text) {
+            self.modals.open(Modal::Hotplug);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Write the offered profile's configuration for the plugged-in NIC.
+    pub fn accept_hotplug_offer(&mut self) {
+        self.modals.close(Modal::Hotplug);
+        let Some(offer) = self.hotplug_offer.take() else {
+            return;
+        };
+        if self.held_by_other_manager(&offer.interface) {
+            return;
+        }
+        self.queue_job(Job::ApplyProfile {
+            interface: offer.interface,
+            profile: offer.profile,
+        });
+    }
+
+    /// Run `job` once those before it are done; the main loop starts it
+    pub fn queue_job(&mut self, job: Job) {
+        let message = match self.jobs.active() {
+            0 => format!("{}...", job.describe()),
+            waiting => format!(
+                "{} queued after {} job(s) (J: jobs)",
+                job.describe(),
+                waiting
+            ),
+        };
+        self.jobs.push(job);
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    fn job_description(&self, id: JobId) -> String {
+        self.jobs
+            .jobs()
+            .iter()
+            .find(|queued| queued.id == id)
+            .map(|queued| queued.job.describe())
+            .unwrap_or_else(|| "Job".to_string())
+    }
+
+    pub fn open_jobs_panel(&mut self) {
+        self.selected_job = self.jobs.jobs().len().saturating_sub(1);
+        self.modals.open(Modal::Jobs);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_jobs_panel(&mut self) {
+        self.modals.close(Modal::Jobs);
+        self.needs_redraw = true;
+    }
+
+    pub fn job_navigate(&mut self, down: bool) {
+        let count = self.jobs.jobs().len();
+        if count == 0 {
+            return;
+        }
+        self.selected_job = if down {
+            (self.selected_job + 1) % count
+        } else {
+            (self.selected_job + count - 1) % count
+        };
+        self.needs_redraw = true;
+    }
+
+    fn selected_job_id(&self) -> Option<JobId> {
+        self.jobs
+            .jobs()
+            .get(self.selected_job)
+            .map(|queued| queued.id)
+    }
+
+    /// A running job stops where it is; what it already changed stays
+    pub fn cancel_selected_job(&mut self) {
+        let Some(id) = self.selected_job_id() else {
+            return;
+        };
+        let running = self
+            .jobs
+            .jobs()
+            .iter()
+            .any(|queued| queued.id == id && queued.state == JobState::Running);
+        let message = match (self.jobs.cancel(id), running) {
+            (true, true) => format!(
+                "{} cancelled; what it already changed stays",
+                self.job_description(id)
+            ),
+            (true, false) => format!("{} cancelled", self.job_description(id)),
+            (false, _) => "Only pending and running jobs can be cancelled".to_string(),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    pub fn retry_selected_job(&mut self) {
+        let Some(id) = self.selected_job_id() else {
+            return;
+        };
+        match self.jobs.retry(id) {
+            Some(_) => {
+                self.selected_job = self.jobs.jobs().len() - 1;
+                self.status_message = Some((
+                    format!("{} queued again", self.job_description(id)),
+                    Instant::now(),
+                ));
+            }
+            None => {
+                self.status_message = Some((
+                    "Only failed and cancelled jobs can be retried".to_string(),
+                    Instant::now(),
+                ));
+            }
+        }
+        self.needs_redraw = true;
+    }
+
+    pub fn dismiss_hotplug_offer(&mut self) {
+        self.modals.close(Modal::Hotplug);
+        self.hotplug_offer = None;
     }
 
     pub async fn refresh_interfaces(&mut self) -> Result<()> {
-        self.interfaces = self.network_manager.get_interfaces().await?;
+        let interfaces = self.network_manager.get_interfaces().await?;
+        self.set_interfaces(interfaces);
         self.last_interface_refresh = Instant::now();
         // Silent refresh for automatic updates
         Ok(())
     }
 
     pub async fn manual_refresh_interfaces(&mut self) -> Result<()> {
-        self.interfaces = self.network_manager.get_interfaces().await?;
+        let interfaces = self.network_manager.get_interfaces().await?;
+        self.set_interfaces(interfaces);
         self.last_interface_refresh = Instant::now();
         self.status_message = Some(("Interfaces refreshed".to_string(), Instant::now()));
         Ok(())
     }
 
     pub fn next(&mut self) {
-        if !self.show_edit_dialog && self.selected_index < self.interfaces.len() - 1 {
+        if !self.modals.is_open(Modal::Edit) && self.selected_index + 1 < self.interfaces.len() {
             self.selected_index += 1;
             self.needs_redraw = true;
         }
     }
 
     pub fn previous(&mut self) {
-        if !self.show_edit_dialog && self.selected_index > 0 {
+        if !self.modals.is_open(Modal::Edit) && self.selected_index > 0 {
             self.selected_index -= 1;
             self.needs_redraw = true;
         }
     }
 
-    pub fn toggle_details(&mut self) {
-        if !self.show_edit_dialog {
-            self.show_details = !self.show_details;
-            self.needs_redraw = true;
-        }
+    pub fn next_detail_tab(&mut self) {
+        self.detail_tab = self.detail_tab.next();
+        self.needs_redraw = true;
     }
 
-    pub fn edit_interface(&mut self) {
-        if let Some(interface) = self.interfaces.get(self.selected_index) {
-            self.edit_interface = Some(interface.clone());
-            self.show_edit_dialog = true;
+    pub fn previous_detail_tab(&mut self) {
+        self.detail_tab = self.detail_tab.previous();
+        self.needs_redraw = true;
+    }
 
-            // Pre-fill current values
-            if let Some(ip) = interface.ipv4_addresses.first() {
-                self.ip_input = Input::default().with_value(ip.clone());
-            }
-            if let Some(gateway) = &interface.gateway {
-                self.gateway_input = Input::default().with_value(gateway.clone());
-            }
-            if !interface.dns_servers.is_empty() {
-                self.dns_input = Input::default().with_value(interface.dns_servers.join(", "));
-            }
-        }
+    /// What the visible detail tab needs fetched, if anything: the tab and
+    /// the selected interface.
+    pub fn detail_fetch_due(&self) -> Option<(DetailTab, String)> {
+        let name = &self.get_selected_interface()?.name;
+        let due = match self.detail_tab {
+            DetailTab::Routes => self.routes.due(name),
+            DetailTab::Queues => self.queues.due(name),
+            DetailTab::WireGuard => self.wireguard.due(name),
+            _ => false,
+        };
+        due.then(|| (self.detail_tab, name.clone()))
     }
 
-    pub fn close_dialog(&mut self) {
-        self.show_edit_dialog = false;
-        self.edit_interface = None;
-        self.ip_input = Input::default();
-        self.gateway_input = Input::default();
-        self.dns_input = Input::default();
-        self.active_input = 0;
+    /// Ring sizes and RSS spread of the selected NIC, filled in with what
+    /// the Queues tab last read
+    pub fn open_queue_tuning_form(&mut self) {
+        let Some(name) = self
+            .get_selected_interface()
+            .map(|iface| iface.name.clone())
+        else {
+            return;
+        };
+        self.detail_tab = DetailTab::Queues;
+        let current = if self.queues.interface.as_deref() == Some(name.as_str()) {
+            self.queues.value.clone()
+        } else {
+            QueueStats::default()
+        };
+        let rings = current.rings.unwrap_or_default();
+        let value = |number: Option<u32>| number.map(|n| n.to_string()).unwrap_or_default();
+        self.queue_rx_ring_input = Input::default().with_value(value(rings.rx));
+        self.queue_tx_ring_input = Input::default().with_value(value(rings.tx));
+        self.queue_rss_input =
+            Input::default().with_value(value(crate::queues::equal_rss_queues(&current.rss_table)));
+        self.queue_tuning_active_input = 0;
+        self.queue_tuning_interface = name;
+        self.modals.open(Modal::QueueTuning);
+        self.needs_redraw = true;
     }
 
-    pub fn toggle_dhcp(&mut self) {
-        self.use_dhcp = !self.use_dhcp;
+    pub fn close_queue_tuning_form(&mut self) {
+        self.modals.close(Modal::QueueTuning);
+        self.needs_redraw = true;
     }
 
-    pub fn next_input(&mut self) {
-        if !self.use_dhcp {
-            self.active_input = (self.active_input + 1) % 3;
-        }
+    pub fn queue_tuning_next_input(&mut self) {
+        self.queue_tuning_active_input = (self.queue_tuning_active_input + 1) % 3;
+        self.needs_redraw = true;
     }
 
-    pub fn input_char(&mut self, c: char) {
-        match self.active_input {
-            0 => {
-                self.ip_input.handle_event(&crossterm::event::Event::Key(
+    pub fn queue_tuning_input(&mut self, key: crossterm::event::KeyEvent) {
+        let event = crossterm::event::Event::Key(key);
+        match self.queue_tuning_active_input {
+            0 => self.queue_rx_ring_input.handle_event(&event),
+            1 => self.queue_tx_ring_input.handle_event(&event),
+            _ => self.queue_rss_input.handle_event(&event),
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Apply the form now; with `persist`, also replay it whenever the NIC appears
+    pub async fn save_queue_tuning(&mut self, persist: bool) {
+        let interface = self.queue_tuning_interface.clone();
+        let current = if self.queues.interface.as_deref() == Some(interface.as_str()) {
+            self.queues.value.clone()
+        } else {
+            QueueStats::default()
+        };
+        let tuning = match crate::queues::tuning_from_form(
+            self.queue_rx_ring_input.value(),
+            self.queue_tx_ring_input.value(),
+            self.queue_rss_input.value(),
+            &current,
+        ) {
+            Ok(tuning) => tuning,
+            Err(e) => {
+                self.status_message = Some((e.to_string(), Instant::now()));
+                return;
+            }
+        };
+        if tuning.is_empty() {
+            self.status_message = Some((
+                format!("Nothing to change on {}", interface),
+                Instant::now(),
+            ));
+            self.close_queue_tuning_form();
+            return;
+        }
+        if self.network_manager.is_demo() {
+            self.status_message = Some((
+                "Demo mode: queue tuning not applied".to_string(),
+                Instant::now(),
+            ));
+            self.close_queue_tuning_form();
+            return;
+        }
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return;
+        };
+
+        // Left open to try other values
+        if let Err(e) = crate::queues::apply_tuning(&interface, &tuning).await {
+            self.status_message = Some((e.to_string(), Instant::now()));
+            return;
+        }
+        let message = if !persist {
+            format!("Tuning applied to {} until it is reset", interface)
+        } else {
+            match crate::queues::persist_tuning(&interface, &tuning) {
+                Ok(path) => format!(
+                    "Tuning applied to {} and kept in {}",
+                    interface,
+                    path.display()
+                ),
+                Err(e) => format!("Tuning applied to {} but not kept: {}", interface, e),
+            }
+        };
+        self.close_queue_tuning_form();
+        self.queues.expire();
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    pub async fn edit_interface(&mut self) {
+        if let Some(interface) = self.interfaces.get(self.selected_index).cloned() {
+            self.edit_interface = Some(interface.clone());
+            self.modals.open(Modal::Edit);
+
+            // Pre-fill current values
+            if let Some(ip) = interface.ipv4_addresses.first() {
+                self.ip_input = Input::default().with_value(ip.clone());
+            }
+            if let Some(gateway) = &interface.gateway {
+                self.gateway_input = Input::default().with_value(gateway.clone());
+            }
+            if !interface.dns_servers.is_empty() {
+                self.dns_input = Input::default().with_value(interface.dns_servers.join(", "));
+            }
+            if self.network_manager.is_demo() {
+                self.link_responders_now = crate::resolved::LinkResponders {
+                    llmnr: Some("yes".to_string()),
+                    multicast_dns: Some("no".to_string()),
+                };
+            } else {
+                self.link_responders = crate::resolved::link_settings(&interface.name).await;
+                self.link_responders_now = crate::resolved::link_state(&interface.name).await;
+                self.link_local_fallback = crate::linklocal::fallback(&interface.name).await;
+            }
+            self.link_local_fallback_was = self.link_local_fallback;
+        }
+    }
+
+    pub fn close_dialog(&mut self) {
+        self.modals.close(Modal::Edit);
+        self.edit_interface = None;
+        self.ip_input = Input::default();
+        self.gateway_input = Input::default();
+        self.dns_input = Input::default();
+        self.revert_input = Input::default();
+        self.link_responders = Default::default();
+        self.link_responders_now = Default::default();
+        self.link_local_fallback = false;
+        self.link_local_fallback_was = false;
+        self.active_input = 0;
+    }
+
+    /// Space: DHCP on the address fields, the next mode on LLMNR and mDNS
+    pub fn edit_toggle(&mut self) {
+        let modes = &crate::resolved::RESPONDER_MODES;
+        match self.active_input {
+            4 => {
+                self.link_responders.llmnr =
+                    crate::resolved::cycle(&self.link_responders.llmnr, modes)
+            }
+            5 => {
+                self.link_responders.multicast_dns =
+                    crate::resolved::cycle(&self.link_responders.multicast_dns, modes)
+            }
+            6 => self.link_local_fallback = !self.link_local_fallback,
+            _ => self.toggle_dhcp(),
+        }
+    }
+
+    pub fn toggle_dhcp(&mut self) {
+        self.use_dhcp = !self.use_dhcp;
+        if self.use_dhcp {
+            self.active_input = 3;
+        }
+    }
+
+    pub fn next_input(&mut self) {
+        self.active_input = if self.use_dhcp {
+            // No addresses to fill in: revert time, LLMNR, mDNS, IPv4LL
+            match self.active_input {
+                3 => 4,
+                4 => 5,
+                5 => 6,
+                _ => 3,
+            }
+        } else {
+            (self.active_input + 1) % 7
+        };
+    }
+
+    pub fn input_char(&mut self, c: char) {
+        match self.active_input {
+            0 => {
+                self.ip_input.handle_event(&crossterm::event::Event::Key(
                     crossterm::event::KeyEvent::new(
                         crossterm::event::KeyCode::Char(c),
                         crossterm::event::KeyModifiers::empty(),
@@ -257,6 +1734,15 @@ impl App {
                     ),
                 ));
             }
+            3 => {
+                self.revert_input
+                    .handle_event(&crossterm::event::Event::Key(
+                        crossterm::event::KeyEvent::new(
+                            crossterm::event::KeyCode::Char(c),
+                            crossterm::event::KeyModifiers::empty(),
+                        ),
+                    ));
+            }
             _ => {}
         }
     }
@@ -277,12 +1763,41 @@ impl App {
             2 => {
                 self.dns_input.handle_event(&backspace_event);
             }
+            3 => {
+                self.revert_input.handle_event(&backspace_event);
+            }
             _ => {}
         }
     }
 
     pub async fn save_configuration(&mut self) -> Result<()> {
+        let held = self.edit_interface.as_ref().map(|i| i.name.clone());
+        if held.is_some_and(|name| self.held_by_other_manager(&name)) {
+            self.close_dialog();
+            return Ok(());
+        }
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+
+        if self.network_manager.is_demo() {
+            self.status_message = Some((
+                "Demo mode: configuration not written".to_string(),
+                Instant::now(),
+            ));
+            self.close_dialog();
+            return Ok(());
+        }
+
         if let Some(interface) = &self.edit_interface {
+            let minutes = match crate::pending::parse_minutes(self.revert_input.value()) {
+                Ok(minutes) => minutes,
+                // Keep the dialog open to fix the typo
+                Err(e) => {
+                    self.status_message = Some((e.to_string(), Instant::now()));
+                    return Ok(());
+                }
+            };
             let dns_servers: Vec<String> = self
                 .dns_input
                 .value()
@@ -291,7 +1806,29 @@ impl App {
                 .filter(|s| !s.is_empty())
                 .collect();
 
-            self.systemd_config
+            // The revert is in place before the change, in case the change cuts us off
+            let pending = match minutes {
+                Some(minutes) => {
+                    let path = crate::systemd::network_file_path(&interface.name);
+                    let revert = Revert::NetworkFile {
+                        interface: interface.name.clone(),
+                        previous: std::fs::read_to_string(&path).ok(),
+                        path,
+                    };
+                    let description = format!("{} addressing", interface.name);
+                    match crate::pending::schedule(description, minutes, revert).await {
+                        Ok(change) => Some(change),
+                        Err(e) => {
+                            self.status_message = Some((e.to_string(), Instant::now()));
+                            return Ok(());
+                        }
+                    }
+                }
+                None => None,
+            };
+
+            let created = self
+                .systemd_config
                 .create_config(
                     &interface.name,
                     self.use_dhcp,
@@ -310,17 +1847,70 @@ impl App {
                     } else {
                         Some(dns_servers)
                     },
+                    &NetworkExtras::default(),
                 )
-                .await?;
+                .await;
+            if let Err(e) = created {
+                if let Some(change) = &pending {
+                    crate::pending::confirm(&change.id).await.ok();
+                }
+                return Err(e);
+            }
 
-            self.status_message = Some(("Configuration saved".to_string(), Instant::now()));
+            let mut message = match &pending {
+                Some(change) => format!(
+                    "Configuration applied; reverts in {} unless kept (K)",
+                    crate::pending::format_remaining(change.remaining(SystemTime::now()))
+                ),
+                None => "Configuration saved".to_string(),
+            };
+            // After the .network file, so the drop-in goes next to the one now applied
+            if crate::resolved::link_settings(&interface.name).await != self.link_responders {
+                match crate::resolved::set_link(&interface.name, &self.link_responders).await {
+                    Ok(true) => {}
+                    Ok(false) => message.push_str("; LLMNR/mDNS set until networkd manages it"),
+                    Err(e) => message = format!("{}; LLMNR/mDNS not changed: {}", message, e),
+                }
+            }
+            if self.link_local_fallback != self.link_local_fallback_was {
+                if let Err(e) =
+                    crate::linklocal::set_fallback(&interface.name, self.link_local_fallback).await
+                {
+                    message = format!("{}; link-local fallback not changed: {}", message, e);
+                }
+            }
+            self.last_pending_check = None;
+            self.status_message = Some((message, Instant::now()));
             self.close_dialog();
             self.refresh_interfaces().await?;
         }
         Ok(())
     }
 
+    /// Ask for a lease again, for an interface stuck on a 169.254 address
+    pub async fn retry_dhcp(&mut self) {
+        let Some(name) = self
+            .get_selected_interface()
+            .map(|iface| iface.name.clone())
+        else {
+            return;
+        };
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return;
+        };
+        let message = match self.network_manager.retry_dhcp(&name).await {
+            Ok(()) => format!("Asking for a DHCP lease on {} again", name),
+            Err(e) => format!("Could not retry DHCP on {}: {}", name, e),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
     pub async fn toggle_interface_state(&mut self) -> Result<()> {
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+
         if let Some(interface) = self.interfaces.get(self.selected_index) {
             let interface_name = interface.name.clone();
             let new_state = if interface.state == "UP" {
@@ -340,20 +1930,243 @@ impl App {
         Ok(())
     }
 
+    pub async fn toggle_promiscuous(&mut self) -> Result<()> {
+        let Some(interface) = self.get_selected_interface() else {
+            return Ok(());
+        };
+        let (name, on) = (interface.name.clone(), !interface.promiscuous);
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+
+        let message = match self.network_manager.set_promiscuous(&name, on).await {
+            Ok(()) => format!(
+                "Promiscuous mode {} on {}",
+                if on { "enabled" } else { "disabled" },
+                name
+            ),
+            Err(e) => e.to_string(),
+        };
+        self.refresh_interfaces().await?;
+        self.status_message = Some((message, Instant::now()));
+        Ok(())
+    }
+
+    /// Blink the selected NIC's port LED for a while, to find its cable.
+    pub fn identify_selected(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        let name = interface.name.clone();
+        if let Some(blinking) = &self.identifying {
+            self.status_message = Some((format!("Already blinking {}", blinking), Instant::now()));
+            return;
+        }
+        self.status_message = Some((
+            format!("Blinking {}'s port LED for {}s", name, IDENTIFY_SECONDS),
+            Instant::now(),
+        ));
+        self.identifying = Some(name);
+        self.needs_redraw = true;
+    }
+
+    pub fn identify_due(&self) -> Option<(String, u64)> {
+        if self.identify_running {
+            return None;
+        }
+        self.identifying
+            .clone()
+            .map(|interface| (interface, IDENTIFY_SECONDS))
+    }
+
+    pub fn mark_identify_started(&mut self) {
+        self.identify_running = true;
+    }
+
+    /// Add or remove the monitor interface of the selected wireless NIC.
+    pub async fn toggle_monitor_mode(&mut self) -> Result<()> {
+        let Some(interface) = self.get_selected_interface() else {
+            return Ok(());
+        };
+        let name = interface.name.clone();
+        if interface.wifi_info.is_none() && !interface.monitor {
+            self.status_message = Some((
+                format!("{} is not a wireless interface", name),
+                Instant::now(),
+            ));
+            return Ok(());
+        }
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+
+        let result = self
+            .network_manager
+            .toggle_monitor_interface(&name, &self.interfaces)
+            .await;
+        let message = match result {
+            Ok((monitor, true)) => format!("Monitor interface {} created on {}", monitor, name),
+            Ok((monitor, false)) => format!("Monitor interface {} removed", monitor),
+            Err(e) => e.to_string(),
+        };
+        self.refresh_interfaces().await?;
+        self.status_message = Some((message, Instant::now()));
+        Ok(())
+    }
+
+    // Serialize system changes with other lantern instances and scripts
+    fn begin_change(&mut self) -> Option<OperationLock> {
+        match OperationLock::try_acquire() {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                self.status_message = Some((e.to_string(), Instant::now()));
+                None
+            }
+        }
+    }
+
+    /// Like `begin_change`, for a change to the selected interface, which
+    /// must not be one lantern is told to ignore.
+    fn begin_change_on_selected(&mut self) -> Option<OperationLock> {
+        if self.selected_is_ignored() {
+            return None;
+        }
+        self.begin_change()
+    }
+
+    /// Ignored interfaces are left alone; says so when the selected one is
+    fn selected_is_ignored(&mut self) -> bool {
+        let selected = self
+            .get_selected_interface()
+            .map(|iface| iface.name.clone());
+        let Some(name) = selected.filter(|name| self.config.interfaces.is_ignored(name)) else {
+            return false;
+        };
+        self.status_message = Some((
+            format!("{} is ignored by lantern; press I to manage it again", name),
+            Instant::now(),
+        ));
+        self.needs_redraw = true;
+        true
+    }
+
     pub fn should_refresh_stats(&self) -> bool {
-        self.last_refresh.elapsed() > Duration::from_secs(1)
+        self.pacer
+            .stats_interval(Instant::now())
+            .is_some_and(|interval| self.last_refresh.elapsed() > interval)
     }
 
     pub fn should_refresh_interfaces(&self) -> bool {
-        self.last_interface_refresh.elapsed() > Duration::from_secs(5)
+        let now = Instant::now();
+        let Some(interval) = self.pacer.interface_interval(now) else {
+            return false; // Paused; link changes wait for the resume
+        };
+        if self.safe_mode {
+            return false; // `ip` runs only on r
+        }
+        let elapsed = self.last_interface_refresh.elapsed();
+        // Netlink notifications come in bursts; coalesce them
+        (self.interface_refresh_requested && elapsed > self.pacer.link_debounce(now))
+            || elapsed > interval
+    }
+
+    /// The selected interface's recent 802.11 events, newest first.
+    pub fn selected_frame_events(&self) -> Vec<&SeenFrameEvent> {
+        let Some(interface) = self.get_selected_interface() else {
+            return Vec::new();
+        };
+        self.frame_events
+            .iter()
+            .rev()
+            .filter(|seen| seen.event.interface == interface.name)
+            .collect()
+    }
+
+    /// Summed counters and rates of each configured aggregate group.
+    pub fn group_totals(&self) -> Vec<GroupTotals> {
+        self.rates.totals(&self.config.groups, &self.interfaces)
+    }
+
+    /// Freeze or resume automatic refreshes. Resuming catches up at once.
+    pub fn toggle_pause(&mut self) {
+        let paused = self.pacer.toggle_pause(Instant::now());
+        if !paused {
+            self.interface_refresh_requested = true;
+        }
+        let message = if paused {
+            "Refresh paused (f: resume, r: refresh once)"
+        } else {
+            "Refresh resumed"
+        };
+        self.status_message = Some((message.to_string(), Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    /// Count the selected interface's traffic from now on
+    pub fn zero_counters(&mut self) {
+        let Some(interface) = self.get_selected_interface().cloned() else {
+            return;
+        };
+        self.baselines.reset(&interface);
+        self.status_message = Some((
+            format!(
+                "{} counters zeroed (Z: back to totals since boot)",
+                interface.name
+            ),
+            Instant::now(),
+        ));
+        self.needs_redraw = true;
+    }
+
+    pub fn restore_counters(&mut self) {
+        let Some(name) = self
+            .get_selected_interface()
+            .map(|iface| iface.name.clone())
+        else {
+            return;
+        };
+        if self.baselines.clear(&name) {
+            self.status_message = Some((
+                format!("{} counters show the kernel's totals again", name),
+                Instant::now(),
+            ));
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Cycle dBm / percent / both for this session; `[display] signal` keeps it
+    pub fn cycle_units(&mut self) {
+        self.config.display.next_units();
+        let (_, unit) = self.config.display.mega_rate();
+        self.status_message = Some((
+            format!(
+                "Rates shown in {} (set [display] units and rates in config.toml to keep it)",
+                unit
+            ),
+            Instant::now(),
+        ));
+        self.needs_redraw = true;
+    }
+
+    pub fn cycle_signal_unit(&mut self) {
+        let unit = self.config.display.signal.next();
+        self.config.display.signal = unit;
+        self.status_message = Some((
+            format!(
+                "Signal shown as {} (set [display] signal in config.toml to keep it)",
+                unit.label()
+            ),
+            Instant::now(),
+        ));
+        self.needs_redraw = true;
     }
 
     pub fn should_update_wifi_info(&self) -> bool {
-        self.last_wifi_update.elapsed() > Duration::from_secs(10)
+        !self.safe_mode && self.last_wifi_update.elapsed() > Duration::from_secs(10)
     }
 
     pub fn should_check_auto_connect(&self) -> bool {
-        self.last_auto_connect_check.elapsed() > Duration::from_secs(30)
+        !self.safe_mode && self.last_auto_connect_check.elapsed() > Duration::from_secs(30)
     }
 
     #[allow(dead_code)]
@@ -386,6 +2199,7 @@ impl App {
 
     pub fn mark_interface_refresh_started(&mut self) {
         self.last_interface_refresh = Instant::now();
+        self.interface_refresh_requested = false;
     }
 
     pub fn mark_wifi_update_started(&mut self) {
@@ -396,113 +2210,111 @@ impl App {
         self.last_auto_connect_check = Instant::now();
     }
 
-    // Auto-connect functionality
-    pub async fn check_auto_connect(&mut self) -> Result<()> {
-        // Only auto-connect if no WiFi interface is currently connected
-        let has_connected_wifi = self.interfaces.iter().any(|iface| {
-            iface.wifi_info.is_some()
-                && iface.state == "UP"
-                && iface.wifi_info.as_ref().unwrap().current_network.is_some()
-        });
-
-        if has_connected_wifi {
-            return Ok(()); // Already connected to WiFi
+    pub fn should_reresolve_endpoints(&self) -> bool {
+        if self.network_manager.is_demo() || self.safe_mode {
+            return false;
         }
+        let interval = self.config.wireguard.reresolve_endpoints_secs;
+        interval > 0 && self.last_endpoint_resolve.elapsed() > Duration::from_secs(interval)
+    }
 
-        // Find the first available WiFi interface
-        let wifi_interface = self
-            .interfaces
-            .iter()
-            .find(|iface| iface.wifi_info.is_some())
-            .map(|iface| iface.name.clone());
-
-        if let Some(interface_name) = wifi_interface {
-            // Get auto-connect profiles sorted by priority (clone to avoid borrowing issues)
-            let auto_connect_profiles: Vec<_> = self
-                .config
-                .get_wifi_profiles_by_priority()
-                .into_iter()
-                .filter(|profile| profile.auto_connect && profile.interface == interface_name)
-                .cloned()
-                .collect();
+    pub fn mark_endpoint_resolve_started(&mut self) {
+        self.last_endpoint_resolve = Instant::now();
+    }
 
-            if !auto_connect_profiles.is_empty() {
-                // Scan for available networks
-                if let Ok(available_networks) = self
-                    .network_manager
-                    .scan_wifi_networks(&interface_name)
-                    .await
-                {
-                    // Try to connect to the highest priority available network
-                    for profile in auto_connect_profiles {
-                        if let Some(_network) = available_networks
-                            .iter()
-                            .find(|net| net.ssid == profile.ssid)
-                        {
-                            // Attempt auto-connect
-                            if let Err(e) = self
-                                .auto_connect_to_profile(&profile, &interface_name)
-                                .await
-                            {
-                                eprintln!("Auto-connect failed for {}: {}", profile.ssid, e);
-                                continue; // Try next profile
-                            } else {
-                                self.status_message = Some((
-                                    format!("Auto-connected to {}", profile.ssid),
-                                    Instant::now(),
-                                ));
-                                break; // Successfully connected
-                            }
-                        }
-                    }
-                }
-            }
+    pub fn should_check_network_policies(&self) -> bool {
+        // The simulation has no real uplink to judge, but the firewall is real
+        if self.network_manager.is_demo() || self.safe_mode {
+            return false;
         }
+        let trusted = &self.config.trusted;
+        let configured = !self.config.wireguard.tunnels.is_empty()
+            || !trusted.ssids.is_empty()
+            || !trusted.gateways.is_empty()
+            || trusted.enforces_firewall()
+            || crate::trust::firewall_installed(); // Still has to be taken down
+        configured
+            && self
+                .last_policy_check
+                .is_none_or(|at| at.elapsed() > Duration::from_secs(30))
+    }
 
-        Ok(())
+    pub fn mark_policy_check_started(&mut self) {
+        self.last_policy_check = Some(Instant::now());
     }
 
-    async fn auto_connect_to_profile(
-        &mut self,
-        profile: &crate::config::WifiProfile,
-        interface_name: &str,
-    ) -> Result<()> {
-        let credentials = crate::network::WifiCredentials {
-            ssid: profile.ssid.clone(),
-            password: profile.password.clone(),
-            security: self.parse_security_type(&profile.security_type),
-            hidden: false, // Auto-connect typically for visible networks
-            enterprise: profile.enterprise.clone(),
+    /// Re-read config.toml, e.g. after editing it in another terminal. A file
+    /// that no longer parses is reported and the current settings are kept.
+    pub fn reload_config(&mut self) {
+        let message = match Config::load() {
+            Ok(config) => {
+                crate::command::configure(&config.timeouts);
+                self.pacer.configure(&config.refresh);
+                // Reconnect with the new broker settings on the next publish
+                if config.mqtt != self.config.mqtt {
+                    self.mqtt = None;
+                    self.mqtt_error = None;
+                    self.last_mqtt_publish = None;
+                }
+                if config.influx != self.config.influx {
+                    self.influx = None;
+                    self.influx_error = None;
+                    self.last_influx_push = None;
+                }
+                if config.snmp != self.config.snmp {
+                    self.snmp = None;
+                    self.last_snmp_refresh = None;
+                }
+                self.config = config;
+                self.last_policy_check = None; // Trusted networks or tunnels may have changed
+                "Configuration reloaded".to_string()
+            }
+            // TOML errors quote the offending line below the first; the footer has room for one
+            Err(e) => format!(
+                "Config not reloaded: {}",
+                e.to_string().lines().next().unwrap_or_default()
+            ),
         };
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
 
-        self.network_manager
-            .connect_to_wifi(
-                interface_name,
-                &credentials,
-                profile.dhcp,
-                profile.ip.clone(),
-                profile.gateway.clone(),
-                profile.dns.clone(),
-            )
-            .await?;
-
-        // Update connection time
-        self.config
-            .update_wifi_connection(&profile.ssid, interface_name);
-        let _ = self.config.save(); // Save updated connection time
+    /// Snapshot what classifying the uplinks and enforcing the VPN policies
+    /// and untrusted-network firewall needs; results come back as
+    /// `AppEvent::Trust`, `AppEvent::VpnPolicyApplied` and
+    /// `AppEvent::FirewallFailed`.
+    pub fn network_policy_job(&self) -> NetworkPolicyJob {
+        NetworkPolicyJob {
+            network_manager: self.network_manager.clone(),
+            systemd_config: self.systemd_config.clone(),
+            interfaces: self.managed_interfaces(),
+            policies: self.config.wireguard.tunnels.clone(),
+            trusted: self.config.trusted.clone(),
+            profiles: self.config.profiles.clone(),
+        }
+    }
 
-        Ok(())
+    // Auto-connect functionality
+    /// Snapshot what an auto-connect attempt needs so it can run in the
+    /// background; the outcome comes back as `AppEvent::AutoConnected`.
+    pub fn wifi_info_job(&self) -> WifiInfoJob {
+        WifiInfoJob {
+            network_manager: self.network_manager.clone(),
+            interfaces: self.managed_interfaces(),
+        }
     }
 
-    fn parse_security_type(&self, security_str: &str) -> crate::network::WifiSecurity {
-        match security_str {
-            "Open" => crate::network::WifiSecurity::Open,
-            "WEP" => crate::network::WifiSecurity::WEP,
-            "WPA" => crate::network::WifiSecurity::WPA,
-            "WPA2" => crate::network::WifiSecurity::WPA2,
-            "WPA3" => crate::network::WifiSecurity::WPA3,
-            "Enterprise" => crate::network::WifiSecurity::Enterprise,
-            _ => crate::network::WifiSecurity::WPA2, // Default fallback
+    pub fn auto_connect_job(&self) -> AutoConnectJob {
+        AutoConnectJob {
+            network_manager: self.network_manager.clone(),
+            interfaces: self.managed_interfaces(),
+            profiles: self
+                .config
+                .get_wifi_profiles_by_priority()
+                .into_iter()
+                .filter(|profile| profile.auto_connect)
+                .cloned()
+                .collect(),
         }
     }
 
@@ -543,26 +2355,98 @@ impl App {
         Ok(())
     }
 
-    pub fn get_selected_interface(&self) -> Option<&Interface> {
-        self.interfaces.get(self.selected_index)
-    }
+    /// Cycle the selected saved network between any band, 2.4 GHz and 5 GHz;
+    /// takes effect on its next connection
+    pub fn cycle_wifi_band_lock(&mut self) {
+        let interface_name = self.get_selected_interface().map(|i| i.name.clone());
+        let network_ssid = self.get_selected_wifi_network().map(|n| n.ssid.clone());
+        let (Some(interface_name), Some(network_ssid)) = (interface_name, network_ssid) else {
+            return;
+        };
+        let Some(profile) = self
+            .config
+            .wifi_profiles
+            .iter_mut()
+            .find(|p| p.ssid == network_ssid && p.interface == interface_name)
+        else {
+            self.status_message = Some((
+                "Network not saved - connect first to lock its band".to_string(),
+                Instant::now(),
+            ));
+            return;
+        };
+        profile.band = profile.band.next();
+        let band = profile.band;
 
+        if let Err(e) = self.config.save() {
+            eprintln!("Warning: Failed to save band lock: {}", e);
+        }
+        let message = if band == WifiBand::Any {
+            format!("{} may use any band from its next connection", network_ssid)
+        } else {
+            format!(
+                "{} locked to {} from its next connection",
+                network_ssid,
+                band.label()
+            )
+        };
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    fn saved_band(&self, ssid: &str, interface: &str) -> WifiBand {
+        self.config
+            .get_wifi_profile(ssid, interface)
+            .map(|profile| profile.band)
+            .unwrap_or_default()
+    }
+
+    fn saved_tx_power(&self, ssid: &str, interface: &str) -> Option<i32> {
+        self.config
+            .get_wifi_profile(ssid, interface)
+            .and_then(|profile| profile.tx_power_dbm)
+    }
+
+    // Routes and search domains live in config.toml only, so a reconnect
+    // from the scan list keeps what was added there
+    fn saved_extras(&self, ssid: &str, interface: &str) -> NetworkExtras {
+        self.config
+            .get_wifi_profile(ssid, interface)
+            .map(WifiProfile::extras)
+            .unwrap_or_default()
+    }
+
+    pub fn get_selected_interface(&self) -> Option<&Interface> {
+        self.interfaces.get(self.selected_index)
+    }
+
+    /// Anything to draw: everything after a key press, otherwise just the
+    /// regions background updates, flashes, the pace or the status touched
     pub fn needs_redraw(&self) -> bool {
         self.needs_redraw
+            || self.redraw.any_dirty()
+            || self.events.needs_redraw()
+            || self.redraw.pace_changed(self.pacer.pace(Instant::now()))
             || self
-                .status_message
-                .as_ref()
-                .map_or(false, |(_, time)| time.elapsed().as_secs() < 3)
+                .redraw
+                .status_changed(self.visible_status().map(|(_, time)| *time))
     }
 
     pub fn mark_redrawn(&mut self) {
         self.needs_redraw = false;
+        self.redraw.clear();
+    }
+
+    /// The status message while the footer still shows it
+    pub fn visible_status(&self) -> Option<&(String, Instant)> {
+        self.status_message
+            .as_ref()
+            .filter(|(_, time)| time.elapsed().as_secs() < STATUS_DURATION_SECS)
     }
 
     // WiFi-specific methods
     pub fn open_wifi_dialog(&mut self) {
         // Show loading dialog immediately for better UX
-        self.show_wifi_loading_dialog = true;
+        self.modals.open(Modal::WifiLoading);
         self.wifi_scan_pending = true;
     }
 
@@ -574,19 +2458,7 @@ impl App {
 
         self.wifi_scan_pending = false;
 
-        // Try to find and use a WiFi interface automatically
-        let wifi_interface = if let Some(interface) = self.get_selected_interface() {
-            // First try the selected interface if it has WiFi capability
-            if interface.wifi_info.is_some() || self.is_likely_wifi_interface(&interface.name) {
-                Some(interface.name.clone())
-            } else {
-                // Find the first WiFi-capable interface
-                self.find_wifi_interface().await
-            }
-        } else {
-            // No interface selected, find any WiFi interface
-            self.find_wifi_interface().await
-        };
+        let wifi_interface = self.resolve_wifi_interface().await;
 
         if let Some(wifi_interface_name) = wifi_interface {
             match self
@@ -595,24 +2467,85 @@ impl App {
             {
                 Ok(_) => {
                     // Hide loading dialog and show results
-                    self.show_wifi_loading_dialog = false;
-                    self.show_wifi_dialog = true;
+                    self.modals.close(Modal::WifiLoading);
+                    self.modals.open(Modal::Wifi);
                 }
-                Err(_) => {
+                Err(e) => {
                     // Scan failed, hide loading dialog
-                    self.show_wifi_loading_dialog = false;
+                    self.modals.close(Modal::WifiLoading);
+                    if crate::command::is_timeout(&e) {
+                        self.status_message =
+                            Some(("WiFi scan timed out".to_string(), Instant::now()));
+                    }
                 }
             }
         } else {
             // No WiFi interface found, hide loading dialog
-            self.show_wifi_loading_dialog = false;
+            self.modals.close(Modal::WifiLoading);
         }
         Ok(())
     }
 
+    /// Open the WiFi dialog straight away with cached results, if recent ones
+    /// exist. Returns the interface to rescan in the background.
+    pub async fn open_cached_wifi_dialog(&mut self) -> Option<String> {
+        let interface_name = self.resolve_wifi_interface().await?;
+        let cached = self.scan_cache.get(&interface_name)?.clone();
+
+        self.wifi_networks = cached.networks;
+        self.mark_saved_networks(&interface_name);
+        self.wifi_cached_since = Some(cached.scanned_at);
+        self.selected_wifi_index = 0;
+        self.wifi_scanning = true;
+        self.modals.open(Modal::Wifi);
+        Some(interface_name)
+    }
+
+    /// Apply results from a background scan, keeping the selected network.
+    pub fn apply_wifi_scan(&mut self, interface_name: &str, networks: Vec<WifiNetwork>) {
+        let selected_ssid = self
+            .get_selected_wifi_network()
+            .map(|network| network.ssid.clone());
+
+        self.wifi_networks = networks;
+        self.mark_saved_networks(interface_name);
+        self.selected_wifi_index = selected_ssid
+            .and_then(|ssid| self.wifi_networks.iter().position(|n| n.ssid == ssid))
+            .unwrap_or(0);
+        self.finish_wifi_scan(interface_name);
+    }
+
+    /// Background scan failed: keep showing the cached list, but say so.
+    pub fn wifi_scan_failed(&mut self, error: &str) {
+        self.wifi_scanning = false;
+        self.status_message = Some((
+            format!("WiFi scan failed, showing cached results: {}", error),
+            Instant::now(),
+        ));
+    }
+
+    // Scan finished: stop the spinner and remember the results for next start
+    fn finish_wifi_scan(&mut self, interface_name: &str) {
+        self.wifi_scanning = false;
+        self.wifi_cached_since = None;
+        self.last_wifi_scan = Instant::now();
+        self.scan_cache.store(interface_name, &self.wifi_networks);
+        let _ = self.scan_cache.save(); // Cache is best-effort
+    }
+
+    fn mark_saved_networks(&mut self, interface_name: &str) {
+        for network in &mut self.wifi_networks {
+            network.in_history = self
+                .config
+                .get_wifi_profile(&network.ssid, interface_name)
+                .is_some();
+        }
+    }
+
     pub fn close_wifi_dialog(&mut self) {
-        self.show_wifi_dialog = false;
-        self.show_wifi_loading_dialog = false;
+        self.modals.close(Modal::Wifi);
+        self.wifi_cached_since = None;
+        self.modals.close(Modal::WifiLoading);
         self.wifi_scan_pending = false;
         self.wifi_networks.clear();
         self.selected_wifi_index = 0;
@@ -639,16 +2572,9 @@ impl App {
             .await?;
 
         // Populate in_history field for performance optimization
-        for network in &mut self.wifi_networks {
-            network.in_history = self
-                .config
-                .get_wifi_profile(&network.ssid, interface_name)
-                .is_some();
-        }
-
-        self.wifi_scanning = false;
-        self.last_wifi_scan = Instant::now();
+        self.mark_saved_networks(interface_name);
         self.selected_wifi_index = 0;
+        self.finish_wifi_scan(interface_name);
         Ok(())
     }
 
@@ -662,6 +2588,19 @@ impl App {
             || interface_name.starts_with("wlo")
     }
 
+    // Prefer the selected interface if it looks like WiFi, otherwise the first WiFi one
+    async fn resolve_wifi_interface(&self) -> Option<String> {
+        match self.get_selected_interface() {
+            Some(interface)
+                if interface.wifi_info.is_some()
+                    || self.is_likely_wifi_interface(&interface.name) =>
+            {
+                Some(interface.name.clone())
+            }
+            _ => self.find_wifi_interface().await,
+        }
+    }
+
     // Helper method to find the first available WiFi interface
     async fn find_wifi_interface(&self) -> Option<String> {
         // First check interfaces with wifi_info
@@ -683,7 +2622,7 @@ impl App {
     }
 
     pub fn should_refresh_wifi_scan(&self) -> bool {
-        self.show_wifi_dialog && self.last_wifi_scan.elapsed() > Duration::from_secs(30)
+        self.modals.is_open(Modal::Wifi) && self.last_wifi_scan.elapsed() > Duration::from_secs(30)
     }
 
     pub fn wifi_navigate_up(&mut self) {
@@ -698,6 +2637,17 @@ impl App {
         }
     }
 
+    /// Expand or collapse the access points of the selected SSID.
+    pub fn set_wifi_group_expanded(&mut self, expanded: bool) {
+        if let Some(ssid) = self.get_selected_wifi_network().map(|n| n.ssid.clone()) {
+            if expanded {
+                self.wifi_expanded.insert(ssid);
+            } else {
+                self.wifi_expanded.remove(&ssid);
+            }
+        }
+    }
+
     pub fn get_selected_wifi_network(&self) -> Option<&WifiNetwork> {
         self.wifi_networks.get(self.selected_wifi_index)
     }
@@ -705,7 +2655,7 @@ impl App {
     pub fn open_wifi_connect_dialog(&mut self) {
         if let Some(network) = self.get_selected_wifi_network().cloned() {
             self.selected_wifi_network = Some(network.clone());
-            self.show_wifi_connect_dialog = true;
+            self.modals.open(Modal::WifiConnect);
 
             // Check if we have a saved profile for this network
             let saved_profile = if let Some(interface) = self.get_selected_interface() {
@@ -740,7 +2690,7 @@ impl App {
     }
 
     pub fn close_wifi_connect_dialog(&mut self) {
-        self.show_wifi_connect_dialog = false;
+        self.modals.close(Modal::WifiConnect);
         self.selected_wifi_network = None;
         self.wifi_password_input = Input::default();
         self.wifi_active_input = 0;
@@ -831,9 +2781,18 @@ impl App {
     }
 
     pub async fn connect_to_selected_wifi(&mut self) -> Result<()> {
+        let selected = self.get_selected_interface().map(|i| i.name.clone());
+        if selected.is_some_and(|name| self.held_by_other_manager(&name)) {
+            return Ok(());
+        }
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+
         if let (Some(interface), Some(network)) =
             (self.get_selected_interface(), &self.selected_wifi_network)
         {
+            let extras = self.saved_extras(&network.ssid, &interface.name);
             let credentials = WifiCredentials {
                 ssid: network.ssid.clone(),
                 password: if self.wifi_password_input.value().is_empty() {
@@ -844,6 +2803,10 @@ impl App {
                 security: network.security.clone(),
                 hidden: self.wifi_hidden_ssid,
                 enterprise: None, // Regular WiFi connection doesn't use Enterprise
+                band: self.saved_band(&network.ssid, &interface.name),
+                tx_power_dbm: self.saved_tx_power(&network.ssid, &interface.name),
+                routes: extras.routes,
+                domains: extras.domains,
             };
 
             let dns_servers = if !self.wifi_use_dhcp && !self.wifi_dns_input.value().is_empty() {
@@ -901,6 +2864,10 @@ impl App {
                 auto_connect: false, // User can enable this later
                 priority: 0,         // Default priority
                 enterprise: None,    // Regular WiFi doesn't use Enterprise credentials
+                band: credentials.band,
+                tx_power_dbm: credentials.tx_power_dbm,
+                routes: credentials.routes.clone(),
+                domains: credentials.domains.clone(),
             };
 
             self.config.add_wifi_profile(wifi_profile);
@@ -923,6 +2890,10 @@ impl App {
     }
 
     pub async fn disconnect_from_wifi(&mut self) -> Result<()> {
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+
         if let Some(interface) = self.get_selected_interface() {
             if interface.wifi_info.is_some()
                 && interface
@@ -949,14 +2920,14 @@ impl App {
     pub fn open_wifi_enterprise_dialog(&mut self) {
         if let Some(network) = self.get_selected_wifi_network().cloned() {
             self.selected_wifi_network = Some(network);
-            self.show_wifi_enterprise_dialog = true;
+            self.modals.open(Modal::WifiEnterprise);
             self.enterprise_active_input = 2; // Start with username field
         }
     }
 
     #[allow(dead_code)]
     pub fn open_wifi_enterprise_dialog_direct(&mut self) {
-        self.show_wifi_enterprise_dialog = true;
+        self.modals.open(Modal::WifiEnterprise);
         self.enterprise_active_input = 2; // Start with username field
 
         // Reset fields
@@ -970,7 +2941,7 @@ impl App {
     }
 
     pub fn close_wifi_enterprise_dialog(&mut self) {
-        self.show_wifi_enterprise_dialog = false;
+        self.modals.close(Modal::WifiEnterprise);
         self.enterprise_active_input = 2; // Reset to username field
     }
 
@@ -1075,6 +3046,14 @@ impl App {
     }
 
     pub async fn connect_to_enterprise_wifi(&mut self) -> Result<()> {
+        let selected = self.get_selected_interface().map(|i| i.name.clone());
+        if selected.is_some_and(|name| self.held_by_other_manager(&name)) {
+            return Ok(());
+        }
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+
         if let (Some(interface), Some(network)) =
             (self.get_selected_interface(), &self.selected_wifi_network)
         {
@@ -1110,12 +3089,17 @@ impl App {
                 phase2_auth: self.enterprise_phase2_auth.clone(),
             };
 
+            let extras = self.saved_extras(&network.ssid, &interface.name);
             let credentials = WifiCredentials {
                 ssid: network.ssid.clone(),
                 password: None, // Not used for Enterprise
                 security: WifiSecurity::Enterprise,
                 hidden: self.wifi_hidden_ssid,
                 enterprise: Some(enterprise_creds.clone()),
+                band: self.saved_band(&network.ssid, &interface.name),
+                tx_power_dbm: self.saved_tx_power(&network.ssid, &interface.name),
+                routes: extras.routes,
+                domains: extras.domains,
             };
 
             let dns_servers = if !self.wifi_use_dhcp && !self.wifi_dns_input.value().is_empty() {
@@ -1173,6 +3157,10 @@ impl App {
                 auto_connect: false, // User can enable this later
                 priority: 0,         // Default priority
                 enterprise: Some(enterprise_creds.clone()),
+                band: credentials.band,
+                tx_power_dbm: credentials.tx_power_dbm,
+                routes: credentials.routes.clone(),
+                domains: credentials.domains.clone(),
             };
 
             self.config.add_wifi_profile(wifi_profile);
@@ -1197,12 +3185,12 @@ impl App {
 
     // Hotspot methods
     pub fn open_hotspot_dialog(&mut self) {
-        self.show_hotspot_dialog = true;
+        self.modals.open(Modal::Hotspot);
         self.hotspot_active_input = 0;
     }
 
     pub fn close_hotspot_dialog(&mut self) {
-        self.show_hotspot_dialog = false;
+        self.modals.close(Modal::Hotspot);
         self.hotspot_active_input = 0;
     }
 
@@ -1213,15 +3201,30 @@ impl App {
     pub fn hotspot_cycle_channel(&mut self) {
         // Cycle through common WiFi channels
         self.hotspot_channel = match self.hotspot_channel {
+            AUTO_CHANNEL => 1,
             1 => 6,
             6 => 11,
             11 => 36,
             36 => 44,
-            44 => 1,
+            44 => AUTO_CHANNEL,
             _ => 6, // Default
         };
     }
 
+    pub fn hotspot_generate_password(&mut self) {
+        match crate::password::generate_passphrase(4) {
+            Ok(passphrase) => {
+                self.hotspot_password_input = Input::default().with_value(passphrase);
+            }
+            Err(e) => {
+                self.status_message = Some((
+                    format!("Failed to generate password: {}", e),
+                    Instant::now(),
+                ));
+            }
+        }
+    }
+
     pub fn hotspot_input_char(&mut self, c: char) {
         let event = crossterm::event::Event::Key(crossterm::event::KeyEvent::new(
             crossterm::event::KeyCode::Char(c),
@@ -1258,72 +3261,3288 @@ impl App {
         }
     }
 
-    pub async fn create_hotspot(&mut self) -> Result<()> {
-        if let Some(interface) = self.get_selected_interface() {
-            // Check if it's a WiFi interface
-            if interface.wifi_info.is_none() {
+    /// Queue the hotspot; the channel survey and setup run as a job
+    pub fn create_hotspot(&mut self) {
+        if self.selected_is_ignored() {
+            return;
+        }
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        if interface.wifi_info.is_none() {
+            self.status_message = Some((
+                "Selected interface is not a WiFi interface".to_string(),
+                Instant::now(),
+            ));
+            return;
+        }
+        let config = crate::network::HotspotConfig {
+            ssid: self.hotspot_ssid_input.value().to_string(),
+            password: self.hotspot_password_input.value().to_string(),
+            interface: interface.name.clone(),
+            channel: self.hotspot_channel,
+            ip_range: "192.168.4.0/24".to_string(),
+            gateway: "192.168.4.1".to_string(),
+            reservations: self.config.hotspot.reservations.clone(),
+            port_forwards: self.config.hotspot.port_forwards.clone(),
+            persistent: false,
+        };
+        let survey = self.hotspot_channel == AUTO_CHANNEL;
+        self.close_hotspot_dialog();
+        self.queue_job(Job::StartHotspot { config, survey });
+    }
+
+    // Share network methods
+    pub fn open_share_dialog(&mut self) {
+        // A running hotspot takes precedence - that's what guests want to join
+        let payload = self
+            .hotspot_qr_payload()
+            .or_else(|| self.connected_network_payload());
+
+        match payload {
+            Some(payload) => {
+                self.share_payload = Some(payload);
+                self.modals.open(Modal::Share);
+            }
+            None => {
                 self.status_message = Some((
-                    "Selected interface is not a WiFi interface".to_string(),
+                    "No connected WiFi network or hotspot to share".to_string(),
                     Instant::now(),
                 ));
-                return Ok(());
             }
+        }
+    }
 
-            let hotspot_config = crate::network::HotspotConfig {
-                ssid: self.hotspot_ssid_input.value().to_string(),
-                password: self.hotspot_password_input.value().to_string(),
-                interface: interface.name.clone(),
-                channel: self.hotspot_channel,
-                ip_range: "192.168.4.0/24".to_string(),
-                gateway: "192.168.4.1".to_string(),
-            };
+    pub fn close_share_dialog(&mut self) {
+        self.modals.close(Modal::Share);
+        self.share_payload = None;
+    }
 
-            match self.network_manager.create_hotspot(&hotspot_config).await {
-                Ok(()) => {
-                    self.status_message = Some((
-                        format!("Hotspot '{}' created successfully", hotspot_config.ssid),
-                        Instant::now(),
-                    ));
-                }
-                Err(e) => {
-                    self.status_message =
-                        Some((format!("Failed to create hotspot: {}", e), Instant::now()));
-                }
+    pub fn open_routing_dialog(&mut self) {
+        self.modals.open(Modal::Routing);
+        self.last_routing_fetch = None;
+        self.needs_redraw = true;
+    }
+
+    pub fn close_routing_dialog(&mut self) {
+        self.modals.close(Modal::Routing);
+        self.routing = None;
+        self.needs_redraw = true;
+    }
+
+    /// The tree follows the interface list, so there is nothing to fetch
+    pub fn open_topology_dialog(&mut self) {
+        self.modals.open(Modal::Topology);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_topology_dialog(&mut self) {
+        self.modals.close(Modal::Topology);
+        self.needs_redraw = true;
+    }
+
+    /// What guests need to join, with a count of who has, once the hotspot is up
+    pub fn open_hotspot_summary(&mut self) {
+        self.modals.open(Modal::HotspotSummary);
+        self.hotspot_clients.clear();
+        self.hotspot_clients_error = None;
+        self.last_hotspot_clients_fetch = None;
+        self.needs_redraw = true;
+    }
+
+    pub fn close_hotspot_summary(&mut self) {
+        self.modals.close(Modal::HotspotSummary);
+        self.hotspot_clients.clear();
+        self.needs_redraw = true;
+    }
+
+    /// Keep the running hotspot across reboots as systemd units, or stop it
+    /// and remove them again
+    pub fn toggle_hotspot_persistence(&mut self) {
+        let Some(config) = self.active_hotspot.clone() else {
+            return;
+        };
+        let persist = !config.persistent;
+        self.queue_job(Job::PersistHotspot { config, persist });
+    }
+
+    /// Share the connection with whatever is at the other end of the
+    /// selected USB gadget link, or stop sharing it
+    pub fn toggle_usb_share(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        if interface.kind != LinkKind::Gadget {
+            self.status_message = Some((
+                format!("{} is not a USB gadget link (usb0)", interface.name),
+                Instant::now(),
+            ));
+            return;
+        }
+        let interface = interface.name.clone();
+        if self.held_by_other_manager(&interface) {
+            return;
+        }
+        let share = !self.usb_shares.contains_key(&interface);
+        self.queue_job(Job::UsbShare {
+            interface,
+            settings: self.config.usb_share.clone(),
+            share,
+        });
+    }
+
+    /// Check on the hotspot units every 30s; what a reboot started shows up
+    /// as the running hotspot. Demo mode has no units.
+    pub fn hotspot_unit_check_due(&self) -> bool {
+        !self.network_manager.is_demo()
+            && !self.safe_mode
+            && self
+                .last_hotspot_unit_check
+                .is_none_or(|at| at.elapsed() > Duration::from_secs(30))
+    }
+
+    pub fn mark_hotspot_unit_check_started(&mut self) {
+        self.last_hotspot_unit_check = Some(Instant::now());
+    }
+
+    fn apply_persistent_hotspot(&mut self, found: Option<(HotspotConfig, String)>) {
+        let Some((config, state)) = found else {
+            // Removed from outside lantern
+            if self.active_hotspot.as_ref().is_some_and(|h| h.persistent) {
+                self.active_hotspot = None;
             }
+            self.hotspot_unit_state = None;
+            return;
+        };
+        if state == "failed" && self.hotspot_unit_state.as_deref() != Some("failed") {
+            self.status_message = Some((
+                format!(
+                    "Hotspot '{}' failed (systemctl status {})",
+                    config.ssid,
+                    crate::hotspot_units::MAIN_UNIT
+                ),
+                Instant::now(),
+            ));
+        }
+        if self.active_hotspot.as_ref().is_none_or(|h| h.persistent) {
+            self.active_hotspot = Some(config);
+        }
+        self.hotspot_unit_state = Some(state);
+    }
 
-            self.close_hotspot_dialog();
-            self.refresh_interfaces().await?;
+    /// The QR code payload that joins the running hotspot
+    pub fn hotspot_qr_payload(&self) -> Option<WifiQrPayload> {
+        let hotspot = self.active_hotspot.as_ref()?;
+        Some(WifiQrPayload {
+            ssid: hotspot.ssid.clone(),
+            password: Some(hotspot.password.clone()),
+            security: WifiSecurity::WPA2,
+            hidden: false,
+        })
+    }
+
+    pub fn open_hotspot_clients_dialog(&mut self) {
+        self.modals.open(Modal::HotspotClients);
+        self.hotspot_clients.clear();
+        self.hotspot_clients_error = None;
+        self.last_hotspot_clients_fetch = None;
+        self.needs_redraw = true;
+    }
+
+    pub fn close_hotspot_clients_dialog(&mut self) {
+        self.modals.close(Modal::HotspotClients);
+        self.hotspot_clients.clear();
+        self.selected_hotspot_client = 0;
+        self.needs_redraw = true;
+    }
+
+    pub fn hotspot_client_navigate(&mut self, down: bool) {
+        let count = self.hotspot_clients.clients.len();
+        if count == 0 {
+            return;
         }
+        self.selected_hotspot_client = if down {
+            (self.selected_hotspot_client + 1) % count
+        } else {
+            (self.selected_hotspot_client + count - 1) % count
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Reserve the address the selected client has now, under its hostname.
+    pub async fn pin_selected_hotspot_client(&mut self) -> Result<()> {
+        let Some(station) = self
+            .hotspot_clients
+            .clients
+            .get(self.selected_hotspot_client)
+            .map(|client| client.station.clone())
+        else {
+            return Ok(());
+        };
+        let Some(ip) = &station.ip else {
+            self.status_message = Some((
+                format!("{} has no DHCP lease to pin yet", station.mac),
+                Instant::now(),
+            ));
+            return Ok(());
+        };
+        // Clients send all sorts of names; pin without one it can't be used
+        let hostname = station.hostname.as_deref().unwrap_or_default();
+        let reservation = DhcpReservation::new(&station.mac, ip, hostname)
+            .or_else(|_| DhcpReservation::new(&station.mac, ip, ""))?;
+        let message = format!("{} pinned to {}", station.mac, reservation.ip);
+        let reservations = &mut self.config.hotspot.reservations;
+        reservations.retain(|r| r.mac != reservation.mac && r.ip != reservation.ip);
+        reservations.push(reservation);
+        self.apply_reservations(message).await;
         Ok(())
     }
 
-    // WiFi Diagnostics methods
-    pub async fn open_wifi_diagnostics_dialog(&mut self) {
-        // Fetch diagnostics data when opening the dialog
-        self.wifi_diagnostics_data = self.get_detailed_wifi_info().await.unwrap_or(None);
-        self.show_wifi_diagnostics_dialog = true;
+    pub fn open_reservations_dialog(&mut self) {
+        self.modals.open(Modal::Reservations);
+        self.selected_reservation = 0;
+        self.needs_redraw = true;
     }
 
-    pub fn close_wifi_diagnostics_dialog(&mut self) {
-        self.show_wifi_diagnostics_dialog = false;
-        self.wifi_diagnostics_data = None;
+    pub fn close_reservations_dialog(&mut self) {
+        self.modals.close(Modal::Reservations);
+        self.needs_redraw = true;
     }
 
-    pub async fn get_detailed_wifi_info(&self) -> Result<Option<DetailedWifiInfo>> {
-        if let Some(interface) = self.get_selected_interface() {
-            if interface.wifi_info.is_some() {
-                return self
-                    .network_manager
-                    .get_detailed_wifi_info(&interface.name)
-                    .await;
+    pub fn reservation_navigate(&mut self, down: bool) {
+        let count = self.config.hotspot.reservations.len();
+        if count == 0 {
+            return;
+        }
+        self.selected_reservation = if down {
+            (self.selected_reservation + 1) % count
+        } else {
+            (self.selected_reservation + count - 1) % count
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Start a new reservation, or edit the selected one with `edit`.
+    pub fn open_reservation_form(&mut self, edit: bool) {
+        let selected = self
+            .config
+            .hotspot
+            .reservations
+            .get(self.selected_reservation)
+            .filter(|_| edit);
+        self.reservation_mac_input =
+            Input::default().with_value(selected.map(|r| r.mac.clone()).unwrap_or_default());
+        self.reservation_ip_input =
+            Input::default().with_value(selected.map(|r| r.ip.clone()).unwrap_or_default());
+        self.reservation_hostname_input = Input::default().with_value(
+            selected
+                .and_then(|r| r.hostname.clone())
+                .unwrap_or_default(),
+        );
+        self.reservation_editing = selected.map(|_| self.selected_reservation);
+        self.reservation_active_input = 0;
+        self.modals.open(Modal::ReservationForm);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_reservation_form(&mut self) {
+        self.modals.close(Modal::ReservationForm);
+        self.needs_redraw = true;
+    }
+
+    pub fn reservation_next_input(&mut self) {
+        self.reservation_active_input = (self.reservation_active_input + 1) % 3;
+        self.needs_redraw = true;
+    }
+
+    pub fn reservation_input(&mut self, key: crossterm::event::KeyEvent) {
+        let event = crossterm::event::Event::Key(key);
+        match self.reservation_active_input {
+            0 => self.reservation_mac_input.handle_event(&event),
+            1 => self.reservation_ip_input.handle_event(&event),
+            _ => self.reservation_hostname_input.handle_event(&event),
+        };
+        self.needs_redraw = true;
+    }
+
+    pub async fn save_reservation_form(&mut self) -> Result<()> {
+        let reservation = match DhcpReservation::new(
+            self.reservation_mac_input.value(),
+            self.reservation_ip_input.value(),
+            self.reservation_hostname_input.value(),
+        ) {
+            Ok(reservation) => reservation,
+            // Keep the form open to fix the typo
+            Err(e) => {
+                self.status_message = Some((e.to_string(), Instant::now()));
+                return Ok(());
+            }
+        };
+        let editing = self.reservation_editing.take();
+        let taken = self
+            .config
+            .hotspot
+            .reservations
+            .iter()
+            .enumerate()
+            .find(|&(i, r)| Some(i) != editing && r.ip == reservation.ip);
+        if let Some((_, other)) = taken {
+            self.reservation_editing = editing;
+            self.status_message = Some((
+                format!("{} is already reserved for {}", other.ip, other.mac),
+                Instant::now(),
+            ));
+            return Ok(());
+        }
+
+        let message = format!("{} reserved for {}", reservation.ip, reservation.mac);
+        let reservations = &mut self.config.hotspot.reservations;
+        match editing {
+            Some(i) if i < reservations.len() => reservations[i] = reservation,
+            _ => {
+                reservations.retain(|r| r.mac != reservation.mac);
+                reservations.push(reservation);
             }
         }
-        Ok(None)
+        self.modals.close(Modal::ReservationForm);
+        self.apply_reservations(message).await;
+        Ok(())
     }
 
-    pub async fn refresh_wifi_diagnostics(&mut self) {
-        if self.show_wifi_diagnostics_dialog {
-            self.wifi_diagnostics_data = self.get_detailed_wifi_info().await.unwrap_or(None);
+    pub async fn remove_selected_reservation(&mut self) -> Result<()> {
+        let reservations = &mut self.config.hotspot.reservations;
+        if self.selected_reservation >= reservations.len() {
+            return Ok(());
+        }
+        let removed = reservations.remove(self.selected_reservation);
+        self.selected_reservation = self
+            .selected_reservation
+            .min(reservations.len().saturating_sub(1));
+        self.apply_reservations(format!("Reservation of {} removed", removed.ip))
+            .await;
+        Ok(())
+    }
+
+    // Save the reservations and hand them to a running hotspot
+    async fn apply_reservations(&mut self, mut message: String) {
+        if let Err(e) = self.config.save() {
+            message = format!("Failed to save reservations: {}", e);
+        }
+        if let Some(hotspot) = self.active_hotspot.as_mut() {
+            hotspot.reservations = self.config.hotspot.reservations.clone();
+            if let Err(e) = self.network_manager.reload_dhcp_reservations(hotspot).await {
+                message = e.to_string();
+            }
+        }
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    pub fn open_port_forward_dialog(&mut self) {
+        self.modals.open(Modal::PortForwards);
+        self.selected_port_forward = 0;
+        self.needs_redraw = true;
+    }
+
+    pub fn close_port_forward_dialog(&mut self) {
+        self.modals.close(Modal::PortForwards);
+        self.needs_redraw = true;
+    }
+
+    pub fn port_forward_navigate(&mut self, down: bool) {
+        let count = self.config.hotspot.port_forwards.len();
+        if count == 0 {
+            return;
+        }
+        self.selected_port_forward = if down {
+            (self.selected_port_forward + 1) % count
+        } else {
+            (self.selected_port_forward + count - 1) % count
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Start a new forward, to the client selected in the clients view, or
+    /// edit the selected one with `edit`.
+    pub fn open_port_forward_form(&mut self, edit: bool) {
+        let selected = self
+            .config
+            .hotspot
+            .port_forwards
+            .get(self.selected_port_forward)
+            .filter(|_| edit)
+            .cloned();
+        let client_ip = self
+            .hotspot_clients
+            .clients
+            .get(self.selected_hotspot_client)
+            .and_then(|client| client.station.ip.clone());
+        self.port_forward_protocol = selected.as_ref().map(|f| f.protocol).unwrap_or_default();
+        self.port_forward_external_input = Input::default().with_value(
+            selected
+                .as_ref()
+                .map(|f| f.external_port.to_string())
+                .unwrap_or_default(),
+        );
+        self.port_forward_ip_input = Input::default().with_value(
+            selected
+                .as_ref()
+                .map(|f| f.internal_ip.clone())
+                .or(client_ip)
+                .unwrap_or_default(),
+        );
+        self.port_forward_internal_input = Input::default().with_value(
+            selected
+                .as_ref()
+                .map(|f| f.internal_port.to_string())
+                .unwrap_or_default(),
+        );
+        self.port_forward_revert_input = Input::default();
+        self.port_forward_editing = selected.map(|_| self.selected_port_forward);
+        self.port_forward_active_input = 1;
+        self.modals.open(Modal::PortForwardForm);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_port_forward_form(&mut self) {
+        self.modals.close(Modal::PortForwardForm);
+        self.needs_redraw = true;
+    }
+
+    pub fn port_forward_next_input(&mut self) {
+        self.port_forward_active_input = (self.port_forward_active_input + 1) % 5;
+        self.needs_redraw = true;
+    }
+
+    pub fn port_forward_input(&mut self, key: crossterm::event::KeyEvent) {
+        let event = crossterm::event::Event::Key(key);
+        match self.port_forward_active_input {
+            0 => {
+                use crossterm::event::KeyCode;
+                if matches!(
+                    key.code,
+                    KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right
+                ) {
+                    self.port_forward_protocol = self.port_forward_protocol.next();
+                }
+            }
+            1 => {
+                self.port_forward_external_input.handle_event(&event);
+            }
+            2 => {
+                self.port_forward_ip_input.handle_event(&event);
+            }
+            3 => {
+                self.port_forward_internal_input.handle_event(&event);
+            }
+            _ => {
+                self.port_forward_revert_input.handle_event(&event);
+            }
+        }
+        self.needs_redraw = true;
+    }
+
+    pub async fn save_port_forward_form(&mut self) -> Result<()> {
+        let forward = match PortForward::new(
+            self.port_forward_protocol,
+            self.port_forward_external_input.value(),
+            self.port_forward_ip_input.value(),
+            self.port_forward_internal_input.value(),
+        ) {
+            Ok(forward) => forward,
+            // Keep the form open to fix the typo
+            Err(e) => {
+                self.status_message = Some((e.to_string(), Instant::now()));
+                return Ok(());
+            }
+        };
+        let minutes = match crate::pending::parse_minutes(self.port_forward_revert_input.value()) {
+            Ok(minutes) => minutes,
+            Err(e) => {
+                self.status_message = Some((e.to_string(), Instant::now()));
+                return Ok(());
+            }
+        };
+        let editing = self.port_forward_editing.take();
+        let overlaps = |protocol: Protocol| {
+            protocol == forward.protocol
+                || protocol == Protocol::Both
+                || forward.protocol == Protocol::Both
+        };
+        let taken = self
+            .config
+            .hotspot
+            .port_forwards
+            .iter()
+            .enumerate()
+            .find(|&(i, f)| {
+                Some(i) != editing
+                    && f.external_port == forward.external_port
+                    && overlaps(f.protocol)
+            });
+        if let Some((_, other)) = taken {
+            self.port_forward_editing = editing;
+            self.status_message = Some((
+                format!(
+                    "Port {} is already forwarded to {}",
+                    other.external_port, other.internal_ip
+                ),
+                Instant::now(),
+            ));
+            return Ok(());
+        }
+
+        let mut message = format!("Forwarding {}", forward.summary());
+        if let Some(minutes) = minutes {
+            match self.schedule_port_forward_revert(minutes).await {
+                Ok(Some(change)) => {
+                    message = format!(
+                        "{}; reverts in {} unless kept (K)",
+                        message,
+                        crate::pending::format_remaining(change.remaining(SystemTime::now()))
+                    );
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.port_forward_editing = editing;
+                    self.status_message = Some((e.to_string(), Instant::now()));
+                    return Ok(());
+                }
+            }
+        }
+        let forwards = &mut self.config.hotspot.port_forwards;
+        match editing {
+            Some(i) if i < forwards.len() => forwards[i] = forward,
+            _ => forwards.push(forward),
+        }
+        self.modals.close(Modal::PortForwardForm);
+        self.apply_port_forwards(message).await;
+        Ok(())
+    }
+
+    pub async fn remove_selected_port_forward(&mut self) -> Result<()> {
+        let forwards = &mut self.config.hotspot.port_forwards;
+        if self.selected_port_forward >= forwards.len() {
+            return Ok(());
+        }
+        let removed = forwards.remove(self.selected_port_forward);
+        self.selected_port_forward = self
+            .selected_port_forward
+            .min(forwards.len().saturating_sub(1));
+        self.apply_port_forwards(format!(
+            "Port {} no longer forwarded",
+            removed.external_port
+        ))
+        .await;
+        Ok(())
+    }
+
+    // Go back to the forwards as they are now in `minutes`; not in demo mode
+    async fn schedule_port_forward_revert(
+        &mut self,
+        minutes: u64,
+    ) -> Result<Option<PendingChange>> {
+        if self.network_manager.is_demo() {
+            return Ok(None);
+        }
+        let revert = Revert::PortForwards {
+            previous: self.config.hotspot.port_forwards.clone(),
+            gateway: self
+                .active_hotspot
+                .as_ref()
+                .map(|hotspot| hotspot.gateway.clone())
+                .unwrap_or_else(|| "192.168.4.1".to_string()),
+        };
+        let change =
+            crate::pending::schedule("hotspot port forwards".to_string(), minutes, revert).await?;
+        self.last_pending_check = None;
+        Ok(Some(change))
+    }
+
+    // Save the forwards and load them for a running hotspot
+    async fn apply_port_forwards(&mut self, mut message: String) {
+        if let Err(e) = self.config.save() {
+            message = format!("Failed to save port forwards: {}", e);
+        }
+        if let Some(hotspot) = self.active_hotspot.as_mut() {
+            hotspot.port_forwards = self.config.hotspot.port_forwards.clone();
+            if let Err(e) = self.network_manager.reload_port_forwards(hotspot).await {
+                message = e.to_string();
+            }
+        }
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    pub fn open_pxe_dialog(&mut self) {
+        if self.pxe_server.is_none() {
+            let settings = &self.config.pxe;
+            self.pxe_mode = settings.mode;
+            self.pxe_root_input = Input::default().with_value(settings.tftp_root.clone());
+            self.pxe_bios_input = Input::default().with_value(settings.bios_boot_file.clone());
+            self.pxe_efi_input = Input::default().with_value(settings.efi_boot_file.clone());
+            self.pxe_active_input = 1;
+        }
+        self.modals.open(Modal::Pxe);
+        self.needs_redraw = true;
+    }
+
+    /// The server keeps running with the dialog closed
+    pub fn close_pxe_dialog(&mut self) {
+        self.modals.close(Modal::Pxe);
+        self.needs_redraw = true;
+    }
+
+    pub fn pxe_next_input(&mut self) {
+        self.pxe_active_input = (self.pxe_active_input + 1) % 4;
+        self.needs_redraw = true;
+    }
+
+    pub fn pxe_input(&mut self, key: crossterm::event::KeyEvent) {
+        let event = crossterm::event::Event::Key(key);
+        match self.pxe_active_input {
+            0 => {
+                use crossterm::event::KeyCode;
+                if matches!(
+                    key.code,
+                    KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right
+                ) {
+                    self.pxe_mode = self.pxe_mode.toggled();
+                }
+            }
+            1 => {
+                self.pxe_root_input.handle_event(&event);
+            }
+            2 => {
+                self.pxe_bios_input.handle_event(&event);
+            }
+            _ => {
+                self.pxe_efi_input.handle_event(&event);
+            }
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Serve the boot files from the selected interface, remembering the
+    /// settings for next time once it's up
+    pub async fn start_pxe(&mut self) -> Result<()> {
+        if self.pxe_server.is_some() {
+            return Ok(());
+        }
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+        let Some(interface) = self.get_selected_interface().cloned() else {
+            return Ok(());
+        };
+
+        let settings = PxeSettings {
+            mode: self.pxe_mode,
+            tftp_root: self.pxe_root_input.value().trim().to_string(),
+            bios_boot_file: self.pxe_bios_input.value().trim().to_string(),
+            efi_boot_file: self.pxe_efi_input.value().trim().to_string(),
+            address: self.config.pxe.address.clone(),
+        };
+        let started = match PxeConfig::new(&interface.name, &interface.ipv4_addresses, &settings) {
+            Ok(config) => self.network_manager.start_pxe(config).await,
+            Err(e) => Err(e),
+        };
+        let message = match started {
+            Ok(server) => {
+                let mut message = format!(
+                    "PXE boot server running on {} ({})",
+                    interface.name, server.config.network
+                );
+                self.pxe_logged = server.logged();
+                self.pxe_server = Some(server);
+                if settings != self.config.pxe {
+                    self.config.pxe = settings;
+                    if let Err(e) = self.config.save() {
+                        message = format!("{}; failed to save its settings: {}", message, e);
+                    }
+                }
+                message
+            }
+            Err(e) => format!("Failed to start the PXE boot server: {:#}", e),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    pub async fn stop_pxe(&mut self) {
+        let Some(server) = self.pxe_server.take() else {
+            return;
+        };
+        let interface = server.config.interface.clone();
+        server.stop().await;
+        self.status_message = Some((
+            format!("PXE boot server on {} stopped", interface),
+            Instant::now(),
+        ));
+        self.needs_redraw = true;
+    }
+
+    /// Show what dnsmasq logged since the last frame, and notice when it died
+    pub async fn poll_pxe(&mut self) {
+        let Some(server) = self.pxe_server.as_mut() else {
+            return;
+        };
+        if let Some(reason) = server.exited() {
+            if let Some(server) = self.pxe_server.take() {
+                server.stop().await;
+            }
+            self.status_message = Some((
+                format!("PXE boot server stopped: {}", reason),
+                Instant::now(),
+            ));
+            self.needs_redraw = true;
+            return;
+        }
+        let logged = server.logged();
+        if logged != self.pxe_logged {
+            self.pxe_logged = logged;
+            if self.modals.is_open(Modal::Pxe) {
+                self.redraw.mark(&[Region::Overlay]);
+            }
+        }
+    }
+
+    pub fn open_global_dns_dialog(&mut self) {
+        let (effective, ours) = crate::resolved::load();
+        self.global_dns_effective = effective;
+        self.global_dns_input = Input::default().with_value(ours.dns.join(" "));
+        self.global_fallback_dns_input = Input::default().with_value(ours.fallback_dns.join(" "));
+        self.global_domains_input = Input::default().with_value(ours.domains.join(" "));
+        self.global_dns_over_tls = ours.dns_over_tls;
+        self.global_llmnr = ours.llmnr;
+        self.global_multicast_dns = ours.multicast_dns;
+        self.global_dns_active_input = 0;
+        self.modals.open(Modal::GlobalDns);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_global_dns_dialog(&mut self) {
+        self.modals.close(Modal::GlobalDns);
+        self.needs_redraw = true;
+    }
+
+    pub fn global_dns_next_input(&mut self) {
+        self.global_dns_active_input = (self.global_dns_active_input + 1) % 6;
+        self.needs_redraw = true;
+    }
+
+    pub fn global_dns_input(&mut self, key: crossterm::event::KeyEvent) {
+        use crate::resolved::{cycle, RESPONDER_MODES, TLS_MODES};
+        use crossterm::event::KeyCode;
+        let event = crossterm::event::Event::Key(key);
+        let toggle = matches!(
+            key.code,
+            KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right
+        );
+        match self.global_dns_active_input {
+            0 => {
+                self.global_dns_input.handle_event(&event);
+            }
+            1 => {
+                self.global_fallback_dns_input.handle_event(&event);
+            }
+            2 => {
+                self.global_domains_input.handle_event(&event);
+            }
+            3 if toggle => self.global_dns_over_tls = cycle(&self.global_dns_over_tls, &TLS_MODES),
+            4 if toggle => self.global_llmnr = cycle(&self.global_llmnr, &RESPONDER_MODES),
+            5 if toggle => {
+                self.global_multicast_dns = cycle(&self.global_multicast_dns, &RESPONDER_MODES)
+            }
+            _ => {}
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Write the global DNS drop-in and restart resolved to apply it
+    pub async fn save_global_dns(&mut self) -> Result<()> {
+        // Servers and domains may be separated by spaces or commas
+        let list = |input: &Input| -> Vec<String> {
+            input
+                .value()
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let settings = GlobalDns {
+            dns: list(&self.global_dns_input),
+            fallback_dns: list(&self.global_fallback_dns_input),
+            domains: list(&self.global_domains_input),
+            dns_over_tls: self.global_dns_over_tls.clone(),
+            llmnr: self.global_llmnr.clone(),
+            multicast_dns: self.global_multicast_dns.clone(),
+        };
+        if let Err(e) = settings.validate() {
+            self.status_message = Some((e.to_string(), Instant::now()));
+            return Ok(());
+        }
+        if self.network_manager.is_demo() {
+            self.status_message = Some((
+                "Demo mode: global DNS settings not written".to_string(),
+                Instant::now(),
+            ));
+            self.close_global_dns_dialog();
+            return Ok(());
+        }
+        let Some(_lock) = self.begin_change() else {
+            return Ok(());
+        };
+
+        let message = match crate::resolved::save(&settings).await {
+            Ok(true) => "Global DNS settings saved; systemd-resolved restarted",
+            Ok(false) => "Global DNS settings saved; systemd-resolved isn't running to apply them",
+            Err(e) => {
+                // Left open to try again
+                let message = format!("Failed to save global DNS settings: {:#}", e);
+                self.status_message = Some((message, Instant::now()));
+                return Ok(());
+            }
+        };
+        self.close_global_dns_dialog();
+        // The DNS servers interfaces show may have changed with them
+        self.refresh_interfaces().await?;
+        self.status_message = Some((message.to_string(), Instant::now()));
+        Ok(())
+    }
+
+    /// Re-read the temporary changes once a second for the countdown. One
+    /// that went away without being kept here was kept or reverted from the
+    /// shell, or by its timer, and what a revert put back has to be read again.
+    pub fn poll_pending(&mut self) {
+        if self.network_manager.is_demo()
+            || self
+                .last_pending_check
+                .is_some_and(|at| at.elapsed() < Duration::from_secs(1))
+        {
+            return;
+        }
+        self.last_pending_check = Some(Instant::now());
+        let pending = crate::pending::list();
+        let gone: Vec<PendingChange> = self
+            .pending
+            .iter()
+            .filter(|change| !pending.iter().any(|other| other.id == change.id))
+            .cloned()
+            .collect();
+        if !pending.is_empty() || pending != self.pending {
+            self.redraw.mark(&[Region::Header]);
+        }
+        self.pending = pending;
+        if gone.is_empty() {
+            return;
+        }
+
+        self.reload_config();
+        if let Some(hotspot) = self.active_hotspot.as_mut() {
+            hotspot.port_forwards = self.config.hotspot.port_forwards.clone();
+        }
+        self.interface_refresh_requested = true;
+        let now = SystemTime::now();
+        let expired: Vec<&str> = gone
+            .iter()
+            .filter(|change| change.remaining(now).is_zero())
+            .map(|change| change.description.as_str())
+            .collect();
+        if !expired.is_empty() {
+            self.status_message =
+                Some((format!("Reverted: {}", expired.join(", ")), Instant::now()));
+        }
+    }
+
+    /// Keep every temporary change as it is now
+    pub async fn keep_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let mut kept = 0;
+        let mut message = None;
+        for change in std::mem::take(&mut self.pending) {
+            match crate::pending::confirm(&change.id).await {
+                Ok(_) => kept += 1,
+                Err(e) => message = Some(e.to_string()),
+            }
+        }
+        self.last_pending_check = None;
+        let message = message.unwrap_or_else(|| format!("Kept {} temporary change(s)", kept));
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    pub fn open_palette(&mut self) {
+        self.palette_input = Input::default();
+        self.palette_selected = 0;
+        self.modals.open(Modal::Palette);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_palette(&mut self) {
+        self.modals.close(Modal::Palette);
+        self.needs_redraw = true;
+    }
+
+    pub fn palette_navigate(&mut self, down: bool) {
+        let count = crate::palette::matches(self.palette_input.value()).len();
+        if count == 0 {
+            return;
+        }
+        self.palette_selected = if down {
+            (self.palette_selected + 1) % count
+        } else {
+            (self.palette_selected + count - 1) % count
+        };
+        self.needs_redraw = true;
+    }
+
+    pub fn palette_input(&mut self, key: crossterm::event::KeyEvent) {
+        let changed = self
+            .palette_input
+            .handle_event(&crossterm::event::Event::Key(key))
+            .is_some_and(|change| change.value);
+        if changed {
+            self.palette_selected = 0; // The best match again
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Run the selected entry: a key is queued for the main loop, the
+    /// palette-only actions run here.
+    pub async fn run_palette_entry(&mut self) {
+        let matches = crate::palette::matches(self.palette_input.value());
+        let Some(entry) = matches.get(self.palette_selected) else {
+            return;
+        };
+        self.close_palette();
+        match entry.action {
+            PaletteAction::Key(code) => {
+                self.queued_key = Some(crossterm::event::KeyEvent::from(code));
+            }
+            PaletteAction::RestartNetworkd => self.restart_networkd().await,
+            PaletteAction::ExportReport => self.export_report(),
+            PaletteAction::ScreenshotAnsi => {
+                self.request_screenshot(crate::screenshot::Format::Ansi)
+            }
+        }
+    }
+
+    pub async fn restart_networkd(&mut self) {
+        let Some(_lock) = self.begin_change() else {
+            return;
+        };
+        let message = match self.network_manager.restart_networkd().await {
+            Ok(()) => "systemd-networkd restarted".to_string(),
+            Err(e) => e.to_string(),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    /// Save what the interface list shows as JSON in the working directory
+    pub fn export_report(&mut self) {
+        let now = chrono::Local::now();
+        let path = format!("lantern-report-{}.json", now.format("%Y%m%d-%H%M%S"));
+        let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname")
+            .map(|name| name.trim().to_string())
+            .unwrap_or_default();
+        let report = serde_json::json!({
+            "generated": now.to_rfc3339(),
+            "hostname": hostname,
+            "interfaces": self.interfaces,
+        });
+        let written = serde_json::to_string_pretty(&report)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(&path, json + "\n")?));
+        let message = match written {
+            Ok(()) => {
+                let message = format!("Report saved to {}", path);
+                self.last_report_path = std::fs::canonicalize(&path)
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .ok()
+                    .or(Some(path));
+                message
+            }
+            Err(e) => format!("Failed to save {}: {}", path, e),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    /// Save the screen once the next frame is drawn, so dialogs just closed
+    /// (the palette) are gone from it
+    pub fn request_screenshot(&mut self, format: crate::screenshot::Format) {
+        self.screenshot = Some(format);
+        self.needs_redraw = true;
+    }
+
+    pub fn screenshot_due(&mut self) -> Option<crate::screenshot::Format> {
+        self.screenshot.take()
+    }
+
+    pub fn screenshot_saved(&mut self, result: Result<String>) {
+        let message = match result {
+            Ok(path) => format!("Screen saved to {}", path),
+            Err(e) => format!("Failed to save the screen: {}", e),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    /// Dump the hotspot's stations every 2s while the clients view or the
+    /// summary is open; the interface to ask when one is due.
+    pub fn hotspot_clients_due(&self) -> Option<String> {
+        let hotspot = self.active_hotspot.as_ref()?;
+        let due = (self.modals.is_open(Modal::HotspotClients)
+            || self.modals.is_open(Modal::HotspotSummary))
+            && self
+                .last_hotspot_clients_fetch
+                .is_none_or(|at| at.elapsed() > Duration::from_secs(2));
+        due.then(|| hotspot.interface.clone())
+    }
+
+    pub fn mark_hotspot_clients_fetch_started(&mut self) {
+        self.last_hotspot_clients_fetch = Some(Instant::now());
+    }
+
+    pub fn open_networkd_files_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        self.networkd_files_interface = interface.name.clone();
+        self.modals.open(Modal::NetworkdFiles);
+        self.networkd_files = None;
+        self.networkd_files_error = None;
+        self.selected_networkd_file = 0;
+        self.networkd_files_stale = true;
+        self.needs_redraw = true;
+    }
+
+    pub fn close_networkd_files_dialog(&mut self) {
+        self.modals.close(Modal::NetworkdFiles);
+        self.networkd_files = None;
+        self.needs_redraw = true;
+    }
+
+    /// The interface to ask networkd about, when the dialog opens and on `r`
+    /// or after an edit.
+    pub fn networkd_files_due(&self) -> Option<String> {
+        (self.modals.is_open(Modal::NetworkdFiles) && self.networkd_files_stale)
+            .then(|| self.networkd_files_interface.clone())
+    }
+
+    pub fn mark_networkd_files_fetch_started(&mut self) {
+        self.networkd_files_stale = false;
+    }
+
+    pub fn refresh_networkd_files(&mut self) {
+        self.networkd_files_stale = true;
+    }
+
+    pub fn networkd_file_navigate(&mut self, down: bool) {
+        let count = self.networkd_files.as_ref().map_or(0, |m| m.files.len());
+        if count == 0 {
+            return;
+        }
+        self.selected_networkd_file = if down {
+            (self.selected_networkd_file + 1) % count
+        } else {
+            (self.selected_networkd_file + count - 1) % count
+        };
+        self.needs_redraw = true;
+    }
+
+    /// The selected file, if it is one that can be edited in place: package
+    /// defaults under /usr/lib are overridden by a copy in /etc instead, and
+    /// .netdev files need no [Match] section the editor's check insists on.
+    pub fn editable_networkd_file(&mut self) -> Option<std::path::PathBuf> {
+        let file = self
+            .networkd_files
+            .as_ref()?
+            .files
+            .get(self.selected_networkd_file)?;
+        let refusal = if file.kind == crate::networkd::FileKind::NetDev {
+            Some(format!(
+                "{} can be viewed here, not edited",
+                file.path.display()
+            ))
+        } else if !file.path.starts_with("/etc/systemd/network") {
+            Some(format!(
+                "{} is a default; copy it to /etc/systemd/network to override it",
+                file.path.display()
+            ))
+        } else {
+            None
+        };
+        match refusal {
+            Some(message) => {
+                self.status_message = Some((message, Instant::now()));
+                self.needs_redraw = true;
+                None
+            }
+            None => Some(file.path.clone()),
+        }
+    }
+
+    /// Edit one of lantern's own .network or .netdev files as text, within
+    /// the TUI; other files go to $EDITOR with E
+    pub fn open_raw_edit(&mut self) {
+        let Some(file) = self
+            .networkd_files
+            .as_ref()
+            .and_then(|matched| matched.files.get(self.selected_networkd_file))
+        else {
+            return;
+        };
+        let refusal = if !file.lantern || file.kind == crate::networkd::FileKind::Link {
+            Some(format!(
+                "Only lantern's .network and .netdev files are edited here; E opens {} in $EDITOR",
+                file.path.display()
+            ))
+        } else if file.contents.is_none() {
+            Some(format!("Cannot read {}", file.path.display()))
+        } else {
+            None
+        };
+        if let Some(message) = refusal {
+            self.status_message = Some((message, Instant::now()));
+            return;
+        }
+        self.raw_editor = Some(RawEditor::new(
+            &self.networkd_files_interface,
+            file.path.clone(),
+            file.kind,
+            file.contents.as_deref().unwrap_or_default(),
+        ));
+        self.modals.open(Modal::RawEdit);
+        self.needs_redraw = true;
+    }
+
+    /// Esc: closes straight away unless that would lose changes
+    pub fn close_raw_edit(&mut self) {
+        if let Some(editor) = self.raw_editor.as_mut() {
+            if editor.modified() && !editor.confirm_discard {
+                editor.confirm_discard = true;
+                self.status_message = Some((
+                    "Unsaved changes: Esc again to discard them, Ctrl-S to save".to_string(),
+                    Instant::now(),
+                ));
+                return;
+            }
+        }
+        self.modals.close(Modal::RawEdit);
+        self.raw_editor = None;
+        self.needs_redraw = true;
+    }
+
+    pub fn raw_edit_input(&mut self, key: crossterm::event::KeyEvent) {
+        if let Some(editor) = self.raw_editor.as_mut() {
+            editor.key(key);
+        }
+    }
+
+    /// Ctrl-S: write the file once it has no errors (unknown keys are only
+    /// warned about) and have networkd apply it
+    pub async fn save_raw_edit(&mut self) -> Result<()> {
+        let Some(editor) = self.raw_editor.clone() else {
+            return Ok(());
+        };
+        if !editor.modified() {
+            self.status_message = Some(("No changes".to_string(), Instant::now()));
+            self.finish_raw_edit();
+            return Ok(());
+        }
+        if let Some(issue) = editor.first_error() {
+            let message = format!("Line {}: {}", issue.line + 1, issue.message);
+            self.status_message = Some((message, Instant::now()));
+            return Ok(());
+        }
+        if self.network_manager.is_demo() {
+            self.status_message = Some((
+                "Demo mode: the file was not written".to_string(),
+                Instant::now(),
+            ));
+            self.finish_raw_edit();
+            return Ok(());
+        }
+        let Some(_lock) = self.begin_change() else {
+            return Ok(());
+        };
+
+        let (path, interface) = (&editor.path, &editor.interface);
+        let contents = crate::provenance::stamp(&editor.contents(), "Edited as text in lantern");
+        let written = if editor.kind == crate::networkd::FileKind::NetDev {
+            // WireGuard keys: readable by networkd, nobody else
+            crate::secure_file::write_private_for_group(&path, &contents, "systemd-network")
+        } else {
+            crate::secure_file::write_public(&path, &contents)
+        };
+        if let Err(e) = written {
+            // Left open to try again
+            let message = format!("Failed to write {}: {:#}", path.display(), e);
+            self.status_message = Some((message, Instant::now()));
+            return Ok(());
+        }
+        let message = if editor.kind == crate::networkd::FileKind::NetDev {
+            match self.systemd_config.reload().await {
+                Ok(()) => format!(
+                    "{} saved; networkd reloaded, though some netdev settings wait until {} is recreated",
+                    path.display(),
+                    interface
+                ),
+                Err(e) => format!("{} saved, but reloading failed: {}", path.display(), e),
+            }
+        } else {
+            match self.systemd_config.apply_edited_network(interface).await {
+                Ok(()) => format!("{} saved and applied", path.display()),
+                Err(e) => format!("{} saved, but reloading failed: {}", path.display(), e),
+            }
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.finish_raw_edit();
+        Ok(())
+    }
+
+    fn finish_raw_edit(&mut self) {
+        self.modals.close(Modal::RawEdit);
+        self.raw_editor = None;
+        self.refresh_networkd_files();
+        self.needs_redraw = true;
+    }
+
+    pub fn open_doctor(&mut self) {
+        self.modals.open(Modal::Doctor);
+        self.selected_finding = 0;
+        self.run_doctor();
+    }
+
+    pub fn close_doctor(&mut self) {
+        self.modals.close(Modal::Doctor);
+        self.doctor = None;
+        self.needs_redraw = true;
+    }
+
+    /// Reading a handful of small files; quick enough to do in place
+    pub fn run_doctor(&mut self) {
+        let result = self
+            .network_manager
+            .lint_networkd_files()
+            .map_err(|e| format!("{:#}", e));
+        let count = result.as_ref().map_or(0, Vec::len);
+        self.selected_finding = self.selected_finding.min(count.saturating_sub(1));
+        self.doctor = Some(result);
+        self.needs_redraw = true;
+    }
+
+    pub fn doctor_navigate(&mut self, down: bool) {
+        let count = match &self.doctor {
+            Some(Ok(findings)) => findings.len(),
+            _ => 0,
+        };
+        if count == 0 {
+            return;
+        }
+        self.selected_finding = if down {
+            (self.selected_finding + 1) % count
+        } else {
+            (self.selected_finding + count - 1) % count
+        };
+        self.needs_redraw = true;
+    }
+
+    /// The file the selected finding is about, to open in the editor
+    pub fn selected_finding_file(&self) -> Option<std::path::PathBuf> {
+        match &self.doctor {
+            Some(Ok(findings)) => findings
+                .get(self.selected_finding)
+                .map(|finding| finding.path.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn open_neighbor_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        self.neighbor_interface = interface.name.clone();
+        self.modals.open(Modal::Neighbors);
+        self.static_neighbors = None;
+        self.static_neighbors_error = None;
+        self.selected_neighbor = 0;
+        self.neighbors_stale = true;
+        self.needs_redraw = true;
+    }
+
+    pub fn close_neighbor_dialog(&mut self) {
+        self.modals.close(Modal::Neighbors);
+        self.static_neighbors = None;
+        self.needs_redraw = true;
+    }
+
+    /// The interface to list entries for, once when the editor opens and
+    /// again after each change.
+    pub fn neighbors_due(&self) -> Option<String> {
+        (self.modals.is_open(Modal::Neighbors) && self.neighbors_stale)
+            .then(|| self.neighbor_interface.clone())
+    }
+
+    pub fn mark_neighbors_fetch_started(&mut self) {
+        self.neighbors_stale = false;
+    }
+
+    /// List what can be copied from the selected interface: its addresses,
+    /// MAC, gateway, WireGuard key and the last exported report
+    pub fn open_copy_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        let strip = |address: &String| address.split('/').next().unwrap_or(address).to_string();
+        let mut choices = Vec::new();
+        for address in &interface.ipv4_addresses {
+            choices.push(("IPv4".to_string(), strip(address)));
+        }
+        for address in &interface.ipv6_addresses {
+            choices.push(("IPv6".to_string(), strip(address)));
+        }
+        if !interface.mac_address.is_empty() {
+            choices.push(("MAC".to_string(), interface.mac_address.clone()));
+        }
+        for gateway in interface.gateway.iter().chain(&interface.ipv6_gateway) {
+            choices.push(("Gateway".to_string(), gateway.clone()));
+        }
+        if let Some(status) = self
+            .wireguard
+            .value
+            .as_ref()
+            .filter(|status| status.interface == interface.name)
+        {
+            choices.push(("Public key".to_string(), status.public_key.clone()));
+        }
+        if let Some(path) = &self.last_report_path {
+            choices.push(("Report".to_string(), path.clone()));
+        }
+        if choices.is_empty() {
+            self.status_message = Some((
+                format!("Nothing to copy from {}", interface.name),
+                Instant::now(),
+            ));
+        } else {
+            self.copy_choices = choices;
+            self.selected_copy = 0;
+            self.modals.open(Modal::Copy);
+        }
+        self.needs_redraw = true;
+    }
+
+    pub fn close_copy_dialog(&mut self) {
+        self.modals.close(Modal::Copy);
+        self.needs_redraw = true;
+    }
+
+    pub fn copy_navigate(&mut self, down: bool) {
+        let count = self.copy_choices.len();
+        if count == 0 {
+            return;
+        }
+        self.selected_copy = if down {
+            (self.selected_copy + 1) % count
+        } else {
+            (self.selected_copy + count - 1) % count
+        };
+        self.needs_redraw = true;
+    }
+
+    pub fn copy_selected(&mut self) {
+        self.modals.close(Modal::Copy);
+        self.needs_redraw = true;
+        let Some((label, value)) = self.copy_choices.get(self.selected_copy) else {
+            return;
+        };
+        let message = match crate::clipboard::copy(value) {
+            Ok(via) => format!("Copied {} {} via {}", label, value, via),
+            Err(e) => e.to_string(),
+        };
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    pub fn neighbor_navigate(&mut self, down: bool) {
+        let count = self.static_neighbors.as_ref().map_or(0, Vec::len);
+        if count == 0 {
+            return;
+        }
+        self.selected_neighbor = if down {
+            (self.selected_neighbor + 1) % count
+        } else {
+            (self.selected_neighbor + count - 1) % count
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Start a new entry, or edit the selected one with `edit`.
+    pub fn open_neighbor_form(&mut self, edit: bool) {
+        let selected = self
+            .static_neighbors
+            .as_ref()
+            .and_then(|neighbors| neighbors.get(self.selected_neighbor))
+            .filter(|_| edit);
+        let (address, mac) = selected
+            .map(|n| (n.address.clone(), n.mac.clone()))
+            .unwrap_or_default();
+        self.neighbor_editing = selected.map(|n| n.address.clone());
+        self.neighbor_address_input = Input::default().with_value(address);
+        self.neighbor_mac_input = Input::default().with_value(mac);
+        self.neighbor_active_input = 0;
+        self.modals.open(Modal::NeighborForm);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_neighbor_form(&mut self) {
+        self.modals.close(Modal::NeighborForm);
+        self.needs_redraw = true;
+    }
+
+    pub fn neighbor_next_input(&mut self) {
+        self.neighbor_active_input = (self.neighbor_active_input + 1) % 2;
+        self.needs_redraw = true;
+    }
+
+    pub fn neighbor_input(&mut self, key: crossterm::event::KeyEvent) {
+        let event = crossterm::event::Event::Key(key);
+        match self.neighbor_active_input {
+            0 => self.neighbor_address_input.handle_event(&event),
+            _ => self.neighbor_mac_input.handle_event(&event),
+        };
+        self.needs_redraw = true;
+    }
+
+    pub async fn save_neighbor_form(&mut self) -> Result<()> {
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+        let interface = self.neighbor_interface.clone();
+        let address = self.neighbor_address_input.value().trim().to_string();
+        let result = self
+            .network_manager
+            .add_static_neighbor(&interface, &address, self.neighbor_mac_input.value())
+            .await;
+        let mut message = match result {
+            Ok(true) => format!("{} pinned on {}", address, interface),
+            Ok(false) => format!(
+                "{} pinned on {} until reboot: systemd-networkd doesn't manage it",
+                address, interface
+            ),
+            // Keep the form open to fix the typo
+            Err(e) => {
+                self.status_message = Some((e.to_string(), Instant::now()));
+                return Ok(());
+            }
+        };
+        // An edit that changed the address replaces the old entry
+        if let Some(old) = self.neighbor_editing.take().filter(|old| *old != address) {
+            if let Err(e) = self
+                .network_manager
+                .remove_static_neighbor(&interface, &old)
+                .await
+            {
+                message = e.to_string();
+            }
+        }
+        self.modals.close(Modal::NeighborForm);
+        self.neighbors_stale = true;
+        self.status_message = Some((message, Instant::now()));
+        Ok(())
+    }
+
+    pub async fn remove_selected_neighbor(&mut self) -> Result<()> {
+        let Some(address) = self
+            .static_neighbors
+            .as_ref()
+            .and_then(|neighbors| neighbors.get(self.selected_neighbor))
+            .map(|neighbor| neighbor.address.clone())
+        else {
+            return Ok(());
+        };
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+        let interface = self.neighbor_interface.clone();
+        let message = match self
+            .network_manager
+            .remove_static_neighbor(&interface, &address)
+            .await
+        {
+            Ok(()) => format!("{} unpinned from {}", address, interface),
+            Err(e) => e.to_string(),
+        };
+        self.neighbors_stale = true;
+        self.status_message = Some((message, Instant::now()));
+        Ok(())
+    }
+
+    pub fn open_dns_leak_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        self.dns_leak_interface = interface.name.clone();
+        self.dns_leak = None;
+        self.modals.open(Modal::DnsLeak);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_dns_leak_dialog(&mut self) {
+        self.modals.close(Modal::DnsLeak);
+        self.dns_leak = None;
+        self.needs_redraw = true;
+    }
+
+    /// Throw the result away so the test runs again
+    pub fn rerun_dns_leak_test(&mut self) {
+        if !self.dns_leak_running {
+            self.dns_leak = None;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// The interface to test and those that are up, when the dialog waits
+    /// for a result and no test is running
+    pub fn dns_leak_due(&self) -> Option<(String, Vec<String>)> {
+        let due = self.modals.is_open(Modal::DnsLeak)
+            && self.dns_leak.is_none()
+            && !self.dns_leak_running;
+        due.then(|| {
+            let up = self
+                .interfaces
+                .iter()
+                .filter(|interface| interface.state != "DOWN")
+                .map(|interface| interface.name.clone())
+                .collect();
+            (self.dns_leak_interface.clone(), up)
+        })
+    }
+
+    pub fn mark_dns_leak_started(&mut self) {
+        self.dns_leak_running = true;
+    }
+
+    pub fn open_dhcp_probe_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        self.dhcp_probe_interface = interface.name.clone();
+        self.dhcp_probe = None;
+        self.modals.open(Modal::DhcpProbe);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_dhcp_probe_dialog(&mut self) {
+        self.modals.close(Modal::DhcpProbe);
+        self.dhcp_probe = None;
+        self.needs_redraw = true;
+    }
+
+    /// Throw the result away so the probe runs again
+    pub fn rerun_dhcp_probe(&mut self) {
+        if !self.dhcp_probe_running {
+            self.dhcp_probe = None;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// The interface to probe and its MAC, when the dialog waits for a
+    /// result and no probe is running
+    pub fn dhcp_probe_due(&self) -> Option<(String, String)> {
+        let due = self.modals.is_open(Modal::DhcpProbe)
+            && self.dhcp_probe.is_none()
+            && !self.dhcp_probe_running;
+        if !due {
+            return None;
+        }
+        let interface = self
+            .interfaces
+            .iter()
+            .find(|interface| interface.name == self.dhcp_probe_interface)?;
+        Some((interface.name.clone(), interface.mac_address.clone()))
+    }
+
+    pub fn mark_dhcp_probe_started(&mut self) {
+        self.dhcp_probe_running = true;
+    }
+
+    pub fn open_reachability_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        self.reachability_interface = interface.name.clone();
+        self.reachability = None;
+        self.modals.open(Modal::Reachability);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_reachability_dialog(&mut self) {
+        self.modals.close(Modal::Reachability);
+        self.reachability = None;
+        self.needs_redraw = true;
+    }
+
+    pub fn rerun_reachability_test(&mut self) {
+        if !self.reachability_running {
+            self.reachability = None;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// The interface to test when the dialog waits for a result
+    pub fn reachability_due(&self) -> Option<Interface> {
+        let due = self.modals.is_open(Modal::Reachability)
+            && self.reachability.is_none()
+            && !self.reachability_running;
+        due.then(|| {
+            self.interfaces
+                .iter()
+                .find(|interface| interface.name == self.reachability_interface)
+                .cloned()
+        })
+        .flatten()
+    }
+
+    pub fn mark_reachability_started(&mut self) {
+        self.reachability_running = true;
+    }
+
+    pub fn open_troubleshoot_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        self.troubleshoot_interface = interface.name.clone();
+        self.troubleshoot = None;
+        self.modals.open(Modal::Troubleshoot);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_troubleshoot_dialog(&mut self) {
+        self.modals.close(Modal::Troubleshoot);
+        self.troubleshoot = None;
+        self.needs_redraw = true;
+    }
+
+    pub fn rerun_troubleshoot(&mut self) {
+        if !self.troubleshoot_running {
+            self.troubleshoot = None;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// The interface to walk when the dialog waits for a result
+    pub fn troubleshoot_due(&self) -> Option<Interface> {
+        let due = self.modals.is_open(Modal::Troubleshoot)
+            && self.troubleshoot.is_none()
+            && !self.troubleshoot_running;
+        due.then(|| {
+            self.interfaces
+                .iter()
+                .find(|interface| interface.name == self.troubleshoot_interface)
+                .cloned()
+        })
+        .flatten()
+    }
+
+    pub fn mark_troubleshoot_started(&mut self) {
+        self.troubleshoot_running = true;
+    }
+
+    /// Offer a GET through the selected interface; the URL from last time stays
+    pub fn open_http_check_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        self.http_check_bindings = Binding::choices(interface);
+        self.http_check_binding = 0;
+        self.http_check = None;
+        self.modals.open(Modal::HttpCheck);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_http_check_dialog(&mut self) {
+        self.modals.close(Modal::HttpCheck);
+        self.http_check = None;
+        self.http_check_queued = false;
+        self.needs_redraw = true;
+    }
+
+    /// The interface, then each of its addresses in turn
+    pub fn cycle_http_check_binding(&mut self) {
+        if !self.http_check_bindings.is_empty() {
+            self.http_check_binding =
+                (self.http_check_binding + 1) % self.http_check_bindings.len();
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn http_check_input(&mut self, key: crossterm::event::KeyEvent) {
+        self.http_check_input
+            .handle_event(&crossterm::event::Event::Key(key));
+        self.needs_redraw = true;
+    }
+
+    pub fn run_http_check(&mut self) {
+        if self.http_check_running {
+            return;
+        }
+        match crate::httpcheck::validate_url(self.http_check_input.value().trim()) {
+            Ok(()) => {
+                self.http_check = None;
+                self.http_check_queued = true;
+            }
+            Err(e) => self.http_check = Some(Err(e.to_string())),
+        }
+        self.needs_redraw = true;
+    }
+
+    /// The URL and where to send it from, once Enter asked for a check
+    pub fn http_check_due(&self) -> Option<(String, Binding)> {
+        if !self.http_check_queued || self.http_check_running {
+            return None;
+        }
+        let binding = self.http_check_bindings.get(self.http_check_binding)?;
+        Some((
+            self.http_check_input.value().trim().to_string(),
+            binding.clone(),
+        ))
+    }
+
+    pub fn mark_http_check_started(&mut self) {
+        self.http_check_queued = false;
+        self.http_check_running = true;
+    }
+
+    pub fn http_check_running(&self) -> bool {
+        self.http_check_running || self.http_check_queued
+    }
+
+    pub fn open_address_preference_dialog(&mut self) {
+        self.address_preference = self.network_manager.address_preference();
+        self.address_preference_choice = self.address_preference.preference;
+        self.address_order = None;
+        self.modals.open(Modal::AddressPreference);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_address_preference_dialog(&mut self) {
+        self.modals.close(Modal::AddressPreference);
+        self.address_order = None;
+        self.needs_redraw = true;
+    }
+
+    pub fn toggle_address_preference(&mut self) {
+        self.address_preference_choice = self.address_preference_choice.toggle();
+        self.needs_redraw = true;
+    }
+
+    /// Write the chosen preference to gai.conf, then look the sample name
+    /// up again to show the new order
+    pub fn save_address_preference(&mut self) {
+        let preference = self.address_preference_choice;
+        if preference == self.address_preference.preference {
+            self.close_address_preference_dialog();
+            return;
+        }
+        let Some(_lock) = self.begin_change() else {
+            return;
+        };
+        let message = match self.network_manager.set_address_preference(preference) {
+            Ok(()) if self.network_manager.is_demo() => {
+                format!("Demo mode: {} (gai.conf not written)", preference.label())
+            }
+            Ok(()) => format!(
+                "{} saved to {}; programs use it from their next lookup",
+                preference.label(),
+                crate::gai::GAI_CONF
+            ),
+            Err(e) => format!("Failed to change the address preference: {:#}", e),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.address_preference = self.network_manager.address_preference();
+        if !self.address_order_running {
+            self.address_order = None;
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Whether the dialog waits for the sample name to be looked up
+    pub fn address_order_due(&self) -> bool {
+        self.modals.is_open(Modal::AddressPreference)
+            && self.address_order.is_none()
+            && !self.address_order_running
+    }
+
+    pub fn mark_address_order_started(&mut self) {
+        self.address_order_running = true;
+    }
+
+    pub fn open_speed_test_panel(&mut self) {
+        self.speed_history = self.network_manager.speed_history();
+        self.modals.open(Modal::SpeedTest);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_speed_test_panel(&mut self) {
+        self.modals.close(Modal::SpeedTest);
+        self.needs_redraw = true;
+    }
+
+    pub fn request_speed_test(&mut self) {
+        if self.speed_test_running {
+            return;
+        }
+        self.speed_test_requested = true;
+        self.status_message = Some(("Speed test running...".to_string(), Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    /// Write the history as CSV to the working directory, to hand to an ISP
+    pub fn export_speed_history(&mut self) {
+        let path = format!(
+            "lantern-speedtest-{}.csv",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        );
+        let message = match std::fs::write(&path, crate::speedtest::to_csv(&self.speed_history)) {
+            Ok(()) => format!(
+                "Speed history saved to {} ({} tests)",
+                path,
+                self.speed_history.len()
+            ),
+            Err(e) => format!("Failed to save {}: {}", path, e),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    /// A test asked for in the panel
+    pub fn speed_test_due(&self) -> bool {
+        self.speed_test_requested && !self.speed_test_running
+    }
+
+    /// The daemon's scheduled test; the first one waits a full interval
+    pub fn scheduled_speed_test_due(&mut self) -> bool {
+        let Some(interval) = self.config.speedtest.interval() else {
+            return false;
+        };
+        let last = *self
+            .last_scheduled_speed_test
+            .get_or_insert_with(Instant::now);
+        !self.speed_test_running && last.elapsed() >= interval
+    }
+
+    pub fn mark_speed_test_started(&mut self) {
+        self.speed_test_running = true;
+        self.speed_test_manual = std::mem::take(&mut self.speed_test_requested);
+        self.last_scheduled_speed_test = Some(Instant::now());
+    }
+
+    /// Whether the daemon should see if the internet answers, for alerts
+    pub fn wan_check_due(&self) -> bool {
+        let alerts = &self.config.alerts;
+        alerts.enabled()
+            && alerts.wan_down_mins > 0
+            && !self.network_manager.is_demo()
+            && !self.wan_checking
+            && self
+                .last_wan_check
+                .is_none_or(|at| at.elapsed() >= alerts.wan_check_interval())
+    }
+
+    pub fn mark_wan_check_started(&mut self) {
+        self.wan_checking = true;
+        self.last_wan_check = Some(Instant::now());
+    }
+
+    /// Read the Enterprise profiles' certificates again for those running out
+    fn check_certificates(&mut self) {
+        self.last_cert_check = Some(Instant::now());
+        let days = self.config.alerts.cert_expiry_days;
+        self.cert_expiries = if days == 0 {
+            Vec::new()
+        } else {
+            crate::certs::expiring(&self.config.wifi_profiles, chrono::Utc::now(), days)
+        };
+    }
+
+    /// The saved network's certificate that runs out first, if one is close
+    pub fn cert_expiry(&self, ssid: &str) -> Option<&CertExpiry> {
+        self.cert_expiries.iter().find(|expiry| expiry.ssid == ssid)
+    }
+
+    /// Alerts waiting to be sent, with links that have started flapping and
+    /// certificates close to expiry
+    pub fn take_alerts(&mut self) -> Vec<Alert> {
+        if !self.config.alerts.enabled() {
+            return Vec::new();
+        }
+        if self
+            .last_cert_check
+            .is_none_or(|at| at.elapsed() >= CERT_CHECK_INTERVAL)
+        {
+            self.check_certificates();
+            let alerts = self
+                .alerts
+                .certificates(&self.cert_expiries, chrono::Utc::now());
+            self.pending_alerts.extend(alerts);
+        }
+        let flapping: Vec<String> = self
+            .interfaces
+            .iter()
+            .filter(|iface| self.events.flaps().is_flapping(&iface.name))
+            .map(|iface| iface.name.clone())
+            .collect();
+        let alerts = self.alerts.flapping(&self.config.alerts, &flapping);
+        self.pending_alerts.extend(alerts);
+        std::mem::take(&mut self.pending_alerts)
+    }
+
+    /// Offer to listen on the selected WiFi interface's radio; nothing
+    /// happens until the dialog's Enter
+    pub fn open_rf_monitor_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        if interface.wifi_info.is_none() && !interface.monitor {
+            self.status_message = Some((
+                "Select a WiFi interface to listen with".to_string(),
+                Instant::now(),
+            ));
+            return;
+        }
+        self.rf_interface = interface.name.clone();
+        self.rf_session = None;
+        self.modals.open(Modal::RfMonitor);
+        self.needs_redraw = true;
+    }
+
+    /// Put a monitor interface on the radio and start listening
+    pub async fn start_rf_monitor(&mut self) -> Result<()> {
+        if self.rf_session.is_some() {
+            return Ok(());
+        }
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+        let interface = self.rf_interface.clone();
+        // Hopping would pull a connected interface off its AP's channel
+        let associated = self
+            .interfaces
+            .iter()
+            .find(|iface| iface.name == interface)
+            .and_then(|iface| iface.wifi_info.as_ref())
+            .is_some_and(|info| info.current_network.is_some());
+        match self
+            .network_manager
+            .open_rf_monitor(&interface, &self.interfaces)
+            .await
+        {
+            Ok((monitor, created)) => {
+                self.rf_session = Some(RfSession::new(&interface, monitor, created, !associated));
+                if created {
+                    self.refresh_interfaces().await?;
+                }
+            }
+            Err(e) => {
+                self.status_message = Some((
+                    format!("Failed to start the RF monitor: {:#}", e),
+                    Instant::now(),
+                ));
+            }
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Stop listening and remove the monitor interface if lantern added it
+    pub async fn close_rf_monitor(&mut self) -> Result<()> {
+        self.modals.close(Modal::RfMonitor);
+        self.needs_redraw = true;
+        let Some(session) = self.rf_session.take() else {
+            return Ok(());
+        };
+        session.stop.store(true, Ordering::Relaxed);
+        let survey = &session.survey;
+        let mut message = format!(
+            "RF monitor: {} hidden networks ({} named), {} devices probing",
+            survey.hidden.len(),
+            survey.revealed(),
+            survey.clients.len()
+        );
+        if session.created {
+            let _lock = self.begin_change();
+            if let Err(e) = self
+                .network_manager
+                .toggle_monitor_interface(&session.monitor, &self.interfaces)
+                .await
+            {
+                message = format!("Failed to remove {}: {:#}", session.monitor, e);
+            }
+            self.refresh_interfaces().await?;
+        }
+        self.status_message = Some((message, Instant::now()));
+        Ok(())
+    }
+
+    /// The monitor to listen on, how, and the flag that stops it, once a
+    /// session is waiting for its capture
+    pub fn rf_capture_due(&self) -> Option<(String, bool, Arc<AtomicBool>)> {
+        let session = self.rf_session.as_ref()?;
+        (session.capture == Capture::Starting)
+            .then(|| (session.monitor.clone(), session.hop, session.stop.clone()))
+    }
+
+    pub fn mark_rf_capture_started(&mut self) {
+        if let Some(session) = &mut self.rf_session {
+            session.capture = Capture::Listening;
+        }
+    }
+
+    /// Start summarizing packets on the selected interface. Only reads, so
+    /// it starts at once
+    pub fn open_packet_view(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        self.packet_view = Some(PacketView::new(&interface.name));
+        self.modals.open(Modal::Packets);
+        self.needs_redraw = true;
+    }
+
+    pub fn close_packet_view(&mut self) {
+        self.modals.close(Modal::Packets);
+        self.needs_redraw = true;
+        let Some(view) = self.packet_view.take() else {
+            return;
+        };
+        view.stop.store(true, Ordering::Relaxed);
+        self.status_message = Some((
+            format!("{}: {} packets while watching", view.interface, view.seen),
+            Instant::now(),
+        ));
+    }
+
+    /// The interface to capture on and the flag that stops it, once the pane
+    /// is waiting for its capture
+    pub fn packet_capture_due(&self) -> Option<(String, Arc<AtomicBool>)> {
+        let view = self.packet_view.as_ref()?;
+        (view.capture == Capture::Starting).then(|| (view.interface.clone(), view.stop.clone()))
+    }
+
+    pub fn mark_packet_capture_started(&mut self) {
+        if let Some(view) = &mut self.packet_view {
+            view.capture = Capture::Listening;
+        }
+    }
+
+    pub fn open_survey(&mut self) {
+        let Some(interface) = self.get_selected_interface() else {
+            return;
+        };
+        if interface.wifi_info.is_none() {
+            self.status_message = Some((
+                "Select a WiFi interface to survey from".to_string(),
+                Instant::now(),
+            ));
+            return;
+        }
+        self.survey = Some(Survey::new(&interface.name));
+        self.last_survey_scan = None;
+        self.last_survey_gps = None;
+        self.modals.open(Modal::Survey);
+        self.needs_redraw = true;
+    }
+
+    /// Stop surveying, saving what was recorded
+    pub fn close_survey(&mut self) {
+        if self.survey.as_ref().is_some_and(|survey| survey.scans > 0) {
+            self.save_survey();
+        }
+        self.modals.close(Modal::Survey);
+        self.survey = None;
+        self.needs_redraw = true;
+    }
+
+    /// Write everything recorded so far as CSV, in the working directory
+    pub fn save_survey(&mut self) {
+        let Some(survey) = &self.survey else {
+            return;
+        };
+        let path = format!(
+            "lantern-survey-{}.csv",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        );
+        let message = match std::fs::write(&path, survey.to_csv()) {
+            Ok(()) => format!(
+                "Survey saved to {} ({} scans, {} samples)",
+                path,
+                survey.scans,
+                survey.records.len()
+            ),
+            Err(e) => format!("Failed to save {}: {}", path, e),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
+
+    /// The interface to scan from when the survey's next scan is due
+    pub fn survey_scan_due(&self) -> Option<String> {
+        let survey = self.survey.as_ref()?;
+        let interval = Duration::from_secs(self.config.survey.interval_secs.max(1));
+        let due = !self.survey_scanning
+            && self
+                .last_survey_scan
+                .is_none_or(|at| at.elapsed() >= interval);
+        due.then(|| survey.interface.clone())
+    }
+
+    pub fn mark_survey_scan_started(&mut self) {
+        self.survey_scanning = true;
+    }
+
+    /// gpsd's address when it is time to ask again, every 2s while surveying
+    pub fn survey_gps_due(&self) -> Option<String> {
+        let due = self.survey.is_some()
+            && self.config.survey.gps
+            && !self.survey_gps_polling
+            && self
+                .last_survey_gps
+                .is_none_or(|at| at.elapsed() > Duration::from_secs(2));
+        due.then(|| self.config.survey.gpsd.clone())
+    }
+
+    pub fn mark_survey_gps_started(&mut self) {
+        self.survey_gps_polling = true;
+        self.last_survey_gps = Some(Instant::now());
+    }
+
+    /// Poll the routing daemon every 5s while the dialog is open.
+    pub fn routing_fetch_due(&self) -> bool {
+        self.modals.is_open(Modal::Routing)
+            && !self.routing_loading
+            && self
+                .last_routing_fetch
+                .is_none_or(|at| at.elapsed() > Duration::from_secs(5))
+    }
+
+    pub fn mark_routing_fetch_started(&mut self) {
+        self.routing_loading = true;
+        self.last_routing_fetch = Some(Instant::now());
+    }
+
+    /// Containers come and go with their veths; look again every 10 seconds
+    /// while there are any.
+    pub fn container_refresh_due(&self) -> bool {
+        self.config.containers.enabled
+            && !self.safe_mode
+            && self.interfaces.iter().any(|i| i.name.starts_with("veth"))
+            && self
+                .last_container_refresh
+                .is_none_or(|at| at.elapsed() > Duration::from_secs(10))
+    }
+
+    pub fn mark_container_refresh_started(&mut self) {
+        self.last_container_refresh = Some(Instant::now());
+    }
+
+    /// The listed interfaces minus ignored ones shown with `.`, for anything
+    /// that configures or looks closely at them on its own.
+    pub fn managed_interfaces(&self) -> Vec<Interface> {
+        self.interfaces
+            .iter()
+            .filter(|iface| !self.config.interfaces.is_ignored(&iface.name))
+            .cloned()
+            .collect()
+    }
+
+    /// Show or hide the ignored interfaces.
+    pub fn toggle_show_ignored(&mut self) {
+        self.show_ignored = !self.show_ignored;
+        self.interface_refresh_requested = true;
+        self.status_message = Some((
+            if self.show_ignored {
+                "Showing ignored interfaces".to_string()
+            } else {
+                "Hiding ignored interfaces".to_string()
+            },
+            Instant::now(),
+        ));
+        self.needs_redraw = true;
+    }
+
+    /// Start or stop ignoring the selected interface.
+    pub fn toggle_ignore_selected(&mut self) {
+        let Some(name) = self
+            .get_selected_interface()
+            .map(|iface| iface.name.clone())
+        else {
+            return;
+        };
+        let settings = &mut self.config.interfaces;
+        let message = if settings.ignored.contains(&name) {
+            settings.ignored.retain(|pattern| *pattern != name);
+            format!("{} is managed by lantern again", name)
+        } else if settings.is_ignored(&name) {
+            // A prefix pattern covers it; removing that would free its siblings too
+            format!(
+                "{} is ignored by a pattern in [interfaces]; edit config.toml (c) to change it",
+                name
+            )
+        } else {
+            settings.ignored.push(name.clone());
+            format!(
+                "{} is ignored from now on; . shows ignored interfaces",
+                name
+            )
+        };
+        let message = match self.config.save() {
+            Ok(()) => message,
+            Err(e) => format!("Failed to save config: {}", e),
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.interface_refresh_requested = true;
+        self.needs_redraw = true;
+    }
+
+    /// Look for other network managers every 30 seconds; the interfaces to
+    /// check when due.
+    pub fn foreign_check_due(&self) -> Option<Vec<String>> {
+        let due = !self.interfaces.is_empty()
+            && !self.safe_mode
+            && self
+                .last_foreign_check
+                .is_none_or(|at| at.elapsed() > Duration::from_secs(30));
+        due.then(|| {
+            self.managed_interfaces()
+                .into_iter()
+                .map(|iface| iface.name)
+                .collect()
+        })
+    }
+
+    pub fn mark_foreign_check_started(&mut self) {
+        self.last_foreign_check = Some(Instant::now());
+    }
+
+    /// Compare the neighbor table with the interfaces every minute
+    pub fn mac_check_due(&self) -> bool {
+        !self.interfaces.is_empty()
+            && !self.safe_mode
+            && self
+                .last_mac_check
+                .is_none_or(|at| at.elapsed() > Duration::from_secs(60))
+    }
+
+    pub fn mark_mac_check_started(&mut self) {
+        self.last_mac_check = Some(Instant::now());
+    }
+
+    /// Conflicts not seen on the previous check go into the timeline and the
+    /// status line; ones that cleared up are dropped quietly
+    fn apply_mac_conflicts(&mut self, conflicts: Vec<Conflict>) {
+        let ignored = &self.config.interfaces;
+        let conflicts: Vec<Conflict> = conflicts
+            .into_iter()
+            .filter(|conflict| !ignored.is_ignored(conflict.interface()))
+            .collect();
+        for conflict in &conflicts {
+            if self.mac_conflicts.contains(conflict) {
+                continue;
+            }
+            let message = conflict.message();
+            self.events.push(
+                conflict.interface().to_string(),
+                EventKind::MacConflict,
+                message.clone(),
+            );
+            self.status_message = Some((format!("MAC conflict on {}", message), Instant::now()));
+        }
+        self.mac_conflicts = conflicts;
+    }
+
+    /// Publish to the MQTT broker every `[mqtt] interval_secs`. The demo's
+    /// made-up interfaces are never published.
+    pub fn mqtt_publish_due(&self) -> bool {
+        self.config.mqtt.enabled
+            && !self.network_manager.is_demo()
+            && !self.safe_mode
+            && self
+                .last_mqtt_publish
+                .is_none_or(|at| at.elapsed() >= self.config.mqtt.interval())
+    }
+
+    /// Push to the time-series database every `[influx] interval_secs`;
+    /// never the demo's interfaces
+    pub fn influx_push_due(&self) -> bool {
+        self.config.influx.enabled
+            && !self.network_manager.is_demo()
+            && self
+                .last_influx_push
+                .is_none_or(|at| at.elapsed() >= self.config.influx.interval())
+    }
+
+    pub fn snmp_refresh_due(&self) -> bool {
+        self.config.snmp.enabled
+            && !self.network_manager.is_demo()
+            && self
+                .last_snmp_refresh
+                .is_none_or(|at| at.elapsed() >= self.config.snmp.refresh())
+    }
+
+    /// Rebuild what the SNMP subagent answers with, starting the subagent
+    /// on first use. None when it can't start.
+    pub fn snmp_job(&mut self) -> Option<SnmpJob> {
+        if self.snmp.is_none() {
+            match Agent::start(&self.config.snmp) {
+                Ok(agent) => self.snmp = Some(agent),
+                Err(e) => {
+                    self.status_message = Some((format!("SNMP: {}", e), Instant::now()));
+                    return None;
+                }
+            }
+        }
+        let hotspot = self
+            .active_hotspot
+            .as_ref()
+            .map(|hotspot| (hotspot.interface.clone(), hotspot.ssid.clone()));
+        let agent = self.snmp.as_ref()?;
+        Some(agent.refresh_job(
+            self.network_manager.clone(),
+            self.managed_interfaces(),
+            hotspot,
+        ))
+    }
+
+    pub fn mark_snmp_refresh_started(&mut self) {
+        self.last_snmp_refresh = Some(Instant::now());
+    }
+
+    /// Send interface status, rates and WiFi signal to the broker,
+    /// connecting on first use. Doesn't wait for the network.
+    pub fn publish_telemetry(&mut self) {
+        self.last_mqtt_publish = Some(Instant::now());
+        if self.mqtt.is_none() {
+            match Publisher::connect(&self.config.mqtt) {
+                Ok(publisher) => self.mqtt = Some(publisher),
+                Err(e) => {
+                    self.status_message = Some((format!("MQTT: {}", e), Instant::now()));
+                    return;
+                }
+            }
+        }
+        let interfaces = self.managed_interfaces();
+        let Some(publisher) = self.mqtt.as_mut() else {
+            return;
+        };
+        publisher.publish(&interfaces);
+        let error = publisher.error();
+        if let Some(e) = error
+            .as_ref()
+            .filter(|e| self.mqtt_error.as_ref() != Some(*e))
+        {
+            self.status_message = Some((format!("MQTT broker unreachable: {}", e), Instant::now()));
+        }
+        self.mqtt_error = error;
+    }
+
+    /// Send interface rates and WiFi signal to the time-series database.
+    /// A failure is reported once, when it first happens.
+    pub fn push_metrics(&mut self) {
+        self.last_influx_push = Some(Instant::now());
+        if self.influx.is_none() {
+            match crate::influx::Pusher::new(&self.config.influx) {
+                Ok(pusher) => self.influx = Some(pusher),
+                Err(e) => {
+                    self.status_message = Some((format!("InfluxDB: {}", e), Instant::now()));
+                    return;
+                }
+            }
+        }
+        let interfaces = self.managed_interfaces();
+        let Some(pusher) = self.influx.as_mut() else {
+            return;
+        };
+        // The previous push's outcome; this one is still in flight
+        let error = pusher.error();
+        pusher.push(&interfaces);
+        if let Some(e) = error
+            .as_ref()
+            .filter(|e| self.influx_error.as_ref() != Some(*e))
+        {
+            self.status_message = Some((format!("InfluxDB push failed: {}", e), Instant::now()));
+        }
+        self.influx_error = error;
+    }
+
+    // Writing networkd config for an interface another manager runs would
+    // leave the two fighting over it; point at adopting it instead.
+    fn held_by_other_manager(&mut self, interface: &str) -> bool {
+        let Some(foreign) = self.foreign_managers.get(interface) else {
+            return false;
+        };
+        self.status_message = Some((
+            format!(
+                "{} is managed by {}; press T to adopt it into lantern first",
+                interface,
+                foreign.manager.label()
+            ),
+            Instant::now(),
+        ));
+        self.needs_redraw = true;
+        true
+    }
+
+    pub fn open_adopt_dialog(&mut self) {
+        let Some(interface) = self.get_selected_interface().map(|i| i.name.clone()) else {
+            return;
+        };
+        if !self.foreign_managers.contains_key(&interface) {
+            self.status_message = Some((
+                format!("No other network manager runs {}", interface),
+                Instant::now(),
+            ));
+        } else {
+            self.adopt_interface = interface;
+            self.modals.open(Modal::Adopt);
+        }
+        self.needs_redraw = true;
+    }
+
+    pub fn close_adopt_dialog(&mut self) {
+        self.modals.close(Modal::Adopt);
+        self.needs_redraw = true;
+    }
+
+    /// Take the interface away from its manager and give networkd the
+    /// settings it had.
+    pub async fn adopt_interface(&mut self) -> Result<()> {
+        self.modals.close(Modal::Adopt);
+        self.needs_redraw = true;
+        let interface = self.adopt_interface.clone();
+        let Some(foreign) = self.foreign_managers.get(&interface).cloned() else {
+            return Ok(());
+        };
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return Ok(());
+        };
+        if let Err(e) = self
+            .network_manager
+            .adopt_interface(&interface, &foreign)
+            .await
+        {
+            self.status_message = Some((
+                format!(
+                    "Could not take {} from {}: {}",
+                    interface,
+                    foreign.manager.label(),
+                    e
+                ),
+                Instant::now(),
+            ));
+            return Ok(());
+        }
+        self.foreign_managers.remove(&interface);
+
+        let imported = foreign.imported;
+        let message = if self.network_manager.is_demo() {
+            format!("Demo mode: {} adopted, nothing written", interface)
+        } else {
+            let dns = (!imported.dns.is_empty()).then_some(imported.dns);
+            match self
+                .systemd_config
+                .create_config(
+                    &interface,
+                    imported.dhcp,
+                    imported.address,
+                    imported.gateway,
+                    dns,
+                    &NetworkExtras::default(),
+                )
+                .await
+            {
+                Ok(()) => format!(
+                    "{} adopted from {}; networkd now configures it",
+                    interface,
+                    foreign.manager.label()
+                ),
+                Err(e) => format!(
+                    "{} released by {}, but writing its networkd config failed: {}",
+                    interface,
+                    foreign.manager.label(),
+                    e
+                ),
+            }
+        };
+        self.status_message = Some((message, Instant::now()));
+        Ok(())
+    }
+
+    // Build a share payload for the selected (or first connected) WiFi interface
+    fn connected_network_payload(&self) -> Option<WifiQrPayload> {
+        let selected = self
+            .get_selected_interface()
+            .filter(|iface| {
+                iface
+                    .wifi_info
+                    .as_ref()
+                    .map_or(false, |w| w.current_network.is_some())
+            })
+            .or_else(|| {
+                self.interfaces.iter().find(|iface| {
+                    iface
+                        .wifi_info
+                        .as_ref()
+                        .map_or(false, |w| w.current_network.is_some())
+                })
+            })?;
+
+        let network = selected.wifi_info.as_ref()?.current_network.as_ref()?;
+        let profile = self.config.get_wifi_profile(&network.ssid, &selected.name);
+
+        // Prefer the saved security type, the live one is only a best guess
+        let security = profile
+            .map(|p| parse_security_type(&p.security_type))
+            .unwrap_or_else(|| network.security.clone());
+
+        Some(WifiQrPayload {
+            ssid: network.ssid.clone(),
+            password: profile.and_then(|p| p.password.clone()),
+            security,
+            hidden: false,
+        })
+    }
+
+    // WiFi Diagnostics methods
+    pub async fn open_wifi_diagnostics_dialog(&mut self) {
+        // Fetch diagnostics data when opening the dialog
+        self.wifi_diagnostics_data = self.get_detailed_wifi_info().await.unwrap_or(None);
+        self.modals.open(Modal::WifiDiagnostics);
+    }
+
+    pub fn close_wifi_diagnostics_dialog(&mut self) {
+        self.modals.close(Modal::WifiDiagnostics);
+        self.wifi_diagnostics_data = None;
+    }
+
+    pub async fn get_detailed_wifi_info(&self) -> Result<Option<DetailedWifiInfo>> {
+        if let Some(interface) = self.get_selected_interface() {
+            if interface.wifi_info.is_some() {
+                return self
+                    .network_manager
+                    .get_detailed_wifi_info(&interface.name)
+                    .await;
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn refresh_wifi_diagnostics(&mut self) {
+        if self.modals.is_open(Modal::WifiDiagnostics) {
+            self.wifi_diagnostics_data = self.get_detailed_wifi_info().await.unwrap_or(None);
+        }
+    }
+
+    /// Raise or lower the transmit power by `step` dBm from what the driver
+    /// reports now
+    pub async fn step_tx_power(&mut self, step: i32) {
+        let Some(current) = self
+            .wifi_diagnostics_data
+            .as_ref()
+            .and_then(|diagnostics| diagnostics.tx_power)
+        else {
+            self.status_message = Some((
+                "The driver doesn't report its transmit power".to_string(),
+                Instant::now(),
+            ));
+            return;
+        };
+        self.set_tx_power(Some((current + step).clamp(MIN_TX_POWER, MAX_TX_POWER)))
+            .await;
+    }
+
+    /// Set the power now and keep it in the connected network's profile, so
+    /// it comes back with the next connection; None hands it to the driver
+    pub async fn set_tx_power(&mut self, dbm: Option<i32>) {
+        let Some(ssid) = self
+            .wifi_diagnostics_data
+            .as_ref()
+            .map(|diagnostics| diagnostics.ssid.clone())
+        else {
+            return;
+        };
+        let Some(interface) = self.get_selected_interface().map(|i| i.name.clone()) else {
+            return;
+        };
+        let Some(_lock) = self.begin_change_on_selected() else {
+            return;
+        };
+        if let Err(e) = self.network_manager.set_tx_power(&interface, dbm).await {
+            self.status_message = Some((
+                format!("Failed to set the transmit power: {:#}", e),
+                Instant::now(),
+            ));
+            return;
+        }
+
+        let saved = match self
+            .config
+            .wifi_profiles
+            .iter_mut()
+            .find(|p| p.ssid == ssid && p.interface == interface)
+        {
+            Some(profile) => {
+                profile.tx_power_dbm = dbm;
+                if let Err(e) = self.config.save() {
+                    eprintln!("Warning: Failed to save transmit power: {}", e);
+                }
+                true
+            }
+            None => false,
+        };
+        let power = match dbm {
+            Some(dbm) => format!("{} dBm", dbm),
+            None => "the driver's choice".to_string(),
+        };
+        let message = if saved {
+            format!("{} transmits at {}, saved for {}", interface, power, ssid)
+        } else {
+            format!(
+                "{} transmits at {} until it reconnects ({} isn't saved)",
+                interface, power, ssid
+            )
+        };
+        self.status_message = Some((message, Instant::now()));
+        self.refresh_wifi_diagnostics().await;
+    }
+}
+
+/// A background auto-connect attempt, working on a snapshot of the App.
+/// Fetches the current network and signal of the wireless interfaces that
+/// are up; the result comes back as `AppEvent::WifiInfo`.
+pub struct WifiInfoJob {
+    network_manager: NetworkManager,
+    interfaces: Vec<Interface>,
+}
+
+impl WifiInfoJob {
+    pub async fn run(self) -> Option<AppEvent> {
+        let mut updated_interfaces = Vec::new();
+        for interface in self.interfaces {
+            if interface.wifi_info.is_some() && interface.state == "UP" {
+                if let Ok(wifi_info) = self.network_manager.get_wifi_info(&interface.name).await {
+                    let mut updated = interface.clone();
+                    updated.wifi_info = wifi_info;
+                    updated_interfaces.push(updated);
+                }
+            }
+        }
+        (!updated_interfaces.is_empty()).then_some(AppEvent::WifiInfo(updated_interfaces))
+    }
+}
+
+pub struct AutoConnectJob {
+    network_manager: NetworkManager,
+    interfaces: Vec<Interface>,
+    profiles: Vec<WifiProfile>, // Auto-connect profiles, highest priority first
+}
+
+impl AutoConnectJob {
+    pub async fn run(self) -> Option<AppEvent> {
+        // Only auto-connect if no WiFi interface is currently connected
+        let has_connected_wifi = self.interfaces.iter().any(|iface| {
+            iface.state == "UP"
+                && iface
+                    .wifi_info
+                    .as_ref()
+                    .is_some_and(|w| w.current_network.is_some())
+        });
+        if has_connected_wifi {
+            return None;
+        }
+
+        // Find the first available WiFi interface
+        let interface_name = self
+            .interfaces
+            .iter()
+            .find(|iface| iface.wifi_info.is_some())
+            .map(|iface| iface.name.clone())?;
+
+        let profiles: Vec<_> = self
+            .profiles
+            .iter()
+            .filter(|profile| profile.interface == interface_name)
+            .collect();
+        if profiles.is_empty() {
+            return None;
+        }
+
+        // Background task: quietly retry on the next check if another instance is busy
+        let _lock = OperationLock::try_acquire().ok()?;
+
+        let available_networks = self
+            .network_manager
+            .scan_wifi_networks(&interface_name)
+            .await
+            .ok()?;
+
+        // Try to connect to the highest priority available network
+        for profile in profiles {
+            if !available_networks
+                .iter()
+                .any(|net| net.ssid == profile.ssid)
+            {
+                continue;
+            }
+
+            let credentials = WifiCredentials {
+                ssid: profile.ssid.clone(),
+                password: profile.password.clone(),
+                security: parse_security_type(&profile.security_type),
+                hidden: false, // Auto-connect typically for visible networks
+                enterprise: profile.enterprise.clone(),
+                band: profile.band,
+                tx_power_dbm: profile.tx_power_dbm,
+                routes: profile.routes.clone(),
+                domains: profile.domains.clone(),
+            };
+
+            match self
+                .network_manager
+                .connect_to_wifi(
+                    &interface_name,
+                    &credentials,
+                    profile.dhcp,
+                    profile.ip.clone(),
+                    profile.gateway.clone(),
+                    profile.dns.clone(),
+                )
+                .await
+            {
+                Ok(()) => {
+                    return Some(AppEvent::AutoConnected {
+                        ssid: profile.ssid.clone(),
+                        interface: interface_name,
+                    })
+                }
+                Err(e) => eprintln!("Auto-connect failed for {}: {}", profile.ssid, e),
+            }
+        }
+
+        None
+    }
+}
+
+/// A saved profile that matches a NIC that was just plugged in
+#[derive(Debug, Clone)]
+pub struct HotplugOffer {
+    pub interface: String,
+    pub hardware: String,
+    pub profile: Profile,
+    pub auto: bool, // Apply without asking ([hotplug] auto_apply)
+}
+
+pub struct NetworkPolicyJob {
+    network_manager: NetworkManager,
+    systemd_config: SystemdNetworkConfig,
+    interfaces: Vec<Interface>,
+    policies: Vec<VpnPolicy>,
+    trusted: TrustedNetworks,
+    profiles: Vec<Profile>,
+}
+
+impl NetworkPolicyJob {
+    pub async fn run(self) -> Vec<AppEvent> {
+        let uplinks = self.uplinks().await;
+        let trust: HashMap<String, bool> = uplinks
+            .iter()
+            .map(|uplink| (uplink.interface.clone(), self.trusted.trusts(uplink)))
+            .collect();
+        let untrusted: Vec<String> = trust
+            .iter()
+            .filter(|(_, trusted)| !**trusted)
+            .map(|(name, _)| name.clone())
+            .collect();
+        let ssids: Vec<String> = uplinks.into_iter().filter_map(|u| u.ssid).collect();
+        let mut events = vec![AppEvent::Trust(trust)];
+
+        let Ok(_lock) = OperationLock::try_acquire() else {
+            return events;
+        };
+
+        let mut applied = Vec::new();
+        let mut failed = Vec::new();
+        for policy in &self.policies {
+            // The netdev may have been removed; nothing to bring up
+            let Some(tunnel) = self.interfaces.iter().find(|i| i.name == policy.interface) else {
+                continue;
+            };
+            let Some(wanted) = vpn_wanted(
+                &policy.auto_connect,
+                &self.interfaces,
+                &ssids,
+                !untrusted.is_empty(),
+                &self.profiles,
+            ) else {
+                continue;
+            };
+            // WireGuard links report "UNKNOWN" rather than "UP" once running
+            let up = tunnel.state != "DOWN";
+
+            let result = match (wanted, up) {
+                (true, false) => self.network_manager.connect_wireguard(&tunnel.name).await,
+                (false, true) => {
+                    self.network_manager
+                        .disconnect_wireguard(&tunnel.name)
+                        .await
+                }
+                _ => continue,
+            };
+            match result {
+                Ok(()) => applied.push((tunnel.name.clone(), wanted)),
+                Err(e) => failed.push((tunnel.name.clone(), e.to_string())),
+            }
+        }
+        if !applied.is_empty() || !failed.is_empty() {
+            events.push(AppEvent::VpnPolicyApplied { applied, failed });
+        }
+
+        let ruleset = if self.trusted.enforces_firewall() {
+            let endpoints = self.wireguard_endpoints().await;
+            crate::trust::firewall_ruleset(&self.trusted, &untrusted, &endpoints)
+        } else {
+            None
+        };
+        if let Err(e) = crate::trust::apply_firewall(ruleset).await {
+            events.push(AppEvent::FirewallFailed(format!("{:#}", e)));
+        }
+
+        events
+    }
+
+    /// Interfaces with a default route, and what identifies their network.
+    async fn uplinks(&self) -> Vec<Uplink> {
+        let mut uplinks = Vec::new();
+        for iface in &self.interfaces {
+            let Some(gateway) = &iface.gateway else {
+                continue;
+            };
+            // Interface refreshes leave out the SSID, so ask the radio directly
+            let ssid = match iface.wifi_info {
+                Some(_) => self
+                    .network_manager
+                    .get_wifi_info(&iface.name)
+                    .await
+                    .ok()
+                    .flatten()
+                    .and_then(|info| info.current_network)
+                    .map(|network| network.ssid),
+                None => None,
+            };
+            uplinks.push(Uplink {
+                interface: iface.name.clone(),
+                ssid,
+                gateway: Some(gateway.clone()),
+                gateway_mac: self
+                    .network_manager
+                    .get_neighbor_mac(&iface.name, gateway)
+                    .await,
+            });
+        }
+        uplinks
+    }
+
+    /// Where the kill switch must still let WireGuard through: live peer
+    /// addresses plus the configured endpoints. Hostnames only resolve while
+    /// DNS still works, so a running tunnel's address is what keeps it up.
+    async fn wireguard_endpoints(&self) -> Vec<std::net::SocketAddr> {
+        let mut endpoints = self.network_manager.active_wireguard_endpoints().await;
+        for peer in self.systemd_config.wireguard_endpoints() {
+            if let Ok(addresses) = tokio::net::lookup_host(peer.endpoint.as_str()).await {
+                endpoints.extend(addresses);
+            }
+        }
+        endpoints.sort();
+        endpoints.dedup();
+        endpoints
+    }
+}
+
+/// Whether a tunnel's policy wants it up right now; None leaves it alone.
+fn vpn_wanted(
+    policy: &VpnAutoConnect,
+    interfaces: &[Interface],
+    connected_ssids: &[String],
+    on_untrusted_network: bool,
+    profiles: &[Profile],
+) -> Option<bool> {
+    match policy {
+        VpnAutoConnect::Never => None,
+        VpnAutoConnect::Always => Some(true),
+        VpnAutoConnect::UntrustedWifi => Some(on_untrusted_network),
+        VpnAutoConnect::Profile(name) => {
+            let wired_active =
+                profiles
+                    .iter()
+                    .filter(|profile| &profile.name == name)
+                    .any(|profile| {
+                        interfaces
+                            .iter()
+                            .any(|i| i.name == profile.interface && i.state == "UP")
+                    });
+            Some(wired_active || connected_ssids.contains(name))
+        }
+    }
+}
+
+fn parse_security_type(security_str: &str) -> WifiSecurity {
+    match security_str {
+        "Open" => WifiSecurity::Open,
+        "WEP" => WifiSecurity::WEP,
+        "WPA" => WifiSecurity::WPA,
+        "WPA2" => WifiSecurity::WPA2,
+        "WPA3" => WifiSecurity::WPA3,
+        "Enterprise" => WifiSecurity::Enterprise,
+        _ => WifiSecurity::WPA2, // Default fallback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_kind::LinkKind;
+    use crate::network::{InterfaceStats, WifiInfo};
+
+    fn iface(name: &str, rx_bytes: u64) -> Interface {
+        Interface {
+            name: name.to_string(),
+            mac_address: String::new(),
+            state: "UP".to_string(),
+            mtu: 1500,
+            ipv4_addresses: Vec::new(),
+            ipv6_addresses: Vec::new(),
+            ipv6_info: None,
+            gateway: None,
+            ipv6_gateway: None,
+            dns_servers: Vec::new(),
+            stats: InterfaceStats {
+                rx_bytes,
+                ..Default::default()
+            },
+            wifi_info: None,
+            hardware: None,
+            admin_up: true,
+            carrier: Some(true),
+            carrier_changes: None,
+            promiscuous: false,
+            monitor: false,
+            kind: LinkKind::Physical,
+            master: None,
+            lower: None,
+            peer_netns: None,
+        }
+    }
+
+    fn app(interfaces: Vec<Interface>) -> App {
+        App::with_interfaces(NetworkManager::new(), interfaces, Config::default())
+    }
+
+    fn stats(entries: &[(&str, u64)]) -> AppEvent {
+        AppEvent::Stats(
+            entries
+                .iter()
+                .map(|&(name, rx_bytes)| (name.to_string(), iface(name, rx_bytes).stats))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn stats_survive_interface_removed_during_refresh() {
+        // Stats were collected for eth0, usb0 and wlan0, then usb0 was unplugged
+        let mut app = app(vec![iface("eth0", 0), iface("wlan0", 0)]);
+        app.needs_redraw = false;
+
+        app.apply(stats(&[("eth0", 100), ("usb0", 300), ("wlan0", 200)]));
+
+        assert_eq!(app.interfaces[0].stats.rx_bytes, 100);
+        assert_eq!(app.interfaces[1].stats.rx_bytes, 200);
+        // Only the panes showing statistics are redrawn
+        assert!(app.redraw.is_dirty(Region::Interfaces));
+        assert!(app.redraw.is_dirty(Region::Details));
+        assert!(!app.redraw.is_dirty(Region::Footer));
+        assert!(!app.needs_redraw);
+    }
+
+    #[test]
+    fn stats_survive_interface_added_during_refresh() {
+        // usb0 was plugged in after the stats task took its list of names
+        let mut app = app(vec![iface("eth0", 0), iface("usb0", 7), iface("wlan0", 0)]);
+
+        app.apply(stats(&[("eth0", 100), ("wlan0", 200)]));
+
+        assert_eq!(app.interfaces[0].stats.rx_bytes, 100);
+        assert_eq!(app.interfaces[1].stats.rx_bytes, 7);
+        assert_eq!(app.interfaces[2].stats.rx_bytes, 200);
+    }
+
+    #[test]
+    fn late_results_are_dropped() {
+        let mut app = app(vec![iface("eth0", 0), iface("wlan0", 0)]);
+
+        // Scan finished after the WiFi dialog was closed
+        app.apply(AppEvent::WifiScan("wlan0".to_string(), Ok(Vec::new())));
+        assert!(app.wifi_networks.is_empty());
+        assert!(!app.wifi_scanning);
+
+        // Routes for an interface that is no longer selected
+        app.routes.start("eth0");
+        app.routes.start("wlan0");
+        app.apply(AppEvent::Routes(
+            "eth0".to_string(),
+            vec![Route {
+                destination: "default".to_string(),
+                gateway: None,
+                protocol: None,
+                metric: None,
+                ipv6: false,
+            }],
+        ));
+        assert!(app.routes.value.is_empty());
+        assert!(!app.routes.loading);
+    }
+
+    #[test]
+    fn failed_vpn_policies_are_reported() {
+        let mut app = app(vec![iface("eth0", 0), iface("wg0", 0), iface("wg1", 0)]);
+
+        app.apply(AppEvent::VpnPolicyApplied {
+            applied: vec![("wg0".to_string(), true)],
+            failed: vec![("wg1".to_string(), "Failed to start wg1".to_string())],
+        });
+        let status = app
+            .status_message
+            .as_ref()
+            .map(|(message, _)| message.as_str());
+        assert_eq!(
+            status,
+            Some("Auto-connect policy for wg1 failed: Failed to start wg1")
+        );
+        let logged: Vec<&str> = app
+            .events
+            .recent()
+            .map(|event| event.interface.as_str())
+            .collect();
+        assert_eq!(logged, vec!["wg1", "wg0"]);
+    }
+
+    #[test]
+    fn firewall_failure_shows_until_a_pass_succeeds() {
+        let mut app = app(vec![iface("eth0", 0)]);
+        let untrusted = HashMap::from([("eth0".to_string(), false)]);
+
+        app.apply(AppEvent::Trust(untrusted.clone()));
+        app.apply(AppEvent::FirewallFailed("nft: not found".to_string()));
+        assert_eq!(app.firewall_failed.as_deref(), Some("nft: not found"));
+        let status = app
+            .status_message
+            .as_ref()
+            .map(|(message, _)| message.as_str());
+        assert_eq!(
+            status,
+            Some("Untrusted network firewall not applied: nft: not found")
+        );
+
+        // The next pass got it in place
+        app.apply(AppEvent::Trust(untrusted));
+        assert!(app.firewall_failed.is_none());
+    }
+
+    #[test]
+    fn hotplugged_nic_gets_profile_offer() {
+        let mut config = Config::default();
+        config.add_profile(Profile {
+            name: "Diagnostics".to_string(),
+            interface: "enx*".to_string(),
+            dhcp: true,
+            ip: None,
+            gateway: None,
+            dns: None,
+            routes: Vec::new(),
+            domains: Vec::new(),
+        });
+        let mut app = App::with_interfaces(NetworkManager::new(), vec![iface("eth0", 0)], config);
+
+        let mut dongle = iface("enx00e04c680001", 0);
+        dongle.hardware = Some(crate::network::NicHardware {
+            driver: Some("r8152".to_string()),
+            ..Default::default()
+        });
+        let virtual_link = iface("enxveth", 0); // Matches the pattern but has no device
+
+        app.set_interfaces(vec![iface("eth0", 0), virtual_link.clone()]);
+        assert!(app.hotplug_offer.is_none());
+
+        app.set_interfaces(vec![iface("eth0", 0), virtual_link, dongle]);
+        let offer = app.hotplug_offer.as_ref().unwrap();
+        assert_eq!(offer.interface, "enx00e04c680001");
+        assert_eq!(offer.profile.name, "Diagnostics");
+        assert!(!offer.auto);
+    }
+
+    #[test]
+    fn hotplug_prompt_waits_for_text_fields() {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        let mut config = Config::default();
+        config.add_profile(Profile {
+            name: "Diagnostics".to_string(),
+            interface: "enx*".to_string(),
+            dhcp: true,
+            ip: None,
+            gateway: None,
+            dns: None,
+            routes: Vec::new(),
+            domains: Vec::new(),
+        });
+        let mut app = App::with_interfaces(NetworkManager::new(), vec![iface("eth0", 0)], config);
+        let mut dongle = iface("enx00e04c680001", 0);
+        dongle.hardware = Some(crate::network::NicHardware::default());
+
+        // Someone is typing when the NIC shows up: the `y` is theirs
+        app.open_palette();
+        app.set_interfaces(vec![iface("eth0", 0), dongle]);
+        assert!(app.hotplug_offer.is_some());
+        assert_eq!(app.modals.top(), Some(Modal::Palette));
+        app.palette_input(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE));
+        assert_eq!(app.palette_input.value(), "y");
+        assert!(app.hotplug_offer.is_some());
+
+        // The prompt comes up once the field is gone, and answering closes it
+        app.close_palette();
+        app.show_hotplug_offer();
+        assert_eq!(app.modals.top(), Some(Modal::Hotplug));
+        app.dismiss_hotplug_offer();
+        assert!(app.modals.is_empty());
+        assert!(app.hotplug_offer.is_none());
+    }
+
+    #[test]
+    fn vpn_policies_follow_network() {
+        let ssids = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let interfaces = vec![iface("eth0", 0)];
+        let office = Profile {
+            name: "Office".to_string(),
+            interface: "eth0".to_string(),
+            dhcp: true,
+            ip: None,
+            gateway: None,
+            dns: None,
+            routes: Vec::new(),
+            domains: Vec::new(),
+        };
+        let wanted = |policy: VpnAutoConnect, connected: &[&str]| {
+            vpn_wanted(
+                &policy,
+                &interfaces,
+                &ssids(connected),
+                connected.contains(&"Cafe"), // The only untrusted network here
+                std::slice::from_ref(&office),
+            )
+        };
+
+        assert_eq!(wanted(VpnAutoConnect::Never, &["Cafe"]), None);
+        assert_eq!(wanted(VpnAutoConnect::Always, &[]), Some(true));
+        assert_eq!(wanted(VpnAutoConnect::UntrustedWifi, &["Cafe"]), Some(true));
+        assert_eq!(
+            wanted(VpnAutoConnect::UntrustedWifi, &["Home"]),
+            Some(false)
+        );
+
+        let profile = |name: &str| VpnAutoConnect::Profile(name.to_string());
+        assert_eq!(wanted(profile("Office"), &[]), Some(true));
+        assert_eq!(wanted(profile("Cafe"), &["Cafe"]), Some(true));
+        assert_eq!(wanted(profile("Cafe"), &["Home"]), Some(false));
+    }
+
+    #[test]
+    fn wifi_info_updates_timeline() {
+        let mut wlan0 = iface("wlan0", 0);
+        wlan0.wifi_info = Some(WifiInfo {
+            current_network: None,
+            signal_strength: None,
+            frequency: None,
+            channel: None,
+        });
+        let mut app = app(vec![wlan0.clone()]);
+
+        app.apply(AppEvent::WifiInfo(vec![wlan0.clone()]));
+        assert_eq!(app.events.recent().count(), 0);
+
+        let network = WifiNetwork {
+            ssid: "Home".to_string(),
+            bssid: String::new(),
+            signal_strength: -50,
+            frequency: 2412,
+            channel: 1,
+            security: WifiSecurity::WPA2,
+            encryption: Vec::new(),
+            security_details: Default::default(),
+            connected: true,
+            in_history: false,
+            access_points: Vec::new(),
+        };
+        wlan0.wifi_info.as_mut().unwrap().current_network = Some(network);
+        app.apply(AppEvent::WifiInfo(vec![wlan0]));
+
+        assert_eq!(
+            app.events.recent().next().unwrap().message,
+            "wlan0 connected to Home"
+        );
+        assert!(app.interfaces[0]
+            .wifi_info
+            .as_ref()
+            .unwrap()
+            .current_network
+            .is_some());
+    }
+
+    #[test]
+    fn safe_mode_turns_off_every_background_check() {
+        let mut app = app(vec![iface("eth0", 0), iface("veth1a2b", 0)]);
+        app.config.containers.enabled = true;
+        app.config.mqtt.enabled = true;
+        app.config.trusted.ssids = vec!["home".to_string()];
+        app.config.wireguard.reresolve_endpoints_secs = 60;
+        let hour_ago = Instant::now() - Duration::from_secs(3600);
+        app.last_interface_refresh = hour_ago;
+        app.last_wifi_update = hour_ago;
+        app.last_auto_connect_check = hour_ago;
+        app.last_endpoint_resolve = hour_ago;
+
+        let gates = |app: &App| {
+            [
+                ("interfaces", app.should_refresh_interfaces()),
+                ("wifi info", app.should_update_wifi_info()),
+                ("auto-connect", app.should_check_auto_connect()),
+                ("endpoints", app.should_reresolve_endpoints()),
+                ("policies", app.should_check_network_policies()),
+                ("hotspot units", app.hotspot_unit_check_due()),
+                ("containers", app.container_refresh_due()),
+                ("foreign", app.foreign_check_due().is_some()),
+                ("mac conflicts", app.mac_check_due()),
+                ("mqtt", app.mqtt_publish_due()),
+            ]
+        };
+        for (gate, due) in gates(&app) {
+            assert!(due, "{} should be due before safe mode", gate);
+        }
+        app.safe_mode = true;
+        for (gate, due) in gates(&app) {
+            assert!(!due, "{} still runs in safe mode", gate);
         }
     }
 }
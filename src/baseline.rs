@@ -0,0 +1,116 @@
+// src/baseline.rs - Zeroed counters: kernel totals shown relative to when the user reset them
+use crate::network::{Interface, InterfaceStats};
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+
+/// The counters at the moment of a reset
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    pub stats: InterfaceStats,
+    pub taken: DateTime<Local>,
+}
+
+/// The kernel's counters can't be cleared, so a reset keeps their values and
+/// what is shown is the difference. Only for this session.
+#[derive(Debug, Clone, Default)]
+pub struct Baselines {
+    links: HashMap<String, Baseline>,
+}
+
+impl Baselines {
+    pub fn reset(&mut self, interface: &Interface) {
+        self.links.insert(
+            interface.name.clone(),
+            Baseline {
+                stats: interface.stats.clone(),
+                taken: Local::now(),
+            },
+        );
+    }
+
+    /// Back to the kernel's totals; false if there was no baseline
+    pub fn clear(&mut self, interface: &str) -> bool {
+        self.links.remove(interface).is_some()
+    }
+
+    pub fn get(&self, interface: &str) -> Option<&Baseline> {
+        self.links.get(interface)
+    }
+
+    /// Forget the baselines of interfaces that are gone or whose counters
+    /// went backwards: the device was recreated and counts from zero again
+    pub fn observe(&mut self, interfaces: &[Interface]) {
+        self.links.retain(|name, baseline| {
+            interfaces
+                .iter()
+                .find(|interface| &interface.name == name)
+                .is_some_and(|interface| {
+                    let (now, then) = (&interface.stats, &baseline.stats);
+                    now.rx_bytes >= then.rx_bytes
+                        && now.tx_bytes >= then.tx_bytes
+                        && now.rx_packets >= then.rx_packets
+                        && now.tx_packets >= then.tx_packets
+                })
+        });
+    }
+
+    /// The interface's counters since its reset, or the kernel's totals
+    pub fn relative(&self, interface: &Interface) -> InterfaceStats {
+        let now = &interface.stats;
+        let Some(baseline) = self.get(&interface.name) else {
+            return now.clone();
+        };
+        let then = &baseline.stats;
+        InterfaceStats {
+            rx_bytes: now.rx_bytes.saturating_sub(then.rx_bytes),
+            tx_bytes: now.tx_bytes.saturating_sub(then.tx_bytes),
+            rx_packets: now.rx_packets.saturating_sub(then.rx_packets),
+            tx_packets: now.tx_packets.saturating_sub(then.tx_packets),
+            rx_errors: now.rx_errors.saturating_sub(then.rx_errors),
+            tx_errors: now.tx_errors.saturating_sub(then.tx_errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_from_the_reset() {
+        let mut eth0 = crate::demo::Simulation::new()
+            .interfaces()
+            .into_iter()
+            .find(|interface| interface.name == "eth0")
+            .unwrap();
+        eth0.stats = InterfaceStats {
+            rx_bytes: 5_000,
+            tx_bytes: 3_000,
+            rx_packets: 50,
+            tx_packets: 30,
+            rx_errors: 2,
+            tx_errors: 0,
+        };
+        let mut baselines = Baselines::default();
+        assert_eq!(baselines.relative(&eth0).rx_bytes, 5_000);
+
+        baselines.reset(&eth0);
+        eth0.stats.rx_bytes += 1_200;
+        eth0.stats.rx_packets += 3;
+        let since = baselines.relative(&eth0);
+        assert_eq!((since.rx_bytes, since.rx_packets), (1_200, 3));
+        assert_eq!((since.tx_bytes, since.rx_errors), (0, 0));
+
+        // Still counting up: kept
+        baselines.observe(std::slice::from_ref(&eth0));
+        assert!(baselines.get("eth0").is_some());
+        // Recreated, counting from zero again: dropped
+        eth0.stats.rx_bytes = 100;
+        baselines.observe(std::slice::from_ref(&eth0));
+        assert!(baselines.get("eth0").is_none());
+
+        baselines.reset(&eth0);
+        assert!(baselines.clear("eth0"));
+        assert!(!baselines.clear("eth0"));
+    }
+}
@@ -0,0 +1,96 @@
+// src/capabilities.rs - What each interface is, probed once per ifindex instead of every refresh
+use crate::link_kind::LinkKind;
+use crate::network::NicHardware;
+use std::collections::{HashMap, HashSet};
+
+/// Facts about an interface that don't change while it exists
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Capabilities {
+    pub wireless: bool,
+    pub phy: Option<String>, // phy0, ... for WiFi
+    pub hardware: Option<NicHardware>,
+    pub kind: LinkKind, // As sysfs tells it
+}
+
+#[derive(Debug, Default)]
+pub struct CapabilityCache {
+    // The name is kept too: a rename reuses the ifindex, and sysfs paths with it
+    interfaces: HashMap<u64, (String, Capabilities)>,
+}
+
+impl CapabilityCache {
+    /// Cached capabilities, probing only interfaces not seen before. A device
+    /// plugged in gets a fresh ifindex, so hotplug is what triggers a probe.
+    pub fn get_or_probe(
+        &mut self,
+        ifindex: u64,
+        name: &str,
+        probe: impl FnOnce() -> Capabilities,
+    ) -> Capabilities {
+        match self.interfaces.get(&ifindex) {
+            Some((known, capabilities)) if known == name => capabilities.clone(),
+            _ => {
+                let capabilities = probe();
+                self.interfaces
+                    .insert(ifindex, (name.to_string(), capabilities.clone()));
+                capabilities
+            }
+        }
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Capabilities> {
+        self.interfaces
+            .values()
+            .find(|(known, _)| known == name)
+            .map(|(_, capabilities)| capabilities)
+    }
+
+    /// Forget interfaces that are gone.
+    pub fn retain(&mut self, present: &HashSet<u64>) {
+        self.interfaces
+            .retain(|ifindex, _| present.contains(ifindex));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_each_interface_once_until_it_changes() {
+        let mut cache = CapabilityCache::default();
+        let mut probes = 0;
+        let wifi = || Capabilities {
+            wireless: true,
+            phy: Some("phy0".to_string()),
+            hardware: None,
+            kind: LinkKind::Physical,
+        };
+
+        for _ in 0..3 {
+            let capabilities = cache.get_or_probe(3, "wlan0", || {
+                probes += 1;
+                wifi()
+            });
+            assert!(capabilities.wireless);
+        }
+        assert_eq!(probes, 1);
+        assert_eq!(cache.by_name("wlan0").unwrap().phy.as_deref(), Some("phy0"));
+
+        // Renamed by udev: same ifindex, new sysfs paths
+        cache.get_or_probe(3, "wlp2s0", || {
+            probes += 1;
+            wifi()
+        });
+        assert_eq!(probes, 2);
+
+        // Unplugged and plugged back in under a new ifindex
+        cache.retain(&HashSet::from([2]));
+        cache.get_or_probe(7, "wlp2s0", || {
+            probes += 1;
+            wifi()
+        });
+        assert_eq!(probes, 3);
+        assert_eq!(cache.interfaces.len(), 1);
+    }
+}
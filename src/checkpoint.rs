@@ -0,0 +1,281 @@
+// src/checkpoint.rs - Snapshots of networkd's config that roll back unless confirmed, as NetworkManager's checkpoints do
+use crate::command::TimedOutput;
+use crate::secure_file;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+pub const NETWORK_DIR: &str = "/etc/systemd/network";
+/// How long a checkpoint waits for confirmation when none is given
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+/// Longest allowed; past this, nobody is coming back to confirm
+pub const MAX_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+/// One file as it was: contents, mode and owner, since WireGuard netdevs
+/// are readable by systemd-network only
+#[derive(Debug, Clone, PartialEq)]
+struct SavedFile {
+    contents: Vec<u8>,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+}
+
+/// Every file under a config directory, drop-in directories included
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    files: BTreeMap<PathBuf, SavedFile>,
+}
+
+impl Snapshot {
+    pub fn take(dir: &Path) -> Result<Self> {
+        let mut snapshot = Self::default();
+        if dir.exists() {
+            snapshot.add_dir(dir)?;
+        }
+        Ok(snapshot)
+    }
+
+    fn add_dir(&mut self, dir: &Path) -> Result<()> {
+        for entry in
+            fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            let metadata = fs::symlink_metadata(&path)?;
+            if metadata.is_dir() {
+                self.add_dir(&path)?;
+            } else if metadata.is_file() {
+                let contents = fs::read(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                self.files.insert(
+                    path,
+                    SavedFile {
+                        contents,
+                        mode: metadata.mode() & 0o7777,
+                        uid: metadata.uid(),
+                        gid: metadata.gid(),
+                    },
+                );
+            }
+            // Symlinks (masking a file with /dev/null) are left as they are
+        }
+        Ok(())
+    }
+
+    /// Files that were added, changed or removed since
+    pub fn changed(&self, now: &Snapshot) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = self
+            .files
+            .iter()
+            .filter(|(path, saved)| now.files.get(*path) != Some(saved))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(
+            now.files
+                .keys()
+                .filter(|path| !self.files.contains_key(*path))
+                .cloned(),
+        );
+        changed.sort();
+        changed
+    }
+
+    /// Put `dir` back the way it was; the files that had to change
+    pub fn restore(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let changed = self.changed(&Snapshot::take(dir)?);
+        for path in &changed {
+            match self.files.get(path) {
+                Some(saved) => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    // Private first, so a secret is never readable on the way
+                    secure_file::write_private(path, &saved.contents)?;
+                    std::os::unix::fs::chown(path, Some(saved.uid), Some(saved.gid)).with_context(
+                        || format!("Failed to restore the owner of {}", path.display()),
+                    )?;
+                    fs::set_permissions(path, fs::Permissions::from_mode(saved.mode))?;
+                }
+                None => fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?,
+            }
+        }
+        Ok(changed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub id: u64,
+    pub created: Instant,
+    pub deadline: Instant,
+    snapshot: Snapshot,
+}
+
+impl Checkpoint {
+    pub fn describe(&self, now: Instant) -> String {
+        format!(
+            "checkpoint {}: rolls back in {}s unless confirmed",
+            self.id,
+            self.deadline.saturating_duration_since(now).as_secs()
+        )
+    }
+}
+
+/// The checkpoint in force. It covers all of networkd's config, so there is
+/// one at a time, as NetworkManager refuses overlapping ones.
+#[derive(Debug, Default)]
+pub struct Checkpoints {
+    current: Option<Checkpoint>,
+    last_id: u64,
+}
+
+fn timeout(secs: u64) -> Result<Duration> {
+    if !(1..=MAX_TIMEOUT_SECS).contains(&secs) {
+        bail!(
+            "Timeout must be 1-{} seconds, got {}",
+            MAX_TIMEOUT_SECS,
+            secs
+        );
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+impl Checkpoints {
+    pub fn current(&self) -> Option<&Checkpoint> {
+        self.current.as_ref()
+    }
+
+    pub fn create(&mut self, snapshot: Snapshot, secs: u64, now: Instant) -> Result<&Checkpoint> {
+        let timeout = timeout(secs)?;
+        if let Some(current) = &self.current {
+            bail!(
+                "Checkpoint {} is still open; confirm or roll it back first",
+                current.id
+            );
+        }
+        self.last_id += 1;
+        Ok(self.current.insert(Checkpoint {
+            id: self.last_id,
+            created: now,
+            deadline: now + timeout,
+            snapshot,
+        }))
+    }
+
+    fn take(&mut self, id: u64) -> Result<Checkpoint> {
+        match self.current.take() {
+            Some(checkpoint) if checkpoint.id == id => Ok(checkpoint),
+            other => {
+                self.current = other;
+                Err(anyhow!("No checkpoint {}", id))
+            }
+        }
+    }
+
+    /// Keep the changes made since: the checkpoint goes away
+    pub fn confirm(&mut self, id: u64) -> Result<()> {
+        self.take(id).map(|_| ())
+    }
+
+    /// The checkpoint to roll back now, on request
+    pub fn rollback(&mut self, id: u64) -> Result<Checkpoint> {
+        self.take(id)
+    }
+
+    /// Start the countdown over, as a keepalive from whoever is making changes
+    pub fn adjust(&mut self, id: u64, secs: u64, now: Instant) -> Result<Instant> {
+        let timeout = timeout(secs)?;
+        match &mut self.current {
+            Some(checkpoint) if checkpoint.id == id => {
+                checkpoint.deadline = now + timeout;
+                Ok(checkpoint.deadline)
+            }
+            _ => Err(anyhow!("No checkpoint {}", id)),
+        }
+    }
+
+    /// The checkpoint whose time ran out, to roll back
+    pub fn expired(&mut self, now: Instant) -> Option<Checkpoint> {
+        if self.current.as_ref()?.deadline > now {
+            return None;
+        }
+        self.current.take()
+    }
+}
+
+/// Put networkd's config back as it was at the checkpoint and reconfigure
+/// `interfaces` with it; the files that changed
+pub async fn roll_back(checkpoint: &Checkpoint, interfaces: &[String]) -> Result<Vec<PathBuf>> {
+    let changed = checkpoint.snapshot.restore(Path::new(NETWORK_DIR))?;
+    if changed.is_empty() {
+        return Ok(changed);
+    }
+    crate::systemd::reload_networkd().await?;
+    for interface in interfaces.iter().filter(|name| *name != "lo") {
+        Command::new("/usr/bin/networkctl")
+            .args(["reconfigure", interface])
+            .timed_output()
+            .await?;
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_files_and_expires_once() {
+        let dir = std::env::temp_dir().join(format!("lantern-checkpoint-{}", std::process::id()));
+        fs::create_dir_all(dir.join("10-eth0.network.d")).unwrap();
+        fs::write(dir.join("10-eth0.network"), "[Network]\nDHCP=yes\n").unwrap();
+        fs::write(dir.join("50-wg0.netdev"), "[WireGuard]\nPrivateKey=x\n").unwrap();
+        fs::set_permissions(dir.join("50-wg0.netdev"), fs::Permissions::from_mode(0o640)).unwrap();
+        let snapshot = Snapshot::take(&dir).unwrap();
+
+        fs::write(
+            dir.join("10-eth0.network"),
+            "[Network]\nAddress=10.0.0.2/24\n",
+        )
+        .unwrap();
+        fs::write(dir.join("10-eth0.network.d/60-lantern.conf"), "[Network]\n").unwrap();
+        fs::remove_file(dir.join("50-wg0.netdev")).unwrap();
+        let restored = snapshot.restore(&dir).unwrap();
+        assert_eq!(restored.len(), 3);
+        assert_eq!(
+            fs::read_to_string(dir.join("10-eth0.network")).unwrap(),
+            "[Network]\nDHCP=yes\n"
+        );
+        assert!(!dir.join("10-eth0.network.d/60-lantern.conf").exists());
+        let mode = fs::metadata(dir.join("50-wg0.netdev")).unwrap().mode() & 0o777;
+        assert_eq!(mode, 0o640);
+        assert!(snapshot.restore(&dir).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let start = Instant::now();
+        let mut checkpoints = Checkpoints::default();
+        let id = checkpoints.create(snapshot.clone(), 60, start).unwrap().id;
+        assert!(checkpoints.create(snapshot, 60, start).is_err()); // One at a time
+        assert!(checkpoints.adjust(id + 1, 60, start).is_err());
+        checkpoints
+            .adjust(id, 120, start + Duration::from_secs(50))
+            .unwrap();
+        assert!(checkpoints
+            .expired(start + Duration::from_secs(120))
+            .is_none());
+        assert_eq!(
+            checkpoints
+                .expired(start + Duration::from_secs(170))
+                .map(|checkpoint| checkpoint.id),
+            Some(id)
+        );
+        assert!(checkpoints.current().is_none());
+        assert!(checkpoints.confirm(id).is_err());
+        assert!(checkpoints.create(Snapshot::default(), 0, start).is_err());
+    }
+}
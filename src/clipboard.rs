@@ -0,0 +1,79 @@
+// src/clipboard.rs - Copy text to the clipboard through the terminal (OSC 52) or wl-copy/xclip
+use anyhow::{bail, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The terminal escape that sets the clipboard. Inside tmux it has to be
+/// wrapped to get past tmux to the outer terminal.
+pub fn osc52(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+/// A clipboard tool for the desktop session we run in, if there is one
+fn helper() -> Option<(&'static str, &'static [&'static str])> {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        Some(("wl-copy", &[]))
+    } else if std::env::var_os("DISPLAY").is_some() {
+        Some(("xclip", &["-selection", "clipboard"]))
+    } else {
+        None
+    }
+}
+
+fn run_helper(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    // wl-copy and xclip stay behind to serve the selection; only the
+    // foreground process is waited for
+    if !child.wait()?.success() {
+        bail!("{} failed", program);
+    }
+    Ok(())
+}
+
+/// Copy `text`; what it went through. OSC 52 works over SSH but terminals
+/// may ignore it, so a desktop helper is tried as well when there is one.
+/// A serial console gets no escape it would print as garbage.
+pub fn copy(text: &str) -> Result<&'static str> {
+    if let Some((program, args)) = helper() {
+        if run_helper(program, args, text).is_ok() {
+            return Ok(program);
+        }
+    }
+    if crate::console::is_minimal() {
+        bail!("No clipboard: no wl-copy or xclip, and the console can't take OSC 52");
+    }
+    let tmux = std::env::var_os("TMUX").is_some();
+    let mut stdout = std::io::stdout();
+    stdout.write_all(osc52(text, tmux).as_bytes())?;
+    stdout.flush()?;
+    Ok("the terminal")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_osc52_for_tmux() {
+        assert_eq!(osc52("10.0.0.1", false), "\x1b]52;c;MTAuMC4wLjE=\x07");
+        assert_eq!(
+            osc52("10.0.0.1", true),
+            "\x1bPtmux;\x1b\x1b]52;c;MTAuMC4wLjE=\x07\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+// src/command.rs - Timeouts for external commands
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::io;
+use std::process::Output;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::process::Command;
+
+static TIMEOUTS: Lazy<RwLock<CommandTimeouts>> = Lazy::new(Default::default);
+
+/// Where every caller runs iw from, beside ip and the other tools; the one
+/// place to change for a distribution that keeps it elsewhere
+pub const IW: &str = "/usr/bin/iw";
+
+/// Limits for external tools, configurable under `[timeouts]` in config.toml.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandTimeouts {
+    /// Most commands (ip, networkctl, systemctl, ...) finish well under a second
+    pub default_secs: u64,
+    /// WiFi scans and connection attempts wait on the radio
+    pub scan_secs: u64,
+}
+
+impl Default for CommandTimeouts {
+    fn default() -> Self {
+        Self {
+            default_secs: 10,
+            scan_secs: 30,
+        }
+    }
+}
+
+/// Set the timeouts from the loaded config, again whenever it is reloaded.
+pub fn configure(timeouts: &CommandTimeouts) {
+    *TIMEOUTS.write().unwrap_or_else(|e| e.into_inner()) = timeouts.clone();
+}
+
+fn timeouts() -> CommandTimeouts {
+    TIMEOUTS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn default_timeout() -> Duration {
+    Duration::from_secs(timeouts().default_secs)
+}
+
+pub fn scan_timeout() -> Duration {
+    Duration::from_secs(timeouts().scan_secs)
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Command '{program}' timed out after {}s", .timeout.as_secs())]
+pub struct CommandTimeout {
+    pub program: String,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("'{program}' is not installed")]
+pub struct MissingTool {
+    pub program: String,
+}
+
+/// True if any error in the chain is a command timeout, so callers can report
+/// "timed out" instead of a generic failure.
+pub fn is_timeout(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause.is::<CommandTimeout>()
+            || cause
+                .downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::TimedOut)
+    })
+}
+
+/// True if a command in the chain couldn't run because its program is missing
+pub fn is_missing_tool(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause.is::<MissingTool>()
+            || cause
+                .downcast_ref::<io::Error>()
+                .and_then(|e| e.get_ref())
+                .is_some_and(|inner| inner.is::<MissingTool>())
+    })
+}
+
+pub trait TimedOutput {
+    /// Like `output()`, but kills the process if it runs past the default timeout.
+    fn timed_output(&mut self) -> impl Future<Output = io::Result<Output>> + Send;
+
+    /// Like `output()`, but kills the process if it runs past `timeout`.
+    fn timed_output_with(
+        &mut self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<Output>> + Send;
+}
+
+impl TimedOutput for Command {
+    fn timed_output(&mut self) -> impl Future<Output = io::Result<Output>> + Send {
+        self.timed_output_with(default_timeout())
+    }
+
+    fn timed_output_with(
+        &mut self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<Output>> + Send {
+        let program = self
+            .as_std()
+            .get_program()
+            .to_string_lossy()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        // Dropping the output future on timeout drops the child, which kills it
+        let output = self.kill_on_drop(true).output();
+
+        async move {
+            match tokio::time::timeout(timeout, output).await {
+                // Spawning is the only way output() reports NotFound
+                Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    MissingTool { program },
+                )),
+                Ok(result) => result,
+                Err(_) => Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    CommandTimeout { program, timeout },
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hung_command_is_killed_and_reported() {
+        let err = Command::new("/bin/sleep")
+            .arg("5")
+            .timed_output_with(Duration::from_millis(100))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(is_timeout(&anyhow::Error::new(err)));
+    }
+
+    #[tokio::test]
+    async fn missing_program_is_named() {
+        let err = Command::new("/nonexistent/lantern-tool")
+            .timed_output()
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "'lantern-tool' is not installed");
+    }
+
+    #[tokio::test]
+    async fn fast_command_completes() {
+        let output = Command::new("/bin/true").timed_output().await.unwrap();
+        assert!(output.status.success());
+    }
+}
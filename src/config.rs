@@ -1,11 +1,38 @@
 // src/config.rs
-use crate::network::EnterpriseCredentials;
+use crate::alerts::AlertSettings;
+use crate::command::CommandTimeouts;
+use crate::containers::ContainerSettings;
+use crate::groups::AggregateGroup;
+use crate::hotspot::HotspotSettings;
+use crate::influx::InfluxSettings;
+use crate::mqtt::MqttSettings;
+use crate::network::{EnterpriseCredentials, VpnAutoConnect, WifiBand};
+use crate::pacing::RefreshSettings;
+use crate::pxe::PxeSettings;
+use crate::snmp::SnmpSettings;
+use crate::speedtest::SpeedTestSettings;
+use crate::survey::SurveySettings;
+use crate::usbshare::UsbShareSettings;
 use anyhow::Result;
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+static SANDBOX: OnceCell<PathBuf> = OnceCell::new();
+
+/// Keep the config, scan cache and operation lock under `dir` for the rest of
+/// the process, so a `--demo` session leaves the real ones alone. Only the
+/// first call has an effect.
+pub fn sandbox(dir: PathBuf) {
+    let _ = SANDBOX.set(dir);
+}
+
+pub fn sandbox_dir() -> Option<&'static Path> {
+    SANDBOX.get().map(PathBuf::as_path)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
     pub name: String,
@@ -14,6 +41,53 @@ pub struct Profile {
     pub ip: Option<String>,
     pub gateway: Option<String>,
     pub dns: Option<Vec<String>>,
+    #[serde(default)]
+    pub routes: Vec<ProfileRoute>,
+    #[serde(default)]
+    pub domains: Vec<String>, // Search domains; "~corp.example" routes lookups only
+}
+
+/// An extra route a profile brings along, as `[[profiles.routes]]` or
+/// `[[wifi_profiles.routes]]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileRoute {
+    pub destination: String, // e.g. "10.20.0.0/16"
+    #[serde(default)]
+    pub gateway: Option<String>, // On-link when unset
+    #[serde(default)]
+    pub metric: Option<u32>,
+}
+
+/// Routes and search domains written next to whatever addressing a
+/// profile uses
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkExtras {
+    pub routes: Vec<ProfileRoute>,
+    pub domains: Vec<String>,
+}
+
+impl NetworkExtras {
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty() && self.domains.is_empty()
+    }
+}
+
+impl Profile {
+    pub fn extras(&self) -> NetworkExtras {
+        NetworkExtras {
+            routes: self.routes.clone(),
+            domains: self.domains.clone(),
+        }
+    }
+
+    /// Profiles name an interface exactly, or act as a template for a family
+    /// of them with a trailing `*` (e.g. `enx*` for USB Ethernet dongles).
+    pub fn matches_interface(&self, name: &str) -> bool {
+        match self.interface.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => self.interface == name,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,12 +104,315 @@ pub struct WifiProfile {
     pub auto_connect: bool,
     pub priority: i32, // Higher number = higher priority
     pub enterprise: Option<EnterpriseCredentials>,
+    #[serde(default)]
+    pub band: WifiBand, // Keep to "2.4" or "5" GHz; "any" by default
+    #[serde(default)]
+    pub tx_power_dbm: Option<i32>, // Transmit power while connected; the driver's choice if unset
+    #[serde(default)]
+    pub routes: Vec<ProfileRoute>,
+    #[serde(default)]
+    pub domains: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl WifiProfile {
+    pub fn extras(&self) -> NetworkExtras {
+        NetworkExtras {
+            routes: self.routes.clone(),
+            domains: self.domains.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
     pub profiles: Vec<Profile>,
+    #[serde(default)]
     pub wifi_profiles: Vec<WifiProfile>,
+    #[serde(default)]
+    pub timeouts: CommandTimeouts,
+    #[serde(default)]
+    pub wireguard: WireGuardSettings,
+    #[serde(default)]
+    pub trusted: TrustedNetworks,
+    #[serde(default)]
+    pub hotplug: HotplugSettings,
+    #[serde(default)]
+    pub shutdown: ShutdownSettings,
+    #[serde(default)]
+    pub containers: ContainerSettings,
+    #[serde(default)]
+    pub refresh: RefreshSettings,
+    #[serde(default)]
+    pub groups: Vec<AggregateGroup>,
+    #[serde(default)]
+    pub display: DisplaySettings,
+    #[serde(default)]
+    pub hotspot: HotspotSettings,
+    #[serde(default)]
+    pub interfaces: InterfaceSettings,
+    #[serde(default)]
+    pub mqtt: MqttSettings,
+    #[serde(default)]
+    pub influx: InfluxSettings,
+    #[serde(default)]
+    pub snmp: SnmpSettings,
+    #[serde(default)]
+    pub pxe: PxeSettings,
+    #[serde(default)]
+    pub survey: SurveySettings,
+    #[serde(default)]
+    pub speedtest: SpeedTestSettings,
+    #[serde(default)]
+    pub alerts: AlertSettings,
+    #[serde(default)]
+    pub dhcp: DhcpSettings,
+    #[serde(default)]
+    pub usb_share: UsbShareSettings,
+}
+
+/// Interfaces lantern keeps its hands off, as `[interfaces]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterfaceSettings {
+    /// Names, or prefixes with a trailing `*` (e.g. `vnet*`), that are hidden,
+    /// never configured and only lightly refreshed
+    pub ignored: Vec<String>,
+}
+
+impl InterfaceSettings {
+    pub fn is_ignored(&self, name: &str) -> bool {
+        self.ignored
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => pattern == name,
+            })
+    }
+}
+
+/// How things are shown, as `[display]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    /// WiFi signal as "dbm", "percent" or "both"; `%` cycles it at runtime
+    pub signal: SignalUnit,
+    /// Sizes and rates in "binary" (MiB, 1024s) or "si" (MB, 1000s) units
+    pub units: UnitSystem,
+    /// Rates in "bytes" (MiB/s) or "bits" (Mbit/s) per second; `U` cycles
+    /// both at runtime
+    pub rates: RateUnit,
+    /// Times as the locale writes them ("locale"), or always "24h" or "12h"
+    pub clock: Clock,
+}
+
+impl DisplaySettings {
+    /// `U`'s order: MiB/s, MB/s, Mbit/s, Mibit/s
+    pub fn next_units(&mut self) {
+        (self.units, self.rates) = match (self.units, self.rates) {
+            (UnitSystem::Binary, RateUnit::Bytes) => (UnitSystem::Si, RateUnit::Bytes),
+            (UnitSystem::Si, RateUnit::Bytes) => (UnitSystem::Si, RateUnit::Bits),
+            (UnitSystem::Si, RateUnit::Bits) => (UnitSystem::Binary, RateUnit::Bits),
+            (UnitSystem::Binary, RateUnit::Bits) => (UnitSystem::Binary, RateUnit::Bytes),
+        };
+    }
+
+    /// For byte_unit's get_appropriate_unit, for sizes
+    pub fn unit_type(&self) -> byte_unit::UnitType {
+        match self.units {
+            UnitSystem::Binary => byte_unit::UnitType::Binary,
+            UnitSystem::Si => byte_unit::UnitType::Decimal,
+        }
+    }
+
+    /// A rate in the unit that suits it: "1.2 MiB/s", "1.3 MB/s", "10.5 Mbit/s"
+    pub fn rate(&self, bytes_per_sec: u64) -> String {
+        if self.rates == RateUnit::Bytes {
+            let rate =
+                byte_unit::Byte::from_u64(bytes_per_sec).get_appropriate_unit(self.unit_type());
+            return format!("{:.1}/s", rate);
+        }
+        let (step, prefixes) = match self.units {
+            UnitSystem::Binary => (1024.0, ["", "Ki", "Mi", "Gi", "Ti"]),
+            UnitSystem::Si => (1000.0, ["", "k", "M", "G", "T"]),
+        };
+        let mut bits = bytes_per_sec as f64 * 8.0;
+        let mut prefix = 0;
+        while bits >= step && prefix < prefixes.len() - 1 {
+            bits /= step;
+            prefix += 1;
+        }
+        format!("{:.1} {}bit/s", bits, prefixes[prefix])
+    }
+
+    /// Speed tests measure in Mbit/s; the factor to the mega unit shown
+    /// instead, and its name
+    pub fn mega_rate(&self) -> (f64, &'static str) {
+        let binary = 1_000_000.0 / 1_048_576.0;
+        match (self.units, self.rates) {
+            (UnitSystem::Si, RateUnit::Bits) => (1.0, "Mbit/s"),
+            (UnitSystem::Binary, RateUnit::Bits) => (binary, "Mibit/s"),
+            (UnitSystem::Si, RateUnit::Bytes) => (1.0 / 8.0, "MB/s"),
+            (UnitSystem::Binary, RateUnit::Bytes) => (binary / 8.0, "MiB/s"),
+        }
+    }
+
+    /// A speed test's Mbit/s in the unit shown
+    pub fn mbps(&self, mbps: f64) -> String {
+        let (factor, unit) = self.mega_rate();
+        format!("{:.1} {}", mbps * factor, unit)
+    }
+
+    /// The system locale, with the clock overridden if the config says so
+    pub fn locale(&self) -> crate::locale::Locale {
+        let mut locale = crate::locale::Locale::system();
+        match self.clock {
+            Clock::Locale => {}
+            Clock::TwentyFour => locale.twelve_hour = false,
+            Clock::Twelve => locale.twelve_hour = true,
+        }
+        locale
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Clock {
+    #[default]
+    #[serde(rename = "locale")]
+    Locale,
+    #[serde(rename = "24h")]
+    TwentyFour,
+    #[serde(rename = "12h")]
+    Twelve,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    #[default]
+    Binary,
+    Si,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateUnit {
+    #[default]
+    Bytes,
+    Bits,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalUnit {
+    #[default]
+    Dbm,
+    Percent,
+    Both,
+}
+
+impl SignalUnit {
+    pub fn next(self) -> Self {
+        match self {
+            SignalUnit::Dbm => SignalUnit::Percent,
+            SignalUnit::Percent => SignalUnit::Both,
+            SignalUnit::Both => SignalUnit::Dbm,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SignalUnit::Dbm => "dBm",
+            SignalUnit::Percent => "percentage",
+            SignalUnit::Both => "percentage and dBm",
+        }
+    }
+
+    /// "-52dBm", "63%" or "63% -52dBm"
+    pub fn format(self, dbm: i32) -> String {
+        let percent = crate::network::signal_percent(dbm);
+        match self {
+            SignalUnit::Dbm => format!("{}dBm", dbm),
+            SignalUnit::Percent => format!("{}%", percent),
+            SignalUnit::Both => format!("{}% {}dBm", percent, dbm),
+        }
+    }
+}
+
+/// What quitting does besides leaving, as `[shutdown]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShutdownSettings {
+    /// How long to wait for background changes before cancelling them
+    pub wait_secs: u64,
+    /// Take down a hotspot started from this session
+    pub stop_hotspot: bool,
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        Self {
+            wait_secs: 10,
+            stop_hotspot: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotplugSettings {
+    /// Apply a matching profile to a plugged-in NIC without asking
+    pub auto_apply: bool,
+}
+
+/// The rogue DHCP probe, as `[dhcp]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DhcpSettings {
+    /// Server identifiers allowed to answer; any other that offers is
+    /// flagged. Empty trusts the server of the current lease.
+    pub servers: Vec<String>,
+}
+
+/// Networks on which the VPN and firewall may relax, as `[trusted]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrustedNetworks {
+    pub ssids: Vec<String>,
+    /// Gateway IP or MAC addresses; a MAC also tells apart two networks
+    /// that both use 192.168.1.1
+    pub gateways: Vec<String>,
+    /// Drop unsolicited inbound traffic on untrusted networks
+    pub firewall: bool,
+    /// Block outbound traffic on untrusted networks except to WireGuard peers
+    pub kill_switch: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WireGuardSettings {
+    /// Ping each peer's tunnel address to show round-trip times
+    pub probe_latency: bool,
+    /// How often to re-resolve hostname endpoints of idle peers; 0 disables
+    pub reresolve_endpoints_secs: u64,
+    pub tunnels: Vec<VpnPolicy>,
+}
+
+/// Auto-connect policy for one WireGuard interface, as `[[wireguard.tunnels]]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VpnPolicy {
+    pub interface: String,
+    pub auto_connect: VpnAutoConnect,
+}
+
+impl Default for WireGuardSettings {
+    fn default() -> Self {
+        Self {
+            probe_latency: false,
+            reresolve_endpoints_secs: 120,
+            tunnels: Vec::new(),
+        }
+    }
 }
 
 impl Config {
@@ -44,16 +421,16 @@ impl Config {
         let config_path = Self::config_path()?;
 
         if config_path.exists() {
-            let content = fs::read_to_string(config_path)?;
-            Ok(toml::from_str(&content)?)
+            Self::parse(&fs::read_to_string(config_path)?)
         } else {
-            Ok(Self {
-                profiles: Vec::new(),
-                wifi_profiles: Vec::new(),
-            })
+            Ok(Self::default())
         }
     }
 
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
     #[allow(dead_code)]
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_path()?;
@@ -62,14 +439,17 @@ impl Config {
             fs::create_dir_all(parent)?;
         }
 
-        let content = toml::to_string_pretty(self)?;
-        fs::write(config_path, content)?;
+        let content = crate::provenance::stamp(&toml::to_string_pretty(self)?, "Lantern settings");
+        // Saved WiFi passwords live here, keep it owner-only
+        crate::secure_file::write_private(config_path, content)?;
 
         Ok(())
     }
 
-    #[allow(dead_code)]
-    fn config_path() -> Result<PathBuf> {
+    pub fn config_path() -> Result<PathBuf> {
+        if let Some(dir) = sandbox_dir() {
+            return Ok(dir.join("config.toml"));
+        }
         let config_dir =
             dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
         Ok(config_dir.join("lantern").join("config.toml"))
@@ -120,6 +500,17 @@ impl Config {
         profiles
     }
 
+    /// Set a tunnel's auto-connect policy; `Never` just forgets the tunnel.
+    pub fn set_vpn_policy(&mut self, interface: &str, auto_connect: VpnAutoConnect) {
+        self.wireguard.tunnels.retain(|t| t.interface != interface);
+        if auto_connect != VpnAutoConnect::Never {
+            self.wireguard.tunnels.push(VpnPolicy {
+                interface: interface.to_string(),
+                auto_connect,
+            });
+        }
+    }
+
     pub fn update_wifi_connection(&mut self, ssid: &str, interface: &str) {
         if let Some(profile) = self
             .wifi_profiles
@@ -130,3 +521,31 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_rates_in_the_chosen_units() {
+        let mut display = DisplaySettings::default();
+        assert_eq!(display.rate(1_310_720), "1.2 MiB/s");
+        display.next_units();
+        assert_eq!(display.rate(1_310_720), "1.3 MB/s");
+        display.next_units();
+        assert_eq!(display.rate(1_310_720), "10.5 Mbit/s");
+        assert_eq!(display.rate(100), "800.0 bit/s");
+        assert_eq!(display.mbps(94.2), "94.2 Mbit/s");
+        display.next_units();
+        assert_eq!(display.rate(1_310_720), "10.0 Mibit/s");
+        display.next_units();
+        assert_eq!(
+            (display.units, display.rates),
+            (UnitSystem::Binary, RateUnit::Bytes)
+        );
+        assert_eq!(display.mbps(80.0), "9.5 MiB/s");
+
+        let config = Config::parse("[display]\nunits = \"si\"\nrates = \"bits\"\n").unwrap();
+        assert_eq!(config.display.mega_rate().1, "Mbit/s");
+    }
+}
@@ -0,0 +1,110 @@
+// src/console.rs - A reduced TUI profile for serial consoles, IPMI SOL and other limited terminals
+use ratatui::buffer::Buffer;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static MINIMAL: AtomicBool = AtomicBool::new(false);
+
+/// How much the terminal can be asked to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Full,
+    /// No alternate screen or mouse, ASCII only, fewer frames
+    Minimal,
+}
+
+impl Profile {
+    /// Minimal when asked for, or when TERM names a terminal that can't do
+    /// better: what agetty sets on a serial line, and what IPMI SOL reports.
+    pub fn detect(forced: bool) -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        Self::resolve(forced, &term)
+    }
+
+    fn resolve(forced: bool, term: &str) -> Self {
+        let limited = term.is_empty()
+            || term == "dumb"
+            || term == "ansi"
+            || term.starts_with("vt")
+            || term.starts_with("serial");
+        if forced || limited {
+            Profile::Minimal
+        } else {
+            Profile::Full
+        }
+    }
+}
+
+/// Set the profile before the terminal is set up; restoring it reads this too,
+/// including from the panic hook.
+pub fn configure(profile: Profile) {
+    MINIMAL.store(profile == Profile::Minimal, Ordering::SeqCst);
+}
+
+pub fn is_minimal() -> bool {
+    MINIMAL.load(Ordering::SeqCst)
+}
+
+/// Replace everything outside ASCII in a rendered frame: box drawing becomes
+/// `+-|`, bars and braille `#`, Nerd Font glyphs `*`.
+pub fn to_ascii(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        if let Some(ascii) = ascii_for(cell.symbol()) {
+            cell.set_char(ascii);
+        }
+    }
+}
+
+/// None when the symbol is already ASCII
+fn ascii_for(symbol: &str) -> Option<char> {
+    let c = symbol.chars().next()?;
+    if symbol.is_ascii() {
+        return None;
+    }
+    Some(match c {
+        '─' | '━' | '═' | '╌' | '┄' => '-',
+        '│' | '┃' | '║' | '╎' | '┆' => '|',
+        '\u{2500}'..='\u{257f}' => '+', // Corners and junctions
+        '\u{2800}' => ' ',              // Empty braille
+        '\u{2580}'..='\u{259f}' | '\u{2801}'..='\u{28ff}' => '#', // Bars, charts
+        '→' | '▶' | '►' => '>',
+        '←' | '◀' | '◄' => '<',
+        '↑' => '^',
+        '↓' => 'v',
+        '•' | '·' => '*',
+        '…' => '~',
+        '\u{e000}'..='\u{f8ff}' => '*', // Nerd Font glyphs
+        _ => '?',
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{
+        layout::Rect,
+        widgets::{Block, Borders, Widget},
+    };
+
+    #[test]
+    fn limited_terminals_get_the_minimal_profile() {
+        assert_eq!(Profile::resolve(false, "vt100"), Profile::Minimal);
+        assert_eq!(Profile::resolve(false, "vt220"), Profile::Minimal);
+        assert_eq!(Profile::resolve(false, "dumb"), Profile::Minimal);
+        assert_eq!(Profile::resolve(false, ""), Profile::Minimal);
+        assert_eq!(Profile::resolve(false, "xterm-256color"), Profile::Full);
+        assert_eq!(Profile::resolve(false, "linux"), Profile::Full);
+        assert_eq!(Profile::resolve(true, "xterm-256color"), Profile::Minimal);
+    }
+
+    #[test]
+    fn borders_and_glyphs_become_ascii() {
+        let area = Rect::new(0, 0, 6, 3);
+        let mut buffer = Buffer::empty(area);
+        Block::default()
+            .borders(Borders::ALL)
+            .title("\u{f1eb} w")
+            .render(area, &mut buffer);
+        to_ascii(&mut buffer);
+        assert_eq!(buffer, Buffer::with_lines(["+* w-+", "|    |", "+----+"]));
+    }
+}
@@ -0,0 +1,240 @@
+// src/containers.rs - Which Docker/Podman container sits behind each host veth
+use crate::command::TimedOutput;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::process::Command;
+
+// Docker-compatible API sockets; Podman serves the same API
+const API_SOCKETS: &[&str] = &["/var/run/docker.sock", "/run/podman/podman.sock"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerSettings {
+    /// Name the container behind each veth in the interface list
+    pub enabled: bool,
+}
+
+/// The container at the far end of a host-side veth
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerLink {
+    pub name: String, // From the runtime's API, else the short container ID
+    pub runtime: &'static str,
+    pub addresses: Vec<String>, // Inside the container, with prefix
+}
+
+/// The runtime and full container ID a process's /proc/<pid>/cgroup puts it in.
+/// Covers systemd-managed (`docker-<id>.scope`, `libpod-<id>.scope`) and
+/// cgroupfs (`/docker/<id>`) layouts.
+pub fn parse_cgroup(cgroup: &str) -> Option<(&'static str, String)> {
+    for line in cgroup.lines() {
+        let path = line.rsplit(':').next().unwrap_or_default();
+        for segment in path.split('/').rev() {
+            let segment = segment.strip_suffix(".scope").unwrap_or(segment);
+            let found = if let Some(id) = segment.strip_prefix("docker-") {
+                Some(("docker", id))
+            } else if let Some(id) = segment.strip_prefix("libpod-") {
+                Some(("podman", id))
+            } else if path.contains("/docker/") {
+                Some(("docker", segment))
+            } else {
+                None
+            };
+            if let Some((runtime, id)) = found {
+                if id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Some((runtime, id.to_string()));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Container IDs to names from a `GET /containers/json` response body.
+pub fn parse_container_names(body: &str) -> HashMap<String, String> {
+    let Ok(Value::Array(containers)) = serde_json::from_str::<Value>(body) else {
+        return HashMap::new();
+    };
+    containers
+        .iter()
+        .filter_map(|container| {
+            let id = container["Id"].as_str()?;
+            let name = container["Names"][0].as_str()?.trim_start_matches('/');
+            Some((id.to_string(), name.to_string()))
+        })
+        .collect()
+}
+
+/// The host-side ifindex each veth inside a container pairs with, and the
+/// addresses on it, from `ip -j addr` run in the container's namespace.
+pub fn parse_container_links(ip_json: &str) -> Vec<(u32, Vec<String>)> {
+    let Ok(Value::Array(links)) = serde_json::from_str::<Value>(ip_json) else {
+        return Vec::new();
+    };
+    links
+        .iter()
+        .filter_map(|link| {
+            let peer = u32::try_from(link["link_index"].as_u64()?).ok()?;
+            let addresses = link["addr_info"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|addr| addr["scope"] != "link")
+                .filter_map(|addr| {
+                    Some(format!(
+                        "{}/{}",
+                        addr["local"].as_str()?,
+                        addr["prefixlen"].as_u64()?
+                    ))
+                })
+                .collect();
+            Some((peer, addresses))
+        })
+        .collect()
+}
+
+/// One process per running container, found by walking /proc.
+fn find_containers() -> HashMap<String, (&'static str, u32)> {
+    let mut containers = HashMap::new();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return containers;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(cgroup) = std::fs::read_to_string(entry.path().join("cgroup")) else {
+            continue;
+        };
+        if let Some((runtime, id)) = parse_cgroup(&cgroup) {
+            containers.entry(id).or_insert((runtime, pid));
+        }
+    }
+    containers
+}
+
+/// Names from whichever runtime API answers; an empty map without one.
+async fn container_names() -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    for socket in API_SOCKETS.iter().filter(|s| Path::new(s).exists()) {
+        let request = async {
+            let mut stream = UnixStream::connect(socket).await?;
+            stream
+                .write_all(b"GET /containers/json HTTP/1.0\r\nHost: localhost\r\n\r\n")
+                .await?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await?;
+            Ok::<_, std::io::Error>(response)
+        };
+        if let Ok(Ok(response)) = tokio::time::timeout(Duration::from_secs(2), request).await {
+            if let Some((_, body)) = response.split_once("\r\n\r\n") {
+                names.extend(parse_container_names(body));
+            }
+        }
+    }
+    names
+}
+
+fn host_ifindex_names() -> HashMap<u32, String> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/net") else {
+        return HashMap::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let index = std::fs::read_to_string(entry.path().join("ifindex")).ok()?;
+            Some((
+                index.trim().parse().ok()?,
+                entry.file_name().into_string().ok()?,
+            ))
+        })
+        .collect()
+}
+
+/// Host veth name to the container on its other end, for every running
+/// container lantern can see.
+pub async fn map_veths() -> HashMap<String, ContainerLink> {
+    let containers = find_containers();
+    if containers.is_empty() {
+        return HashMap::new();
+    }
+    let names = container_names().await;
+    let host_names = host_ifindex_names();
+
+    let mut links = HashMap::new();
+    for (id, (runtime, pid)) in containers {
+        let output = Command::new("/usr/bin/nsenter")
+            .arg(format!("--net=/proc/{}/ns/net", pid))
+            .args(["/usr/bin/ip", "-j", "addr", "show"])
+            .timed_output()
+            .await;
+        let Some(output) = output.ok().filter(|o| o.status.success()) else {
+            continue;
+        };
+        let name = names
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| id[..12].to_string());
+        for (peer, addresses) in parse_container_links(&String::from_utf8_lossy(&output.stdout)) {
+            if let Some(veth) = host_names.get(&peer) {
+                links.insert(
+                    veth.clone(),
+                    ContainerLink {
+                        name: name.clone(),
+                        runtime,
+                        addresses,
+                    },
+                );
+            }
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "4f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0";
+
+    #[test]
+    fn finds_containers_and_their_veths() {
+        let systemd = format!("0::/system.slice/docker-{}.scope\n", ID);
+        assert_eq!(parse_cgroup(&systemd), Some(("docker", ID.to_string())));
+        let podman = format!("0::/machine.slice/libpod-{}.scope/container\n", ID);
+        assert_eq!(parse_cgroup(&podman), Some(("podman", ID.to_string())));
+        let cgroupfs = format!("12:pids:/docker/{}\n0::/docker/{}\n", ID, ID);
+        assert_eq!(parse_cgroup(&cgroupfs), Some(("docker", ID.to_string())));
+        assert_eq!(
+            parse_cgroup("0::/user.slice/user-1000.slice/session-2.scope\n"),
+            None
+        );
+        assert_eq!(parse_cgroup("0::/system.slice/docker-abc.scope\n"), None);
+
+        let names = parse_container_names(&format!(
+            r#"[{{"Id":"{}","Names":["/web-1"]}},{{"Id":"x"}}]"#,
+            ID
+        ));
+        assert_eq!(names.get(ID).map(String::as_str), Some("web-1"));
+        assert_eq!(names.len(), 1);
+
+        let ip = r#"[
+            {"ifindex":1,"ifname":"lo","addr_info":[{"family":"inet","local":"127.0.0.1","prefixlen":8,"scope":"host"}]},
+            {"ifindex":2,"ifname":"eth0","link_index":7,"link_netnsid":0,"addr_info":[
+                {"family":"inet","local":"172.17.0.2","prefixlen":16,"scope":"global"},
+                {"family":"inet6","local":"fe80::42:acff:fe11:2","prefixlen":64,"scope":"link"}]}
+        ]"#;
+        assert_eq!(
+            parse_container_links(ip),
+            vec![(7, vec!["172.17.0.2/16".to_string()])]
+        );
+    }
+}
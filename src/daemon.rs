@@ -0,0 +1,538 @@
+// src/daemon.rs - Headless background engine for systemd: sd_notify, watchdog and control socket
+use crate::app::{App, AppEvent};
+use crate::checkpoint::{Checkpoints, Snapshot};
+use crate::command::TimedOutput;
+use crate::provenance;
+use crate::secure_file;
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use std::ffi::OsString;
+use std::os::fd::FromRawFd;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::process::Command;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc;
+
+pub const CONTROL_SOCKET: &str = "/run/lantern.sock";
+pub const UNIT_DIR: &str = "/etc/systemd/system";
+
+// First descriptor systemd passes with socket activation (SD_LISTEN_FDS_START)
+const LISTEN_FDS_START: i32 = 3;
+
+// Taken out of the environment before anything is spawned: systemd tools
+// such as networkctl report to $NOTIFY_SOCKET too, as if they were the service
+static NOTIFY_SOCKET: Lazy<Option<OsString>> = Lazy::new(|| {
+    let path = std::env::var_os("NOTIFY_SOCKET");
+    std::env::remove_var("NOTIFY_SOCKET");
+    path
+});
+
+/// Send a state change ("READY=1", "WATCHDOG=1", ...) to the service manager.
+/// Does nothing when not started by systemd as Type=notify.
+pub fn notify(state: &str) {
+    let Some(path) = NOTIFY_SOCKET.as_ref() else {
+        return;
+    };
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return;
+    };
+    // A leading '@' names a socket in the abstract namespace
+    let _ = match path.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)
+            .and_then(|addr| socket.send_to_addr(state.as_bytes(), &addr)),
+        None => socket.send_to(state.as_bytes(), path),
+    };
+}
+
+/// How often to pet the watchdog: half of WatchdogSec=, as sd_watchdog_enabled
+/// recommends. None when the watchdog is off or meant for another process.
+fn watchdog_interval(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    if pid.is_some_and(|pid| pid.parse() != Ok(own_pid)) {
+        return None;
+    }
+    let usec: u64 = usec?.parse().ok().filter(|usec| *usec > 0)?;
+    Some(Duration::from_micros(usec / 2))
+}
+
+/// The control socket: handed over by systemd with socket activation, or
+/// bound here when started by hand.
+fn control_listener() -> Result<UnixListener> {
+    let var = |name| std::env::var(name).ok();
+    let activated = var("LISTEN_PID").and_then(|pid| pid.parse().ok()) == Some(std::process::id())
+        && var("LISTEN_FDS").and_then(|fds| fds.parse::<u32>().ok()) >= Some(1);
+
+    let listener = if activated {
+        // Not meant for anything lantern starts
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+        std::env::remove_var("LISTEN_FDNAMES");
+        // SAFETY: with LISTEN_PID naming this process, systemd passed the
+        // listening socket as fd 3 and nothing else owns it
+        unsafe { std::os::unix::net::UnixListener::from_raw_fd(LISTEN_FDS_START) }
+    } else {
+        let _ = std::fs::remove_file(CONTROL_SOCKET); // Left behind by an earlier run
+        let listener = std::os::unix::net::UnixListener::bind(CONTROL_SOCKET)
+            .with_context(|| format!("Failed to bind {}", CONTROL_SOCKET))?;
+        std::fs::set_permissions(CONTROL_SOCKET, std::fs::Permissions::from_mode(0o600))?;
+        listener
+    };
+    listener.set_nonblocking(true)?;
+    Ok(UnixListener::from_std(listener)?)
+}
+
+/// Run the engine without a UI: refresh interfaces, apply hotplug profiles,
+/// auto-connect, enforce trusted-network policies and re-resolve WireGuard
+/// endpoints, as the TUI does while it is open. Runs until SIGTERM; SIGHUP
+/// reloads config.toml.
+pub async fn run() -> Result<()> {
+    Lazy::force(&NOTIFY_SOCKET);
+    let listener = control_listener()?;
+    let mut app = App::new(false).await?;
+    let mut checkpoints = Checkpoints::default();
+    let (tx, mut rx) = mpsc::unbounded_channel::<AppEvent>();
+
+    let link_tx = tx.clone();
+    std::thread::spawn(move || {
+        let _ = crate::netlink::watch_links(|| link_tx.send(AppEvent::LinkChanged).is_ok());
+    });
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sighup = signal(SignalKind::hangup())?;
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    let watchdog = watchdog_interval(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    );
+    let mut watchdog_tick = tokio::time::interval(watchdog.unwrap_or(Duration::from_secs(3600)));
+
+    notify(&format!(
+        "READY=1\nSTATUS=Watching {} interfaces",
+        app.interfaces.len()
+    ));
+
+    loop {
+        tokio::select! {
+            _ = sigterm.recv() => break,
+            _ = sighup.recv() => {
+                app.reload_config();
+                notify(&format!("STATUS={}", status_text(&app)));
+            }
+            Some(event) = rx.recv() => app.apply(event),
+            Ok((stream, _)) = listener.accept() => {
+                if let Err(e) = serve(&mut app, &mut checkpoints, stream).await {
+                    eprintln!("Control connection failed: {:#}", e);
+                }
+            }
+            _ = tick.tick() => {
+                roll_back_expired(&mut app, &mut checkpoints).await;
+                start_due_jobs(&mut app, &tx).await;
+            }
+            // Petted from the same loop, so a wedged engine gets restarted
+            _ = watchdog_tick.tick(), if watchdog.is_some() => notify("WATCHDOG=1"),
+        }
+    }
+
+    notify("STOPPING=1");
+    Ok(())
+}
+
+fn status_text(app: &App) -> String {
+    app.status_message
+        .as_ref()
+        .map(|(message, _)| message.clone())
+        .unwrap_or_default()
+}
+
+async fn start_due_jobs(app: &mut App, tx: &mpsc::UnboundedSender<AppEvent>) {
+    // Nobody is there to answer a hotplug prompt
+    match &app.hotplug_offer {
+        Some(offer) if offer.auto => {
+            app.accept_hotplug_offer();
+        }
+        Some(_) => app.dismiss_hotplug_offer(),
+        None => {}
+    }
+
+    if let Some((id, job)) = app.jobs.next_due() {
+        let tx = tx.clone();
+        let network_manager = app.network_manager.clone();
+        let systemd_config = app.systemd_config.clone();
+        let handle = tokio::spawn(async move {
+            let result = job.run(network_manager, systemd_config).await;
+            let _ = tx.send(AppEvent::JobFinished(id, result));
+        });
+        app.jobs.started(id, handle.abort_handle());
+    }
+
+    if app.should_refresh_interfaces() {
+        let tx = tx.clone();
+        let network_manager = app.network_manager.clone();
+        tokio::spawn(async move {
+            if let Ok(interfaces) = network_manager.get_interfaces().await {
+                let _ = tx.send(AppEvent::Interfaces(interfaces));
+            }
+        });
+        app.mark_interface_refresh_started();
+    }
+
+    // Signal strength is only wanted for MQTT, InfluxDB and SNMP here
+    let telemetry = app.config.mqtt.enabled || app.config.influx.enabled || app.config.snmp.enabled;
+    if telemetry && app.should_update_wifi_info() {
+        let tx = tx.clone();
+        let job = app.wifi_info_job();
+        tokio::spawn(async move {
+            if let Some(event) = job.run().await {
+                let _ = tx.send(event);
+            }
+        });
+        app.mark_wifi_update_started();
+    }
+
+    if app.mqtt_publish_due() {
+        app.publish_telemetry();
+    }
+
+    if app.influx_push_due() {
+        app.push_metrics();
+    }
+
+    if app.snmp_refresh_due() {
+        if let Some(job) = app.snmp_job() {
+            tokio::spawn(job.run());
+        }
+        app.mark_snmp_refresh_started();
+    }
+
+    if app.should_check_auto_connect() {
+        let tx = tx.clone();
+        let job = app.auto_connect_job();
+        tokio::spawn(async move {
+            if let Some(event) = job.run().await {
+                let _ = tx.send(event);
+            }
+        });
+        app.mark_auto_connect_check_started();
+    }
+
+    if app.should_check_network_policies() {
+        let tx = tx.clone();
+        let job = app.network_policy_job();
+        tokio::spawn(async move {
+            for event in job.run().await {
+                let _ = tx.send(event);
+            }
+        });
+        app.mark_policy_check_started();
+    }
+
+    if app.scheduled_speed_test_due() {
+        let tx = tx.clone();
+        let network_manager = app.network_manager.clone();
+        let settings = app.config.speedtest.clone();
+        tokio::spawn(async move {
+            let result = network_manager.speed_test(&settings).await;
+            let _ = tx.send(AppEvent::SpeedTest(result));
+        });
+        app.mark_speed_test_started();
+    }
+
+    if app.wan_check_due() {
+        let tx = tx.clone();
+        let network_manager = app.network_manager.clone();
+        tokio::spawn(async move {
+            let up = network_manager
+                .check_internet_connectivity()
+                .await
+                .unwrap_or(false);
+            let _ = tx.send(AppEvent::WanChecked(up));
+        });
+        app.mark_wan_check_started();
+    }
+
+    for alert in app.take_alerts() {
+        let channels = app.config.alerts.channels.clone();
+        tokio::spawn(async move {
+            for error in crate::alerts::send_all(&channels, &alert).await {
+                eprintln!("Alert \"{}\" not sent through {}", alert.title, error);
+            }
+        });
+    }
+
+    if app.should_reresolve_endpoints() {
+        let tx = tx.clone();
+        let network_manager = app.network_manager.clone();
+        let endpoints = app.systemd_config.wireguard_hostname_endpoints();
+        if !endpoints.is_empty() {
+            tokio::spawn(async move {
+                let changes = network_manager
+                    .reresolve_wireguard_endpoints(endpoints)
+                    .await;
+                if !changes.is_empty() {
+                    let _ = tx.send(AppEvent::EndpointsReresolved(changes));
+                }
+            });
+        }
+        app.mark_endpoint_resolve_started();
+    }
+}
+
+/// Nobody confirmed in time: whoever made the changes may have cut
+/// themselves off with them
+async fn roll_back_expired(app: &mut App, checkpoints: &mut Checkpoints) {
+    let due = checkpoints
+        .current()
+        .is_some_and(|checkpoint| checkpoint.deadline <= Instant::now());
+    if !due {
+        return;
+    }
+    // A change being written is let finish first; the next tick tries again
+    let Ok(_lock) = crate::lock::OperationLock::try_acquire() else {
+        return;
+    };
+    let Some(checkpoint) = checkpoints.expired(Instant::now()) else {
+        return;
+    };
+    let message = match roll_back(app, &checkpoint).await {
+        Ok(message) => format!("Checkpoint {} expired: {}", checkpoint.id, message),
+        Err(e) => format!(
+            "Checkpoint {} expired, rollback failed: {:#}",
+            checkpoint.id, e
+        ),
+    };
+    eprintln!("{}", message);
+    notify(&format!("STATUS={}", message));
+    app.status_message = Some((message, Instant::now()));
+}
+
+async fn roll_back(app: &mut App, checkpoint: &crate::checkpoint::Checkpoint) -> Result<String> {
+    let interfaces: Vec<String> = app
+        .interfaces
+        .iter()
+        .map(|iface| iface.name.clone())
+        .collect();
+    let changed = crate::checkpoint::roll_back(checkpoint, &interfaces).await?;
+    app.interface_refresh_requested = true;
+    Ok(match changed.len() {
+        0 => "nothing had changed".to_string(),
+        1 => "rolled back 1 file".to_string(),
+        files => format!("rolled back {} files", files),
+    })
+}
+
+/// `checkpoint create [SECS]`, `confirm ID`, `rollback ID`, `adjust ID SECS`
+/// or nothing for the one open
+async fn checkpoint_command(
+    app: &mut App,
+    checkpoints: &mut Checkpoints,
+    args: &[&str],
+) -> Result<String> {
+    let id = |index: usize| -> Result<u64> {
+        let id = args.get(index).context("Which checkpoint?")?;
+        id.parse()
+            .with_context(|| format!("Bad checkpoint id {:?}", id))
+    };
+    let secs = |index: usize, default: Option<u64>| -> Result<u64> {
+        match (args.get(index), default) {
+            (Some(secs), _) => secs
+                .parse()
+                .with_context(|| format!("Bad timeout {:?}", secs)),
+            (None, Some(default)) => Ok(default),
+            (None, None) => anyhow::bail!("How many seconds?"),
+        }
+    };
+    let now = Instant::now();
+    match args.first().copied() {
+        None | Some("list") => Ok(match checkpoints.current() {
+            Some(checkpoint) => checkpoint.describe(now),
+            None => "No checkpoint open".to_string(),
+        }),
+        Some("create") => {
+            let secs = secs(1, Some(crate::checkpoint::DEFAULT_TIMEOUT_SECS))?;
+            // Not halfway through another change's files
+            let _lock = crate::lock::OperationLock::try_acquire()?;
+            let snapshot = Snapshot::take(std::path::Path::new(crate::checkpoint::NETWORK_DIR))?;
+            let checkpoint = checkpoints.create(snapshot, secs, now)?;
+            Ok(format!("Created {}", checkpoint.describe(now)))
+        }
+        Some("confirm") => {
+            let id = id(1)?;
+            checkpoints.confirm(id)?;
+            Ok(format!("Checkpoint {} confirmed; the changes stay", id))
+        }
+        Some("rollback") => {
+            let id = id(1)?;
+            let _lock = crate::lock::OperationLock::try_acquire()?;
+            let checkpoint = checkpoints.rollback(id)?;
+            let message = roll_back(app, &checkpoint).await?;
+            Ok(format!("Checkpoint {}: {}", id, message))
+        }
+        Some("adjust") => {
+            let (id, secs) = (id(1)?, secs(2, None)?);
+            checkpoints.adjust(id, secs, now)?;
+            Ok(format!("Checkpoint {} now rolls back in {}s", id, secs))
+        }
+        Some(other) => anyhow::bail!(
+            "Unknown checkpoint command '{}'; try create, confirm, rollback, adjust or list",
+            other
+        ),
+    }
+}
+
+/// Send one command to the daemon and return its reply
+pub async fn request(command: &str) -> Result<String> {
+    let mut stream = UnixStream::connect(CONTROL_SOCKET).await.with_context(|| {
+        format!(
+            "Is lantern running as a daemon? Failed to connect to {}",
+            CONTROL_SOCKET
+        )
+    })?;
+    stream.write_all(command.as_bytes()).await?;
+    stream.write_all(b"\n").await?;
+    let mut reply = String::new();
+    tokio::io::AsyncReadExt::read_to_string(&mut stream, &mut reply).await?;
+    Ok(reply.trim_end().to_string())
+}
+
+/// Answer one command per connection: `status` (interfaces as JSON),
+/// `reload` (re-read config.toml), `checkpoint ...` or `ping`. Failed
+/// commands reply with a line starting "Error: ".
+async fn serve(app: &mut App, checkpoints: &mut Checkpoints, stream: UnixStream) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut command = String::new();
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        BufReader::new(reader).read_line(&mut command),
+    )
+    .await
+    .context("Timed out waiting for a command")??;
+
+    let reply = match command.trim() {
+        "status" => serde_json::to_string_pretty(&app.interfaces)?,
+        "reload" => {
+            app.reload_config();
+            status_text(app)
+        }
+        "ping" => "pong".to_string(),
+        command if command.split_whitespace().next() == Some("checkpoint") => {
+            let args: Vec<&str> = command.split_whitespace().skip(1).collect();
+            checkpoint_command(app, checkpoints, &args)
+                .await
+                .unwrap_or_else(|e| format!("Error: {:#}", e))
+        }
+        other => format!(
+            "Unknown command '{}'; try status, reload, checkpoint or ping",
+            other
+        ),
+    };
+    writer.write_all(reply.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    Ok(())
+}
+
+pub fn service_unit(executable: &Path) -> String {
+    format!(
+        "[Unit]
+Description=Lantern network engine
+Documentation=https://github.com/jardahrazdera/lantern
+Requires=lantern.socket
+After=lantern.socket systemd-networkd.service
+Wants=systemd-networkd.service
+
+[Service]
+Type=notify
+ExecStart={} daemon
+ExecReload=/bin/kill -HUP $MAINPID
+WatchdogSec=30
+Restart=on-failure
+
+[Install]
+WantedBy=multi-user.target
+Also=lantern.socket
+",
+        executable.display()
+    )
+}
+
+pub fn socket_unit() -> String {
+    format!(
+        "[Unit]
+Description=Lantern control socket
+
+[Socket]
+ListenStream={}
+SocketMode=0600
+
+[Install]
+WantedBy=sockets.target
+",
+        CONTROL_SOCKET
+    )
+}
+
+/// Write lantern.service and lantern.socket for the running executable and
+/// reload systemd; with `enable`, also start them now and on boot.
+pub async fn install_service(dir: &Path, enable: bool) -> Result<()> {
+    let executable = std::env::current_exe().context("Failed to find the lantern executable")?;
+    std::fs::create_dir_all(dir)?;
+    let operation = "lantern install-service";
+    let service = provenance::stamp(&service_unit(&executable), operation);
+    secure_file::write_public(dir.join("lantern.service"), service)?;
+    secure_file::write_public(
+        dir.join("lantern.socket"),
+        provenance::stamp(&socket_unit(), operation),
+    )?;
+
+    systemctl(&["daemon-reload"]).await?;
+    if enable {
+        systemctl(&["enable", "--now", "lantern.socket", "lantern.service"]).await?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn systemctl(args: &[&str]) -> Result<()> {
+    let output = Command::new("/usr/bin/systemctl")
+        .args(args)
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "systemctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pets_the_watchdog_at_half_the_timeout() {
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None, 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some("42"), 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("7"), 42), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 42), None);
+        assert_eq!(watchdog_interval(None, None, 42), None);
+
+        let unit = service_unit(Path::new("/usr/local/bin/lantern"));
+        assert!(unit.contains("Type=notify\n"));
+        assert!(unit.contains("ExecStart=/usr/local/bin/lantern daemon\n"));
+        assert!(socket_unit().contains("ListenStream=/run/lantern.sock\n"));
+    }
+}
@@ -0,0 +1,1195 @@
+// src/demo.rs - Simulated interfaces, WiFi networks and traffic for `lantern --demo`
+use crate::dhcpprobe::{self, DhcpReport, Offer};
+use crate::dnsleak::{self, LeakReport, Probe};
+use crate::gai::{GaiState, Preference};
+use crate::hotspot::Station;
+use crate::httpcheck::{Binding, HttpCheck, Timings, TlsDetails};
+use crate::link_kind::LinkKind;
+use crate::macconflict::Neighbor;
+use crate::neighbors::StaticNeighbor;
+use crate::network::{
+    frequency_to_channel, DetailedWifiInfo, HotspotConfig, Interface, InterfaceStats, Ipv6Address,
+    Ipv6Info, Ipv6Scope, NicHardware, Route, WifiInfo, WifiNetwork, WifiSecurity,
+    WireGuardPeerStatus, WireGuardStatus,
+};
+use crate::networkd::{FileKind, MatchedFile, MatchedFiles};
+use crate::packets::{Direction, PacketSummary};
+use crate::reachability::{self, Check, Family, Outcome, ReachabilityReport, StackResult};
+use crate::rfmon::{FrameKind, Sighting};
+use crate::speedtest::SpeedResult;
+use crate::troubleshoot::{self, Diagnosis};
+use crate::wifi_ies::{ManagementFrameProtection, SecurityDetails};
+use crate::wifi_rate::parse_bitrate;
+use anyhow::{anyhow, bail, Result};
+use ipnetwork::Ipv4Network;
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+const WIRELESS: &str = "wlan0";
+const TUNNEL: &str = "wg0";
+
+/// A made-up machine: wired uplink, a laptop radio, a WireGuard tunnel and an
+/// unplugged bridge. Every change the TUI makes lands here instead of on the
+/// system, and counters grow at a plausible, slightly noisy rate.
+pub struct Simulation {
+    interfaces: Vec<Interface>,
+    networks: Vec<WifiNetwork>, // One entry per BSS, as a scan returns them
+    tunnel_public_key: String,
+    hotspot: Option<(String, Vec<Station>, Instant)>, // Interface, clients, last tick
+    neighbors: Vec<(String, StaticNeighbor)>,         // Interface, entry
+    address_preference: Preference,
+    tx_power: Option<i32>, // Fixed by the user; the driver's 22 dBm otherwise
+    last_tick: Instant,
+    started: Instant,
+    seed: u64,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        let mut eth0 = interface("eth0", "3c:7c:3f:1a:2b:3c", &["192.168.1.50/24"]);
+        eth0.gateway = Some("192.168.1.1".to_string());
+        eth0.ipv6_addresses = vec![
+            "2001:db8:1::50/64".to_string(),
+            "fe80::3e7c:3fff:fe1a:2b3c/64".to_string(),
+        ];
+        eth0.ipv6_gateway = Some("fe80::1".to_string());
+        eth0.ipv6_info = Some(ipv6_info(&eth0.ipv6_addresses));
+        eth0.hardware = Some(hardware(
+            "Intel Corporation",
+            "Ethernet Controller I225-V",
+            "igc",
+        ));
+        eth0.stats = stats(48_213_922_816, 3_116_830_720);
+
+        let networks = scan_results();
+        let mut wlan0 = interface(WIRELESS, "a4:c3:f0:85:12:9e", &["10.0.0.23/24"]);
+        wlan0.hardware = Some(hardware("Intel Corporation", "Wi-Fi 6 AX200", "iwlwifi"));
+        wlan0.stats = stats(2_908_114_432, 412_993_536);
+        wlan0.wifi_info = Some(associated(&networks[0]));
+
+        let mut wg0 = interface(TUNNEL, "", &["10.8.0.2/32"]);
+        wg0.state = "UNKNOWN".to_string(); // What the kernel reports for a running tunnel
+        wg0.mtu = 1420;
+        wg0.kind = LinkKind::WireGuard;
+        wg0.stats = stats(118_004_224, 36_880_384);
+
+        let mut docker0 = interface("docker0", "02:42:5e:61:0a:11", &["172.17.0.1/16"]);
+        docker0.state = "DOWN".to_string();
+        docker0.carrier = Some(false);
+        docker0.kind = LinkKind::Bridge;
+
+        let now = Instant::now();
+        Self {
+            interfaces: vec![eth0, wlan0, wg0, docker0],
+            networks,
+            tunnel_public_key: "HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8ykw=".to_string(),
+            hotspot: None,
+            neighbors: vec![(
+                "eth0".to_string(),
+                StaticNeighbor {
+                    address: "192.168.1.1".to_string(),
+                    mac: "00:1a:2b:3c:4d:5e".to_string(),
+                    active: true,
+                    persisted: true,
+                },
+            )],
+            address_preference: Preference::Ipv6,
+            tx_power: None,
+            last_tick: now,
+            started: now,
+            seed: 0x1a27_e2d0,
+        }
+    }
+
+    pub fn interfaces(&mut self) -> Vec<Interface> {
+        self.tick();
+        self.interfaces.clone()
+    }
+
+    pub fn stats(&mut self, names: &[String]) -> HashMap<String, InterfaceStats> {
+        self.tick();
+        self.interfaces
+            .iter()
+            .filter(|iface| names.contains(&iface.name))
+            .map(|iface| (iface.name.clone(), iface.stats.clone()))
+            .collect()
+    }
+
+    pub fn wifi_info(&mut self, name: &str) -> Option<WifiInfo> {
+        let jitter = self.noise(6) as i32 - 3;
+        let mut info = self.find(name).ok()?.wifi_info.clone()?;
+        if let Some(network) = info.current_network.as_mut() {
+            network.signal_strength += jitter;
+            info.signal_strength = Some(network.signal_strength);
+        }
+        Some(info)
+    }
+
+    pub fn detailed_wifi_info(&mut self, name: &str) -> Option<DetailedWifiInfo> {
+        let info = self.wifi_info(name)?;
+        let network = info.current_network?;
+        let stats = self.find(name).ok()?.stats.clone();
+        Some(DetailedWifiInfo {
+            ssid: network.ssid,
+            bssid: network.bssid,
+            signal_strength: network.signal_strength,
+            signal_quality: Some((2 * (network.signal_strength + 100)).clamp(0, 100) as u32),
+            frequency: network.frequency,
+            channel: network.channel,
+            tx_power: Some(self.tx_power.unwrap_or(22)),
+            link_speed: Some(866),
+            security: network.security,
+            encryption: network.encryption,
+            connected_time: Some(self.started.elapsed() + Duration::from_secs(3 * 3600)),
+            tx_packets: stats.tx_packets,
+            rx_packets: stats.rx_packets,
+            tx_bytes: stats.tx_bytes,
+            rx_bytes: stats.rx_bytes,
+            tx_errors: stats.tx_errors,
+            rx_errors: stats.rx_errors,
+            tx_dropped: 0,
+            rx_dropped: stats.rx_errors / 2,
+            tx_retries: stats.tx_packets / 40,
+            tx_rate: parse_bitrate("866.7 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 2"),
+            rx_rate: parse_bitrate("780.0 MBit/s VHT-MCS 8 80MHz short GI VHT-NSS 2"),
+        })
+    }
+
+    pub fn scan(&mut self, name: &str) -> Result<Vec<WifiNetwork>> {
+        let current = self
+            .find(name)?
+            .wifi_info
+            .as_ref()
+            .ok_or_else(|| anyhow!("Interface '{}' is not a wireless interface", name))?
+            .current_network
+            .as_ref()
+            .map(|network| network.bssid.clone());
+
+        let mut networks = self.networks.clone();
+        for network in &mut networks {
+            network.signal_strength += self.noise(5) as i32 - 2;
+            network.connected = current.as_ref() == Some(&network.bssid);
+        }
+        networks.sort_by_key(|n| std::cmp::Reverse(n.signal_strength));
+        Ok(networks)
+    }
+
+    pub fn routes(&self, name: &str) -> Vec<Route> {
+        let Ok(iface) = self.find(name) else {
+            return Vec::new();
+        };
+        let mut routes = Vec::new();
+        if let Some(gateway) = &iface.gateway {
+            routes.push(route("default", Some(gateway), "dhcp", Some(100), false));
+        }
+        for address in &iface.ipv4_addresses {
+            if let Ok(network) = address.parse::<ipnetwork::IpNetwork>() {
+                if network.prefix() < 32 {
+                    let destination = format!("{}/{}", network.network(), network.prefix());
+                    routes.push(route(&destination, None, "kernel", None, false));
+                }
+            }
+        }
+        if name == TUNNEL && iface.admin_up {
+            routes.push(route("10.8.0.0/24", None, "static", None, false));
+        }
+        if let Some(gateway) = &iface.ipv6_gateway {
+            routes.push(route("default", Some(gateway), "ra", Some(1024), true));
+        }
+        routes
+    }
+
+    pub fn wireguard_status(&mut self, name: &str) -> Option<WireGuardStatus> {
+        if name != TUNNEL {
+            return None;
+        }
+        let iface = self.find(name).ok()?;
+        let up = iface.admin_up;
+        let traffic = iface.stats.clone();
+        // Handshakes are renewed every two minutes while traffic flows
+        let handshake =
+            SystemTime::now() - Duration::from_secs(self.started.elapsed().as_secs() % 120 + 5);
+        let latest_handshake = up.then_some(handshake);
+
+        Some(WireGuardStatus {
+            interface: name.to_string(),
+            public_key: self.tunnel_public_key.clone(),
+            listen_port: None,
+            peers: vec![WireGuardPeerStatus {
+                public_key: "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=".to_string(),
+                endpoint: Some("203.0.113.7:51820".to_string()),
+                allowed_ips: vec!["10.8.0.0/24".to_string()],
+                latest_handshake,
+                transfer_rx: traffic.rx_bytes,
+                transfer_tx: traffic.tx_bytes,
+                persistent_keepalive: Some(25),
+                rtt: None,
+            }],
+            connected: up,
+            last_handshake: latest_handshake,
+        })
+    }
+
+    /// The tunnel has its own resolver, but the wired uplink wins the race
+    /// now and then: a leak, which is what the test is for
+    pub fn dns_leak(&self, interface: &str) -> LeakReport {
+        let through = |resolver: &str, link: &str| Probe {
+            resolver: Some(resolver.to_string()),
+            link: Some(link.to_string()),
+            error: None,
+        };
+        let direct = match interface {
+            TUNNEL => through("203.0.113.53", TUNNEL),
+            "eth0" => through("198.51.100.20", "eth0"),
+            WIRELESS => through("198.51.100.77", WIRELESS),
+            _ => Probe {
+                error: Some(format!("No DNS servers on {}", interface)),
+                ..Probe::default()
+            },
+        };
+        let default_path = vec![
+            through("203.0.113.53", TUNNEL),
+            through("198.51.100.20", "eth0"),
+            through("203.0.113.53", TUNNEL),
+        ];
+        let vpns = vec![TUNNEL.to_string()];
+        LeakReport {
+            interface: interface.to_string(),
+            verdict: dnsleak::verdict(&default_path, &vpns),
+            direct,
+            default_path,
+            vpns,
+        }
+    }
+
+    /// Someone plugged a travel router into the office LAN: eth0 hears a
+    /// second server handing out its own subnet
+    pub fn dhcp_probe(&self, interface: &str, configured: &[String]) -> DhcpReport {
+        let offer = |server: [u8; 4], address: [u8; 4], prefix: u8| {
+            let server = Ipv4Addr::from(server);
+            let address = Ipv4Addr::from(address);
+            Offer {
+                server,
+                from: server,
+                address,
+                network: Ipv4Network::new(address, prefix)
+                    .and_then(|network| Ipv4Network::new(network.network(), prefix))
+                    .ok(),
+                router: Some(server),
+                dns: vec![server],
+                lease_secs: Some(86400),
+                expected: true,
+            }
+        };
+        let (mut offers, lease) = match interface {
+            "eth0" => (
+                vec![
+                    offer([192, 168, 1, 1], [192, 168, 1, 50], 24),
+                    offer([192, 168, 8, 1], [192, 168, 8, 143], 24),
+                ],
+                Some(Ipv4Addr::new(192, 168, 1, 1)),
+            ),
+            WIRELESS => (vec![offer([10, 0, 0, 1], [10, 0, 0, 23], 24)], None),
+            _ => (Vec::new(), None),
+        };
+        let error =
+            (interface == TUNNEL).then(|| format!("{} has no Ethernet MAC address", TUNNEL));
+        DhcpReport {
+            interface: interface.to_string(),
+            basis: dhcpprobe::judge(&mut offers, configured, lease),
+            offers,
+            error,
+        }
+    }
+
+    /// IPv4 works wherever there is a gateway; IPv6 on the uplink has an
+    /// address and a route but goes nowhere upstream, the classic half-broken
+    /// dual-stack network
+    pub fn reachability(&self, interface: &str) -> Result<ReachabilityReport> {
+        let iface = self.find(interface)?;
+        let stack = |family: Family| {
+            let pass = |detail: &str| Outcome::Pass(detail.to_string());
+            let fail = |detail: &str| Outcome::Fail(detail.to_string());
+            let address = reachability::global_addresses(iface, family);
+            let gateway = match family {
+                Family::V4 => iface.gateway.as_ref(),
+                Family::V6 => iface.ipv6_gateway.as_ref(),
+            };
+            let checks = match (address.first(), gateway, family) {
+                (None, _, _) => vec![
+                    (Check::Address, fail("No global address")),
+                    (Check::Route, Outcome::Skipped),
+                    (Check::Ping, Outcome::Skipped),
+                    (Check::Dns, fail("No servers")),
+                    (Check::Http, Outcome::Skipped),
+                ],
+                (Some(address), None, _) => vec![
+                    (Check::Address, pass(address)),
+                    (Check::Route, fail("No default route")),
+                    (Check::Ping, Outcome::Skipped),
+                    (Check::Dns, fail("No servers")),
+                    (Check::Http, Outcome::Skipped),
+                ],
+                (Some(address), Some(gateway), Family::V4) => vec![
+                    (Check::Address, pass(address)),
+                    (Check::Route, pass(&format!("default via {}", gateway))),
+                    (Check::Ping, pass("1.1.1.1 in 14.2 ms")),
+                    (Check::Dns, pass("www.google.com A 142.250.180.196")),
+                    (Check::Http, pass("HTTP 204 in 61 ms")),
+                ],
+                (Some(address), Some(gateway), Family::V6) => vec![
+                    (Check::Address, pass(address)),
+                    (Check::Route, pass(&format!("default via {}", gateway))),
+                    (Check::Ping, fail("No reply from 2606:4700:4700::1111")),
+                    (
+                        Check::Dns,
+                        pass("www.google.com AAAA 2a00:1450:4014:80d::2004"),
+                    ),
+                    (Check::Http, fail("No response")),
+                ],
+            };
+            StackResult {
+                family,
+                checks,
+                accept_ra: true,
+            }
+        };
+        Ok(ReachabilityReport {
+            interface: interface.to_string(),
+            v4: stack(Family::V4),
+            v6: stack(Family::V6),
+        })
+    }
+
+    /// Up to the gateway from the interface itself; past it everything
+    /// answers wherever there is one
+    pub fn troubleshoot(&self, interface: &str) -> Result<Diagnosis> {
+        let iface = self.find(interface)?;
+        let gateway = match &iface.gateway {
+            Some(gateway) => Outcome::Pass(format!("{} is at 00:0d:b9:4f:1c:20", gateway)),
+            None => Outcome::Fail("No default gateway".to_string()),
+        };
+        let tried = vec![
+            troubleshoot::link(iface),
+            troubleshoot::address(iface),
+            gateway,
+            Outcome::Pass("www.google.com A 142.250.180.196".to_string()),
+            Outcome::Pass("HTTP 204 in 61 ms".to_string()),
+        ];
+        Ok(Diagnosis::new(iface, tried))
+    }
+
+    pub fn address_preference(&self) -> GaiState {
+        GaiState {
+            preference: self.address_preference,
+            foreign: Vec::new(),
+        }
+    }
+
+    pub fn set_address_preference(&mut self, preference: Preference) {
+        self.address_preference = preference;
+    }
+
+    pub fn set_tx_power(&mut self, dbm: Option<i32>) {
+        self.tx_power = dbm;
+    }
+
+    /// A dual-stack name, in the order gai.conf would put it
+    pub fn address_order(&self) -> Vec<String> {
+        let mut order = vec![
+            "2a00:1450:4014:80d::2004".to_string(),
+            "142.250.180.196".to_string(),
+        ];
+        if self.address_preference == Preference::Ipv4 {
+            order.reverse();
+        }
+        order
+    }
+
+    /// A 500/50 line that sags in the evening, the way shared cable does
+    fn speed_at(&mut self, hour: u32) -> SpeedResult {
+        let evening = match hour {
+            19..=22 => 0.25,
+            17 | 18 | 23 => 0.6,
+            _ => 0.95,
+        };
+        let download = 500.0 * evening - self.noise(40) as f64;
+        SpeedResult {
+            timestamp: String::new(),
+            download_mbps: Some(download),
+            upload_mbps: Some(48.0 - self.noise(6) as f64),
+            latency_ms: Some(12.0 + self.noise(8) as f64 + (1.0 - evening) * 30.0),
+            error: None,
+        }
+    }
+
+    pub fn speed_test(&mut self) -> SpeedResult {
+        let now = chrono::Local::now();
+        SpeedResult {
+            timestamp: now.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            ..self.speed_at(chrono::Timelike::hour(&now))
+        }
+    }
+
+    /// A week of hourly tests
+    pub fn speed_history(&mut self) -> Vec<SpeedResult> {
+        let now = chrono::Local::now();
+        (1..=7 * 24)
+            .rev()
+            .map(|hours_ago| {
+                let at = now - chrono::Duration::hours(hours_ago);
+                SpeedResult {
+                    timestamp: at.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+                    ..self.speed_at(chrono::Timelike::hour(&at))
+                }
+            })
+            .collect()
+    }
+
+    pub fn probe_rtt(&mut self) -> Duration {
+        Duration::from_micros(17_000 + self.noise(9_000))
+    }
+
+    pub fn set_state(&mut self, name: &str, up: bool) -> Result<()> {
+        let iface = self.find_mut(name)?;
+        iface.admin_up = up;
+        iface.state = match (up, iface.carrier) {
+            (false, _) => "DOWN",
+            (true, Some(false)) => "DOWN",
+            (true, _) if name == TUNNEL => "UNKNOWN",
+            (true, _) => "UP",
+        }
+        .to_string();
+        Ok(())
+    }
+
+    /// No DHCP server to ask; the interface keeps what it has
+    pub fn retry_dhcp(&self, name: &str) -> Result<()> {
+        self.find(name).map(|_| ())
+    }
+
+    pub fn set_promiscuous(&mut self, name: &str, on: bool) -> Result<()> {
+        self.find_mut(name)?.promiscuous = on;
+        Ok(())
+    }
+
+    /// Add or remove mon0, returning its name and whether it was created.
+    pub fn toggle_monitor(&mut self, name: &str) -> Result<(String, bool)> {
+        let monitor = "mon0".to_string();
+        if self.find(name)?.monitor {
+            self.interfaces.retain(|iface| iface.name != name);
+            return Ok((name.to_string(), false));
+        }
+        if let Some(index) = self.interfaces.iter().position(|i| i.name == monitor) {
+            self.interfaces.remove(index);
+            return Ok((monitor, false));
+        }
+        let mut mon0 = interface(&monitor, "a4:c3:f0:85:12:9e", &[]);
+        mon0.monitor = true;
+        mon0.promiscuous = true;
+        self.interfaces.push(mon0);
+        Ok((monitor, true))
+    }
+
+    pub fn add_address(&mut self, name: &str, address: &str) -> Result<()> {
+        let network: ipnetwork::IpNetwork = address
+            .parse()
+            .map_err(|e| anyhow!("Failed to add {} on {}: {}", address, name, e))?;
+        let iface = self.find_mut(name)?;
+        let list = match network {
+            ipnetwork::IpNetwork::V4(_) => &mut iface.ipv4_addresses,
+            ipnetwork::IpNetwork::V6(_) => &mut iface.ipv6_addresses,
+        };
+        if list.iter().any(|existing| existing == address) {
+            bail!(
+                "Failed to add {} on {}: Address already assigned",
+                address,
+                name
+            );
+        }
+        list.push(address.to_string());
+        Ok(())
+    }
+
+    pub fn remove_address(&mut self, name: &str, address: &str) -> Result<()> {
+        let iface = self.find_mut(name)?;
+        let before = iface.ipv4_addresses.len() + iface.ipv6_addresses.len();
+        iface.ipv4_addresses.retain(|existing| existing != address);
+        iface.ipv6_addresses.retain(|existing| existing != address);
+        if before == iface.ipv4_addresses.len() + iface.ipv6_addresses.len() {
+            bail!(
+                "Failed to remove {} from {}: Cannot assign requested address",
+                address,
+                name
+            );
+        }
+        Ok(())
+    }
+
+    pub fn connect_wifi(&mut self, name: &str, ssid: &str, password: Option<&str>) -> Result<()> {
+        let network = self
+            .networks
+            .iter()
+            .filter(|network| network.ssid == ssid)
+            .max_by_key(|network| network.signal_strength)
+            .cloned()
+            .ok_or_else(|| anyhow!("Network '{}' not found", ssid))?;
+        if network.security != WifiSecurity::Open && password.is_none_or(str::is_empty) {
+            bail!("{} needs a passphrase", ssid);
+        }
+
+        let iface = self.find_mut(name)?;
+        if iface.wifi_info.is_none() {
+            bail!("Interface '{}' is not a wireless interface", name);
+        }
+        iface.wifi_info = Some(associated(&network));
+        iface.ipv4_addresses = vec!["10.0.0.23/24".to_string()];
+        iface.gateway = Some("10.0.0.1".to_string());
+        iface.carrier = Some(true);
+        Ok(())
+    }
+
+    pub fn disconnect_wifi(&mut self, name: &str) -> Result<()> {
+        let iface = self.find_mut(name)?;
+        iface.wifi_info = Some(WifiInfo {
+            current_network: None,
+            signal_strength: None,
+            frequency: None,
+            channel: None,
+        });
+        iface.ipv4_addresses.clear();
+        iface.gateway = None;
+        iface.carrier = Some(false);
+        Ok(())
+    }
+
+    pub fn create_hotspot(&mut self, config: &HotspotConfig) -> Result<()> {
+        self.disconnect_wifi(&config.interface)?;
+        let iface = self.find_mut(&config.interface)?;
+        iface.ipv4_addresses = vec![format!("{}/24", config.gateway)];
+        iface.carrier = Some(true);
+
+        let prefix = config
+            .gateway
+            .rsplit_once('.')
+            .map_or("", |(prefix, _)| prefix);
+        let client = |mac: &str, host: u8, hostname: Option<&str>, signal: i32| Station {
+            mac: mac.to_string(),
+            ip: Some(format!("{}.{}", prefix, host)),
+            hostname: hostname.map(str::to_string),
+            signal: Some(signal),
+            ..Default::default()
+        };
+        let clients = vec![
+            client("5c:e9:1e:42:7a:10", 10, Some("pixel-7"), -48),
+            client("a8:5b:78:0c:33:e1", 11, Some("work-laptop"), -61),
+            client("02:1f:9a:6d:c4:58", 12, None, -74), // Randomized MAC, no hostname sent
+        ];
+        self.hotspot = Some((config.interface.clone(), clients, Instant::now()));
+        Ok(())
+    }
+
+    /// The made-up hotspot clients: a phone streaming, a laptop syncing and a
+    /// mostly idle third one.
+    pub fn hotspot_stations(&mut self, interface: &str) -> Vec<Station> {
+        let Some((hotspot_interface, mut clients, last_tick)) = self.hotspot.take() else {
+            return Vec::new();
+        };
+        let seconds = last_tick.elapsed().as_secs_f64();
+        for (client, (down, up)) in clients.iter_mut().zip([
+            (2_400_000.0, 90_000.0),
+            (350_000.0, 600_000.0),
+            (4_000.0, 1_000.0),
+        ]) {
+            let factor = 0.5 + self.noise(100) as f64 / 100.0;
+            client.tx_bytes += (down * seconds * factor) as u64;
+            client.rx_bytes += (up * seconds * factor) as u64;
+        }
+        let stations = if hotspot_interface == interface {
+            clients.clone()
+        } else {
+            Vec::new()
+        };
+        self.hotspot = Some((hotspot_interface, clients, Instant::now()));
+        stations
+    }
+
+    pub fn static_neighbors(&self, interface: &str) -> Vec<StaticNeighbor> {
+        self.neighbors
+            .iter()
+            .filter(|(name, _)| name == interface)
+            .map(|(_, neighbor)| neighbor.clone())
+            .collect()
+    }
+
+    /// The office LAN, where a cheap camera and a printer came with the same MAC
+    pub fn neighbor_table(&self) -> Vec<Neighbor> {
+        let neighbor = |address: &str, mac: &str| Neighbor {
+            interface: "eth0".to_string(),
+            address: address.to_string(),
+            mac: mac.to_string(),
+        };
+        vec![
+            neighbor("192.168.1.1", "00:0d:b9:4f:1c:20"),
+            neighbor("fe80::1", "00:0d:b9:4f:1c:20"),
+            neighbor("192.168.1.23", "00:12:34:56:78:9a"),
+            neighbor("192.168.1.64", "00:12:34:56:78:9a"),
+        ]
+    }
+
+    pub fn add_static_neighbor(&mut self, interface: &str, address: &str, mac: &str) -> Result<()> {
+        self.find(interface)?;
+        let (address, mac) = crate::neighbors::validate(address, mac)?;
+        self.neighbors
+            .retain(|(name, neighbor)| !(name == interface && neighbor.address == address));
+        self.neighbors.push((
+            interface.to_string(),
+            StaticNeighbor {
+                address,
+                mac,
+                active: true,
+                persisted: true,
+            },
+        ));
+        Ok(())
+    }
+
+    pub fn remove_static_neighbor(&mut self, interface: &str, address: &str) {
+        self.neighbors
+            .retain(|(name, neighbor)| !(name == interface && neighbor.address == address));
+    }
+
+    /// What networkd would report: lantern's files for the uplink, radio and
+    /// tunnel, nothing for the container bridge.
+    pub fn networkd_files(&self, interface: &str) -> Result<MatchedFiles> {
+        self.find(interface)?;
+        let file = |kind, path: &str, contents: String, lantern| MatchedFile {
+            kind,
+            path: path.into(),
+            contents: Some(contents),
+            lantern,
+        };
+        let default_link = file(
+            FileKind::Link,
+            "/usr/lib/systemd/network/99-default.link",
+            "[Match]\nOriginalName=*\n\n[Link]\nNamePolicy=keep kernel database onboard slot path\nAlternativeNamesPolicy=database onboard slot path\nMACAddressPolicy=persistent\n".to_string(),
+            false,
+        );
+        let network = |prefix: &str, body: &str| {
+            file(
+                FileKind::Network,
+                &format!("/etc/systemd/network/{}-{}.network", prefix, interface),
+                format!("[Match]\nName={}\n\n[Network]\n{}", interface, body),
+                true,
+            )
+        };
+        let (setup_state, files) = match interface {
+            "eth0" => (
+                "configured",
+                vec![default_link, network("10", "DHCP=yes\n")],
+            ),
+            WIRELESS => (
+                "configured",
+                vec![default_link, network("25", "DHCP=yes\nIgnoreCarrierLoss=3s\n")],
+            ),
+            TUNNEL => (
+                "configured",
+                vec![
+                    file(
+                        FileKind::NetDev,
+                        "/etc/systemd/network/50-wg0.netdev",
+                        "[NetDev]\nName=wg0\nKind=wireguard\n\n[WireGuard]\nPrivateKeyFile=/etc/systemd/network/50-wg0.key\n".to_string(),
+                        true,
+                    ),
+                    network("50", "Address=10.8.0.2/32\n"),
+                ],
+            ),
+            _ => ("unmanaged", vec![default_link]),
+        };
+        Ok(MatchedFiles {
+            setup_state: Some(setup_state.to_string()),
+            files,
+            shadowed: None,
+        })
+    }
+
+    /// /etc/systemd/network for the doctor: lantern's files and a catch-all
+    /// left over from the installer
+    pub fn networkd_dir(&self) -> Vec<(PathBuf, String)> {
+        let file = |name: &str, contents: &str| {
+            (
+                Path::new(crate::doctor::NETWORKD_DIR).join(name),
+                contents.to_string(),
+            )
+        };
+        vec![
+            file(
+                "10-eth0.network",
+                "[Match]\nName=eth0\n\n[Network]\nDHCP=yes\n",
+            ),
+            file(
+                "25-wlan0.network",
+                "[Match]\nName=wlan0\n\n[Network]\nDHCP=yes\nIgnoreCarrierLoss=3s\n",
+            ),
+            file(
+                "50-wg0.network",
+                "[Match]\nName=wg0\n\n[Network]\nAddress=10.8.0.2/32\n",
+            ),
+            file(
+                "80-installer.network",
+                "[Match]\nName=en* eth*\n\n[Network]\nDHCP=ipv4\nAddress=192.168.1.50/24\nGateway=192.168.1.1\n",
+            ),
+        ]
+    }
+
+    fn find(&self, name: &str) -> Result<&Interface> {
+        self.interfaces
+            .iter()
+            .find(|iface| iface.name == name)
+            .ok_or_else(|| anyhow!("Cannot find device \"{}\"", name))
+    }
+
+    fn find_mut(&mut self, name: &str) -> Result<&mut Interface> {
+        self.interfaces
+            .iter_mut()
+            .find(|iface| iface.name == name)
+            .ok_or_else(|| anyhow!("Cannot find device \"{}\"", name))
+    }
+
+    /// Advance the counters of every interface that has a link.
+    fn tick(&mut self) {
+        let seconds = self.last_tick.elapsed().as_secs_f64();
+        self.last_tick = Instant::now();
+
+        for index in 0..self.interfaces.len() {
+            let iface = &self.interfaces[index];
+            let (rx_rate, tx_rate) = match iface.name.as_str() {
+                "eth0" => (1_200_000.0, 160_000.0),
+                WIRELESS if has_network(iface) => (420_000.0, 65_000.0),
+                TUNNEL => (45_000.0, 18_000.0),
+                _ => continue,
+            };
+            if !iface.admin_up || iface.carrier == Some(false) {
+                continue;
+            }
+            let wireless = iface.name == WIRELESS;
+
+            // Between a third and twice the average, with the odd burst
+            let factor = 0.3 + self.noise(170) as f64 / 100.0;
+            let burst = if self.noise(20) == 0 { 6.0 } else { 1.0 };
+            let rx = (rx_rate * seconds * factor * burst) as u64;
+            let tx = (tx_rate * seconds * factor) as u64;
+            let error = wireless && self.noise(50) == 0;
+
+            let stats = &mut self.interfaces[index].stats;
+            stats.rx_bytes += rx;
+            stats.tx_bytes += tx;
+            stats.rx_packets += rx / 1200 + u64::from(rx > 0);
+            stats.tx_packets += tx / 600 + u64::from(tx > 0);
+            stats.rx_errors += u64::from(error);
+        }
+    }
+
+    /// A number below `bound` from a small xorshift generator.
+    fn noise(&mut self, bound: u64) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed % bound.max(1)
+    }
+}
+
+fn interface(name: &str, mac: &str, ipv4: &[&str]) -> Interface {
+    Interface {
+        name: name.to_string(),
+        mac_address: mac.to_string(),
+        state: "UP".to_string(),
+        mtu: 1500,
+        ipv4_addresses: ipv4.iter().map(|a| a.to_string()).collect(),
+        ipv6_addresses: Vec::new(),
+        ipv6_info: None,
+        gateway: None,
+        ipv6_gateway: None,
+        dns_servers: vec!["192.168.1.1".to_string(), "2001:db8:1::1".to_string()],
+        stats: InterfaceStats::default(),
+        wifi_info: None,
+        hardware: None,
+        admin_up: true,
+        carrier: Some(true),
+        carrier_changes: Some(2),
+        promiscuous: false,
+        monitor: false,
+        kind: LinkKind::Physical,
+        master: None,
+        lower: None,
+        peer_netns: None,
+    }
+}
+
+fn hardware(vendor: &str, model: &str, driver: &str) -> NicHardware {
+    NicHardware {
+        vendor: Some(vendor.to_string()),
+        model: Some(model.to_string()),
+        driver: Some(driver.to_string()),
+    }
+}
+
+fn stats(rx_bytes: u64, tx_bytes: u64) -> InterfaceStats {
+    InterfaceStats {
+        rx_bytes,
+        tx_bytes,
+        rx_packets: rx_bytes / 1200,
+        tx_packets: tx_bytes / 600,
+        rx_errors: 0,
+        tx_errors: 0,
+    }
+}
+
+fn ipv6_info(addresses: &[String]) -> Ipv6Info {
+    Ipv6Info {
+        addresses: addresses
+            .iter()
+            .filter_map(|address| {
+                let (address, prefix) = address.split_once('/')?;
+                Some(Ipv6Address {
+                    address: address.to_string(),
+                    prefix_length: prefix.parse().ok()?,
+                    scope: if address.starts_with("fe80") {
+                        Ipv6Scope::LinkLocal
+                    } else {
+                        Ipv6Scope::Global
+                    },
+                    flags: Vec::new(),
+                    preferred_lifetime: None,
+                    valid_lifetime: None,
+                })
+            })
+            .collect(),
+        default_route: Some("fe80::1".to_string()),
+        dns_servers: vec!["2001:db8:1::1".to_string()],
+        accept_ra: true,
+        privacy_extensions: true,
+        dhcpv6_enabled: false,
+    }
+}
+
+fn route(
+    destination: &str,
+    gateway: Option<&String>,
+    protocol: &str,
+    metric: Option<u32>,
+    ipv6: bool,
+) -> Route {
+    Route {
+        destination: destination.to_string(),
+        gateway: gateway.cloned(),
+        protocol: Some(protocol.to_string()),
+        metric,
+        ipv6,
+    }
+}
+
+fn has_network(iface: &Interface) -> bool {
+    iface
+        .wifi_info
+        .as_ref()
+        .is_some_and(|info| info.current_network.is_some())
+}
+
+fn associated(network: &WifiNetwork) -> WifiInfo {
+    WifiInfo {
+        current_network: Some(WifiNetwork {
+            connected: true,
+            ..network.clone()
+        }),
+        signal_strength: Some(network.signal_strength),
+        frequency: Some(network.frequency),
+        channel: Some(network.channel),
+    }
+}
+
+fn security(kind: &WifiSecurity) -> SecurityDetails {
+    let rsn = |akm: &[&str], mfp| SecurityDetails {
+        rsn: true,
+        privacy: true,
+        group_cipher: Some("CCMP".to_string()),
+        pairwise_ciphers: vec!["CCMP".to_string()],
+        akm_suites: akm.iter().map(|a| a.to_string()).collect(),
+        mfp,
+        ..SecurityDetails::default()
+    };
+    match kind {
+        WifiSecurity::Open => SecurityDetails::default(),
+        WifiSecurity::WEP => SecurityDetails {
+            privacy: true,
+            ..SecurityDetails::default()
+        },
+        WifiSecurity::WPA => SecurityDetails {
+            wpa: true,
+            privacy: true,
+            group_cipher: Some("TKIP".to_string()),
+            pairwise_ciphers: vec!["TKIP".to_string()],
+            akm_suites: vec!["PSK".to_string()],
+            ..SecurityDetails::default()
+        },
+        WifiSecurity::WPA2 => rsn(&["PSK"], ManagementFrameProtection::Disabled),
+        WifiSecurity::WPA3 => rsn(&["SAE"], ManagementFrameProtection::Required),
+        WifiSecurity::Enterprise => rsn(&["IEEE 802.1X"], ManagementFrameProtection::Capable),
+    }
+}
+
+/// A browser fetching a page over the wired uplink, with the usual chatter
+pub fn packets() -> Vec<PacketSummary> {
+    let packet =
+        |direction, protocol: &str, source: &str, destination: &str, length, info: &str| {
+            PacketSummary {
+                direction,
+                protocol: protocol.to_string(),
+                source: source.to_string(),
+                destination: destination.to_string(),
+                length,
+                info: info.to_string(),
+            }
+        };
+    let (local, server) = ("192.168.1.5:51234", "93.184.216.34:443");
+    vec![
+        packet(
+            Direction::Out,
+            "UDP",
+            "192.168.1.5:40512",
+            "192.168.1.1:53",
+            56,
+            "",
+        ),
+        packet(
+            Direction::In,
+            "UDP",
+            "192.168.1.1:53",
+            "192.168.1.5:40512",
+            72,
+            "",
+        ),
+        packet(Direction::Out, "TCP", local, server, 60, "[S]"),
+        packet(Direction::In, "TCP", server, local, 60, "[S.]"),
+        packet(Direction::Out, "TCP", local, server, 52, "[.]"),
+        packet(Direction::Out, "TCP", local, server, 569, "[P.]"),
+        packet(Direction::In, "TCP", server, local, 1500, "[.]"),
+        packet(Direction::In, "TCP", server, local, 1500, "[.]"),
+        packet(Direction::In, "TCP", server, local, 842, "[P.]"),
+        packet(Direction::Out, "TCP", local, server, 52, "[.]"),
+        packet(
+            Direction::In,
+            "ARP",
+            "192.168.1.1",
+            "192.168.1.5",
+            28,
+            "who-has 192.168.1.5 tell 192.168.1.1",
+        ),
+        packet(
+            Direction::Out,
+            "ICMPv6",
+            "fe80::a6c3:f0ff:fe85:129e",
+            "ff02::2",
+            16,
+            "router solicitation",
+        ),
+    ]
+}
+
+/// A quick answer over TLS from any binding; plain HTTP skips the handshake
+pub fn http_check(url: &str, binding: &Binding) -> HttpCheck {
+    let https = url.starts_with("https://");
+    HttpCheck {
+        url: url.to_string(),
+        binding: binding.label(),
+        status: Some(204),
+        http_version: "2".to_string(),
+        remote_ip: "142.250.180.196".to_string(),
+        timings: Timings {
+            dns: 0.004,
+            connect: 0.012,
+            tls: https.then_some(0.029),
+            first_byte: 0.026,
+            total: if https { 0.071 } else { 0.042 },
+        },
+        tls: https.then(|| TlsDetails {
+            version: "TLSv1.3".to_string(),
+            cipher: "TLS_AES_256_GCM_SHA384".to_string(),
+            subject: "CN=www.google.com".to_string(),
+            issuer: "C=US; O=Google Trust Services; CN=WR2".to_string(),
+            expires: "Nov 24 08:36:04 2026 GMT".to_string(),
+            verified: true,
+        }),
+        bytes: 0,
+        error: None,
+    }
+}
+
+/// What a monitor hears in turn: two hidden APs, one named by its probe
+/// response, and phones asking for networks they remember
+pub fn rf_sightings() -> Vec<Sighting> {
+    let frame = |kind, source: &str, bssid: &str, ssid: Option<&str>, signal, frequency| Sighting {
+        kind,
+        source: source.to_string(),
+        bssid: bssid.to_string(),
+        ssid: ssid.map(str::to_string),
+        signal: Some(signal),
+        frequency: Some(frequency),
+    };
+    let lab = "b0:be:76:10:22:99";
+    let camera = "c4:2f:90:7a:01:3d";
+    vec![
+        frame(FrameKind::Beacon, lab, lab, None, -58, 2437),
+        frame(
+            FrameKind::ProbeRequest,
+            "5a:1e:c3:9b:20:44",
+            "ff:ff:ff:ff:ff:ff",
+            Some("Home"),
+            -49,
+            2437,
+        ),
+        frame(FrameKind::Beacon, camera, camera, None, -77, 2462),
+        frame(
+            FrameKind::ProbeRequest,
+            "f0:18:98:3c:aa:01",
+            "ff:ff:ff:ff:ff:ff",
+            Some("eduroam"),
+            -66,
+            2412,
+        ),
+        frame(
+            FrameKind::ProbeRequest,
+            "5a:1e:c3:9b:20:44",
+            "ff:ff:ff:ff:ff:ff",
+            Some("Airport_Free_WiFi"),
+            -50,
+            2437,
+        ),
+        frame(
+            FrameKind::ProbeResponse,
+            lab,
+            lab,
+            Some("LabNet"),
+            -57,
+            2437,
+        ),
+        frame(
+            FrameKind::ProbeRequest,
+            "f0:18:98:3c:aa:01",
+            "ff:ff:ff:ff:ff:ff",
+            None,
+            -65,
+            2412,
+        ),
+        frame(
+            FrameKind::ProbeRequest,
+            "3e:91:07:d2:6b:e8",
+            "ff:ff:ff:ff:ff:ff",
+            Some("Cafe Guest"),
+            -80,
+            2412,
+        ),
+    ]
+}
+
+fn scan_results() -> Vec<WifiNetwork> {
+    [
+        ("Home", "b0:be:76:10:22:31", 5180, -52, WifiSecurity::WPA2),
+        ("Home", "b0:be:76:10:22:30", 2437, -47, WifiSecurity::WPA2),
+        ("Office", "10:20:30:40:50:61", 5955, -63, WifiSecurity::WPA3),
+        (
+            "Corp",
+            "10:20:30:40:50:70",
+            5500,
+            -67,
+            WifiSecurity::Enterprise,
+        ),
+        (
+            "Cafe Guest",
+            "66:77:88:99:aa:bb",
+            2412,
+            -71,
+            WifiSecurity::Open,
+        ),
+        (
+            "Neighbour",
+            "d8:47:32:0c:11:02",
+            2462,
+            -82,
+            WifiSecurity::WPA,
+        ),
+        (
+            "OldPrinter",
+            "00:1b:a9:44:02:7e",
+            2462,
+            -88,
+            WifiSecurity::WEP,
+        ),
+    ]
+    .into_iter()
+    .map(|(ssid, bssid, frequency, signal, kind)| {
+        let details = security(&kind);
+        WifiNetwork {
+            ssid: ssid.to_string(),
+            bssid: bssid.to_string(),
+            signal_strength: signal,
+            frequency,
+            channel: frequency_to_channel(frequency),
+            encryption: details.ciphers(),
+            security: kind,
+            security_details: details,
+            connected: false,
+            in_history: false,
+            access_points: Vec::new(),
+        }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_land_in_the_simulation() {
+        let mut simulation = Simulation::new();
+        let names: Vec<String> = simulation
+            .interfaces()
+            .iter()
+            .map(|i| i.name.clone())
+            .collect();
+        assert!(names.contains(&WIRELESS.to_string()));
+
+        simulation.set_state("eth0", false).unwrap();
+        simulation.add_address("eth0", "192.0.2.5/24").unwrap();
+        assert!(simulation.add_address("eth0", "192.0.2.5/24").is_err());
+        let eth0 = simulation.find("eth0").unwrap();
+        assert_eq!(eth0.state, "DOWN");
+        assert!(eth0.ipv4_addresses.contains(&"192.0.2.5/24".to_string()));
+
+        assert!(simulation.connect_wifi(WIRELESS, "Office", None).is_err());
+        simulation
+            .connect_wifi(WIRELESS, "Cafe Guest", None)
+            .unwrap();
+        let scan = simulation.scan(WIRELESS).unwrap();
+        assert!(scan.iter().any(|n| n.ssid == "Cafe Guest" && n.connected));
+        assert!(simulation.scan("eth0").is_err());
+
+        assert_eq!(
+            simulation.toggle_monitor(WIRELESS).unwrap(),
+            ("mon0".to_string(), true)
+        );
+        assert_eq!(
+            simulation.toggle_monitor("mon0").unwrap(),
+            ("mon0".to_string(), false)
+        );
+    }
+}
@@ -0,0 +1,382 @@
+// src/dhcpprobe.rs - Broadcast a DHCPDISCOVER and list every server that offers, flagging unexpected ones
+use anyhow::{bail, Context, Result};
+use ipnetwork::Ipv4Network;
+use nix::sys::socket::{
+    bind, setsockopt, socket, sockopt, AddressFamily, SockFlag, SockType, SockaddrIn,
+};
+use std::ffi::OsString;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::os::fd::AsRawFd;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+
+/// How long offers are waited for; servers answer within a second, relays
+/// and busy routers can take a little longer
+const LISTEN: Duration = Duration::from_secs(3);
+const CLIENT_PORT: u16 = 68;
+const SERVER_PORT: u16 = 67;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+// Fixed BOOTP header, up to the magic cookie
+const HEADER_LEN: usize = 236;
+
+// Options
+const SUBNET_MASK: u8 = 1;
+const ROUTER: u8 = 3;
+const DNS_SERVERS: u8 = 6;
+const LEASE_TIME: u8 = 51;
+const MESSAGE_TYPE: u8 = 53;
+const SERVER_ID: u8 = 54;
+const PARAMETER_LIST: u8 = 55;
+const END: u8 = 255;
+const PAD: u8 = 0;
+
+const DISCOVER: u8 = 1;
+const OFFER: u8 = 2;
+
+/// One server's answer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Offer {
+    /// Its server identifier, or where the offer came from if it gave none
+    pub server: Ipv4Addr,
+    /// The UDP source: a relay when it differs from `server`
+    pub from: Ipv4Addr,
+    pub address: Ipv4Addr,
+    /// The subnet the address is offered in
+    pub network: Option<Ipv4Network>,
+    pub router: Option<Ipv4Addr>,
+    pub dns: Vec<Ipv4Addr>,
+    pub lease_secs: Option<u32>,
+    pub expected: bool,
+}
+
+impl Offer {
+    pub fn describe(&self) -> String {
+        let mut text = match self.network {
+            Some(network) => format!("{} in {}", self.address, network),
+            None => self.address.to_string(),
+        };
+        if let Some(router) = self.router {
+            text.push_str(&format!(", gateway {}", router));
+        }
+        if !self.dns.is_empty() {
+            let dns: Vec<String> = self.dns.iter().map(|dns| dns.to_string()).collect();
+            text.push_str(&format!(", DNS {}", dns.join(" ")));
+        }
+        if let Some(lease) = self.lease_secs {
+            text.push_str(&format!(", lease {}s", lease));
+        }
+        text
+    }
+}
+
+/// What an offer was judged against
+#[derive(Debug, Clone, PartialEq)]
+pub enum Basis {
+    /// `[dhcp] servers` in config.toml
+    Configured,
+    /// The server the interface's current lease came from
+    Lease(Ipv4Addr),
+    /// Nothing to go by: more than one server is a conflict in itself
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DhcpReport {
+    pub interface: String,
+    pub offers: Vec<Offer>,
+    pub basis: Basis,
+    pub error: Option<String>,
+}
+
+impl DhcpReport {
+    pub fn unexpected(&self) -> usize {
+        self.offers.iter().filter(|offer| !offer.expected).count()
+    }
+
+    /// One line for the status bar
+    pub fn summary(&self) -> String {
+        if let Some(error) = &self.error {
+            return format!("DHCP probe on {} failed: {}", self.interface, error);
+        }
+        match (self.offers.len(), self.unexpected()) {
+            (0, _) => format!("No DHCP server answered on {}", self.interface),
+            (servers, 0) => format!(
+                "{} DHCP server{} answered on {}, as expected",
+                servers,
+                if servers == 1 { "" } else { "s" },
+                self.interface
+            ),
+            (servers, unexpected) => format!(
+                "{} DHCP server{} answered on {}; {} unexpected",
+                servers,
+                if servers == 1 { "" } else { "s" },
+                self.interface,
+                unexpected
+            ),
+        }
+    }
+}
+
+/// A broadcast DHCPDISCOVER from `mac`, asking for the answer to be broadcast
+/// too since we have no address yet as far as the server knows
+pub fn discover(xid: u32, mac: [u8; 6]) -> Vec<u8> {
+    let mut packet = vec![0u8; HEADER_LEN];
+    packet[0] = 1; // BOOTREQUEST
+    packet[1] = 1; // Ethernet
+    packet[2] = 6;
+    packet[4..8].copy_from_slice(&xid.to_be_bytes());
+    packet[10] = 0x80; // Broadcast flag
+    packet[28..34].copy_from_slice(&mac);
+    packet.extend_from_slice(&MAGIC_COOKIE);
+    packet.extend_from_slice(&[MESSAGE_TYPE, 1, DISCOVER]);
+    packet.extend_from_slice(&[
+        PARAMETER_LIST,
+        5,
+        SUBNET_MASK,
+        ROUTER,
+        DNS_SERVERS,
+        LEASE_TIME,
+        SERVER_ID,
+    ]);
+    packet.push(END);
+    // Some old servers ignore anything shorter than a BOOTP packet
+    packet.resize(300, PAD);
+    packet
+}
+
+fn ipv4(bytes: &[u8]) -> Option<Ipv4Addr> {
+    let octets: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+    Some(Ipv4Addr::from(octets))
+}
+
+/// A DHCPOFFER answering `xid`, or None for anything else on the port
+pub fn parse_offer(packet: &[u8], xid: u32, from: Ipv4Addr) -> Option<Offer> {
+    if packet.len() < HEADER_LEN + 4
+        || packet[0] != 2
+        || packet[4..8] != xid.to_be_bytes()
+        || packet[HEADER_LEN..HEADER_LEN + 4] != MAGIC_COOKIE
+    {
+        return None;
+    }
+    let address = ipv4(&packet[16..20])?;
+    let mut offer = Offer {
+        server: from,
+        from,
+        address,
+        network: None,
+        router: None,
+        dns: Vec::new(),
+        lease_secs: None,
+        expected: true,
+    };
+    let mut message_type = None;
+    let mut options = &packet[HEADER_LEN + 4..];
+    while let Some((&code, rest)) = options.split_first() {
+        if code == END {
+            break;
+        }
+        if code == PAD {
+            options = rest;
+            continue;
+        }
+        let (&length, rest) = rest.split_first()?;
+        let value = rest.get(..length as usize)?;
+        match code {
+            MESSAGE_TYPE => message_type = value.first().copied(),
+            SERVER_ID => offer.server = ipv4(value).unwrap_or(from),
+            SUBNET_MASK => {
+                offer.network = ipv4(value)
+                    .and_then(|mask| Ipv4Network::with_netmask(address, mask).ok())
+                    .and_then(|network| Ipv4Network::new(network.network(), network.prefix()).ok());
+            }
+            ROUTER => offer.router = ipv4(value),
+            DNS_SERVERS => offer.dns = value.chunks(4).filter_map(ipv4).collect(),
+            LEASE_TIME => {
+                offer.lease_secs = value.try_into().ok().map(u32::from_be_bytes);
+            }
+            _ => {}
+        }
+        options = &rest[length as usize..];
+    }
+    (message_type == Some(OFFER)).then_some(offer)
+}
+
+/// Mark the offers nobody asked for: those from servers outside `configured`,
+/// or else not from the one the current lease came from, or else all of
+/// them when more than one server answered
+pub fn judge(offers: &mut [Offer], configured: &[String], lease: Option<Ipv4Addr>) -> Basis {
+    let configured: Vec<Ipv4Addr> = configured
+        .iter()
+        .filter_map(|server| server.parse().ok())
+        .collect();
+    let basis = match lease {
+        _ if !configured.is_empty() => Basis::Configured,
+        Some(lease) => Basis::Lease(lease),
+        None => Basis::Unknown,
+    };
+    let single = offers.len() <= 1;
+    for offer in offers.iter_mut() {
+        offer.expected = match basis {
+            Basis::Configured => configured.contains(&offer.server),
+            Basis::Lease(lease) => offer.server == lease,
+            Basis::Unknown => single,
+        };
+    }
+    basis
+}
+
+/// SERVER_ADDRESS from a systemd-networkd lease file
+pub fn lease_server(content: &str) -> Option<Ipv4Addr> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("SERVER_ADDRESS="))
+        .and_then(|server| server.trim().parse().ok())
+}
+
+/// Where networkd's lease for `interface` says it came from, if it has one
+fn current_lease_server(interface: &str) -> Option<Ipv4Addr> {
+    let index = fs::read_to_string(format!("/sys/class/net/{}/ifindex", interface)).ok()?;
+    let lease = fs::read_to_string(format!("/run/systemd/netif/leases/{}", index.trim())).ok()?;
+    lease_server(&lease)
+}
+
+fn parse_mac(interface: &str, mac: &str) -> Result<[u8; 6]> {
+    let octets: Vec<u8> = mac
+        .split(':')
+        .map(|octet| u8::from_str_radix(octet, 16))
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("Bad MAC address {}", mac))?;
+    match <[u8; 6]>::try_from(octets) {
+        Ok(mac) if mac != [0; 6] => Ok(mac),
+        _ => bail!("{} has no Ethernet MAC address", interface),
+    }
+}
+
+/// Port 68 on `interface` only, able to broadcast. networkd's client uses a
+/// raw socket, so the port is normally free even while it runs.
+fn client_socket(interface: &str) -> Result<UdpSocket> {
+    let fd = socket(
+        AddressFamily::Inet,
+        SockType::Datagram,
+        SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
+        None,
+    )?;
+    setsockopt(&fd, sockopt::ReuseAddr, &true)?;
+    setsockopt(&fd, sockopt::Broadcast, &true)?;
+    setsockopt(&fd, sockopt::BindToDevice, &OsString::from(interface))
+        .context("Binding to the interface needs root")?;
+    bind(fd.as_raw_fd(), &SockaddrIn::new(0, 0, 0, 0, CLIENT_PORT))
+        .context("Port 68 is taken by another DHCP client")?;
+    Ok(UdpSocket::from_std(std::net::UdpSocket::from(fd))?)
+}
+
+async fn probe(interface: &str, mac: &str) -> Result<Vec<Offer>> {
+    let mac = parse_mac(interface, mac)?;
+    let socket = client_socket(interface)?;
+    let xid = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.subsec_nanos() ^ std::process::id())
+        .unwrap_or_default();
+    socket
+        .send_to(&discover(xid, mac), (Ipv4Addr::BROADCAST, SERVER_PORT))
+        .await
+        .context("Sending DHCPDISCOVER failed")?;
+
+    let deadline = Instant::now() + LISTEN;
+    let mut offers: Vec<Offer> = Vec::new();
+    let mut buffer = [0u8; 1500];
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await
+    {
+        let (length, from) = received?;
+        let std::net::SocketAddr::V4(from) = from else {
+            continue;
+        };
+        let Some(offer) = parse_offer(&buffer[..length], xid, *from.ip()) else {
+            continue;
+        };
+        // Servers resend, and relays may pass the same offer twice
+        if !offers.iter().any(|seen| seen.server == offer.server) {
+            offers.push(offer);
+        }
+    }
+    offers.sort_by_key(|offer| offer.server);
+    Ok(offers)
+}
+
+/// Ask every DHCP server reachable from `interface` for an offer. Nothing
+/// is accepted, so the offers lapse on the servers by themselves.
+pub async fn run(interface: &str, mac: &str, configured: &[String]) -> DhcpReport {
+    let mut report = DhcpReport {
+        interface: interface.to_string(),
+        offers: Vec::new(),
+        basis: Basis::Unknown,
+        error: None,
+    };
+    match probe(interface, mac).await {
+        Ok(mut offers) => {
+            report.basis = judge(&mut offers, configured, current_lease_server(interface));
+            report.offers = offers;
+        }
+        Err(e) => report.error = Some(format!("{:#}", e)),
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The server's side: an offer of 192.168.1.57/24 from `server`
+    fn offer_packet(xid: u32, server: [u8; 4]) -> Vec<u8> {
+        let mut packet = discover(xid, [0x3c, 0x7c, 0x3f, 0x1a, 0x2b, 0x3c]);
+        packet[0] = 2;
+        packet[16..20].copy_from_slice(&[192, 168, 1, 57]);
+        let options = HEADER_LEN + 4;
+        packet.truncate(options);
+        packet.extend_from_slice(&[MESSAGE_TYPE, 1, OFFER, PAD, SERVER_ID, 4]);
+        packet.extend_from_slice(&server);
+        packet.extend_from_slice(&[SUBNET_MASK, 4, 255, 255, 255, 0]);
+        packet.extend_from_slice(&[ROUTER, 4, 192, 168, 1, 1]);
+        packet.extend_from_slice(&[DNS_SERVERS, 8, 192, 168, 1, 1, 9, 9, 9, 9]);
+        packet.extend_from_slice(&[LEASE_TIME, 4, 0, 0, 0x0e, 0x10, END]);
+        packet
+    }
+
+    #[test]
+    fn parses_offers_and_flags_unknown_servers() {
+        let relay = Ipv4Addr::new(192, 168, 1, 254);
+        let offer = parse_offer(&offer_packet(7, [192, 168, 1, 1]), 7, relay).unwrap();
+        assert_eq!(offer.server, Ipv4Addr::new(192, 168, 1, 1));
+        assert_eq!(offer.from, relay);
+        assert_eq!(
+            offer.describe(),
+            "192.168.1.57 in 192.168.1.0/24, gateway 192.168.1.1, DNS 192.168.1.1 9.9.9.9, lease 3600s"
+        );
+        // Someone else's transaction, and our own DISCOVER echoed back
+        assert!(parse_offer(&offer_packet(7, [192, 168, 1, 1]), 8, relay).is_none());
+        assert!(parse_offer(&discover(7, [0; 6]), 7, relay).is_none());
+        assert!(parse_offer(&offer_packet(7, [192, 168, 1, 1])[..242], 7, relay).is_none());
+
+        let rogue = parse_offer(&offer_packet(7, [192, 168, 1, 23]), 7, relay).unwrap();
+        let mut offers = vec![offer, rogue];
+        let lease = lease_server(
+            "# This is private data.\nADDRESS=192.168.1.50\nSERVER_ADDRESS=192.168.1.1\n",
+        );
+        assert_eq!(
+            judge(&mut offers, &[], lease),
+            Basis::Lease(Ipv4Addr::new(192, 168, 1, 1))
+        );
+        assert_eq!((offers[0].expected, offers[1].expected), (true, false));
+
+        let configured = ["192.168.1.23".to_string()];
+        assert_eq!(judge(&mut offers, &configured, lease), Basis::Configured);
+        assert_eq!((offers[0].expected, offers[1].expected), (false, true));
+
+        assert_eq!(judge(&mut offers, &[], None), Basis::Unknown);
+        assert!(offers.iter().all(|offer| !offer.expected));
+        assert_eq!(judge(&mut offers[..1], &[], None), Basis::Unknown);
+        assert!(offers[0].expected);
+    }
+}
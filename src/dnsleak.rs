@@ -0,0 +1,186 @@
+// src/dnsleak.rs - Which resolver answers for an interface, and whether lookups escape a VPN
+use crate::command::TimedOutput;
+use crate::sanitize;
+use std::fs;
+use tokio::process::Command;
+
+/// Akamai answers with the address of the resolver that asked it, so the
+/// answer names whoever actually resolves for us, forwarders included
+pub const PROBE_NAME: &str = "whoami.akamai.net";
+// resolved races the links it may use, so one probe can miss a leak
+const DEFAULT_PATH_PROBES: usize = 3;
+
+// ARPHRD_NONE (WireGuard, tun) and ARPHRD_PPP: links without an Ethernet header
+const TUNNEL_TYPES: [&str; 2] = ["65534", "512"];
+
+/// One lookup of PROBE_NAME
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Probe {
+    /// Where the resolver's queries come from, as the authoritative server saw it
+    pub resolver: Option<String>,
+    /// The link resolved sent the query out of
+    pub link: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// No tunnel up, so nothing to leak around
+    NoVpn,
+    /// Every default-path lookup went through a tunnel
+    NoLeak,
+    /// Lookups went out of these links instead of the tunnel
+    Leak(Vec<String>),
+    /// No lookup said which link it used
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeakReport {
+    pub interface: String,
+    /// Through the interface's own DNS servers only
+    pub direct: Probe,
+    /// The way applications resolve, through whatever link resolved picks
+    pub default_path: Vec<Probe>,
+    /// Tunnels up when the test ran
+    pub vpns: Vec<String>,
+    pub verdict: Verdict,
+}
+
+/// The first answer from `resolvectl query`:
+/// "whoami.akamai.net: 192.0.2.53                   -- link: wg0"
+pub fn parse_query(output: &str) -> Probe {
+    let Some(line) = output
+        .lines()
+        .find(|line| line.trim_start().starts_with(PROBE_NAME))
+    else {
+        return Probe {
+            error: Some("No answer".to_string()),
+            ..Probe::default()
+        };
+    };
+    let (answer, link) = match line.split_once("-- link:") {
+        Some((answer, link)) => (answer, Some(link.trim().to_string())),
+        None => (line, None),
+    };
+    Probe {
+        resolver: answer
+            .split_once(": ")
+            .and_then(|(_, address)| address.split_whitespace().next())
+            .map(str::to_string),
+        link: link.filter(|link| !link.is_empty()),
+        error: None,
+    }
+}
+
+/// A leak is any default-path lookup that left through something other
+/// than a tunnel while one is up
+pub fn verdict(default_path: &[Probe], vpns: &[String]) -> Verdict {
+    if vpns.is_empty() {
+        return Verdict::NoVpn;
+    }
+    let links: Vec<&String> = default_path
+        .iter()
+        .filter_map(|probe| probe.link.as_ref())
+        .collect();
+    if links.is_empty() {
+        return Verdict::Unknown;
+    }
+    let mut leaked: Vec<String> = Vec::new();
+    for link in links {
+        if !vpns.contains(link) && !leaked.contains(link) {
+            leaked.push(link.clone());
+        }
+    }
+    if leaked.is_empty() {
+        Verdict::NoLeak
+    } else {
+        Verdict::Leak(leaked)
+    }
+}
+
+/// A WireGuard, tun or PPP link, going by its hardware type
+pub fn is_tunnel(interface: &str) -> bool {
+    fs::read_to_string(format!("/sys/class/net/{}/type", interface))
+        .is_ok_and(|kind| TUNNEL_TYPES.contains(&kind.trim()))
+}
+
+/// Look PROBE_NAME up, bypassing the cache: through `interface`'s DNS
+/// servers only, or as applications would when None
+pub async fn probe(interface: Option<&str>) -> Probe {
+    let mut command = Command::new("/usr/bin/resolvectl");
+    command.args(["query", "--cache=no", "--legend=no"]);
+    if let Some(interface) = interface {
+        command.args(["-i", interface]);
+    }
+    command.arg(PROBE_NAME);
+    match command.timed_output().await {
+        Ok(output) if output.status.success() => {
+            parse_query(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(output) => Probe {
+            error: Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+            ..Probe::default()
+        },
+        Err(e) => Probe {
+            error: Some(e.to_string()),
+            ..Probe::default()
+        },
+    }
+}
+
+/// Test `interface`; `up` are the interfaces that are up, among which the
+/// tunnels are found
+pub async fn run(interface: &str, up: &[String]) -> LeakReport {
+    let vpns: Vec<String> = up.iter().filter(|name| is_tunnel(name)).cloned().collect();
+    let direct = match sanitize::interface_name(interface) {
+        Ok(_) => probe(Some(interface)).await,
+        Err(e) => Probe {
+            error: Some(e.to_string()),
+            ..Probe::default()
+        },
+    };
+    let mut default_path = Vec::with_capacity(DEFAULT_PATH_PROBES);
+    for _ in 0..DEFAULT_PATH_PROBES {
+        default_path.push(probe(None).await);
+    }
+    LeakReport {
+        interface: interface.to_string(),
+        verdict: verdict(&default_path, &vpns),
+        direct,
+        default_path,
+        vpns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_lookups_that_skip_the_tunnel() {
+        let probe = parse_query(
+            "whoami.akamai.net: 172.253.226.34                   -- link: wlan0\n\n\
+             -- Information acquired via protocol DNS in 31.2ms.\n",
+        );
+        assert_eq!(probe.resolver.as_deref(), Some("172.253.226.34"));
+        assert_eq!(probe.link.as_deref(), Some("wlan0"));
+        assert!(parse_query("").error.is_some());
+
+        let through = |link: &str| Probe {
+            link: Some(link.to_string()),
+            ..Probe::default()
+        };
+        let vpns = vec!["wg0".to_string()];
+        assert_eq!(verdict(&[through("wlan0")], &[]), Verdict::NoVpn);
+        assert_eq!(
+            verdict(&[through("wg0"), through("wg0")], &vpns),
+            Verdict::NoLeak
+        );
+        assert_eq!(
+            verdict(&[through("wg0"), through("wlan0"), through("wlan0")], &vpns),
+            Verdict::Leak(vec!["wlan0".to_string()])
+        );
+        assert_eq!(verdict(&[Probe::default()], &vpns), Verdict::Unknown);
+    }
+}
@@ -0,0 +1,477 @@
+// src/doctor.rs - Doctor: common mistakes in the systemd-networkd files already on the system
+use crate::systemd::validate_network_file;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+pub const NETWORKD_DIR: &str = "/etc/systemd/network";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Hint,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// One Key=value line, with the section it is in
+struct Setting<'a> {
+    section: &'a str,
+    key: &'a str,
+    value: &'a str,
+    line: usize,
+}
+
+/// Name= patterns, and the other [Match] settings as key and value
+type MatchKeys<'a> = (Vec<&'a str>, Vec<(&'a str, &'a str)>);
+
+struct Unit<'a> {
+    path: &'a Path,
+    settings: Vec<Setting<'a>>,
+}
+
+impl<'a> Unit<'a> {
+    fn parse(path: &'a Path, contents: &'a str) -> Self {
+        let mut settings = Vec::new();
+        let mut section = "";
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name;
+            } else if let Some((key, value)) = line.split_once('=') {
+                settings.push(Setting {
+                    section,
+                    key: key.trim(),
+                    value: value.trim(),
+                    line: number + 1,
+                });
+            }
+        }
+        Unit { path, settings }
+    }
+
+    fn get(&self, section: &str, key: &str) -> impl Iterator<Item = &Setting<'a>> + '_ {
+        let (section, key) = (section.to_string(), key.to_string());
+        self.settings
+            .iter()
+            .filter(move |s| s.section == section && s.key == key)
+    }
+
+    fn first(&self, section: &str, key: &str) -> Option<&Setting<'a>> {
+        self.get(section, key).next()
+    }
+
+    fn is_network(&self) -> bool {
+        self.path.extension().is_some_and(|e| e == "network")
+    }
+
+    /// Name= patterns and the other [Match] settings, sorted; None with a
+    /// negated pattern, as there is no telling what that leaves
+    fn match_keys(&self) -> Option<MatchKeys<'a>> {
+        let mut names = Vec::new();
+        let mut others = Vec::new();
+        for setting in self.settings.iter().filter(|s| s.section == "Match") {
+            if setting.key == "Name" {
+                names.extend(setting.value.split_whitespace());
+            } else {
+                others.push((setting.key, setting.value));
+            }
+        }
+        if names.iter().any(|name| name.starts_with('!')) {
+            return None;
+        }
+        others.sort();
+        Some((names, others))
+    }
+
+    fn match_line(&self) -> Option<usize> {
+        self.settings
+            .iter()
+            .find(|s| s.section == "Match")
+            .map(|s| s.line)
+    }
+
+    fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Lint the .network and .link files in `dir`
+pub fn check_dir(dir: &Path) -> Result<Vec<Finding>> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|e| e == "network" || e == "link")
+        })
+        .collect();
+    paths.sort();
+
+    let mut files = Vec::new();
+    let mut findings = Vec::new();
+    for path in paths {
+        match fs::read_to_string(&path) {
+            Ok(contents) => files.push((path, contents)),
+            Err(e) => findings.push(Finding {
+                severity: Severity::Error,
+                path,
+                line: None,
+                message: format!("Can't be read: {}", e),
+            }),
+        }
+    }
+    findings.extend(lint(&files));
+    Ok(findings)
+}
+
+/// Problems in `files`, given in the order networkd reads them (by name).
+/// Sorted by file, then line.
+pub fn lint(files: &[(PathBuf, String)]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    // The rest only makes sense of files networkd can read
+    let mut units = Vec::new();
+    for (path, contents) in files {
+        let unit = Unit::parse(path, contents);
+        match validate_network_file(contents) {
+            Ok(()) => units.push(unit),
+            Err(e) => findings.push(finding(&unit, Severity::Error, None, format!("{:#}", e))),
+        }
+    }
+    overlapping_matches(&units, &mut findings);
+    duplicate_addresses(&units, &mut findings);
+    for unit in units.iter().filter(|unit| unit.is_network()) {
+        missing_required_for_online(unit, &mut findings);
+        dhcp_and_static(unit, &mut findings);
+    }
+
+    findings.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    findings
+}
+
+fn finding(unit: &Unit, severity: Severity, line: Option<usize>, message: String) -> Finding {
+    Finding {
+        severity,
+        path: unit.path.to_path_buf(),
+        line,
+        message,
+    }
+}
+
+/// networkd applies only the first .network (or .link) file that matches an
+/// interface, so a later one matching the same interfaces is dead
+fn overlapping_matches(units: &[Unit], findings: &mut Vec<Finding>) {
+    for (i, later) in units.iter().enumerate() {
+        let Some(later_keys) = later.match_keys() else {
+            continue;
+        };
+        let earlier = units[..i].iter().find(|earlier| {
+            earlier.is_network() == later.is_network()
+                && earlier
+                    .match_keys()
+                    .is_some_and(|keys| matches_overlap(&keys, &later_keys))
+        });
+        if let Some(earlier) = earlier {
+            findings.push(finding(
+                later,
+                Severity::Warning,
+                later.match_line(),
+                format!(
+                    "[Match] overlaps {}, which comes first; networkd applies only that one to interfaces both match",
+                    earlier.name()
+                ),
+            ));
+        }
+    }
+}
+
+fn matches_overlap((a_names, a_others): &MatchKeys, (b_names, b_others): &MatchKeys) -> bool {
+    // Matching on MAC, driver or path as well: only the same ones surely overlap
+    if a_others != b_others {
+        return false;
+    }
+    if a_names.is_empty() || b_names.is_empty() {
+        return true;
+    }
+    a_names
+        .iter()
+        .any(|a| b_names.iter().any(|b| patterns_overlap(a, b)))
+}
+
+fn has_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+fn patterns_overlap(a: &str, b: &str) -> bool {
+    match (has_glob(a), has_glob(b)) {
+        (false, _) => glob_match(b, a),
+        (_, false) => glob_match(a, b),
+        // Two globs: judge by what comes before the first wildcard
+        (true, true) => {
+            let prefix = |p: &str| p[..p.find(['*', '?', '[']).unwrap_or(p.len())].to_string();
+            let (a, b) = (prefix(a), prefix(b));
+            a.starts_with(&b) || b.starts_with(&a)
+        }
+    }
+}
+
+/// fnmatch(3) with `*`, `?` and `[...]` classes, as networkd's Name= uses
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_chars(&pattern, &text)
+}
+
+fn glob_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => (0..=text.len()).any(|skip| glob_chars(&pattern[1..], &text[skip..])),
+        Some('?') => !text.is_empty() && glob_chars(&pattern[1..], &text[1..]),
+        Some('[') => {
+            let Some(end) = pattern
+                .iter()
+                .skip(2)
+                .position(|&c| c == ']')
+                .map(|p| p + 2)
+            else {
+                return text.first() == Some(&'[') && glob_chars(&pattern[1..], &text[1..]);
+            };
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            let class = &pattern[1..end];
+            let (negated, class) = match class.first() {
+                Some('!' | '^') => (true, &class[1..]),
+                _ => (false, class),
+            };
+            let mut hit = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    hit |= (class[i]..=class[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    hit |= class[i] == c;
+                    i += 1;
+                }
+            }
+            hit != negated && glob_chars(&pattern[end + 1..], &text[1..])
+        }
+        Some(&p) => text.first() == Some(&p) && glob_chars(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Address= values, in [Network] or an [Address] section, without the prefix
+fn addresses<'a>(unit: &'a Unit) -> impl Iterator<Item = (IpAddr, &'a Setting<'a>)> + 'a {
+    unit.settings
+        .iter()
+        .filter(|s| s.key == "Address" && (s.section == "Network" || s.section == "Address"))
+        .filter_map(|s| {
+            let address = s.value.split('/').next()?.parse().ok()?;
+            Some((address, s))
+        })
+}
+
+fn duplicate_addresses(units: &[Unit], findings: &mut Vec<Finding>) {
+    let mut seen: HashMap<IpAddr, (&Unit, usize)> = HashMap::new();
+    for unit in units.iter().filter(|unit| unit.is_network()) {
+        for (address, setting) in addresses(unit) {
+            // 0.0.0.0 and :: ask networkd to pick a free address
+            if address.is_unspecified() {
+                continue;
+            }
+            match seen.get(&address) {
+                Some((first, line)) => {
+                    let place = if first.path == unit.path {
+                        format!("line {}", line)
+                    } else {
+                        format!("{} line {}", first.name(), line)
+                    };
+                    findings.push(finding(
+                        unit,
+                        Severity::Warning,
+                        Some(setting.line),
+                        format!("Address {} is also set on {}", address, place),
+                    ));
+                }
+                None => {
+                    seen.insert(address, (unit, setting.line));
+                }
+            }
+        }
+    }
+}
+
+/// Without RequiredForOnline=, boot waits in systemd-networkd-wait-online for
+/// every interface the file matches; a problem for WiFi and for patterns that
+/// match links which may never come up
+fn missing_required_for_online(unit: &Unit, findings: &mut Vec<Finding>) {
+    if unit.first("Link", "RequiredForOnline").is_some()
+        || unit
+            .first("Link", "Unmanaged")
+            .is_some_and(|s| matches!(s.value, "yes" | "true" | "1" | "on"))
+    {
+        return;
+    }
+    let Some((names, others)) = unit.match_keys() else {
+        return;
+    };
+    let wireless = names.iter().any(|name| name.starts_with("wl"))
+        || others.contains(&("Type", "wlan"))
+        || others.iter().any(|(key, _)| *key == "SSID");
+    let wildcard = (names.is_empty() && others.is_empty()) || names.iter().any(|n| has_glob(n));
+    let reason = if wireless {
+        "a WiFi link"
+    } else if wildcard {
+        "every interface the pattern matches"
+    } else {
+        return;
+    };
+    findings.push(finding(
+        unit,
+        Severity::Hint,
+        unit.match_line(),
+        format!(
+            "No RequiredForOnline=: boot waits for {} to come online; add RequiredForOnline=no under [Link] if it may not",
+            reason
+        ),
+    ));
+}
+
+fn dhcp_and_static(unit: &Unit, findings: &mut Vec<Finding>) {
+    let Some(dhcp) = unit.first("Network", "DHCP") else {
+        return;
+    };
+    let (v4, v6) = match dhcp.value {
+        "yes" | "true" | "both" | "1" | "on" => (true, true),
+        "ipv4" => (true, false),
+        "ipv6" => (false, true),
+        _ => return,
+    };
+    let covers = |address: &IpAddr| if address.is_ipv4() { v4 } else { v6 };
+
+    for (address, setting) in addresses(unit) {
+        let link_local = match address {
+            IpAddr::V4(v4) => v4.is_link_local(),
+            IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) == 0xfe80,
+        };
+        if covers(&address) && !link_local && !address.is_unspecified() {
+            findings.push(finding(
+                unit,
+                Severity::Warning,
+                Some(setting.line),
+                format!(
+                    "DHCP={} and a static address {}: the link gets both",
+                    dhcp.value, setting.value
+                ),
+            ));
+        }
+    }
+    for gateway in unit.get("Network", "Gateway") {
+        let Ok(address) = gateway.value.parse::<IpAddr>() else {
+            continue;
+        };
+        if covers(&address) {
+            findings.push(finding(
+                unit,
+                Severity::Warning,
+                Some(gateway.line),
+                format!(
+                    "Gateway={} and DHCP={} both add a default route",
+                    gateway.value, dhcp.value
+                ),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, contents: &str) -> (PathBuf, String) {
+        (Path::new(NETWORKD_DIR).join(name), contents.to_string())
+    }
+
+    fn messages(findings: &[Finding]) -> Vec<(String, Option<usize>, Severity)> {
+        findings
+            .iter()
+            .map(|f| {
+                let name = f.path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, f.line, f.severity)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flags_overlaps_duplicates_and_dhcp_conflicts() {
+        let files = [
+            file(
+                "10-eth0.network",
+                "[Match]\nName=eth0\n\n[Network]\nAddress=192.168.1.5/24\n",
+            ),
+            file(
+                "20-wired.network",
+                "[Match]\nName=eth* en*\n\n[Link]\nRequiredForOnline=no\n\n[Network]\nDHCP=yes\nAddress=192.168.1.5/24\nGateway=192.168.1.1\n",
+            ),
+            file(
+                "30-wifi.network",
+                "[Match]\nName=wlan0\n\n[Network]\nDHCP=ipv6\nAddress=10.0.0.2/24\n",
+            ),
+            file("40-mac.network", "[Match]\nMACAddress=aa:bb:cc:dd:ee:ff\n\n[Network]\nDHCP=yes\n"),
+            file("50-broken.network", "Name=eth0\n"),
+        ];
+
+        let findings = lint(&files);
+        assert_eq!(
+            messages(&findings),
+            vec![
+                ("20-wired.network".to_string(), Some(2), Severity::Warning), // Overlaps 10-eth0
+                ("20-wired.network".to_string(), Some(9), Severity::Warning), // Duplicate address
+                ("20-wired.network".to_string(), Some(9), Severity::Warning), // DHCP + static
+                ("20-wired.network".to_string(), Some(10), Severity::Warning), // Gateway + DHCP
+                ("30-wifi.network".to_string(), Some(2), Severity::Hint),
+                ("50-broken.network".to_string(), None, Severity::Error),
+            ]
+        );
+        assert!(findings[0].message.contains("10-eth0.network"));
+        assert!(findings[1].message.contains("10-eth0.network line 5"));
+        assert!(findings[2].message.contains("gets both"));
+    }
+
+    #[test]
+    fn matches_globs_like_fnmatch() {
+        assert!(glob_match("en*", "enp3s0"));
+        assert!(glob_match("eth[0-3]", "eth2"));
+        assert!(!glob_match("eth[!0-3]", "eth2"));
+        assert!(glob_match("wl?0", "wlp0"));
+        assert!(!glob_match("en*", "eth0"));
+        assert!(patterns_overlap("en*", "enp*"));
+        assert!(!patterns_overlap("wl*", "en*"));
+    }
+}
@@ -0,0 +1,100 @@
+// src/editor.rs - Hand-editing config files in $EDITOR, validated before they are saved
+use crate::secure_file;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How an edit ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edited {
+    Saved,
+    Unchanged,
+    Discarded, // Invalid, and the user gave up on it
+}
+
+/// $VISUAL, then $EDITOR, then vi. The variable may carry arguments ("code -w").
+fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|value| value.split_whitespace().map(str::to_string).collect())
+        .find(|words: &Vec<String>| !words.is_empty())
+        .unwrap_or_else(|| vec!["/usr/bin/vi".to_string()])
+}
+
+/// Open a private copy of `path` in the user's editor and, once it validates,
+/// write it back atomically. An invalid edit is reported on the terminal with
+/// the choice to fix it or throw it away, so the real file is never left
+/// broken. The caller suspends the TUI around this.
+pub fn edit(path: &Path, validate: impl Fn(&str) -> Result<()>, private: bool) -> Result<Edited> {
+    let original = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let draft = draft_path(path)?;
+    secure_file::write_private(&draft, &original)?;
+
+    let result = edit_draft(path, &draft, &original, validate, private);
+    let _ = fs::remove_file(&draft);
+    result
+}
+
+fn edit_draft(
+    path: &Path,
+    draft: &Path,
+    original: &str,
+    validate: impl Fn(&str) -> Result<()>,
+    private: bool,
+) -> Result<Edited> {
+    let command = editor_command();
+    loop {
+        let status = Command::new(&command[0])
+            .args(&command[1..])
+            .arg(draft)
+            .status()
+            .with_context(|| format!("Failed to start {}", command[0]))?;
+        if !status.success() {
+            anyhow::bail!("{} exited with {}", command[0], status);
+        }
+
+        let edited = fs::read_to_string(draft)?;
+        if edited == original {
+            return Ok(Edited::Unchanged);
+        }
+        if let Err(e) = validate(&edited) {
+            println!("\n{}: {:#}", path.display(), e);
+            print!("(e)dit again or (d)iscard the changes? [e] ");
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().lock().read_line(&mut answer)?;
+            if answer.trim().starts_with(['d', 'D']) {
+                return Ok(Edited::Discarded);
+            }
+            continue;
+        }
+
+        let edited = crate::provenance::stamp(&edited, "Edited in $EDITOR");
+        if private {
+            secure_file::write_private(path, &edited)?;
+        } else {
+            secure_file::write_public(path, &edited)?;
+        }
+        return Ok(Edited::Saved);
+    }
+}
+
+// In the temp directory, keeping the file name so the editor picks the right
+// syntax highlighting
+fn draft_path(path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("Invalid file path: {}", path.display()))?;
+    Ok(std::env::temp_dir().join(format!(
+        "lantern-edit-{}-{}",
+        std::process::id(),
+        name.to_string_lossy()
+    )))
+}
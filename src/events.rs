@@ -0,0 +1,339 @@
+// src/events.rs - Interface change detection and the event timeline
+use crate::network::Interface;
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const MAX_EVENTS: usize = 200;
+/// How long a changed row stays highlighted
+pub const FLASH_DURATION: Duration = Duration::from_secs(3);
+/// Carrier transitions are counted over this window to spot a flapping link
+pub const FLAP_WINDOW: Duration = Duration::from_secs(300);
+/// Transitions within the window that make a link count as flapping
+pub const FLAP_THRESHOLD: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Added,
+    Removed,
+    StateChanged,
+    AddressAdded,
+    AddressRemoved,
+    WifiChanged,
+    EndpointChanged,
+    LinkFlapping,
+    WifiDropped, // Disconnect, beacon loss or failed connect, with the 802.11 reason
+    MacConflict,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkEvent {
+    pub timestamp: DateTime<Local>,
+    pub interface: String,
+    pub kind: EventKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventTimeline {
+    events: VecDeque<NetworkEvent>,
+    flashes: HashMap<String, (EventKind, Instant)>,
+    // Last SSID seen per interface; periodic refreshes don't carry WiFi details
+    known_ssids: HashMap<String, Option<String>>,
+    flaps: LinkFlaps,
+}
+
+/// Carrier transitions per interface, from the kernel's IFLA_CARRIER_CHANGES
+/// counter so that bounces between two refreshes are not missed.
+#[derive(Debug, Clone, Default)]
+pub struct LinkFlaps {
+    links: HashMap<String, FlapHistory>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct FlapHistory {
+    last_count: Option<u32>,
+    transitions: VecDeque<Instant>,
+    total: u32, // Since lantern started watching
+}
+
+impl LinkFlaps {
+    /// Take a new counter reading. Returns true when the link has just
+    /// crossed into flapping, so that is reported once per episode.
+    pub fn record(&mut self, interface: &str, carrier_changes: u32, now: Instant) -> bool {
+        let history = self.links.entry(interface.to_string()).or_default();
+        let was_flapping = history.transitions.len() >= FLAP_THRESHOLD;
+
+        // The first reading is only a baseline; a lower one means the device was recreated
+        if let Some(last) = history.last_count.filter(|&last| carrier_changes > last) {
+            let new = carrier_changes - last;
+            history.total += new;
+            // Only the window's worth matters, however many came in at once
+            for _ in 0..new.min(FLAP_THRESHOLD as u32 * 4) {
+                history.transitions.push_back(now);
+            }
+        }
+        history.last_count = Some(carrier_changes);
+        while history
+            .transitions
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > FLAP_WINDOW)
+        {
+            history.transitions.pop_front();
+        }
+
+        !was_flapping && history.transitions.len() >= FLAP_THRESHOLD
+    }
+
+    /// Transitions within the flap window
+    pub fn recent(&self, interface: &str) -> usize {
+        self.links
+            .get(interface)
+            .map_or(0, |history| history.transitions.len())
+    }
+
+    pub fn total(&self, interface: &str) -> u32 {
+        self.links.get(interface).map_or(0, |history| history.total)
+    }
+
+    pub fn is_flapping(&self, interface: &str) -> bool {
+        self.recent(interface) >= FLAP_THRESHOLD
+    }
+}
+
+impl EventTimeline {
+    /// Compare two interface snapshots, log what changed and flash the rows.
+    pub fn record_changes(&mut self, old: &[Interface], new: &[Interface]) {
+        for (interface, kind, message) in diff_interfaces(old, new) {
+            self.push(interface, kind, message);
+        }
+
+        let now = Instant::now();
+        for iface in new {
+            let Some(changes) = iface.carrier_changes else {
+                continue;
+            };
+            if self.flaps.record(&iface.name, changes, now) {
+                let message = format!(
+                    "{} link flapping: {} carrier changes in {} min, check the cable and switch port",
+                    iface.name,
+                    self.flaps.recent(&iface.name),
+                    FLAP_WINDOW.as_secs() / 60
+                );
+                self.push(iface.name.clone(), EventKind::LinkFlapping, message);
+            }
+        }
+    }
+
+    pub fn flaps(&self) -> &LinkFlaps {
+        &self.flaps
+    }
+
+    /// Record the network a WiFi interface is on, logging a change from the
+    /// last known one. The first observation only sets the baseline.
+    pub fn record_wifi(&mut self, interface: &str, ssid: Option<String>) {
+        let previous = self.known_ssids.insert(interface.to_string(), ssid.clone());
+        if previous.is_some_and(|previous| previous != ssid) {
+            let message = match ssid {
+                Some(network) => format!("{} connected to {}", interface, network),
+                None => format!("{} disconnected", interface),
+            };
+            self.push(interface.to_string(), EventKind::WifiChanged, message);
+        }
+    }
+
+    pub fn push(&mut self, interface: String, kind: EventKind, message: String) {
+        self.flashes
+            .insert(interface.clone(), (kind, Instant::now()));
+        self.events.push_back(NetworkEvent {
+            timestamp: Local::now(),
+            interface,
+            kind,
+            message,
+        });
+        while self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+    }
+
+    /// The kind of the change currently highlighted on this row, if any.
+    pub fn flash(&self, interface: &str) -> Option<EventKind> {
+        self.flashes
+            .get(interface)
+            .filter(|(_, at)| at.elapsed() < FLASH_DURATION)
+            .map(|(kind, _)| *kind)
+    }
+
+    /// True while a flash is showing or has just ended and must be cleared.
+    pub fn needs_redraw(&self) -> bool {
+        self.flashes
+            .values()
+            .any(|(_, at)| at.elapsed() < FLASH_DURATION + Duration::from_secs(1))
+    }
+
+    /// Newest first.
+    pub fn recent(&self) -> impl Iterator<Item = &NetworkEvent> {
+        self.events.iter().rev()
+    }
+}
+
+/// Describe the differences between two snapshots as (interface, kind, message).
+pub fn diff_interfaces(old: &[Interface], new: &[Interface]) -> Vec<(String, EventKind, String)> {
+    let mut changes = Vec::new();
+
+    for iface in new {
+        let Some(before) = old.iter().find(|o| o.name == iface.name) else {
+            changes.push((
+                iface.name.clone(),
+                EventKind::Added,
+                format!("{} appeared", iface.name),
+            ));
+            continue;
+        };
+
+        if before.state != iface.state {
+            changes.push((
+                iface.name.clone(),
+                EventKind::StateChanged,
+                format!("{} {} → {}", iface.name, before.state, iface.state),
+            ));
+        }
+
+        let addresses = |i: &Interface| -> Vec<String> {
+            i.ipv4_addresses
+                .iter()
+                .chain(&i.ipv6_addresses)
+                .cloned()
+                .collect()
+        };
+        let (before_addrs, after_addrs) = (addresses(before), addresses(iface));
+        for addr in after_addrs.iter().filter(|a| !before_addrs.contains(a)) {
+            changes.push((
+                iface.name.clone(),
+                EventKind::AddressAdded,
+                format!("{} gained {}", iface.name, addr),
+            ));
+        }
+        for addr in before_addrs.iter().filter(|a| !after_addrs.contains(a)) {
+            changes.push((
+                iface.name.clone(),
+                EventKind::AddressRemoved,
+                format!("{} lost {}", iface.name, addr),
+            ));
+        }
+    }
+
+    for iface in old.iter().filter(|o| !new.iter().any(|n| n.name == o.name)) {
+        changes.push((
+            iface.name.clone(),
+            EventKind::Removed,
+            format!("{} disappeared", iface.name),
+        ));
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_kind::LinkKind;
+    use crate::network::InterfaceStats;
+
+    fn iface(name: &str, state: &str, addrs: &[&str]) -> Interface {
+        Interface {
+            name: name.to_string(),
+            mac_address: String::new(),
+            state: state.to_string(),
+            mtu: 1500,
+            ipv4_addresses: addrs.iter().map(|a| a.to_string()).collect(),
+            ipv6_addresses: Vec::new(),
+            ipv6_info: None,
+            gateway: None,
+            ipv6_gateway: None,
+            dns_servers: Vec::new(),
+            stats: InterfaceStats::default(),
+            wifi_info: None,
+            hardware: None,
+            admin_up: true,
+            carrier: Some(true),
+            carrier_changes: None,
+            promiscuous: false,
+            monitor: false,
+            kind: LinkKind::Physical,
+            master: None,
+            lower: None,
+            peer_netns: None,
+        }
+    }
+
+    #[test]
+    fn detects_state_address_and_presence_changes() {
+        let old = vec![
+            iface("eth0", "DOWN", &[]),
+            iface("usb0", "UP", &["10.0.0.2/24"]),
+        ];
+        let new = vec![
+            iface("eth0", "UP", &["192.168.1.5/24"]),
+            iface("wg0", "UNKNOWN", &[]),
+        ];
+
+        let kinds: Vec<(String, EventKind)> = diff_interfaces(&old, &new)
+            .into_iter()
+            .map(|(name, kind, _)| (name, kind))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                ("eth0".to_string(), EventKind::StateChanged),
+                ("eth0".to_string(), EventKind::AddressAdded),
+                ("wg0".to_string(), EventKind::Added),
+                ("usb0".to_string(), EventKind::Removed),
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_snapshot_records_nothing() {
+        let snapshot = vec![iface("eth0", "UP", &["192.168.1.5/24"])];
+        let mut timeline = EventTimeline::default();
+        timeline.record_changes(&snapshot, &snapshot);
+        assert_eq!(timeline.recent().count(), 0);
+        assert!(timeline.flash("eth0").is_none());
+    }
+
+    #[test]
+    fn counts_carrier_flaps_in_window() {
+        let mut flaps = LinkFlaps::default();
+        let start = Instant::now();
+
+        assert!(!flaps.record("eth0", 10, start)); // Baseline only
+        assert_eq!(flaps.total("eth0"), 0);
+        assert!(!flaps.record("eth0", 12, start));
+        assert!(flaps.record("eth0", 14, start + Duration::from_secs(60)));
+        assert!(flaps.is_flapping("eth0"));
+        // Reported once per episode
+        assert!(!flaps.record("eth0", 15, start + Duration::from_secs(90)));
+        assert_eq!((flaps.recent("eth0"), flaps.total("eth0")), (5, 5));
+
+        // Quiet for a while: the window empties, the total stays
+        assert!(!flaps.record("eth0", 15, start + Duration::from_secs(500)));
+        assert!(!flaps.is_flapping("eth0"));
+        assert_eq!((flaps.recent("eth0"), flaps.total("eth0")), (0, 5));
+    }
+
+    #[test]
+    fn wifi_changes_after_baseline() {
+        let mut timeline = EventTimeline::default();
+        timeline.record_wifi("wlan0", Some("Home".to_string()));
+        timeline.record_wifi("wlan0", Some("Home".to_string()));
+        assert_eq!(timeline.recent().count(), 0);
+
+        timeline.record_wifi("wlan0", None);
+        let event = timeline.recent().next().unwrap();
+        assert_eq!(event.kind, EventKind::WifiChanged);
+        assert_eq!(event.message, "wlan0 disconnected");
+        assert_eq!(timeline.flash("wlan0"), Some(EventKind::WifiChanged));
+    }
+}
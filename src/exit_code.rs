@@ -0,0 +1,112 @@
+// src/exit_code.rs - Exit codes scripts can branch on instead of matching stderr
+use crate::command;
+use crate::lock::LockError;
+use crate::network::NetworkError;
+use std::io;
+
+/// How a CLI run ended. The numbers are stable; new failures get new codes
+/// rather than reusing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Ok = 0,
+    /// Anything without a more specific code
+    Failure = 1,
+    /// Not root, or the kernel refused the operation
+    Permission = 2,
+    /// An external tool (ip, iw, ethtool, ...) isn't installed
+    MissingTool = 3,
+    InterfaceNotFound = 4,
+    /// An external command stopped responding (see `[timeouts]`)
+    Timeout = 5,
+    /// Another lantern instance holds the lock; try again
+    Busy = 6,
+    /// Bad arguments on the command line
+    Usage = 7,
+}
+
+impl ExitCode {
+    /// The code for an error, from the most specific cause in its chain
+    pub fn of(err: &anyhow::Error) -> Self {
+        if command::is_timeout(err) {
+            return ExitCode::Timeout;
+        }
+        if command::is_missing_tool(err) {
+            return ExitCode::MissingTool;
+        }
+        if err
+            .chain()
+            .any(|cause| matches!(cause.downcast_ref(), Some(LockError::Busy)))
+        {
+            return ExitCode::Busy;
+        }
+        for cause in err.chain() {
+            match cause.downcast_ref::<NetworkError>() {
+                Some(
+                    NetworkError::InterfaceNotFound { .. } | NetworkError::NoWirelessInterface,
+                ) => return ExitCode::InterfaceNotFound,
+                Some(NetworkError::PermissionDenied { .. }) => return ExitCode::Permission,
+                _ => {}
+            }
+            if cause
+                .downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::PermissionDenied)
+            {
+                return ExitCode::Permission;
+            }
+        }
+
+        // What ip and friends print on stderr, carried in the message
+        let message = format!("{:#}", err);
+        if message.contains("Cannot find device") {
+            ExitCode::InterfaceNotFound
+        } else if message.contains("Operation not permitted") {
+            ExitCode::Permission
+        } else {
+            ExitCode::Failure
+        }
+    }
+
+    pub fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::MissingTool;
+    use std::time::Duration;
+
+    #[test]
+    fn codes_follow_the_cause() {
+        let timeout = io::Error::new(
+            io::ErrorKind::TimedOut,
+            command::CommandTimeout {
+                program: "iw".to_string(),
+                timeout: Duration::from_secs(30),
+            },
+        );
+        assert_eq!(ExitCode::of(&timeout.into()), ExitCode::Timeout);
+
+        let missing = io::Error::new(
+            io::ErrorKind::NotFound,
+            MissingTool {
+                program: "ethtool".to_string(),
+            },
+        );
+        let missing = anyhow::Error::new(missing).context("Failed to read ring sizes");
+        assert_eq!(ExitCode::of(&missing), ExitCode::MissingTool);
+
+        let gone = NetworkError::InterfaceNotFound {
+            interface: "eth9".to_string(),
+        };
+        assert_eq!(ExitCode::of(&gone.into()), ExitCode::InterfaceNotFound);
+        let refused = anyhow::anyhow!("ip: RTNETLINK answers: Operation not permitted");
+        assert_eq!(ExitCode::of(&refused), ExitCode::Permission);
+        assert_eq!(ExitCode::of(&LockError::Busy.into()), ExitCode::Busy);
+
+        // A file that isn't there is not a missing tool
+        let file = io::Error::from(io::ErrorKind::NotFound);
+        assert_eq!(ExitCode::of(&file.into()), ExitCode::Failure);
+    }
+}
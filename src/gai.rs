@@ -0,0 +1,196 @@
+// src/gai.rs - Whether getaddrinfo() hands out IPv4 or IPv6 addresses first, through /etc/gai.conf
+use crate::secure_file;
+use anyhow::{Context, Result};
+use std::fs;
+
+pub const GAI_CONF: &str = "/etc/gai.conf";
+/// Lantern's lines in gai.conf sit between these; the rest of the file is left alone
+const BEGIN: &str = "# BEGIN lantern: address family preference";
+const END: &str = "# END lantern";
+
+/// The name looked up to show which address applications try first
+pub const SAMPLE_NAME: &str = "www.google.com";
+
+/// RFC 6724's policy table with IPv4-mapped addresses raised above every
+/// IPv6 prefix. Any precedence line replaces glibc's whole built-in table,
+/// so the rest of it has to be spelled out again.
+const PREFER_IPV4: [&str; 9] = [
+    "precedence ::1/128 50",
+    "precedence ::/0 40",
+    "precedence ::ffff:0:0/96 100",
+    "precedence 2002::/16 30",
+    "precedence 2001::/32 5",
+    "precedence fc00::/7 3",
+    "precedence ::/96 1",
+    "precedence fec0::/10 1",
+    "precedence 3ffe::/16 1",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preference {
+    /// glibc's default: IPv6 first whenever the host has a global IPv6 address
+    Ipv6,
+    /// IPv4 first; IPv6 is still used when a name has no IPv4 address
+    Ipv4,
+}
+
+impl Preference {
+    pub fn label(self) -> &'static str {
+        match self {
+            Preference::Ipv6 => "Prefer IPv6 (default)",
+            Preference::Ipv4 => "Prefer IPv4",
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Preference::Ipv6 => Preference::Ipv4,
+            Preference::Ipv4 => Preference::Ipv6,
+        }
+    }
+}
+
+/// What gai.conf says about address order
+#[derive(Debug, Clone, PartialEq)]
+pub struct GaiState {
+    /// Lantern's block if there is one, IPv6 first otherwise
+    pub preference: Preference,
+    /// precedence lines outside lantern's block, which glibc merges with it
+    pub foreign: Vec<String>,
+}
+
+impl Default for GaiState {
+    fn default() -> Self {
+        Self {
+            preference: Preference::Ipv6,
+            foreign: Vec::new(),
+        }
+    }
+}
+
+fn is_precedence(line: &str) -> bool {
+    line.split_whitespace().next() == Some("precedence")
+}
+
+/// Split gai.conf into lantern's block and everything else
+fn split(content: &str) -> (Vec<&str>, Vec<&str>) {
+    let mut ours = Vec::new();
+    let mut rest = Vec::new();
+    let mut inside = false;
+    for line in content.lines() {
+        match line.trim() {
+            BEGIN => inside = true,
+            END if inside => inside = false,
+            _ if inside => ours.push(line),
+            _ => rest.push(line),
+        }
+    }
+    (ours, rest)
+}
+
+pub fn parse(content: &str) -> GaiState {
+    let (ours, rest) = split(content);
+    let preference = if ours.iter().any(|line| is_precedence(line)) {
+        Preference::Ipv4
+    } else {
+        Preference::Ipv6
+    };
+    GaiState {
+        preference,
+        foreign: rest
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| is_precedence(line))
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+/// `content` with lantern's block replaced by one for `preference`; the
+/// default needs no block at all
+pub fn apply(content: &str, preference: Preference) -> String {
+    let (_, rest) = split(content);
+    let mut lines: Vec<String> = rest.iter().map(|line| line.to_string()).collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    if preference == Preference::Ipv4 {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(BEGIN.to_string());
+        lines.extend(PREFER_IPV4.iter().map(|line| line.to_string()));
+        lines.push(END.to_string());
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!("{}\n", lines.join("\n"))
+}
+
+pub fn load() -> GaiState {
+    fs::read_to_string(GAI_CONF)
+        .map(|content| parse(&content))
+        .unwrap_or_default()
+}
+
+/// Rewrite lantern's block. glibc notices the changed file on the next
+/// lookup, so nothing needs restarting.
+pub fn save(preference: Preference) -> Result<()> {
+    let content = match fs::read_to_string(GAI_CONF) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", GAI_CONF)),
+    };
+    let updated = apply(&content, preference);
+    if updated == content {
+        return Ok(());
+    }
+    let updated = crate::provenance::stamp(&updated, "Address family preference");
+    secure_file::write_public(GAI_CONF, updated)
+        .with_context(|| format!("Failed to write {}", GAI_CONF))
+}
+
+/// The addresses getaddrinfo() returns for `name`, in the order
+/// applications try them
+pub async fn address_order(name: &str) -> Result<Vec<String>> {
+    let addresses = tokio::net::lookup_host((name, 443))
+        .await
+        .with_context(|| format!("Failed to look up {}", name))?;
+    let mut order: Vec<String> = Vec::new();
+    for address in addresses {
+        let ip = address.ip().to_string();
+        if !order.contains(&ip) {
+            order.push(ip);
+        }
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manages_only_its_own_block() {
+        let stock = "# Configuration for getaddrinfo(3).\n#\n#precedence ::ffff:0:0/96  100\n";
+        assert_eq!(parse(stock), GaiState::default());
+
+        let preferred = apply(stock, Preference::Ipv4);
+        assert!(preferred.starts_with(stock));
+        assert!(preferred.contains("precedence ::ffff:0:0/96 100"));
+        assert_eq!(parse(&preferred).preference, Preference::Ipv4);
+        // Applying twice doesn't grow the file, and going back restores it
+        assert_eq!(apply(&preferred, Preference::Ipv4), preferred);
+        assert_eq!(apply(&preferred, Preference::Ipv6), stock);
+        assert_eq!(apply("", Preference::Ipv6), "");
+
+        let edited = format!(
+            "label 2001:db8::/32 7\nprecedence 2001:db8::/32 45\n{}",
+            preferred
+        );
+        let state = parse(&edited);
+        assert_eq!(state.preference, Preference::Ipv4);
+        assert_eq!(state.foreign, vec!["precedence 2001:db8::/32 45"]);
+    }
+}
@@ -0,0 +1,138 @@
+// src/groups.rs - Named aggregate groups of interfaces with summed counters and rates
+use crate::network::{Interface, InterfaceStats};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// A `[[groups]]` entry: interfaces counted together, e.g. all uplinks as "WAN"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregateGroup {
+    pub name: String,
+    /// Interface names, or prefixes with a trailing `*` (`veth*`), as in profiles
+    pub members: Vec<String>,
+}
+
+impl AggregateGroup {
+    pub fn matches(&self, interface: &str) -> bool {
+        self.members
+            .iter()
+            .any(|member| match member.strip_suffix('*') {
+                Some(prefix) => interface.starts_with(prefix),
+                None => member == interface,
+            })
+    }
+}
+
+/// A group's synthetic row: the sum over the members present right now
+#[derive(Debug, Clone, Default)]
+pub struct GroupTotals {
+    pub name: String,
+    pub members: Vec<String>,
+    pub stats: InterfaceStats,
+    pub rx_rate: u64, // Bytes per second
+    pub tx_rate: u64,
+}
+
+/// Per-interface byte rates from consecutive statistics samples. Groups sum
+/// their members' rates rather than differencing summed counters, so a member
+/// appearing or vanishing doesn't show up as a burst of traffic.
+#[derive(Debug, Default)]
+pub struct Rates {
+    samples: HashMap<String, (Instant, u64, u64)>,
+    rates: HashMap<String, (u64, u64)>,
+}
+
+impl Rates {
+    pub fn record(&mut self, interfaces: &[Interface], now: Instant) {
+        let mut samples = HashMap::with_capacity(interfaces.len());
+        for interface in interfaces {
+            let (rx, tx) = (interface.stats.rx_bytes, interface.stats.tx_bytes);
+            if let Some(&(at, last_rx, last_tx)) = self.samples.get(&interface.name) {
+                let secs = now.duration_since(at).as_secs_f64();
+                if secs > 0.0 {
+                    // A recreated interface starts its counters over
+                    let rate =
+                        |now: u64, last: u64| (now.saturating_sub(last) as f64 / secs) as u64;
+                    self.rates.insert(
+                        interface.name.clone(),
+                        (rate(rx, last_rx), rate(tx, last_tx)),
+                    );
+                }
+            }
+            samples.insert(interface.name.clone(), (now, rx, tx));
+        }
+        self.rates.retain(|name, _| samples.contains_key(name));
+        self.samples = samples;
+    }
+
+    pub fn get(&self, interface: &str) -> (u64, u64) {
+        self.rates.get(interface).copied().unwrap_or_default()
+    }
+
+    /// One row per configured group, including groups with no members present.
+    pub fn totals(&self, groups: &[AggregateGroup], interfaces: &[Interface]) -> Vec<GroupTotals> {
+        groups
+            .iter()
+            .map(|group| {
+                let mut totals = GroupTotals {
+                    name: group.name.clone(),
+                    ..Default::default()
+                };
+                for interface in interfaces.iter().filter(|i| group.matches(&i.name)) {
+                    let stats = &interface.stats;
+                    totals.stats.rx_bytes += stats.rx_bytes;
+                    totals.stats.tx_bytes += stats.tx_bytes;
+                    totals.stats.rx_packets += stats.rx_packets;
+                    totals.stats.tx_packets += stats.tx_packets;
+                    totals.stats.rx_errors += stats.rx_errors;
+                    totals.stats.tx_errors += stats.tx_errors;
+                    let (rx_rate, tx_rate) = self.get(&interface.name);
+                    totals.rx_rate += rx_rate;
+                    totals.tx_rate += tx_rate;
+                    totals.members.push(interface.name.clone());
+                }
+                totals
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::NetworkManager;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn sums_member_counters_and_rates() {
+        let mut interfaces = NetworkManager::demo().get_interfaces().await.unwrap();
+        interfaces.retain(|i| i.name == "eth0" || i.name == "wlan0");
+        let group = AggregateGroup {
+            name: "uplinks".to_string(),
+            members: vec!["eth0".to_string(), "wl*".to_string()],
+        };
+        assert!(group.matches("wlan1"));
+        assert!(!group.matches("wg0"));
+
+        let start = Instant::now();
+        let mut rates = Rates::default();
+        rates.record(&interfaces, start);
+        for interface in &mut interfaces {
+            interface.stats.rx_bytes += 2000;
+            interface.stats.tx_bytes += 500;
+        }
+        rates.record(&interfaces, start + Duration::from_secs(2));
+
+        let totals = rates.totals(&[group], &interfaces);
+        assert_eq!(totals[0].members, vec!["eth0", "wlan0"]);
+        assert_eq!(
+            totals[0].stats.rx_bytes,
+            interfaces.iter().map(|i| i.stats.rx_bytes).sum::<u64>()
+        );
+        assert_eq!((totals[0].rx_rate, totals[0].tx_rate), (2000, 500));
+
+        // A vanished member drops out instead of counting as negative traffic
+        rates.record(&interfaces[..1], start + Duration::from_secs(4));
+        assert_eq!(rates.get("wlan0"), (0, 0));
+    }
+}
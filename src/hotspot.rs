@@ -0,0 +1,398 @@
+// src/hotspot.rs - Clients of a running hotspot: nl80211 station dumps and dnsmasq leases
+use crate::portfwd::PortForward;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::time::Instant;
+
+/// Where the hotspot's dnsmasq keeps its leases (`dhcp-leasefile=`)
+pub const LEASE_FILE: &str = "/tmp/dnsmasq.leases";
+
+/// Reservations for the hotspot's dnsmasq (`dhcp-hostsfile=`), re-read on SIGHUP
+pub const HOSTS_FILE: &str = "/tmp/dnsmasq.hosts";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotspotSettings {
+    /// Fixed addresses for known clients, as `[[hotspot.reservations]]`
+    pub reservations: Vec<DhcpReservation>,
+    /// Ports opened to hotspot clients, as `[[hotspot.port_forwards]]`
+    pub port_forwards: Vec<PortForward>,
+}
+
+/// A client that always gets the same address from the hotspot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DhcpReservation {
+    pub mac: String,
+    pub ip: String,
+    #[serde(default)]
+    pub hostname: Option<String>,
+}
+
+impl DhcpReservation {
+    /// A checked reservation; the MAC comes out lowercase and colon-separated.
+    pub fn new(mac: &str, ip: &str, hostname: &str) -> Result<Self> {
+        let (ip, mac) = crate::neighbors::validate(ip, mac)?;
+        if ip.parse::<Ipv4Addr>().is_err() {
+            return Err(anyhow!(
+                "The hotspot hands out IPv4 addresses only, not {}",
+                ip
+            ));
+        }
+        let hostname = hostname.trim();
+        let valid_hostname = hostname.len() <= 63
+            && !hostname.starts_with('-')
+            && hostname
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-');
+        if !valid_hostname {
+            return Err(anyhow!("'{}' is not a valid hostname", hostname));
+        }
+        Ok(Self {
+            mac,
+            ip,
+            hostname: Some(hostname.to_string()).filter(|name| !name.is_empty()),
+        })
+    }
+}
+
+/// The dnsmasq hosts file: `<mac>,<ip>[,<hostname>]` for each reservation
+/// inside the hotspot's /24. Others can't be handed out and are left out.
+pub fn render_hosts_file(reservations: &[DhcpReservation], gateway: &str) -> String {
+    let Ok(gateway) = gateway.parse::<Ipv4Addr>() else {
+        return String::new();
+    };
+    reservations
+        .iter()
+        .filter(|reservation| {
+            reservation
+                .ip
+                .parse::<Ipv4Addr>()
+                .is_ok_and(|ip| ip != gateway && ip.octets()[..3] == gateway.octets()[..3])
+        })
+        .map(|reservation| match &reservation.hostname {
+            Some(hostname) => format!("{},{},{}\n", reservation.mac, reservation.ip, hostname),
+            None => format!("{},{}\n", reservation.mac, reservation.ip),
+        })
+        .collect()
+}
+
+/// The hotspot dialog's "Auto" channel: survey first, then pick
+pub const AUTO_CHANNEL: u32 = 0;
+
+/// What automatic selection chooses from: the non-overlapping 2.4 GHz
+/// channels, as hostapd runs the hotspot in hw_mode=g
+pub const AUTO_CANDIDATES: [u32; 3] = [1, 6, 11];
+
+// 2.4 GHz channels are 5 MHz apart and 20 MHz wide, so they interfere with
+// neighbours up to four channels away
+const OVERLAP: u32 = 5;
+
+/// Fraction of time each channel was busy, from `iw dev <iface> survey dump`.
+/// Drivers that don't measure report nothing, and are left out.
+pub fn parse_survey_dump(output: &str) -> HashMap<u32, f64> {
+    let mut busy = HashMap::new();
+    let (mut channel, mut active) = (None, None);
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let number = value
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse::<u64>().ok());
+        match key.trim() {
+            "frequency" => {
+                channel = number.map(|mhz| crate::network::frequency_to_channel(mhz as u32));
+                active = None;
+            }
+            "channel active time" => active = number,
+            "channel busy time" => {
+                if let (Some(channel), Some(active), Some(busy_ms)) = (channel, active, number) {
+                    if active > 0 {
+                        busy.insert(channel, busy_ms as f64 / active as f64);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    busy
+}
+
+/// The least congested candidate channel for a hotspot, with how many access
+/// points were heard on or next to it. Each access point counts more the
+/// louder and closer in frequency it is; measured airtime use adds to that.
+pub fn pick_channel(access_points: &[(u32, i32)], busy: &HashMap<u32, f64>) -> (u32, usize) {
+    let congestion = |candidate: u32| {
+        let mut score = busy.get(&candidate).copied().unwrap_or(0.0) * 100.0;
+        let mut heard = 0;
+        for &(channel, signal) in access_points {
+            let distance = channel.abs_diff(candidate);
+            if channel > 14 || distance >= OVERLAP {
+                continue; // 5 GHz, or far enough away
+            }
+            heard += 1;
+            let overlap = 1.0 - distance as f64 / OVERLAP as f64;
+            score += (signal + 100).max(1) as f64 * overlap;
+        }
+        (score, heard)
+    };
+    AUTO_CANDIDATES
+        .iter()
+        .map(|&candidate| (candidate, congestion(candidate)))
+        .min_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b))
+        .map(|(channel, (_, heard))| (channel, heard))
+        .unwrap_or((AUTO_CANDIDATES[1], 0))
+}
+
+/// One associated client, as the access point sees it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Station {
+    pub mac: String,
+    pub ip: Option<String>,       // From the DHCP lease
+    pub hostname: Option<String>, // What the client sent with its DHCP request
+    pub signal: Option<i32>,      // dBm
+    pub rx_bytes: u64,            // Received from the client: its uploads
+    pub tx_bytes: u64,            // Sent to the client: its downloads
+}
+
+/// Parse `iw dev <ap> station dump`.
+pub fn parse_station_dump(output: &str) -> Vec<Station> {
+    let mut stations: Vec<Station> = Vec::new();
+    for line in output.lines() {
+        if let Some(rest) = line.strip_prefix("Station ") {
+            let mac = rest.split_whitespace().next().unwrap_or_default();
+            stations.push(Station {
+                mac: mac.to_lowercase(),
+                ..Default::default()
+            });
+            continue;
+        }
+        let (Some(station), Some((key, value))) = (stations.last_mut(), line.split_once(':'))
+        else {
+            continue;
+        };
+        let first = value.split_whitespace().next().unwrap_or_default();
+        match key.trim() {
+            "rx bytes" => station.rx_bytes = first.parse().unwrap_or(0),
+            "tx bytes" => station.tx_bytes = first.parse().unwrap_or(0),
+            "signal" => station.signal = first.parse().ok(),
+            _ => {}
+        }
+    }
+    stations
+}
+
+/// Interfaces in AP mode and the SSID each broadcasts, from `iw dev`.
+pub fn parse_access_points(output: &str) -> Vec<(String, String)> {
+    let mut access_points = Vec::new();
+    let mut current: Option<(String, String, bool)> = None;
+    for line in output.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("Interface ") {
+            access_points.extend(current.take().filter(|(_, _, ap)| *ap));
+            current = Some((name.trim().to_string(), String::new(), false));
+        } else if let Some((_, ssid, ap)) = current.as_mut() {
+            if let Some(name) = line.strip_prefix("ssid ") {
+                *ssid = name.to_string();
+            } else if line == "type AP" {
+                *ap = true;
+            }
+        }
+    }
+    access_points.extend(current.filter(|(_, _, ap)| *ap));
+    access_points
+        .into_iter()
+        .map(|(interface, ssid, _)| (interface, ssid))
+        .collect()
+}
+
+/// Fill in addresses and hostnames from a dnsmasq lease file
+/// (`<expiry> <mac> <ip> <hostname|*> <client-id>` per line).
+pub fn attach_leases(stations: &mut [Station], leases: &str) {
+    let by_mac: HashMap<String, (&str, &str)> = leases
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            Some((
+                fields.get(1)?.to_lowercase(),
+                (*fields.get(2)?, *fields.get(3)?),
+            ))
+        })
+        .collect();
+    for station in stations {
+        if let Some((ip, hostname)) = by_mac.get(&station.mac) {
+            station.ip = Some(ip.to_string());
+            station.hostname = Some(hostname.to_string()).filter(|name| name != "*");
+        }
+    }
+}
+
+/// A station with its current throughput
+#[derive(Debug, Clone)]
+pub struct HotspotClient {
+    pub station: Station,
+    pub rx_rate: u64, // Bytes per second
+    pub tx_rate: u64,
+}
+
+impl HotspotClient {
+    /// Hostname, else IP, else MAC
+    pub fn label(&self) -> &str {
+        let station = &self.station;
+        station
+            .hostname
+            .as_deref()
+            .or(station.ip.as_deref())
+            .unwrap_or(&station.mac)
+    }
+}
+
+/// Turns successive station dumps into per-client rates
+#[derive(Debug, Default)]
+pub struct ClientTracker {
+    last: HashMap<String, (Instant, u64, u64)>,
+    pub clients: Vec<HotspotClient>, // Busiest first
+}
+
+impl ClientTracker {
+    pub fn update(&mut self, stations: Vec<Station>, now: Instant) {
+        let mut last = HashMap::with_capacity(stations.len());
+        let mut clients: Vec<HotspotClient> = stations
+            .into_iter()
+            .map(|station| {
+                let (rx_rate, tx_rate) = match self.last.get(&station.mac) {
+                    Some(&(at, rx, tx)) if now > at => {
+                        let secs = now.duration_since(at).as_secs_f64();
+                        // A client that reassociated starts its counters over
+                        let rate = |now: u64, before: u64| {
+                            (now.saturating_sub(before) as f64 / secs) as u64
+                        };
+                        (rate(station.rx_bytes, rx), rate(station.tx_bytes, tx))
+                    }
+                    _ => (0, 0),
+                };
+                last.insert(
+                    station.mac.clone(),
+                    (now, station.rx_bytes, station.tx_bytes),
+                );
+                HotspotClient {
+                    station,
+                    rx_rate,
+                    tx_rate,
+                }
+            })
+            .collect();
+        clients.sort_by_key(|client| std::cmp::Reverse(client.rx_rate + client.tx_rate));
+        self.last = last;
+        self.clients = clients;
+    }
+
+    pub fn clear(&mut self) {
+        self.last.clear();
+        self.clients.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn finds_interfaces_in_ap_mode() {
+        let output = "phy#0\n\tInterface wlan1\n\t\tifindex 5\n\t\tssid Lantern Hotspot\n\t\ttype AP\n\t\tchannel 6 (2437 MHz)\n\tInterface wlan0\n\t\tifindex 3\n\t\tssid Home\n\t\ttype managed\n";
+        assert_eq!(
+            parse_access_points(output),
+            vec![("wlan1".to_string(), "Lantern Hotspot".to_string())]
+        );
+    }
+
+    const DUMP: &str = "Station 12:34:56:78:9A:BC (on wlan0)
+\tinactive time:\t304 ms
+\trx bytes:\t18816
+\trx packets:\t75
+\ttx bytes:\t5386
+\ttx packets:\t21
+\tsignal:  \t-29 [-31, -32] dBm
+\tconnected time:\t12 seconds
+Station de:ad:be:ef:00:01 (on wlan0)
+\trx bytes:\t100
+\ttx bytes:\t200
+";
+
+    #[test]
+    fn picks_the_quietest_channel() {
+        let survey = parse_survey_dump(
+            "Survey data from wlan0
+\tfrequency:\t\t\t2412 MHz [in use]
+\tnoise:\t\t\t\t-95 dBm
+\tchannel active time:\t\t200 ms
+\tchannel busy time:\t\t150 ms
+Survey data from wlan0
+\tfrequency:\t\t\t2437 MHz
+Survey data from wlan0
+\tfrequency:\t\t\t2462 MHz
+\tchannel active time:\t\t100 ms
+\tchannel busy time:\t\t10 ms
+",
+        );
+        assert_eq!(survey.get(&1), Some(&0.75));
+        assert_eq!(survey.get(&6), None);
+        assert_eq!(survey.get(&11), Some(&0.1));
+
+        // Two strong neighbours on 6, a faint one on 11, a busy channel 1
+        let heard = [(6, -40), (7, -55), (11, -85), (36, -30)];
+        assert_eq!(pick_channel(&heard, &survey), (11, 2));
+        // Nothing heard or measured: the first candidate
+        assert_eq!(pick_channel(&[], &HashMap::new()), (1, 0));
+    }
+
+    #[test]
+    fn writes_reservations_inside_the_subnet() {
+        let phone = DhcpReservation::new("AA-BB-CC-00-11-22", "192.168.4.23", "pixel-7").unwrap();
+        assert_eq!(phone.mac, "aa:bb:cc:00:11:22");
+        let printer = DhcpReservation::new("aa:bb:cc:00:11:23", "192.168.4.60", "").unwrap();
+        assert_eq!(printer.hostname, None);
+        let elsewhere = DhcpReservation::new("aa:bb:cc:00:11:24", "10.0.0.5", "nas").unwrap();
+        assert!(DhcpReservation::new("aa:bb:cc:00:11:25", "2001:db8::5", "").is_err());
+        assert!(DhcpReservation::new("aa:bb:cc:00:11:25", "192.168.4.5", "my host").is_err());
+
+        assert_eq!(
+            render_hosts_file(&[phone, printer, elsewhere], "192.168.4.1"),
+            "aa:bb:cc:00:11:22,192.168.4.23,pixel-7\naa:bb:cc:00:11:23,192.168.4.60\n"
+        );
+    }
+
+    #[test]
+    fn reads_stations_leases_and_rates() {
+        let mut stations = parse_station_dump(DUMP);
+        assert_eq!(stations.len(), 2);
+        assert_eq!(stations[0].mac, "12:34:56:78:9a:bc");
+        assert_eq!((stations[0].rx_bytes, stations[0].tx_bytes), (18816, 5386));
+        assert_eq!(stations[0].signal, Some(-29));
+
+        attach_leases(
+            &mut stations,
+            "1700000000 12:34:56:78:9a:bc 192.168.4.23 pixel-7 01:12:34:56:78:9a:bc\n\
+             1700000000 de:ad:be:ef:00:01 192.168.4.24 * *\n",
+        );
+        assert_eq!(stations[0].hostname.as_deref(), Some("pixel-7"));
+        assert_eq!(stations[1].hostname, None);
+        assert_eq!(stations[1].ip.as_deref(), Some("192.168.4.24"));
+
+        let start = Instant::now();
+        let mut tracker = ClientTracker::default();
+        tracker.update(stations.clone(), start);
+        stations[1].tx_bytes += 4_000_000;
+        stations[0].rx_bytes += 2_000;
+        tracker.update(stations, start + Duration::from_secs(2));
+
+        // The heavy downloader comes first
+        assert_eq!(tracker.clients[0].label(), "192.168.4.24");
+        assert_eq!(tracker.clients[0].tx_rate, 2_000_000);
+        assert_eq!(tracker.clients[1].label(), "pixel-7");
+        assert_eq!(tracker.clients[1].rx_rate, 1_000);
+    }
+}
@@ -0,0 +1,255 @@
+// src/hotspot_units.rs - A hotspot kept across reboots as systemd units instead of /tmp configs
+use crate::command::TimedOutput;
+use crate::network::{dnsmasq_config, hostapd_config, HotspotConfig};
+use crate::provenance;
+use crate::secure_file;
+use anyhow::{Context, Result};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use tokio::process::Command;
+
+pub const CONFIG_DIR: &str = "/etc/lantern/hotspot";
+const UNIT_DIR: &str = "/etc/systemd/system";
+/// Leases outlive a reboot too, so clients keep their addresses
+pub const LEASE_FILE: &str = "/var/lib/lantern/hotspot.leases";
+/// The config the units were written from, password included
+const SAVED: &str = "hotspot.toml";
+
+/// hostapd, which the other two are bound to
+pub const MAIN_UNIT: &str = "lantern-hotspot.service";
+const DHCP_UNIT: &str = "lantern-hotspot-dhcp.service";
+const NAT_UNIT: &str = "lantern-hotspot-nat.service";
+const UNITS: [&str; 3] = [MAIN_UNIT, DHCP_UNIT, NAT_UNIT];
+
+// Its own table, apart from the port forwards' lantern_nat
+const NAT_TABLE: &str = "lantern_hotspot";
+
+fn hostapd_unit(config: &HotspotConfig, dir: &str) -> String {
+    format!(
+        "[Unit]
+Description=Lantern hotspot on {interface}
+Documentation=https://github.com/jardahrazdera/lantern
+After=network-pre.target
+Wants={DHCP_UNIT} {NAT_UNIT}
+
+[Service]
+ExecStartPre=/usr/bin/ip addr replace {gateway}/24 dev {interface}
+ExecStartPre=/usr/bin/ip link set {interface} up
+ExecStart=/usr/bin/hostapd {dir}/hostapd.conf
+ExecStopPost=-/usr/bin/ip addr flush dev {interface}
+Restart=on-failure
+
+[Install]
+WantedBy=multi-user.target
+",
+        interface = config.interface,
+        gateway = config.gateway,
+    )
+}
+
+fn dhcp_unit(dir: &str) -> String {
+    format!(
+        "[Unit]
+Description=Lantern hotspot DHCP and DNS
+BindsTo={MAIN_UNIT}
+After={MAIN_UNIT}
+
+[Service]
+ExecStart=/usr/bin/dnsmasq -k -C {dir}/dnsmasq.conf
+ExecReload=/bin/kill -HUP $MAINPID
+Restart=on-failure
+
+[Install]
+WantedBy={MAIN_UNIT}
+"
+    )
+}
+
+fn nat_unit(dir: &str) -> String {
+    format!(
+        "[Unit]
+Description=Lantern hotspot NAT and port forwards
+PartOf={MAIN_UNIT}
+After={MAIN_UNIT}
+
+[Service]
+Type=oneshot
+RemainAfterExit=yes
+ExecStart=/usr/bin/sysctl -w net.ipv4.ip_forward=1
+ExecStart=/usr/bin/nft -f {dir}/nat.nft
+ExecStop=-/usr/bin/nft delete table ip {NAT_TABLE}
+ExecStop=-/usr/bin/nft delete table ip lantern_nat
+
+[Install]
+WantedBy={MAIN_UNIT}
+"
+    )
+}
+
+/// Masquerade whatever leaves the hotspot's subnet by another interface, so
+/// nothing depends on which uplink is up at boot; port forwards follow
+pub fn nat_ruleset(config: &HotspotConfig) -> String {
+    let mut rules = format!("table ip {NAT_TABLE} {{}}\ndelete table ip {NAT_TABLE}\n");
+    let _ = write!(
+        rules,
+        "table ip {NAT_TABLE} {{\n\
+         \tchain postrouting {{\n\
+         \t\ttype nat hook postrouting priority srcnat; policy accept;\n\
+         \t\tip saddr {} oifname != \"{}\" masquerade\n\
+         \t}}\n\
+         }}\n",
+        config.ip_range, config.interface
+    );
+    if let Some(forwards) = crate::portfwd::ruleset(&config.port_forwards, &config.gateway) {
+        rules.push_str(&forwards);
+    }
+    rules
+}
+
+/// Write the configs and units for `config` under `dir` and `unit_dir`
+fn write_files(config: &HotspotConfig, dir: &Path, unit_dir: &Path) -> Result<()> {
+    let config = HotspotConfig {
+        persistent: true,
+        ..config.clone()
+    };
+    let dir_name = dir.display().to_string();
+    let hosts = dir.join("dnsmasq.hosts");
+    let operation = format!("Persistent hotspot {} on {}", config.ssid, config.interface);
+    let stamp = |contents: String| provenance::stamp(&contents, &operation);
+    fs::create_dir_all(dir)?;
+    secure_file::write_private(dir.join("hostapd.conf"), stamp(hostapd_config(&config)?))?;
+    secure_file::write_public(
+        dir.join("dnsmasq.conf"),
+        stamp(dnsmasq_config(
+            &config,
+            LEASE_FILE,
+            &hosts.display().to_string(),
+        )?),
+    )?;
+    secure_file::write_public(
+        &hosts,
+        stamp(crate::hotspot::render_hosts_file(
+            &config.reservations,
+            &config.gateway,
+        )),
+    )?;
+    secure_file::write_private(dir.join("nat.nft"), stamp(nat_ruleset(&config)))?;
+    secure_file::write_private(dir.join(SAVED), toml::to_string_pretty(&config)?)?;
+
+    fs::create_dir_all(unit_dir)?;
+    secure_file::write_public(
+        unit_dir.join(MAIN_UNIT),
+        stamp(hostapd_unit(&config, &dir_name)),
+    )?;
+    secure_file::write_public(unit_dir.join(DHCP_UNIT), stamp(dhcp_unit(&dir_name)))?;
+    secure_file::write_public(unit_dir.join(NAT_UNIT), stamp(nat_unit(&dir_name)))?;
+    Ok(())
+}
+
+/// Write everything and start the hotspot now and on every boot
+pub async fn install(config: &HotspotConfig) -> Result<()> {
+    crate::network::validate_hotspot_config(config)?;
+    if let Some(dir) = Path::new(LEASE_FILE).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_files(config, Path::new(CONFIG_DIR), Path::new(UNIT_DIR))
+        .context("Failed to write the hotspot's units")?;
+    crate::daemon::systemctl(&["daemon-reload"]).await?;
+    let mut args = vec!["enable", "--now"];
+    args.extend(UNITS);
+    crate::daemon::systemctl(&args).await
+}
+
+/// Rewrite the configs after reservations or port forwards changed; dnsmasq
+/// re-reads its hosts file and the NAT rules are loaded again
+pub async fn update(config: &HotspotConfig) -> Result<()> {
+    write_files(config, Path::new(CONFIG_DIR), Path::new(UNIT_DIR))?;
+    crate::daemon::systemctl(&["reload", DHCP_UNIT]).await?;
+    crate::daemon::systemctl(&["restart", NAT_UNIT]).await
+}
+
+/// Stop the hotspot and remove its units and configs
+pub async fn remove() -> Result<()> {
+    let mut args = vec!["disable", "--now"];
+    args.extend(UNITS);
+    crate::daemon::systemctl(&args).await?;
+    for unit in UNITS {
+        let path = Path::new(UNIT_DIR).join(unit);
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    if Path::new(CONFIG_DIR).exists() {
+        fs::remove_dir_all(CONFIG_DIR)?;
+    }
+    crate::daemon::systemctl(&["daemon-reload"]).await
+}
+
+/// The hotspot the units were written for, if any
+pub fn load() -> Option<HotspotConfig> {
+    let content = fs::read_to_string(Path::new(CONFIG_DIR).join(SAVED)).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// systemd's word for the main unit: active, activating, failed...
+pub async fn state() -> String {
+    match Command::new("/usr/bin/systemctl")
+        .args(["is-active", MAIN_UNIT])
+        .timed_output()
+        .await
+    {
+        // Non-zero for anything but active, with the state still printed
+        Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_units_that_survive_a_reboot() {
+        let root = std::env::temp_dir().join(format!("lantern-hotspot-{}", std::process::id()));
+        let (dir, unit_dir) = (root.join("hotspot"), root.join("system"));
+        let config = HotspotConfig {
+            ssid: "Lantern-Hotspot".to_string(),
+            password: "correct horse battery".to_string(),
+            interface: "wlan0".to_string(),
+            channel: 6,
+            ip_range: "192.168.4.0/24".to_string(),
+            gateway: "192.168.4.1".to_string(),
+            reservations: Vec::new(),
+            port_forwards: Vec::new(),
+            persistent: false,
+        };
+        write_files(&config, &dir, &unit_dir).unwrap();
+
+        let main = fs::read_to_string(unit_dir.join(MAIN_UNIT)).unwrap();
+        assert!(main.contains(&format!(
+            "ExecStart=/usr/bin/hostapd {}/hostapd.conf",
+            dir.display()
+        )));
+        assert!(main.contains("ip addr replace 192.168.4.1/24 dev wlan0"));
+        assert!(main.contains("WantedBy=multi-user.target"));
+        let dhcp = fs::read_to_string(unit_dir.join(DHCP_UNIT)).unwrap();
+        assert!(dhcp.contains(&format!("BindsTo={}", MAIN_UNIT)));
+        let dnsmasq = fs::read_to_string(dir.join("dnsmasq.conf")).unwrap();
+        assert!(dnsmasq.contains(&format!("dhcp-leasefile={}\n", LEASE_FILE)));
+        assert!(!dnsmasq.contains(crate::hotspot::LEASE_FILE));
+        let nat = fs::read_to_string(dir.join("nat.nft")).unwrap();
+        assert!(nat.contains("ip saddr 192.168.4.0/24 oifname != \"wlan0\" masquerade"));
+
+        let saved: HotspotConfig =
+            toml::from_str(&fs::read_to_string(dir.join(SAVED)).unwrap()).unwrap();
+        assert!(saved.persistent);
+        assert_eq!(saved.password, config.password);
+        let mode = fs::metadata(dir.join("hostapd.conf"))
+            .unwrap()
+            .permissions();
+        assert_eq!(std::os::unix::fs::PermissionsExt::mode(&mode) & 0o077, 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+// src/httpcheck.rs - One HTTP(S) GET through a chosen interface or source address, with TLS details and timings
+use crate::command::TimedOutput;
+use crate::network::Interface;
+use anyhow::{Context, Result};
+use tokio::process::Command;
+
+/// What the dialog starts with
+pub const DEFAULT_URL: &str = "https://www.google.com/generate_204";
+/// curl gives up after this long
+const MAX_SECONDS: &str = "10";
+
+// Everything curl reports about the request that the result needs, split on |
+// since remote_ip is empty when nothing answered
+const WRITE_OUT: &str = "%{http_code}|%{http_version}|%{remote_ip}|%{time_namelookup}|\
+%{time_connect}|%{time_appconnect}|%{time_starttransfer}|%{time_total}|%{ssl_verify_result}|\
+%{size_download}";
+
+/// Where the request leaves from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    /// SO_BINDTODEVICE: out this interface, whatever the routes say
+    Interface(String),
+    /// From this address, routed as usual
+    Source(String),
+}
+
+impl Binding {
+    /// The choices for an interface: the interface itself, then each address
+    pub fn choices(interface: &Interface) -> Vec<Binding> {
+        let mut choices = vec![Binding::Interface(interface.name.clone())];
+        for address in interface
+            .ipv4_addresses
+            .iter()
+            .chain(&interface.ipv6_addresses)
+        {
+            let address = address.split('/').next().unwrap_or(address);
+            choices.push(Binding::Source(address.to_string()));
+        }
+        choices
+    }
+
+    /// curl's `--interface` argument
+    fn curl_arg(&self) -> String {
+        match self {
+            Binding::Interface(name) => format!("if!{}", name),
+            Binding::Source(address) => format!("host!{}", address),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Interface(name) => format!("interface {}", name),
+            Binding::Source(address) => format!("source {}", address),
+        }
+    }
+}
+
+/// Seconds spent in each phase, not since the start
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timings {
+    pub dns: f64,
+    pub connect: f64,
+    /// None for plain HTTP
+    pub tls: Option<f64>,
+    /// From the request going out to the first byte of the answer
+    pub first_byte: f64,
+    pub total: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsDetails {
+    pub version: String,
+    pub cipher: String,
+    pub subject: String,
+    pub issuer: String,
+    pub expires: String,
+    /// The chain checked out against the system's CAs
+    pub verified: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpCheck {
+    pub url: String,
+    pub binding: String,
+    /// None when no response came back
+    pub status: Option<u16>,
+    pub http_version: String,
+    pub remote_ip: String,
+    pub timings: Timings,
+    pub tls: Option<TlsDetails>,
+    pub bytes: u64,
+    /// Why it failed, in curl's words
+    pub error: Option<String>,
+}
+
+impl HttpCheck {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.status.is_some_and(|status| status < 500)
+    }
+
+    /// One line for the status bar
+    pub fn summary(&self) -> String {
+        match (self.status, &self.error) {
+            (Some(status), _) => format!(
+                "HTTP {} from {} in {:.0} ms via {}",
+                status,
+                self.remote_ip,
+                self.timings.total * 1000.0,
+                self.binding
+            ),
+            (None, Some(error)) => format!("No response via {}: {}", self.binding, error),
+            (None, None) => format!("No response via {}", self.binding),
+        }
+    }
+}
+
+/// Fill in what `-w WRITE_OUT` printed; false when it isn't there
+fn parse_write_out(output: &str, check: &mut HttpCheck) -> bool {
+    let fields: Vec<&str> = output.trim().split('|').collect();
+    let [code, version, remote_ip, namelookup, connect, appconnect, starttransfer, total, verify, size] =
+        fields[..]
+    else {
+        return false;
+    };
+    let seconds = |field: &str| field.parse::<f64>().unwrap_or(0.0);
+    let (namelookup, connect, appconnect) =
+        (seconds(namelookup), seconds(connect), seconds(appconnect));
+    // Phases end at these marks; appconnect stays 0 without TLS
+    let connected = if appconnect > 0.0 {
+        appconnect
+    } else {
+        connect
+    };
+    check.status = code.parse().ok().filter(|&code| code != 0);
+    check.http_version = version.to_string();
+    check.remote_ip = remote_ip.to_string();
+    check.timings = Timings {
+        dns: namelookup,
+        connect: (connect - namelookup).max(0.0),
+        tls: (appconnect > 0.0).then(|| (appconnect - connect).max(0.0)),
+        first_byte: (seconds(starttransfer) - connected).max(0.0),
+        total: seconds(total),
+    };
+    check.bytes = size.parse().unwrap_or(0);
+    if let Some(tls) = &mut check.tls {
+        tls.verified = verify == "0";
+    }
+    true
+}
+
+/// The TLS handshake as `curl -v` describes it on stderr:
+/// "* SSL connection using TLSv1.3 / TLS_AES_256_GCM_SHA384 / X25519 / RSASSA-PSS"
+/// "*  subject: CN=www.google.com"
+pub fn parse_verbose(stderr: &str) -> Option<TlsDetails> {
+    let mut tls: Option<TlsDetails> = None;
+    for line in stderr.lines() {
+        let line = line.trim_start_matches('*').trim();
+        if let Some(rest) = line.strip_prefix("SSL connection using ") {
+            let mut parts = rest.split(" / ");
+            let details = tls.get_or_insert_with(TlsDetails::default);
+            details.version = parts.next().unwrap_or_default().trim().to_string();
+            details.cipher = parts.next().unwrap_or_default().trim().to_string();
+            continue;
+        }
+        let Some(details) = &mut tls else {
+            continue;
+        };
+        if let Some(subject) = line.strip_prefix("subject: ") {
+            details.subject = subject.to_string();
+        } else if let Some(issuer) = line.strip_prefix("issuer: ") {
+            details.issuer = issuer.to_string();
+        } else if let Some(expires) = line.strip_prefix("expire date: ") {
+            details.expires = expires.to_string();
+        }
+    }
+    tls
+}
+
+/// curl's own error line, e.g. "curl: (7) Failed to connect to ..."
+fn curl_error(stderr: &str) -> Option<String> {
+    stderr
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix("curl: "))
+        .map(|error| error.trim().to_string())
+}
+
+/// Only http(s) URLs go to curl, which would take file:// or an option too
+pub fn validate_url(url: &str) -> Result<()> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) || url.contains(' ') {
+        anyhow::bail!("Enter an http:// or https:// URL");
+    }
+    Ok(())
+}
+
+/// GET `url` once, leaving from `binding`. The body is thrown away.
+pub async fn run(url: &str, binding: &Binding) -> Result<HttpCheck> {
+    validate_url(url)?;
+    let output = Command::new("/usr/bin/curl")
+        .args([
+            "-sS",
+            "-v",
+            "-o",
+            "/dev/null",
+            "--max-time",
+            MAX_SECONDS,
+            "--interface",
+            &binding.curl_arg(),
+            "-w",
+            WRITE_OUT,
+            url,
+        ])
+        .timed_output()
+        .await
+        .context("Failed to run curl")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut check = HttpCheck {
+        url: url.to_string(),
+        binding: binding.label(),
+        tls: parse_verbose(&stderr),
+        ..HttpCheck::default()
+    };
+    parse_write_out(&String::from_utf8_lossy(&output.stdout), &mut check);
+    if !output.status.success() {
+        check.error = Some(curl_error(&stderr).unwrap_or_else(|| "curl failed".to_string()));
+    }
+    Ok(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_timings_and_tls_from_curl() {
+        let stderr = "\
+* Connected to www.google.com (142.250.180.196) port 443
+* SSL connection using TLSv1.3 / TLS_AES_256_GCM_SHA384 / X25519 / RSASSA-PSS
+* Server certificate:
+*  subject: CN=www.google.com
+*  start date: Sep  1 08:36:05 2026 GMT
+*  expire date: Nov 24 08:36:04 2026 GMT
+*  issuer: C=US; O=Google Trust Services; CN=WR2
+*  SSL certificate verify ok.
+> GET /generate_204 HTTP/2
+";
+        let mut check = HttpCheck {
+            tls: parse_verbose(stderr),
+            ..HttpCheck::default()
+        };
+        assert!(parse_write_out(
+            "204|2|142.250.180.196|0.004|0.016|0.045|0.071|0.071|0|0",
+            &mut check
+        ));
+        assert_eq!(check.status, Some(204));
+        let timings = &check.timings;
+        assert!((timings.connect - 0.012).abs() < 1e-9);
+        assert!((timings.tls.unwrap() - 0.029).abs() < 1e-9);
+        assert!((timings.first_byte - 0.026).abs() < 1e-9);
+        let tls = check.tls.unwrap();
+        assert_eq!(
+            (tls.version.as_str(), tls.cipher.as_str()),
+            ("TLSv1.3", "TLS_AES_256_GCM_SHA384")
+        );
+        assert_eq!(tls.issuer, "C=US; O=Google Trust Services; CN=WR2");
+        assert_eq!(tls.expires, "Nov 24 08:36:04 2026 GMT");
+        assert!(tls.verified);
+
+        // Plain HTTP with nothing listening
+        let mut failed = HttpCheck::default();
+        assert!(parse_write_out(
+            "000|0||0.001|0.000|0.000|0.000|0.002|0|0",
+            &mut failed
+        ));
+        assert_eq!((failed.status, failed.timings.tls), (None, None));
+        assert_eq!(
+            curl_error("*   Trying 10.0.0.9:80...\ncurl: (7) Failed to connect\n").as_deref(),
+            Some("(7) Failed to connect")
+        );
+    }
+}
@@ -30,12 +30,30 @@ pub const SUCCESS: &str = ""; // nf-fa-check_circle
 pub const INFO: &str = ""; // nf-fa-info_circle
 pub const HISTORY: &str = ""; // nf-fa-history
 pub const AUTO_CONNECT: &str = ""; // nf-fa-refresh
+pub const LIVE: &str = ""; // nf-fa-play
+pub const PAUSED: &str = ""; // nf-fa-pause
 pub const HOTSPOT: &str = ""; // nf-fa-hotspot
+pub const PIN: &str = ""; // nf-fa-thumb_tack
 
 // Interface state icons
 pub const UP: &str = ""; // nf-fa-arrow_circle_up
 pub const DOWN: &str = ""; // nf-fa-arrow_circle_down
 pub const UNKNOWN: &str = ""; // nf-fa-question_circle
+pub const NO_CARRIER: &str = ""; // nf-fa-chain_broken
+pub const CAPTURE: &str = ""; // nf-md-eye
+pub const CONTAINER: &str = ""; // nf-fa-cube
+pub const IDENTIFY: &str = ""; // nf-fa-bolt
+
+// Link kind icons, for virtual interfaces
+pub const BRIDGE: &str = ""; // nf-fa-code_fork
+pub const BOND: &str = ""; // nf-fa-chain
+pub const VLAN: &str = ""; // nf-fa-tag
+pub const TUNNEL: &str = ""; // nf-fa-exchange
+pub const WIREGUARD: &str = ""; // nf-fa-shield
+pub const VETH: &str = ""; // nf-fa-arrows_h
+pub const USB: &str = ""; // nf-fa-usb
+pub const DUMMY: &str = ""; // nf-fa-circle_o
+pub const VIRTUAL: &str = ""; // nf-fa-cloud
 
 // Traffic direction icons
 pub const RX: &str = ""; // nf-fa-download
@@ -0,0 +1,223 @@
+// src/influx.rs - Interface rates and WiFi signal pushed as InfluxDB line protocol, for history in an existing TSDB
+use crate::groups::Rates;
+use crate::network::Interface;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// `[influx]`: push to InfluxDB, VictoriaMetrics or anything else that takes
+/// line protocol over HTTP
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InfluxSettings {
+    pub enabled: bool,
+    /// The whole write URL, database or bucket included:
+    /// http://influx:8086/api/v2/write?org=home&bucket=lantern,
+    /// http://victoria:8428/write
+    pub url: String,
+    /// Sent as "Authorization: Token ..." (InfluxDB 2)
+    pub token: Option<String>,
+    /// Basic auth instead (InfluxDB 1)
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub measurement: String,
+    pub interval_secs: u64,
+}
+
+impl Default for InfluxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "http://localhost:8086/write?db=lantern".to_string(),
+            token: None,
+            username: None,
+            password: None,
+            measurement: "lantern_interface".to_string(),
+            interval_secs: 30,
+        }
+    }
+}
+
+impl InfluxSettings {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+}
+
+/// Tag keys and values, and measurement names, escape commas, spaces and equals signs
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | ' ' | '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// One line per interface, stamped in nanoseconds (line protocol's default precision):
+/// `lantern_interface,host=router,interface=wlan0,ssid=Home up=true,rx_bytes=1000i,... 1760...`
+pub fn lines(
+    measurement: &str,
+    host: &str,
+    interfaces: &[Interface],
+    rates: &Rates,
+    at: SystemTime,
+) -> String {
+    let nanos = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let mut lines = String::new();
+    for interface in interfaces {
+        let wifi = interface.wifi_info.as_ref();
+        let network = wifi.and_then(|wifi| wifi.current_network.as_ref());
+        let _ = write!(
+            lines,
+            "{},host={},interface={}",
+            escape(measurement),
+            escape(host),
+            escape(&interface.name)
+        );
+        if let Some(network) = network.filter(|network| !network.ssid.is_empty()) {
+            let _ = write!(lines, ",ssid={}", escape(&network.ssid));
+        }
+        let (rx_rate, tx_rate) = rates.get(&interface.name);
+        let _ = write!(
+            lines,
+            " up={},rx_bytes={}i,tx_bytes={}i,rx_bytes_per_sec={}i,tx_bytes_per_sec={}i,rx_errors={}i,tx_errors={}i",
+            interface.state == "UP",
+            interface.stats.rx_bytes,
+            interface.stats.tx_bytes,
+            rx_rate,
+            tx_rate,
+            interface.stats.rx_errors,
+            interface.stats.tx_errors,
+        );
+        let signal = wifi
+            .and_then(|wifi| wifi.signal_strength)
+            .or(network.map(|network| network.signal_strength));
+        if let Some(dbm) = signal {
+            let _ = write!(lines, ",signal_dbm={}i", dbm);
+        }
+        let _ = writeln!(lines, " {}", nanos);
+    }
+    lines
+}
+
+/// Pushes in the background; the last failure is kept until a push works
+pub struct Pusher {
+    settings: InfluxSettings,
+    host: String,
+    rates: Rates,
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl Pusher {
+    pub fn new(settings: &InfluxSettings) -> Result<Self> {
+        let url = settings.url.trim();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(anyhow!("[influx] url must be http:// or https://"));
+        }
+        Ok(Self {
+            settings: settings.clone(),
+            host: crate::mqtt::hostname(),
+            rates: Rates::default(),
+            error: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Send the state of every interface, without waiting for the server
+    pub fn push(&mut self, interfaces: &[Interface]) {
+        self.rates.record(interfaces, Instant::now());
+        let body = lines(
+            &self.settings.measurement,
+            &self.host,
+            interfaces,
+            &self.rates,
+            SystemTime::now(),
+        );
+        if body.is_empty() {
+            return;
+        }
+        let settings = &self.settings;
+        let mut config = crate::alerts::config_line("url", settings.url.trim());
+        config.push_str(&crate::alerts::config_line(
+            "header",
+            "Content-Type: text/plain; charset=utf-8",
+        ));
+        if let Some(token) = &settings.token {
+            config.push_str(&crate::alerts::config_line(
+                "header",
+                &format!("Authorization: Token {}", token),
+            ));
+        } else if let Some(username) = &settings.username {
+            let password = settings.password.as_deref().unwrap_or_default();
+            config.push_str(&crate::alerts::config_line(
+                "user",
+                &format!("{}:{}", username, password),
+            ));
+        }
+        config.push_str(&crate::alerts::config_line("data-binary", &body));
+        let error = Arc::clone(&self.error);
+        tokio::spawn(async move {
+            let result = crate::alerts::curl(config).await;
+            *error.lock().unwrap_or_else(|e| e.into_inner()) = result.err().map(|e| e.to_string());
+        });
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn writes_line_protocol() {
+        let wlan0: Interface = serde_json::from_value(json!({
+            "name": "wlan0",
+            "mac_address": "aa:bb:cc:dd:ee:ff",
+            "state": "UP",
+            "mtu": 1500,
+            "ipv4_addresses": [],
+            "ipv6_addresses": [],
+            "gateway": null,
+            "dns_servers": [],
+            "stats": {"rx_bytes": 1000, "tx_bytes": 500, "rx_packets": 0, "tx_packets": 0,
+                      "rx_errors": 2, "tx_errors": 0},
+            "wifi_info": {
+                "current_network": {
+                    "ssid": "Home Net", "bssid": "11:22:33:44:55:66", "signal_strength": -58,
+                    "frequency": 5180, "channel": 36, "security": "WPA2", "encryption": [],
+                    "connected": true, "in_history": true,
+                },
+            },
+        }))
+        .unwrap();
+
+        let at = UNIX_EPOCH + Duration::from_secs(1_760_000_000);
+        let body = lines(
+            "lantern_interface",
+            "my router",
+            &[wlan0],
+            &Rates::default(),
+            at,
+        );
+        assert_eq!(
+            body,
+            "lantern_interface,host=my\\ router,interface=wlan0,ssid=Home\\ Net \
+             up=true,rx_bytes=1000i,tx_bytes=500i,rx_bytes_per_sec=0i,tx_bytes_per_sec=0i,\
+             rx_errors=2i,tx_errors=0i,signal_dbm=-58i 1760000000000000000\n"
+        );
+        assert_eq!(escape("a,b=c"), "a\\,b\\=c");
+        assert!(Pusher::new(&InfluxSettings {
+            url: "localhost:8086".to_string(),
+            ..InfluxSettings::default()
+        })
+        .is_err());
+    }
+}
@@ -2,17 +2,31 @@
 #![allow(dead_code)] // Many methods are for future features or CLI mode
 #![allow(clippy::needless_borrows_for_generic_args)] // Command args are clearer with explicit borrows
 #![allow(clippy::collapsible_if)] // Code clarity over micro-optimizations
+use crate::command::TimedOutput;
+use crate::network::{WifiBand, WifiSecurity};
+use crate::wifi_ies::{ElementParser, SecurityDetails};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::path::Path;
+use tokio::process::Command;
+
+const MAIN_CONF: &str = "/etc/iwd/main.conf";
+
+// iwd's per-band rank modifiers; 0.0 keeps it off a band entirely
+const BAND_MODIFIERS: [&str; 3] = ["BandModifier2_4GHz", "BandModifier5GHz", "BandModifier6GHz"];
 
 // No more fake signal generation - using real iw data only!
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IwdNetwork {
     pub name: String,
+    pub bssid: String,
     pub signal_strength: i16,
-    pub security_type: String,
+    pub frequency: u32,       // MHz
+    pub channel: Option<u32>, // As advertised by the AP, if it says
+
+    pub security_type: String, // As iwd names it: open, wep, psk, sae or 8021x
+    pub security_details: SecurityDetails,
     pub path: String,
     pub connected: bool,
 }
@@ -27,7 +41,7 @@ pub struct IwdDevice {
     pub path: String,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct IwdManager;
 
 impl IwdManager {
@@ -35,120 +49,27 @@ impl IwdManager {
         Self
     }
 
-    // Parse iw scan output to extract real WiFi network data
-    fn parse_iw_scan_output(&self, output: &str) -> Result<Vec<IwdNetwork>> {
-        let mut networks = Vec::new();
-        let mut _current_bss: Option<String> = None;
-        let mut current_ssid: Option<String> = None;
-        let mut current_signal: Option<i16> = None;
-        let mut current_security = "open".to_string();
-
-        for line in output.lines() {
-            let line = line.trim();
-
-            // New BSS entry starts
-            if line.starts_with("BSS ") {
-                // Save previous network if complete
-                if let (Some(ssid), Some(signal)) = (&current_ssid, current_signal) {
-                    if !ssid.is_empty() {
-                        networks.push(IwdNetwork {
-                            name: ssid.clone(),
-                            signal_strength: signal,
-                            security_type: current_security.clone(),
-                            path: format!("/net/connman/iwd/network/{}", ssid),
-                            connected: false, // We'll detect this separately
-                        });
-                    }
-                }
-
-                // Reset for new BSS
-                _current_bss = Some(line.to_string());
-                current_ssid = None;
-                current_signal = None;
-                current_security = "open".to_string();
-            }
-
-            // Signal strength
-            if line.starts_with("signal: ") && line.contains("dBm") {
-                if let Some(dbm_str) = line.split_whitespace().nth(1) {
-                    if let Ok(dbm_float) = dbm_str.parse::<f32>() {
-                        current_signal = Some(dbm_float as i16);
-                    }
-                }
-            }
-
-            // SSID
-            if line.starts_with("SSID: ") {
-                let ssid = line.strip_prefix("SSID: ").unwrap_or("").trim();
-                if !ssid.is_empty() {
-                    current_ssid = Some(ssid.to_string());
-                }
-            }
-
-            // Security (detect WPA/WPA2/WPA3)
-            if line.contains("RSN:") || line.contains("WPA:") {
-                current_security = "psk".to_string();
-            }
-            if line.contains("Privacy") {
-                if current_security == "open" {
-                    current_security = "wep".to_string();
-                }
-            }
-        }
-
-        // Save last network
-        if let (Some(ssid), Some(signal)) = (&current_ssid, current_signal) {
-            if !ssid.is_empty() {
-                networks.push(IwdNetwork {
-                    name: ssid.clone(),
-                    signal_strength: signal,
-                    security_type: current_security,
-                    path: format!("/net/connman/iwd/network/{}", ssid),
-                    connected: false,
-                });
-            }
-        }
-
-        // Sort by signal strength (strongest first)
-        networks.sort_by(|a, b| b.signal_strength.cmp(&a.signal_strength));
-
-        Ok(networks)
-    }
-
     // Get real signal strength for a connected network
     pub async fn get_connection_signal(&self, device_name: &str) -> Result<Option<i16>> {
         let output = Command::new("/usr/bin/iwctl")
             .args(&["station", device_name, "show"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to get station info")?;
 
         if !output.status.success() {
             return Ok(None);
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        // Look for RSSI line in the output
-        for line in stdout.lines() {
-            if line.contains("RSSI") && line.contains("dBm") {
-                // Parse line like "RSSI                  -54 dBm"
-                if let Some(dbm_part) = line.split_whitespace().find(|part| part.ends_with("dBm")) {
-                    let dbm_str = dbm_part.trim_end_matches("dBm");
-                    if let Ok(dbm) = dbm_str.parse::<i16>() {
-                        return Ok(Some(dbm));
-                    }
-                }
-            }
-        }
-
-        Ok(None)
+        Ok(parse_station_rssi(&String::from_utf8_lossy(&output.stdout)))
     }
 
     pub async fn connect(&mut self) -> Result<()> {
         // Check if iwctl is available by listing devices
         let output = Command::new("/usr/bin/iwctl")
             .args(&["device", "list"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to check iwctl availability")?;
 
         if !output.status.success() {
@@ -158,7 +79,8 @@ impl IwdManager {
         // Check if iwd service is running
         let status = Command::new("/usr/bin/systemctl")
             .args(&["is-active", "iwd"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to check iwd service status")?;
 
         if !status.status.success() {
@@ -171,43 +93,23 @@ impl IwdManager {
     pub async fn get_devices(&self) -> Result<Vec<IwdDevice>> {
         let output = Command::new("/usr/bin/iwctl")
             .args(&["device", "list"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to list wireless devices")?;
 
         if !output.status.success() {
             return Err(anyhow::anyhow!("Failed to get wireless devices"));
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut devices = Vec::new();
-
-        // Parse iwctl device list output
-        for line in stdout.lines().skip(4) {
-            // Skip header lines
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 4 {
-                let name = parts[0].to_string();
-                let powered = parts[3] == "on";
-
-                devices.push(IwdDevice {
-                    name: name.clone(),
-                    powered,
-                    adapter: "unknown".to_string(),
-                    mode: "station".to_string(),
-                    scanning: false,
-                    path: format!("/net/connman/iwd/{}", name),
-                });
-            }
-        }
-
-        Ok(devices)
+        Ok(parse_device_list(&String::from_utf8_lossy(&output.stdout)))
     }
 
     pub async fn scan_networks(&self, device_name: &str) -> Result<Vec<IwdNetwork>> {
         // Use iw to trigger scan and get results directly
-        let scan_output = Command::new("/usr/bin/iw")
+        let scan_output = Command::new(crate::command::IW)
             .args(&["dev", device_name, "scan"])
-            .output()
+            .timed_output_with(crate::command::scan_timeout())
+            .await
             .context("Failed to scan with iw")?;
 
         if !scan_output.status.success() {
@@ -215,7 +117,9 @@ impl IwdManager {
         }
 
         // Parse iw scan output directly - no need to wait
-        self.parse_iw_scan_output(&String::from_utf8_lossy(&scan_output.stdout))
+        Ok(parse_iw_scan_output(&String::from_utf8_lossy(
+            &scan_output.stdout,
+        )))
     }
 
     pub async fn get_networks(&self, device_name: &str) -> Result<Vec<IwdNetwork>> {
@@ -229,6 +133,8 @@ impl IwdManager {
         network_name: &str,
         passphrase: Option<&str>,
     ) -> Result<()> {
+        crate::sanitize::interface_name(device_name)?;
+
         let mut cmd = Command::new("/usr/bin/iwctl");
         cmd.args(&["station", device_name, "connect", network_name]);
 
@@ -236,7 +142,10 @@ impl IwdManager {
             cmd.args(&["--passphrase", pass]);
         }
 
-        let output = cmd.output().context("Failed to connect to WiFi network")?;
+        let output = cmd
+            .timed_output_with(crate::command::scan_timeout())
+            .await
+            .context("Failed to connect to WiFi network")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -246,10 +155,48 @@ impl IwdManager {
         Ok(())
     }
 
+    /// Hold iwd to one band, or release it, by rewriting the `[Rank]` band
+    /// modifiers in main.conf. iwd has no per-network setting for this, so the
+    /// lock applies to whatever it connects to until the next connection
+    /// through lantern changes it. Restarts iwd only when the file changed.
+    pub async fn lock_band(&self, band: WifiBand) -> Result<()> {
+        let current = std::fs::read_to_string(MAIN_CONF).unwrap_or_default();
+        let updated = with_band_lock(&current, band);
+        if updated == current {
+            return Ok(());
+        }
+        let status = Command::new("/usr/bin/systemctl")
+            .args(&["is-active", "iwd"])
+            .timed_output()
+            .await
+            .context("Failed to check iwd service status")?;
+        if !status.status.success() {
+            return Err(anyhow::anyhow!("iwd service is not running"));
+        }
+
+        if let Some(dir) = Path::new(MAIN_CONF).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let updated = crate::provenance::stamp(&updated, "iwd network configuration");
+        crate::secure_file::write_public(MAIN_CONF, updated)?;
+
+        let output = Command::new("/usr/bin/systemctl")
+            .args(&["restart", "iwd"])
+            .timed_output()
+            .await
+            .context("Failed to restart iwd")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("Failed to restart iwd: {}", stderr));
+        }
+        Ok(())
+    }
+
     pub async fn disconnect_device(&self, device_name: &str) -> Result<()> {
         let output = Command::new("/usr/bin/iwctl")
             .args(&["station", device_name, "disconnect"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to disconnect from WiFi")?;
 
         if !output.status.success() {
@@ -279,7 +226,8 @@ impl IwdManager {
                 "Powered",
                 power_state,
             ])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to set device power state")?;
 
         if !output.status.success() {
@@ -290,3 +238,296 @@ impl IwdManager {
         Ok(())
     }
 }
+
+/// main.conf with the other bands' modifiers at 0.0 for `band`, and any
+/// earlier lock (a modifier at 0.0) lifted. Other preferences are left alone.
+pub fn with_band_lock(main_conf: &str, band: WifiBand) -> String {
+    let disabled: &[&str] = match band {
+        WifiBand::Any => &[],
+        WifiBand::Ghz2_4 => &[BAND_MODIFIERS[1], BAND_MODIFIERS[2]],
+        WifiBand::Ghz5 => &[BAND_MODIFIERS[0], BAND_MODIFIERS[2]],
+    };
+    let modifiers: String = disabled
+        .iter()
+        .map(|key| format!("{}=0.0\n", key))
+        .collect();
+
+    let mut updated = String::new();
+    let mut in_rank = false;
+    let mut has_rank = false;
+    for line in main_conf.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_rank = trimmed == "[Rank]";
+            if in_rank {
+                has_rank = true;
+                updated.push_str(line);
+                updated.push('\n');
+                updated.push_str(&modifiers);
+                continue;
+            }
+        }
+        let (key, value) = trimmed.split_once('=').unwrap_or((trimmed, ""));
+        let key = key.trim();
+        if in_rank
+            && BAND_MODIFIERS.contains(&key)
+            && (disabled.contains(&key) || value.trim().parse::<f64>() == Ok(0.0))
+        {
+            continue;
+        }
+        updated.push_str(line);
+        updated.push('\n');
+    }
+    if !has_rank && !modifiers.is_empty() {
+        if !updated.is_empty() {
+            updated.push('\n');
+        }
+        updated.push_str("[Rank]\n");
+        updated.push_str(&modifiers);
+    }
+    updated
+}
+
+/// Networks from `iw dev <iface> scan`, strongest first. Hidden networks are skipped.
+pub fn parse_iw_scan_output(output: &str) -> Vec<IwdNetwork> {
+    let mut networks = Vec::new();
+    let mut current: Option<IwdNetwork> = None;
+    let mut elements = ElementParser::default();
+
+    let finish = |mut network: IwdNetwork, elements: ElementParser| {
+        network.security_details = elements.finish();
+        network.security_type = iwd_security_type(&network.security_details).to_string();
+        Some(network).filter(|n| !n.name.is_empty())
+    };
+
+    for raw_line in output.lines() {
+        // New BSS entry starts. Only unindented lines: "BSS Load:" is an
+        // element inside an entry
+        if let Some(header) = raw_line.strip_prefix("BSS ") {
+            if let Some(network) = current.take() {
+                networks.extend(finish(network, std::mem::take(&mut elements)));
+            }
+
+            // "BSS 00:11:22:33:44:55(on wlan0) -- associated"
+            let bssid = header.split('(').next().unwrap_or("").trim();
+            current = Some(IwdNetwork {
+                name: String::new(),
+                bssid: bssid.to_string(),
+                signal_strength: 0,
+                frequency: 0,
+                channel: None,
+                security_type: "open".to_string(),
+                security_details: SecurityDetails::default(),
+                path: String::new(),
+                connected: header.contains("-- associated"),
+            });
+            continue;
+        }
+
+        let Some(network) = current.as_mut() else {
+            continue;
+        };
+        let line = raw_line.trim();
+
+        // Frequency, "freq: 2412" or "freq: 5180.0" on newer iw
+        if let Some(freq) = line.strip_prefix("freq: ") {
+            if let Ok(mhz) = freq.trim().parse::<f32>() {
+                network.frequency = mhz as u32;
+            }
+        }
+
+        // Channel, from the DS element (2.4 GHz) or HT operation (5 GHz)
+        let channel = line
+            .strip_prefix("DS Parameter set: channel ")
+            .or_else(|| line.strip_prefix("* primary channel: "));
+        if let Some(channel) = channel.and_then(|c| c.trim().parse().ok()) {
+            network.channel = Some(channel);
+        }
+
+        // Signal strength
+        if line.starts_with("signal: ") && line.contains("dBm") {
+            if let Some(dbm_str) = line.split_whitespace().nth(1) {
+                if let Ok(dbm_float) = dbm_str.parse::<f32>() {
+                    network.signal_strength = dbm_float as i16;
+                }
+            }
+        }
+
+        // SSID
+        if let Some(ssid) = line.strip_prefix("SSID: ") {
+            let ssid = ssid.trim();
+            if !ssid.is_empty() {
+                network.name = crate::network::unescape_ssid(ssid);
+                network.path = format!("/net/connman/iwd/network/{}", ssid);
+            }
+        }
+
+        // Security elements (RSN, WPA, WPS, capability flags)
+        elements.feed(line);
+    }
+
+    // Save last network
+    if let Some(network) = current {
+        networks.extend(finish(network, elements));
+    }
+
+    // Sort by signal strength (strongest first)
+    networks.sort_by_key(|n| std::cmp::Reverse(n.signal_strength));
+    networks
+}
+/// Devices from `iwctl device list`. Columns are found by the header, since
+/// iwd releases differ in the columns they print and pad them with colour codes.
+pub fn parse_device_list(output: &str) -> Vec<IwdDevice> {
+    let mut columns: Vec<String> = Vec::new();
+    let mut devices = Vec::new();
+
+    for line in output.lines() {
+        let line = strip_ansi(line);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() || line.trim_start().starts_with('-') {
+            continue;
+        }
+        if fields.first() == Some(&"Name") {
+            columns = fields.iter().map(|field| field.to_lowercase()).collect();
+            continue;
+        }
+        // The title row above the header, or "No devices in Station mode"
+        if columns.is_empty() || fields.len() < columns.len() {
+            continue;
+        }
+
+        let column = |name: &str| {
+            columns
+                .iter()
+                .position(|column| column == name)
+                .and_then(|index| fields.get(index))
+                .copied()
+        };
+        let name = fields[0].to_string();
+        devices.push(IwdDevice {
+            powered: column("powered") == Some("on"),
+            adapter: column("adapter").unwrap_or("unknown").to_string(),
+            mode: column("mode").unwrap_or("station").to_string(),
+            scanning: false,
+            path: format!("/net/connman/iwd/{}", name),
+            name,
+        });
+    }
+
+    devices
+}
+
+/// RSSI from `iwctl station <device> show`: "RSSI   -54 dBm". AverageRSSI is skipped.
+pub fn parse_station_rssi(output: &str) -> Option<i16> {
+    output.lines().find_map(|line| {
+        let line = strip_ansi(line);
+        let mut fields = line.split_whitespace();
+        fields.find(|field| *field == "RSSI")?;
+        let value = fields.next()?;
+        value.trim_end_matches("dBm").parse().ok()
+    })
+}
+
+/// iwctl colours its tables even when stdout isn't a terminal.
+fn strip_ansi(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequence: ESC [ parameters final-byte
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+fn iwd_security_type(details: &SecurityDetails) -> &'static str {
+    match details.security() {
+        WifiSecurity::Open => "open",
+        WifiSecurity::WEP => "wep",
+        WifiSecurity::WPA | WifiSecurity::WPA2 => "psk",
+        WifiSecurity::WPA3 => "sae",
+        WifiSecurity::Enterprise => "8021x",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCAN: &str = "\
+BSS 00:11:22:33:44:55(on wlan0) -- associated
+\tTSF: 1234 usec (0d, 00:00:00)
+\tfreq: 5180.0
+\tsignal: -48.00 dBm
+\tSSID: Home
+\tBSS Load:
+\t\t * station count: 3
+\tRSN:\t * Version: 1
+\t\t * Authentication suites: PSK
+\tHT operation:
+\t\t * primary channel: 36
+BSS 66:77:88:99:aa:bb(on wlan0)
+\tfreq: 2437
+\tsignal: -71.00 dBm
+\tSSID: Cafe
+\tDS Parameter set: channel 6
+BSS cc:dd:ee:ff:00:11(on wlan0)
+\tfreq: 2462
+\tsignal: -80.00 dBm
+\tSSID: 
+";
+
+    #[test]
+    fn locks_and_releases_a_band() {
+        let conf = "[General]\nEnableNetworkConfiguration=true\n\n[Rank]\nBandModifier5GHz=1.5\n";
+        let locked = with_band_lock(conf, WifiBand::Ghz2_4);
+        assert_eq!(
+            locked,
+            "[General]\nEnableNetworkConfiguration=true\n\n[Rank]\nBandModifier5GHz=0.0\nBandModifier6GHz=0.0\n"
+        );
+        // Switching to 5 GHz lifts the old lock; only 2.4 and 6 GHz stay off
+        let locked = with_band_lock(&locked, WifiBand::Ghz5);
+        assert!(locked.contains("BandModifier2_4GHz=0.0\nBandModifier6GHz=0.0\n"));
+        assert!(!locked.contains("BandModifier5GHz"));
+
+        let released = "[General]\nEnableNetworkConfiguration=true\n\n[Rank]\n";
+        assert_eq!(with_band_lock(&locked, WifiBand::Any), released);
+        // A user's own non-zero preference survives a release
+        assert_eq!(with_band_lock(conf, WifiBand::Any), conf);
+        assert_eq!(with_band_lock("", WifiBand::Any), "");
+        assert_eq!(
+            with_band_lock("", WifiBand::Ghz5),
+            "[Rank]\nBandModifier2_4GHz=0.0\nBandModifier6GHz=0.0\n"
+        );
+        assert_eq!(WifiBand::Ghz2_4.freq_list().unwrap().split(' ').count(), 14);
+    }
+
+    #[test]
+    fn parses_bssid_frequency_and_channel() {
+        let networks = parse_iw_scan_output(SCAN);
+        assert_eq!(networks.len(), 2); // Hidden network skipped
+
+        let home = &networks[0];
+        assert_eq!(home.name, "Home");
+        assert_eq!(home.bssid, "00:11:22:33:44:55");
+        assert_eq!((home.frequency, home.channel), (5180, Some(36)));
+        assert_eq!(home.signal_strength, -48);
+        assert_eq!(home.security_type, "psk"); // Not cut short by "BSS Load:"
+        assert!(home.connected);
+
+        let cafe = &networks[1];
+        assert_eq!(cafe.bssid, "66:77:88:99:aa:bb");
+        assert_eq!((cafe.frequency, cafe.channel), (2437, Some(6)));
+        assert_eq!(cafe.security_type, "open");
+        assert!(!cafe.connected);
+    }
+}
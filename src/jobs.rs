@@ -0,0 +1,408 @@
+// src/jobs.rs - Changes that take a while, queued and run one at a time with their state kept
+use crate::config::Profile;
+use crate::lock::OperationLock;
+use crate::network::{HotspotConfig, NetworkManager};
+use crate::systemd::SystemdNetworkConfig;
+use crate::usbshare::UsbShareSettings;
+use std::time::Instant;
+use tokio::task::AbortHandle;
+
+/// Finished jobs kept for the panel; the oldest go first
+const KEEP_FINISHED: usize = 20;
+
+pub type JobId = u64;
+
+/// A change to make, with everything it needs taken from the App when queued
+#[derive(Debug, Clone)]
+pub enum Job {
+    /// A saved profile written as the interface's .network file
+    ApplyProfile { interface: String, profile: Profile },
+    /// The channel is surveyed first when `survey` is set
+    StartHotspot { config: HotspotConfig, survey: bool },
+    /// Move the running hotspot into boot-time units, or remove them
+    PersistHotspot {
+        config: HotspotConfig,
+        persist: bool,
+    },
+    /// Share this machine's connection over a USB gadget link, or stop
+    UsbShare {
+        interface: String,
+        settings: UsbShareSettings,
+        share: bool,
+    },
+}
+
+/// What a job that went through leaves the App to do
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Applied(String),
+    HotspotStarted(HotspotConfig, String),
+    /// The hotspot still running afterwards, if any
+    HotspotPersisted(Option<HotspotConfig>, String),
+    /// The interface, and its address while it's shared
+    UsbShared(String, Option<String>, String),
+}
+
+impl Outcome {
+    pub fn message(&self) -> &str {
+        match self {
+            Outcome::Applied(message)
+            | Outcome::HotspotStarted(_, message)
+            | Outcome::HotspotPersisted(_, message)
+            | Outcome::UsbShared(_, _, message) => message,
+        }
+    }
+}
+
+impl Job {
+    /// For spawn_task and the quit prompt
+    pub fn name(&self) -> &'static str {
+        match self {
+            Job::ApplyProfile { .. } => "apply profile",
+            Job::StartHotspot { .. } => "start hotspot",
+            Job::PersistHotspot { .. } => "persist hotspot",
+            Job::UsbShare { .. } => "USB sharing",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Job::ApplyProfile { interface, profile } => {
+                format!("Apply profile '{}' to {}", profile.name, interface)
+            }
+            Job::StartHotspot { config, .. } => {
+                format!("Start hotspot '{}' on {}", config.ssid, config.interface)
+            }
+            Job::PersistHotspot { config, persist } => format!(
+                "{} hotspot '{}' {} boot",
+                if *persist { "Keep" } else { "Stop" },
+                config.ssid,
+                if *persist { "across" } else { "starting at" }
+            ),
+            Job::UsbShare {
+                interface, share, ..
+            } => format!(
+                "{} sharing the network over {}",
+                if *share { "Start" } else { "Stop" },
+                interface
+            ),
+        }
+    }
+
+    /// Make the change. Waits for nobody: another instance holding the lock
+    /// fails the job, and it can be retried.
+    pub async fn run(
+        self,
+        network_manager: NetworkManager,
+        systemd_config: SystemdNetworkConfig,
+    ) -> Result<Outcome, String> {
+        let _lock = OperationLock::try_acquire().map_err(|e| e.to_string())?;
+        match self {
+            Job::ApplyProfile { interface, profile } => {
+                if network_manager.is_demo() {
+                    return Ok(Outcome::Applied(format!(
+                        "Demo mode: '{}' not written",
+                        profile.name
+                    )));
+                }
+                let extras = profile.extras();
+                systemd_config
+                    .create_config(
+                        &interface,
+                        profile.dhcp,
+                        profile.ip,
+                        profile.gateway,
+                        profile.dns,
+                        &extras,
+                    )
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(Outcome::Applied(format!(
+                    "Applied profile '{}' to {}",
+                    profile.name, interface
+                )))
+            }
+            Job::StartHotspot { mut config, survey } => {
+                // Before the interface turns into an access point and can't scan
+                let heard = if survey {
+                    let (channel, heard) = network_manager
+                        .survey_hotspot_channel(&config.interface)
+                        .await
+                        .map_err(|e| format!("Channel survey failed: {}", e))?;
+                    config.channel = channel;
+                    Some(heard)
+                } else {
+                    None
+                };
+                network_manager
+                    .create_hotspot(&config)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let message = match heard {
+                    Some(heard) => format!(
+                        "Hotspot '{}' created on channel {} (least congested, {} access points nearby)",
+                        config.ssid, config.channel, heard
+                    ),
+                    None => format!("Hotspot '{}' created successfully", config.ssid),
+                };
+                Ok(Outcome::HotspotStarted(config, message))
+            }
+            Job::PersistHotspot {
+                mut config,
+                persist,
+            } => {
+                network_manager
+                    .persist_hotspot(&config, persist)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if !persist {
+                    return Ok(Outcome::HotspotPersisted(
+                        None,
+                        format!("Hotspot '{}' stopped and removed from boot", config.ssid),
+                    ));
+                }
+                config.persistent = true;
+                let message = format!("Hotspot '{}' now starts at boot", config.ssid);
+                Ok(Outcome::HotspotPersisted(Some(config), message))
+            }
+            Job::UsbShare {
+                interface,
+                settings,
+                share,
+            } => {
+                let address = share.then(|| settings.address.trim().to_string());
+                if network_manager.is_demo() {
+                    let message = format!("Demo mode: {} not reconfigured", interface);
+                    return Ok(Outcome::UsbShared(interface, address, message));
+                }
+                let message = if share {
+                    crate::usbshare::share(&interface, &settings)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    format!(
+                        "Sharing the network over {}: the USB host gets an address by DHCP",
+                        interface
+                    )
+                } else {
+                    crate::usbshare::unshare(&interface)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    format!("Stopped sharing the network over {}", interface)
+                };
+                Ok(Outcome::UsbShared(interface, address, message))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    Pending,
+    Running,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+impl JobState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobState::Pending => "pending",
+            JobState::Running => "running",
+            JobState::Done => "done",
+            JobState::Failed(_) => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+
+    fn finished(&self) -> bool {
+        matches!(
+            self,
+            JobState::Done | JobState::Failed(_) | JobState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct QueuedJob {
+    pub id: JobId,
+    pub job: Job,
+    pub state: JobState,
+    pub queued: Instant,
+    /// When it last changed state
+    pub since: Instant,
+    abort: Option<AbortHandle>,
+}
+
+/// Jobs in the order they were queued. One runs at a time, since each takes
+/// the operation lock anyway.
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    jobs: Vec<QueuedJob>,
+    next_id: JobId,
+}
+
+impl JobQueue {
+    pub fn push(&mut self, job: Job) -> JobId {
+        self.next_id += 1;
+        let now = Instant::now();
+        self.jobs.push(QueuedJob {
+            id: self.next_id,
+            job,
+            state: JobState::Pending,
+            queued: now,
+            since: now,
+            abort: None,
+        });
+        let finished = self.jobs.iter().filter(|job| job.state.finished()).count();
+        let mut excess = finished.saturating_sub(KEEP_FINISHED);
+        self.jobs.retain(|job| {
+            let drop = excess > 0 && job.state.finished();
+            excess -= drop as usize;
+            !drop
+        });
+        self.next_id
+    }
+
+    pub fn jobs(&self) -> &[QueuedJob] {
+        &self.jobs
+    }
+
+    /// Pending or running
+    pub fn active(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Pending | JobState::Running))
+            .count()
+    }
+
+    /// The next job to start, when none is running; marked running
+    pub fn next_due(&mut self) -> Option<(JobId, Job)> {
+        if self.jobs.iter().any(|job| job.state == JobState::Running) {
+            return None;
+        }
+        let next = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.state == JobState::Pending)?;
+        next.state = JobState::Running;
+        next.since = Instant::now();
+        Some((next.id, next.job.clone()))
+    }
+
+    /// How to stop the running job, for cancel
+    pub fn started(&mut self, id: JobId, abort: AbortHandle) {
+        if let Some(job) = self.get_mut(id) {
+            job.abort = Some(abort);
+        }
+    }
+
+    /// Record the result; false when the job was cancelled meanwhile and
+    /// the result should be ignored
+    pub fn finish(&mut self, id: JobId, result: &Result<Outcome, String>) -> bool {
+        let Some(job) = self
+            .get_mut(id)
+            .filter(|job| job.state == JobState::Running)
+        else {
+            return false;
+        };
+        job.state = match result {
+            Ok(_) => JobState::Done,
+            Err(e) => JobState::Failed(e.clone()),
+        };
+        job.since = Instant::now();
+        job.abort = None;
+        true
+    }
+
+    /// The running job's task panicked, so no result will come
+    pub fn panicked(&mut self, name: &str, error: &str) {
+        if let Some(job) = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.state == JobState::Running && job.job.name() == name)
+        {
+            job.state = JobState::Failed(error.to_string());
+            job.since = Instant::now();
+            job.abort = None;
+        }
+    }
+
+    /// Drop a pending job, or stop a running one where it is; false when
+    /// it had already finished
+    pub fn cancel(&mut self, id: JobId) -> bool {
+        let Some(job) = self.get_mut(id).filter(|job| !job.state.finished()) else {
+            return false;
+        };
+        if let Some(abort) = job.abort.take() {
+            abort.abort();
+        }
+        job.state = JobState::Cancelled;
+        job.since = Instant::now();
+        true
+    }
+
+    /// Queue a failed or cancelled job again, as a new one
+    pub fn retry(&mut self, id: JobId) -> Option<JobId> {
+        let job = self
+            .jobs
+            .iter()
+            .find(|job| job.id == id)
+            .filter(|job| matches!(job.state, JobState::Failed(_) | JobState::Cancelled))?
+            .job
+            .clone();
+        Some(self.push(job))
+    }
+
+    fn get_mut(&mut self, id: JobId) -> Option<&mut QueuedJob> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str) -> Job {
+        Job::ApplyProfile {
+            interface: "eth0".to_string(),
+            profile: Profile {
+                name: name.to_string(),
+                interface: "eth0".to_string(),
+                dhcp: true,
+                ip: None,
+                gateway: None,
+                dns: None,
+                routes: Vec::new(),
+                domains: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn runs_one_job_at_a_time_and_retries_failures() {
+        let mut queue = JobQueue::default();
+        let first = queue.push(profile("office"));
+        let second = queue.push(profile("home"));
+        assert_eq!(queue.active(), 2);
+
+        assert_eq!(queue.next_due().map(|(id, _)| id), Some(first));
+        assert!(queue.next_due().is_none()); // The first still runs
+        queue.finish(first, &Err("Another lantern is busy".to_string()));
+        assert_eq!(queue.jobs()[0].state.label(), "failed");
+
+        assert_eq!(queue.next_due().map(|(id, _)| id), Some(second));
+        assert!(queue.cancel(second));
+        // A result arriving after the cancel doesn't bring it back
+        assert!(!queue.finish(second, &Ok(Outcome::Applied(String::new()))));
+        assert_eq!(queue.jobs()[1].state, JobState::Cancelled);
+        assert!(!queue.cancel(second));
+
+        let again = queue.retry(first).unwrap();
+        assert!(queue.retry(again).is_none()); // Pending, nothing to retry
+        let (id, job) = queue.next_due().unwrap();
+        assert_eq!(id, again);
+        assert_eq!(job.describe(), "Apply profile 'office' to eth0");
+        assert_eq!(queue.active(), 1);
+    }
+}
@@ -0,0 +1,85 @@
+// src/lib.rs - Lantern's modules, shared by the binary and the benchmarks
+#![allow(clippy::needless_borrows_for_generic_args)] // Command args are clearer with explicit borrows
+
+pub mod agentx;
+pub mod alerts;
+pub mod app;
+pub mod baseline;
+pub mod capabilities;
+pub mod certs;
+pub mod checkpoint;
+pub mod clipboard;
+pub mod command;
+pub mod config;
+pub mod console;
+pub mod containers;
+pub mod daemon;
+pub mod demo;
+pub mod dhcpprobe;
+pub mod dnsleak;
+pub mod doctor;
+pub mod editor;
+pub mod events;
+pub mod exit_code;
+pub mod gai;
+pub mod groups;
+pub mod hotspot;
+pub mod hotspot_units;
+pub mod httpcheck;
+pub mod icons;
+pub mod influx;
+pub mod iwd;
+pub mod jobs;
+pub mod link_kind;
+pub mod linklocal;
+pub mod locale;
+pub mod lock;
+pub mod macconflict;
+pub mod modal;
+pub mod mqtt;
+pub mod neighbors;
+pub mod netlink;
+pub mod network;
+pub mod networkd;
+pub mod output;
+pub mod pacing;
+pub mod packets;
+pub mod palette;
+#[cfg(test)]
+mod parser_corpus;
+pub mod password;
+pub mod pending;
+pub mod perf;
+pub mod portfwd;
+pub mod provenance;
+pub mod pxe;
+pub mod qr;
+pub mod queues;
+pub mod rawedit;
+pub mod reachability;
+pub mod redraw;
+pub mod replay;
+pub mod resolved;
+pub mod retry;
+pub mod rfmon;
+pub mod routing;
+pub mod sanitize;
+pub mod scan_cache;
+pub mod screenshot;
+pub mod secure_file;
+pub mod snmp;
+pub mod speedtest;
+pub mod survey;
+pub mod systemd;
+pub mod takeover;
+pub mod tcp;
+pub mod throughput;
+pub mod topology;
+pub mod troubleshoot;
+pub mod trust;
+pub mod ui;
+pub mod usbshare;
+pub mod wgkey;
+pub mod wifi_events;
+pub mod wifi_ies;
+pub mod wifi_rate;
@@ -0,0 +1,131 @@
+// src/link_kind.rs - What sort of link an interface is: physical NIC or one of the virtual kinds
+use crate::icons;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LinkKind {
+    /// Backed by a device: Ethernet, WiFi, USB, ...
+    #[default]
+    Physical,
+    Bridge,
+    Bond,
+    Vlan,
+    /// tun or tap, as OpenVPN and VMs use
+    Tun,
+    WireGuard,
+    Veth,
+    Dummy,
+    /// A USB device-side Ethernet gadget (RNDIS/ECM), as usb0 on a
+    /// Raspberry Pi or a phone
+    Gadget,
+    /// Any other kind without a device (macvlan, vxlan, gre, ...)
+    Virtual,
+}
+
+impl LinkKind {
+    /// From IFLA_INFO_KIND, or the DEVTYPE a uevent file names
+    pub fn from_kind(kind: &str) -> Self {
+        match kind {
+            "bridge" => LinkKind::Bridge,
+            "bond" => LinkKind::Bond,
+            "vlan" => LinkKind::Vlan,
+            "tun" | "tap" => LinkKind::Tun,
+            "wireguard" => LinkKind::WireGuard,
+            "veth" => LinkKind::Veth,
+            "dummy" => LinkKind::Dummy,
+            "gadget" => LinkKind::Gadget,
+            // Types of physical devices rather than kinds of link
+            "wlan" | "wwan" => LinkKind::Physical,
+            _ => LinkKind::Virtual,
+        }
+    }
+
+    /// From the DEVTYPE line of a /sys/class/net/<if>/uevent file; tun, veth
+    /// and dummy links don't set one
+    pub fn from_uevent(uevent: &str) -> Option<Self> {
+        uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("DEVTYPE="))
+            .map(|devtype| Self::from_kind(devtype.trim()))
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkKind::Physical => "Physical",
+            LinkKind::Bridge => "Bridge",
+            LinkKind::Bond => "Bond",
+            LinkKind::Vlan => "VLAN",
+            LinkKind::Tun => "TUN/TAP",
+            LinkKind::WireGuard => "WireGuard",
+            LinkKind::Veth => "veth pair",
+            LinkKind::Dummy => "Dummy",
+            LinkKind::Gadget => "USB gadget",
+            LinkKind::Virtual => "Virtual",
+        }
+    }
+
+    /// The list icon; physical links get theirs from being wireless or not
+    pub fn icon(self, wireless: bool) -> &'static str {
+        match self {
+            LinkKind::Physical if wireless => icons::WIFI,
+            LinkKind::Physical => icons::ETHERNET,
+            LinkKind::Bridge => icons::BRIDGE,
+            LinkKind::Bond => icons::BOND,
+            LinkKind::Vlan => icons::VLAN,
+            LinkKind::Tun => icons::TUNNEL,
+            LinkKind::WireGuard => icons::WIREGUARD,
+            LinkKind::Veth => icons::VETH,
+            LinkKind::Dummy => icons::DUMMY,
+            LinkKind::Gadget => icons::USB,
+            LinkKind::Virtual => icons::VIRTUAL,
+        }
+    }
+}
+
+/// The kind sysfs tells: DEVTYPE from uevent, else whether a device backs it.
+/// Netlink's IFLA_INFO_KIND, when there is one, is more precise.
+pub fn probe(name: &str) -> LinkKind {
+    let dir = Path::new("/sys/class/net").join(name);
+    if let Some(kind) = std::fs::read_to_string(dir.join("uevent"))
+        .ok()
+        .and_then(|uevent| LinkKind::from_uevent(&uevent))
+    {
+        return kind;
+    }
+    if dir.join("device").exists() {
+        LinkKind::Physical
+    } else if dir.join("tun_flags").exists() {
+        LinkKind::Tun
+    } else {
+        LinkKind::Virtual
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_kinds_from_uevent_and_info_kind() {
+        assert_eq!(
+            LinkKind::from_uevent("DEVTYPE=bridge\nINTERFACE=br0\nIFINDEX=4\n"),
+            Some(LinkKind::Bridge)
+        );
+        assert_eq!(
+            LinkKind::from_uevent("DEVTYPE=wlan\nINTERFACE=wlan0\nIFINDEX=3\n"),
+            Some(LinkKind::Physical)
+        );
+        assert_eq!(LinkKind::from_uevent("INTERFACE=veth1\nIFINDEX=9\n"), None);
+
+        assert_eq!(LinkKind::from_kind("wireguard"), LinkKind::WireGuard);
+        assert_eq!(LinkKind::from_kind("tun"), LinkKind::Tun);
+        assert_eq!(LinkKind::from_kind("veth"), LinkKind::Veth);
+        assert_eq!(LinkKind::from_kind("vxlan"), LinkKind::Virtual);
+        assert_eq!(
+            LinkKind::from_uevent("DEVTYPE=gadget\nINTERFACE=usb0\n"),
+            Some(LinkKind::Gadget)
+        );
+        assert_eq!(LinkKind::Physical.icon(true), icons::WIFI);
+    }
+}
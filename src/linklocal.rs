@@ -0,0 +1,126 @@
+// src/linklocal.rs - IPv4 link-local (169.254/16) fallback for interfaces DHCP fails on
+use crate::command::TimedOutput;
+use crate::provenance;
+use crate::sanitize;
+use crate::secure_file;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::net::Ipv4Addr;
+use tokio::process::Command;
+
+const DROP_IN: &str = "60-lantern-link-local.conf";
+
+/// Whether an "address/prefix" is in 169.254.0.0/16
+pub fn is_link_local(address: &str) -> bool {
+    address
+        .split('/')
+        .next()
+        .and_then(|address| address.parse::<Ipv4Addr>().ok())
+        .is_some_and(|address| address.is_link_local())
+}
+
+/// The interface got nothing but a link-local address, which is what a
+/// failed DHCP leaves behind when the fallback is on
+pub fn only_link_local(addresses: &[String]) -> bool {
+    !addresses.is_empty() && addresses.iter().all(|address| is_link_local(address))
+}
+
+/// LinkLocalAddressing= from the [Network] section of a .network file or
+/// drop-in; IPv4 is on for "yes" and "ipv4"
+pub fn parse(content: &str) -> bool {
+    let mut in_network = false;
+    let mut ipv4 = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_network = line == "[Network]";
+            continue;
+        }
+        if let Some(value) = line
+            .strip_prefix("LinkLocalAddressing=")
+            .filter(|_| in_network)
+        {
+            ipv4 = matches!(value.trim(), "yes" | "ipv4");
+        }
+    }
+    ipv4
+}
+
+/// Both families, since setting it at all replaces networkd's IPv6-only
+/// default. networkd (252 and later) drops the 169.254 address again as soon
+/// as DHCP or a static address gives the interface a real one.
+fn drop_in() -> String {
+    "[Network]\nLinkLocalAddressing=yes\n".to_string()
+}
+
+/// Whether lantern's drop-in turns the fallback on for the interface
+pub async fn fallback(interface: &str) -> bool {
+    let Some(path) = crate::networkd::drop_in_path(interface, DROP_IN).await else {
+        return false;
+    };
+    fs::read_to_string(path)
+        .map(|content| parse(&content))
+        .unwrap_or(false)
+}
+
+/// Write or remove the drop-in and reconfigure the interface, which restarts
+/// its DHCP client too. Fails when networkd doesn't manage the interface.
+pub async fn set_fallback(interface: &str, on: bool) -> Result<()> {
+    sanitize::interface_name(interface)?;
+    let path = crate::networkd::drop_in_path(interface, DROP_IN)
+        .await
+        .ok_or_else(|| anyhow!("networkd doesn't manage {}", interface))?;
+    if on {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let operation = format!("IPv4 link-local fallback for {}", interface);
+        secure_file::write_public(&path, provenance::stamp(&drop_in(), &operation))?;
+    } else if path.exists() {
+        fs::remove_file(&path)?;
+    }
+    crate::systemd::reload_networkd().await?;
+    retry_dhcp(interface).await
+}
+
+/// Start DHCP over: `renew` only works with a lease to renew, so the link
+/// is reconfigured instead, which also picks up edited files
+pub async fn retry_dhcp(interface: &str) -> Result<()> {
+    sanitize::interface_name(interface)?;
+    let output = Command::new("/usr/bin/networkctl")
+        .args(["reconfigure", interface])
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "networkctl reconfigure {} failed: {}",
+            interface,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_link_local_fallback_apart() {
+        assert!(is_link_local("169.254.17.3/16"));
+        assert!(!is_link_local("192.168.1.50/24"));
+        assert!(!is_link_local("fe80::1/64"));
+        assert!(only_link_local(&["169.254.17.3/16".to_string()]));
+        assert!(!only_link_local(&[
+            "169.254.17.3/16".to_string(),
+            "10.0.0.23/24".to_string()
+        ]));
+        assert!(!only_link_local(&[]));
+
+        assert!(parse(&drop_in()));
+        assert!(parse(
+            "[Network]\nLinkLocalAddressing=ipv6\nLinkLocalAddressing=ipv4\n"
+        ));
+        assert!(!parse("[Network]\nLinkLocalAddressing=ipv6\n"));
+        assert!(!parse("[Link]\nLinkLocalAddressing=yes\n"));
+    }
+}
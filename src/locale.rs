@@ -0,0 +1,235 @@
+// src/locale.rs - Times, dates, durations and numbers the way the user's locale (LC_TIME, LC_NUMERIC) writes them
+use chrono::{DateTime, Datelike, TimeZone, Timelike};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOrder {
+    /// 2026-10-17, also what C and POSIX get
+    Ymd,
+    /// 17.10.2026
+    Dmy,
+    /// 10/17/2026
+    Mdy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub twelve_hour: bool,
+    pub date_order: DateOrder,
+    pub date_separator: char,
+    pub decimal: char,
+    /// Between groups of thousands; None leaves long numbers alone
+    pub grouping: Option<char>,
+}
+
+impl Default for Locale {
+    /// C/POSIX: ISO dates, 24-hour clock, no grouping
+    fn default() -> Self {
+        Locale {
+            twelve_hour: false,
+            date_order: DateOrder::Ymd,
+            date_separator: '-',
+            decimal: '.',
+            grouping: None,
+        }
+    }
+}
+
+/// "de_DE.UTF-8@euro" -> ("de", "DE"); None for C, POSIX and unset
+fn language_territory(value: &str) -> Option<(String, String)> {
+    let name = value.split(['.', '@']).next()?;
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        return None;
+    }
+    let (language, territory) = name.split_once('_').unwrap_or((name, ""));
+    Some((language.to_lowercase(), territory.to_uppercase()))
+}
+
+// Where the 12-hour clock is the everyday one
+const TWELVE_HOUR: [&str; 8] = ["US", "CA", "AU", "NZ", "PH", "IN", "PK", "EG"];
+const YEAR_FIRST: [&str; 6] = ["zh", "ja", "ko", "hu", "lt", "sv"];
+const DOTTED_DATES: [&str; 16] = [
+    "de", "cs", "sk", "pl", "ru", "fi", "nb", "nn", "no", "da", "tr", "uk", "ro", "hr", "sl", "bg",
+];
+const DECIMAL_COMMA: [&str; 24] = [
+    "de", "cs", "sk", "pl", "ru", "fr", "es", "it", "pt", "nl", "fi", "sv", "nb", "nn", "no", "da",
+    "tr", "uk", "ro", "hr", "sl", "bg", "hu", "el",
+];
+// Of those, the ones grouping with dots rather than spaces
+const DOT_GROUPING: [&str; 12] = [
+    "de", "es", "it", "pt", "nl", "da", "tr", "ro", "hr", "sl", "el", "id",
+];
+
+impl Locale {
+    /// From the values of LC_TIME and LC_NUMERIC
+    pub fn parse(time: &str, numeric: &str) -> Self {
+        let mut locale = Locale::default();
+        if let Some((language, territory)) = language_territory(time) {
+            locale.twelve_hour = TWELVE_HOUR.contains(&territory.as_str())
+                && !(territory == "CA" && language == "fr");
+            (locale.date_order, locale.date_separator) = match (&*language, &*territory) {
+                (_, "US" | "PH") => (DateOrder::Mdy, '/'),
+                ("en", "CA") => (DateOrder::Ymd, '-'),
+                (language, _) if YEAR_FIRST.contains(&language) => (DateOrder::Ymd, '-'),
+                (language, _) if DOTTED_DATES.contains(&language) => (DateOrder::Dmy, '.'),
+                ("nl", _) => (DateOrder::Dmy, '-'),
+                _ => (DateOrder::Dmy, '/'),
+            };
+        }
+        if let Some((language, territory)) = language_territory(numeric) {
+            (locale.decimal, locale.grouping) = match (&*language, &*territory) {
+                (_, "CH") => ('.', Some('\'')),
+                (language, _) if DOT_GROUPING.contains(&language) => (',', Some('.')),
+                (language, _) if DECIMAL_COMMA.contains(&language) => (',', Some('\u{a0}')),
+                _ => ('.', Some(',')),
+            };
+        }
+        locale
+    }
+
+    /// The environment's, looked up once: LC_ALL wins over LC_TIME and
+    /// LC_NUMERIC, which win over LANG
+    pub fn system() -> Self {
+        static SYSTEM: OnceLock<Locale> = OnceLock::new();
+        *SYSTEM.get_or_init(|| {
+            let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+            let category = |name: &str| {
+                var("LC_ALL")
+                    .or_else(|| var(name))
+                    .or_else(|| var("LANG"))
+                    .unwrap_or_default()
+            };
+            Locale::parse(&category("LC_TIME"), &category("LC_NUMERIC"))
+        })
+    }
+
+    fn clock<Tz: TimeZone>(&self, at: &DateTime<Tz>, seconds: bool) -> String {
+        let seconds = match seconds {
+            true => format!(":{:02}", at.second()),
+            false => String::new(),
+        };
+        if self.twelve_hour {
+            let (pm, hour) = at.hour12();
+            let suffix = if pm { "PM" } else { "AM" };
+            format!("{}:{:02}{} {}", hour, at.minute(), seconds, suffix)
+        } else {
+            format!("{:02}:{:02}{}", at.hour(), at.minute(), seconds)
+        }
+    }
+
+    /// Time of day with seconds, as in the event timeline: "14:05:09", "2:05:09 PM"
+    pub fn time<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> String {
+        self.clock(at, true)
+    }
+
+    pub fn date<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> String {
+        let (year, month, day) = (at.year(), at.month(), at.day());
+        let separator = self.date_separator;
+        match self.date_order {
+            DateOrder::Ymd => format!("{year}{separator}{month:02}{separator}{day:02}"),
+            DateOrder::Dmy => format!("{day:02}{separator}{month:02}{separator}{year}"),
+            DateOrder::Mdy => format!("{month:02}{separator}{day:02}{separator}{year}"),
+        }
+    }
+
+    /// Date and time to the minute
+    pub fn date_time<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> String {
+        format!("{} {}", self.date(at), self.clock(at, false))
+    }
+
+    /// Day and month only, for chart axes: "17.10. 14:05", "10/17 2:05 PM"
+    pub fn short_date_time<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> String {
+        let separator = self.date_separator;
+        let date = match self.date_order {
+            DateOrder::Ymd => format!("{:02}{separator}{:02}", at.month(), at.day()),
+            DateOrder::Dmy if separator == '.' => format!("{:02}.{:02}.", at.day(), at.month()),
+            DateOrder::Dmy => format!("{:02}{separator}{:02}", at.day(), at.month()),
+            DateOrder::Mdy => format!("{:02}{separator}{:02}", at.month(), at.day()),
+        };
+        format!("{} {}", date, self.clock(at, false))
+    }
+
+    /// A whole number with its thousands grouped: "1,234,567", "1.234.567"
+    pub fn count(&self, value: u64) -> String {
+        let digits = value.to_string();
+        let Some(separator) = self.grouping else {
+            return digits;
+        };
+        let mut grouped = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    /// A fraction to `places` decimals with the locale's separator
+    pub fn decimal(&self, value: f64, places: usize) -> String {
+        let formatted = format!("{:.*}", places, value);
+        match self.decimal {
+            '.' => formatted,
+            decimal => formatted.replacen('.', &decimal.to_string(), 1),
+        }
+    }
+}
+
+/// How long, to the two largest units: "45 s", "12 min", "2 h 05 min", "3 d 4 h"
+pub fn duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60);
+    match seconds {
+        0..60 => format!("{} s", seconds),
+        60..3600 => format!("{} min", seconds / 60),
+        3600..86_400 => format!("{} h {:02} min", hours, minutes),
+        _ => format!("{} d {} h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn formats_like_the_locale() {
+        let at = NaiveDate::from_ymd_opt(2026, 10, 17)
+            .unwrap()
+            .and_hms_opt(14, 5, 9)
+            .unwrap()
+            .and_utc();
+
+        let c = Locale::parse("C.UTF-8", "");
+        assert_eq!(c.time(&at), "14:05:09");
+        assert_eq!(c.date_time(&at), "2026-10-17 14:05");
+        assert_eq!(c.count(1_234_567), "1234567");
+
+        let us = Locale::parse("en_US.UTF-8", "en_US.UTF-8");
+        assert_eq!(us.time(&at), "2:05:09 PM");
+        assert_eq!(us.date_time(&at), "10/17/2026 2:05 PM");
+        assert_eq!(us.count(1_234_567), "1,234,567");
+        assert_eq!(us.decimal(12.345, 2), "12.35");
+
+        let czech = Locale::parse("cs_CZ.UTF-8", "de_DE.UTF-8");
+        assert_eq!(czech.time(&at), "14:05:09");
+        assert_eq!(czech.date(&at), "17.10.2026");
+        assert_eq!(czech.short_date_time(&at), "17.10. 14:05");
+        assert_eq!(czech.count(1_234_567), "1.234.567");
+        assert_eq!(czech.decimal(12.34, 1), "12,3");
+        assert_eq!(Locale::parse("", "fr_FR").count(12_345), "12\u{a0}345");
+        assert_eq!(Locale::parse("en_GB.UTF-8", "").date(&at), "17/10/2026");
+
+        assert_eq!(duration(Duration::from_secs(45)), "45 s");
+        assert_eq!(duration(Duration::from_secs(12 * 60 + 30)), "12 min");
+        assert_eq!(
+            duration(Duration::from_secs(2 * 3600 + 5 * 60)),
+            "2 h 05 min"
+        );
+        assert_eq!(
+            duration(Duration::from_secs(3 * 86_400 + 4 * 3600)),
+            "3 d 4 h"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+// src/lock.rs - Advisory lock serializing changes to system network configuration
+use nix::errno::Errno;
+use nix::fcntl::{Flock, FlockArg};
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+const LOCK_PATH: &str = "/run/lantern.lock";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("Another lantern instance is applying changes, try again in a moment")]
+    Busy,
+
+    #[error("Failed to acquire {path}: {details}")]
+    Io { path: String, details: String },
+}
+
+/// Held for the duration of an operation that writes systemd/wpa_supplicant
+/// files or changes interface state. Scripts can take the same lock with
+/// `flock /run/lantern.lock <command>`. Released on drop.
+pub struct OperationLock {
+    _lock: Flock<File>,
+}
+
+impl OperationLock {
+    /// Take the lock without waiting, so the UI never blocks on another instance.
+    pub fn try_acquire() -> Result<Self, LockError> {
+        let path = crate::config::sandbox_dir()
+            .map(|dir| dir.join("lantern.lock"))
+            .unwrap_or_else(|| PathBuf::from(LOCK_PATH));
+        Self::acquire_at(&path)
+    }
+
+    /// `try_acquire` against a lock file of the caller's choosing
+    pub fn acquire_at(path: &Path) -> Result<Self, LockError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o644)
+            .open(path)
+            .map_err(|e| LockError::Io {
+                path: path.display().to_string(),
+                details: e.to_string(),
+            })?;
+
+        match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+            Ok(lock) => Ok(Self { _lock: lock }),
+            Err((_, Errno::EWOULDBLOCK)) => Err(LockError::Busy),
+            Err((_, errno)) => Err(LockError::Io {
+                path: path.display().to_string(),
+                details: errno.desc().to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_holder_is_busy() {
+        let path = std::env::temp_dir().join(format!("lantern-lock-{}", std::process::id()));
+        let held = OperationLock::acquire_at(&path).expect("first holder gets the lock");
+        assert!(matches!(
+            OperationLock::acquire_at(&path),
+            Err(LockError::Busy)
+        ));
+        drop(held);
+        assert!(OperationLock::acquire_at(&path).is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+}
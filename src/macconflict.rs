@@ -0,0 +1,195 @@
+// src/macconflict.rs - MAC conflicts on the segment: one MAC behind several IPv4 addresses, or this machine's MAC on another host
+use crate::network::Interface;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// One usable entry of the kernel's ARP/NDP table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighbor {
+    pub interface: String,
+    pub address: String,
+    pub mac: String, // Lowercase, colon-separated
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Conflict {
+    /// Several IPv4 addresses answer from one MAC: a cloned MAC, or a
+    /// router doing proxy ARP
+    SharedMac {
+        interface: String,
+        mac: String,
+        addresses: Vec<String>,
+    },
+    /// A neighbor answers with the MAC of one of this machine's interfaces
+    OwnMac {
+        interface: String,
+        mac: String,
+        address: String,
+        own: String,
+    },
+}
+
+impl Conflict {
+    pub fn interface(&self) -> &str {
+        match self {
+            Conflict::SharedMac { interface, .. } | Conflict::OwnMac { interface, .. } => interface,
+        }
+    }
+
+    /// What's wrong, for the interface's details
+    pub fn describe(&self) -> String {
+        match self {
+            Conflict::SharedMac { mac, addresses, .. } => format!(
+                "{} answers for {} (cloned MAC or proxy ARP?)",
+                mac,
+                addresses.join(", ")
+            ),
+            Conflict::OwnMac {
+                mac, address, own, ..
+            } => format!(
+                "{} uses {}'s MAC {}: a clone of this machine is on the segment",
+                address, own, mac
+            ),
+        }
+    }
+
+    /// For the timeline and status line
+    pub fn message(&self) -> String {
+        format!("{}: {}", self.interface(), self.describe())
+    }
+}
+
+/// Entries of `ip -j neigh show` that resolved to a unicast MAC; FAILED and
+/// INCOMPLETE ones have no lladdr
+pub fn parse_neighbors(ip_json: &str) -> Vec<Neighbor> {
+    let Ok(Value::Array(entries)) = serde_json::from_str::<Value>(ip_json) else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter(|entry| {
+            !entry["state"].as_array().is_some_and(|states| {
+                states
+                    .iter()
+                    .any(|state| state == "FAILED" || state == "NOARP")
+            })
+        })
+        .filter_map(|entry| {
+            let mac = entry["lladdr"].as_str()?.to_lowercase();
+            Some(Neighbor {
+                interface: entry["dev"].as_str()?.to_string(),
+                address: entry["dst"].as_str()?.to_string(),
+                mac,
+            })
+        })
+        .filter(|neighbor| unicast(&neighbor.mac))
+        .collect()
+}
+
+/// Not all zeros and without the multicast bit in the first octet
+fn unicast(mac: &str) -> bool {
+    let first = mac
+        .split(':')
+        .next()
+        .and_then(|octet| u8::from_str_radix(octet, 16).ok());
+    first.is_some_and(|octet| octet & 1 == 0) && mac != "00:00:00:00:00:00"
+}
+
+/// Conflicts in the neighbor table, against this machine's own interfaces.
+/// IPv6 hosts have several addresses per MAC by design, so only IPv4 counts
+/// for SharedMac.
+pub fn find(neighbors: &[Neighbor], interfaces: &[Interface]) -> Vec<Conflict> {
+    let own: HashMap<String, &str> = interfaces
+        .iter()
+        .filter(|interface| unicast(&interface.mac_address))
+        .map(|interface| {
+            (
+                interface.mac_address.to_lowercase(),
+                interface.name.as_str(),
+            )
+        })
+        .collect();
+    let mut conflicts = Vec::new();
+    let mut by_mac: BTreeMap<(&str, &str), Vec<String>> = BTreeMap::new();
+    for neighbor in neighbors {
+        if let Some(own) = own.get(&neighbor.mac) {
+            conflicts.push(Conflict::OwnMac {
+                interface: neighbor.interface.clone(),
+                mac: neighbor.mac.clone(),
+                address: neighbor.address.clone(),
+                own: own.to_string(),
+            });
+        } else if !neighbor.address.contains(':') {
+            by_mac
+                .entry((&neighbor.interface, &neighbor.mac))
+                .or_default()
+                .push(neighbor.address.clone());
+        }
+    }
+    for ((interface, mac), mut addresses) in by_mac {
+        if addresses.len() > 1 {
+            addresses.sort();
+            conflicts.push(Conflict::SharedMac {
+                interface: interface.to_string(),
+                mac: mac.to_string(),
+                addresses,
+            });
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_shared_and_cloned_macs() {
+        let neighbors = parse_neighbors(
+            r#"[
+            {"dst":"192.168.1.1","dev":"eth0","lladdr":"00:11:22:33:44:55","state":["REACHABLE"]},
+            {"dst":"192.168.1.7","dev":"eth0","lladdr":"AA:BB:CC:00:00:07","state":["STALE"]},
+            {"dst":"192.168.1.9","dev":"eth0","lladdr":"aa:bb:cc:00:00:07","state":["DELAY"]},
+            {"dst":"192.168.1.20","dev":"eth0","lladdr":"02:00:00:00:00:01","state":["REACHABLE"]},
+            {"dst":"192.168.1.30","dev":"eth0","state":["FAILED"]},
+            {"dst":"fe80::1","dev":"eth0","lladdr":"00:11:22:33:44:55","router":null,"state":["STALE"]},
+            {"dst":"224.0.0.251","dev":"eth0","lladdr":"01:00:5e:00:00:fb","state":["NOARP"]}
+        ]"#,
+        );
+        assert_eq!(neighbors.len(), 5);
+
+        let mut wlan0: Interface = serde_json::from_value(serde_json::json!({
+            "name": "wlan0", "mac_address": "02:00:00:00:00:01", "state": "UP", "mtu": 1500,
+            "ipv4_addresses": [], "ipv6_addresses": [], "gateway": null, "dns_servers": [],
+            "stats": {"rx_bytes": 0, "tx_bytes": 0, "rx_packets": 0, "tx_packets": 0,
+                      "rx_errors": 0, "tx_errors": 0},
+        }))
+        .unwrap();
+        let conflicts = find(&neighbors, std::slice::from_ref(&wlan0));
+        assert_eq!(
+            conflicts,
+            vec![
+                Conflict::OwnMac {
+                    interface: "eth0".to_string(),
+                    mac: "02:00:00:00:00:01".to_string(),
+                    address: "192.168.1.20".to_string(),
+                    own: "wlan0".to_string(),
+                },
+                Conflict::SharedMac {
+                    interface: "eth0".to_string(),
+                    mac: "aa:bb:cc:00:00:07".to_string(),
+                    addresses: vec!["192.168.1.7".to_string(), "192.168.1.9".to_string()],
+                },
+            ]
+        );
+        assert!(conflicts[1].message().contains("192.168.1.7, 192.168.1.9"));
+
+        // The router's IPv4 and IPv6 addresses share a MAC, and that's fine
+        wlan0.mac_address = "02:00:00:00:00:02".to_string();
+        assert_eq!(find(&neighbors[..1], &[wlan0.clone()]), Vec::new());
+        assert_eq!(
+            find(&[neighbors[0].clone(), neighbors[4].clone()], &[wlan0]),
+            Vec::new()
+        );
+    }
+}
@@ -1,21 +1,22 @@
 // src/main.rs
-#![allow(clippy::enum_variant_names)] // UpdateMessage variants are descriptive
 #![allow(clippy::needless_borrows_for_generic_args)] // Command args are clearer with explicit borrows
 
-mod app;
-mod config;
-mod icons;
-mod iwd;
-mod network;
-mod systemd;
-mod ui;
-
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Arg, Command};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use lantern::{
+    app, command, config, console, daemon, editor, exit_code::ExitCode, gai, icons, lock,
+    modal::Modal, netlink, network, output, pending, perf::Measure, qr, queues, replay, routing,
+    sanitize, screenshot, systemd, tcp, ui, wifi_events,
 };
 use ratatui::{
     backend::{Backend, CrosstermBackend},
@@ -23,23 +24,29 @@ use ratatui::{
 };
 use std::{
     io::{self, Write},
-    time::Duration,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::mpsc;
 
-// Messages for non-blocking updates
-#[derive(Debug)]
-enum UpdateMessage {
-    StatsUpdate(Vec<network::Interface>),
-    InterfacesUpdate(Vec<network::Interface>),
-    WiFiInfoUpdate(Vec<network::Interface>),
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run().await {
+        eprintln!("{} {:#}", output::error(), err);
+        ExitCode::of(&err).exit();
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+async fn run() -> Result<()> {
     // Parse command line arguments
     let matches = Command::new("lantern")
         .version(env!("CARGO_PKG_VERSION"))
+        .disable_version_flag(true)
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
         .long_about("Lantern is a modern TUI for Linux network interface management.\n\nFeatures:\n• Network interface configuration (DHCP/static)\n• WiFi management with WPA/WPA2/WPA3/Enterprise support\n• WiFi hotspot creation\n• IPv6 configuration\n• WireGuard VPN management\n• Real-time network monitoring\n• systemd-networkd integration")
@@ -48,12 +55,377 @@ async fn main() -> Result<()> {
             .short('c')
             .help("Force CLI mode (no TUI)")
             .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("demo")
+            .long("demo")
+            .help("Run the TUI against a simulated network; needs no root and changes nothing")
+            .conflicts_with("cli")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("safe")
+            .long("safe")
+            .help("Start without auto-connect, background checks or config writes, for when lantern may be fighting another tool")
+            .conflicts_with_all(["cli", "demo"])
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("plain")
+            .long("plain")
+            .global(true)
+            .help("Plain CLI output: no Nerd Font glyphs or colors (the default when not on a terminal)")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("no-emoji")
+            .long("no-emoji")
+            .global(true)
+            .help("CLI output without Nerd Font glyphs, keeping colors")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("minimal")
+            .long("minimal")
+            .help("Reduced TUI for serial consoles: no alternate screen, ASCII only (automatic when TERM is vt100, vt220 or dumb)")
+            .conflicts_with("cli")
+            .action(clap::ArgAction::SetTrue))
         .arg(Arg::new("version")
             .long("version")
             .short('V')
             .help("Print version information")
             .action(clap::ArgAction::SetTrue))
-        .get_matches();
+        .subcommand(Command::new("wifi")
+            .about("WiFi commands")
+            .subcommand_required(true)
+            .subcommand(Command::new("join")
+                .about("Join a WiFi network from a WIFI: QR code payload")
+                .arg(Arg::new("qr")
+                    .long("qr")
+                    .value_name("IMAGE|STRING")
+                    .required(true)
+                    .help("QR image (decoded with zbarimg), WIFI: payload string, or - for stdin"))
+                .arg(Arg::new("interface")
+                    .long("interface")
+                    .short('i')
+                    .value_name("IFACE")
+                    .help("Wireless interface to use (default: first WiFi interface)")))
+            .subcommand(Command::new("survey")
+                .about("Scan repeatedly and record every access point heard, for site-survey heatmaps")
+                .arg(Arg::new("interface")
+                    .long("interface")
+                    .short('i')
+                    .value_name("IFACE")
+                    .help("Wireless interface to scan from (default: first WiFi interface)"))
+                .arg(Arg::new("interval")
+                    .long("interval")
+                    .value_name("SECONDS")
+                    .value_parser(clap::value_parser!(u64).range(1..))
+                    .default_value("10")
+                    .help("Time between the starts of scans"))
+                .arg(Arg::new("count")
+                    .long("count")
+                    .value_name("N")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("Stop after N scans (default: run until Ctrl+C)"))
+                .arg(Arg::new("output")
+                    .long("output")
+                    .short('o')
+                    .value_name("FILE")
+                    .help("File to write (default: stdout)"))
+                .arg(Arg::new("format")
+                    .long("format")
+                    .value_name("csv|json")
+                    .help("Output format (default: from the file extension, else csv)"))
+                .arg(Arg::new("gps")
+                    .long("gps")
+                    .help("Tag each scan with the position from gpsd (localhost:2947 unless [survey] gpsd says otherwise)")
+                    .action(clap::ArgAction::SetTrue))))
+        .subcommand(Command::new("wireguard")
+            .about("WireGuard commands")
+            .subcommand_required(true)
+            .subcommand(Command::new("rotate-key")
+                .about("Rotate an interface's private key in two steps, keeping the old key until confirmed")
+                .arg(Arg::new("interface")
+                    .value_name("IFACE")
+                    .required(true)
+                    .help("WireGuard interface managed by lantern"))
+                .arg(Arg::new("confirm")
+                    .long("confirm")
+                    .help("Switch to the staged key once the remote peers have the new public key")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with("abort"))
+                .arg(Arg::new("abort")
+                    .long("abort")
+                    .help("Discard the staged key and keep the current one")
+                    .action(clap::ArgAction::SetTrue)))
+            .subcommand(Command::new("auto-connect")
+                .about("Set when lantern brings a tunnel up or down on its own")
+                .arg(Arg::new("interface")
+                    .value_name("IFACE")
+                    .required(true)
+                    .help("WireGuard interface managed by lantern"))
+                .arg(Arg::new("policy")
+                    .value_name("POLICY")
+                    .required(true)
+                    .help("never, always, untrusted-wifi or profile:<NAME>")))
+            .subcommand(Command::new("psk")
+                .about("Attach a preshared key to a peer, generating one unless given on stdin")
+                .arg(Arg::new("interface")
+                    .value_name("IFACE")
+                    .required(true)
+                    .help("WireGuard interface managed by lantern"))
+                .arg(Arg::new("peer")
+                    .value_name("PEER_PUBLIC_KEY")
+                    .required(true)
+                    .help("Public key of the peer to protect"))
+                .arg(Arg::new("stdin")
+                    .long("stdin")
+                    .help("Read an existing preshared key from stdin instead of generating one")
+                    .action(clap::ArgAction::SetTrue))))
+        .subcommand(Command::new("show")
+            .about("Print interfaces as JSON, for scripts")
+            .arg(Arg::new("interface")
+                .value_name("IFACE")
+                .help("Only this interface")))
+        .subcommand(Command::new("routes")
+            .about("Print the routes through an interface as JSON")
+            .arg(Arg::new("interface")
+                .value_name("IFACE")
+                .required(true)))
+        .subcommand(Command::new("address")
+            .about("Add or remove an address until the next reboot (use the TUI to persist)")
+            .subcommand_required(true)
+            .subcommand(Command::new("add")
+                .arg(Arg::new("interface").value_name("IFACE").required(true))
+                .arg(Arg::new("address").value_name("ADDRESS/PREFIX").required(true))
+                .arg(temporary_arg()))
+            .subcommand(Command::new("remove")
+                .arg(Arg::new("interface").value_name("IFACE").required(true))
+                .arg(Arg::new("address").value_name("ADDRESS/PREFIX").required(true))
+                .arg(temporary_arg())))
+        .subcommand(Command::new("pending")
+            .about("Temporary changes that revert on their own unless confirmed")
+            .subcommand_required(true)
+            .subcommand(Command::new("list")
+                .about("List the changes waiting to be confirmed"))
+            .subcommand(Command::new("confirm")
+                .about("Keep a change, or all of them")
+                .arg(Arg::new("id").value_name("ID").help("Change to keep (default: all)")))
+            .subcommand(Command::new("revert")
+                .about("Undo a change now, or all of them")
+                .arg(Arg::new("id").value_name("ID").help("Change to undo (default: all)"))))
+        .subcommand(Command::new("dummy")
+            .about("Dummy interfaces for service addresses, persisted as networkd files")
+            .subcommand_required(true)
+            .subcommand(Command::new("create")
+                .about("Create a dummy interface, or replace its addresses")
+                .arg(Arg::new("name")
+                    .value_name("NAME")
+                    .required(true)
+                    .help("Interface name, e.g. vip0"))
+                .arg(Arg::new("addresses")
+                    .value_name("ADDRESS")
+                    .num_args(0..)
+                    .help("Addresses to assign; a bare address means /32 or /128")))
+            .subcommand(Command::new("remove")
+                .about("Delete a dummy interface created by lantern")
+                .arg(Arg::new("name")
+                    .value_name("NAME")
+                    .required(true))))
+        .subcommand(Command::new("loopback")
+            .about("Additional addresses on lo, e.g. anycast addresses announced over BGP")
+            .subcommand_required(true)
+            .subcommand(Command::new("list")
+                .about("List the addresses lantern added to lo"))
+            .subcommand(Command::new("add")
+                .about("Add an address to lo")
+                .arg(Arg::new("address")
+                    .value_name("ADDRESS")
+                    .required(true)))
+            .subcommand(Command::new("remove")
+                .about("Remove an address lantern added to lo")
+                .arg(Arg::new("address")
+                    .value_name("ADDRESS")
+                    .required(true))))
+        .subcommand(Command::new("tune")
+            .about("Show or change ring sizes, RSS spread, RPS/XPS CPU masks and offloads of a NIC")
+            .arg(Arg::new("interface")
+                .value_name("IFACE")
+                .required(true)
+                .help("Network interface to tune"))
+            .arg(Arg::new("rx-ring")
+                .long("rx-ring")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .help("RX ring buffer size (ethtool -G rx)"))
+            .arg(Arg::new("tx-ring")
+                .long("tx-ring")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .help("TX ring buffer size (ethtool -G tx)"))
+            .arg(Arg::new("rss-queues")
+                .long("rss-queues")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .help("Spread the RSS indirection table evenly over N queues (ethtool -X equal)"))
+            .arg(Arg::new("rps-cpus")
+                .long("rps-cpus")
+                .value_name("MASK")
+                .help("Hex CPU mask for receive packet steering on every RX queue"))
+            .arg(Arg::new("xps-cpus")
+                .long("xps-cpus")
+                .value_name("MASK")
+                .help("Hex CPU mask for transmit packet steering on every TX queue"))
+            .arg(Arg::new("offload")
+                .long("offload")
+                .value_name("NAME=on|off")
+                .action(clap::ArgAction::Append)
+                .help("Toggle an offload (ethtool -K), e.g. generic-receive-offload=off; repeatable"))
+            .arg(Arg::new("persist")
+                .long("persist")
+                .help("Also write a udev rule so the settings return at boot and on replug")
+                .action(clap::ArgAction::SetTrue))
+            .arg(Arg::new("forget")
+                .long("forget")
+                .help("Remove the persisted settings for this interface")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("persist")))
+        .subcommand(Command::new("replay")
+            .about("Send a pcap capture, or generated test frames, out an interface at a set rate")
+            .arg(Arg::new("interface")
+                .value_name("IFACE")
+                .required(true)
+                .help("Interface to send from"))
+            .arg(Arg::new("pcap")
+                .value_name("PCAP")
+                .help("Classic pcap file of Ethernet frames (default: generate test frames)"))
+            .arg(Arg::new("pps")
+                .long("pps")
+                .value_name("N")
+                .value_parser(clap::value_parser!(f64))
+                .conflicts_with("mbps")
+                .help("Frames per second (default for test frames: 100; a capture keeps its own timing)"))
+            .arg(Arg::new("mbps")
+                .long("mbps")
+                .value_name("N")
+                .value_parser(clap::value_parser!(f64))
+                .help("Megabits of frame data per second"))
+            .arg(Arg::new("loop")
+                .long("loop")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .requires("pcap")
+                .help("Send the capture N times (default: 1)"))
+            .arg(Arg::new("count")
+                .long("count")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("pcap")
+                .help("Number of test frames (default: 100)"))
+            .arg(Arg::new("size")
+                .long("size")
+                .value_name("BYTES")
+                .value_parser(clap::value_parser!(u16).range(64..))
+                .conflicts_with("pcap")
+                .help("Test frame size on the wire, FCS included (default: 64)"))
+            .arg(Arg::new("vlan")
+                .long("vlan")
+                .value_name("ID")
+                .value_parser(clap::value_parser!(u16).range(1..=4094))
+                .conflicts_with("pcap")
+                .help("Tag the test frames with this 802.1Q VLAN ID"))
+            .arg(Arg::new("dst")
+                .long("dst")
+                .value_name("MAC")
+                .conflicts_with("pcap")
+                .help("Destination of the test frames (default: broadcast)")))
+        .subcommand(Command::new("speedtest")
+            .about("Test download and upload speed and add the result to the history")
+            .arg(Arg::new("history")
+                .long("history")
+                .help("Print the recorded tests as CSV instead")
+                .action(clap::ArgAction::SetTrue)))
+        .subcommand(Command::new("perf")
+            .about("Measure TCP or UDP throughput and jitter between two machines running lantern")
+            .subcommand_required(true)
+            .subcommand(Command::new("serve")
+                .about("Wait for tests from lantern perf connect, one at a time, until Ctrl+C")
+                .arg(Arg::new("bind")
+                    .long("bind")
+                    .value_name("ADDRESS")
+                    .value_parser(clap::value_parser!(std::net::IpAddr))
+                    .help("Address to listen on (default: all)"))
+                .arg(perf_port_arg()))
+            .subcommand(Command::new("connect")
+                .about("Run a test against lantern perf serve on HOST")
+                .arg(Arg::new("host")
+                    .value_name("HOST")
+                    .required(true))
+                .arg(perf_port_arg())
+                .arg(Arg::new("udp")
+                    .long("udp")
+                    .short('u')
+                    .help("Test UDP at a set bitrate and report jitter and loss, instead of TCP")
+                    .action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("time")
+                    .long("time")
+                    .short('t')
+                    .value_name("SECONDS")
+                    .value_parser(clap::value_parser!(u64).range(1..=600))
+                    .default_value("10")
+                    .help("How long to send"))
+                .arg(Arg::new("bitrate")
+                    .long("bitrate")
+                    .short('b')
+                    .value_name("MBIT")
+                    .value_parser(clap::value_parser!(f64))
+                    .requires("udp")
+                    .help("UDP sending rate in Mbit/s (default: 10)"))
+                .arg(Arg::new("reverse")
+                    .long("reverse")
+                    .short('R')
+                    .help("The server sends and this machine receives")
+                    .action(clap::ArgAction::SetTrue))))
+        .subcommand(Command::new("alerts")
+            .about("Alert channels for lantern daemon")
+            .subcommand_required(true)
+            .subcommand(Command::new("test")
+                .about("Send a test alert through every channel in config.toml")))
+        .subcommand(Command::new("daemon")
+            .about("Run auto-connect, trusted-network policies and hotplug profiles without the TUI (for systemd)"))
+        .subcommand(Command::new("checkpoint")
+            .about("Snapshot networkd's config through the daemon; it rolls back unless confirmed in time")
+            .subcommand_required(true)
+            .subcommand(Command::new("create")
+                .about("Open a checkpoint before making changes")
+                .arg(Arg::new("timeout")
+                    .long("timeout")
+                    .value_name("SECS")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("Roll back after this long unless confirmed (default: 60)")))
+            .subcommand(Command::new("confirm")
+                .about("Keep the changes made since the checkpoint")
+                .arg(Arg::new("id").value_name("ID").required(true)))
+            .subcommand(Command::new("rollback")
+                .about("Undo the changes made since the checkpoint now")
+                .arg(Arg::new("id").value_name("ID").required(true)))
+            .subcommand(Command::new("adjust")
+                .about("Restart the countdown, to keep a checkpoint open while working")
+                .arg(Arg::new("id").value_name("ID").required(true))
+                .arg(Arg::new("timeout").value_name("SECS").required(true)))
+            .subcommand(Command::new("list")
+                .about("Show the open checkpoint")))
+        .subcommand(Command::new("install-service")
+            .about("Install lantern.service and lantern.socket units for the daemon")
+            .arg(Arg::new("dir")
+                .long("dir")
+                .value_name("DIR")
+                .default_value(daemon::UNIT_DIR)
+                .help("Where to write the unit files"))
+            .arg(Arg::new("enable")
+                .long("enable")
+                .help("Also enable and start them")
+                .action(clap::ArgAction::SetTrue)))
+        .try_get_matches()
+        .unwrap_or_else(|e| {
+            // clap would exit 2 on bad arguments, which is ours for permissions
+            let code = if e.use_stderr() { ExitCode::Usage } else { ExitCode::Ok };
+            let _ = e.print();
+            code.exit()
+        });
+    output::configure(matches.get_flag("plain"), matches.get_flag("no-emoji"));
+    console::configure(console::Profile::detect(matches.get_flag("minimal")));
 
     // Handle version flag
     if matches.get_flag("version") {
@@ -66,11 +438,27 @@ async fn main() -> Result<()> {
     // Force CLI mode if requested
     let force_cli = matches.get_flag("cli");
 
+    let safe = matches.get_flag("safe");
+    if safe && matches.subcommand().is_some() {
+        anyhow::bail!("--safe only applies to the TUI");
+    }
+
+    // Demo mode keeps its config, scan cache and lock in a scratch directory
+    let demo = matches.get_flag("demo");
+    if demo {
+        if matches.subcommand().is_some() {
+            anyhow::bail!("--demo only applies to the TUI; subcommands act on the real system");
+        }
+        let dir = std::env::temp_dir().join(format!("lantern-demo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        config::sandbox(dir);
+    }
+
     // Check if running as root
-    if !nix::unistd::Uid::effective().is_root() {
+    if !demo && !nix::unistd::Uid::effective().is_root() {
         eprintln!(
-            "{}  Lantern requires root privileges for network configuration",
-            crate::icons::WARNING
+            "{} Lantern requires root privileges for network configuration",
+            output::warning()
         );
         eprintln!("   Please run with: sudo lantern");
         eprintln!("   This is required for:");
@@ -78,589 +466,2327 @@ async fn main() -> Result<()> {
         eprintln!("   • WiFi configuration");
         eprintln!("   • VPN/WireGuard setup");
         eprintln!("   • systemd-networkd configuration");
-        std::process::exit(1);
+        ExitCode::Permission.exit();
+    }
+
+    // Apply command timeouts before anything shells out
+    command::configure(&config::Config::load().unwrap_or_default().timeouts);
+
+    // Handle subcommands (non-interactive)
+    if let Some(("wifi", wifi_matches)) = matches.subcommand() {
+        if let Some(("join", join_matches)) = wifi_matches.subcommand() {
+            let qr_source = join_matches
+                .get_one::<String>("qr")
+                .map(String::as_str)
+                .unwrap_or("-");
+            let interface = join_matches.get_one::<String>("interface").cloned();
+            return run_wifi_join(qr_source, interface).await;
+        }
+        if let Some(("survey", survey_matches)) = wifi_matches.subcommand() {
+            return run_wifi_survey(survey_matches).await;
+        }
+    }
+    if let Some(("wireguard", wg_matches)) = matches.subcommand() {
+        if let Some(("rotate-key", rotate_matches)) = wg_matches.subcommand() {
+            let interface = rotate_matches
+                .get_one::<String>("interface")
+                .map(String::as_str)
+                .unwrap_or_default();
+            let step = if rotate_matches.get_flag("confirm") {
+                KeyRotationStep::Confirm
+            } else if rotate_matches.get_flag("abort") {
+                KeyRotationStep::Abort
+            } else {
+                KeyRotationStep::Stage
+            };
+            return run_wireguard_rotate_key(interface, step).await;
+        }
+        if let Some(("auto-connect", policy_matches)) = wg_matches.subcommand() {
+            let arg = |name| {
+                policy_matches
+                    .get_one::<String>(name)
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            };
+            return run_wireguard_auto_connect(arg("interface"), arg("policy"));
+        }
+        if let Some(("psk", psk_matches)) = wg_matches.subcommand() {
+            let arg = |name| {
+                psk_matches
+                    .get_one::<String>(name)
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            };
+            return run_wireguard_psk(arg("interface"), arg("peer"), psk_matches.get_flag("stdin"))
+                .await;
+        }
+    }
+
+    if let Some(("show", show_matches)) = matches.subcommand() {
+        return run_show(
+            show_matches
+                .get_one::<String>("interface")
+                .map(String::as_str),
+        )
+        .await;
+    }
+    if let Some(("routes", routes_matches)) = matches.subcommand() {
+        let interface = routes_matches
+            .get_one::<String>("interface")
+            .map(String::as_str)
+            .unwrap_or_default();
+        let routes = crate::network::NetworkManager::new()
+            .get_routes(interface)
+            .await?;
+        println!("{}", serde_json::to_string_pretty(&routes)?);
+        return Ok(());
+    }
+    if let Some(("address", address_matches)) = matches.subcommand() {
+        return run_address(address_matches).await;
+    }
+    if let Some(("pending", pending_matches)) = matches.subcommand() {
+        return run_pending(pending_matches).await;
+    }
+    if let Some(("dummy", dummy_matches)) = matches.subcommand() {
+        return run_dummy(dummy_matches).await;
+    }
+    if let Some(("loopback", loopback_matches)) = matches.subcommand() {
+        return run_loopback(loopback_matches).await;
+    }
+    if let Some(("speedtest", speed_matches)) = matches.subcommand() {
+        return run_speedtest(speed_matches.get_flag("history")).await;
+    }
+    if let Some(("perf", perf_matches)) = matches.subcommand() {
+        return run_perf(perf_matches).await;
+    }
+    if let Some(("alerts", _)) = matches.subcommand() {
+        return run_alerts_test().await;
+    }
+    if let Some(("daemon", _)) = matches.subcommand() {
+        return daemon::run().await;
+    }
+    if let Some(("checkpoint", checkpoint_matches)) = matches.subcommand() {
+        return run_checkpoint(checkpoint_matches).await;
+    }
+    if let Some(("install-service", install_matches)) = matches.subcommand() {
+        let dir = install_matches
+            .get_one::<String>("dir")
+            .map(String::as_str)
+            .unwrap_or(daemon::UNIT_DIR);
+        let enable = install_matches.get_flag("enable");
+        daemon::install_service(std::path::Path::new(dir), enable).await?;
+        println!(
+            "{} Installed lantern.service and lantern.socket in {}",
+            output::success(),
+            dir
+        );
+        if !enable {
+            println!("   Start with: sudo systemctl enable --now lantern.socket lantern.service");
+        }
+        return Ok(());
+    }
+    if let Some(("tune", tune_matches)) = matches.subcommand() {
+        let interface = tune_matches
+            .get_one::<String>("interface")
+            .map(String::as_str)
+            .unwrap_or_default();
+        let tuning = queues::QueueTuning {
+            rx_ring: tune_matches.get_one::<u32>("rx-ring").copied(),
+            tx_ring: tune_matches.get_one::<u32>("tx-ring").copied(),
+            rss_queues: tune_matches.get_one::<u32>("rss-queues").copied(),
+            rps_cpus: tune_matches.get_one::<String>("rps-cpus").cloned(),
+            xps_cpus: tune_matches.get_one::<String>("xps-cpus").cloned(),
+            offloads: tune_matches
+                .get_many::<String>("offload")
+                .unwrap_or_default()
+                .map(|setting| queues::parse_offload(setting))
+                .collect::<Result<_>>()?,
+        };
+        return run_tune(
+            interface,
+            tuning,
+            tune_matches.get_flag("persist"),
+            tune_matches.get_flag("forget"),
+        )
+        .await;
+    }
+
+    if let Some(("replay", replay_matches)) = matches.subcommand() {
+        return run_replay(replay_matches).await;
     }
 
     // Try to setup terminal, fall back to CLI mode if it fails or if forced
     if force_cli || enable_raw_mode().is_err() {
+        if demo {
+            anyhow::bail!("Demo mode needs a terminal that can run the TUI");
+        }
         if force_cli {
             eprintln!(
                 "{} Starting in CLI mode (--cli flag used)...",
-                crate::icons::SETTINGS
+                output::icon(icons::SETTINGS)
             );
         } else {
             eprintln!(
                 "{} TUI mode not available, starting in CLI mode...",
-                crate::icons::SETTINGS
+                output::icon(icons::SETTINGS)
             );
         }
         return run_cli_mode().await;
     }
 
-    let mut stdout = io::stdout();
-    if let Err(e) = execute!(stdout, EnterAlternateScreen, EnableMouseCapture) {
+    // From here on the terminal is put back however the TUI ends: normally,
+    // through an early `?` or in a panic (see install_panic_hook)
+    install_panic_hook();
+    let terminal_guard = scopeguard::guard((), |()| restore_terminal());
+
+    if let Err(e) = enter_screen() {
         disable_raw_mode().ok();
-        eprintln!("{} Screen setup failed: {}", crate::icons::ERROR, e);
+        eprintln!("{} Screen setup failed: {}", output::error(), e);
         eprintln!("   Your terminal may not support the required features.");
-        std::process::exit(1);
+        ExitCode::Failure.exit();
     }
 
-    let backend = CrosstermBackend::new(stdout);
+    let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = match Terminal::new(backend) {
         Ok(t) => t,
         Err(e) => {
             disable_raw_mode().ok();
-            eprintln!(
-                "{} Terminal initialization failed: {}",
-                crate::icons::ERROR,
-                e
-            );
-            std::process::exit(1);
+            eprintln!("{} Terminal initialization failed: {}", output::error(), e);
+            ExitCode::Failure.exit();
         }
     };
 
     // Create app and run
-    let app = app::App::new().await?;
+    let mut app = if demo {
+        app::App::demo().await?
+    } else {
+        app::App::new(safe).await?
+    };
+    app.pacer.set_minimal(console::is_minimal());
     let res = run_app(&mut terminal, app).await;
 
     // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    drop(terminal_guard);
+
+    if let Some(dir) = config::sandbox_dir() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
 
     if let Err(err) = res {
-        eprintln!("{} Application Error: {}", crate::icons::ERROR, err);
+        eprintln!("{} Application Error: {}", output::error(), err);
 
         // Provide helpful context for common errors
         let err_str = format!("{:?}", err);
         if err_str.contains("Permission denied") {
             eprintln!(
                 "{} This may be caused by insufficient privileges.",
-                crate::icons::INFO
+                output::info()
             );
             eprintln!("   Make sure you're running as root: sudo lantern");
         } else if err_str.contains("Command") && err_str.contains("not found") {
-            eprintln!("{} Missing required system tools.", crate::icons::INFO);
+            eprintln!("{} Missing required system tools.", output::info());
             eprintln!("   Please install: iproute2, wireless-tools, wireguard-tools");
+        } else if command::is_timeout(&err) {
+            eprintln!("{} A system command stopped responding.", output::info());
+            eprintln!("   Limits can be raised under [timeouts] in ~/.config/lantern/config.toml");
         } else if err_str.contains("systemd") {
-            eprintln!(
-                "{} systemd-networkd may not be running.",
-                crate::icons::INFO
-            );
+            eprintln!("{} systemd-networkd may not be running.", output::info());
             eprintln!("   Try: sudo systemctl enable --now systemd-networkd");
         }
 
-        std::process::exit(1);
+        ExitCode::of(&err).exit();
     }
 
     Ok(())
 }
 
-async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: app::App) -> Result<()> {
-    // Create channel for non-blocking updates
-    let (update_tx, mut update_rx) = mpsc::unbounded_channel::<UpdateMessage>();
-    loop {
-        // Process pending WiFi scan BEFORE checking for new events
-        // This ensures the loading dialog is drawn first
-        if app.wifi_scan_pending {
-            // First, make sure loading dialog is visible
-            terminal.draw(|f| ui::draw(f, &mut app))?;
-            terminal.backend_mut().flush()?;
+/// Switch to the alternate screen and capture the mouse. The minimal profile
+/// draws over the normal screen instead, which serial consoles can handle.
+fn enter_screen() -> io::Result<()> {
+    if console::is_minimal() {
+        execute!(io::stdout(), Clear(ClearType::All))
+    } else {
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+    }
+}
 
-            // Small delay to ensure render
-            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+/// Leave raw mode and the alternate screen. Harmless when already restored.
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = if console::is_minimal() {
+        execute!(
+            io::stdout(),
+            Clear(ClearType::All),
+            crossterm::cursor::MoveTo(0, 0),
+            crossterm::cursor::Show
+        )
+    } else {
+        execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            crossterm::cursor::Show
+        )
+    };
+}
 
-            // Now do the actual scan
-            app.process_wifi_scan_if_pending().await?;
-            app.needs_redraw = true;
+/// Restore the terminal before a panic message is printed, or the message
+/// lands on the alternate screen and vanishes with it. Background tasks
+/// report their panics in the UI instead (see spawn_task), so printing over
+/// the running TUI is skipped for them.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name() != Some("main") {
+            return;
         }
+        restore_terminal();
+        default_hook(info);
+    }));
+}
 
-        // Only redraw if needed (performance optimization)
-        if app.needs_redraw() {
-            terminal.draw(|f| ui::draw(f, &mut app))?;
-            terminal.backend_mut().flush()?; // Force immediate flush
-            app.mark_redrawn();
-        }
+/// A background task started by spawn_task
+struct Task {
+    name: &'static str,
+    finished: tokio::task::JoinHandle<()>, // Completes with the task, panicked or not
+    abort: tokio::task::AbortHandle,
+}
 
-        // Use shorter poll time for more responsive UI
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => {
-                        return Ok(());
-                    }
-                    KeyCode::Char('r') if !app.show_wifi_dialog => {
-                        app.manual_refresh_interfaces().await?;
-                        app.needs_redraw = true;
-                    }
-                    // WiFi dialog navigation (only when connect and enterprise dialogs are NOT open)
-                    KeyCode::Up | KeyCode::Char('k')
-                        if app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.wifi_navigate_up();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Down | KeyCode::Char('j')
-                        if app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.wifi_navigate_down();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Enter
-                        if app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.open_wifi_connect_dialog();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Enter
-                        if app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        // Check if network is Enterprise and open Enterprise dialog
-                        if let Some(network) = app.get_selected_wifi_network() {
-                            if network.security == crate::network::WifiSecurity::Enterprise {
-                                app.open_wifi_enterprise_dialog();
-                            } else {
-                                app.connect_to_selected_wifi().await?;
-                            }
-                        }
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Enter
-                        if app.show_wifi_enterprise_dialog && !app.show_hotspot_dialog =>
-                    {
-                        app.connect_to_enterprise_wifi().await?;
-                        app.needs_redraw = true;
-                    }
-                    // General navigation (only when no dialogs are active)
-                    KeyCode::Up | KeyCode::Char('k')
-                        if !app.show_edit_dialog
-                            && !app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.previous()
-                    }
-                    KeyCode::Down | KeyCode::Char('j')
-                        if !app.show_edit_dialog
-                            && !app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.next()
-                    }
-                    KeyCode::Enter
-                        if !app.show_edit_dialog
-                            && !app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.toggle_details()
-                    }
-                    KeyCode::Char('e')
-                        if !app.show_edit_dialog
-                            && !app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.edit_interface();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('u')
-                        if !app.show_edit_dialog
-                            && !app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.toggle_interface_state().await?;
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('h')
-                        if !app.show_edit_dialog
-                            && !app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.open_hotspot_dialog();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('w')
-                        if !app.show_edit_dialog
-                            && !app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        // Show loading dialog IMMEDIATELY in the event handler
-                        app.show_wifi_loading_dialog = true;
-                        app.wifi_scan_pending = true;
-
-                        // Force immediate redraw RIGHT NOW
-                        terminal.draw(|f| ui::draw(f, &mut app))?;
-                        // Multiple flushes to ensure it works in release mode
-                        let _ = terminal.backend_mut().flush();
-                        let _ = std::io::stdout().flush();
-                        let _ = std::io::stderr().flush();
-                    }
-                    KeyCode::Char(' ') if app.show_edit_dialog => {
-                        app.toggle_dhcp();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Tab if app.show_edit_dialog => {
-                        app.next_input();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Esc => {
-                        if app.show_wifi_diagnostics_dialog {
-                            app.close_wifi_diagnostics_dialog();
-                        } else if app.show_hotspot_dialog {
-                            app.close_hotspot_dialog();
-                        } else if app.show_wifi_enterprise_dialog {
-                            app.close_wifi_enterprise_dialog();
-                        } else if app.show_wifi_connect_dialog {
-                            app.close_wifi_connect_dialog();
-                        } else if app.show_wifi_loading_dialog {
-                            app.show_wifi_loading_dialog = false;
-                        } else if app.show_wifi_dialog {
-                            app.close_wifi_dialog();
-                        } else {
-                            app.close_dialog();
-                        }
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('s') if app.show_edit_dialog => {
-                        app.save_configuration().await?;
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('r')
-                        if app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.scan_wifi_networks().await?;
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('a')
-                        if app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.toggle_wifi_auto_connect()?;
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('e')
-                        if app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.open_wifi_enterprise_dialog();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('d')
-                        if app.show_wifi_dialog
-                            && !app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.open_wifi_diagnostics_dialog().await;
-                        app.needs_redraw = true;
-                    }
-                    // WiFi connect dialog input
-                    KeyCode::Tab
-                        if app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.wifi_connect_next_input();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char(' ')
-                        if app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.wifi_connect_toggle_dhcp();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char(c)
-                        if app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog
-                            && c != ' ' =>
-                    {
-                        app.wifi_connect_input_char(c);
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Backspace
-                        if app.show_wifi_connect_dialog
-                            && !app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog =>
-                    {
-                        app.wifi_connect_delete_char();
-                        app.needs_redraw = true;
-                    }
-                    // Enterprise WiFi dialog input
-                    KeyCode::Tab if app.show_wifi_enterprise_dialog && !app.show_hotspot_dialog => {
-                        app.enterprise_next_input();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('1')
-                        if app.show_wifi_enterprise_dialog && !app.show_hotspot_dialog =>
-                    {
-                        app.enterprise_cycle_auth_method();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char('2')
-                        if app.show_wifi_enterprise_dialog && !app.show_hotspot_dialog =>
-                    {
-                        app.enterprise_cycle_phase2_auth();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char(c)
-                        if app.show_wifi_enterprise_dialog
-                            && !app.show_hotspot_dialog
-                            && c != '1'
-                            && c != '2' =>
-                    {
-                        app.enterprise_input_char(c);
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Backspace
-                        if app.show_wifi_enterprise_dialog && !app.show_hotspot_dialog =>
-                    {
-                        app.enterprise_delete_char();
-                        app.needs_redraw = true;
-                    }
-                    // Hotspot dialog input
-                    KeyCode::Tab if app.show_hotspot_dialog => {
-                        app.hotspot_next_input();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char(' ')
-                        if app.show_hotspot_dialog && app.hotspot_active_input == 2 =>
-                    {
-                        app.hotspot_cycle_channel();
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Enter if app.show_hotspot_dialog => {
-                        app.create_hotspot().await?;
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char(c) if app.show_hotspot_dialog && c != ' ' => {
-                        app.hotspot_input_char(c);
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Backspace if app.show_hotspot_dialog => {
-                        app.hotspot_delete_char();
-                        app.needs_redraw = true;
-                    }
-                    // WiFi diagnostics dialog input
-                    KeyCode::Char('r') if app.show_wifi_diagnostics_dialog => {
-                        app.refresh_wifi_diagnostics().await;
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Char(c) if app.show_edit_dialog && c != ' ' => {
-                        app.input_char(c);
-                        app.needs_redraw = true;
-                    }
-                    KeyCode::Backspace if app.show_edit_dialog => {
-                        app.delete_char();
-                        app.needs_redraw = true;
-                    }
-                    _ => {}
-                }
+/// Run `future` in the background; if it panics, the UI says so instead of
+/// the result silently never arriving.
+fn spawn_task<F>(tx: &mpsc::UnboundedSender<app::AppEvent>, task: &'static str, future: F) -> Task
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    let handle = tokio::spawn(future);
+    let abort = handle.abort_handle();
+    let tx = tx.clone();
+    let finished = tokio::spawn(async move {
+        if let Err(e) = handle.await {
+            if e.is_panic() {
+                let payload = e.into_panic();
+                let error = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "panicked".to_string());
+                let _ = tx.send(app::AppEvent::TaskFailed { task, error });
             }
         }
+    });
+    Task {
+        name: task,
+        finished,
+        abort,
+    }
+}
 
-        // Check for non-blocking update results
-        while let Ok(update) = update_rx.try_recv() {
-            match update {
-                UpdateMessage::StatsUpdate(updated_interfaces) => {
-                    // Update stats only (preserve other interface data)
-                    for (i, updated) in updated_interfaces.iter().enumerate() {
-                        if let Some(interface) = app.interfaces.get_mut(i) {
-                            interface.stats = updated.stats.clone();
-                        }
-                    }
-                    app.needs_redraw = true;
-                }
-                UpdateMessage::InterfacesUpdate(interfaces) => {
-                    app.interfaces = interfaces;
-                    app.needs_redraw = true;
-                }
-                UpdateMessage::WiFiInfoUpdate(updated_interfaces) => {
-                    // Update WiFi info only
-                    for updated in updated_interfaces {
-                        if let Some(interface) =
-                            app.interfaces.iter_mut().find(|i| i.name == updated.name)
-                        {
-                            interface.wifi_info = updated.wifi_info;
-                        }
-                    }
-                    app.needs_redraw = true;
+/// What `edit_externally` opens
+enum EditTarget {
+    Config,
+    /// The selected interface's generated .network file
+    GeneratedNetwork,
+    /// A .network or .link file networkd applies to an interface
+    Networkd {
+        interface: String,
+        path: PathBuf,
+    },
+    /// A file the doctor found a problem in
+    Linted(PathBuf),
+}
+
+/// Suspend the TUI while `target` is edited in $EDITOR, then load the result.
+async fn edit_externally<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut app::App,
+    target: EditTarget,
+) -> Result<()> {
+    let selected = app
+        .interfaces
+        .get(app.selected_index)
+        .map(|interface| interface.name.clone())
+        .unwrap_or_default();
+    let network_file = !matches!(target, EditTarget::Config);
+    let linted = matches!(target, EditTarget::Linted(_));
+    if network_file && app.network_manager.is_demo() {
+        app.status_message = Some((
+            "Demo mode: no .network files to edit".to_string(),
+            Instant::now(),
+        ));
+        return Ok(());
+    }
+    let (interface, path) = match target {
+        EditTarget::Config => {
+            let path = config::Config::config_path()?;
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            (selected, path)
+        }
+        EditTarget::GeneratedNetwork => {
+            match app.systemd_config.generated_network_file(&selected) {
+                Some(path) => (selected, path),
+                None => {
+                    app.status_message = Some((
+                        format!("Lantern has not configured {} yet (e: Edit)", selected),
+                        Instant::now(),
+                    ));
+                    return Ok(());
                 }
             }
         }
+        EditTarget::Networkd { interface, path } => (interface, path),
+        EditTarget::Linted(path) => (String::new(), path),
+    };
+    // networkd must not pick up the file while another lantern rewrites it
+    let _lock = match network_file.then(lock::OperationLock::try_acquire) {
+        Some(Err(e)) => {
+            app.status_message = Some((e.to_string(), Instant::now()));
+            return Ok(());
+        }
+        lock => lock,
+    };
 
-        // Start non-blocking updates when needed
-        if app.should_refresh_stats() {
-            let tx = update_tx.clone();
-            let network_manager = app.network_manager.clone();
-            let mut interfaces = app.interfaces.clone();
-            tokio::spawn(async move {
-                if let Ok(()) = network_manager
-                    .update_interface_stats(&mut interfaces)
-                    .await
-                {
-                    let _ = tx.send(UpdateMessage::StatsUpdate(interfaces));
-                }
-            });
-            app.mark_stats_refresh_started();
+    restore_terminal();
+    let result = if network_file {
+        // A .link file needs its [Match] section just the same
+        editor::edit(&path, systemd::validate_network_file, false)
+    } else {
+        // Saved WiFi passwords live in config.toml
+        editor::edit(
+            &path,
+            |content| config::Config::parse(content).map(drop),
+            true,
+        )
+    };
+    enable_raw_mode()?;
+    enter_screen()?;
+    terminal.clear()?;
+
+    match result {
+        Ok(editor::Edited::Saved) if linted => {
+            let message = match app.systemd_config.reload().await {
+                Ok(()) => format!("{} saved; networkd reloaded", path.display()),
+                Err(e) => format!("{} saved, but reloading failed: {}", path.display(), e),
+            };
+            app.status_message = Some((message, Instant::now()));
+            app.run_doctor();
+        }
+        Ok(editor::Edited::Saved) if network_file => {
+            let message = match app.systemd_config.apply_edited_network(&interface).await {
+                // udev reads .link files only when a device appears
+                Ok(()) if path.extension().is_some_and(|e| e == "link") => format!(
+                    "{} saved; it applies when {} is next added",
+                    path.display(),
+                    interface
+                ),
+                Ok(()) => format!("{} saved and applied", path.display()),
+                Err(e) => format!("{} saved, but reloading failed: {}", path.display(), e),
+            };
+            app.status_message = Some((message, Instant::now()));
+            app.refresh_networkd_files();
+        }
+        Ok(editor::Edited::Saved) => app.reload_config(),
+        Ok(editor::Edited::Unchanged) => {
+            app.status_message = Some(("No changes".to_string(), Instant::now()));
+        }
+        Ok(editor::Edited::Discarded) => {
+            app.status_message = Some(("Changes discarded".to_string(), Instant::now()));
         }
+        Err(e) => app.status_message = Some((format!("Edit failed: {:#}", e), Instant::now())),
+    }
+    app.needs_redraw = true;
+    Ok(())
+}
 
-        if app.should_refresh_interfaces() {
-            let tx = update_tx.clone();
-            let network_manager = app.network_manager.clone();
-            tokio::spawn(async move {
-                if let Ok(interfaces) = network_manager.get_interfaces().await {
-                    let _ = tx.send(UpdateMessage::InterfacesUpdate(interfaces));
-                }
-            });
-            app.mark_interface_refresh_started();
+/// A flag raised each time the process receives `kind`
+fn flag_on_signal(kind: SignalKind) -> Result<Arc<AtomicBool>> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let mut signals = signal(kind)?;
+    let raised = flag.clone();
+    tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            raised.store(true, Ordering::SeqCst);
         }
+    });
+    Ok(flag)
+}
 
-        if app.should_update_wifi_info() {
-            let tx = update_tx.clone();
-            let network_manager = app.network_manager.clone();
-            let interfaces = app.interfaces.clone();
-            tokio::spawn(async move {
-                let mut updated_interfaces = Vec::new();
-                for interface in interfaces {
-                    if interface.wifi_info.is_some() && interface.state == "UP" {
-                        if let Ok(wifi_info) = network_manager.get_wifi_info(&interface.name).await
-                        {
-                            let mut updated = interface.clone();
-                            updated.wifi_info = wifi_info;
-                            updated_interfaces.push(updated);
-                        }
-                    }
-                }
-                if !updated_interfaces.is_empty() {
-                    let _ = tx.send(UpdateMessage::WiFiInfoUpdate(updated_interfaces));
-                }
-            });
-            app.mark_wifi_update_started();
+/// Let background changes finish (or cancel them), then stop what lantern
+/// started if configured to. Runs before the terminal is restored so the
+/// wait stays visible.
+async fn shut_down<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut app::App,
+    mut changes: Vec<Task>,
+    terminate: &AtomicBool,
+) -> Result<()> {
+    let deadline = Instant::now() + Duration::from_secs(app.config.shutdown.wait_secs);
+    loop {
+        changes.retain(|task| !task.finished.is_finished());
+        if changes.is_empty() {
+            break;
         }
 
-        // Auto-connect check every 30 seconds
-        if app.should_check_auto_connect() {
-            // Run auto-connect in background (non-blocking)
-            let mut app_clone = app.clone();
-            tokio::spawn(async move {
-                let _ = app_clone.check_auto_connect().await;
-            });
-            app.mark_auto_connect_check_started();
+        let cancel = Instant::now() >= deadline
+            || terminate.swap(false, Ordering::SeqCst)
+            || (event::poll(Duration::from_millis(100))?
+                && matches!(
+                    event::read()?,
+                    Event::Key(key) if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                ));
+        if cancel {
+            for task in &changes {
+                task.abort.abort();
+            }
+            break;
+        }
+
+        let names: Vec<&str> = changes.iter().map(|task| task.name).collect();
+        app.status_message = Some((
+            format!("Waiting for {} (q: cancel)", names.join(", ")),
+            Instant::now(),
+        ));
+        terminal.draw(|f| ui::draw(f, app))?;
+    }
+
+    if app.config.shutdown.stop_hotspot {
+        // One kept across reboots stays up; systemd runs it, not this session
+        if let Some(hotspot) = app.active_hotspot.take().filter(|h| !h.persistent) {
+            app.status_message = Some(("Stopping hotspot...".to_string(), Instant::now()));
+            terminal.draw(|f| ui::draw(f, app))?;
+            let _ = app.network_manager.stop_hotspot(&hotspot).await;
         }
     }
+    // A boot server only lives as long as the session that started it
+    if app.pxe_server.is_some() {
+        app.status_message = Some(("Stopping PXE boot server...".to_string(), Instant::now()));
+        terminal.draw(|f| ui::draw(f, app))?;
+        app.stop_pxe().await;
+    }
+    // So is the monitor interface an RF monitor added
+    if app.rf_session.is_some() {
+        let _ = app.close_rf_monitor().await;
+    }
+    Ok(())
 }
 
-async fn run_cli_mode() -> Result<()> {
-    use crate::network::NetworkManager;
+/// What the event loop does after a key
+#[derive(PartialEq)]
+enum Flow {
+    Continue,
+    Quit,
+}
 
-    println!(
-        "{} Lantern Network Manager - CLI Mode",
-        crate::icons::LANTERN
-    );
-    println!("======================================");
+/// Keys go to the innermost open dialog, or the main screen when none is
+async fn handle_key<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut app::App,
+    key: KeyEvent,
+    update_tx: &mpsc::UnboundedSender<app::AppEvent>,
+) -> Result<Flow> {
+    match key.code {
+        KeyCode::F(5) => app.reload_config(),
+        KeyCode::F(12) => app.show_perf_overlay = !app.show_perf_overlay,
+        KeyCode::F(2) => app.request_screenshot(screenshot::Format::Plain),
+        // Anywhere but in a text field
+        KeyCode::Char('q') if !app.modals.top().is_some_and(Modal::takes_text) => {
+            return Ok(Flow::Quit);
+        }
+        _ => match app.modals.top() {
+            Some(modal) => dialog_key(terminal, app, modal, key).await?,
+            None => main_screen_key(terminal, app, key, update_tx).await?,
+        },
+    }
+    app.needs_redraw = true;
+    Ok(Flow::Continue)
+}
 
-    let mut network_manager = NetworkManager::new();
+async fn main_screen_key<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut app::App,
+    key: KeyEvent,
+    update_tx: &mpsc::UnboundedSender<app::AppEvent>,
+) -> Result<()> {
+    match key.code {
+        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.open_palette();
+        }
+        KeyCode::Char('r') => app.manual_refresh_interfaces().await?,
+        KeyCode::Up | KeyCode::Char('k') => app.previous(),
+        KeyCode::Down | KeyCode::Char('j') => app.next(),
+        KeyCode::Enter | KeyCode::Right => app.next_detail_tab(),
+        KeyCode::Left => app.previous_detail_tab(),
+        KeyCode::Char('e') => app.edit_interface().await,
+        KeyCode::Char('u') => app.toggle_interface_state().await?,
+        KeyCode::Char('p') => app.toggle_promiscuous().await?,
+        KeyCode::Char('i') => app.identify_selected(),
+        KeyCode::Char('f') => app.toggle_pause(),
+        KeyCode::Char('z') => app.zero_counters(),
+        KeyCode::Char('Z') => app.restore_counters(),
+        KeyCode::Char('%') => app.cycle_signal_unit(),
+        KeyCode::Char('U') => app.cycle_units(),
+        KeyCode::Char('b') => app.open_routing_dialog(),
+        KeyCode::Char('g') => app.open_topology_dialog(),
+        KeyCode::Char('I') => app.toggle_ignore_selected(),
+        KeyCode::Char('.') => app.toggle_show_ignored(),
+        KeyCode::Char('T') => app.open_adopt_dialog(),
+        KeyCode::Char('F') => app.open_networkd_files_dialog(),
+        KeyCode::Char('C') => app.open_doctor(),
+        KeyCode::Char('K') => app.keep_pending().await,
+        KeyCode::Char('P') => app.open_pxe_dialog(),
+        KeyCode::Char('D') => app.open_global_dns_dialog(),
+        KeyCode::Char('A') => app.open_neighbor_dialog(),
+        KeyCode::Char('m') => app.toggle_monitor_mode().await?,
+        // With a hotspot running, show who is on it instead
+        KeyCode::Char('h') if app.active_hotspot.is_some() => app.open_hotspot_clients_dialog(),
+        KeyCode::Char('h') => app.open_hotspot_dialog(),
+        KeyCode::Char('c') => edit_externally(terminal, app, EditTarget::Config).await?,
+        KeyCode::Char('E') => edit_externally(terminal, app, EditTarget::GeneratedNetwork).await?,
+        KeyCode::Char('s') => app.open_share_dialog(),
+        KeyCode::Char('S') => app.open_survey(),
+        KeyCode::Char('L') => app.open_dns_leak_dialog(),
+        KeyCode::Char('O') => app.open_dhcp_probe_dialog(),
+        KeyCode::Char('N') => app.retry_dhcp().await,
+        KeyCode::Char('M') => app.open_rf_monitor_dialog(),
+        KeyCode::Char('v') => app.open_packet_view(),
+        KeyCode::Char('R') => app.open_reachability_dialog(),
+        KeyCode::Char('W') => app.open_troubleshoot_dialog(),
+        KeyCode::Char('X') => app.toggle_usb_share(),
+        KeyCode::Char('H') => app.open_http_check_dialog(),
+        KeyCode::Char('y') => app.open_copy_dialog(),
+        KeyCode::Char('J') => app.open_jobs_panel(),
+        KeyCode::Char('G') => app.open_address_preference_dialog(),
+        KeyCode::Char('B') => app.open_speed_test_panel(),
+        KeyCode::Char('t') => app.open_queue_tuning_form(),
+        KeyCode::Char('w') => {
+            // Recent cached results show instantly while a fresh scan runs
+            if let Some(interface_name) = app.open_cached_wifi_dialog().await {
+                let tx = update_tx.clone();
+                let network_manager = app.network_manager.clone();
+                spawn_task(update_tx, "wifi scan", async move {
+                    let result = network_manager
+                        .scan_wifi_networks(&interface_name)
+                        .await
+                        .map_err(|e| e.to_string());
+                    let _ = tx.send(app::AppEvent::WifiScan(interface_name, result));
+                });
+                return Ok(());
+            }
 
-    // Try to initialize iwd
-    match network_manager.init_iwd().await {
-        Ok(_) => println!("{} iwd integration enabled", crate::icons::SUCCESS),
-        Err(_) => println!(
-            "{}  iwd not available, using fallback methods",
-            crate::icons::WARNING
-        ),
+            // Show loading dialog IMMEDIATELY in the event handler
+            app.open_wifi_dialog();
+
+            // Force immediate redraw RIGHT NOW
+            terminal.draw(|f| ui::draw(f, app))?;
+            // Multiple flushes to ensure it works in release mode
+            let _ = terminal.backend_mut().flush();
+            let _ = std::io::stdout().flush();
+            let _ = std::io::stderr().flush();
+        }
+        _ => {}
     }
-    println!();
+    Ok(())
+}
 
-    // Get and display interfaces
-    match network_manager.get_interfaces().await {
-        Ok(interfaces) => {
-            println!("\n{} Network Interfaces:", crate::icons::ETHERNET);
-            println!(
-                "   {:<12} {:<8} {:<15} {:<10} {:<10}",
-                "Interface", "State", "IP Address", "RX", "TX"
-            );
-            println!("   {}", "-".repeat(60));
+/// Keys for the dialog on top; each one only knows its own
+async fn dialog_key<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut app::App,
+    modal: Modal,
+    key: KeyEvent,
+) -> Result<()> {
+    match (modal, key.code) {
+        (Modal::Palette, KeyCode::Esc) => app.close_palette(),
+        (Modal::Palette, KeyCode::Up) => app.palette_navigate(false),
+        (Modal::Palette, KeyCode::Down) => app.palette_navigate(true),
+        (Modal::Palette, KeyCode::Enter) => app.run_palette_entry().await,
+        (Modal::Palette, _) => app.palette_input(key),
 
-            for interface in &interfaces {
-                let ip = interface
-                    .ipv4_addresses
-                    .first()
-                    .map(|addr| addr.split('/').next().unwrap_or("N/A"))
-                    .unwrap_or("N/A");
+        (Modal::Hotplug, KeyCode::Char('y') | KeyCode::Enter) => app.accept_hotplug_offer(),
+        (Modal::Hotplug, KeyCode::Char('n') | KeyCode::Esc) => app.dismiss_hotplug_offer(),
 
-                let rx = if interface.stats.rx_bytes > 1024 * 1024 {
-                    format!("{:.1}MB", interface.stats.rx_bytes as f64 / 1024.0 / 1024.0)
-                } else if interface.stats.rx_bytes > 1024 {
-                    format!("{:.1}KB", interface.stats.rx_bytes as f64 / 1024.0)
-                } else {
-                    format!("{}B", interface.stats.rx_bytes)
-                };
+        // Its fields never need an 's', so it saves
+        (Modal::Edit, KeyCode::Esc) => app.close_dialog(),
+        (Modal::Edit, KeyCode::Tab) => app.next_input(),
+        (Modal::Edit, KeyCode::Char(' ')) => app.edit_toggle(),
+        (Modal::Edit, KeyCode::Char('s')) => app.save_configuration().await?,
+        (Modal::Edit, KeyCode::Char(c)) => app.input_char(c),
+        (Modal::Edit, KeyCode::Backspace) => app.delete_char(),
 
-                let tx = if interface.stats.tx_bytes > 1024 * 1024 {
-                    format!("{:.1}MB", interface.stats.tx_bytes as f64 / 1024.0 / 1024.0)
-                } else if interface.stats.tx_bytes > 1024 {
-                    format!("{:.1}KB", interface.stats.tx_bytes as f64 / 1024.0)
-                } else {
-                    format!("{}B", interface.stats.tx_bytes)
-                };
+        (Modal::WifiLoading, KeyCode::Esc) => app.modals.close(Modal::WifiLoading),
 
-                println!(
-                    "   {:<12} {:<8} {:<15} {:<10} {:<10}",
-                    interface.name, &interface.state, ip, rx, tx
-                );
+        (Modal::Wifi, KeyCode::Esc) => app.close_wifi_dialog(),
+        (Modal::Wifi, KeyCode::Up | KeyCode::Char('k')) => app.wifi_navigate_up(),
+        (Modal::Wifi, KeyCode::Down | KeyCode::Char('j')) => app.wifi_navigate_down(),
+        (Modal::Wifi, KeyCode::Enter) => app.open_wifi_connect_dialog(),
+        (Modal::Wifi, KeyCode::Char('r')) => app.scan_wifi_networks().await?,
+        (Modal::Wifi, KeyCode::Char('a')) => app.toggle_wifi_auto_connect()?,
+        (Modal::Wifi, KeyCode::Char('l')) => app.cycle_wifi_band_lock(),
+        (Modal::Wifi, KeyCode::Char('e')) => app.open_wifi_enterprise_dialog(),
+        (Modal::Wifi, KeyCode::Right | KeyCode::Left) => {
+            app.set_wifi_group_expanded(key.code == KeyCode::Right);
+        }
+        (Modal::Wifi, KeyCode::Char('i')) => app.wifi_show_details = !app.wifi_show_details,
+        (Modal::Wifi, KeyCode::Char('d')) => app.open_wifi_diagnostics_dialog().await,
+        // Both list signals
+        (Modal::Wifi | Modal::WifiDiagnostics, KeyCode::Char('%')) => app.cycle_signal_unit(),
+
+        (Modal::WifiDiagnostics, KeyCode::Esc) => app.close_wifi_diagnostics_dialog(),
+        (Modal::WifiDiagnostics, KeyCode::Char('r')) => app.refresh_wifi_diagnostics().await,
+        (Modal::WifiDiagnostics, KeyCode::Char('+')) => app.step_tx_power(1).await,
+        (Modal::WifiDiagnostics, KeyCode::Char('-')) => app.step_tx_power(-1).await,
+        (Modal::WifiDiagnostics, KeyCode::Char('a')) => app.set_tx_power(None).await,
+
+        (Modal::WifiConnect, KeyCode::Esc) => app.close_wifi_connect_dialog(),
+        (Modal::WifiConnect, KeyCode::Tab) => app.wifi_connect_next_input(),
+        (Modal::WifiConnect, KeyCode::Char(' ')) => app.wifi_connect_toggle_dhcp(),
+        (Modal::WifiConnect, KeyCode::Char(c)) => app.wifi_connect_input_char(c),
+        (Modal::WifiConnect, KeyCode::Backspace) => app.wifi_connect_delete_char(),
+        (Modal::WifiConnect, KeyCode::Enter) => {
+            // Check if network is Enterprise and open Enterprise dialog
+            if let Some(network) = app.get_selected_wifi_network() {
+                if network.security == network::WifiSecurity::Enterprise {
+                    app.open_wifi_enterprise_dialog();
+                } else {
+                    app.connect_to_selected_wifi().await?;
+                }
             }
+        }
 
-            println!(
-                "\n{} Lantern CLI mode completed successfully!",
-                crate::icons::SUCCESS
-            );
-            println!(
-                "{} For interactive management, run from a proper terminal with TUI support",
-                crate::icons::INFO
-            );
+        (Modal::WifiEnterprise, KeyCode::Esc) => app.close_wifi_enterprise_dialog(),
+        (Modal::WifiEnterprise, KeyCode::Tab) => app.enterprise_next_input(),
+        (Modal::WifiEnterprise, KeyCode::Char('1')) => app.enterprise_cycle_auth_method(),
+        (Modal::WifiEnterprise, KeyCode::Char('2')) => app.enterprise_cycle_phase2_auth(),
+        (Modal::WifiEnterprise, KeyCode::Char(c)) => app.enterprise_input_char(c),
+        (Modal::WifiEnterprise, KeyCode::Backspace) => app.enterprise_delete_char(),
+        (Modal::WifiEnterprise, KeyCode::Enter) => app.connect_to_enterprise_wifi().await?,
+
+        (Modal::Hotspot, KeyCode::Esc) => app.close_hotspot_dialog(),
+        (Modal::Hotspot, KeyCode::Char('g')) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.hotspot_generate_password();
         }
-        Err(e) => {
-            eprintln!("{} Failed to get interfaces: {}", crate::icons::ERROR, e);
-            return Err(e);
+        (Modal::Hotspot, KeyCode::Tab) => app.hotspot_next_input(),
+        (Modal::Hotspot, KeyCode::Char(' ')) if app.hotspot_active_input == 2 => {
+            app.hotspot_cycle_channel();
         }
-    }
+        (Modal::Hotspot, KeyCode::Enter) => app.create_hotspot(),
+        (Modal::Hotspot, KeyCode::Char(c)) if c != ' ' => app.hotspot_input_char(c),
+        (Modal::Hotspot, KeyCode::Backspace) => app.hotspot_delete_char(),
 
+        // The hotspot clients view pins clients and opens its editors
+        (Modal::HotspotSummary, KeyCode::Esc | KeyCode::Enter) => app.close_hotspot_summary(),
+        (Modal::HotspotSummary, KeyCode::Char('c')) => app.open_hotspot_clients_dialog(),
+        (Modal::HotspotSummary, KeyCode::Char('p')) => app.toggle_hotspot_persistence(),
+
+        (Modal::HotspotClients, KeyCode::Char('h') | KeyCode::Esc) => {
+            app.close_hotspot_clients_dialog();
+        }
+        (Modal::HotspotClients, KeyCode::Up | KeyCode::Char('k')) => {
+            app.hotspot_client_navigate(false);
+        }
+        (Modal::HotspotClients, KeyCode::Down | KeyCode::Char('j')) => {
+            app.hotspot_client_navigate(true);
+        }
+        (Modal::HotspotClients, KeyCode::Char('p')) => app.pin_selected_hotspot_client().await?,
+        (Modal::HotspotClients, KeyCode::Char('r')) => app.open_reservations_dialog(),
+        (Modal::HotspotClients, KeyCode::Char('f')) => app.open_port_forward_dialog(),
+
+        // DHCP reservations, opened from the hotspot clients view
+        (Modal::Reservations, KeyCode::Char('r') | KeyCode::Esc) => {
+            app.close_reservations_dialog();
+        }
+        (Modal::Reservations, KeyCode::Char('a')) => app.open_reservation_form(false),
+        (Modal::Reservations, KeyCode::Char('e') | KeyCode::Enter) => {
+            app.open_reservation_form(true);
+        }
+        (Modal::Reservations, KeyCode::Char('d') | KeyCode::Delete) => {
+            app.remove_selected_reservation().await?;
+        }
+        (Modal::Reservations, KeyCode::Up | KeyCode::Char('k')) => {
+            app.reservation_navigate(false);
+        }
+        (Modal::Reservations, KeyCode::Down | KeyCode::Char('j')) => {
+            app.reservation_navigate(true);
+        }
+        (Modal::ReservationForm, KeyCode::Esc) => app.close_reservation_form(),
+        (Modal::ReservationForm, KeyCode::Enter) => app.save_reservation_form().await?,
+        (Modal::ReservationForm, KeyCode::Tab) => app.reservation_next_input(),
+        (Modal::ReservationForm, _) => app.reservation_input(key),
+
+        // Port forwards, opened from the hotspot clients view
+        (Modal::PortForwards, KeyCode::Char('f') | KeyCode::Esc) => {
+            app.close_port_forward_dialog();
+        }
+        (Modal::PortForwards, KeyCode::Char('a')) => app.open_port_forward_form(false),
+        (Modal::PortForwards, KeyCode::Char('e') | KeyCode::Enter) => {
+            app.open_port_forward_form(true);
+        }
+        (Modal::PortForwards, KeyCode::Char('d') | KeyCode::Delete) => {
+            app.remove_selected_port_forward().await?;
+        }
+        (Modal::PortForwards, KeyCode::Up | KeyCode::Char('k')) => {
+            app.port_forward_navigate(false);
+        }
+        (Modal::PortForwards, KeyCode::Down | KeyCode::Char('j')) => {
+            app.port_forward_navigate(true);
+        }
+        (Modal::PortForwardForm, KeyCode::Esc) => app.close_port_forward_form(),
+        (Modal::PortForwardForm, KeyCode::Enter) => app.save_port_forward_form().await?,
+        (Modal::PortForwardForm, KeyCode::Tab) => app.port_forward_next_input(),
+        (Modal::PortForwardForm, _) => app.port_forward_input(key),
+
+        // The PXE boot server: a form until it runs, then its log
+        (Modal::Pxe, KeyCode::Esc) => app.close_pxe_dialog(),
+        (Modal::Pxe, KeyCode::Char('s')) if app.pxe_server.is_some() => app.stop_pxe().await,
+        (Modal::Pxe, KeyCode::Enter) if app.pxe_server.is_none() => app.start_pxe().await?,
+        (Modal::Pxe, KeyCode::Tab) if app.pxe_server.is_none() => app.pxe_next_input(),
+        (Modal::Pxe, _) if app.pxe_server.is_none() => app.pxe_input(key),
+
+        // Global DNS: text fields, then toggles cycled with Space
+        (Modal::GlobalDns, KeyCode::Esc) => app.close_global_dns_dialog(),
+        (Modal::GlobalDns, KeyCode::Enter) => app.save_global_dns().await?,
+        (Modal::GlobalDns, KeyCode::Tab) => app.global_dns_next_input(),
+        (Modal::GlobalDns, _) => app.global_dns_input(key),
+
+        // The static neighbor editor: its form takes text, the list a/e/d
+        (Modal::Neighbors, KeyCode::Char('A') | KeyCode::Esc) => app.close_neighbor_dialog(),
+        (Modal::Neighbors, KeyCode::Char('a')) => app.open_neighbor_form(false),
+        (Modal::Neighbors, KeyCode::Char('e') | KeyCode::Enter) => app.open_neighbor_form(true),
+        (Modal::Neighbors, KeyCode::Char('d') | KeyCode::Delete) => {
+            app.remove_selected_neighbor().await?;
+        }
+        (Modal::Neighbors, KeyCode::Up | KeyCode::Char('k')) => app.neighbor_navigate(false),
+        (Modal::Neighbors, KeyCode::Down | KeyCode::Char('j')) => app.neighbor_navigate(true),
+        (Modal::NeighborForm, KeyCode::Esc) => app.close_neighbor_form(),
+        (Modal::NeighborForm, KeyCode::Enter) => app.save_neighbor_form().await?,
+        (Modal::NeighborForm, KeyCode::Tab) => app.neighbor_next_input(),
+        (Modal::NeighborForm, _) => app.neighbor_input(key),
+
+        // The networkd files view: pick a file to preview or edit
+        (Modal::NetworkdFiles, KeyCode::Char('F') | KeyCode::Esc) => {
+            app.close_networkd_files_dialog();
+        }
+        (Modal::NetworkdFiles, KeyCode::Char('E') | KeyCode::Enter) => {
+            if let Some(path) = app.editable_networkd_file() {
+                let interface = app.networkd_files_interface.clone();
+                let target = EditTarget::Networkd { interface, path };
+                edit_externally(terminal, app, target).await?;
+            }
+        }
+        (Modal::NetworkdFiles, KeyCode::Char('r')) => app.refresh_networkd_files(),
+        (Modal::NetworkdFiles, KeyCode::Char('i')) => app.open_raw_edit(),
+
+        // Ring sizes and RSS; its fields only take numbers
+        (Modal::QueueTuning, KeyCode::Esc) => app.close_queue_tuning_form(),
+        (Modal::QueueTuning, KeyCode::Enter) => app.save_queue_tuning(false).await,
+        (Modal::QueueTuning, KeyCode::Char('s'))
+            if key.modifiers.contains(KeyModifiers::CONTROL) =>
+        {
+            app.save_queue_tuning(true).await;
+        }
+        (Modal::QueueTuning, KeyCode::Tab) => app.queue_tuning_next_input(),
+        (Modal::QueueTuning, _) => app.queue_tuning_input(key),
+
+        // Editing one of lantern's files as text: every key but these is typing
+        (Modal::RawEdit, KeyCode::Esc) => app.close_raw_edit(),
+        (Modal::RawEdit, KeyCode::Char('s')) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.save_raw_edit().await?;
+        }
+        (Modal::RawEdit, _) => app.raw_edit_input(key),
+
+        // Doctor: jump to the file a finding is about
+        (Modal::Doctor, KeyCode::Char('C') | KeyCode::Esc) => app.close_doctor(),
+        (Modal::Doctor, KeyCode::Char('E') | KeyCode::Enter) => {
+            if let Some(path) = app.selected_finding_file() {
+                edit_externally(terminal, app, EditTarget::Linted(path)).await?;
+            }
+        }
+        (Modal::Doctor, KeyCode::Char('r')) => app.run_doctor(),
+        (Modal::Doctor, KeyCode::Up | KeyCode::Char('k')) => app.doctor_navigate(false),
+        (Modal::Doctor, KeyCode::Down | KeyCode::Char('j')) => app.doctor_navigate(true),
+        (Modal::NetworkdFiles, KeyCode::Up | KeyCode::Char('k')) => {
+            app.networkd_file_navigate(false);
+        }
+        (Modal::NetworkdFiles, KeyCode::Down | KeyCode::Char('j')) => {
+            app.networkd_file_navigate(true);
+        }
+
+        (Modal::Share, KeyCode::Esc) => app.close_share_dialog(),
+
+        (Modal::DnsLeak, KeyCode::Esc) => app.close_dns_leak_dialog(),
+        (Modal::DnsLeak, KeyCode::Char('r')) => app.rerun_dns_leak_test(),
+        (Modal::DhcpProbe, KeyCode::Esc) => app.close_dhcp_probe_dialog(),
+        (Modal::DhcpProbe, KeyCode::Char('r')) => app.rerun_dhcp_probe(),
+
+        (Modal::Reachability, KeyCode::Esc) => app.close_reachability_dialog(),
+        (Modal::Reachability, KeyCode::Char('r')) => app.rerun_reachability_test(),
+        (Modal::Troubleshoot, KeyCode::Esc) => app.close_troubleshoot_dialog(),
+        (Modal::Troubleshoot, KeyCode::Char('r')) => app.rerun_troubleshoot(),
+
+        (Modal::HttpCheck, KeyCode::Esc) => app.close_http_check_dialog(),
+        (Modal::HttpCheck, KeyCode::Tab) => app.cycle_http_check_binding(),
+        (Modal::HttpCheck, KeyCode::Enter) => app.run_http_check(),
+        (Modal::HttpCheck, _) => app.http_check_input(key),
+
+        (Modal::AddressPreference, KeyCode::Esc) => app.close_address_preference_dialog(),
+        (Modal::AddressPreference, KeyCode::Enter) => app.save_address_preference(),
+        (
+            Modal::AddressPreference,
+            KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down,
+        ) => app.toggle_address_preference(),
+
+        (Modal::SpeedTest, KeyCode::Esc) => app.close_speed_test_panel(),
+        (Modal::SpeedTest, KeyCode::Char('t')) => app.request_speed_test(),
+        (Modal::SpeedTest, KeyCode::Char('x')) => app.export_speed_history(),
+
+        (Modal::Survey, KeyCode::Esc) => app.close_survey(),
+        (Modal::Survey, KeyCode::Char('x')) => app.save_survey(),
+        // Listening starts only on an explicit Enter
+        (Modal::RfMonitor, KeyCode::Enter) => app.start_rf_monitor().await?,
+        (Modal::RfMonitor, KeyCode::Esc) => app.close_rf_monitor().await?,
+        (Modal::Packets, KeyCode::Char('v') | KeyCode::Esc) => app.close_packet_view(),
+        // Read-only
+        (Modal::Routing, KeyCode::Char('b') | KeyCode::Esc) => app.close_routing_dialog(),
+        (Modal::Topology, KeyCode::Char('g') | KeyCode::Esc) => app.close_topology_dialog(),
+        // Adopting an interface from another manager needs a yes
+        (Modal::Adopt, KeyCode::Char('y') | KeyCode::Enter) => app.adopt_interface().await?,
+        (Modal::Adopt, _) => app.close_adopt_dialog(),
+
+        (Modal::Jobs, KeyCode::Esc | KeyCode::Char('J')) => app.close_jobs_panel(),
+        (Modal::Jobs, KeyCode::Up | KeyCode::Char('k')) => app.job_navigate(false),
+        (Modal::Jobs, KeyCode::Down | KeyCode::Char('j')) => app.job_navigate(true),
+        (Modal::Jobs, KeyCode::Char('c')) => app.cancel_selected_job(),
+        (Modal::Jobs, KeyCode::Char('r')) => app.retry_selected_job(),
+
+        (Modal::Copy, KeyCode::Esc) => app.close_copy_dialog(),
+        (Modal::Copy, KeyCode::Up | KeyCode::Char('k')) => app.copy_navigate(false),
+        (Modal::Copy, KeyCode::Down | KeyCode::Char('j')) => app.copy_navigate(true),
+        (Modal::Copy, KeyCode::Enter | KeyCode::Char('y')) => app.copy_selected(),
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: app::App) -> Result<()> {
+    // Create channel for non-blocking updates
+    let (update_tx, mut update_rx) = mpsc::unbounded_channel::<app::AppEvent>();
+
+    // Refresh as soon as links come, go or change state instead of polling
+    // for it. The thread blocks in recv(), so it is simply left to die with the process
+    let link_tx = update_tx.clone();
+    std::thread::spawn(move || {
+        let _ = netlink::watch_links(|| link_tx.send(app::AppEvent::LinkChanged).is_ok());
+    });
+
+    // Why WiFi dropped: nl80211 reports the reason codes as it happens
+    if !app.network_manager.is_demo() {
+        let frame_tx = update_tx.clone();
+        tokio::spawn(async move {
+            wifi_events::watch(|event| frame_tx.send(app::AppEvent::Frame(event)).is_ok()).await;
+        });
+    }
+
+    // SIGTERM quits like 'q'; a second one cancels whatever is still pending.
+    // SIGHUP reloads config.toml like F5
+    let terminate = flag_on_signal(SignalKind::terminate())?;
+    let reload = flag_on_signal(SignalKind::hangup())?;
+
+    // Background tasks that change the system; quitting waits for them
+    let mut changes: Vec<Task> = Vec::new();
+    let mut key_pressed = false;
+
+    loop {
+        if terminate.swap(false, Ordering::SeqCst) {
+            break;
+        }
+        if reload.swap(false, Ordering::SeqCst) {
+            app.reload_config();
+        }
+
+        // Process pending WiFi scan BEFORE checking for new events
+        // This ensures the loading dialog is drawn first
+        if app.wifi_scan_pending {
+            // First, make sure loading dialog is visible
+            terminal.draw(|f| ui::draw(f, &mut app))?;
+            terminal.backend_mut().flush()?;
+
+            // Small delay to ensure render
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+            // Now do the actual scan
+            app.process_wifi_scan_if_pending().await?;
+            app.needs_redraw = true;
+        }
+
+        // Only redraw if needed (performance optimization). Updates from
+        // background tasks are rate-limited; key presses are answered at once
+        let now = Instant::now();
+        if app.needs_redraw() && (key_pressed || app.pacer.frame_due(now)) {
+            let frame = terminal.draw(|f| ui::draw(f, &mut app))?;
+            let screenshot = app
+                .screenshot_due()
+                .map(|format| screenshot::save(frame.buffer, format));
+            terminal.backend_mut().flush()?; // Force immediate flush
+            app.timings.record(Measure::Frame, now.elapsed());
+            app.pacer.mark_frame(now);
+            app.mark_redrawn();
+            if let Some(result) = screenshot {
+                app.screenshot_saved(result);
+            }
+        }
+        key_pressed = false;
+
+        // An action picked from the palette is handled as if its key was pressed.
+        // Short polls while live keep the UI responsive; idle and paused poll less
+        let key = match app.queued_key.take() {
+            Some(key) => Some(key),
+            None if event::poll(app.pacer.poll_timeout(now))? => match event::read()? {
+                Event::Key(key) => Some(key),
+                _ => None,
+            },
+            None => None,
+        };
+        if let Some(key) = key {
+            key_pressed = true;
+            app.pacer.activity(Instant::now());
+            if handle_key(terminal, &mut app, key, &update_tx).await? == Flow::Quit {
+                break;
+            }
+        }
+
+        // Check for non-blocking update results
+        let mut updates = Vec::new();
+        while let Ok(event) = update_rx.try_recv() {
+            updates.push(event);
+        }
+        for event in app::coalesce(updates) {
+            app.apply(event);
+        }
+
+        if app.hotplug_offer.as_ref().is_some_and(|offer| offer.auto) {
+            app.accept_hotplug_offer();
+        }
+        // A prompt held back while a text field was open
+        app.show_hotplug_offer();
+
+        // Queued changes, one at a time; quitting waits for the running one
+        if let Some((id, job)) = app.jobs.next_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            let systemd_config = app.systemd_config.clone();
+            let task = spawn_task(&update_tx, job.name(), async move {
+                let result = job.run(network_manager, systemd_config).await;
+                let _ = tx.send(app::AppEvent::JobFinished(id, result));
+            });
+            app.jobs.started(id, task.abort.clone());
+            changes.push(task);
+        }
+
+        // Start non-blocking updates when needed
+        if app.should_refresh_stats() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            let names: Vec<String> = app.interfaces.iter().map(|i| i.name.clone()).collect();
+            spawn_task(&update_tx, "statistics refresh", async move {
+                let started = Instant::now();
+                let stats = network_manager.collect_interface_stats(&names).await;
+                let _ = tx.send(app::AppEvent::Timed(Measure::Stats, started.elapsed()));
+                let _ = tx.send(app::AppEvent::Stats(stats));
+                let _ = tx.send(app::AppEvent::Tcp(tcp::read_snapshot().await));
+            });
+            app.mark_stats_refresh_started();
+        }
+
+        if app.should_refresh_interfaces() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "interface refresh", async move {
+                let started = Instant::now();
+                match network_manager.get_interfaces().await {
+                    Ok(interfaces) => {
+                        let _ =
+                            tx.send(app::AppEvent::Timed(Measure::Interfaces, started.elapsed()));
+                        let _ = tx.send(app::AppEvent::Interfaces(interfaces));
+                    }
+                    Err(e) => {
+                        let _ = tx.send(app::AppEvent::TaskFailed {
+                            task: "interface refresh",
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            });
+            app.mark_interface_refresh_started();
+        }
+
+        if app.should_update_wifi_info() {
+            let tx = update_tx.clone();
+            let job = app.wifi_info_job();
+            spawn_task(&update_tx, "WiFi info refresh", async move {
+                if let Some(event) = job.run().await {
+                    let _ = tx.send(event);
+                }
+            });
+            app.mark_wifi_update_started();
+        }
+
+        if app.mqtt_publish_due() {
+            app.publish_telemetry();
+        }
+
+        app.poll_pxe().await;
+        app.poll_pending();
+
+        if app.hotspot_unit_check_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "hotspot unit check", async move {
+                let found = network_manager.persistent_hotspot().await;
+                let _ = tx.send(app::AppEvent::PersistentHotspot(found));
+            });
+            app.mark_hotspot_unit_check_started();
+        }
+
+        if let Some(interfaces) = app.foreign_check_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "network manager detection", async move {
+                let foreign = network_manager.detect_foreign_managers(&interfaces).await;
+                let _ = tx.send(app::AppEvent::ForeignManagers(foreign));
+            });
+            app.mark_foreign_check_started();
+        }
+
+        if app.mac_check_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            let interfaces = app.interfaces.clone();
+            spawn_task(&update_tx, "MAC conflict check", async move {
+                if let Ok(neighbors) = network_manager.neighbor_table().await {
+                    let conflicts = lantern::macconflict::find(&neighbors, &interfaces);
+                    let _ = tx.send(app::AppEvent::MacConflicts(conflicts));
+                }
+            });
+            app.mark_mac_check_started();
+        }
+
+        if app.container_refresh_due() {
+            let tx = update_tx.clone();
+            spawn_task(&update_tx, "container lookup", async move {
+                let _ = tx.send(app::AppEvent::Containers(
+                    lantern::containers::map_veths().await,
+                ));
+            });
+            app.mark_container_refresh_started();
+        }
+
+        // The capture blocks in recv(), so it gets a blocking-pool thread
+        // (channel hops run iw through the runtime from there); it ends when
+        // the dialog sets the flag
+        if let Some((monitor, hop, stop)) = app.rf_capture_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            tokio::task::spawn_blocking(move || {
+                let result = network_manager.rf_capture(&monitor, hop, &stop, |sighting| {
+                    tx.send(app::AppEvent::RfSighting(sighting)).is_ok()
+                });
+                let error = result.err().map(|e| format!("{:#}", e));
+                let _ = tx.send(app::AppEvent::RfCaptureEnded(error));
+            });
+            app.mark_rf_capture_started();
+        }
+
+        // Blocks in recv() the same way
+        if let Some((interface, stop)) = app.packet_capture_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            std::thread::spawn(move || {
+                let result = network_manager.packet_capture(&interface, &stop, |sample| {
+                    tx.send(app::AppEvent::Packets(sample)).is_ok()
+                });
+                let error = result.err().map(|e| format!("{:#}", e));
+                let _ = tx.send(app::AppEvent::PacketCaptureEnded(error));
+            });
+            app.mark_packet_capture_started();
+        }
+
+        if let Some((interface, up)) = app.dns_leak_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "DNS leak test", async move {
+                let report = network_manager.dns_leak_test(&interface, &up).await;
+                let _ = tx.send(app::AppEvent::DnsLeak(report));
+            });
+            app.mark_dns_leak_started();
+        }
+
+        if let Some((interface, mac)) = app.dhcp_probe_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            let configured = app.config.dhcp.servers.clone();
+            spawn_task(&update_tx, "DHCP probe", async move {
+                let report = network_manager
+                    .dhcp_probe(&interface, &mac, &configured)
+                    .await;
+                let _ = tx.send(app::AppEvent::DhcpProbe(report));
+            });
+            app.mark_dhcp_probe_started();
+        }
+
+        if let Some(interface) = app.reachability_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "reachability test", async move {
+                let result = network_manager
+                    .reachability_test(&interface)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(app::AppEvent::Reachability(interface.name, result));
+            });
+            app.mark_reachability_started();
+        }
+
+        if let Some(interface) = app.troubleshoot_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "troubleshooting", async move {
+                let result = network_manager
+                    .troubleshoot(&interface)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(app::AppEvent::Troubleshoot(interface.name, result));
+            });
+            app.mark_troubleshoot_started();
+        }
+
+        if let Some((interface, seconds)) = app.identify_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "identify", async move {
+                let result = network_manager
+                    .identify(&interface, seconds)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(app::AppEvent::Identified(interface, result));
+            });
+            app.mark_identify_started();
+        }
+
+        if let Some((url, binding)) = app.http_check_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "HTTP check", async move {
+                let result = network_manager
+                    .http_check(&url, &binding)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(app::AppEvent::HttpCheck(result));
+            });
+            app.mark_http_check_started();
+        }
+
+        if app.address_order_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "address order lookup", async move {
+                let result = network_manager
+                    .address_order(gai::SAMPLE_NAME)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(app::AppEvent::AddressOrder(result));
+            });
+            app.mark_address_order_started();
+        }
+
+        if app.speed_test_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            let settings = app.config.speedtest.clone();
+            spawn_task(&update_tx, "speed test", async move {
+                let result = network_manager.speed_test(&settings).await;
+                let _ = tx.send(app::AppEvent::SpeedTest(result));
+            });
+            app.mark_speed_test_started();
+        }
+
+        if let Some(interface) = app.survey_scan_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "survey scan", async move {
+                let result = network_manager
+                    .scan_wifi_networks(&interface)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(app::AppEvent::SurveyScan(result));
+            });
+            app.mark_survey_scan_started();
+        }
+
+        if let Some(address) = app.survey_gps_due() {
+            let tx = update_tx.clone();
+            spawn_task(&update_tx, "gpsd poll", async move {
+                let status = lantern::survey::gps_status(&address).await;
+                let _ = tx.send(app::AppEvent::SurveyGps(status));
+            });
+            app.mark_survey_gps_started();
+        }
+
+        if let Some(interface) = app.hotspot_clients_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "hotspot client listing", async move {
+                let stations = network_manager
+                    .get_hotspot_stations(&interface)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(app::AppEvent::HotspotStations(stations));
+            });
+            app.mark_hotspot_clients_fetch_started();
+        }
+
+        if let Some(interface) = app.networkd_files_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "networkd file lookup", async move {
+                let files = network_manager
+                    .get_networkd_files(&interface)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(app::AppEvent::NetworkdFiles(interface, files));
+            });
+            app.mark_networkd_files_fetch_started();
+        }
+
+        if let Some(interface) = app.neighbors_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            spawn_task(&update_tx, "static neighbor listing", async move {
+                let neighbors = network_manager
+                    .get_static_neighbors(&interface)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(app::AppEvent::StaticNeighbors(interface, neighbors));
+            });
+            app.mark_neighbors_fetch_started();
+        }
+
+        if app.routing_fetch_due() {
+            let tx = update_tx.clone();
+            spawn_task(&update_tx, "routing status", async move {
+                let _ = tx.send(app::AppEvent::Routing(routing::get_routing_status().await));
+            });
+            app.mark_routing_fetch_started();
+        }
+
+        if let Some((tab, interface_name)) = app.detail_fetch_due() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            let probe_latency = app.config.wireguard.probe_latency;
+            match tab {
+                app::DetailTab::Routes => app.routes.start(&interface_name),
+                app::DetailTab::Queues => app.queues.start(&interface_name),
+                _ => app.wireguard.start(&interface_name),
+            }
+            spawn_task(&update_tx, "detail refresh", async move {
+                let event = if tab == app::DetailTab::Routes {
+                    let routes = match network_manager.get_routes(&interface_name).await {
+                        Ok(routes) => routes,
+                        Err(e) => {
+                            let _ = tx.send(app::AppEvent::TaskFailed {
+                                task: "route listing",
+                                error: e.to_string(),
+                            });
+                            Vec::new()
+                        }
+                    };
+                    app::AppEvent::Routes(interface_name, routes)
+                } else if tab == app::DetailTab::Queues {
+                    let stats = queues::get_queue_stats(&interface_name).await;
+                    app::AppEvent::Queues(interface_name, stats)
+                } else {
+                    let mut status = network_manager
+                        .get_wireguard_status(&interface_name)
+                        .await
+                        .ok()
+                        .flatten();
+                    if let Some(status) = status.as_mut().filter(|_| probe_latency) {
+                        network_manager.probe_wireguard_peers(status).await;
+                    }
+                    app::AppEvent::WireGuard(interface_name, status)
+                };
+                let _ = tx.send(event);
+            });
+        }
+
+        // Auto-connect check every 30 seconds
+        if app.should_check_auto_connect() {
+            // Run auto-connect in background (non-blocking)
+            let tx = update_tx.clone();
+            let job = app.auto_connect_job();
+            changes.push(spawn_task(&update_tx, "auto-connect", async move {
+                if let Some(event) = job.run().await {
+                    let _ = tx.send(event);
+                }
+            }));
+            app.mark_auto_connect_check_started();
+        }
+
+        if app.should_check_network_policies() {
+            let tx = update_tx.clone();
+            let job = app.network_policy_job();
+            changes.push(spawn_task(&update_tx, "network policy check", async move {
+                for event in job.run().await {
+                    let _ = tx.send(event);
+                }
+            }));
+            app.mark_policy_check_started();
+        }
+
+        // Peers behind dynamic DNS stop handshaking once their address moves
+        if app.should_reresolve_endpoints() {
+            let tx = update_tx.clone();
+            let network_manager = app.network_manager.clone();
+            let endpoints = app.systemd_config.wireguard_hostname_endpoints();
+            if !endpoints.is_empty() {
+                changes.push(spawn_task(
+                    &update_tx,
+                    "endpoint re-resolution",
+                    async move {
+                        let changes = network_manager
+                            .reresolve_wireguard_endpoints(endpoints)
+                            .await;
+                        if !changes.is_empty() {
+                            let _ = tx.send(app::AppEvent::EndpointsReresolved(changes));
+                        }
+                    },
+                ));
+            }
+            app.mark_endpoint_resolve_started();
+        }
+        changes.retain(|task| !task.finished.is_finished());
+    }
+
+    shut_down(terminal, &mut app, changes, &terminate).await
+}
+
+async fn run_cli_mode() -> Result<()> {
+    use crate::network::NetworkManager;
+
+    println!(
+        "{} Lantern Network Manager - CLI Mode",
+        output::icon(icons::LANTERN)
+    );
+    println!("======================================");
+
+    let mut network_manager = NetworkManager::new();
+
+    // Try to initialize iwd
+    match network_manager.init_iwd().await {
+        Ok(_) => println!("{} iwd integration enabled", output::success()),
+        Err(_) => println!(
+            "{} iwd not available, using fallback methods",
+            output::warning()
+        ),
+    }
+    println!();
+
+    // Get and display interfaces
+    match network_manager.get_interfaces().await {
+        Ok(interfaces) => {
+            println!("\n{} Network Interfaces:", output::icon(icons::ETHERNET));
+            println!(
+                "   {:<12} {:<8} {:<15} {:<10} {:<10}",
+                "Interface", "State", "IP Address", "RX", "TX"
+            );
+            println!("   {}", "-".repeat(60));
+
+            for interface in &interfaces {
+                let ip = interface
+                    .ipv4_addresses
+                    .first()
+                    .map(|addr| addr.split('/').next().unwrap_or("N/A"))
+                    .unwrap_or("N/A");
+
+                let rx = if interface.stats.rx_bytes > 1024 * 1024 {
+                    format!("{:.1}MB", interface.stats.rx_bytes as f64 / 1024.0 / 1024.0)
+                } else if interface.stats.rx_bytes > 1024 {
+                    format!("{:.1}KB", interface.stats.rx_bytes as f64 / 1024.0)
+                } else {
+                    format!("{}B", interface.stats.rx_bytes)
+                };
+
+                let tx = if interface.stats.tx_bytes > 1024 * 1024 {
+                    format!("{:.1}MB", interface.stats.tx_bytes as f64 / 1024.0 / 1024.0)
+                } else if interface.stats.tx_bytes > 1024 {
+                    format!("{:.1}KB", interface.stats.tx_bytes as f64 / 1024.0)
+                } else {
+                    format!("{}B", interface.stats.tx_bytes)
+                };
+
+                println!(
+                    "   {:<12} {:<8} {:<15} {:<10} {:<10}",
+                    interface.name, &interface.state, ip, rx, tx
+                );
+            }
+
+            println!(
+                "\n{} Lantern CLI mode completed successfully!",
+                output::success()
+            );
+            println!(
+                "{} For interactive management, run from a proper terminal with TUI support",
+                output::info()
+            );
+        }
+        Err(e) => {
+            eprintln!("{} Failed to get interfaces: {}", output::error(), e);
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_wifi_join(qr_source: &str, interface: Option<String>) -> Result<()> {
+    use crate::config::{Config, WifiProfile};
+    use crate::network::{NetworkManager, WifiSecurity};
+
+    let payload = qr::WifiQrPayload::parse(&qr::read_qr_source(qr_source).await?)?;
+
+    if payload.security == WifiSecurity::Enterprise {
+        eprintln!(
+            "{} Enterprise networks cannot be joined from a QR code; use the TUI instead",
+            output::error()
+        );
+        ExitCode::Failure.exit();
+    }
+
+    let mut network_manager = NetworkManager::new();
+    let _ = network_manager.init_iwd().await; // Falls back to wpa_supplicant if unavailable
+
+    let interface_name = match interface {
+        Some(name) => name,
+        None => network_manager
+            .get_interfaces()
+            .await?
+            .into_iter()
+            .find(|iface| iface.wifi_info.is_some())
+            .map(|iface| iface.name)
+            .ok_or(network::NetworkError::NoWirelessInterface)?,
+    };
+
+    println!(
+        "{} Joining '{}' ({:?}) on {}...",
+        output::icon(icons::WIFI),
+        payload.ssid,
+        payload.security,
+        interface_name
+    );
+
+    let _lock = lock::OperationLock::try_acquire()?;
+
+    let mut config = Config::load().unwrap_or_default();
+    let mut credentials = payload.to_credentials();
+    if let Some(saved) = config.get_wifi_profile(&payload.ssid, &interface_name) {
+        credentials.routes = saved.routes.clone();
+        credentials.domains = saved.domains.clone();
+    }
+    network_manager
+        .connect_to_wifi(&interface_name, &credentials, true, None, None, None)
+        .await?;
+
+    // Save the network like a TUI connection so it shows up in history
+    config.add_wifi_profile(WifiProfile {
+        ssid: payload.ssid.clone(),
+        security_type: format!("{:?}", payload.security),
+        password: credentials.password.clone(),
+        interface: interface_name.clone(),
+        dhcp: true,
+        ip: None,
+        gateway: None,
+        dns: None,
+        last_connected: Some(std::time::SystemTime::now()),
+        auto_connect: false,
+        priority: 0,
+        enterprise: None,
+        band: credentials.band,
+        tx_power_dbm: credentials.tx_power_dbm,
+        routes: credentials.routes.clone(),
+        domains: credentials.domains.clone(),
+    });
+    if let Err(e) = config.save() {
+        eprintln!("{} Failed to save WiFi profile: {}", output::warning(), e);
+    }
+
+    println!(
+        "{} Connected to '{}' and saved profile",
+        output::success(),
+        payload.ssid
+    );
+
+    match network_manager.wait_for_dhcp_lease(&interface_name).await {
+        Some(address) => println!("{} Got address {}", output::success(), address),
+        None => eprintln!(
+            "{} No DHCP lease on {} yet; check `networkctl status {}`",
+            output::warning(),
+            interface_name,
+            interface_name
+        ),
+    }
+
+    Ok(())
+}
+
+async fn run_alerts_test() -> Result<()> {
+    use lantern::alerts::{self, Alert};
+
+    let channels = config::Config::load()?.alerts.channels;
+    if channels.is_empty() {
+        anyhow::bail!("No alert channels: add [[alerts.channels]] to config.toml");
+    }
+    let alert = Alert {
+        title: "lantern test alert".to_string(),
+        message: "Alerts from lantern daemon will arrive like this.".to_string(),
+    };
+    let mut failed = 0;
+    for channel in &channels {
+        match alerts::send(channel, &alert).await {
+            Ok(()) => println!("{} {}: sent", output::success(), channel.name()),
+            Err(e) => {
+                failed += 1;
+                println!("{} {}: {:#}", output::error(), channel.name(), e);
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} channels failed", failed, channels.len());
+    }
+    Ok(())
+}
+
+async fn run_speedtest(history: bool) -> Result<()> {
+    use lantern::speedtest;
+
+    if history {
+        print!("{}", speedtest::to_csv(&speedtest::load_history()));
+        return Ok(());
+    }
+    let settings = config::Config::load().unwrap_or_default().speedtest;
+    eprintln!(
+        "{} Testing against {}",
+        output::icon(icons::NETWORK),
+        settings.download_url
+    );
+    let result = speedtest::run(&settings).await;
+    speedtest::record(&result, settings.keep_days)?;
+    match &result.error {
+        Some(error) => println!("{} {} ({})", output::warning(), result.summary(), error),
+        None => println!("{} {}", output::success(), result.summary()),
+    }
+    Ok(())
+}
+
+fn perf_port_arg() -> Arg {
+    Arg::new("port")
+        .long("port")
+        .short('p')
+        .value_name("PORT")
+        .value_parser(clap::value_parser!(u16).range(1..))
+        .default_value("5202")
+        .help("TCP port of the control connection; UDP tests pick their own")
+}
+
+async fn run_perf(matches: &clap::ArgMatches) -> Result<()> {
+    use lantern::throughput::{self, Protocol, Request};
+
+    match matches.subcommand() {
+        Some(("serve", serve_matches)) => {
+            let address = serve_matches
+                .get_one::<std::net::IpAddr>("bind")
+                .copied()
+                .unwrap_or(std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED));
+            let port = serve_matches
+                .get_one::<u16>("port")
+                .copied()
+                .unwrap_or(throughput::DEFAULT_PORT);
+            eprintln!(
+                "{} Waiting for tests on port {} (Ctrl+C to stop)",
+                output::icon(icons::NETWORK),
+                port
+            );
+            throughput::serve(address, port, |line| {
+                println!("{} {}", output::info(), line)
+            })
+            .await
+        }
+        Some(("connect", connect_matches)) => {
+            let host = connect_matches
+                .get_one::<String>("host")
+                .cloned()
+                .unwrap_or_default();
+            let port = connect_matches
+                .get_one::<u16>("port")
+                .copied()
+                .unwrap_or(throughput::DEFAULT_PORT);
+            let bitrate = match connect_matches.get_one::<f64>("bitrate") {
+                Some(&mbit) if mbit > 0.0 => (mbit * 1_000_000.0) as u64,
+                Some(_) => anyhow::bail!("The bitrate must be above zero"),
+                None => throughput::DEFAULT_BITRATE,
+            };
+            let request = Request {
+                protocol: if connect_matches.get_flag("udp") {
+                    Protocol::Udp
+                } else {
+                    Protocol::Tcp
+                },
+                millis: connect_matches
+                    .get_one::<u64>("time")
+                    .copied()
+                    .unwrap_or(10)
+                    * 1000,
+                reverse: connect_matches.get_flag("reverse"),
+                bitrate,
+            };
+            eprintln!(
+                "{} {} with {} for {} s...",
+                output::icon(icons::NETWORK),
+                request.describe(),
+                host,
+                request.millis / 1000
+            );
+            let protocol = request.protocol;
+            let report = throughput::connect(&host, port, request).await?;
+            println!("{} {}", output::success(), report.summary(protocol));
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn run_wifi_survey(matches: &clap::ArgMatches) -> Result<()> {
+    use lantern::survey::{self, Format};
+
+    let output = matches.get_one::<String>("output").cloned();
+    let format = match (matches.get_one::<String>("format"), &output) {
+        (Some(format), _) => Format::parse(format)?,
+        (None, Some(path)) if path.ends_with(".json") => Format::Json,
+        _ => Format::Csv,
+    };
+    let interval = Duration::from_secs(matches.get_one::<u64>("interval").copied().unwrap_or(10));
+    let count = matches.get_one::<usize>("count").copied();
+    let gpsd = matches
+        .get_flag("gps")
+        .then(|| config::Config::load().unwrap_or_default().survey.gpsd);
+
+    let mut network_manager = network::NetworkManager::new();
+    let _ = network_manager.init_iwd().await; // Falls back to wpa_supplicant if unavailable
+    let interface_name = match matches.get_one::<String>("interface") {
+        Some(name) => name.clone(),
+        None => network_manager
+            .get_interfaces()
+            .await?
+            .into_iter()
+            .find(|iface| iface.wifi_info.is_some())
+            .map(|iface| iface.name)
+            .ok_or(network::NetworkError::NoWirelessInterface)?,
+    };
+
+    let mut writer: Box<dyn Write> = match &output {
+        Some(path) => Box::new(io::BufWriter::new(
+            std::fs::File::create(path).with_context(|| format!("Failed to create {}", path))?,
+        )),
+        None => Box::new(io::stdout()),
+    };
+    // CSV goes out a scan at a time, so an interrupted survey keeps what it has
+    if format == Format::Csv {
+        writeln!(writer, "{}", survey::CSV_HEADER)?;
+    }
+    eprintln!(
+        "{} Surveying from {} every {}s; Ctrl+C to stop",
+        output::icon(icons::WIFI),
+        interface_name,
+        interval.as_secs()
+    );
+
+    let mut records = Vec::new();
+    let mut ticker = tokio::time::interval(interval);
+    let mut scans = 0;
+    let mut warned_gps = false;
+    while count.is_none_or(|count| scans < count) {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+        let networks = tokio::select! {
+            result = network_manager.scan_wifi_networks(&interface_name) => result,
+            _ = tokio::signal::ctrl_c() => break,
+        };
+        let networks = match networks {
+            Ok(networks) => networks,
+            // A busy radio now and then shouldn't end a walk around the building
+            Err(e) => {
+                eprintln!("{} Scan failed: {}", output::warning(), e);
+                continue;
+            }
+        };
+        scans += 1;
+        let fix = match &gpsd {
+            Some(address) => survey::gps_status(address).await.fix(),
+            None => None,
+        };
+        if gpsd.is_some() && fix.is_none() && !warned_gps {
+            eprintln!(
+                "{} No position from gpsd yet; rows go without one until it has a fix",
+                output::warning()
+            );
+            warned_gps = true;
+        }
+
+        let timestamp = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+        let scan = survey::records(&networks, scans, &timestamp, fix);
+        eprintln!(
+            "   Scan {}: {} access points{}",
+            scans,
+            scan.len(),
+            fix.map(|fix| format!(" at {:.5}, {:.5}", fix.latitude, fix.longitude))
+                .unwrap_or_default()
+        );
+        match format {
+            Format::Csv => {
+                for record in &scan {
+                    writeln!(writer, "{}", record.to_csv_row())?;
+                }
+                writer.flush()?;
+            }
+            Format::Json => records.extend(scan),
+        }
+    }
+
+    if format == Format::Json {
+        writeln!(writer, "{}", serde_json::to_string_pretty(&records)?)?;
+    }
+    writer.flush()?;
+    if let Some(path) = output {
+        eprintln!("{} {} scans saved to {}", output::success(), scans, path);
+    }
+    Ok(())
+}
+
+enum KeyRotationStep {
+    Stage,
+    Confirm,
+    Abort,
+}
+
+async fn run_wireguard_rotate_key(interface: &str, step: KeyRotationStep) -> Result<()> {
+    let systemd_config = crate::systemd::SystemdNetworkConfig::new();
+
+    match step {
+        KeyRotationStep::Stage => {
+            let public_key = systemd_config.stage_wireguard_key_rotation(interface)?;
+            println!(
+                "{} New key staged for {}; the current key stays active",
+                output::success(),
+                interface
+            );
+            println!("\nNew public key:\n  {}\n", public_key);
+            match qr::render_qr_lines(&public_key) {
+                Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+                Err(e) => eprintln!("{} Could not render QR code: {}", output::warning(), e),
+            }
+            println!("\nAdd this public key to every remote peer, then run:");
+            println!("  lantern wireguard rotate-key {} --confirm", interface);
+            println!("or discard it with --abort.");
+        }
+        KeyRotationStep::Confirm => {
+            let _lock = lock::OperationLock::try_acquire()?;
+            systemd_config
+                .confirm_wireguard_key_rotation(interface)
+                .await?;
+            println!(
+                "{} {} now uses the new private key",
+                output::success(),
+                interface
+            );
+        }
+        KeyRotationStep::Abort => {
+            if systemd_config.abort_wireguard_key_rotation(interface)? {
+                println!(
+                    "{} Discarded the staged key; {} keeps its current key",
+                    output::success(),
+                    interface
+                );
+            } else {
+                println!(
+                    "{} No key rotation pending for {}",
+                    output::info(),
+                    interface
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_wireguard_auto_connect(interface: &str, policy: &str) -> Result<()> {
+    crate::sanitize::interface_name(interface)?;
+    let auto_connect: crate::network::VpnAutoConnect = policy.parse()?;
+
+    let mut config = crate::config::Config::load().unwrap_or_default();
+    config.set_vpn_policy(interface, auto_connect);
+    config.save()?;
+
+    println!(
+        "{} Auto-connect policy for {} set to {}",
+        output::success(),
+        interface,
+        policy
+    );
+    println!("   Applied by the lantern TUI while it is running");
+    Ok(())
+}
+
+async fn run_wireguard_psk(interface: &str, peer: &str, from_stdin: bool) -> Result<()> {
+    let supplied = if from_stdin {
+        let mut key = String::new();
+        std::io::stdin().read_line(&mut key)?;
+        Some(key)
+    } else {
+        None
+    };
+
+    let _lock = lock::OperationLock::try_acquire()?;
+    let preshared_key = crate::systemd::SystemdNetworkConfig::new()
+        .set_wireguard_preshared_key(interface, peer, supplied.as_deref())
+        .await?;
+
+    println!(
+        "{} Preshared key set for peer {} on {}",
+        output::success(),
+        peer,
+        interface
+    );
+    if !from_stdin {
+        // The remote side needs the same key in its [Peer] section
+        println!("\nPresharedKey = {}", preshared_key);
+    }
+
+    Ok(())
+}
+
+async fn run_tune(
+    interface: &str,
+    tuning: queues::QueueTuning,
+    persist: bool,
+    forget: bool,
+) -> Result<()> {
+    crate::sanitize::interface_name(interface)?;
+
+    if forget {
+        if queues::remove_persisted_tuning(interface)? {
+            println!(
+                "{} Persisted tuning for {} removed; current settings stay until the NIC is reset",
+                output::success(),
+                interface
+            );
+        } else {
+            println!("No persisted tuning for {}", interface);
+        }
+        return Ok(());
+    }
+
+    if tuning.is_empty() {
+        if persist {
+            anyhow::bail!("Nothing to persist; pass the settings to keep along with --persist");
+        }
+        print_queue_tuning(interface, &queues::get_queue_stats(interface).await);
+        return Ok(());
+    }
+
+    let _lock = lock::OperationLock::try_acquire()?;
+    queues::apply_tuning(interface, &tuning).await?;
+    println!("{} Tuning applied to {}", output::success(), interface);
+    if persist {
+        let path = queues::persist_tuning(interface, &tuning)?;
+        println!("   Persisted in {}", path.display());
+    }
+    Ok(())
+}
+
+async fn run_replay(matches: &clap::ArgMatches) -> Result<()> {
+    let interface = matches
+        .get_one::<String>("interface")
+        .cloned()
+        .unwrap_or_default();
+    sanitize::interface_name(&interface)?;
+
+    let rate = match (
+        matches.get_one::<f64>("pps").copied(),
+        matches.get_one::<f64>("mbps").copied(),
+    ) {
+        (Some(pps), _) if pps > 0.0 => replay::Rate::PacketsPerSecond(pps),
+        (_, Some(mbps)) if mbps > 0.0 => replay::Rate::Megabits(mbps),
+        (Some(_), _) | (_, Some(_)) => anyhow::bail!("The rate must be above zero"),
+        (None, None) if matches.contains_id("pcap") => replay::Rate::Recorded,
+        (None, None) => replay::Rate::PacketsPerSecond(100.0),
+    };
+    let source = match matches.get_one::<String>("pcap") {
+        Some(path) => {
+            let file = std::fs::read(path).with_context(|| format!("Failed to read {}", path))?;
+            let (frames, truncated) = replay::parse_pcap(&file)?;
+            if frames.is_empty() {
+                anyhow::bail!("{} holds no frames", path);
+            }
+            if truncated > 0 {
+                eprintln!(
+                    "{} {} frames were cut short by the capture's snap length and go out as captured",
+                    output::warning(),
+                    truncated
+                );
+            }
+            replay::Source::Capture {
+                frames,
+                loops: matches.get_one::<usize>("loop").copied().unwrap_or(1),
+            }
+        }
+        None => {
+            let mac = std::fs::read_to_string(format!("/sys/class/net/{}/address", interface))
+                .map_err(|_| network::NetworkError::InterfaceNotFound {
+                    interface: interface.to_string(),
+                })?;
+            let destination = matches
+                .get_one::<String>("dst")
+                .map(String::as_str)
+                .unwrap_or("ff:ff:ff:ff:ff:ff");
+            replay::Source::Generated {
+                frames: replay::TestFrames {
+                    destination: replay::parse_mac(destination)?,
+                    source: replay::parse_mac(mac.trim())?,
+                    vlan: matches.get_one::<u16>("vlan").copied(),
+                    size: matches
+                        .get_one::<u16>("size")
+                        .map(|&size| size as usize)
+                        .unwrap_or(replay::MIN_FRAME_SIZE),
+                },
+                count: matches.get_one::<usize>("count").copied().unwrap_or(100),
+            }
+        }
+    };
+
+    // Raw sends block; keep them off the runtime's threads
+    let summary = tokio::task::spawn_blocking(move || {
+        replay::send(&interface, &source, rate, |summary, total| {
+            eprint!(
+                "\r   {}/{} frames, {} bytes, {:.0}s",
+                summary.sent + summary.failed,
+                total,
+                summary.bytes,
+                summary.elapsed.as_secs_f64()
+            );
+        })
+    })
+    .await??;
+
+    let secs = summary.elapsed.as_secs_f64().max(f64::EPSILON);
+    eprint!("\r{:70}\r", ""); // Clear the progress line
+    println!(
+        "{} Sent {} frames ({} bytes) in {:.2}s: {:.0} frames/s, {:.2} Mbit/s",
+        output::success(),
+        summary.sent,
+        summary.bytes,
+        secs,
+        summary.sent as f64 / secs,
+        summary.bytes as f64 * 8.0 / secs / 1e6
+    );
+    if let Some(error) = summary.first_error {
+        println!(
+            "{} {} frames failed, the first with {}",
+            output::warning(),
+            summary.failed,
+            error
+        );
+        ExitCode::Failure.exit();
+    }
+    Ok(())
+}
+
+fn print_queue_tuning(interface: &str, stats: &queues::QueueStats) {
+    println!(
+        "{}: {} rx / {} tx queues",
+        interface, stats.rx_queues, stats.tx_queues
+    );
+    if let Some(rings) = &stats.rings {
+        let size = |value: Option<u32>| value.map_or("n/a".to_string(), |v| v.to_string());
+        println!(
+            "Rings: rx {} (max {}), tx {} (max {})",
+            size(rings.rx),
+            size(rings.rx_max),
+            size(rings.tx),
+            size(rings.tx_max)
+        );
+    }
+    if !stats.rss_table.is_empty() {
+        println!("RSS: {}", queues::rss_spread(&stats.rss_table));
+    }
+    for (queue, mask) in &stats.rps_cpus {
+        println!("rx-{} rps_cpus {}", queue, mask);
+    }
+    for (queue, mask) in &stats.xps_cpus {
+        println!("tx-{} xps_cpus {}", queue, mask);
+    }
+    for (name, on) in &stats.offloads {
+        println!("{}: {}", name, if *on { "on" } else { "off" });
+    }
+}
+
+async fn run_dummy(matches: &clap::ArgMatches) -> Result<()> {
+    let systemd_config = crate::systemd::SystemdNetworkConfig::new();
+    let _lock = lock::OperationLock::try_acquire()?;
+    match matches.subcommand() {
+        Some(("create", create_matches)) => {
+            let name = create_matches
+                .get_one::<String>("name")
+                .map(String::as_str)
+                .unwrap_or_default();
+            let addresses: Vec<String> = create_matches
+                .get_many::<String>("addresses")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            systemd_config
+                .create_dummy_interface(name, &addresses)
+                .await?;
+            println!(
+                "{} Dummy interface {} configured with {} address(es)",
+                output::success(),
+                name,
+                addresses.len()
+            );
+        }
+        Some(("remove", remove_matches)) => {
+            let name = remove_matches
+                .get_one::<String>("name")
+                .map(String::as_str)
+                .unwrap_or_default();
+            systemd_config.remove_dummy_interface(name).await?;
+            println!("{} Dummy interface {} removed", output::success(), name);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn run_loopback(matches: &clap::ArgMatches) -> Result<()> {
+    let systemd_config = crate::systemd::SystemdNetworkConfig::new();
+    let address = |matches: &clap::ArgMatches| {
+        matches
+            .get_one::<String>("address")
+            .cloned()
+            .unwrap_or_default()
+    };
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            for address in systemd_config.loopback_addresses() {
+                println!("{}", address);
+            }
+        }
+        Some(("add", add_matches)) => {
+            let _lock = lock::OperationLock::try_acquire()?;
+            let added = systemd_config
+                .add_loopback_address(&address(add_matches))
+                .await?;
+            println!("{} {} added to lo", output::success(), added);
+        }
+        Some(("remove", remove_matches)) => {
+            let _lock = lock::OperationLock::try_acquire()?;
+            let address = address(remove_matches);
+            if systemd_config.remove_loopback_address(&address).await? {
+                println!("{} {} removed from lo", output::success(), address);
+            } else {
+                println!("{} was not added by lantern", address);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn run_show(interface: Option<&str>) -> Result<()> {
+    let mut interfaces = crate::network::NetworkManager::new()
+        .get_interfaces()
+        .await?;
+    if let Some(interface) = interface {
+        interfaces.retain(|iface| iface.name == interface);
+        if interfaces.is_empty() {
+            return Err(network::NetworkError::InterfaceNotFound {
+                interface: interface.to_string(),
+            }
+            .into());
+        }
+    }
+    println!("{}", serde_json::to_string_pretty(&interfaces)?);
+    Ok(())
+}
+
+async fn run_address(matches: &clap::ArgMatches) -> Result<()> {
+    let Some((action, action_matches)) = matches.subcommand() else {
+        return Ok(());
+    };
+    let arg = |name| {
+        action_matches
+            .get_one::<String>(name)
+            .map(String::as_str)
+            .unwrap_or_default()
+    };
+    let (interface, address) = (arg("interface"), arg("address"));
+    let added = action == "add";
+
+    let _lock = lock::OperationLock::try_acquire()?;
+    let pending = match action_matches.get_one::<u64>("temporary") {
+        Some(&minutes) => {
+            let description = if added {
+                format!("add {} to {}", address, interface)
+            } else {
+                format!("remove {} from {}", address, interface)
+            };
+            let revert = pending::Revert::Address {
+                interface: interface.to_string(),
+                address: address.to_string(),
+                added,
+            };
+            Some(pending::schedule(description, minutes, revert).await?)
+        }
+        None => None,
+    };
+    let network_manager = crate::network::NetworkManager::new();
+    let result = if added {
+        network_manager.add_ip_address(interface, address).await
+    } else {
+        network_manager.remove_ip_address(interface, address).await
+    };
+    if let Err(e) = result {
+        if let Some(change) = &pending {
+            pending::confirm(&change.id).await.ok();
+        }
+        return Err(e);
+    }
+    println!(
+        "{} {} {} {}",
+        output::success(),
+        address,
+        if added { "added to" } else { "removed from" },
+        interface
+    );
+    if let Some(change) = pending {
+        println!(
+            "   Reverts in {} unless kept with: lantern pending confirm {}",
+            pending::format_remaining(change.remaining(std::time::SystemTime::now())),
+            change.id
+        );
+    }
+    Ok(())
+}
+
+/// `--temporary MINUTES` for changes that can revert on their own
+fn temporary_arg() -> Arg {
+    Arg::new("temporary")
+        .long("temporary")
+        .short('t')
+        .value_name("MINUTES")
+        .value_parser(clap::value_parser!(u64).range(1..=pending::MAX_MINUTES))
+        .help("Revert after MINUTES unless confirmed with `lantern pending confirm`")
+}
+
+/// The same command line, passed on to the daemon that holds the checkpoint
+async fn run_checkpoint(matches: &clap::ArgMatches) -> Result<()> {
+    let Some((action, action_matches)) = matches.subcommand() else {
+        return Ok(());
+    };
+    let mut command = format!("checkpoint {}", action);
+    if let Some(id) = action_matches.try_get_one::<String>("id").ok().flatten() {
+        command.push_str(&format!(" {}", id));
+    }
+    let timeout = match action {
+        "create" => action_matches.get_one::<u64>("timeout").map(u64::to_string),
+        "adjust" => action_matches.get_one::<String>("timeout").cloned(),
+        _ => None,
+    };
+    if let Some(timeout) = timeout {
+        command.push_str(&format!(" {}", timeout));
+    }
+    let reply = daemon::request(&command).await?;
+    match reply.strip_prefix("Error: ") {
+        Some(error) => {
+            eprintln!("{} {}", output::error(), error);
+            ExitCode::Failure.exit();
+        }
+        None => println!("{}", reply),
+    }
+    Ok(())
+}
+
+async fn run_pending(matches: &clap::ArgMatches) -> Result<()> {
+    let Some((action, action_matches)) = matches.subcommand() else {
+        return Ok(());
+    };
+    let now = std::time::SystemTime::now();
+    if action == "list" {
+        let changes = pending::list();
+        if changes.is_empty() {
+            println!("No temporary changes pending");
+        }
+        for change in changes {
+            println!(
+                "{} reverts in {:>6}  {}",
+                change.id,
+                pending::format_remaining(change.remaining(now)),
+                change.description
+            );
+        }
+        return Ok(());
+    }
+
+    let ids: Vec<String> = match action_matches.get_one::<String>("id") {
+        Some(id) => vec![id.clone()],
+        None => pending::list()
+            .into_iter()
+            .map(|change| change.id)
+            .collect(),
+    };
+    if ids.is_empty() {
+        println!("No temporary changes pending");
+    }
+    // From its timer, a revert waits out a change in progress rather than miss its moment
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let _lock = loop {
+        match lock::OperationLock::try_acquire() {
+            Ok(lock) => break lock,
+            Err(e) if Instant::now() >= deadline => return Err(e.into()),
+            Err(_) => tokio::time::sleep(Duration::from_millis(250)).await,
+        }
+    };
+    let mut failed = false;
+    for id in ids {
+        let result = if action == "confirm" {
+            pending::confirm(&id).await
+        } else {
+            pending::revert(&id).await
+        };
+        match result {
+            Ok(change) if action == "confirm" => {
+                println!("{} Kept: {}", output::success(), change.description)
+            }
+            Ok(change) => println!("{} Reverted: {}", output::success(), change.description),
+            Err(e) => {
+                eprintln!("{} {:#}", output::error(), e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        ExitCode::Failure.exit();
+    }
     Ok(())
 }
@@ -0,0 +1,132 @@
+// src/modal.rs - Dialogs open over the main screen, as a stack
+/// A dialog, or a form within one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modal {
+    Edit,
+    WifiLoading,
+    Wifi,
+    WifiConnect,
+    WifiEnterprise,
+    WifiDiagnostics,
+    Hotspot,
+    HotspotSummary,
+    HotspotClients,
+    Reservations,
+    ReservationForm,
+    PortForwards,
+    PortForwardForm,
+    Pxe,
+    GlobalDns,
+    DnsLeak,
+    DhcpProbe,
+    Reachability,
+    Troubleshoot,
+    HttpCheck,
+    AddressPreference,
+    SpeedTest,
+    Neighbors,
+    NeighborForm,
+    NetworkdFiles,
+    RawEdit,
+    QueueTuning,
+    Doctor,
+    Share,
+    Survey,
+    RfMonitor,
+    Packets,
+    Routing,
+    Topology,
+    Adopt,
+    Copy,
+    Jobs,
+    Palette,
+    Hotplug,
+}
+
+impl Modal {
+    /// Typed into, so letters are text rather than commands (`q` included)
+    pub fn takes_text(self) -> bool {
+        matches!(
+            self,
+            Modal::Edit
+                | Modal::WifiConnect
+                | Modal::WifiEnterprise
+                | Modal::Hotspot
+                | Modal::ReservationForm
+                | Modal::PortForwardForm
+                | Modal::Pxe
+                | Modal::GlobalDns
+                | Modal::NeighborForm
+                | Modal::HttpCheck
+                | Modal::RawEdit
+                | Modal::QueueTuning
+                | Modal::Palette
+        )
+    }
+}
+
+/// The open dialogs, innermost last. Keys go to the innermost one; closing a
+/// dialog closes those opened from it too.
+#[derive(Debug, Clone, Default)]
+pub struct ModalStack(Vec<Modal>);
+
+impl ModalStack {
+    /// The dialog that gets the keys; None on the main screen
+    pub fn top(&self) -> Option<Modal> {
+        self.0.last().copied()
+    }
+
+    pub fn is_open(&self, modal: Modal) -> bool {
+        self.0.contains(&modal)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Open `modal` over the others; one already open stays where it is
+    pub fn open(&mut self, modal: Modal) {
+        if !self.is_open(modal) {
+            self.0.push(modal);
+        }
+    }
+
+    /// Close `modal` and everything opened over it
+    pub fn close(&mut self, modal: Modal) {
+        if let Some(index) = self.0.iter().position(|&open| open == modal) {
+            self.0.truncate(index);
+        }
+    }
+
+    /// Outermost first, the order to draw them in
+    pub fn iter(&self) -> impl Iterator<Item = Modal> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closing_a_dialog_closes_those_above() {
+        let mut modals = ModalStack::default();
+        assert_eq!(modals.top(), None);
+        modals.open(Modal::Wifi);
+        modals.open(Modal::WifiConnect);
+        modals.open(Modal::WifiEnterprise);
+        modals.open(Modal::WifiConnect); // Already open: no change
+        assert_eq!(modals.top(), Some(Modal::WifiEnterprise));
+        assert!(modals.top().unwrap().takes_text());
+
+        modals.close(Modal::WifiEnterprise);
+        assert_eq!(modals.top(), Some(Modal::WifiConnect));
+        modals.close(Modal::Share); // Not open: no change
+        assert_eq!(modals.top(), Some(Modal::WifiConnect));
+
+        modals.open(Modal::WifiEnterprise);
+        modals.close(Modal::Wifi);
+        assert!(modals.is_empty());
+        assert!(!modals.is_open(Modal::WifiEnterprise));
+    }
+}
@@ -0,0 +1,224 @@
+// src/mqtt.rs - Interface status, rates and WiFi signal published to an MQTT broker
+use crate::groups::Rates;
+use crate::network::Interface;
+use anyhow::{anyhow, Result};
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+// The event loop reconnects on its own; this paces the attempts
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// `[mqtt]`: publish telemetry for dashboards such as Home Assistant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Root of the topic tree; lantern/<hostname> when not set
+    pub topic: Option<String>,
+    pub interval_secs: u64,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 1883,
+            username: None,
+            password: None,
+            topic: None,
+            interval_secs: 30,
+        }
+    }
+}
+
+impl MqttSettings {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+
+    pub fn base_topic(&self) -> String {
+        match &self.topic {
+            Some(topic) => topic.trim_end_matches('/').to_string(),
+            None => format!("lantern/{}", hostname()),
+        }
+    }
+}
+
+pub(crate) fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Wildcards aren't allowed in a topic that is published to
+fn topic_segment(name: &str) -> String {
+    name.replace(['+', '#', '/'], "_")
+}
+
+/// What `<base>/interfaces/<name>` carries, as JSON for value templates
+pub fn interface_payload(interface: &Interface, (rx_rate, tx_rate): (u64, u64)) -> String {
+    let wifi = interface.wifi_info.as_ref();
+    let network = wifi.and_then(|wifi| wifi.current_network.as_ref());
+    json!({
+        "state": interface.state,
+        "carrier": interface.carrier,
+        "ipv4": interface.ipv4_addresses,
+        "ipv6": interface.ipv6_addresses,
+        "gateway": interface.gateway,
+        "rx_bytes": interface.stats.rx_bytes,
+        "tx_bytes": interface.stats.tx_bytes,
+        "rx_bytes_per_sec": rx_rate,
+        "tx_bytes_per_sec": tx_rate,
+        "ssid": network.map(|network| &network.ssid),
+        "signal_dbm": wifi
+            .and_then(|wifi| wifi.signal_strength)
+            .or(network.map(|network| network.signal_strength)),
+    })
+    .to_string()
+}
+
+/// A connection to the broker. `<base>/status` is a retained "online",
+/// turned "offline" by the broker when lantern goes away.
+pub struct Publisher {
+    client: AsyncClient,
+    base: String,
+    rates: Rates,
+    error: Arc<Mutex<Option<String>>>, // Why the broker can't be reached, while it can't
+    event_loop: JoinHandle<()>,
+}
+
+impl Publisher {
+    pub fn connect(settings: &MqttSettings) -> Result<Self> {
+        if settings.host.trim().is_empty() {
+            return Err(anyhow!("no broker host set in [mqtt]"));
+        }
+        let base = settings.base_topic();
+        let status = format!("{}/status", base);
+        let mut options = MqttOptions::new(
+            format!("lantern-{}", hostname()),
+            settings.host.trim(),
+            settings.port,
+        );
+        options.set_keep_alive(Duration::from_secs(30));
+        options.set_last_will(LastWill::new(&status, "offline", QoS::AtLeastOnce, true));
+        if let Some(username) = &settings.username {
+            options.set_credentials(username, settings.password.clone().unwrap_or_default());
+        }
+
+        let (client, mut event_loop) = AsyncClient::new(options, 64);
+        let error = Arc::new(Mutex::new(None));
+        let online = client.clone();
+        let failure = Arc::clone(&error);
+        let event_loop = tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    // Also after reconnecting, which clears the will's "offline"
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        *failure.lock().unwrap_or_else(|e| e.into_inner()) = None;
+                        let _ = online.try_publish(&status, QoS::AtLeastOnce, true, "online");
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        *failure.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.to_string());
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            client,
+            base,
+            rates: Rates::default(),
+            error,
+            event_loop,
+        })
+    }
+
+    /// Queue the state of every interface for the event loop to send.
+    /// Skipped while the broker can't be reached, so nothing stale piles up.
+    pub fn publish(&mut self, interfaces: &[Interface]) {
+        self.rates.record(interfaces, Instant::now());
+        if self.error().is_some() {
+            return;
+        }
+        for interface in interfaces {
+            let topic = format!(
+                "{}/interfaces/{}",
+                self.base,
+                topic_segment(&interface.name)
+            );
+            let payload = interface_payload(interface, self.rates.get(&interface.name));
+            let _ = self
+                .client
+                .try_publish(topic, QoS::AtMostOnce, false, payload);
+        }
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Drop for Publisher {
+    fn drop(&mut self) {
+        // Closing the connection without a DISCONNECT makes the broker
+        // publish the will
+        self.event_loop.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn publishes_interface_state_as_json() {
+        let wlan0: Interface = serde_json::from_value(json!({
+            "name": "wlan0",
+            "mac_address": "aa:bb:cc:dd:ee:ff",
+            "state": "UP",
+            "mtu": 1500,
+            "ipv4_addresses": ["192.168.1.20/24"],
+            "ipv6_addresses": [],
+            "gateway": "192.168.1.1",
+            "dns_servers": [],
+            "stats": {"rx_bytes": 1000, "tx_bytes": 500, "rx_packets": 0, "tx_packets": 0,
+                      "rx_errors": 0, "tx_errors": 0},
+            "wifi_info": {
+                "current_network": {
+                    "ssid": "Home", "bssid": "11:22:33:44:55:66", "signal_strength": -58,
+                    "frequency": 5180, "channel": 36, "security": "WPA2", "encryption": [],
+                    "connected": true, "in_history": true,
+                },
+            },
+        }))
+        .unwrap();
+
+        let payload: serde_json::Value =
+            serde_json::from_str(&interface_payload(&wlan0, (2048, 512))).unwrap();
+        assert_eq!(payload["state"], "UP");
+        assert_eq!(payload["ipv4"][0], "192.168.1.20/24");
+        assert_eq!(payload["rx_bytes_per_sec"], 2048);
+        assert_eq!(payload["ssid"], "Home");
+        assert_eq!(payload["signal_dbm"], -58);
+
+        assert_eq!(topic_segment("veth+1#a"), "veth_1_a");
+        let settings = MqttSettings {
+            topic: Some("home/router/".to_string()),
+            ..MqttSettings::default()
+        };
+        assert_eq!(settings.base_topic(), "home/router");
+    }
+}
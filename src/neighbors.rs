@@ -0,0 +1,258 @@
+// src/neighbors.rs - Permanent neighbor (static ARP/NDP) entries, persisted as networkd [Neighbor] sections
+use crate::command::TimedOutput;
+use crate::{provenance, sanitize, secure_file};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use tokio::process::Command;
+
+// Drop-in next to whatever .network file networkd applies to the interface,
+// so lantern's entries survive edits to that file and vice versa
+const DROP_IN: &str = "60-lantern-neighbors.conf";
+
+/// An IP address pinned to a MAC address on one interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticNeighbor {
+    pub address: String,
+    pub mac: String,     // Lowercase, colon-separated
+    pub active: bool,    // In the kernel's neighbor table right now
+    pub persisted: bool, // Written to the networkd drop-in
+}
+
+/// Check an IP address and MAC address; the MAC comes back normalised.
+pub fn validate(address: &str, mac: &str) -> Result<(String, String)> {
+    let address: IpAddr = address
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("'{}' is not an IP address", address.trim()))?;
+    let octets: Vec<&str> = mac.trim().split([':', '-']).collect();
+    let valid = octets.len() == 6
+        && octets
+            .iter()
+            .all(|octet| octet.len() == 2 && octet.bytes().all(|b| b.is_ascii_hexdigit()));
+    if !valid {
+        return Err(anyhow!("'{}' is not a MAC address", mac.trim()));
+    }
+    Ok((address.to_string(), octets.join(":").to_lowercase()))
+}
+
+/// Permanent entries from `ip -j neigh show dev <iface> nud permanent`.
+pub fn parse_permanent(ip_json: &str) -> Vec<(String, String)> {
+    let Ok(Value::Array(entries)) = serde_json::from_str::<Value>(ip_json) else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter(|entry| {
+            entry["state"]
+                .as_array()
+                .is_some_and(|states| states.iter().any(|s| s == "PERMANENT"))
+        })
+        .filter_map(|entry| {
+            Some((
+                entry["dst"].as_str()?.to_string(),
+                entry["lladdr"].as_str()?.to_lowercase(),
+            ))
+        })
+        .collect()
+}
+
+/// Address and MAC of each `[Neighbor]` section.
+pub fn parse_drop_in(contents: &str) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    let mut current: Option<(Option<String>, Option<String>)> = None;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            if let Some((Some(address), Some(mac))) = current.take() {
+                entries.push((address, mac));
+            }
+            current = (line == "[Neighbor]").then_some((None, None));
+        } else if let (Some((address, mac)), Some((key, value))) =
+            (current.as_mut(), line.split_once('='))
+        {
+            match key.trim() {
+                "Address" => *address = Some(value.trim().to_string()),
+                "LinkLayerAddress" => *mac = Some(value.trim().to_lowercase()),
+                _ => {}
+            }
+        }
+    }
+    if let Some((Some(address), Some(mac))) = current {
+        entries.push((address, mac));
+    }
+    entries
+}
+
+pub fn render_drop_in(entries: &[(String, String)]) -> String {
+    let sections: Vec<String> = entries
+        .iter()
+        .map(|(address, mac)| {
+            format!(
+                "[Neighbor]\nAddress={}\nLinkLayerAddress={}\n",
+                address, mac
+            )
+        })
+        .collect();
+    format!(
+        "# Permanent neighbor entries managed by lantern\n\n{}",
+        sections.join("\n")
+    )
+}
+
+fn read_drop_in(path: &Path) -> Vec<(String, String)> {
+    fs::read_to_string(path)
+        .map(|contents| parse_drop_in(&contents))
+        .unwrap_or_default()
+}
+
+async fn write_drop_in(path: &Path, entries: &[(String, String)]) -> Result<()> {
+    if entries.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    } else {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let drop_in = provenance::stamp(&render_drop_in(entries), "Permanent neighbor entries");
+        secure_file::write_public(path, drop_in)?;
+    }
+    // The kernel already has the change; networkd only needs to know for next time
+    crate::systemd::reload_networkd().await
+}
+
+/// Permanent entries in the kernel and in the drop-in, merged.
+pub async fn list(interface: &str) -> Result<Vec<StaticNeighbor>> {
+    sanitize::interface_name(interface)?;
+    let output = Command::new("/usr/bin/ip")
+        .args(["-j", "neigh", "show", "dev", interface, "nud", "permanent"])
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to list neighbors of {}: {}",
+            interface,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let persisted = match crate::networkd::drop_in_path(interface, DROP_IN).await {
+        Some(path) => read_drop_in(&path),
+        None => Vec::new(),
+    };
+
+    let mut neighbors: Vec<StaticNeighbor> =
+        parse_permanent(&String::from_utf8_lossy(&output.stdout))
+            .into_iter()
+            .map(|(address, mac)| StaticNeighbor {
+                persisted: persisted.contains(&(address.clone(), mac.clone())),
+                address,
+                mac,
+                active: true,
+            })
+            .collect();
+    for (address, mac) in persisted {
+        if !neighbors.iter().any(|n| n.address == address) {
+            neighbors.push(StaticNeighbor {
+                address,
+                mac,
+                active: false,
+                persisted: true,
+            });
+        }
+    }
+    Ok(neighbors)
+}
+
+/// Pin `address` to `mac` now and in networkd's config. Returns whether it
+/// could be persisted, which needs the interface to be managed by networkd.
+pub async fn add(interface: &str, address: &str, mac: &str) -> Result<bool> {
+    sanitize::interface_name(interface)?;
+    let (address, mac) = validate(address, mac)?;
+    let output = Command::new("/usr/bin/ip")
+        .args([
+            "neigh",
+            "replace",
+            &address,
+            "lladdr",
+            &mac,
+            "dev",
+            interface,
+            "nud",
+            "permanent",
+        ])
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to add {} on {}: {}",
+            address,
+            interface,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let Some(path) = crate::networkd::drop_in_path(interface, DROP_IN).await else {
+        return Ok(false);
+    };
+    let mut entries = read_drop_in(&path);
+    entries.retain(|(existing, _)| *existing != address);
+    entries.push((address, mac));
+    write_drop_in(&path, &entries).await?;
+    Ok(true)
+}
+
+pub async fn remove(interface: &str, address: &str) -> Result<()> {
+    sanitize::interface_name(interface)?;
+    sanitize::argument(address)?;
+    if let Some(path) = crate::networkd::drop_in_path(interface, DROP_IN).await {
+        let mut entries = read_drop_in(&path);
+        let before = entries.len();
+        entries.retain(|(existing, _)| existing != address);
+        if entries.len() != before {
+            write_drop_in(&path, &entries).await?;
+        }
+    }
+    // Already gone from the kernel (interface down, say) is fine
+    Command::new("/usr/bin/ip")
+        .args(["neigh", "del", address, "dev", interface])
+        .timed_output()
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_and_writes_static_entries() {
+        assert_eq!(
+            validate(" 192.168.1.20", "AA-BB-CC-00-11-22").unwrap(),
+            ("192.168.1.20".to_string(), "aa:bb:cc:00:11:22".to_string())
+        );
+        assert!(validate("192.168.1.300", "aa:bb:cc:00:11:22").is_err());
+        assert!(validate("fe80::1", "aa:bb:cc:00:11").is_err());
+
+        let ip = r#"[
+            {"dst":"192.168.1.20","dev":"eth0","lladdr":"AA:BB:CC:00:11:22","state":["PERMANENT"]},
+            {"dst":"192.168.1.1","dev":"eth0","lladdr":"00:11:22:33:44:55","state":["REACHABLE"]}
+        ]"#;
+        assert_eq!(
+            parse_permanent(ip),
+            vec![("192.168.1.20".to_string(), "aa:bb:cc:00:11:22".to_string())]
+        );
+
+        let entries = vec![
+            ("192.168.1.20".to_string(), "aa:bb:cc:00:11:22".to_string()),
+            ("2001:db8::20".to_string(), "aa:bb:cc:00:11:23".to_string()),
+        ];
+        let drop_in = render_drop_in(&entries);
+        assert_eq!(parse_drop_in(&drop_in), entries);
+        assert_eq!(
+            parse_drop_in("[Network]\nAddress=10.0.0.1/24\n[Neighbor]\nAddress=10.0.0.2\n"),
+            Vec::new()
+        );
+    }
+}
@@ -0,0 +1,685 @@
+// src/netlink.rs - Interface counters from a single RTM_GETLINK dump, link change notifications,
+// and ring and RSS changes through the ethtool generic netlink family
+use crate::network::InterfaceStats;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io;
+
+const NLMSG_HDR_LEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+const RTA_HDR_LEN: usize = 4;
+const NLA_TYPE_MASK: u16 = 0x3fff; // Without the nested and byte-order flags
+
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const RTM_NEWLINK: u16 = 16;
+const RTM_DELLINK: u16 = 17;
+const RTM_GETLINK: u16 = 18;
+
+const RTMGRP_LINK: u32 = 1;
+
+const IFLA_IFNAME: u16 = 3;
+const IFLA_LINK: u16 = 5;
+const IFLA_MASTER: u16 = 10;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_STATS64: u16 = 23;
+const IFLA_CARRIER: u16 = 33;
+const IFLA_CARRIER_CHANGES: u16 = 35;
+const IFLA_LINK_NETNSID: u16 = 37;
+
+const IFLA_INFO_KIND: u16 = 1; // Nested in IFLA_LINKINFO
+
+const GENL_HDR_LEN: usize = 4;
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+const CTRL_ATTR_OPS: u16 = 6;
+const CTRL_ATTR_OP_ID: u16 = 1; // Nested in each entry of CTRL_ATTR_OPS
+const NLA_F_NESTED: u16 = 0x8000;
+
+// Extended acks: the kernel's reason for a failed request, not just an errno
+const NETLINK_CAP_ACK: libc::c_int = 10;
+const NETLINK_EXT_ACK: libc::c_int = 11;
+const NLM_F_CAPPED: u16 = 0x100;
+const NLM_F_ACK_TLVS: u16 = 0x200;
+const NLMSGERR_ATTR_MSG: u16 = 1;
+
+const ETHTOOL_GENL_VERSION: u8 = 1;
+const ETHTOOL_MSG_RINGS_SET: u8 = 16;
+const ETHTOOL_MSG_RSS_GET: u8 = 38;
+const ETHTOOL_MSG_RSS_SET: u8 = 48; // Linux 6.17
+
+const ETHTOOL_A_HEADER: u16 = 1; // First attribute of every ethtool message
+const ETHTOOL_A_HEADER_DEV_NAME: u16 = 2;
+const ETHTOOL_A_RINGS_RX: u16 = 6;
+const ETHTOOL_A_RINGS_TX: u16 = 9;
+const ETHTOOL_A_RSS_INDIR: u16 = 4;
+
+/// What one RTM_NEWLINK reply says about a link
+#[derive(Debug, Clone, Default)]
+pub struct LinkInfo {
+    pub stats: InterfaceStats,
+    pub carrier: Option<bool>, // IFLA_CARRIER: is the physical link (cable, association) up
+    pub carrier_changes: Option<u32>, // Transitions since the device was created
+    pub kind: Option<String>,  // IFLA_INFO_KIND: "bridge", "veth", ...; None for physical links
+    pub index: u32,
+    pub master: Option<u32>, // IFLA_MASTER: the bridge or bond this link is enslaved to
+    pub link: Option<u32>,   // IFLA_LINK: the lower link of a VLAN or macvlan, or a veth's peer
+    pub link_netnsid: Option<i32>, // Set when `link` is in another network namespace
+}
+
+/// Dump link statistics for every interface with one netlink request. The
+/// kernel includes rtnl_link_stats64 in each RTM_NEWLINK reply, so this
+/// replaces six /sys reads per interface.
+pub fn dump_link_stats() -> Result<HashMap<String, InterfaceStats>> {
+    Ok(dump_links()?
+        .into_iter()
+        .map(|(name, link)| (name, link.stats))
+        .collect())
+}
+
+/// Dump statistics and carrier state for every interface.
+pub fn dump_links() -> Result<HashMap<String, LinkInfo>> {
+    let socket = NetlinkSocket::open()?;
+    socket.send(&link_dump_request(1))?;
+
+    let mut links = HashMap::new();
+    let mut buf = vec![0u8; 32 * 1024];
+    loop {
+        let len = socket.recv(&mut buf)?;
+        if parse_link_messages(&buf[..len], &mut links)? {
+            return Ok(links);
+        }
+    }
+}
+
+/// Block until the kernel reports links being added, removed or changing
+/// state (carrier, up/down), calling `on_change` once per batch of
+/// notifications. Returns when `on_change` returns false.
+pub fn watch_links(mut on_change: impl FnMut() -> bool) -> Result<()> {
+    let socket = NetlinkSocket::open()?;
+    socket.subscribe(RTMGRP_LINK)?;
+
+    let mut buf = vec![0u8; 32 * 1024];
+    loop {
+        let len = socket.recv(&mut buf)?;
+        if contains_link_change(&buf[..len]) && !on_change() {
+            return Ok(());
+        }
+    }
+}
+
+fn contains_link_change(buf: &[u8]) -> bool {
+    let mut offset = 0;
+    while offset + NLMSG_HDR_LEN <= buf.len() {
+        let msg_len = read_u32(buf, offset) as usize;
+        if msg_len < NLMSG_HDR_LEN {
+            return false;
+        }
+        if matches!(read_u16(buf, offset + 4), RTM_NEWLINK | RTM_DELLINK) {
+            return true;
+        }
+        offset += align(msg_len);
+    }
+    false
+}
+
+fn link_dump_request(seq: u32) -> Vec<u8> {
+    let len = (NLMSG_HDR_LEN + IFINFOMSG_LEN) as u32;
+    let flags = (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16;
+
+    let mut msg = Vec::with_capacity(len as usize);
+    msg.extend_from_slice(&len.to_ne_bytes());
+    msg.extend_from_slice(&RTM_GETLINK.to_ne_bytes());
+    msg.extend_from_slice(&flags.to_ne_bytes());
+    msg.extend_from_slice(&seq.to_ne_bytes());
+    msg.extend_from_slice(&0u32.to_ne_bytes()); // pid: let the kernel fill it in
+    msg.push(libc::AF_UNSPEC as u8); // ifinfomsg, all other fields zero
+    msg.resize(len as usize, 0);
+    msg
+}
+
+/// Parse one recv() worth of netlink messages. Returns true once the dump is done.
+fn parse_link_messages(buf: &[u8], links: &mut HashMap<String, LinkInfo>) -> Result<bool> {
+    let mut offset = 0;
+
+    while offset + NLMSG_HDR_LEN <= buf.len() {
+        let msg_len = read_u32(buf, offset) as usize;
+        let msg_type = read_u16(buf, offset + 4);
+        if msg_len < NLMSG_HDR_LEN || offset + msg_len > buf.len() {
+            return Err(anyhow::anyhow!("Truncated netlink message"));
+        }
+
+        match msg_type {
+            NLMSG_DONE => return Ok(true),
+            NLMSG_ERROR => {
+                let errno = read_u32(buf, offset + NLMSG_HDR_LEN) as i32;
+                return Err(io::Error::from_raw_os_error(-errno).into());
+            }
+            RTM_NEWLINK if msg_len >= NLMSG_HDR_LEN + IFINFOMSG_LEN => {
+                let attrs = &buf[offset + NLMSG_HDR_LEN + IFINFOMSG_LEN..offset + msg_len];
+                if let Some((name, mut link)) = parse_link_attributes(attrs) {
+                    // struct ifinfomsg: family, pad, type, then ifi_index
+                    link.index = read_u32(buf, offset + NLMSG_HDR_LEN + 4);
+                    links.insert(name, link);
+                }
+            }
+            _ => {}
+        }
+
+        offset += align(msg_len);
+    }
+
+    Ok(false)
+}
+
+fn parse_link_attributes(attrs: &[u8]) -> Option<(String, LinkInfo)> {
+    let mut name = None;
+    let mut link = LinkInfo::default();
+
+    for (rta_type, payload) in attributes(attrs) {
+        match rta_type {
+            IFLA_IFNAME => name = Some(c_string(payload)),
+            IFLA_LINKINFO => {
+                link.kind = attributes(payload)
+                    .find(|(nested, _)| *nested == IFLA_INFO_KIND)
+                    .map(|(_, kind)| c_string(kind));
+            }
+            // struct rtnl_link_stats64 starts with rx/tx packets, bytes, errors
+            IFLA_STATS64 if payload.len() >= 6 * 8 => {
+                let field =
+                    |i: usize| u64::from_ne_bytes(payload[i * 8..i * 8 + 8].try_into().unwrap());
+                link.stats = InterfaceStats {
+                    rx_packets: field(0),
+                    tx_packets: field(1),
+                    rx_bytes: field(2),
+                    tx_bytes: field(3),
+                    rx_errors: field(4),
+                    tx_errors: field(5),
+                };
+            }
+            IFLA_MASTER if payload.len() >= 4 => link.master = Some(read_u32(payload, 0)),
+            IFLA_LINK if payload.len() >= 4 => link.link = Some(read_u32(payload, 0)),
+            IFLA_LINK_NETNSID if payload.len() >= 4 => {
+                link.link_netnsid = Some(read_u32(payload, 0) as i32);
+            }
+            IFLA_CARRIER if !payload.is_empty() => link.carrier = Some(payload[0] != 0),
+            IFLA_CARRIER_CHANGES if payload.len() >= 4 => {
+                link.carrier_changes = Some(read_u32(payload, 0));
+            }
+            _ => {}
+        }
+    }
+
+    Some((name?, link))
+}
+
+/// Change the RX and/or TX ring size, like `ethtool -G`
+pub fn set_ring_sizes(interface: &str, rx: Option<u32>, tx: Option<u32>) -> Result<()> {
+    let mut attrs = ethtool_header(interface);
+    for (rings_type, size) in [(ETHTOOL_A_RINGS_RX, rx), (ETHTOOL_A_RINGS_TX, tx)] {
+        if let Some(size) = size {
+            attrs.extend(attr(rings_type, &size.to_ne_bytes()));
+        }
+    }
+    let (socket, family) = ethtool_socket()?;
+    ethtool_request(&socket, family.id, ETHTOOL_MSG_RINGS_SET, &attrs)?;
+    Ok(())
+}
+
+/// Point the RSS indirection table evenly at the first `queues` RX queues,
+/// like `ethtool -X equal`
+pub fn spread_rss(interface: &str, queues: u32) -> Result<()> {
+    if queues == 0 {
+        anyhow::bail!("RSS needs at least one queue");
+    }
+    let (socket, family) = ethtool_socket()?;
+    if !family.commands.contains(&ETHTOOL_MSG_RSS_SET) {
+        anyhow::bail!("Changing the RSS table over netlink needs Linux 6.17 or newer");
+    }
+    let replies = ethtool_request(
+        &socket,
+        family.id,
+        ETHTOOL_MSG_RSS_GET,
+        &ethtool_header(interface),
+    )?;
+    let size = replies
+        .iter()
+        .flat_map(|reply| attributes(reply))
+        .find(|(attr_type, _)| *attr_type == ETHTOOL_A_RSS_INDIR)
+        .map_or(0, |(_, table)| table.len() / 4);
+    if size == 0 {
+        anyhow::bail!("{} has no RSS indirection table", interface);
+    }
+
+    // The kernel only takes a table of the size the device has
+    let mut attrs = ethtool_header(interface);
+    let table: Vec<u8> = (0..size as u32)
+        .flat_map(|entry| (entry % queues).to_ne_bytes())
+        .collect();
+    attrs.extend(attr(ETHTOOL_A_RSS_INDIR, &table));
+    ethtool_request(&socket, family.id, ETHTOOL_MSG_RSS_SET, &attrs)?;
+    Ok(())
+}
+
+/// A generic netlink family: its id and the commands this kernel knows
+#[derive(Debug, PartialEq)]
+struct Family {
+    id: u16,
+    commands: Vec<u8>,
+}
+
+fn parse_family(replies: &[Vec<u8>]) -> Option<Family> {
+    let mut id = None;
+    let mut commands = Vec::new();
+    for (attr_type, payload) in replies.iter().flat_map(|reply| attributes(reply)) {
+        match attr_type {
+            CTRL_ATTR_FAMILY_ID if payload.len() >= 2 => id = Some(read_u16(payload, 0)),
+            CTRL_ATTR_OPS => {
+                for (_, op) in attributes(payload) {
+                    commands.extend(
+                        attributes(op)
+                            .filter(|(op_type, id)| *op_type == CTRL_ATTR_OP_ID && id.len() >= 4)
+                            .map(|(_, id)| read_u32(id, 0) as u8),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(Family { id: id?, commands })
+}
+
+/// A generic netlink socket and the ethtool family on it
+fn ethtool_socket() -> Result<(NetlinkSocket, Family)> {
+    let socket = NetlinkSocket::open_protocol(libc::NETLINK_GENERIC)?;
+    socket.enable_ext_ack();
+    let replies = request(
+        &socket,
+        genl_message(
+            GENL_ID_CTRL,
+            CTRL_CMD_GETFAMILY,
+            1,
+            &attr(CTRL_ATTR_FAMILY_NAME, b"ethtool\0"),
+        ),
+    )
+    .map_err(|e| e.context("The kernel has no ethtool netlink interface"))?;
+    let family = parse_family(&replies)
+        .ok_or_else(|| anyhow::anyhow!("The kernel has no ethtool netlink interface"))?;
+    Ok((socket, family))
+}
+
+/// The header attribute naming the device, which every ethtool request starts with
+fn ethtool_header(interface: &str) -> Vec<u8> {
+    let mut name = interface.as_bytes().to_vec();
+    name.push(0);
+    attr(
+        ETHTOOL_A_HEADER | NLA_F_NESTED,
+        &attr(ETHTOOL_A_HEADER_DEV_NAME, &name),
+    )
+}
+
+fn ethtool_request(
+    socket: &NetlinkSocket,
+    family: u16,
+    command: u8,
+    attrs: &[u8],
+) -> Result<Vec<Vec<u8>>> {
+    request(
+        socket,
+        genl_message(family, command, ETHTOOL_GENL_VERSION, attrs),
+    )
+}
+
+fn genl_message(family: u16, command: u8, version: u8, attrs: &[u8]) -> Vec<u8> {
+    let len = (NLMSG_HDR_LEN + GENL_HDR_LEN + attrs.len()) as u32;
+    let flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+
+    let mut msg = Vec::with_capacity(len as usize);
+    msg.extend_from_slice(&len.to_ne_bytes());
+    msg.extend_from_slice(&family.to_ne_bytes());
+    msg.extend_from_slice(&flags.to_ne_bytes());
+    msg.extend_from_slice(&1u32.to_ne_bytes());
+    msg.extend_from_slice(&0u32.to_ne_bytes());
+    msg.extend_from_slice(&[command, version, 0, 0]);
+    msg.extend_from_slice(attrs);
+    msg
+}
+
+/// Send an acked generic netlink request; the attributes of every reply
+/// before the ack
+fn request(socket: &NetlinkSocket, msg: Vec<u8>) -> Result<Vec<Vec<u8>>> {
+    socket.send(&msg)?;
+    let mut replies = Vec::new();
+    let mut buf = vec![0u8; 32 * 1024];
+    loop {
+        let len = socket.recv(&mut buf)?;
+        if parse_genl_replies(&buf[..len], &mut replies)? {
+            return Ok(replies);
+        }
+    }
+}
+
+/// Parse one recv() worth of generic netlink replies. Returns true at the ack.
+fn parse_genl_replies(buf: &[u8], replies: &mut Vec<Vec<u8>>) -> Result<bool> {
+    let mut offset = 0;
+
+    while offset + NLMSG_HDR_LEN <= buf.len() {
+        let msg_len = read_u32(buf, offset) as usize;
+        let msg_type = read_u16(buf, offset + 4);
+        if msg_len < NLMSG_HDR_LEN || offset + msg_len > buf.len() {
+            return Err(anyhow::anyhow!("Truncated netlink message"));
+        }
+        let msg = &buf[offset..offset + msg_len];
+
+        match msg_type {
+            NLMSG_ERROR if msg_len >= NLMSG_HDR_LEN + 4 => {
+                let errno = read_u32(msg, NLMSG_HDR_LEN) as i32;
+                if errno == 0 {
+                    return Ok(true);
+                }
+                let error = io::Error::from_raw_os_error(-errno);
+                return Err(match ack_message(msg) {
+                    Some(message) => anyhow::Error::new(error).context(message),
+                    None => error.into(),
+                });
+            }
+            NLMSG_DONE => return Ok(true),
+            _ if msg_len >= NLMSG_HDR_LEN + GENL_HDR_LEN => {
+                replies.push(msg[NLMSG_HDR_LEN + GENL_HDR_LEN..].to_vec());
+            }
+            _ => {}
+        }
+
+        offset += align(msg_len);
+    }
+
+    Ok(false)
+}
+
+/// The kernel's explanation in an extended ack, after the errno and the
+/// (capped) request it answers
+fn ack_message(msg: &[u8]) -> Option<String> {
+    let flags = read_u16(msg, 6);
+    if flags & NLM_F_ACK_TLVS == 0 {
+        return None;
+    }
+    let request = NLMSG_HDR_LEN + 4;
+    let request_len = if flags & NLM_F_CAPPED != 0 {
+        NLMSG_HDR_LEN
+    } else {
+        read_u32(msg, request) as usize
+    };
+    let tlvs = msg.get(request + align(request_len)..)?;
+    attributes(tlvs)
+        .find(|(attr_type, _)| *attr_type == NLMSGERR_ATTR_MSG)
+        .map(|(_, message)| c_string(message))
+}
+
+fn attr(rta_type: u16, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&((RTA_HDR_LEN + payload.len()) as u16).to_ne_bytes());
+    out.extend_from_slice(&rta_type.to_ne_bytes());
+    out.extend_from_slice(payload);
+    out.resize(align(out.len()), 0);
+    out
+}
+
+/// The (type, payload) of each rtattr in `attrs`, stopping at a malformed one
+fn attributes(attrs: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        if offset + RTA_HDR_LEN > attrs.len() {
+            return None;
+        }
+        let rta_len = read_u16(attrs, offset) as usize;
+        let rta_type = read_u16(attrs, offset + 2);
+        if rta_len < RTA_HDR_LEN || offset + rta_len > attrs.len() {
+            return None;
+        }
+        let payload = &attrs[offset + RTA_HDR_LEN..offset + rta_len];
+        offset += align(rta_len);
+        Some((rta_type & NLA_TYPE_MASK, payload))
+    })
+}
+
+fn c_string(payload: &[u8]) -> String {
+    let end = payload
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(payload.len());
+    String::from_utf8_lossy(&payload[..end]).to_string()
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+struct NetlinkSocket(libc::c_int);
+
+impl NetlinkSocket {
+    fn open() -> io::Result<Self> {
+        Self::open_protocol(libc::NETLINK_ROUTE)
+    }
+
+    fn open_protocol(protocol: libc::c_int) -> io::Result<Self> {
+        // SAFETY: plain socket(2) call, the fd is owned and closed by Drop
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                protocol,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self(fd))
+    }
+
+    /// Ask for the kernel's error messages in acks, with the echoed request
+    /// cut to its header. Best effort: without them errors are bare errnos.
+    fn enable_ext_ack(&self) {
+        for option in [NETLINK_EXT_ACK, NETLINK_CAP_ACK] {
+            let on: libc::c_int = 1;
+            // SAFETY: `on` outlives the call and its length is passed along
+            unsafe {
+                libc::setsockopt(
+                    self.0,
+                    libc::SOL_NETLINK,
+                    option,
+                    &on as *const libc::c_int as *const libc::c_void,
+                    std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+                );
+            }
+        }
+    }
+
+    /// Join multicast groups (RTMGRP_*) to receive notifications.
+    fn subscribe(&self, groups: u32) -> io::Result<()> {
+        // SAFETY: zeroed sockaddr_nl is valid; family and groups are set below
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = groups;
+
+        // SAFETY: addr outlives the call and its length is passed along
+        let bound = unsafe {
+            libc::bind(
+                self.0,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn send(&self, msg: &[u8]) -> io::Result<()> {
+        // SAFETY: the kernel address is zeroed apart from the family
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+
+        // SAFETY: msg and addr outlive the call and their lengths are passed along
+        let sent = unsafe {
+            libc::sendto(
+                self.0,
+                msg.as_ptr() as *const libc::c_void,
+                msg.len(),
+                0,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        // SAFETY: buf is valid for writes of buf.len() bytes
+        let received =
+            unsafe { libc::recv(self.0, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+        if received < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(received as usize)
+    }
+}
+
+impl Drop for NetlinkSocket {
+    fn drop(&mut self) {
+        // SAFETY: the fd came from socket(2) and is closed exactly once
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(msg_type: u16, body: &[u8]) -> Vec<u8> {
+        let len = (NLMSG_HDR_LEN + body.len()) as u32;
+        let mut out = Vec::new();
+        out.extend_from_slice(&len.to_ne_bytes());
+        out.extend_from_slice(&msg_type.to_ne_bytes());
+        out.extend_from_slice(&[0u8; 10]);
+        out.extend_from_slice(body);
+        out.resize(align(out.len()), 0);
+        out
+    }
+
+    #[test]
+    fn spots_link_notifications() {
+        let ifinfo = vec![0u8; IFINFOMSG_LEN];
+        assert!(contains_link_change(&message(RTM_DELLINK, &ifinfo)));
+
+        let mut buf = message(NLMSG_DONE, &0u32.to_ne_bytes());
+        assert!(!contains_link_change(&buf));
+        buf.extend(message(RTM_NEWLINK, &ifinfo));
+        assert!(contains_link_change(&buf));
+    }
+
+    #[test]
+    fn parses_newlink_stats_and_done() {
+        let counters: Vec<u8> = [10u64, 20, 1000, 2000, 1, 2, 0, 0]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect();
+        let mut body = vec![0u8; IFINFOMSG_LEN];
+        body[4..8].copy_from_slice(&2u32.to_ne_bytes());
+        body.extend(attr(IFLA_IFNAME, b"eth0\0"));
+        body.extend(attr(IFLA_MASTER, &5u32.to_ne_bytes()));
+        body.extend(attr(IFLA_STATS64, &counters));
+        body.extend(attr(IFLA_CARRIER, &[0]));
+        body.extend(attr(IFLA_CARRIER_CHANGES, &7u32.to_ne_bytes()));
+        body.extend(attr(IFLA_LINKINFO, &attr(IFLA_INFO_KIND, b"veth\0")));
+
+        let mut buf = message(RTM_NEWLINK, &body);
+        let mut links = HashMap::new();
+        assert!(!parse_link_messages(&buf, &mut links).unwrap());
+
+        assert_eq!(links["eth0"].carrier, Some(false));
+        assert_eq!(links["eth0"].carrier_changes, Some(7));
+        assert_eq!(links["eth0"].kind.as_deref(), Some("veth"));
+        assert_eq!((links["eth0"].index, links["eth0"].master), (2, Some(5)));
+        let eth0 = &links["eth0"].stats;
+        assert_eq!((eth0.rx_packets, eth0.tx_packets), (10, 20));
+        assert_eq!((eth0.rx_bytes, eth0.tx_bytes), (1000, 2000));
+        assert_eq!((eth0.rx_errors, eth0.tx_errors), (1, 2));
+
+        buf.extend(message(NLMSG_DONE, &0u32.to_ne_bytes()));
+        assert!(parse_link_messages(&buf, &mut links).unwrap());
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        let mut buf = message(RTM_NEWLINK, &[0u8; IFINFOMSG_LEN]);
+        buf.truncate(NLMSG_HDR_LEN + 4);
+        buf[0] = 200;
+        assert!(parse_link_messages(&buf, &mut HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn reads_genl_replies_and_ack_messages() {
+        let request = genl_message(30, ETHTOOL_MSG_RINGS_SET, 1, &ethtool_header("eth0"));
+        assert_eq!(read_u32(&request, 0) as usize, request.len());
+        assert_eq!(read_u16(&request, 4), 30);
+        let header: Vec<_> = attributes(&request[NLMSG_HDR_LEN + GENL_HDR_LEN..]).collect();
+        assert_eq!(header.len(), 1);
+        assert_eq!(header[0].0, ETHTOOL_A_HEADER);
+        let name: Vec<_> = attributes(header[0].1).collect();
+        assert_eq!(name, vec![(ETHTOOL_A_HEADER_DEV_NAME, &b"eth0\0"[..])]);
+
+        let op = |id: u8| attr(CTRL_ATTR_OP_ID, &(id as u32).to_ne_bytes());
+        let mut family = vec![0u8; GENL_HDR_LEN];
+        family.extend(attr(CTRL_ATTR_FAMILY_ID, &30u16.to_ne_bytes()));
+        family.extend(attr(
+            CTRL_ATTR_OPS | NLA_F_NESTED,
+            &[attr(1, &op(15)), attr(2, &op(ETHTOOL_MSG_RINGS_SET))].concat(),
+        ));
+        let mut buf = message(GENL_ID_CTRL, &family);
+        let mut replies = Vec::new();
+        assert!(!parse_genl_replies(&buf, &mut replies).unwrap());
+        assert_eq!(
+            parse_family(&replies),
+            Some(Family {
+                id: 30,
+                commands: vec![15, ETHTOOL_MSG_RINGS_SET],
+            })
+        );
+        buf.extend(message(NLMSG_ERROR, &[0u8; 4 + NLMSG_HDR_LEN]));
+        assert!(parse_genl_replies(&buf, &mut Vec::new()).unwrap());
+
+        // A refused request: errno, the capped request, then the reason
+        let mut body = (-libc::EINVAL).to_ne_bytes().to_vec();
+        body.extend_from_slice(&[0u8; NLMSG_HDR_LEN]);
+        body.extend(attr(
+            NLMSGERR_ATTR_MSG,
+            b"requested ring size exceeds maximum\0",
+        ));
+        let mut refused = message(NLMSG_ERROR, &body);
+        refused[6..8].copy_from_slice(&(NLM_F_CAPPED | NLM_F_ACK_TLVS).to_ne_bytes());
+        let error = parse_genl_replies(&refused, &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "requested ring size exceeds maximum");
+        assert_eq!(
+            error
+                .downcast_ref::<io::Error>()
+                .and_then(io::Error::raw_os_error),
+            Some(libc::EINVAL)
+        );
+    }
+}
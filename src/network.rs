@@ -5,12 +5,31 @@
 #![allow(clippy::upper_case_acronyms)] // Network protocol acronyms are standard
 #![allow(clippy::redundant_pattern_matching)] // Pattern matching is more readable than is_ok/is_err
 #![allow(clippy::manual_clamp)] // Explicit max/min is clearer than clamp
+use crate::capabilities::{Capabilities, CapabilityCache};
+use crate::command::TimedOutput;
+use crate::config::{NetworkExtras, ProfileRoute};
+use crate::demo::Simulation;
+use crate::hotspot::{DhcpReservation, Station};
 use crate::iwd::IwdManager;
+use crate::link_kind::LinkKind;
+use crate::neighbors::StaticNeighbor;
+use crate::networkd::MatchedFiles;
+use crate::portfwd::PortForward;
+use crate::retry::{poll_until, retry, RetryPolicy};
+use crate::systemd::PeerEndpoint;
+use crate::takeover::Foreign;
+use crate::wifi_ies::{ElementParser, SecurityDetails};
+use crate::wifi_rate::{parse_link_rates, LinkRate};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
-use std::process::Command;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::process::Command;
+
+// The hotspot's dnsmasq, by its command line, so other instances are left alone
+const HOTSPOT_DNSMASQ: &str = "dnsmasq -C /tmp/dnsmasq.conf";
 
 #[derive(Debug, thiserror::Error)]
 pub enum NetworkError {
@@ -20,6 +39,9 @@ pub enum NetworkError {
     #[error("Interface '{interface}' not found")]
     InterfaceNotFound { interface: String },
 
+    #[error("No wireless interface found")]
+    NoWirelessInterface,
+
     #[error("WiFi operation failed: {details}")]
     WiFiError { details: String },
 
@@ -56,6 +78,57 @@ pub struct Interface {
     pub dns_servers: Vec<String>,
     pub stats: InterfaceStats,
     pub wifi_info: Option<WifiInfo>,
+    #[serde(default)]
+    pub hardware: Option<NicHardware>, // None for virtual interfaces
+    #[serde(default)]
+    pub admin_up: bool, // IFF_UP: the interface was set up, whatever the link does
+    #[serde(default)]
+    pub carrier: Option<bool>, // None when the kernel didn't say
+    #[serde(default)]
+    pub carrier_changes: Option<u32>,
+    #[serde(default)]
+    pub promiscuous: bool,
+    #[serde(default)]
+    pub monitor: bool, // An 802.11 monitor (radiotap) interface
+    #[serde(default)]
+    pub kind: LinkKind,
+    #[serde(default)]
+    pub master: Option<String>, // The bridge or bond it is a port of
+    #[serde(default)]
+    pub lower: Option<String>, // What a VLAN or macvlan sits on, or a veth's peer
+    #[serde(default)]
+    pub peer_netns: Option<i32>, // Its lower link is in this other network namespace
+}
+
+impl Interface {
+    /// Set up but no physical link: unplugged cable, or WiFi not associated.
+    pub fn no_carrier(&self) -> bool {
+        self.admin_up && self.carrier == Some(false)
+    }
+}
+
+/// The physical device behind an interface, from sysfs and the udev database
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NicHardware {
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub driver: Option<String>,
+}
+
+impl NicHardware {
+    /// e.g. "Intel Ethernet Controller I225-V (igc)"
+    pub fn summary(&self) -> String {
+        let name = [self.vendor.as_deref(), self.model.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        match (&self.driver, name.is_empty()) {
+            (Some(driver), true) => driver.clone(),
+            (Some(driver), false) => format!("{} ({})", name, driver),
+            (None, _) => name,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -98,6 +171,11 @@ pub struct DetailedWifiInfo {
     pub tx_dropped: u64,
     pub rx_dropped: u64,
     pub tx_retries: u64,
+    /// Negotiated mode and MCS each way, from `iw dev <if> link`
+    #[serde(default)]
+    pub tx_rate: Option<LinkRate>,
+    #[serde(default)]
+    pub rx_rate: Option<LinkRate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,8 +187,45 @@ pub struct WifiNetwork {
     pub channel: u32,
     pub security: WifiSecurity,
     pub encryption: Vec<String>,
+    #[serde(default)]
+    pub security_details: SecurityDetails,
     pub connected: bool,
     pub in_history: bool,
+    /// Every BSS seen for this SSID, strongest first
+    #[serde(default)]
+    pub access_points: Vec<AccessPoint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessPoint {
+    pub bssid: String,
+    pub signal_strength: i32,
+    pub frequency: u32,
+    pub channel: u32,
+    pub connected: bool,
+}
+
+impl AccessPoint {
+    pub fn band(&self) -> &'static str {
+        match self.frequency {
+            0..=2999 => "2.4 GHz",
+            3000..=5924 => "5 GHz",
+            _ => "6 GHz",
+        }
+    }
+}
+
+impl WifiNetwork {
+    /// An access point on a faster band than the associated one that is
+    /// strong enough to roam to; below -67 dBm the extra bandwidth is lost.
+    pub fn band_steering_hint(&self) -> Option<&AccessPoint> {
+        let current = self.access_points.iter().find(|ap| ap.connected)?;
+        self.access_points.iter().find(|ap| {
+            ap.frequency > current.frequency
+                && ap.band() != current.band()
+                && ap.signal_strength >= -67
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -130,6 +245,77 @@ pub struct WifiCredentials {
     pub security: WifiSecurity,
     pub hidden: bool,
     pub enterprise: Option<EnterpriseCredentials>,
+    #[serde(default)]
+    pub band: WifiBand,
+    /// Set once connected; None leaves it to the driver
+    #[serde(default)]
+    pub tx_power_dbm: Option<i32>,
+    /// From the saved profile, added once connected
+    #[serde(default)]
+    pub routes: Vec<ProfileRoute>,
+    #[serde(default)]
+    pub domains: Vec<String>,
+}
+
+impl WifiCredentials {
+    pub fn extras(&self) -> NetworkExtras {
+        NetworkExtras {
+            routes: self.routes.clone(),
+            domains: self.domains.clone(),
+        }
+    }
+}
+
+/// The band a connection is held to, for devices that misbehave when roaming
+/// between 2.4 and 5 GHz. Written as `band = "2.4"` or `"5"` in a profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WifiBand {
+    #[default]
+    #[serde(rename = "any")]
+    Any,
+    #[serde(rename = "2.4")]
+    Ghz2_4,
+    #[serde(rename = "5")]
+    Ghz5,
+}
+
+impl WifiBand {
+    pub fn next(self) -> Self {
+        match self {
+            WifiBand::Any => WifiBand::Ghz2_4,
+            WifiBand::Ghz2_4 => WifiBand::Ghz5,
+            WifiBand::Ghz5 => WifiBand::Any,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WifiBand::Any => "any band",
+            WifiBand::Ghz2_4 => "2.4 GHz",
+            WifiBand::Ghz5 => "5 GHz",
+        }
+    }
+
+    /// wpa_supplicant's `freq_list`: every channel of the band, in MHz
+    pub fn freq_list(self) -> Option<String> {
+        let frequencies: Vec<u32> = match self {
+            WifiBand::Any => return None,
+            WifiBand::Ghz2_4 => (2412..=2472).step_by(5).chain([2484]).collect(),
+            // UNII-1 to UNII-3, channels 36-64, 100-144 and 149-177
+            WifiBand::Ghz5 => (5180..=5320)
+                .step_by(20)
+                .chain((5500..=5720).step_by(20))
+                .chain((5745..=5885).step_by(20))
+                .collect(),
+        };
+        Some(
+            frequencies
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -193,6 +379,15 @@ pub enum Ipv6Scope {
     Unknown,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route {
+    pub destination: String,
+    pub gateway: Option<String>,
+    pub protocol: Option<String>,
+    pub metric: Option<u32>,
+    pub ipv6: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ipv6Config {
     pub enable_ipv6: bool,
@@ -214,7 +409,43 @@ pub struct WireGuardConfig {
     pub dns: Vec<String>,
     pub mtu: Option<u16>,
     pub peers: Vec<WireGuardPeer>,
-    pub auto_connect: bool,
+    pub auto_connect: VpnAutoConnect,
+}
+
+/// When lantern's monitor brings a tunnel up. Conditional policies also take
+/// it down again once their condition stops holding; `Never` leaves the
+/// tunnel entirely to the user (and to networkd at boot).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VpnAutoConnect {
+    #[default]
+    Never,
+    /// Keep up from startup on
+    Always,
+    /// Up while on WiFi that `[trusted]` does not list
+    UntrustedWifi,
+    /// Up while the named WiFi or wired profile is active
+    Profile(String),
+}
+
+impl std::str::FromStr for VpnAutoConnect {
+    type Err = anyhow::Error;
+
+    /// Parse the CLI form: never, always, untrusted-wifi or profile:<NAME>
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "never" => Ok(Self::Never),
+            "always" => Ok(Self::Always),
+            "untrusted-wifi" => Ok(Self::UntrustedWifi),
+            _ => match s.strip_prefix("profile:") {
+                Some(name) if !name.is_empty() => Ok(Self::Profile(name.to_string())),
+                _ => Err(anyhow::anyhow!(
+                    "Unknown policy '{}' (expected never, always, untrusted-wifi or profile:<NAME>)",
+                    s
+                )),
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -246,6 +477,16 @@ pub struct WireGuardPeerStatus {
     pub transfer_rx: u64,
     pub transfer_tx: u64,
     pub persistent_keepalive: Option<u16>,
+    #[serde(default)]
+    pub rtt: Option<std::time::Duration>, // Measured through the tunnel, if probed
+}
+
+/// A peer moved to a new address after its endpoint hostname re-resolved.
+#[derive(Debug, Clone)]
+pub struct EndpointChange {
+    pub interface: String,
+    pub endpoint: String,
+    pub address: std::net::SocketAddr,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -262,28 +503,61 @@ pub struct HotspotConfig {
     pub channel: u32,
     pub ip_range: String, // e.g., "192.168.4.0/24"
     pub gateway: String,  // e.g., "192.168.4.1"
+    #[serde(default)]
+    pub reservations: Vec<DhcpReservation>,
+    #[serde(default)]
+    pub port_forwards: Vec<PortForward>,
+    /// Run from systemd units that start at boot rather than /tmp configs
+    #[serde(default)]
+    pub persistent: bool,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct NetworkManager {
     iwd_manager: IwdManager,
+    demo: Option<Arc<Mutex<Simulation>>>, // --demo: nothing touches the system
+    capabilities: Arc<Mutex<CapabilityCache>>,
 }
 
 impl NetworkManager {
     pub fn new() -> Self {
         Self {
             iwd_manager: IwdManager::new(),
+            demo: None,
+            capabilities: Arc::default(),
+        }
+    }
+
+    /// Backed by made-up interfaces and networks, for `lantern --demo`.
+    pub fn demo() -> Self {
+        Self {
+            iwd_manager: IwdManager::new(),
+            demo: Some(Arc::new(Mutex::new(Simulation::new()))),
+            capabilities: Arc::default(),
         }
     }
 
+    pub fn is_demo(&self) -> bool {
+        self.demo.is_some()
+    }
+
+    fn simulation(&self) -> Option<MutexGuard<'_, Simulation>> {
+        let demo = self.demo.as_ref()?;
+        Some(demo.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
     pub async fn init_iwd(&mut self) -> Result<()> {
         self.iwd_manager.connect().await
     }
 
     pub async fn get_interfaces(&self) -> Result<Vec<Interface>> {
+        if let Some(mut simulation) = self.simulation() {
+            return Ok(simulation.interfaces());
+        }
         let output = Command::new("/usr/bin/ip")
             .args(&["-j", "addr", "show"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to execute 'ip addr show' command")?;
 
         if !output.status.success() {
@@ -299,7 +573,14 @@ impl NetworkManager {
         let interfaces_data: Vec<serde_json::Value> = serde_json::from_str(&json_str)
             .context("Failed to parse network interface JSON data")?;
 
+        let links = crate::netlink::dump_links().ok();
+        let names: HashMap<u32, String> = links
+            .iter()
+            .flatten()
+            .map(|(name, link)| (link.index, name.clone()))
+            .collect();
         let mut interfaces = Vec::new();
+        let mut present = HashSet::new();
 
         for iface_data in interfaces_data {
             // Skip loopback
@@ -337,19 +618,47 @@ impl NetworkManager {
             let gateway = self.get_gateway(&name).await?;
             let ipv6_gateway = self.get_ipv6_gateway(&name).await?;
             let dns_servers = self.get_dns_servers().await?;
-            let stats = self.get_interface_stats(&name).await?;
-            // Skip slow WiFi info gathering at startup - do it lazily when needed
-            let wifi_info = if self.is_wireless_interface(&name).await? {
-                Some(WifiInfo {
-                    current_network: None,
-                    signal_strength: None,
-                    frequency: None,
-                    channel: None,
-                })
-            } else {
-                None
+            let link = links.as_ref().and_then(|links| links.get(&name));
+            let stats = match link {
+                Some(link) => link.stats.clone(),
+                None => self.get_interface_stats(&name).await?,
             };
+            let carrier = link.and_then(|link| link.carrier);
+            let carrier_changes = link.and_then(|link| link.carrier_changes);
+            let has_flag = |wanted: &str| {
+                iface_data["flags"]
+                    .as_array()
+                    .is_some_and(|flags| flags.iter().any(|flag| flag == wanted))
+            };
+            let admin_up = has_flag("UP");
+            let promiscuous = has_flag("PROMISC");
+            let monitor = iface_data["link_type"] == "ieee802.11/radiotap";
+            let capabilities = match iface_data["ifindex"].as_u64() {
+                Some(ifindex) => {
+                    present.insert(ifindex);
+                    self.capabilities_of(ifindex, &name)
+                }
+                None => Capabilities::default(),
+            };
+            // Skip slow WiFi info gathering at startup - do it lazily when needed
+            let wifi_info = capabilities.wireless.then_some(WifiInfo {
+                current_network: None,
+                signal_strength: None,
+                frequency: None,
+                channel: None,
+            });
             let ipv6_info = self.get_ipv6_info(&name).await?;
+            let hardware = capabilities.hardware;
+            let kind = link
+                .and_then(|link| link.kind.as_deref())
+                .map(LinkKind::from_kind)
+                .unwrap_or(capabilities.kind);
+            let master = link.and_then(|link| names.get(&link.master?).cloned());
+            let peer_netns = link.and_then(|link| link.link_netnsid);
+            // An index in another namespace means nothing here
+            let lower = link
+                .filter(|link| link.link_netnsid.is_none() && link.link != Some(link.index))
+                .and_then(|link| names.get(&link.link?).cloned());
 
             interfaces.push(Interface {
                 name,
@@ -364,16 +673,52 @@ impl NetworkManager {
                 dns_servers,
                 stats,
                 wifi_info,
+                hardware,
+                admin_up,
+                carrier,
+                carrier_changes,
+                promiscuous,
+                monitor,
+                kind,
+                master,
+                lower,
+                peer_netns,
             });
         }
 
+        self.lock_capabilities().retain(&present);
         Ok(interfaces)
     }
 
+    fn lock_capabilities(&self) -> MutexGuard<'_, CapabilityCache> {
+        self.capabilities.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // From the cache when the interface has been listed already
+    fn phy_of(&self, interface: &str) -> Option<String> {
+        match self.lock_capabilities().by_name(interface) {
+            Some(capabilities) => capabilities.phy.clone(),
+            None => wireless_phy(interface),
+        }
+    }
+
+    /// Wireless or not, phy and hardware, read from sysfs and udev the first
+    /// time an ifindex shows up rather than on every refresh.
+    fn capabilities_of(&self, ifindex: u64, name: &str) -> Capabilities {
+        self.lock_capabilities()
+            .get_or_probe(ifindex, name, || Capabilities {
+                wireless: Path::new(&format!("/sys/class/net/{}/wireless", name)).exists(),
+                phy: wireless_phy(name),
+                hardware: get_nic_hardware(name, ifindex),
+                kind: crate::link_kind::probe(name),
+            })
+    }
+
     async fn get_gateway(&self, interface: &str) -> Result<Option<String>> {
         let output = Command::new("/usr/bin/ip")
             .args(&["-j", "route", "show", "default", "dev", interface])
-            .output()?;
+            .timed_output()
+            .await?;
 
         let json_str = String::from_utf8_lossy(&output.stdout);
         if json_str.trim().is_empty() {
@@ -391,31 +736,46 @@ impl NetworkManager {
         Ok(None)
     }
 
-    async fn get_dns_servers(&self) -> Result<Vec<String>> {
-        let output = Command::new("/usr/bin/resolvectl").arg("status").output()?;
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let mut dns_servers = Vec::new();
-        let mut in_global = false;
-
-        for line in output_str.lines() {
-            if line.contains("Global") {
-                in_global = true;
-            } else if line.contains("Link") && line.contains("(") {
-                in_global = false;
-            } else if in_global && line.contains("DNS Servers:") {
-                if let Some(server) = line.split(':').nth(1) {
-                    dns_servers.push(server.trim().to_string());
-                }
-            } else if in_global && !line.contains(':') && !line.trim().is_empty() {
-                let trimmed = line.trim();
-                if trimmed.parse::<std::net::IpAddr>().is_ok() {
-                    dns_servers.push(trimmed.to_string());
-                }
+    /// IPv4 and IPv6 routes going out through an interface.
+    pub async fn get_routes(&self, interface: &str) -> Result<Vec<Route>> {
+        if let Some(simulation) = self.simulation() {
+            return Ok(simulation.routes(interface));
+        }
+        crate::sanitize::interface_name(interface)?;
+
+        let mut routes = Vec::new();
+        for family in ["-4", "-6"] {
+            let output = Command::new("/usr/bin/ip")
+                .args([family, "-j", "route", "show", "dev", interface])
+                .timed_output()
+                .await?;
+
+            if !output.status.success() {
+                continue;
             }
+            routes.extend(parse_routes(
+                &String::from_utf8_lossy(&output.stdout),
+                family == "-6",
+            )?);
         }
 
-        Ok(dns_servers)
+        Ok(routes)
+    }
+
+    async fn get_dns_servers(&self) -> Result<Vec<String>> {
+        // Without systemd-resolved there is simply nothing to show
+        let Ok(output) = Command::new("/usr/bin/resolvectl")
+            .arg("status")
+            .timed_output()
+            .await
+        else {
+            return Ok(vec![]);
+        };
+
+        Ok(parse_resolvectl_dns(
+            &String::from_utf8_lossy(&output.stdout),
+            true,
+        ))
     }
 
     async fn get_interface_stats(&self, interface: &str) -> Result<InterfaceStats> {
@@ -465,28 +825,276 @@ impl NetworkManager {
     }
 
     pub async fn set_interface_state(&self, interface: &str, state: &str) -> Result<()> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.set_state(interface, state == "up");
+        }
         Command::new("/usr/bin/ip")
             .args(&["link", "set", interface, state])
-            .output()?;
+            .timed_output()
+            .await?;
+        Ok(())
+    }
+
+    /// Restart the interface's DHCP client, e.g. after it fell back to a
+    /// link-local address
+    pub async fn retry_dhcp(&self, interface: &str) -> Result<()> {
+        if let Some(simulation) = self.simulation() {
+            return simulation.retry_dhcp(interface);
+        }
+        crate::linklocal::retry_dhcp(interface).await
+    }
+
+    pub async fn set_promiscuous(&self, interface: &str, on: bool) -> Result<()> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.set_promiscuous(interface, on);
+        }
+        crate::sanitize::interface_name(interface)?;
+        let output = Command::new("/usr/bin/ip")
+            .args([
+                "link",
+                "set",
+                "dev",
+                interface,
+                "promisc",
+                if on { "on" } else { "off" },
+            ])
+            .timed_output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Failed to set promiscuous mode: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Blink the port LED of a NIC for `seconds`, to find it in a rack.
+    /// Returns once the blinking stops.
+    pub async fn identify(&self, interface: &str, seconds: u64) -> Result<()> {
+        if self.simulation().is_some() {
+            tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+            return Ok(());
+        }
+        crate::sanitize::interface_name(interface)?;
+        let output = Command::new("/usr/sbin/ethtool")
+            .args(["--identify", interface, &seconds.to_string()])
+            .timed_output_with(std::time::Duration::from_secs(seconds + 5))
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // Virtual interfaces, most WiFi and many USB NICs have no LED to drive
+            if stderr.contains("Operation not supported") {
+                return Err(anyhow::anyhow!(
+                    "{}'s driver can't blink its port LED",
+                    interface
+                ));
+            }
+            return Err(anyhow::anyhow!(
+                "Failed to identify {}: {}",
+                interface,
+                stderr.trim()
+            ));
+        }
         Ok(())
     }
 
+    /// Add a monitor interface next to a wireless one, or remove it again.
+    /// Works from either the managed interface or the monitor one; returns
+    /// the monitor interface and whether it was created.
+    pub async fn toggle_monitor_interface(
+        &self,
+        interface: &str,
+        existing: &[Interface],
+    ) -> Result<(String, bool)> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.toggle_monitor(interface);
+        }
+        crate::sanitize::interface_name(interface)?;
+        let phy = self
+            .phy_of(interface)
+            .ok_or_else(|| anyhow::anyhow!("{} is not a wireless interface", interface))?;
+        let monitor = existing
+            .iter()
+            .find(|iface| iface.monitor && self.phy_of(&iface.name).as_ref() == Some(&phy));
+
+        if let Some(monitor) = monitor {
+            let output = Command::new(crate::command::IW)
+                .args(["dev", &monitor.name, "del"])
+                .timed_output()
+                .await?;
+            if !output.status.success() {
+                return Err(anyhow::anyhow!(
+                    "Failed to remove {}: {}",
+                    monitor.name,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            return Ok((monitor.name.clone(), false));
+        }
+
+        let names: Vec<&str> = existing.iter().map(|iface| iface.name.as_str()).collect();
+        let name = free_monitor_name(&names);
+        let output = Command::new(crate::command::IW)
+            .args([
+                "dev",
+                interface,
+                "interface",
+                "add",
+                &name,
+                "type",
+                "monitor",
+            ])
+            .timed_output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Failed to create monitor interface: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        self.set_interface_state(&name, "up").await?;
+        Ok((name, true))
+    }
+
+    /// A monitor interface to listen with: `interface` itself if it is one,
+    /// one already on its radio, or a new one. Returns it and whether it was
+    /// created, so it can be removed again afterwards.
+    pub async fn open_rf_monitor(
+        &self,
+        interface: &str,
+        existing: &[Interface],
+    ) -> Result<(String, bool)> {
+        let same_radio = |other: &Interface| match self.is_demo() {
+            true => true,
+            false => {
+                self.phy_of(&other.name).is_some()
+                    && self.phy_of(&other.name) == self.phy_of(interface)
+            }
+        };
+        if let Some(monitor) = existing
+            .iter()
+            .find(|iface| iface.monitor && (iface.name == interface || same_radio(iface)))
+        {
+            return Ok((monitor.name.clone(), false));
+        }
+        self.toggle_monitor_interface(interface, existing).await
+    }
+
+    /// Listen on `monitor` until `stop` is set or `on_sighting` returns false.
+    /// Blocks, so it runs on a thread of its own.
+    pub fn rf_capture(
+        &self,
+        monitor: &str,
+        hop: bool,
+        stop: &std::sync::atomic::AtomicBool,
+        mut on_sighting: impl FnMut(crate::rfmon::Sighting) -> bool,
+    ) -> Result<()> {
+        if self.is_demo() {
+            for sighting in crate::demo::rf_sightings() {
+                std::thread::sleep(std::time::Duration::from_millis(400));
+                if stop.load(std::sync::atomic::Ordering::Relaxed) || !on_sighting(sighting) {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+        crate::rfmon::capture(monitor, hop, stop, on_sighting)
+    }
+
+    /// Summarize packets on `interface` until `stop` is set or `on_sample`
+    /// returns false. Blocks, so it runs on a thread of its own.
+    pub fn packet_capture(
+        &self,
+        interface: &str,
+        stop: &std::sync::atomic::AtomicBool,
+        mut on_sample: impl FnMut(crate::packets::Sample) -> bool,
+    ) -> Result<()> {
+        if self.is_demo() {
+            let (mut seen, mut bytes) = (0, 0);
+            for packet in crate::demo::packets().into_iter().cycle() {
+                std::thread::sleep(std::time::Duration::from_millis(300));
+                seen += 1;
+                bytes += packet.length as u64;
+                let sample = crate::packets::Sample {
+                    packet: Some(packet),
+                    seen,
+                    bytes,
+                };
+                if stop.load(std::sync::atomic::Ordering::Relaxed) || !on_sample(sample) {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+        crate::packets::capture(interface, stop, on_sample)
+    }
+
+    /// MAC address of a neighbour (e.g. the gateway) from the ARP/NDP cache.
+    pub async fn get_neighbor_mac(&self, interface: &str, address: &str) -> Option<String> {
+        if self.is_demo() {
+            return None;
+        }
+        let output = Command::new("/usr/bin/ip")
+            .args(["-j", "neigh", "show", address, "dev", interface])
+            .timed_output()
+            .await
+            .ok()?;
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).ok()?;
+        entries
+            .iter()
+            .find_map(|entry| entry["lladdr"].as_str().map(String::from))
+    }
+
     pub async fn add_ip_address(&self, interface: &str, ip_with_prefix: &str) -> Result<()> {
-        Command::new("/usr/bin/ip")
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.add_address(interface, ip_with_prefix);
+        }
+        crate::sanitize::interface_name(interface)?;
+        crate::sanitize::argument(ip_with_prefix)?;
+
+        let output = Command::new("/usr/bin/ip")
             .args(&["addr", "add", ip_with_prefix, "dev", interface])
-            .output()?;
+            .timed_output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Failed to add {} on {}: {}",
+                ip_with_prefix,
+                interface,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
         Ok(())
     }
 
     pub async fn remove_ip_address(&self, interface: &str, ip_with_prefix: &str) -> Result<()> {
-        Command::new("/usr/bin/ip")
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.remove_address(interface, ip_with_prefix);
+        }
+        crate::sanitize::interface_name(interface)?;
+        crate::sanitize::argument(ip_with_prefix)?;
+
+        let output = Command::new("/usr/bin/ip")
             .args(&["addr", "del", ip_with_prefix, "dev", interface])
-            .output()?;
+            .timed_output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Failed to remove {} on {}: {}",
+                ip_with_prefix,
+                interface,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
         Ok(())
     }
 
     // WiFi-specific methods using systemd-networkd approach
     pub async fn get_wifi_info(&self, interface: &str) -> Result<Option<WifiInfo>> {
+        if let Some(mut simulation) = self.simulation() {
+            return Ok(simulation.wifi_info(interface));
+        }
         // Check if this is a wireless interface
         if !self.is_wireless_interface(interface).await? {
             return Ok(None);
@@ -514,40 +1122,45 @@ impl NetworkManager {
         if let Ok(Some(iwd_network)) = self.iwd_manager.get_connected_network(interface).await {
             return Ok(Some(WifiNetwork {
                 ssid: iwd_network.name,
-                bssid: "Unknown".to_string(), // iwd doesn't expose BSSID easily
+                bssid: iwd_network.bssid,
                 signal_strength: iwd_network.signal_strength as i32,
-                frequency: 0, // We'll need to get this separately if needed
-                channel: 0,
+                frequency: iwd_network.frequency,
+                channel: iwd_network
+                    .channel
+                    .unwrap_or_else(|| frequency_to_channel(iwd_network.frequency)),
                 connected: iwd_network.connected,
                 security: self.parse_iwd_security_type(&iwd_network.security_type),
-                encryption: vec![iwd_network.security_type],
+                encryption: iwd_network.security_details.ciphers(),
+                security_details: iwd_network.security_details,
                 in_history: false, // Will be set later by caller
+                access_points: Vec::new(),
             }));
         }
 
         // Fallback to legacy iw method
-        let output = match Command::new("/usr/bin/iw")
+        Ok(self.get_iw_link(interface).await)
+    }
+
+    /// The network `iw dev <iface> link` reports the interface associated with.
+    async fn get_iw_link(&self, interface: &str) -> Option<WifiNetwork> {
+        // Neither iwd nor iw available if this fails
+        let output = Command::new(crate::command::IW)
             .args(&["dev", interface, "link"])
-            .output()
-        {
-            Ok(output) => output,
-            Err(_) => {
-                // Neither iwd nor iw available
-                return Ok(None);
-            }
-        };
+            .timed_output()
+            .await
+            .ok()?;
 
         if !output.status.success() {
-            return Ok(None);
+            return None;
         }
 
         let link_info = String::from_utf8_lossy(&output.stdout);
 
         if link_info.contains("Not connected") {
-            return Ok(None);
+            return None;
         }
 
-        self.parse_iw_link_info(&link_info)
+        parse_iw_link_info(&link_info)
     }
 
     fn parse_iwd_security_type(&self, security_type: &str) -> WifiSecurity {
@@ -561,55 +1174,11 @@ impl NetworkManager {
         }
     }
 
-    fn parse_iw_link_info(&self, link_info: &str) -> Result<Option<WifiNetwork>> {
-        let mut ssid = String::new();
-        let mut bssid = String::new();
-        let mut frequency = 0u32;
-        let mut signal = 0i32;
-
-        for line in link_info.lines() {
-            let line = line.trim();
-            if line.starts_with("SSID:") {
-                ssid = line.strip_prefix("SSID:").unwrap_or("").trim().to_string();
-            } else if line.starts_with("Connected to") {
-                bssid = line.split_whitespace().nth(2).unwrap_or("").to_string();
-            } else if line.contains("freq:") {
-                if let Some(freq_str) = line.split("freq:").nth(1) {
-                    if let Some(freq_part) = freq_str.split_whitespace().next() {
-                        frequency = freq_part.parse().unwrap_or(0);
-                    }
-                }
-            } else if line.contains("signal:") {
-                if let Some(signal_str) = line.split("signal:").nth(1) {
-                    if let Some(signal_part) = signal_str.split_whitespace().next() {
-                        signal = signal_part.parse().unwrap_or(0);
-                    }
-                }
-            }
-        }
-
-        if !ssid.is_empty() {
-            let channel = self.frequency_to_channel(frequency);
-            Ok(Some(WifiNetwork {
-                ssid,
-                bssid,
-                signal_strength: signal,
-                frequency,
-                channel,
-                security: WifiSecurity::WPA2, // Will be enhanced with proper detection
-                encryption: vec!["WPA2".to_string()],
-                connected: false,  // This would need to be determined separately
-                in_history: false, // Will be set later by caller
-            }))
-        } else {
-            Ok(None)
-        }
-    }
-
     async fn get_signal_strength(&self, interface: &str) -> Result<Option<i32>> {
-        let output = match Command::new("/usr/bin/iw")
+        let output = match Command::new(crate::command::IW)
             .args(&["dev", interface, "link"])
-            .output()
+            .timed_output()
+            .await
         {
             Ok(output) => output,
             Err(_) => return Ok(None),
@@ -635,9 +1204,10 @@ impl NetworkManager {
     }
 
     async fn get_frequency_info(&self, interface: &str) -> Result<(Option<u32>, Option<u32>)> {
-        let output = match Command::new("/usr/bin/iw")
+        let output = match Command::new(crate::command::IW)
             .args(&["dev", interface, "link"])
-            .output()
+            .timed_output()
+            .await
         {
             Ok(output) => output,
             Err(_) => return Ok((None, None)),
@@ -654,7 +1224,7 @@ impl NetworkManager {
                 if let Some(freq_str) = line.split("freq:").nth(1) {
                     if let Some(freq_part) = freq_str.split_whitespace().next() {
                         if let Ok(frequency) = freq_part.parse::<u32>() {
-                            let channel = self.frequency_to_channel(frequency);
+                            let channel = frequency_to_channel(frequency);
                             return Ok((Some(frequency), Some(channel)));
                         }
                     }
@@ -666,6 +1236,22 @@ impl NetworkManager {
     }
 
     pub async fn scan_wifi_networks(&self, interface: &str) -> Result<Vec<WifiNetwork>> {
+        let mut networks = self.scan_wifi_backend(interface).await?;
+
+        // Scan results don't always flag the network we're associated with
+        if !networks.iter().any(|n| n.connected) {
+            if let Some(link) = self.get_iw_link(interface).await {
+                mark_connected(&mut networks, &link);
+            }
+        }
+
+        Ok(group_by_ssid(networks))
+    }
+
+    async fn scan_wifi_backend(&self, interface: &str) -> Result<Vec<WifiNetwork>> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.scan(interface);
+        }
         // Check if interface exists and is wireless
         if !self.is_wireless_interface(interface).await? {
             return Err(NetworkError::WiFiError {
@@ -680,21 +1266,28 @@ impl NetworkManager {
             for iwd_net in iwd_networks {
                 wifi_networks.push(WifiNetwork {
                     ssid: iwd_net.name,
-                    bssid: "Unknown".to_string(),
+                    bssid: iwd_net.bssid,
                     signal_strength: iwd_net.signal_strength as i32,
-                    frequency: 0, // iwd doesn't expose this easily
-                    channel: 0,   // Will be calculated from frequency if available
+                    frequency: iwd_net.frequency,
+                    channel: iwd_net
+                        .channel
+                        .unwrap_or_else(|| frequency_to_channel(iwd_net.frequency)),
                     connected: iwd_net.connected,
                     security: self.parse_iwd_security_type(&iwd_net.security_type),
-                    encryption: vec![iwd_net.security_type],
+                    encryption: iwd_net.security_details.ciphers(),
+                    security_details: iwd_net.security_details,
                     in_history: false, // Will be set later by caller
+                    access_points: Vec::new(),
                 });
             }
             return Ok(wifi_networks);
         }
 
         // Fallback to legacy iw method
-        let iw_check = Command::new("/usr/bin/which").args(&["iw"]).output();
+        let iw_check = Command::new("/usr/bin/which")
+            .args(&["iw"])
+            .timed_output()
+            .await;
         if iw_check.is_err() || !iw_check.unwrap().status.success() {
             return Err(NetworkError::ResourceUnavailable {
                 resource: "Neither iwd nor iw wireless tools available".to_string(),
@@ -703,11 +1296,13 @@ impl NetworkManager {
         }
 
         // Perform WiFi scan with iw
-        let output = match Command::new("/usr/bin/iw")
+        let output = match Command::new(crate::command::IW)
             .args(&["dev", interface, "scan"])
-            .output()
+            .timed_output_with(crate::command::scan_timeout())
+            .await
         {
             Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => return Err(e.into()),
             Err(_) => return Ok(Vec::new()),
         };
 
@@ -720,108 +1315,7 @@ impl NetworkManager {
         }
 
         let scan_results = String::from_utf8_lossy(&output.stdout);
-        self.parse_wifi_scan_results(&scan_results)
-    }
-
-    fn parse_wifi_scan_results(&self, scan_output: &str) -> Result<Vec<WifiNetwork>> {
-        let mut networks = Vec::new();
-        let mut current_bssid = String::new();
-        let mut current_frequency = 0u32;
-        let mut current_signal = 0i32;
-        let mut current_ssid = String::new();
-        let mut current_security = WifiSecurity::Open;
-        let mut current_encryption = Vec::new();
-
-        for line in scan_output.lines() {
-            let line = line.trim();
-
-            if line.starts_with("BSS ") {
-                // Save previous network if exists
-                if !current_bssid.is_empty() && !current_ssid.is_empty() {
-                    let channel = self.frequency_to_channel(current_frequency);
-                    networks.push(WifiNetwork {
-                        ssid: current_ssid.clone(),
-                        bssid: current_bssid.clone(),
-                        signal_strength: current_signal,
-                        frequency: current_frequency,
-                        channel,
-                        security: current_security.clone(),
-                        encryption: current_encryption.clone(),
-                        connected: false, // Legacy scan doesn't provide connection status
-                        in_history: false, // Will be set later by caller
-                    });
-                }
-
-                // Start new network
-                current_bssid = line.split_whitespace().nth(1).unwrap_or("").to_string();
-                if current_bssid.ends_with('(') {
-                    current_bssid.pop();
-                }
-                current_ssid.clear();
-                current_security = WifiSecurity::Open;
-                current_encryption.clear();
-                current_frequency = 0;
-                current_signal = 0;
-            } else if line.starts_with("freq:") {
-                current_frequency = line
-                    .strip_prefix("freq:")
-                    .unwrap_or("0")
-                    .trim()
-                    .parse()
-                    .unwrap_or(0);
-            } else if line.starts_with("signal:") {
-                let signal_str = line.strip_prefix("signal:").unwrap_or("0").trim();
-                // Parse signal like "-45.00 dBm"
-                current_signal = signal_str
-                    .split('.')
-                    .next()
-                    .unwrap_or("0")
-                    .parse()
-                    .unwrap_or(0);
-            } else if line.starts_with("SSID:") {
-                current_ssid = line.strip_prefix("SSID:").unwrap_or("").trim().to_string();
-            } else if line.contains("Privacy") {
-                current_security = WifiSecurity::WEP;
-                current_encryption.push("WEP".to_string());
-            } else if line.contains("WPA2") {
-                current_security = WifiSecurity::WPA2;
-                current_encryption.push("WPA2".to_string());
-            } else if line.contains("WPA3") {
-                current_security = WifiSecurity::WPA3;
-                current_encryption.push("WPA3".to_string());
-            } else if line.contains("WPA:") && !line.contains("WPA2") && !line.contains("WPA3") {
-                current_security = WifiSecurity::WPA;
-                current_encryption.push("WPA".to_string());
-            } else if line.contains("IEEE 802.1X")
-                || line.contains("EAP")
-                || line.contains("Enterprise")
-            {
-                current_security = WifiSecurity::Enterprise;
-                current_encryption.push("Enterprise".to_string());
-            }
-        }
-
-        // Don't forget the last network
-        if !current_bssid.is_empty() && !current_ssid.is_empty() {
-            let channel = self.frequency_to_channel(current_frequency);
-            networks.push(WifiNetwork {
-                ssid: current_ssid,
-                bssid: current_bssid,
-                signal_strength: current_signal,
-                frequency: current_frequency,
-                channel,
-                security: current_security,
-                encryption: current_encryption,
-                connected: false,  // Legacy scan doesn't provide connection status
-                in_history: false, // Will be set later by caller
-            });
-        }
-
-        // Remove duplicates and sort by signal strength
-        networks.sort_by(|a, b| b.signal_strength.cmp(&a.signal_strength));
-        networks.dedup_by(|a, b| a.ssid == b.ssid);
-
-        Ok(networks)
+        Ok(parse_wifi_scan_results(&scan_results))
     }
 
     pub async fn connect_to_wifi(
@@ -833,17 +1327,32 @@ impl NetworkManager {
         gateway: Option<String>,
         dns: Option<Vec<String>>,
     ) -> Result<()> {
-        // Try iwd first (modern approach)
-        if let Ok(_) = self
-            .iwd_manager
-            .connect_to_network(
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.connect_wifi(
                 interface,
                 &credentials.ssid,
                 credentials.password.as_deref(),
-            )
+            );
+        }
+
+        // Try iwd first (modern approach). It only knows band preferences
+        // machine-wide, so a band lock that can't be set there goes through
+        // wpa_supplicant's freq_list instead.
+        let iwd_band_set = self.iwd_manager.lock_band(credentials.band).await.is_ok();
+        if iwd_band_set
+            && retry(RetryPolicy::IWD_CONNECT, || {
+                self.iwd_manager.connect_to_network(
+                    interface,
+                    &credentials.ssid,
+                    credentials.password.as_deref(),
+                )
+            })
             .await
+            .is_ok()
         {
             // Connection successful with iwd
+            self.apply_saved_tx_power(interface, credentials).await;
+            self.apply_extras(interface, &credentials.extras()).await;
             return Ok(());
         }
 
@@ -856,26 +1365,148 @@ impl NetworkManager {
 
         // Restart the interface to apply configuration
         self.set_interface_state(interface, "down").await?;
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        self.wait_for_operstate(interface, |state| state == "down")
+            .await;
         self.set_interface_state(interface, "up").await?;
+        self.apply_saved_tx_power(interface, credentials).await;
 
         Ok(())
     }
 
-    pub async fn disconnect_wifi(&self, interface: &str) -> Result<()> {
-        // Try iwd first (modern approach)
-        if let Ok(_) = self.iwd_manager.disconnect_device(interface).await {
-            return Ok(());
+    // A power the driver refuses shouldn't undo a connection that worked
+    async fn apply_saved_tx_power(&self, interface: &str, credentials: &WifiCredentials) {
+        if let Some(dbm) = credentials.tx_power_dbm {
+            if let Err(e) = self.set_tx_power(interface, Some(dbm)).await {
+                eprintln!(
+                    "Warning: Failed to set {} dBm on {}: {:#}",
+                    dbm, interface, e
+                );
+            }
         }
-
-        // Fallback to legacy wpa_supplicant approach
-        let systemd_config = crate::systemd::SystemdNetworkConfig::new();
-        systemd_config.disconnect_wifi(interface).await?;
-
-        // Bring interface down
-        self.set_interface_state(interface, "down").await?;
-
-        Ok(())
+    }
+
+    // iwd writes no .network file, so a profile's routes and search domains
+    // are set on the live link; like the power, failing here only warns
+    async fn apply_extras(&self, interface: &str, extras: &NetworkExtras) {
+        if extras.is_empty() || self.simulation().is_some() {
+            return;
+        }
+        for route in &extras.routes {
+            let mut args = vec!["route", "replace", route.destination.as_str()];
+            if let Some(gateway) = &route.gateway {
+                args.extend(["via", gateway.as_str()]);
+            }
+            let metric = route.metric.map(|metric| metric.to_string());
+            if let Some(metric) = &metric {
+                args.extend(["metric", metric.as_str()]);
+            }
+            args.extend(["dev", interface]);
+            if let Err(e) = run_checked("/usr/bin/ip", &args).await {
+                eprintln!(
+                    "Warning: Failed to add route {} on {}: {:#}",
+                    route.destination, interface, e
+                );
+            }
+        }
+        if !extras.domains.is_empty() {
+            let mut args = vec!["domain", interface];
+            args.extend(extras.domains.iter().map(String::as_str));
+            if let Err(e) = run_checked("/usr/bin/resolvectl", &args).await {
+                eprintln!(
+                    "Warning: Failed to set search domains on {}: {:#}",
+                    interface, e
+                );
+            }
+        }
+    }
+
+    /// The transmit power the driver reports, in whole dBm
+    pub async fn get_tx_power(&self, interface: &str) -> Option<i32> {
+        let output = Command::new(crate::command::IW)
+            .args(["dev", interface, "info"])
+            .timed_output()
+            .await
+            .ok()?;
+        parse_tx_power(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Fix the transmit power at `dbm`, or hand it back to the driver with None
+    pub async fn set_tx_power(&self, interface: &str, dbm: Option<i32>) -> Result<()> {
+        if let Some(mut simulation) = self.simulation() {
+            simulation.set_tx_power(dbm);
+            return Ok(());
+        }
+        // iw takes mBm, hundredths of a dBm
+        let level = dbm.map(|dbm| (dbm * 100).to_string());
+        let mut args = vec!["dev", interface, "set", "txpower"];
+        match &level {
+            Some(level) => args.extend(["fixed", level.as_str()]),
+            None => args.push("auto"),
+        }
+        let output = Command::new(crate::command::IW)
+            .args(&args)
+            .timed_output()
+            .await
+            .context("Failed to run iw")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "{}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Wait with backoff until the interface has an IPv4 address, returning it.
+    /// Used after connecting with DHCP to confirm a lease was actually obtained.
+    pub async fn wait_for_dhcp_lease(&self, interface: &str) -> Option<String> {
+        poll_until(RetryPolicy::DHCP_LEASE, || async {
+            let output = Command::new("/usr/bin/ip")
+                .args(&["-j", "-4", "addr", "show", "dev", interface])
+                .timed_output()
+                .await
+                .ok()?;
+            let addrs: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).ok()?;
+            addrs
+                .first()?
+                .get("addr_info")?
+                .as_array()?
+                .iter()
+                .find_map(|info| info["local"].as_str().map(str::to_string))
+        })
+        .await
+    }
+
+    // Wait for the kernel to report a link state, giving up quietly after the policy
+    async fn wait_for_operstate(&self, interface: &str, ready: impl Fn(&str) -> bool) -> bool {
+        let path = format!("/sys/class/net/{}/operstate", interface);
+        poll_until(RetryPolicy::LINK_STATE, || async {
+            fs::read_to_string(&path)
+                .ok()
+                .filter(|state| ready(state.trim()))
+        })
+        .await
+        .is_some()
+    }
+
+    pub async fn disconnect_wifi(&self, interface: &str) -> Result<()> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.disconnect_wifi(interface);
+        }
+
+        // Try iwd first (modern approach)
+        if let Ok(_) = self.iwd_manager.disconnect_device(interface).await {
+            return Ok(());
+        }
+
+        // Fallback to legacy wpa_supplicant approach
+        let systemd_config = crate::systemd::SystemdNetworkConfig::new();
+        systemd_config.disconnect_wifi(interface).await?;
+
+        // Bring interface down
+        self.set_interface_state(interface, "down").await?;
+
+        Ok(())
     }
 
     // IPv6-specific methods
@@ -903,7 +1534,8 @@ impl NetworkManager {
     async fn get_detailed_ipv6_addresses(&self, interface: &str) -> Result<Vec<Ipv6Address>> {
         let output = Command::new("/usr/bin/ip")
             .args(&["-6", "-j", "addr", "show", interface])
-            .output()?;
+            .timed_output()
+            .await?;
 
         if !output.status.success() {
             return Ok(vec![]);
@@ -966,7 +1598,8 @@ impl NetworkManager {
     async fn get_ipv6_gateway(&self, interface: &str) -> Result<Option<String>> {
         let output = Command::new("/usr/bin/ip")
             .args(&["-6", "route", "show", "default", "dev", interface])
-            .output()?;
+            .timed_output()
+            .await?;
 
         if !output.status.success() {
             return Ok(None);
@@ -994,31 +1627,23 @@ impl NetworkManager {
 
     async fn get_ipv6_dns_servers(&self) -> Result<Vec<String>> {
         // Check systemd-resolved for IPv6 DNS servers
-        let output = Command::new("/usr/bin/resolvectl")
+        let Ok(output) = Command::new("/usr/bin/resolvectl")
             .args(&["status"])
-            .output()?;
+            .timed_output()
+            .await
+        else {
+            return Ok(vec![]);
+        };
 
         if !output.status.success() {
             return Ok(vec![]);
         }
 
         let resolve_info = String::from_utf8_lossy(&output.stdout);
-        let mut dns_servers = Vec::new();
-
-        for line in resolve_info.lines() {
-            let line = line.trim();
-            if line.starts_with("DNS Servers:") {
-                let servers = line.strip_prefix("DNS Servers:").unwrap_or("").trim();
-                for server in servers.split_whitespace() {
-                    // Check if it's an IPv6 address (contains colons)
-                    if server.contains(':') && server.parse::<std::net::Ipv6Addr>().is_ok() {
-                        dns_servers.push(server.to_string());
-                    }
-                }
-            }
-        }
-
-        Ok(dns_servers)
+        Ok(parse_resolvectl_dns(&resolve_info, false)
+            .into_iter()
+            .filter(|server| server.contains(':'))
+            .collect())
     }
 
     async fn get_ipv6_settings(&self, interface: &str) -> Result<(bool, bool, bool)> {
@@ -1044,7 +1669,8 @@ impl NetworkManager {
         // Check if DHCPv6 is running (simplified check)
         let output = Command::new("/usr/bin/systemctl")
             .args(&["is-active", "dhcpcd"])
-            .output();
+            .timed_output()
+            .await;
 
         if let Ok(output) = output {
             dhcpv6_enabled = output.status.success();
@@ -1100,64 +1726,17 @@ impl NetworkManager {
 
     // WireGuard methods
     pub async fn generate_wireguard_keys(&self) -> Result<WireGuardKeyPair> {
-        // Check if WireGuard tools are available
-        let wg_check = Command::new("/usr/bin/which").args(&["wg"]).output();
-        if wg_check.is_err() || !wg_check.unwrap().status.success() {
-            return Err(NetworkError::ResourceUnavailable {
-                resource: "WireGuard tools (wg command not found)".to_string(),
+        // Keys are derived in-process so the private key never appears in argv or a shell
+        let private_key =
+            crate::wgkey::generate_private_key().map_err(|e| NetworkError::WireGuardError {
+                details: format!("Key generation failed: {}", e),
+            })?;
+
+        let public_key = crate::wgkey::public_key_from_private(&private_key).map_err(|e| {
+            NetworkError::WireGuardError {
+                details: format!("Public key generation failed: {}", e),
             }
-            .into());
-        }
-
-        // Generate private key
-        let private_output = Command::new("/usr/bin/wg")
-            .args(&["genkey"])
-            .output()
-            .context("Failed to execute 'wg genkey' command")?;
-
-        if !private_output.status.success() {
-            let stderr = String::from_utf8_lossy(&private_output.stderr);
-            return Err(NetworkError::WireGuardError {
-                details: format!("Key generation failed: {}", stderr),
-            }
-            .into());
-        }
-
-        let private_key = String::from_utf8_lossy(&private_output.stdout)
-            .trim()
-            .to_string();
-
-        if private_key.is_empty() {
-            return Err(NetworkError::WireGuardError {
-                details: "Generated private key is empty".to_string(),
-            }
-            .into());
-        }
-
-        // Generate public key from private key using shell pipe
-        let public_output = Command::new("/bin/sh")
-            .args(&["-c", &format!("echo '{}' | wg pubkey", private_key)])
-            .output()
-            .context("Failed to generate WireGuard public key")?;
-
-        if !public_output.status.success() {
-            let stderr = String::from_utf8_lossy(&public_output.stderr);
-            return Err(NetworkError::WireGuardError {
-                details: format!("Public key generation failed: {}", stderr),
-            }
-            .into());
-        }
-
-        let public_key = String::from_utf8_lossy(&public_output.stdout)
-            .trim()
-            .to_string();
-
-        if public_key.is_empty() {
-            return Err(NetworkError::WireGuardError {
-                details: "Generated public key is empty".to_string(),
-            }
-            .into());
-        }
+        })?;
 
         Ok(WireGuardKeyPair {
             private_key,
@@ -1183,7 +1762,8 @@ impl NetworkManager {
         // Remove the interface
         Command::new("/usr/bin/ip")
             .args(&["link", "delete", interface_name])
-            .output()?;
+            .timed_output()
+            .await?;
 
         // Remove systemd configuration
         let systemd_config = crate::systemd::SystemdNetworkConfig::new();
@@ -1198,121 +1778,136 @@ impl NetworkManager {
         &self,
         interface_name: &str,
     ) -> Result<Option<WireGuardStatus>> {
+        if let Some(mut simulation) = self.simulation() {
+            return Ok(simulation.wireguard_status(interface_name));
+        }
         let output = Command::new("/usr/bin/wg")
             .args(&["show", interface_name, "dump"])
-            .output()?;
+            .timed_output()
+            .await?;
 
         if !output.status.success() {
             return Ok(None);
         }
 
         let dump_output = String::from_utf8_lossy(&output.stdout);
-        self.parse_wireguard_dump(&dump_output, interface_name)
+        Ok(parse_wireguard_dump(&dump_output, interface_name))
     }
 
-    fn parse_wireguard_dump(
-        &self,
-        dump_output: &str,
-        interface_name: &str,
-    ) -> Result<Option<WireGuardStatus>> {
-        let lines: Vec<&str> = dump_output.lines().collect();
-
-        if lines.is_empty() {
-            return Ok(None);
+    /// Ping each peer's tunnel address through the interface and record the
+    /// round-trip time. Peers without a single-host allowed IP are skipped:
+    /// there is no way to tell which address inside a subnet answers.
+    pub async fn probe_wireguard_peers(&self, status: &mut WireGuardStatus) {
+        if let Some(mut simulation) = self.simulation() {
+            for peer in &mut status.peers {
+                peer.rtt = Some(simulation.probe_rtt());
+            }
+            return;
         }
-
-        // First line is the interface
-        let interface_line = lines[0];
-        let parts: Vec<&str> = interface_line.split('\t').collect();
-
-        if parts.len() < 3 {
-            return Ok(None);
+        for peer in &mut status.peers {
+            let Some(address) = peer_tunnel_address(&peer.allowed_ips) else {
+                continue;
+            };
+            let output = Command::new("/usr/bin/ping")
+                .args(["-c", "1", "-W", "1", "-n", "-I", &status.interface, address])
+                .timed_output()
+                .await;
+            peer.rtt = output
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| parse_ping_rtt(&String::from_utf8_lossy(&output.stdout)));
         }
+    }
 
-        let public_key = parts[1].to_string();
-        let listen_port = if !parts[2].is_empty() {
-            Some(parts[2].parse().unwrap_or(0))
-        } else {
-            None
-        };
-
-        let mut peers = Vec::new();
-
-        // Remaining lines are peers
-        for line in &lines[1..] {
-            let peer_parts: Vec<&str> = line.split('\t').collect();
-            if peer_parts.len() >= 4 {
-                let peer_public_key = peer_parts[1].to_string();
-                let endpoint = if !peer_parts[3].is_empty() {
-                    Some(peer_parts[3].to_string())
-                } else {
-                    None
-                };
-
-                let allowed_ips: Vec<String> = if !peer_parts[4].is_empty() {
-                    peer_parts[4]
-                        .split(',')
-                        .map(|s| s.trim().to_string())
-                        .collect()
-                } else {
-                    vec![]
-                };
-
-                let latest_handshake = if peer_parts.len() > 5 && !peer_parts[5].is_empty() {
-                    peer_parts[5].parse::<u64>().ok().and_then(|ts| {
-                        std::time::UNIX_EPOCH.checked_add(std::time::Duration::from_secs(ts))
-                    })
-                } else {
-                    None
-                };
-
-                let transfer_rx = if peer_parts.len() > 6 {
-                    peer_parts[6].parse().unwrap_or(0)
-                } else {
-                    0
-                };
-
-                let transfer_tx = if peer_parts.len() > 7 {
-                    peer_parts[7].parse().unwrap_or(0)
-                } else {
-                    0
-                };
+    /// Re-resolve hostname endpoints and point peers that have stopped
+    /// handshaking at the host's current address. Peers with a recent
+    /// handshake are left alone: WireGuard roams to wherever they last
+    /// talked from, which need not match DNS behind NAT.
+    pub async fn reresolve_wireguard_endpoints(
+        &self,
+        endpoints: Vec<PeerEndpoint>,
+    ) -> Vec<EndpointChange> {
+        let mut statuses: HashMap<String, Option<WireGuardStatus>> = HashMap::new();
+        let mut changes = Vec::new();
+
+        for entry in endpoints {
+            if !statuses.contains_key(&entry.interface) {
+                let status = self
+                    .get_wireguard_status(&entry.interface)
+                    .await
+                    .ok()
+                    .flatten();
+                statuses.insert(entry.interface.clone(), status);
+            }
+            // Interface down: networkd resolves afresh when it comes back
+            let Some(peer) = statuses[&entry.interface].as_ref().and_then(|status| {
+                status
+                    .peers
+                    .iter()
+                    .find(|peer| peer.public_key == entry.peer_public_key)
+            }) else {
+                continue;
+            };
+            if !handshake_stale(peer.latest_handshake) {
+                continue;
+            }
 
-                let persistent_keepalive = if peer_parts.len() > 8 && !peer_parts[8].is_empty() {
-                    Some(peer_parts[8].parse().unwrap_or(0))
-                } else {
-                    None
-                };
+            let Ok(addresses) = tokio::net::lookup_host(entry.endpoint.as_str()).await else {
+                continue;
+            };
+            let addresses: Vec<std::net::SocketAddr> = addresses.collect();
+            let current = peer.endpoint.as_deref().and_then(|e| e.parse().ok());
+            let Some(address) = next_endpoint(current, &addresses) else {
+                continue;
+            };
 
-                peers.push(WireGuardPeerStatus {
-                    public_key: peer_public_key,
-                    endpoint,
-                    allowed_ips,
-                    latest_handshake,
-                    transfer_rx,
-                    transfer_tx,
-                    persistent_keepalive,
+            let output = Command::new("/usr/bin/wg")
+                .args([
+                    "set",
+                    &entry.interface,
+                    "peer",
+                    &entry.peer_public_key,
+                    "endpoint",
+                    &address.to_string(),
+                ])
+                .timed_output()
+                .await;
+            if output.is_ok_and(|output| output.status.success()) {
+                changes.push(EndpointChange {
+                    interface: entry.interface,
+                    endpoint: entry.endpoint,
+                    address,
                 });
             }
         }
 
-        let connected = peers.iter().any(|p| p.latest_handshake.is_some());
-        let last_handshake = peers.iter().filter_map(|p| p.latest_handshake).max();
+        changes
+    }
 
-        Ok(Some(WireGuardStatus {
-            interface: interface_name.to_string(),
-            public_key,
-            listen_port,
-            peers,
-            connected,
-            last_handshake,
-        }))
+    /// Current endpoint addresses of all peers on running WireGuard links.
+    pub async fn active_wireguard_endpoints(&self) -> Vec<std::net::SocketAddr> {
+        if self.is_demo() {
+            return vec![];
+        }
+        let Ok(output) = Command::new("/usr/bin/wg")
+            .args(["show", "all", "endpoints"])
+            .timed_output()
+            .await
+        else {
+            return vec![];
+        };
+        // "<iface>\t<peer public key>\t<endpoint or (none)>"
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split('\t').nth(2)?.parse().ok())
+            .collect()
     }
 
     pub async fn list_wireguard_interfaces(&self) -> Result<Vec<String>> {
         let output = Command::new("/usr/bin/wg")
             .args(&["show", "interfaces"])
-            .output()?;
+            .timed_output()
+            .await?;
 
         if !output.status.success() {
             return Ok(vec![]);
@@ -1328,11 +1923,16 @@ impl NetworkManager {
     }
 
     pub async fn connect_wireguard(&self, interface_name: &str) -> Result<()> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.set_state(interface_name, true);
+        }
+
         // WireGuard interfaces auto-connect when brought up if properly configured
         self.set_interface_state(interface_name, "up").await?;
 
-        // Give it a moment to establish connection
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        // WireGuard links report "unknown" rather than "up" once running
+        self.wait_for_operstate(interface_name, |state| state != "down")
+            .await;
 
         Ok(())
     }
@@ -1347,7 +1947,8 @@ impl NetworkManager {
         // Check if we can reach a public DNS server
         let result = Command::new("/usr/bin/ping")
             .args(&["-c", "1", "-W", "3", "8.8.8.8"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to check internet connectivity")?;
 
         Ok(result.status.success())
@@ -1357,7 +1958,8 @@ impl NetworkManager {
         // Find interface with default route (internet connection)
         let output = Command::new("/usr/bin/ip")
             .args(&["route", "show", "default"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to get default route")?;
 
         let route_output = String::from_utf8_lossy(&output.stdout);
@@ -1380,6 +1982,14 @@ impl NetworkManager {
     }
 
     pub async fn create_hotspot(&self, config: &HotspotConfig) -> Result<()> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.create_hotspot(config);
+        }
+
+        validate_hotspot_config(config).map_err(|e| NetworkError::HotspotError {
+            details: e.to_string(),
+        })?;
+
         // Check prerequisites
         if !self.check_internet_connectivity().await? {
             return Err(NetworkError::HotspotError {
@@ -1416,6 +2026,12 @@ impl NetworkManager {
 
         // Setup NAT/iptables rules
         self.setup_nat_rules(config, &internet_interface).await?;
+        crate::portfwd::apply(crate::portfwd::ruleset(
+            &config.port_forwards,
+            &config.gateway,
+        ))
+        .await
+        .context("Failed to set up port forwarding")?;
 
         // Start hostapd
         self.start_hostapd(config).await?;
@@ -1424,25 +2040,9 @@ impl NetworkManager {
     }
 
     async fn create_hostapd_config(&self, config: &HotspotConfig) -> Result<()> {
-        let hostapd_config = format!(
-            "interface={}\n\
-             driver=nl80211\n\
-             ssid={}\n\
-             hw_mode=g\n\
-             channel={}\n\
-             wmm_enabled=1\n\
-             macaddr_acl=0\n\
-             auth_algs=1\n\
-             ignore_broadcast_ssid=0\n\
-             wpa=2\n\
-             wpa_passphrase={}\n\
-             wpa_key_mgmt=WPA-PSK\n\
-             wpa_pairwise=TKIP\n\
-             rsn_pairwise=CCMP\n",
-            config.interface, config.ssid, config.channel, config.password
-        );
-
-        fs::write("/tmp/hostapd.conf", hostapd_config)
+        let operation = format!("Hotspot {} on {}", config.ssid, config.interface);
+        let hostapd = crate::provenance::stamp(&hostapd_config(config)?, &operation);
+        crate::secure_file::write_private("/tmp/hostapd.conf", hostapd)
             .context("Failed to write hostapd configuration")?;
 
         Ok(())
@@ -1452,7 +2052,8 @@ impl NetworkManager {
         // Bring interface down first
         Command::new("/usr/bin/ip")
             .args(&["link", "set", &config.interface, "down"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to bring interface down")?;
 
         // Set interface IP address
@@ -1464,13 +2065,15 @@ impl NetworkManager {
                 "dev",
                 &config.interface,
             ])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to set interface IP")?;
 
         // Bring interface up
         Command::new("/usr/bin/ip")
             .args(&["link", "set", &config.interface, "up"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to bring interface up")?;
 
         Ok(())
@@ -1478,24 +2081,16 @@ impl NetworkManager {
 
     async fn setup_dhcp_server(&self, config: &HotspotConfig) -> Result<()> {
         // Create dnsmasq configuration for DHCP
-        let dnsmasq_config = format!(
-            "interface={}\n\
-             dhcp-range={}.10,{}.50,255.255.255.0,24h\n\
-             dhcp-option=3,{}\n\
-             dhcp-option=6,8.8.8.8,8.8.4.4\n\
-             server=8.8.8.8\n\
-             log-queries\n\
-             log-dhcp\n\
-             listen-address={}\n",
-            config.interface,
-            &config.gateway[..config.gateway.rfind('.').unwrap()], // Get network part
-            &config.gateway[..config.gateway.rfind('.').unwrap()],
-            config.gateway,
-            config.gateway
-        );
-
-        fs::write("/tmp/dnsmasq.conf", dnsmasq_config)
+        let dnsmasq_config = dnsmasq_config(
+            config,
+            crate::hotspot::LEASE_FILE,
+            crate::hotspot::HOSTS_FILE,
+        )?;
+        let operation = format!("Hotspot DHCP on {}", config.interface);
+        let dnsmasq_config = crate::provenance::stamp(&dnsmasq_config, &operation);
+        crate::secure_file::write_public("/tmp/dnsmasq.conf", dnsmasq_config)
             .context("Failed to write dnsmasq configuration")?;
+        self.write_dhcp_reservations(config)?;
 
         // Start dnsmasq
         Command::new("/usr/bin/dnsmasq")
@@ -1506,6 +2101,47 @@ impl NetworkManager {
         Ok(())
     }
 
+    fn write_dhcp_reservations(&self, config: &HotspotConfig) -> Result<()> {
+        let hosts = crate::hotspot::render_hosts_file(&config.reservations, &config.gateway);
+        let hosts = crate::provenance::stamp(&hosts, "Hotspot DHCP reservations");
+        crate::secure_file::write_public(crate::hotspot::HOSTS_FILE, hosts)
+            .context("Failed to write DHCP reservations")
+    }
+
+    /// Hand the running hotspot's dnsmasq a changed set of reservations.
+    /// Clients pick them up when they next renew their lease.
+    pub async fn reload_dhcp_reservations(&self, config: &HotspotConfig) -> Result<()> {
+        if self.is_demo() {
+            return Ok(());
+        }
+        if config.persistent {
+            return crate::hotspot_units::update(config).await;
+        }
+        self.write_dhcp_reservations(config)?;
+        Command::new("/usr/bin/pkill")
+            .args(&["-HUP", "-f", HOTSPOT_DNSMASQ])
+            .timed_output()
+            .await
+            .context("Failed to reload dnsmasq")?;
+        Ok(())
+    }
+
+    /// Replace the running hotspot's port forwards.
+    pub async fn reload_port_forwards(&self, config: &HotspotConfig) -> Result<()> {
+        if self.is_demo() {
+            return Ok(());
+        }
+        if config.persistent {
+            return crate::hotspot_units::update(config).await;
+        }
+        crate::portfwd::apply(crate::portfwd::ruleset(
+            &config.port_forwards,
+            &config.gateway,
+        ))
+        .await
+        .context("Failed to update port forwarding")
+    }
+
     async fn setup_nat_rules(
         &self,
         config: &HotspotConfig,
@@ -1514,7 +2150,8 @@ impl NetworkManager {
         // Enable IP forwarding
         Command::new("/usr/bin/sysctl")
             .args(&["-w", "net.ipv4.ip_forward=1"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to enable IP forwarding")?;
 
         // Setup NAT rules
@@ -1529,7 +2166,8 @@ impl NetworkManager {
                 "-j",
                 "MASQUERADE",
             ])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to setup NAT rule")?;
 
         Command::new("/usr/bin/iptables")
@@ -1547,7 +2185,8 @@ impl NetworkManager {
                 "-j",
                 "ACCEPT",
             ])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to setup forward rule 1")?;
 
         Command::new("/usr/bin/iptables")
@@ -1561,7 +2200,8 @@ impl NetworkManager {
                 "-j",
                 "ACCEPT",
             ])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to setup forward rule 2")?;
 
         Ok(())
@@ -1570,63 +2210,373 @@ impl NetworkManager {
     async fn start_hostapd(&self, _config: &HotspotConfig) -> Result<()> {
         Command::new("/usr/bin/hostapd")
             .args(&["/tmp/hostapd.conf", "-B"]) // -B for background mode
-            .output()
+            .timed_output()
+            .await
             .context("Failed to start hostapd")?;
 
         Ok(())
     }
 
+    /// Restart systemd-networkd, which reapplies its configuration to every
+    /// link it manages; nothing is restarted in demo mode
+    pub async fn restart_networkd(&self) -> Result<()> {
+        if self.is_demo() {
+            return Ok(());
+        }
+        let output = Command::new("/usr/bin/systemctl")
+            .args(["restart", "systemd-networkd"])
+            .timed_output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Failed to restart systemd-networkd: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Start a temporary PXE boot server; nothing is started in demo mode
+    pub async fn start_pxe(&self, config: crate::pxe::PxeConfig) -> Result<crate::pxe::PxeServer> {
+        if self.is_demo() {
+            return Ok(crate::pxe::PxeServer::simulated(config));
+        }
+        crate::pxe::PxeServer::start(config).await
+    }
+
     pub async fn stop_hotspot(&self, config: &HotspotConfig) -> Result<()> {
         // Stop hostapd
         Command::new("/usr/bin/pkill")
             .args(&["hostapd"])
-            .output()
+            .timed_output()
+            .await
             .ok(); // Don't fail if not running
 
-        // Stop dnsmasq
+        // Stop dnsmasq, leaving any other instance (e.g. a PXE boot server) alone
         Command::new("/usr/bin/pkill")
-            .args(&["dnsmasq"])
-            .output()
+            .args(&["-f", HOTSPOT_DNSMASQ])
+            .timed_output()
+            .await
             .ok(); // Don't fail if not running
 
         // Remove iptables rules
         Command::new("/usr/bin/iptables")
             .args(&["-F"])
-            .output()
+            .timed_output()
+            .await
             .ok();
 
         Command::new("/usr/bin/iptables")
             .args(&["-t", "nat", "-F"])
-            .output()
+            .timed_output()
+            .await
             .ok();
+        crate::portfwd::apply(None).await.ok();
 
         // Reset interface
         Command::new("/usr/bin/ip")
             .args(&["addr", "flush", "dev", &config.interface])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to flush interface addresses")?;
 
         Command::new("/usr/bin/ip")
             .args(&["link", "set", &config.interface, "down"])
-            .output()
+            .timed_output()
+            .await
             .context("Failed to bring interface down")?;
 
         Ok(())
     }
 
-    fn frequency_to_channel(&self, frequency: u32) -> u32 {
-        // Convert frequency to WiFi channel
-        match frequency {
-            2412..=2484 => (frequency - 2412) / 5 + 1, // 2.4 GHz band
-            5000..=6000 => (frequency - 5000) / 5,     // 5 GHz band
-            _ => 0,
+    /// Move the running hotspot into systemd units that start it at boot, or
+    /// take those units down and out again; nothing is written in demo mode
+    pub async fn persist_hotspot(&self, config: &HotspotConfig, persist: bool) -> Result<()> {
+        if self.is_demo() {
+            return Ok(());
+        }
+        if persist {
+            // The units start their own hostapd and dnsmasq
+            self.stop_hotspot(config).await?;
+            crate::hotspot_units::install(config).await
+        } else {
+            crate::hotspot_units::remove().await
+        }
+    }
+
+    /// The hotspot kept in systemd units, with the state of its main unit
+    pub async fn persistent_hotspot(&self) -> Option<(HotspotConfig, String)> {
+        if self.is_demo() {
+            return None;
+        }
+        let config = crate::hotspot_units::load()?;
+        Some((config, crate::hotspot_units::state().await))
+    }
+
+    /// Scan from `interface` and measure channel airtime to find the least
+    /// congested channel for a hotspot. Returns the channel and how many
+    /// access points were heard on or next to it.
+    pub async fn survey_hotspot_channel(&self, interface: &str) -> Result<(u32, usize)> {
+        let access_points: Vec<(u32, i32)> = self
+            .scan_wifi_backend(interface)
+            .await?
+            .iter()
+            .map(|network| (network.channel, network.signal_strength))
+            .collect();
+
+        // The scan just visited every channel, so drivers that measure airtime have fresh numbers
+        let mut busy = HashMap::new();
+        if !self.is_demo() {
+            if let Ok(output) = Command::new(crate::command::IW)
+                .args(&["dev", interface, "survey", "dump"])
+                .timed_output()
+                .await
+            {
+                busy = crate::hotspot::parse_survey_dump(&String::from_utf8_lossy(&output.stdout));
+            }
+        }
+        Ok(crate::hotspot::pick_channel(&access_points, &busy))
+    }
+
+    /// Clients associated with the hotspot on `interface`, with their DHCP
+    /// addresses and hostnames where dnsmasq has handed out a lease.
+    /// Interfaces broadcasting as an access point, with their SSID, whoever
+    /// started them.
+    pub async fn running_access_points(&self) -> Vec<(String, String)> {
+        if self.is_demo() {
+            return Vec::new();
+        }
+        match Command::new(crate::command::IW)
+            .arg("dev")
+            .timed_output()
+            .await
+        {
+            Ok(output) if output.status.success() => {
+                crate::hotspot::parse_access_points(&String::from_utf8_lossy(&output.stdout))
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub async fn get_hotspot_stations(&self, interface: &str) -> Result<Vec<Station>> {
+        if let Some(mut simulation) = self.simulation() {
+            return Ok(simulation.hotspot_stations(interface));
+        }
+
+        let output = Command::new(crate::command::IW)
+            .args(&["dev", interface, "station", "dump"])
+            .timed_output()
+            .await
+            .context("Failed to list hotspot clients")?;
+        if !output.status.success() {
+            return Err(NetworkError::HotspotError {
+                details: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            }
+            .into());
+        }
+
+        let mut stations =
+            crate::hotspot::parse_station_dump(&String::from_utf8_lossy(&output.stdout));
+        for lease_file in [crate::hotspot::LEASE_FILE, crate::hotspot_units::LEASE_FILE] {
+            if let Ok(leases) = tokio::fs::read_to_string(lease_file).await {
+                crate::hotspot::attach_leases(&mut stations, &leases);
+            }
+        }
+        Ok(stations)
+    }
+
+    /// Permanent neighbor entries on `interface`, live and persisted.
+    /// Interfaces among `interfaces` another network manager runs; none in
+    /// the demo, which has no other manager to find.
+    pub async fn detect_foreign_managers(&self, interfaces: &[String]) -> HashMap<String, Foreign> {
+        if self.is_demo() {
+            return HashMap::new();
+        }
+        crate::takeover::detect(interfaces).await
+    }
+
+    /// Release `interface` from the manager running it and make sure
+    /// networkd is there to take over.
+    pub async fn adopt_interface(&self, interface: &str, foreign: &Foreign) -> Result<()> {
+        if self.is_demo() {
+            return Ok(());
+        }
+        crate::takeover::release(interface, foreign).await?;
+        crate::takeover::start_networkd().await
+    }
+
+    pub async fn get_networkd_files(&self, interface: &str) -> Result<MatchedFiles> {
+        if let Some(simulation) = self.simulation() {
+            return simulation.networkd_files(interface);
+        }
+        crate::networkd::matched_files(interface).await
+    }
+
+    /// Common mistakes in the .network and .link files in /etc/systemd/network
+    pub fn lint_networkd_files(&self) -> Result<Vec<crate::doctor::Finding>> {
+        if let Some(simulation) = self.simulation() {
+            return Ok(crate::doctor::lint(&simulation.networkd_dir()));
+        }
+        crate::doctor::check_dir(Path::new(crate::doctor::NETWORKD_DIR))
+    }
+
+    /// Test IPv4 and IPv6 through `interface` separately
+    pub async fn reachability_test(
+        &self,
+        interface: &Interface,
+    ) -> Result<crate::reachability::ReachabilityReport> {
+        if let Some(simulation) = self.simulation() {
+            return simulation.reachability(&interface.name);
+        }
+        Ok(crate::reachability::run(interface).await)
+    }
+
+    /// Check the link, address, gateway, DNS and HTTP in turn, stopping at
+    /// the first that fails
+    pub async fn troubleshoot(
+        &self,
+        interface: &Interface,
+    ) -> Result<crate::troubleshoot::Diagnosis> {
+        if let Some(simulation) = self.simulation() {
+            return simulation.troubleshoot(&interface.name);
         }
+        Ok(crate::troubleshoot::run(interface).await)
+    }
+
+    /// GET `url` once from `binding`, with TLS details and timings
+    pub async fn http_check(
+        &self,
+        url: &str,
+        binding: &crate::httpcheck::Binding,
+    ) -> Result<crate::httpcheck::HttpCheck> {
+        if self.is_demo() {
+            return Ok(crate::demo::http_check(url, binding));
+        }
+        crate::httpcheck::run(url, binding).await
+    }
+
+    pub async fn speed_test(
+        &self,
+        settings: &crate::speedtest::SpeedTestSettings,
+    ) -> crate::speedtest::SpeedResult {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.speed_test();
+        }
+        crate::speedtest::run(settings).await
+    }
+
+    /// Recorded speed tests, oldest first
+    pub fn speed_history(&self) -> Vec<crate::speedtest::SpeedResult> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.speed_history();
+        }
+        crate::speedtest::load_history()
+    }
+
+    pub fn address_preference(&self) -> crate::gai::GaiState {
+        if let Some(simulation) = self.simulation() {
+            return simulation.address_preference();
+        }
+        crate::gai::load()
+    }
+
+    pub fn set_address_preference(&self, preference: crate::gai::Preference) -> Result<()> {
+        if let Some(mut simulation) = self.simulation() {
+            simulation.set_address_preference(preference);
+            return Ok(());
+        }
+        crate::gai::save(preference)
+    }
+
+    /// The addresses of `name` in the order applications try them
+    pub async fn address_order(&self, name: &str) -> Result<Vec<String>> {
+        if let Some(simulation) = self.simulation() {
+            return Ok(simulation.address_order());
+        }
+        crate::gai::address_order(name).await
+    }
+
+    /// Which resolver answers through `interface` and as applications
+    /// resolve; `up` are the interfaces that are up
+    pub async fn dns_leak_test(
+        &self,
+        interface: &str,
+        up: &[String],
+    ) -> crate::dnsleak::LeakReport {
+        if let Some(simulation) = self.simulation() {
+            return simulation.dns_leak(interface);
+        }
+        crate::dnsleak::run(interface, up).await
+    }
+
+    /// Every DHCP server that offers on `interface`, judged against the
+    /// `configured` ones
+    pub async fn dhcp_probe(
+        &self,
+        interface: &str,
+        mac: &str,
+        configured: &[String],
+    ) -> crate::dhcpprobe::DhcpReport {
+        if let Some(simulation) = self.simulation() {
+            return simulation.dhcp_probe(interface, configured);
+        }
+        crate::dhcpprobe::run(interface, mac, configured).await
+    }
+
+    /// The ARP/NDP table of every interface, resolved entries only
+    pub async fn neighbor_table(&self) -> Result<Vec<crate::macconflict::Neighbor>> {
+        if let Some(simulation) = self.simulation() {
+            return Ok(simulation.neighbor_table());
+        }
+        let output = Command::new("/usr/bin/ip")
+            .args(["-j", "neigh", "show"])
+            .timed_output()
+            .await
+            .context("Failed to read the neighbor table")?;
+        Ok(crate::macconflict::parse_neighbors(
+            &String::from_utf8_lossy(&output.stdout),
+        ))
+    }
+
+    pub async fn get_static_neighbors(&self, interface: &str) -> Result<Vec<StaticNeighbor>> {
+        if let Some(simulation) = self.simulation() {
+            return Ok(simulation.static_neighbors(interface));
+        }
+        crate::neighbors::list(interface).await
+    }
+
+    /// Returns whether the entry was also persisted.
+    pub async fn add_static_neighbor(
+        &self,
+        interface: &str,
+        address: &str,
+        mac: &str,
+    ) -> Result<bool> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation
+                .add_static_neighbor(interface, address, mac)
+                .map(|()| true);
+        }
+        crate::neighbors::add(interface, address, mac).await
+    }
+
+    pub async fn remove_static_neighbor(&self, interface: &str, address: &str) -> Result<()> {
+        if let Some(mut simulation) = self.simulation() {
+            simulation.remove_static_neighbor(interface, address);
+            return Ok(());
+        }
+        crate::neighbors::remove(interface, address).await
     }
 
     /// Update only statistics for existing interfaces (optimized for frequent polling)
     pub async fn update_interface_stats(&self, interfaces: &mut [Interface]) -> Result<()> {
+        let names: Vec<String> = interfaces.iter().map(|i| i.name.clone()).collect();
+        let mut stats = self.collect_interface_stats(&names).await;
+
         for interface in interfaces {
-            interface.stats = self.get_interface_stats(&interface.name).await?;
+            if let Some(stats) = stats.remove(&interface.name) {
+                interface.stats = stats;
+            }
 
             // Note: WiFi info updates are too slow for stats refresh
             // WiFi info should be updated separately and less frequently
@@ -1634,10 +2584,44 @@ impl NetworkManager {
         Ok(())
     }
 
+    /// Counters for the named interfaces, keyed by name. Interfaces that
+    /// disappeared since the names were taken are left out.
+    pub async fn collect_interface_stats(
+        &self,
+        names: &[String],
+    ) -> HashMap<String, InterfaceStats> {
+        if let Some(mut simulation) = self.simulation() {
+            return simulation.stats(names);
+        }
+
+        // One netlink dump covers every interface; /sys is only a fallback
+        if let Ok(mut link_stats) = crate::netlink::dump_link_stats() {
+            return names
+                .iter()
+                .filter_map(|name| Some((name.clone(), link_stats.remove(name)?)))
+                .collect();
+        }
+
+        let mut stats = HashMap::new();
+        for name in names {
+            if !Path::new("/sys/class/net").join(name).exists() {
+                continue;
+            }
+            if let Ok(found) = self.get_interface_stats(name).await {
+                stats.insert(name.clone(), found);
+            }
+        }
+        stats
+    }
+
     pub async fn get_detailed_wifi_info(
         &self,
         interface: &str,
     ) -> Result<Option<DetailedWifiInfo>> {
+        if let Some(mut simulation) = self.simulation() {
+            return Ok(simulation.detailed_wifi_info(interface));
+        }
+
         // Get basic WiFi info first
         let wifi_info = self.get_wifi_info(interface).await?;
         if wifi_info.is_none() {
@@ -1652,6 +2636,12 @@ impl NetworkManager {
             // Get additional WiFi-specific information using iwconfig or iw
             let (link_speed, tx_power, signal_quality) =
                 self.get_wifi_link_details(interface).await?;
+            let (tx_rate, rx_rate) = self.get_wifi_link_rates(interface).await;
+            // iw link leaves the power out; iw info has it
+            let tx_power = match tx_power {
+                Some(power) => Some(power),
+                None => self.get_tx_power(interface).await,
+            };
 
             // Get connection time by checking when the interface came up
             let connected_time = self.get_connection_uptime(interface).await?;
@@ -1677,20 +2667,38 @@ impl NetworkManager {
                 tx_dropped: 0, // Will be populated by get_wifi_link_details
                 rx_dropped: 0, // Will be populated by get_wifi_link_details
                 tx_retries: 0, // Will be populated by get_wifi_link_details
+                tx_rate,
+                rx_rate,
             }))
         } else {
             Ok(None)
         }
     }
 
+    /// The tx and rx rates of the current association; iwconfig doesn't
+    /// know about MCS, so without iw there are none
+    async fn get_wifi_link_rates(&self, interface: &str) -> (Option<LinkRate>, Option<LinkRate>) {
+        match Command::new(crate::command::IW)
+            .args(["dev", interface, "link"])
+            .timed_output()
+            .await
+        {
+            Ok(output) if output.status.success() => {
+                parse_link_rates(&String::from_utf8_lossy(&output.stdout))
+            }
+            _ => (None, None),
+        }
+    }
+
     async fn get_wifi_link_details(
         &self,
         interface: &str,
     ) -> Result<(Option<u32>, Option<i32>, Option<u32>)> {
         // Try to get link details using iw command
-        let output = Command::new("/usr/bin/iw")
+        let output = Command::new(crate::command::IW)
             .args(&["dev", interface, "link"])
-            .output();
+            .timed_output()
+            .await;
 
         if let Ok(output) = output {
             if output.status.success() {
@@ -1715,10 +2723,7 @@ impl NetworkManager {
                     if line.starts_with("signal:") {
                         if let Some(signal_str) = line.split_whitespace().nth(1) {
                             if let Ok(signal) = signal_str.parse::<i32>() {
-                                // Convert signal strength to quality percentage
-                                // -30 dBm = 100%, -90 dBm = 0%
-                                let quality = ((signal + 90) * 100 / 60).max(0).min(100) as u32;
-                                signal_quality = Some(quality);
+                                signal_quality = Some(signal_percent(signal));
                             }
                         }
                     }
@@ -1729,7 +2734,10 @@ impl NetworkManager {
         }
 
         // Fallback: try iwconfig
-        let output = Command::new("/usr/bin/iwconfig").arg(interface).output();
+        let output = Command::new("/usr/bin/iwconfig")
+            .arg(interface)
+            .timed_output()
+            .await;
 
         if let Ok(output) = output {
             if output.status.success() {
@@ -1773,8 +2781,8 @@ impl NetworkManager {
                                             numerator_str.parse::<u32>(),
                                             denominator_str.parse::<u32>(),
                                         ) {
-                                            if den > 0 {
-                                                signal_quality = Some((num * 100) / den);
+                                            if let Some(quality) = (num * 100).checked_div(den) {
+                                                signal_quality = Some(quality);
                                             }
                                         }
                                     }
@@ -1806,3 +2814,803 @@ impl NetworkManager {
         Ok(None)
     }
 }
+
+/// hostapd.conf for the hotspot, wherever it's written
+pub(crate) fn hostapd_config(config: &HotspotConfig) -> Result<String> {
+    // hostapd accepts the derived PSK directly, keeping the passphrase out of the file
+    let psk = crate::password::derive_wpa_psk(&config.ssid, &config.password).map_err(|e| {
+        NetworkError::HotspotError {
+            details: e.to_string(),
+        }
+    })?;
+
+    let hostapd_config = format!(
+        "interface={}\n\
+         driver=nl80211\n\
+         {}\n\
+         hw_mode=g\n\
+         channel={}\n\
+         wmm_enabled=1\n\
+         macaddr_acl=0\n\
+         auth_algs=1\n\
+         ignore_broadcast_ssid=0\n\
+         wpa=2\n\
+         wpa_psk={}\n\
+         wpa_key_mgmt=WPA-PSK\n\
+         wpa_pairwise=TKIP\n\
+         rsn_pairwise=CCMP\n",
+        config.interface,
+        crate::sanitize::hostapd_ssid_line(&config.ssid),
+        config.channel,
+        psk
+    );
+
+    Ok(hostapd_config)
+}
+
+/// dnsmasq.conf handing out .10-.50 of the gateway's /24
+pub(crate) fn dnsmasq_config(
+    config: &HotspotConfig,
+    lease_file: &str,
+    hosts_file: &str,
+) -> Result<String> {
+    let octets = gateway_octets(&config.gateway)?;
+    let network_part = format!("{}.{}.{}", octets[0], octets[1], octets[2]);
+
+    Ok(format!(
+        "interface={}\n\
+         dhcp-range={}.10,{}.50,255.255.255.0,24h\n\
+         dhcp-option=3,{}\n\
+         dhcp-option=6,8.8.8.8,8.8.4.4\n\
+         server=8.8.8.8\n\
+         log-queries\n\
+         log-dhcp\n\
+         listen-address={}\n\
+         dhcp-leasefile={}\n\
+         dhcp-hostsfile={}\n",
+        config.interface,
+        network_part,
+        network_part,
+        config.gateway,
+        config.gateway,
+        lease_file,
+        hosts_file
+    ))
+}
+
+// Hotspot values are written into hostapd/dnsmasq configs and ip arguments
+pub(crate) fn validate_hotspot_config(config: &HotspotConfig) -> Result<()> {
+    crate::sanitize::interface_name(&config.interface)?;
+    crate::sanitize::ssid(&config.ssid)?;
+    gateway_octets(&config.gateway)?;
+    Ok(())
+}
+
+fn gateway_octets(gateway: &str) -> Result<[u8; 4]> {
+    gateway
+        .parse::<std::net::Ipv4Addr>()
+        .map(|addr| addr.octets())
+        .map_err(|_| anyhow::anyhow!("Invalid hotspot gateway address: {:?}", gateway))
+}
+
+/// Collapse scan entries into one row per SSID: the strongest BSS stands for
+/// the group, with every BSS listed under `access_points`.
+fn group_by_ssid(mut networks: Vec<WifiNetwork>) -> Vec<WifiNetwork> {
+    networks.sort_by_key(|n| std::cmp::Reverse(n.signal_strength));
+
+    let mut groups: Vec<WifiNetwork> = Vec::new();
+    for network in networks {
+        let access_point = AccessPoint {
+            bssid: network.bssid.clone(),
+            signal_strength: network.signal_strength,
+            frequency: network.frequency,
+            channel: network.channel,
+            connected: network.connected,
+        };
+        match groups.iter_mut().find(|g| g.ssid == network.ssid) {
+            Some(group) => {
+                group.connected |= network.connected;
+                group.access_points.push(access_point);
+            }
+            None => groups.push(WifiNetwork {
+                access_points: vec![access_point],
+                ..network
+            }),
+        }
+    }
+    groups
+}
+
+/// Flag the scan entry for the network an interface is associated with:
+/// the exact BSS if it was seen, otherwise the strongest one with the SSID.
+fn mark_connected(networks: &mut [WifiNetwork], link: &WifiNetwork) {
+    let by_bssid = networks
+        .iter()
+        .position(|n| n.bssid.eq_ignore_ascii_case(&link.bssid));
+    let by_ssid = || networks.iter().position(|n| n.ssid == link.ssid);
+
+    if let Some(index) = by_bssid.or_else(by_ssid) {
+        networks[index].connected = true;
+    }
+}
+
+/// The wiphy a wireless interface belongs to, e.g. "phy0".
+fn wireless_phy(interface: &str) -> Option<String> {
+    fs::read_to_string(format!("/sys/class/net/{}/phy80211/name", interface))
+        .ok()
+        .map(|name| name.trim().to_string())
+}
+
+/// First of mon0, mon1, ... that no interface uses yet.
+fn free_monitor_name(existing: &[&str]) -> String {
+    (0..)
+        .map(|index| format!("mon{}", index))
+        .find(|name| !existing.contains(&name.as_str()))
+        .unwrap_or_default()
+}
+
+/// Vendor, model and driver of the device behind a network interface, the
+/// names coming from udev's hwdb and the IDs from sysfs when it has none.
+fn get_nic_hardware(name: &str, ifindex: u64) -> Option<NicHardware> {
+    let device = Path::new("/sys/class/net").join(name).join("device");
+    // Virtual interfaces (bridges, tunnels, veth) have no backing device
+    if !device.exists() {
+        return None;
+    }
+    let udev = fs::read_to_string(format!("/run/udev/data/n{}", ifindex))
+        .map(|data| parse_udev_properties(&data))
+        .unwrap_or_default();
+    let property = |keys: &[&str]| keys.iter().find_map(|key| udev.get(*key).cloned());
+    let sysfs_id = |file: &str| {
+        fs::read_to_string(device.join(file))
+            .ok()
+            .map(|id| id.trim().trim_start_matches("0x").to_string())
+    };
+
+    let vendor = property(&["ID_VENDOR_FROM_DATABASE", "ID_VENDOR"]).map(|v| short_vendor(&v));
+    let model = property(&["ID_MODEL_FROM_DATABASE", "ID_MODEL"]).or_else(|| {
+        let (vendor_id, device_id) = (sysfs_id("vendor")?, sysfs_id("device")?);
+        Some(format!("[{}:{}]", vendor_id, device_id))
+    });
+    let driver = fs::read_link(device.join("driver"))
+        .ok()
+        .and_then(|link| Some(link.file_name()?.to_string_lossy().into_owned()))
+        .or_else(|| property(&["ID_NET_DRIVER"]));
+
+    Some(NicHardware {
+        vendor,
+        model,
+        driver,
+    })
+}
+
+/// "E:KEY=value" lines of a /run/udev/data entry.
+fn parse_udev_properties(data: &str) -> HashMap<String, String> {
+    data.lines()
+        .filter_map(|line| line.strip_prefix("E:")?.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// "Intel Corporation" -> "Intel", "Realtek Semiconductor Co., Ltd." -> "Realtek"
+fn short_vendor(vendor: &str) -> String {
+    const SUFFIXES: [&str; 6] = [
+        " Corporation",
+        " Semiconductor",
+        " Technology",
+        " Communications",
+        " Inc",
+        ",",
+    ];
+    let end = SUFFIXES
+        .iter()
+        .filter_map(|suffix| vendor.find(suffix))
+        .min()
+        .unwrap_or(vendor.len());
+    vendor[..end].trim().to_string()
+}
+
+/// The peer's own address inside the tunnel: its first /32 or /128 allowed IP.
+fn peer_tunnel_address(allowed_ips: &[String]) -> Option<&str> {
+    allowed_ips.iter().find_map(|cidr| {
+        let (address, prefix) = cidr.split_once('/')?;
+        let host = match address.parse::<std::net::IpAddr>().ok()? {
+            std::net::IpAddr::V4(_) => prefix == "32",
+            std::net::IpAddr::V6(_) => prefix == "128",
+        };
+        host.then_some(address)
+    })
+}
+
+/// Signal as a percentage: -90 dBm and below is 0%, -30 dBm and above 100%.
+pub fn signal_percent(dbm: i32) -> u32 {
+    ((dbm + 90) * 100 / 60).clamp(0, 100) as u32
+}
+
+/// The "txpower 22.00 dBm" line of `iw dev <if> info`, rounded
+pub fn parse_tx_power(output: &str) -> Option<i32> {
+    output.lines().find_map(|line| {
+        let power = line.trim().strip_prefix("txpower ")?;
+        let dbm: f64 = power.split_whitespace().next()?.parse().ok()?;
+        Some(dbm.round() as i32)
+    })
+}
+
+async fn run_checked(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program).args(args).timed_output().await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+pub fn frequency_to_channel(frequency: u32) -> u32 {
+    // Convert frequency to WiFi channel
+    match frequency {
+        2412..=2484 => (frequency - 2412) / 5 + 1, // 2.4 GHz band
+        5955..=7115 => (frequency - 5950) / 5,     // 6 GHz band
+        5000..=5950 => (frequency - 5000) / 5,     // 5 GHz band
+        _ => 0,
+    }
+}
+
+/// iw prints SSID bytes that aren't printable ASCII, backslashes and spaces at either
+/// end, as `\xNN`. Decode them back, so `Caf\xc3\xa9` becomes "Café".
+pub fn unescape_ssid(escaped: &str) -> String {
+    let bytes = escaped.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 2..i + 4)
+            .filter(|_| bytes[i] == b'\\' && bytes[i + 1] == b'x')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 4;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The associated network from `iw dev <iface> link`, None when not connected.
+pub fn parse_iw_link_info(link_info: &str) -> Option<WifiNetwork> {
+    let mut ssid = String::new();
+    let mut bssid = String::new();
+    let mut frequency = 0u32;
+    let mut signal = 0i32;
+
+    for line in link_info.lines() {
+        let line = line.trim();
+        if line.starts_with("SSID:") {
+            ssid = unescape_ssid(line.strip_prefix("SSID:").unwrap_or("").trim());
+        } else if line.starts_with("Connected to") {
+            bssid = line.split_whitespace().nth(2).unwrap_or("").to_string();
+        } else if let Some(freq_str) = line.strip_prefix("freq:") {
+            // "freq: 5180", or "freq: 5180.0" on newer iw
+            if let Some(freq_part) = freq_str.split_whitespace().next() {
+                frequency = freq_part.parse::<f32>().map(|mhz| mhz as u32).unwrap_or(0);
+            }
+        } else if let Some(signal_str) = line.strip_prefix("signal:") {
+            if let Some(signal_part) = signal_str.split_whitespace().next() {
+                signal = signal_part.parse().unwrap_or(0);
+            }
+        }
+    }
+
+    if ssid.is_empty() {
+        return None;
+    }
+    Some(WifiNetwork {
+        ssid,
+        bssid,
+        signal_strength: signal,
+        frequency,
+        channel: frequency_to_channel(frequency),
+        security: WifiSecurity::WPA2, // Will be enhanced with proper detection
+        encryption: vec!["WPA2".to_string()],
+        security_details: SecurityDetails::default(),
+        connected: true,   // iw only reports a link while associated
+        in_history: false, // Will be set later by caller
+        access_points: Vec::new(),
+    })
+}
+
+/// Networks from `iw dev <iface> scan`, strongest first. Hidden networks are skipped.
+pub fn parse_wifi_scan_results(scan_output: &str) -> Vec<WifiNetwork> {
+    let mut networks = Vec::new();
+    let mut current_bssid = String::new();
+    let mut current_frequency = 0u32;
+    let mut current_signal = 0i32;
+    let mut current_ssid = String::new();
+    let mut current_elements = ElementParser::default();
+    let mut current_connected = false;
+
+    let mut save_network =
+        |bssid: &str, ssid: &str, frequency, signal, elements: ElementParser, connected| {
+            if bssid.is_empty() || ssid.is_empty() {
+                return;
+            }
+            let details = elements.finish();
+            networks.push(WifiNetwork {
+                ssid: ssid.to_string(),
+                bssid: bssid.to_string(),
+                signal_strength: signal,
+                frequency,
+                channel: frequency_to_channel(frequency),
+                security: details.security(),
+                encryption: details.ciphers(),
+                security_details: details,
+                connected,
+                in_history: false, // Will be set later by caller
+                access_points: Vec::new(),
+            });
+        };
+
+    for raw_line in scan_output.lines() {
+        let line = raw_line.trim();
+
+        // Only unindented lines start an entry: "BSS Load:" is an element inside one
+        if let Some(header) = raw_line.strip_prefix("BSS ") {
+            // Save previous network if exists
+            save_network(
+                &current_bssid,
+                &current_ssid,
+                current_frequency,
+                current_signal,
+                std::mem::take(&mut current_elements),
+                current_connected,
+            );
+
+            // Start new network
+            // "BSS 00:11:22:33:44:55(on wlan0) -- associated"
+            current_bssid = header.split('(').next().unwrap_or("").trim().to_string();
+            current_connected = header.contains("-- associated");
+            current_ssid.clear();
+            current_frequency = 0;
+            current_signal = 0;
+        } else if line.starts_with("freq:") {
+            // "freq: 2412", or "freq: 5180.0" on newer iw
+            current_frequency = line
+                .strip_prefix("freq:")
+                .unwrap_or("0")
+                .trim()
+                .parse::<f32>()
+                .map(|mhz| mhz as u32)
+                .unwrap_or(0);
+        } else if line.starts_with("signal:") {
+            let signal_str = line.strip_prefix("signal:").unwrap_or("0").trim();
+            // Parse signal like "-45.00 dBm"
+            current_signal = signal_str
+                .split('.')
+                .next()
+                .unwrap_or("0")
+                .parse()
+                .unwrap_or(0);
+        } else if line.starts_with("SSID:") {
+            current_ssid = unescape_ssid(line.strip_prefix("SSID:").unwrap_or("").trim());
+        } else {
+            current_elements.feed(line);
+        }
+    }
+
+    // Don't forget the last network
+    save_network(
+        &current_bssid,
+        &current_ssid,
+        current_frequency,
+        current_signal,
+        current_elements,
+        current_connected,
+    );
+
+    networks.sort_by_key(|n| std::cmp::Reverse(n.signal_strength));
+    networks
+}
+
+/// Status from `wg show <iface> dump`. Also accepts the `wg show all dump`
+/// form, where every line starts with the interface name.
+pub fn parse_wireguard_dump(dump_output: &str, interface_name: &str) -> Option<WireGuardStatus> {
+    let mut lines = dump_output.lines().filter(|line| !line.trim().is_empty());
+
+    // private-key public-key listen-port fwmark
+    let interface: Vec<&str> = lines.next()?.split('\t').collect();
+    let interface = match interface.len() {
+        5.. => &interface[1..],
+        4 => &interface[..],
+        _ => return None,
+    };
+    let public_key = interface[1].to_string();
+    let listen_port = interface[2].parse().ok();
+
+    // public-key preshared-key endpoint allowed-ips latest-handshake
+    // transfer-rx transfer-tx persistent-keepalive
+    fn present(field: &str) -> Option<&str> {
+        (!field.is_empty() && field != "(none)").then_some(field)
+    }
+    let mut peers = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let fields = match fields.len() {
+            9.. => &fields[1..],
+            8 => &fields[..],
+            _ => continue,
+        };
+
+        peers.push(WireGuardPeerStatus {
+            public_key: fields[0].to_string(),
+            endpoint: present(fields[2]).map(str::to_string),
+            allowed_ips: present(fields[3])
+                .map(|ips| ips.split(',').map(|ip| ip.trim().to_string()).collect())
+                .unwrap_or_default(),
+            // 0 means no handshake yet
+            latest_handshake: fields[4]
+                .parse::<u64>()
+                .ok()
+                .filter(|&ts| ts > 0)
+                .and_then(|ts| {
+                    std::time::UNIX_EPOCH.checked_add(std::time::Duration::from_secs(ts))
+                }),
+            transfer_rx: fields[5].parse().unwrap_or(0),
+            transfer_tx: fields[6].parse().unwrap_or(0),
+            // A number of seconds or "off"
+            persistent_keepalive: fields[7].parse().ok().filter(|&seconds| seconds > 0),
+            rtt: None,
+        });
+    }
+
+    let connected = peers.iter().any(|p| p.latest_handshake.is_some());
+    let last_handshake = peers.iter().filter_map(|p| p.latest_handshake).max();
+
+    Some(WireGuardStatus {
+        interface: interface_name.to_string(),
+        public_key,
+        listen_port,
+        peers,
+        connected,
+        last_handshake,
+    })
+}
+
+/// DNS servers from `resolvectl status`, from the Global section only or from
+/// every section. Copes with each systemd release's layout: servers on one line
+/// or continued on the following ones, and "#server-name" suffixes for DoT.
+pub fn parse_resolvectl_dns(status: &str, global_only: bool) -> Vec<String> {
+    let mut dns_servers: Vec<String> = Vec::new();
+    let mut in_global = false;
+    let mut in_servers = false;
+
+    for line in status.lines() {
+        let trimmed = line.trim();
+        if !line.starts_with(char::is_whitespace) && !trimmed.is_empty() {
+            // Section header: "Global" or "Link 2 (eth0)"
+            in_global = trimmed == "Global";
+            in_servers = false;
+            continue;
+        }
+
+        // "Label: value", as opposed to a continued server list. IPv6 servers
+        // contain colons too, but never a colon followed by a space
+        let value = match trimmed.split_once(": ") {
+            Some((label, value)) => {
+                in_servers = label == "DNS Servers";
+                value
+            }
+            None if trimmed.ends_with(':') => {
+                in_servers = false;
+                continue;
+            }
+            None => trimmed,
+        };
+        if !in_servers || (global_only && !in_global) {
+            continue;
+        }
+
+        for server in value.split_whitespace() {
+            let address = server.split('#').next().unwrap_or_default();
+            let address = address.split('%').next().unwrap_or_default();
+            if address.parse::<std::net::IpAddr>().is_ok()
+                && !dns_servers.iter().any(|known| known == address)
+            {
+                dns_servers.push(address.to_string());
+            }
+        }
+    }
+
+    dns_servers
+}
+
+/// Round-trip time from ping output: "64 bytes from 10.0.0.1: icmp_seq=1 ttl=64 time=23.4 ms".
+pub(crate) fn parse_ping_rtt(output: &str) -> Option<std::time::Duration> {
+    let value = output.split("time=").nth(1)?.split_whitespace().next()?;
+    let millis: f64 = value.parse().ok()?;
+    Some(std::time::Duration::from_secs_f64(millis / 1000.0))
+}
+
+/// WireGuard gives up on a session after 180s (REJECT_AFTER_TIME); a peer
+/// without a handshake for that long is not reaching its endpoint.
+fn handshake_stale(latest_handshake: Option<std::time::SystemTime>) -> bool {
+    latest_handshake
+        .and_then(|at| at.elapsed().ok())
+        .is_none_or(|age| age > std::time::Duration::from_secs(180))
+}
+
+/// Pick a freshly resolved address for a peer, or None if its current one is
+/// still among them. Stays on the address family the tunnel already uses.
+fn next_endpoint(
+    current: Option<std::net::SocketAddr>,
+    resolved: &[std::net::SocketAddr],
+) -> Option<std::net::SocketAddr> {
+    if current.is_some_and(|current| resolved.contains(&current)) {
+        return None;
+    }
+    resolved
+        .iter()
+        .find(|address| current.is_some_and(|c| c.is_ipv4() == address.is_ipv4()))
+        .or_else(|| resolved.first())
+        .copied()
+}
+
+/// Parse `ip -j route show` output.
+fn parse_routes(json_str: &str, ipv6: bool) -> Result<Vec<Route>> {
+    if json_str.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let entries: Vec<serde_json::Value> = serde_json::from_str(json_str)?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            Some(Route {
+                destination: entry["dst"].as_str()?.to_string(),
+                gateway: entry["gateway"].as_str().map(String::from),
+                protocol: entry["protocol"].as_str().map(String::from),
+                metric: entry["metric"].as_u64().map(|m| m as u32),
+                ipv6,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_tx_power_from_iw_info() {
+        let info = "Interface wlan0\n\tifindex 3\n\ttype managed\n\tchannel 36 (5180 MHz), width: 80 MHz, center1: 5210 MHz\n\ttxpower 22.00 dBm\n";
+        assert_eq!(parse_tx_power(info), Some(22));
+        assert_eq!(parse_tx_power("\ttxpower 15.50 dBm"), Some(16));
+        assert_eq!(parse_tx_power("Interface wlan0\n"), None);
+    }
+
+    #[test]
+    fn picks_free_monitor_name() {
+        assert_eq!(free_monitor_name(&["lo", "wlan0"]), "mon0");
+        assert_eq!(free_monitor_name(&["mon0", "mon2"]), "mon1");
+    }
+
+    #[test]
+    fn parses_ip_route_json() {
+        let json = r#"[{"dst":"default","gateway":"192.168.1.1","dev":"eth0","protocol":"dhcp","metric":1024,"flags":[]},
+            {"dst":"192.168.1.0/24","dev":"eth0","protocol":"kernel","scope":"link","prefsrc":"192.168.1.5","flags":[]}]"#;
+
+        let routes = parse_routes(json, false).unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].destination, "default");
+        assert_eq!(routes[0].gateway.as_deref(), Some("192.168.1.1"));
+        assert_eq!(routes[0].metric, Some(1024));
+        assert_eq!(routes[1].gateway, None);
+        assert_eq!(routes[1].protocol.as_deref(), Some("kernel"));
+
+        assert!(parse_routes("", true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn groups_access_points_by_ssid() {
+        let scan = "\
+BSS 00:11:22:33:44:55(on wlan0)
+\tfreq: 2412
+\tsignal: -40.00 dBm
+\tSSID: Home
+BSS 00:11:22:33:44:66(on wlan0) -- associated
+\tfreq: 5180
+\tsignal: -60.00 dBm
+\tSSID: Home
+\tBSS Load:
+\t\t * station count: 3
+BSS 66:77:88:99:aa:bb(on wlan0)
+\tfreq: 2437
+\tsignal: -70.00 dBm
+\tSSID: Cafe
+";
+        let networks = parse_wifi_scan_results(scan);
+        assert_eq!(networks.len(), 3);
+
+        let groups = group_by_ssid(networks);
+        assert_eq!(groups.len(), 2);
+
+        // Strongest BSS stands for the group; the associated one is still flagged
+        let home = &groups[0];
+        assert_eq!(home.bssid, "00:11:22:33:44:55");
+        assert!(home.connected);
+        let bssids: Vec<&str> = home
+            .access_points
+            .iter()
+            .map(|ap| ap.bssid.as_str())
+            .collect();
+        assert_eq!(bssids, vec!["00:11:22:33:44:55", "00:11:22:33:44:66"]);
+        assert!(home.access_points[1].connected);
+
+        assert!(!groups[1].connected);
+        assert_eq!(groups[1].access_points.len(), 1);
+    }
+
+    #[test]
+    fn hints_at_stronger_band() {
+        let ap = |frequency, signal_strength, connected| AccessPoint {
+            bssid: String::new(),
+            signal_strength,
+            frequency,
+            channel: 0,
+            connected,
+        };
+        let mut network = WifiNetwork {
+            ssid: "Home".to_string(),
+            bssid: String::new(),
+            signal_strength: -50,
+            frequency: 2412,
+            channel: 1,
+            security: WifiSecurity::WPA2,
+            encryption: Vec::new(),
+            security_details: Default::default(),
+            connected: true,
+            in_history: false,
+            access_points: Vec::new(),
+        };
+
+        network.access_points = vec![ap(2412, -50, true), ap(5180, -58, false)];
+        assert_eq!(
+            network.band_steering_hint().map(|ap| ap.frequency),
+            Some(5180)
+        );
+
+        // Too weak to be worth roaming to
+        network.access_points = vec![ap(2412, -50, true), ap(5180, -72, false)];
+        assert!(network.band_steering_hint().is_none());
+
+        // Already on the faster band
+        network.access_points = vec![ap(5180, -58, true), ap(2412, -45, false)];
+        assert!(network.band_steering_hint().is_none());
+    }
+
+    #[test]
+    fn link_marks_matching_bssid_then_ssid() {
+        let network = |ssid: &str, bssid: &str| WifiNetwork {
+            ssid: ssid.to_string(),
+            bssid: bssid.to_string(),
+            signal_strength: -50,
+            frequency: 2412,
+            channel: 1,
+            security: WifiSecurity::WPA2,
+            encryption: Vec::new(),
+            security_details: Default::default(),
+            connected: false,
+            in_history: false,
+            access_points: Vec::new(),
+        };
+
+        let mut networks = vec![
+            network("Home", "00:11:22:33:44:55"),
+            network("Home", "00:11:22:33:44:66"),
+        ];
+        mark_connected(&mut networks, &network("Home", "00:11:22:33:44:66"));
+        assert!(!networks[0].connected && networks[1].connected);
+
+        // The associated BSS was deduplicated away: fall back to the SSID
+        let mut networks = vec![network("Cafe", "aa"), network("Home", "bb")];
+        mark_connected(&mut networks, &network("Home", "cc"));
+        assert!(!networks[0].connected && networks[1].connected);
+    }
+
+    #[test]
+    fn describes_nic_from_udev() {
+        let udev = parse_udev_properties(
+            "I:1234567\n\
+             E:ID_NET_DRIVER=igc\n\
+             E:ID_VENDOR_FROM_DATABASE=Intel Corporation\n\
+             E:ID_MODEL_FROM_DATABASE=Ethernet Controller I225-V\n\
+             G:systemd\n",
+        );
+        assert_eq!(udev["ID_NET_DRIVER"], "igc");
+        assert_eq!(udev.len(), 3);
+
+        assert_eq!(short_vendor("Intel Corporation"), "Intel");
+        assert_eq!(short_vendor("Realtek Semiconductor Co., Ltd."), "Realtek");
+        assert_eq!(short_vendor("Qualcomm Atheros"), "Qualcomm Atheros");
+
+        let hardware = NicHardware {
+            vendor: Some("Intel".to_string()),
+            model: Some("Ethernet Controller I225-V".to_string()),
+            driver: Some("igc".to_string()),
+        };
+        assert_eq!(hardware.summary(), "Intel Ethernet Controller I225-V (igc)");
+        let driver_only = NicHardware {
+            driver: Some("virtio_net".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(driver_only.summary(), "virtio_net");
+    }
+
+    #[test]
+    fn picks_reresolved_endpoint() {
+        let addr = |s: &str| s.parse::<std::net::SocketAddr>().unwrap();
+        let resolved = [addr("[2001:db8::7]:51820"), addr("198.51.100.7:51820")];
+
+        assert_eq!(next_endpoint(Some(resolved[1]), &resolved), None);
+        assert_eq!(
+            next_endpoint(Some(addr("203.0.113.1:51820")), &resolved),
+            Some(resolved[1])
+        );
+        assert_eq!(next_endpoint(None, &resolved), Some(resolved[0]));
+        assert_eq!(next_endpoint(None, &[]), None);
+
+        let now = std::time::SystemTime::now();
+        assert!(handshake_stale(None));
+        assert!(!handshake_stale(Some(now)));
+        assert!(handshake_stale(Some(
+            now - std::time::Duration::from_secs(600)
+        )));
+    }
+
+    #[test]
+    fn picks_peer_tunnel_address_and_parses_rtt() {
+        let allowed = |ips: &[&str]| ips.iter().map(|ip| ip.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            peer_tunnel_address(&allowed(&["0.0.0.0/0", "10.8.0.1/32"])),
+            Some("10.8.0.1")
+        );
+        assert_eq!(
+            peer_tunnel_address(&allowed(&["fd00::1/128"])),
+            Some("fd00::1")
+        );
+        assert_eq!(peer_tunnel_address(&allowed(&["10.8.0.0/24"])), None);
+
+        let ping = "PING 10.8.0.1 (10.8.0.1) from 10.8.0.2 wg0: 56(84) bytes of data.\n\
+                    64 bytes from 10.8.0.1: icmp_seq=1 ttl=64 time=23.4 ms\n";
+        assert_eq!(
+            parse_ping_rtt(ping),
+            Some(std::time::Duration::from_micros(23_400))
+        );
+        assert_eq!(parse_ping_rtt("1 packets transmitted, 0 received"), None);
+    }
+
+    #[test]
+    fn formats_signal_in_the_chosen_unit() {
+        use crate::config::SignalUnit;
+        assert_eq!(signal_percent(-20), 100);
+        assert_eq!(signal_percent(-60), 50);
+        assert_eq!(signal_percent(-95), 0);
+        assert_eq!(SignalUnit::Dbm.format(-60), "-60dBm");
+        assert_eq!(SignalUnit::Percent.format(-60), "50%");
+        assert_eq!(SignalUnit::Both.format(-60), "50% -60dBm");
+        assert_eq!(SignalUnit::Both.next(), SignalUnit::Dbm);
+    }
+
+    #[tokio::test]
+    async fn vanished_interface_is_left_out_of_stats() {
+        let names = vec!["lo".to_string(), "lantern-gone0".to_string()];
+        let stats = NetworkManager::new().collect_interface_stats(&names).await;
+        assert!(stats.contains_key("lo"));
+        assert!(!stats.contains_key("lantern-gone0"));
+    }
+}
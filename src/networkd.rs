@@ -0,0 +1,185 @@
+// src/networkd.rs - Which .link, .network and .netdev files networkd applies to an interface
+use crate::command::TimedOutput;
+use crate::sanitize;
+use crate::systemd::SystemdNetworkConfig;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::PathBuf;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Link,
+    Network,
+    NetDev,
+}
+
+impl FileKind {
+    // How `networkctl status` labels it
+    fn field(self) -> &'static str {
+        match self {
+            FileKind::Link => "Link File:",
+            FileKind::Network => "Network File:",
+            FileKind::NetDev => "NetDev File:",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FileKind::Link => ".link",
+            FileKind::Network => ".network",
+            FileKind::NetDev => ".netdev",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedFile {
+    pub kind: FileKind,
+    pub path: PathBuf,
+    pub contents: Option<String>, // None when it can't be read
+    pub lantern: bool,            // One lantern wrote
+}
+
+/// What networkd did with one interface
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MatchedFiles {
+    pub setup_state: Option<String>, // configured, configuring, unmanaged, failed, ...
+    pub files: Vec<MatchedFile>,
+    /// Lantern's .network file for the interface when networkd applies
+    /// another one instead, so lantern's settings have no effect
+    pub shadowed: Option<PathBuf>,
+}
+
+impl MatchedFiles {
+    pub fn managed(&self) -> bool {
+        self.setup_state
+            .as_deref()
+            .is_some_and(|state| state != "unmanaged")
+    }
+
+    pub fn network_file(&self) -> Option<&PathBuf> {
+        self.files
+            .iter()
+            .find(|file| file.kind == FileKind::Network)
+            .map(|file| &file.path)
+    }
+}
+
+/// The setup state and matched files from `networkctl status <iface>`.
+pub fn parse_status(status: &str) -> (Option<String>, Vec<(FileKind, PathBuf)>) {
+    let mut setup_state = None;
+    let mut files = Vec::new();
+    for line in status.lines().map(str::trim) {
+        // "State: routable (configured)"; "Online state:" is something else
+        if let Some(state) = line.strip_prefix("State:") {
+            setup_state = state
+                .split_once('(')
+                .and_then(|(_, rest)| rest.split_once(')'))
+                .map(|(state, _)| state.trim().to_string());
+            continue;
+        }
+        for kind in [FileKind::Link, FileKind::Network, FileKind::NetDev] {
+            if let Some(path) = line.strip_prefix(kind.field()).map(str::trim) {
+                // "n/a" when nothing matched
+                if path.starts_with('/') {
+                    files.push((kind, PathBuf::from(path)));
+                }
+            }
+        }
+    }
+    (setup_state, files)
+}
+
+pub async fn matched_files(interface: &str) -> Result<MatchedFiles> {
+    sanitize::interface_name(interface)?;
+    let output = Command::new("/usr/bin/networkctl")
+        .args(["status", "--no-pager", interface])
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "networkctl status {} failed: {}",
+            interface,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let systemd = SystemdNetworkConfig::new();
+    let (setup_state, files) = parse_status(&String::from_utf8_lossy(&output.stdout));
+    let files: Vec<MatchedFile> = files
+        .into_iter()
+        .map(|(kind, path)| MatchedFile {
+            kind,
+            contents: fs::read_to_string(&path).ok(),
+            lantern: systemd.written_by_lantern(&path, interface),
+            path,
+        })
+        .collect();
+    let mut matched = MatchedFiles {
+        setup_state,
+        files,
+        shadowed: None,
+    };
+    matched.shadowed = systemd
+        .generated_network_file(interface)
+        .filter(|generated| matched.network_file() != Some(generated));
+    Ok(matched)
+}
+
+/// A drop-in called `name` for the .network file networkd applies to the
+/// interface, so lantern's settings survive edits to that file and vice
+/// versa. None when networkd doesn't manage the interface.
+pub async fn drop_in_path(interface: &str, name: &str) -> Option<PathBuf> {
+    let output = Command::new("/usr/bin/networkctl")
+        .args(["status", "--no-pager", interface])
+        .timed_output()
+        .await
+        .ok()?;
+    let (_, files) = parse_status(&String::from_utf8_lossy(&output.stdout));
+    let (_, network_file) = files
+        .into_iter()
+        .find(|(kind, _)| *kind == FileKind::Network)?;
+    let file_name = network_file.file_name()?.to_str()?;
+    Some(
+        PathBuf::from("/etc/systemd/network")
+            .join(format!("{}.d", file_name))
+            .join(name),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_matched_files_from_networkctl_status() {
+        let status = "● 2: eth0\n                Link File: /usr/lib/systemd/network/99-default.link\n             Network File: /etc/systemd/network/10-eth0.network\n                    State: routable (configured)\n             Online state: online\n";
+        assert_eq!(
+            parse_status(status),
+            (
+                Some("configured".to_string()),
+                vec![
+                    (
+                        FileKind::Link,
+                        PathBuf::from("/usr/lib/systemd/network/99-default.link")
+                    ),
+                    (
+                        FileKind::Network,
+                        PathBuf::from("/etc/systemd/network/10-eth0.network")
+                    ),
+                ]
+            )
+        );
+
+        let status = "● 5: docker0\n  Link File: n/a\n  Network File: n/a\n  State: no-carrier (unmanaged)\n";
+        let (setup_state, files) = parse_status(status);
+        assert_eq!(setup_state.as_deref(), Some("unmanaged"));
+        assert!(files.is_empty());
+        let matched = MatchedFiles {
+            setup_state,
+            ..MatchedFiles::default()
+        };
+        assert!(!matched.managed());
+    }
+}
@@ -0,0 +1,124 @@
+// src/output.rs - How CLI messages are marked: Nerd Font glyphs and color on a terminal, plain words elsewhere
+use crate::icons;
+use once_cell::sync::Lazy;
+use std::io::IsTerminal;
+use std::sync::RwLock;
+
+static STYLE: Lazy<RwLock<Style>> = Lazy::new(|| RwLock::new(Style::detect(false, false)));
+
+/// What CLI output may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub glyphs: bool,
+    pub color: bool,
+}
+
+impl Style {
+    /// Glyphs and color only on a terminal, so logs and cron mail stay
+    /// readable; NO_COLOR (https://no-color.org) turns color off,
+    /// `--no-emoji` the glyphs and `--plain` both.
+    pub fn detect(plain: bool, no_emoji: bool) -> Self {
+        let terminal = std::io::stdout().is_terminal();
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self::resolve(terminal, no_color, plain, no_emoji)
+    }
+
+    fn resolve(terminal: bool, no_color: bool, plain: bool, no_emoji: bool) -> Self {
+        let fancy = terminal && !plain;
+        Self {
+            glyphs: fancy && !no_emoji,
+            color: fancy && !no_color,
+        }
+    }
+}
+
+/// Set the style from the command line, before anything is printed.
+pub fn configure(plain: bool, no_emoji: bool) {
+    *STYLE.write().unwrap_or_else(|e| e.into_inner()) = Style::detect(plain, no_emoji);
+}
+
+fn style() -> Style {
+    *STYLE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// What a message starts with: a glyph when glyphs are on, else a word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Success,
+    Error,
+    Warning,
+    Info,
+    Icon(&'static str), // Headings: WiFi, network, ...
+}
+
+pub fn success() -> String {
+    render(Mark::Success, style())
+}
+
+pub fn error() -> String {
+    render(Mark::Error, style())
+}
+
+pub fn warning() -> String {
+    render(Mark::Warning, style())
+}
+
+pub fn info() -> String {
+    render(Mark::Info, style())
+}
+
+/// Any other glyph from `icons`
+pub fn icon(glyph: &'static str) -> String {
+    render(Mark::Icon(glyph), style())
+}
+
+fn render(mark: Mark, style: Style) -> String {
+    let (glyph, word, color) = match mark {
+        Mark::Success => (icons::SUCCESS, "[ok]", "32"),
+        Mark::Error => (icons::ERROR, "[error]", "31"),
+        Mark::Warning => (icons::WARNING, "[warning]", "33"),
+        Mark::Info => (icons::INFO, "[info]", "34"),
+        Mark::Icon(glyph) => (glyph, "*", ""),
+    };
+    let text = if style.glyphs { glyph } else { word };
+    if style.color && !color.is_empty() {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_off_a_terminal_and_when_asked() {
+        let fancy = Style::resolve(true, false, false, false);
+        assert_eq!(
+            fancy,
+            Style {
+                glyphs: true,
+                color: true
+            }
+        );
+        assert_eq!(
+            render(Mark::Success, fancy),
+            format!("\x1b[32m{}\x1b[0m", icons::SUCCESS)
+        );
+
+        // Piped or redirected, as under cron
+        let piped = Style::resolve(false, false, false, false);
+        assert_eq!(render(Mark::Warning, piped), "[warning]");
+        assert_eq!(render(Mark::Icon(icons::WIFI), piped), "*");
+
+        let no_color = Style::resolve(true, true, false, false);
+        assert_eq!(render(Mark::Error, no_color), icons::ERROR);
+        let no_emoji = Style::resolve(true, false, false, true);
+        assert_eq!(render(Mark::Info, no_emoji), "\x1b[34m[info]\x1b[0m");
+        assert_eq!(
+            Style::resolve(true, false, true, false),
+            Style::resolve(false, false, false, false)
+        );
+    }
+}
@@ -0,0 +1,230 @@
+// src/pacing.rs - Adaptive refresh rates: slower when idle, calmer under link churn, pausable
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Link changes within CHURN_WINDOW that count as churn (containers coming
+// and going, a flapping bond)
+const CHURN_WINDOW: Duration = Duration::from_secs(5);
+const CHURN_CHANGES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RefreshSettings {
+    /// Slow polling down after this long without keys or link changes; 0 never does
+    pub idle_after_secs: u64,
+}
+
+impl Default for RefreshSettings {
+    fn default() -> Self {
+        Self {
+            idle_after_secs: 60,
+        }
+    }
+}
+
+/// How fast lantern is refreshing right now, as the header shows it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pace {
+    Live,
+    Idle,   // Nothing happened for a while; polls less often
+    Churn,  // Links keep changing; refreshes and frames are batched
+    Paused, // Frozen by the user; only manual refreshes
+}
+
+impl Pace {
+    pub fn label(self) -> &'static str {
+        match self {
+            Pace::Live => "LIVE",
+            Pace::Idle => "IDLE",
+            Pace::Churn => "BUSY",
+            Pace::Paused => "PAUSED",
+        }
+    }
+}
+
+/// Decides how often interfaces, statistics and frames are refreshed.
+/// Takes the current time as an argument so it can be tested.
+#[derive(Debug)]
+pub struct Pacer {
+    paused: bool,
+    idle_after: Option<Duration>,
+    last_activity: Instant,
+    link_changes: VecDeque<Instant>,
+    last_frame: Option<Instant>,
+    minimal: bool, // On a serial console every frame costs; see console::Profile
+}
+
+impl Pacer {
+    pub fn new(settings: &RefreshSettings) -> Self {
+        let mut pacer = Self {
+            paused: false,
+            idle_after: None,
+            last_activity: Instant::now(),
+            link_changes: VecDeque::new(),
+            last_frame: None,
+            minimal: false,
+        };
+        pacer.configure(settings);
+        pacer
+    }
+
+    pub fn configure(&mut self, settings: &RefreshSettings) {
+        self.idle_after =
+            Some(Duration::from_secs(settings.idle_after_secs)).filter(|d| !d.is_zero());
+    }
+
+    /// Draw and poll less often, for slow terminals
+    pub fn set_minimal(&mut self, minimal: bool) {
+        self.minimal = minimal;
+    }
+
+    /// A key press: back to full speed
+    pub fn activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    pub fn link_changed(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.link_changes.push_back(now);
+        while self
+            .link_changes
+            .front()
+            .is_some_and(|&at| now.duration_since(at) > CHURN_WINDOW)
+        {
+            self.link_changes.pop_front();
+        }
+    }
+
+    /// Returns whether refreshing is now paused.
+    pub fn toggle_pause(&mut self, now: Instant) -> bool {
+        self.paused = !self.paused;
+        self.last_activity = now;
+        self.paused
+    }
+
+    pub fn pace(&self, now: Instant) -> Pace {
+        if self.paused {
+            Pace::Paused
+        } else if self
+            .link_changes
+            .iter()
+            .filter(|&&at| now.duration_since(at) <= CHURN_WINDOW)
+            .count()
+            >= CHURN_CHANGES
+        {
+            Pace::Churn
+        } else if self
+            .idle_after
+            .is_some_and(|idle_after| now.duration_since(self.last_activity) > idle_after)
+        {
+            Pace::Idle
+        } else {
+            Pace::Live
+        }
+    }
+
+    /// Between traffic statistics refreshes; None while paused.
+    pub fn stats_interval(&self, now: Instant) -> Option<Duration> {
+        match self.pace(now) {
+            Pace::Live | Pace::Churn => Some(Duration::from_secs(1)),
+            Pace::Idle => Some(Duration::from_secs(3)),
+            Pace::Paused => None,
+        }
+    }
+
+    /// Between full interface refreshes when no link changed; None while paused.
+    pub fn interface_interval(&self, now: Instant) -> Option<Duration> {
+        match self.pace(now) {
+            Pace::Live | Pace::Churn => Some(Duration::from_secs(5)),
+            Pace::Idle => Some(Duration::from_secs(15)),
+            Pace::Paused => None,
+        }
+    }
+
+    /// How long to let netlink notifications pile up before refreshing.
+    pub fn link_debounce(&self, now: Instant) -> Duration {
+        match self.pace(now) {
+            Pace::Churn => Duration::from_secs(2),
+            _ => Duration::from_millis(250),
+        }
+    }
+
+    /// Whether a frame caused by background updates may be drawn yet. Frames
+    /// answering a key press are always drawn at once.
+    pub fn frame_due(&self, now: Instant) -> bool {
+        let gap = match self.pace(now) {
+            Pace::Churn => Duration::from_millis(500),
+            Pace::Idle | Pace::Paused => Duration::from_millis(250),
+            Pace::Live => Duration::from_millis(100),
+        };
+        let gap = if self.minimal {
+            gap.max(Duration::from_secs(1))
+        } else {
+            gap
+        };
+        self.last_frame
+            .is_none_or(|at| now.duration_since(at) >= gap)
+    }
+
+    pub fn mark_frame(&mut self, now: Instant) {
+        self.last_frame = Some(now);
+    }
+
+    /// How long to wait for a key before looking at updates again.
+    pub fn poll_timeout(&self, now: Instant) -> Duration {
+        match self.pace(now) {
+            _ if self.minimal => Duration::from_millis(500),
+            Pace::Live => Duration::from_millis(50),
+            _ => Duration::from_millis(200),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slows_down_when_idle_and_under_churn() {
+        let start = Instant::now();
+        let mut pacer = Pacer::new(&RefreshSettings::default());
+        pacer.activity(start);
+        assert_eq!(pacer.pace(start), Pace::Live);
+        assert_eq!(pacer.stats_interval(start), Some(Duration::from_secs(1)));
+
+        let later = start + Duration::from_secs(61);
+        assert_eq!(pacer.pace(later), Pace::Idle);
+        assert_eq!(
+            pacer.interface_interval(later),
+            Some(Duration::from_secs(15))
+        );
+        pacer.activity(later);
+        assert_eq!(pacer.pace(later), Pace::Live);
+
+        for ms in 0..CHURN_CHANGES as u64 {
+            pacer.link_changed(later + Duration::from_millis(ms * 100));
+        }
+        let busy = later + Duration::from_secs(1);
+        assert_eq!(pacer.pace(busy), Pace::Churn);
+        assert_eq!(pacer.link_debounce(busy), Duration::from_secs(2));
+        pacer.mark_frame(busy);
+        assert!(!pacer.frame_due(busy + Duration::from_millis(200)));
+        assert!(pacer.frame_due(busy + Duration::from_millis(500)));
+        assert_eq!(pacer.pace(busy + Duration::from_secs(6)), Pace::Live);
+
+        assert!(pacer.toggle_pause(busy));
+        assert_eq!(pacer.pace(busy), Pace::Paused);
+        assert_eq!(pacer.stats_interval(busy), None);
+        assert!(!pacer.toggle_pause(busy));
+
+        pacer.configure(&RefreshSettings { idle_after_secs: 0 });
+        assert_eq!(pacer.pace(busy + Duration::from_secs(3600)), Pace::Live);
+
+        pacer.set_minimal(true);
+        pacer.mark_frame(busy);
+        assert!(!pacer.frame_due(busy + Duration::from_millis(500)));
+        assert!(pacer.frame_due(busy + Duration::from_secs(1)));
+        assert_eq!(pacer.poll_timeout(busy), Duration::from_millis(500));
+    }
+}
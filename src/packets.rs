@@ -0,0 +1,419 @@
+// src/packets.rs - Live packet summary: protocol, source, destination and length of what crosses an interface
+use crate::rfmon::{Capture, PacketSocket};
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Packets summarized per second at most; the rest are only counted, so a
+/// busy link doesn't flood the UI
+pub const SAMPLES_PER_SECOND: u32 = 20;
+/// Summaries the pane keeps, newest last
+const KEEP: usize = 200;
+/// Enough for the IP and transport headers, which is all a summary reads
+const SNAP_LENGTH: usize = 128;
+
+const ETH_P_IP: u16 = 0x0800;
+const ETH_P_ARP: u16 = 0x0806;
+const ETH_P_IPV6: u16 = 0x86dd;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+/// One line of the pane
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketSummary {
+    pub direction: Direction,
+    pub protocol: String,
+    pub source: String,
+    pub destination: String,
+    /// On the wire, without the link-layer header
+    pub length: usize,
+    /// TCP flags, the ICMP message or what ARP asks
+    pub info: String,
+}
+
+/// What the capture reports: a summary when the sample allows one, and the
+/// running totals either way
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub packet: Option<PacketSummary>,
+    pub seen: u64,
+    pub bytes: u64,
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        buf.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn ipv4(buf: &[u8], offset: usize) -> Option<Ipv4Addr> {
+    let octets: [u8; 4] = buf.get(offset..offset + 4)?.try_into().ok()?;
+    Some(Ipv4Addr::from(octets))
+}
+
+fn ipv6(buf: &[u8], offset: usize) -> Option<Ipv6Addr> {
+    let octets: [u8; 16] = buf.get(offset..offset + 16)?.try_into().ok()?;
+    Some(Ipv6Addr::from(octets))
+}
+
+/// tcpdump's notation: S, F, R, P, and `.` for ACK
+fn tcp_flags(flags: u8) -> String {
+    let mut text = String::from("[");
+    for (bit, c) in [(0x02, 'S'), (0x01, 'F'), (0x04, 'R'), (0x08, 'P')] {
+        if flags & bit != 0 {
+            text.push(c);
+        }
+    }
+    if flags & 0x10 != 0 {
+        text.push('.');
+    }
+    text.push(']');
+    text
+}
+
+fn icmp_info(kind: u8, v6: bool) -> String {
+    match (v6, kind) {
+        (false, 0) | (true, 129) => "echo reply".to_string(),
+        (false, 8) | (true, 128) => "echo request".to_string(),
+        (false, 3) | (true, 1) => "unreachable".to_string(),
+        (false, 11) | (true, 3) => "time exceeded".to_string(),
+        (true, 133) => "router solicitation".to_string(),
+        (true, 134) => "router advertisement".to_string(),
+        (true, 135) => "neighbor solicitation".to_string(),
+        (true, 136) => "neighbor advertisement".to_string(),
+        (_, kind) => format!("type {}", kind),
+    }
+}
+
+/// Protocol, ports and info of an IP payload; `header` is None for a
+/// fragment without the transport header
+fn transport(protocol: u8, header: Option<&[u8]>) -> (String, Option<(u16, u16)>, String) {
+    let ports = |header: &[u8]| Some((read_u16(header, 0)?, read_u16(header, 2)?));
+    let name = match protocol {
+        1 => "ICMP",
+        2 => "IGMP",
+        6 => "TCP",
+        17 => "UDP",
+        47 => "GRE",
+        50 => "ESP",
+        58 => "ICMPv6",
+        other => return (format!("IP/{}", other), None, String::new()),
+    };
+    let (ports, info) = match (protocol, header) {
+        (6, Some(header)) if header.len() >= 14 => (ports(header), tcp_flags(header[13])),
+        (17, Some(header)) => (ports(header), String::new()),
+        (1 | 58, Some([kind, ..])) => (None, icmp_info(*kind, protocol == 58)),
+        _ => (None, String::new()),
+    };
+    (name.to_string(), ports, info)
+}
+
+/// An address with its port, bracketed for IPv6 as in URLs
+fn endpoint(address: IpAddr, port: Option<u16>) -> String {
+    match (address, port) {
+        (IpAddr::V6(v6), Some(port)) => format!("[{}]:{}", v6, port),
+        (address, Some(port)) => format!("{}:{}", address, port),
+        (address, None) => address.to_string(),
+    }
+}
+
+/// Summarize a packet from the link-layer protocol and what follows the
+/// link-layer header, which is all a datagram packet socket hands over
+pub fn summarize(
+    ethertype: u16,
+    payload: &[u8],
+    length: usize,
+    direction: Direction,
+) -> PacketSummary {
+    let mut summary = PacketSummary {
+        direction,
+        protocol: format!("0x{:04x}", ethertype),
+        source: String::new(),
+        destination: String::new(),
+        length,
+        info: String::new(),
+    };
+    let ip = match ethertype {
+        ETH_P_IP => {
+            summary.protocol = "IPv4".to_string();
+            ipv4(payload, 12)
+                .zip(ipv4(payload, 16))
+                .map(|(source, destination)| {
+                    let header_length = (payload[0] & 0x0f) as usize * 4;
+                    // Only the first fragment carries the transport header
+                    let fragment = read_u16(payload, 6).unwrap_or(0) & 0x1fff != 0;
+                    let header = payload.get(header_length..).filter(|_| !fragment);
+                    (source.into(), destination.into(), payload[9], header)
+                })
+        }
+        ETH_P_IPV6 => {
+            summary.protocol = "IPv6".to_string();
+            ipv6(payload, 8)
+                .zip(ipv6(payload, 24))
+                .map(|(source, destination)| {
+                    (
+                        source.into(),
+                        destination.into(),
+                        payload[6],
+                        payload.get(40..),
+                    )
+                })
+        }
+        ETH_P_ARP => {
+            summary.protocol = "ARP".to_string();
+            // Ethernet and IPv4 only, which is all ARP is used for in practice
+            if let (Some(sender), Some(target)) = (ipv4(payload, 14), ipv4(payload, 24)) {
+                summary.source = sender.to_string();
+                summary.destination = target.to_string();
+                summary.info = match read_u16(payload, 6) {
+                    Some(1) => format!("who-has {} tell {}", target, sender),
+                    Some(2) => format!("{} is-at {}", sender, mac(&payload[8..14])),
+                    _ => String::new(),
+                };
+            }
+            None
+        }
+        0x888e => {
+            summary.protocol = "EAPOL".to_string();
+            None
+        }
+        0x88cc => {
+            summary.protocol = "LLDP".to_string();
+            None
+        }
+        _ => None,
+    };
+    if let Some((source, destination, protocol, header)) = ip {
+        let (name, ports, info) = transport(protocol, header);
+        summary.protocol = name;
+        summary.source = endpoint(source, ports.map(|(port, _)| port));
+        summary.destination = endpoint(destination, ports.map(|(_, port)| port));
+        summary.info = info;
+    }
+    summary
+}
+
+fn mac(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// At most `limit` packets per second
+#[derive(Debug)]
+struct Sampler {
+    limit: u32,
+    window: Instant,
+    taken: u32,
+}
+
+impl Sampler {
+    fn new(limit: u32, now: Instant) -> Self {
+        Self {
+            limit,
+            window: now,
+            taken: 0,
+        }
+    }
+
+    fn take(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.window) >= Duration::from_secs(1) {
+            self.window = now;
+            self.taken = 0;
+        }
+        if self.taken < self.limit {
+            self.taken += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The pane while it is open
+#[derive(Debug)]
+pub struct PacketView {
+    pub interface: String,
+    pub stop: Arc<AtomicBool>,
+    pub started: Instant,
+    pub capture: Capture,
+    pub recent: VecDeque<PacketSummary>,
+    pub seen: u64,
+    pub bytes: u64,
+    summarized: u64,
+}
+
+impl PacketView {
+    pub fn new(interface: &str) -> Self {
+        Self {
+            interface: interface.to_string(),
+            stop: Arc::new(AtomicBool::new(false)),
+            started: Instant::now(),
+            capture: Capture::Starting,
+            recent: VecDeque::new(),
+            seen: 0,
+            bytes: 0,
+            summarized: 0,
+        }
+    }
+
+    pub fn record(&mut self, sample: Sample) {
+        self.seen = sample.seen;
+        self.bytes = sample.bytes;
+        if let Some(packet) = sample.packet {
+            self.summarized += 1;
+            if self.recent.len() == KEEP {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(packet);
+        }
+    }
+
+    /// Packets only counted because the sample was full
+    pub fn skipped(&self) -> u64 {
+        self.seen.saturating_sub(self.summarized)
+    }
+}
+
+/// Summarize packets on `interface` until `stop` is set or `on_sample`
+/// returns false. Only the headers are copied out of the kernel.
+pub fn capture(
+    interface: &str,
+    stop: &AtomicBool,
+    mut on_sample: impl FnMut(Sample) -> bool,
+) -> Result<()> {
+    let socket = PacketSocket::open(interface, libc::SOCK_DGRAM)
+        .with_context(|| format!("Failed to listen on {} (needs root)", interface))?;
+    let mut buf = [0u8; SNAP_LENGTH];
+    let mut sampler = Sampler::new(SAMPLES_PER_SECOND, Instant::now());
+    let (mut seen, mut bytes) = (0u64, 0u64);
+    let mut reported = 0u64;
+    while !stop.load(Ordering::Relaxed) {
+        let Some((length, ethertype, outgoing)) = socket.recv_from(&mut buf)? else {
+            // Quiet for a moment: bring the totals up to date
+            if reported != seen {
+                reported = seen;
+                if !on_sample(Sample {
+                    packet: None,
+                    seen,
+                    bytes,
+                }) {
+                    break;
+                }
+            }
+            continue;
+        };
+        seen += 1;
+        bytes += length as u64;
+        if !sampler.take(Instant::now()) {
+            continue;
+        }
+        let direction = if outgoing {
+            Direction::Out
+        } else {
+            Direction::In
+        };
+        let packet = summarize(
+            ethertype,
+            &buf[..length.min(SNAP_LENGTH)],
+            length,
+            direction,
+        );
+        reported = seen;
+        if !on_sample(Sample {
+            packet: Some(packet),
+            seen,
+            bytes,
+        }) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_tcp() -> Vec<u8> {
+        let mut packet = vec![0x45, 0, 0, 60, 0, 0, 0x40, 0, 64, 6, 0, 0];
+        packet.extend([192, 168, 1, 5, 93, 184, 216, 34]);
+        packet.extend(51234u16.to_be_bytes());
+        packet.extend(443u16.to_be_bytes());
+        packet.extend([0; 9]);
+        packet.push(0x12); // SYN, ACK
+        packet
+    }
+
+    #[test]
+    fn summarizes_common_protocols() {
+        let tcp = summarize(ETH_P_IP, &ipv4_tcp(), 60, Direction::In);
+        assert_eq!(
+            (
+                tcp.protocol.as_str(),
+                tcp.source.as_str(),
+                tcp.destination.as_str(),
+                tcp.info.as_str()
+            ),
+            ("TCP", "192.168.1.5:51234", "93.184.216.34:443", "[S.]")
+        );
+
+        let mut ping = vec![0x60, 0, 0, 0, 0, 8, 58, 64];
+        ping.extend(Ipv6Addr::LOCALHOST.octets());
+        ping.extend("fe80::1".parse::<Ipv6Addr>().unwrap().octets());
+        ping.extend([128, 0]);
+        let ping = summarize(ETH_P_IPV6, &ping, 48, Direction::Out);
+        assert_eq!(ping.protocol, "ICMPv6");
+        assert_eq!(
+            (ping.source.as_str(), ping.info.as_str()),
+            ("::1", "echo request")
+        );
+
+        let mut arp = vec![0, 1, 8, 0, 6, 4, 0, 1];
+        arp.extend([0xa4, 0xc3, 0xf0, 0x85, 0x12, 0x9e, 192, 168, 1, 5]);
+        arp.extend([0; 6]);
+        arp.extend([192, 168, 1, 1]);
+        let arp = summarize(ETH_P_ARP, &arp, 28, Direction::Out);
+        assert_eq!(arp.info, "who-has 192.168.1.1 tell 192.168.1.5");
+
+        // Truncated or unknown: named, never a panic
+        assert_eq!(
+            summarize(ETH_P_IP, &[0x45], 20, Direction::In).protocol,
+            "IPv4"
+        );
+        assert_eq!(summarize(0x88cc, &[], 60, Direction::In).protocol, "LLDP");
+    }
+
+    #[test]
+    fn samples_are_limited_per_second() {
+        let start = Instant::now();
+        let mut sampler = Sampler::new(2, start);
+        assert!(sampler.take(start));
+        assert!(sampler.take(start));
+        assert!(!sampler.take(start + Duration::from_millis(900)));
+        assert!(sampler.take(start + Duration::from_secs(1)));
+
+        let mut view = PacketView::new("eth0");
+        let packet = summarize(ETH_P_IP, &ipv4_tcp(), 60, Direction::In);
+        view.record(Sample {
+            packet: Some(packet),
+            seen: 5,
+            bytes: 300,
+        });
+        view.record(Sample {
+            packet: None,
+            seen: 9,
+            bytes: 540,
+        });
+        assert_eq!((view.recent.len(), view.skipped()), (1, 8));
+    }
+}
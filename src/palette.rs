@@ -0,0 +1,303 @@
+// src/palette.rs - Command palette: every action, found by typing part of its name
+use crossterm::event::KeyCode;
+
+/// What picking an entry does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteAction {
+    /// The same as pressing the key on the main screen
+    Key(KeyCode),
+    RestartNetworkd,
+    ExportReport,
+    ScreenshotAnsi,
+}
+
+#[derive(Debug)]
+pub struct PaletteEntry {
+    pub label: &'static str,
+    /// Other words it should be found by
+    keywords: &'static str,
+    pub action: PaletteAction,
+}
+
+impl PaletteEntry {
+    /// The key that does the same, for the entries that have one
+    pub fn key_hint(&self) -> Option<String> {
+        let PaletteAction::Key(code) = self.action else {
+            return None;
+        };
+        Some(match code {
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            other => format!("{:?}", other),
+        })
+    }
+}
+
+const fn entry(label: &'static str, keywords: &'static str, action: PaletteAction) -> PaletteEntry {
+    PaletteEntry {
+        label,
+        keywords,
+        action,
+    }
+}
+
+const fn key(label: &'static str, keywords: &'static str, c: char) -> PaletteEntry {
+    entry(label, keywords, PaletteAction::Key(KeyCode::Char(c)))
+}
+
+pub const ENTRIES: &[PaletteEntry] = &[
+    key("Connect to WiFi", "wireless scan networks ssid", 'w'),
+    key(
+        "Edit interface configuration",
+        "ip address dns gateway dhcp static",
+        'e',
+    ),
+    key(
+        "Bring interface up or down",
+        "toggle enable disable link state",
+        'u',
+    ),
+    key("Refresh interfaces", "reload update rescan", 'r'),
+    entry(
+        "Restart networkd",
+        "systemd-networkd service reset",
+        PaletteAction::RestartNetworkd,
+    ),
+    entry(
+        "Export report",
+        "save json interfaces dump snapshot",
+        PaletteAction::ExportReport,
+    ),
+    entry(
+        "Save screen as text",
+        "screenshot dump capture bug report",
+        PaletteAction::Key(KeyCode::F(2)),
+    ),
+    entry(
+        "Save screen with colors (ANSI)",
+        "screenshot dump capture bug report",
+        PaletteAction::ScreenshotAnsi,
+    ),
+    key("WiFi hotspot or its clients", "access point ap tether", 'h'),
+    key("Share network as QR code", "password wifi hotspot", 's'),
+    key(
+        "WiFi site survey",
+        "gps gpsd war walking heatmap csv scan",
+        'S',
+    ),
+    key(
+        "Copy a value to the clipboard",
+        "yank ip address mac public key report osc52",
+        'y',
+    ),
+    key(
+        "Jobs: queued and running changes",
+        "queue pending failed retry cancel hotspot profile progress",
+        'J',
+    ),
+    key("Toggle promiscuous mode", "capture sniff", 'p'),
+    key(
+        "Identify: blink the port LED",
+        "locate find cable rack ethtool",
+        'i',
+    ),
+    key("Monitor interface", "wireless mon0 capture", 'm'),
+    key(
+        "RF monitor: hidden SSIDs and probing devices",
+        "probe request beacon sniff listen passive clients",
+        'M',
+    ),
+    key(
+        "Live packet summary",
+        "tcpdump capture sniff traffic arriving",
+        'v',
+    ),
+    key("Static ARP/NDP entries", "neighbors neighbours mac", 'A'),
+    key("Routing daemon status", "bgp ospf frr bird routes", 'b'),
+    key(
+        "Tune NIC queues",
+        "ring buffer rss ethtool indirection",
+        't',
+    ),
+    key(
+        "Topology map",
+        "tree graph bridge bond vlan veth namespace ports",
+        'g',
+    ),
+    key("Edit config.toml", "settings editor", 'c'),
+    key("Edit generated .network file", "networkd editor", 'E'),
+    key("networkd files", "link netdev network preview", 'F'),
+    key(
+        "Doctor: check networkd files",
+        "lint problems overlap duplicate address dhcp static requiredforonline",
+        'C',
+    ),
+    key(
+        "Adopt interface from another manager",
+        "networkmanager dhcpcd netctl wicked",
+        'T',
+    ),
+    key("Ignore or manage interface", "hide", 'I'),
+    key("Show or hide ignored interfaces", "", '.'),
+    key("Keep temporary changes", "confirm pending revert", 'K'),
+    key("PXE boot server", "tftp dnsmasq netboot", 'P'),
+    key(
+        "Global DNS settings",
+        "systemd-resolved fallback dot dns-over-tls llmnr mdns",
+        'D',
+    ),
+    key(
+        "DNS leak test",
+        "vpn wireguard resolver privacy diagnostics",
+        'L',
+    ),
+    key(
+        "Rogue DHCP servers",
+        "dhcp offer discover conflict ip changed lease diagnostics",
+        'O',
+    ),
+    key(
+        "Retry DHCP",
+        "renew lease link-local 169.254 apipa ipv4ll zeroconf reconfigure",
+        'N',
+    ),
+    key(
+        "Why is my network broken?",
+        "troubleshoot wizard diagnose no internet carrier gateway arp dns http fix",
+        'W',
+    ),
+    key(
+        "Share network over USB",
+        "usb0 gadget rndis ecm tether raspberry pi phone dhcp nat masquerade",
+        'X',
+    ),
+    key(
+        "IPv4/IPv6 reachability test",
+        "connectivity internet down ping dual-stack ra diagnostics",
+        'R',
+    ),
+    key(
+        "HTTP check through this interface",
+        "curl url tls certificate ttfb timing uplink diagnostics",
+        'H',
+    ),
+    key(
+        "Prefer IPv4 or IPv6",
+        "happy eyeballs gai.conf precedence getaddrinfo slow dual-stack",
+        'G',
+    ),
+    key(
+        "Speed tests and history",
+        "bandwidth throughput isp download upload chart",
+        'B',
+    ),
+    key("Freeze or resume refreshes", "pause", 'f'),
+    key(
+        "Zero traffic counters",
+        "reset baseline statistics bytes",
+        'z',
+    ),
+    key(
+        "Show total traffic counters",
+        "restore baseline statistics",
+        'Z',
+    ),
+    key("Cycle WiFi signal unit", "dbm percent", '%'),
+    key(
+        "Cycle rate units",
+        "bits bytes mbit mib si binary speed",
+        'U',
+    ),
+    entry(
+        "Next detail tab",
+        "ipv6 wireguard routes queues logs",
+        PaletteAction::Key(KeyCode::Right),
+    ),
+    entry("Previous detail tab", "", PaletteAction::Key(KeyCode::Left)),
+    entry(
+        "Reload config.toml",
+        "settings",
+        PaletteAction::Key(KeyCode::F(5)),
+    ),
+    entry(
+        "Debug overlay",
+        "performance timings",
+        PaletteAction::Key(KeyCode::F(12)),
+    ),
+    key("Quit", "exit", 'q'),
+];
+
+/// How well `query` matches `text`: its characters in order, spaces aside,
+/// scoring more for runs and starts of words and less for gaps. None when
+/// it doesn't.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        match previous {
+            Some(previous) if previous + 1 == found => score += 5,
+            // Scattered letters say little
+            Some(previous) => score -= (found - previous - 1).min(3) as i32,
+            None => {}
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// The entries matching `query`, best first; all of them, in order, when empty
+pub fn matches(query: &str) -> Vec<&'static PaletteEntry> {
+    let mut scored: Vec<(i32, &PaletteEntry)> = ENTRIES
+        .iter()
+        .filter_map(|entry| {
+            let label = fuzzy_score(query, entry.label);
+            // A label match wins over one that needs the keywords
+            let keywords = fuzzy_score(query, &format!("{} {}", entry.label, entry.keywords))
+                .map(|score| score - 10);
+            label.max(keywords).map(|score| (score, entry))
+        })
+        .collect();
+    // Stable, so equal scores keep the list's order
+    scored.sort_by_key(|(score, _)| -score);
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_actions_by_name() {
+        assert_eq!(matches("").len(), ENTRIES.len());
+        assert_eq!(matches("connect wifi")[0].label, "Connect to WiFi");
+        assert_eq!(
+            matches("restart networkd")[0].action,
+            PaletteAction::RestartNetworkd
+        );
+        assert_eq!(matches("exrep")[0].action, PaletteAction::ExportReport);
+        // Found by what it does, not only what it is called
+        assert_eq!(matches("bgp")[0].label, "Routing daemon status");
+        assert!(matches("zzz").is_empty());
+
+        assert!(fuzzy_score("cw", "Connect to WiFi").is_some());
+        assert!(fuzzy_score("wc", "Connect to WiFi").is_none());
+        assert!(fuzzy_score("con", "Connect") > fuzzy_score("cnt", "Connect"));
+
+        assert_eq!(ENTRIES[0].key_hint().as_deref(), Some("w"));
+        let report = ENTRIES
+            .iter()
+            .find(|entry| entry.action == PaletteAction::ExportReport)
+            .unwrap();
+        assert_eq!(report.key_hint(), None);
+    }
+}
@@ -0,0 +1,244 @@
+// src/parser_corpus.rs - Snapshot and fuzz tests over captured external tool output
+//
+// tests/corpus/<tool>/ holds real output from the tools lantern scrapes, one
+// file per release or situation worth keeping. Each sample has a .json
+// snapshot of what the parser made of it; a format change that alters the
+// result fails here instead of in front of a user. After adding a sample or
+// changing a parser on purpose, regenerate the snapshots and review the diff:
+//
+//     UPDATE_CORPUS=1 cargo test --lib parser_corpus
+//
+// The fuzz test feeds every parser truncated, shuffled and corrupted variants
+// of every sample. Scraped output is whatever the installed version prints,
+// so the parsers must never panic on it.
+use crate::iwd::{parse_device_list, parse_iw_scan_output, parse_station_rssi};
+use crate::network::{
+    parse_iw_link_info, parse_resolvectl_dns, parse_wifi_scan_results, parse_wireguard_dump,
+};
+use serde_json::{json, Value};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+struct Tool {
+    dir: &'static str, // Under tests/corpus
+    parse: fn(&str) -> Value,
+}
+
+const TOOLS: &[Tool] = &[
+    Tool {
+        dir: "iw-scan",
+        parse: |output| {
+            json!({
+                "network": parse_wifi_scan_results(output),
+                "iwd": parse_iw_scan_output(output),
+            })
+        },
+    },
+    Tool {
+        dir: "iw-link",
+        parse: |output| json!(parse_iw_link_info(output)),
+    },
+    Tool {
+        dir: "wg-dump",
+        parse: |output| json!(parse_wireguard_dump(output, "wg0")),
+    },
+    Tool {
+        dir: "resolvectl-status",
+        parse: |output| {
+            json!({
+                "global": parse_resolvectl_dns(output, true),
+                "all": parse_resolvectl_dns(output, false),
+            })
+        },
+    },
+    Tool {
+        dir: "iwctl-device-list",
+        parse: |output| json!(parse_device_list(output)),
+    },
+    Tool {
+        dir: "iwctl-station-show",
+        parse: |output| json!(parse_station_rssi(output)),
+    },
+];
+
+fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+/// The .txt samples for a tool, sorted so failures come out in a stable order.
+fn samples(tool: &Tool) -> Vec<(PathBuf, String)> {
+    let dir = corpus_dir().join(tool.dir);
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {}", dir.display(), e))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "No samples in {}", dir.display());
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let contents = fs::read_to_string(&path).unwrap();
+            (path, contents)
+        })
+        .collect()
+}
+
+#[test]
+fn parsers_match_snapshots() {
+    let update = std::env::var_os("UPDATE_CORPUS").is_some();
+    let mut mismatched = Vec::new();
+
+    for tool in TOOLS {
+        for (path, sample) in samples(tool) {
+            let actual = serde_json::to_string_pretty(&(tool.parse)(&sample)).unwrap() + "\n";
+            let snapshot = path.with_extension("json");
+            if update {
+                fs::write(&snapshot, &actual).unwrap();
+                continue;
+            }
+            match fs::read_to_string(&snapshot) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => mismatched.push(format!(
+                    "{}\n--- expected\n{}\n--- actual\n{}",
+                    snapshot.display(),
+                    expected,
+                    actual
+                )),
+                Err(_) => mismatched.push(format!("{} is missing", snapshot.display())),
+            }
+        }
+    }
+
+    assert!(
+        mismatched.is_empty(),
+        "Parser output changed (rerun with UPDATE_CORPUS=1 if intended):\n\n{}",
+        mismatched.join("\n\n")
+    );
+}
+
+/// Small xorshift generator so the fuzz cases are the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound.max(1) as u64) as usize
+    }
+}
+
+// Fragments that steer the parsers into their edge cases
+const FRAGMENTS: &[&str] = &[
+    "\t",
+    " ",
+    "\n",
+    ":",
+    ": ",
+    "(",
+    ")",
+    "#",
+    "%",
+    "-",
+    ".",
+    ",",
+    "0",
+    "-0",
+    "off",
+    "(none)",
+    "99999999999999999999",
+    "-99999999999",
+    "NaN",
+    "é",
+    "\u{1b}[",
+    "\u{1b}[1;90m",
+    "BSS ",
+    "BSS Load:",
+    "SSID:",
+    "freq: ",
+    "signal: ",
+    "dBm",
+    "RSN:\t * ",
+    "* Authentication suites: ",
+    "Global",
+    "Link 2 (eth0)",
+    "DNS Servers: ",
+    "Name",
+    "RSSI",
+    "Connected to ",
+];
+
+fn mutate(sample: &str, rng: &mut Rng) -> String {
+    let mut chars: Vec<char> = sample.chars().collect();
+    for _ in 0..=rng.below(4) {
+        let at = rng.below(chars.len() + 1);
+        match rng.below(4) {
+            0 => {
+                let fragment = FRAGMENTS[rng.below(FRAGMENTS.len())];
+                chars.splice(at..at, fragment.chars());
+            }
+            1 => {
+                let end = (at + rng.below(16)).min(chars.len());
+                chars.drain(at..end);
+            }
+            2 if at < chars.len() => chars[at] = ['\t', ' ', ':', '\n', '0'][rng.below(5)],
+            _ => chars.truncate(at),
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// Truncations at every line end (and every fifth byte), each line dropped,
+/// reordered and re-whitespaced copies, and random corruptions.
+fn variants(sample: &str, rng: &mut Rng) -> Vec<String> {
+    let mut variants: Vec<String> = sample
+        .char_indices()
+        .filter(|(i, c)| *c == '\n' || i % 5 == 0)
+        .map(|(i, _)| sample[..i].to_string())
+        .collect();
+
+    let lines: Vec<&str> = sample.lines().collect();
+    for skip in 0..lines.len() {
+        let mut dropped = lines.clone();
+        dropped.remove(skip);
+        variants.push(dropped.join("\n"));
+    }
+    let mut reversed = lines.clone();
+    reversed.reverse();
+    variants.push(reversed.join("\n"));
+    variants.push(sample.replace('\t', " "));
+    variants.push(sample.replace('\t', ""));
+    variants.push(sample.replace("\n", "\r\n"));
+
+    variants.extend((0..200).map(|_| mutate(sample, rng)));
+    variants
+}
+
+#[test]
+fn parsers_survive_mangled_output() {
+    let mut rng = Rng(0x5eed_1a77);
+    let all_samples: Vec<String> = TOOLS
+        .iter()
+        .flat_map(samples)
+        .map(|(_, sample)| sample)
+        .collect();
+
+    let mut failure = None;
+    'fuzz: for sample in &all_samples {
+        for input in variants(sample, &mut rng) {
+            for tool in TOOLS {
+                if panic::catch_unwind(AssertUnwindSafe(|| (tool.parse)(&input))).is_err() {
+                    failure = Some((tool.dir, input));
+                    break 'fuzz;
+                }
+            }
+        }
+    }
+
+    if let Some((parser, input)) = failure {
+        panic!("The {} parser panicked on {:?}", parser, input);
+    }
+}
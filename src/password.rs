@@ -0,0 +1,163 @@
+// src/password.rs - Passphrase generation, strength estimation and WPA PSK derivation
+#![allow(dead_code)] // Generator is shared by hotspot and future WireGuard dialogs
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Read;
+
+// Unambiguous characters only (no 0/O, 1/l/I) so passphrases can be read aloud or typed on phones
+const PASSPHRASE_ALPHABET: &[u8] = b"abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const GROUP_LENGTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Strength {
+    VeryWeak,
+    Weak,
+    Fair,
+    Strong,
+    VeryStrong,
+}
+
+impl Strength {
+    pub fn from_bits(bits: f64) -> Self {
+        match bits {
+            b if b < 28.0 => Strength::VeryWeak,
+            b if b < 36.0 => Strength::Weak,
+            b if b < 60.0 => Strength::Fair,
+            b if b < 80.0 => Strength::Strong,
+            _ => Strength::VeryStrong,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Strength::VeryWeak => "Very weak",
+            Strength::Weak => "Weak",
+            Strength::Fair => "Fair",
+            Strength::Strong => "Strong",
+            Strength::VeryStrong => "Very strong",
+        }
+    }
+}
+
+/// Generate a passphrase like `k7Qm-Rx2v-9HnT-pW4e` from the kernel CSPRNG.
+/// Each group adds ~23 bits of entropy; 4 groups gives ~93 bits.
+pub fn generate_passphrase(groups: usize) -> Result<String> {
+    let mut urandom = File::open("/dev/urandom").context("Failed to open /dev/urandom")?;
+    let mut groups_out = Vec::with_capacity(groups);
+
+    for _ in 0..groups {
+        let mut group = String::with_capacity(GROUP_LENGTH);
+        while group.len() < GROUP_LENGTH {
+            group.push(random_char(&mut urandom)?);
+        }
+        groups_out.push(group);
+    }
+
+    Ok(groups_out.join("-"))
+}
+
+// Rejection sampling avoids modulo bias towards the start of the alphabet
+fn random_char(source: &mut File) -> Result<char> {
+    let limit = 256 - (256 % PASSPHRASE_ALPHABET.len());
+    let mut byte = [0u8; 1];
+    loop {
+        source
+            .read_exact(&mut byte)
+            .context("Failed to read from /dev/urandom")?;
+        if (byte[0] as usize) < limit {
+            return Ok(PASSPHRASE_ALPHABET[byte[0] as usize % PASSPHRASE_ALPHABET.len()] as char);
+        }
+    }
+}
+
+/// Derive the 256-bit WPA pre-shared key from a passphrase, exactly like
+/// `wpa_passphrase` (PBKDF2-HMAC-SHA1, SSID as salt, 4096 rounds). A 64-digit
+/// hex string is already a raw PSK and is returned unchanged.
+pub fn derive_wpa_psk(ssid: &str, passphrase: &str) -> Result<String> {
+    if passphrase.len() == 64 && passphrase.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(passphrase.to_lowercase());
+    }
+
+    if !(8..=63).contains(&passphrase.len()) || !passphrase.is_ascii() {
+        return Err(anyhow::anyhow!(
+            "WPA passphrase must be 8-63 ASCII characters"
+        ));
+    }
+
+    let mut psk = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha1::Sha1>(passphrase.as_bytes(), ssid.as_bytes(), 4096, &mut psk);
+
+    Ok(psk.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Estimate entropy from length and the character classes used. This is an
+/// upper bound for human-chosen passwords but exact for generated ones.
+pub fn estimate_entropy_bits(password: &str) -> f64 {
+    if password.is_empty() {
+        return 0.0;
+    }
+
+    let mut pool = 0u32;
+    if password.chars().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if password
+        .chars()
+        .any(|c| c.is_ascii_punctuation() || c == ' ')
+    {
+        pool += 33;
+    }
+    if !password.is_ascii() {
+        pool += 100;
+    }
+
+    password.chars().count() as f64 * (pool as f64).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_passphrase_is_grouped_and_strong() {
+        let passphrase = generate_passphrase(4).unwrap();
+        let groups: Vec<&str> = passphrase.split('-').collect();
+        assert_eq!(groups.len(), 4);
+        assert!(groups.iter().all(|g| g.len() == GROUP_LENGTH));
+        assert!(groups
+            .iter()
+            .flat_map(|g| g.bytes())
+            .all(|b| PASSPHRASE_ALPHABET.contains(&b)));
+        assert_ne!(passphrase, generate_passphrase(4).unwrap());
+    }
+
+    #[test]
+    fn wpa_psk_matches_ieee_test_vector() {
+        // IEEE 802.11i-2004 Annex H.4.1
+        assert_eq!(
+            derive_wpa_psk("IEEE", "password").unwrap(),
+            "f42c6fc52df0ebef9ebb4b90b38a5f902e83fe1b135a70e23aed762e9710a12e"
+        );
+        assert!(derive_wpa_psk("IEEE", "short").is_err());
+    }
+
+    #[test]
+    fn entropy_reflects_length_and_classes() {
+        assert_eq!(estimate_entropy_bits(""), 0.0);
+        assert_eq!(
+            Strength::from_bits(estimate_entropy_bits("password")),
+            Strength::Fair
+        );
+        assert!(estimate_entropy_bits("Tr0ub4dor&3") > estimate_entropy_bits("troubador"));
+        assert_eq!(
+            Strength::from_bits(estimate_entropy_bits("k7Qm-Rx2v-9HnT-pW4e")),
+            Strength::VeryStrong
+        );
+    }
+}
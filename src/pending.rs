@@ -0,0 +1,303 @@
+// src/pending.rs - Temporary changes that revert on their own unless confirmed in time
+use crate::command::TimedOutput;
+use crate::config::Config;
+use crate::portfwd::PortForward;
+use crate::secure_file;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::process::Command;
+
+// Kept on disk so a change lantern never got to revert, e.g. across a
+// reboot, is still reverted the next time it runs
+const PENDING_DIR: &str = "/var/lib/lantern/pending";
+const UNIT_PREFIX: &str = "lantern-revert-";
+/// Longest a change may stay temporary, in minutes
+pub const MAX_MINUTES: u64 = 24 * 60;
+
+/// How to undo a change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Revert {
+    /// Put a .network file back as it was (None: there was none) and reapply it
+    NetworkFile {
+        interface: String,
+        path: PathBuf,
+        previous: Option<String>,
+    },
+    /// Take back a runtime address change; moot after a reboot
+    Address {
+        interface: String,
+        address: String,
+        added: bool,
+    },
+    /// Put the hotspot's port forwards back, and its DNAT rules if they're loaded
+    PortForwards {
+        previous: Vec<PortForward>,
+        gateway: String,
+    },
+}
+
+impl Revert {
+    // A reboot already undid runtime changes
+    fn survives_reboot(&self) -> bool {
+        !matches!(self, Revert::Address { .. })
+    }
+
+    async fn run(&self) -> Result<()> {
+        match self {
+            Revert::NetworkFile {
+                interface,
+                path,
+                previous,
+            } => {
+                match previous {
+                    Some(contents) => secure_file::write_public(path, contents)?,
+                    None => match fs::remove_file(path) {
+                        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                            return Err(e)
+                                .with_context(|| format!("Failed to remove {}", path.display()))
+                        }
+                        _ => {}
+                    },
+                }
+                crate::systemd::reload_networkd().await?;
+                Command::new("/usr/bin/networkctl")
+                    .args(["reconfigure", interface])
+                    .timed_output()
+                    .await?;
+                Ok(())
+            }
+            Revert::Address {
+                interface,
+                address,
+                added,
+            } => {
+                let network_manager = crate::network::NetworkManager::new();
+                if *added {
+                    network_manager.remove_ip_address(interface, address).await
+                } else {
+                    network_manager.add_ip_address(interface, address).await
+                }
+            }
+            Revert::PortForwards { previous, gateway } => {
+                let mut config = Config::load()?;
+                config.hotspot.port_forwards = previous.clone();
+                config.save()?;
+                if crate::portfwd::installed() {
+                    crate::portfwd::apply(crate::portfwd::ruleset(previous, gateway)).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A change waiting to be confirmed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingChange {
+    pub id: String,
+    pub description: String,
+    pub deadline: SystemTime,
+    boot_id: String,
+    revert: Revert,
+}
+
+impl PendingChange {
+    pub fn remaining(&self, now: SystemTime) -> Duration {
+        self.deadline.duration_since(now).unwrap_or_default()
+    }
+
+    fn unit(&self) -> String {
+        format!("{}{}", UNIT_PREFIX, self.id)
+    }
+}
+
+/// "m:ss" left, for the countdown
+pub fn format_remaining(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// How long a change stays temporary, from a form field: None when empty
+pub fn parse_minutes(value: &str) -> Result<Option<u64>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse::<u64>() {
+        Ok(minutes @ 1..=MAX_MINUTES) => Ok(Some(minutes)),
+        _ => Err(anyhow!(
+            "Revert time must be 1-{} minutes, got {:?}",
+            MAX_MINUTES,
+            value
+        )),
+    }
+}
+
+fn boot_id() -> String {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .map(|id| id.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn record_path(id: &str) -> Result<PathBuf> {
+    let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric());
+    if !valid {
+        return Err(anyhow!("No pending change {:?}", id));
+    }
+    Ok(Path::new(PENDING_DIR).join(format!("{}.json", id)))
+}
+
+fn load(id: &str) -> Result<PendingChange> {
+    let path = record_path(id)?;
+    let record = fs::read_to_string(&path).map_err(|_| anyhow!("No pending change {}", id))?;
+    serde_json::from_str(&record).with_context(|| format!("Unreadable {}", path.display()))
+}
+
+/// The changes waiting to be confirmed, soonest to revert first
+pub fn list() -> Vec<PendingChange> {
+    let Ok(entries) = fs::read_dir(PENDING_DIR) else {
+        return Vec::new();
+    };
+    let mut changes: Vec<PendingChange> = entries
+        .flatten()
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|record| serde_json::from_str(&record).ok())
+        .collect();
+    changes.sort_by_key(|change| change.deadline);
+    changes
+}
+
+/// Arrange for `revert` to run in `minutes` unless confirmed. Call before
+/// making the change; a change that then fails should be `confirm`ed away.
+/// The timer belongs to systemd, so the revert happens even when lantern or
+/// the session it runs in is gone by then.
+pub async fn schedule(description: String, minutes: u64, revert: Revert) -> Result<PendingChange> {
+    let now = SystemTime::now();
+    let change = PendingChange {
+        id: format!("{:x}", now.duration_since(UNIX_EPOCH)?.as_micros()),
+        description,
+        deadline: now + Duration::from_secs(minutes * 60),
+        boot_id: boot_id(),
+        revert,
+    };
+    fs::create_dir_all(PENDING_DIR)?;
+    let path = record_path(&change.id)?;
+    secure_file::write_private(&path, serde_json::to_string_pretty(&change)?)?;
+
+    let lantern = std::env::current_exe().context("Failed to locate the lantern binary")?;
+    let output = Command::new("/usr/bin/systemd-run")
+        .arg(format!("--unit={}", change.unit()))
+        .arg(format!("--on-active={}s", minutes * 60))
+        .arg("--timer-property=AccuracySec=1s")
+        .arg(format!("--description=Revert: {}", change.description))
+        .arg(lantern)
+        .args(["pending", "revert", &change.id])
+        .timed_output()
+        .await;
+    let failure = match output {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => Some(e.to_string()),
+    };
+    if let Some(failure) = failure {
+        let _ = fs::remove_file(&path);
+        return Err(anyhow!("Failed to schedule the revert: {}", failure));
+    }
+    Ok(change)
+}
+
+async fn stop_timer(change: &PendingChange) {
+    Command::new("/usr/bin/systemctl")
+        .args(["stop", &format!("{}.timer", change.unit())])
+        .timed_output()
+        .await
+        .ok();
+}
+
+/// Keep the change: the revert is called off
+pub async fn confirm(id: &str) -> Result<PendingChange> {
+    let change = load(id)?;
+    stop_timer(&change).await;
+    fs::remove_file(record_path(id)?)?;
+    Ok(change)
+}
+
+/// Undo the change now, from its timer or by hand
+pub async fn revert(id: &str) -> Result<PendingChange> {
+    let change = load(id)?;
+    let result = if change.boot_id != boot_id() && !change.revert.survives_reboot() {
+        Ok(())
+    } else {
+        change.revert.run().await
+    };
+    stop_timer(&change).await;
+    // Running again would do no better; the change stays made either way
+    fs::remove_file(record_path(id)?)?;
+    result
+        .with_context(|| format!("Failed to revert {}", change.description))
+        .map(|()| change)
+}
+
+/// Revert what should have been reverted while no timer was left to do it,
+/// i.e. before a reboot
+pub async fn revert_overdue() -> Vec<Result<PendingChange>> {
+    let now = SystemTime::now();
+    let mut reverted = Vec::new();
+    for change in list() {
+        if change.deadline <= now {
+            reverted.push(revert(&change.id).await);
+        }
+    }
+    reverted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_revert_times() {
+        assert_eq!(parse_minutes("").unwrap(), None);
+        assert_eq!(parse_minutes(" 5 ").unwrap(), Some(5));
+        assert!(parse_minutes("0").is_err());
+        assert!(parse_minutes("1441").is_err());
+        assert!(parse_minutes("five").is_err());
+        assert_eq!(format_remaining(Duration::from_secs(272)), "4:32");
+
+        // Records name their file; nothing else may
+        assert!(record_path("18f3a2b").is_ok());
+        assert!(record_path("../config").is_err());
+
+        let change = PendingChange {
+            id: "1".to_string(),
+            description: "eth0 addressing".to_string(),
+            deadline: UNIX_EPOCH + Duration::from_secs(100),
+            boot_id: String::new(),
+            revert: Revert::Address {
+                interface: "eth0".to_string(),
+                address: "10.0.0.2/24".to_string(),
+                added: true,
+            },
+        };
+        let json = serde_json::to_string(&change).unwrap();
+        assert!(json.contains("\"kind\":\"address\""));
+        assert_eq!(
+            serde_json::from_str::<PendingChange>(&json).unwrap(),
+            change
+        );
+        assert_eq!(
+            change
+                .remaining(UNIX_EPOCH + Duration::from_secs(40))
+                .as_secs(),
+            60
+        );
+        assert_eq!(
+            change.remaining(UNIX_EPOCH + Duration::from_secs(400)),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,94 @@
+// src/perf.rs - Frame and refresh timings behind the F12 debug overlay
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+const WINDOW: usize = 60; // Samples kept per measurement
+
+/// What a measurement is of, in the order the overlay lists them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Measure {
+    Startup,    // App::new, before the first frame
+    Frame,      // terminal.draw, including the flush
+    Interfaces, // NetworkManager::get_interfaces
+    Stats,      // NetworkManager::collect_interface_stats
+}
+
+impl Measure {
+    pub fn label(self) -> &'static str {
+        match self {
+            Measure::Startup => "startup",
+            Measure::Frame => "frame",
+            Measure::Interfaces => "interfaces",
+            Measure::Stats => "stats",
+        }
+    }
+}
+
+/// Summary of the samples currently in the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub last: Duration,
+    pub average: Duration,
+    pub max: Duration,
+    pub samples: usize,
+}
+
+/// The most recent durations of each measurement.
+#[derive(Debug, Default)]
+pub struct Timings {
+    samples: BTreeMap<Measure, VecDeque<Duration>>,
+}
+
+impl Timings {
+    pub fn record(&mut self, measure: Measure, duration: Duration) {
+        let samples = self.samples.entry(measure).or_default();
+        if samples.len() == WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(duration);
+    }
+
+    pub fn summary(&self, measure: Measure) -> Option<Summary> {
+        let samples = self.samples.get(&measure).filter(|s| !s.is_empty())?;
+        let total: Duration = samples.iter().sum();
+        Some(Summary {
+            last: *samples.back()?,
+            average: total / samples.len() as u32,
+            max: samples.iter().copied().max()?,
+            samples: samples.len(),
+        })
+    }
+
+    /// Every measurement with at least one sample.
+    pub fn summaries(&self) -> Vec<(Measure, Summary)> {
+        self.samples
+            .keys()
+            .filter_map(|&measure| Some((measure, self.summary(measure)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_a_sliding_window() {
+        let mut timings = Timings::default();
+        assert_eq!(timings.summary(Measure::Frame), None);
+
+        for ms in 1..=WINDOW as u64 + 10 {
+            timings.record(Measure::Frame, Duration::from_millis(ms));
+        }
+        timings.record(Measure::Stats, Duration::from_millis(4));
+
+        let frame = timings.summary(Measure::Frame).unwrap();
+        assert_eq!(frame.samples, WINDOW);
+        assert_eq!(frame.last, Duration::from_millis(70));
+        assert_eq!(frame.max, Duration::from_millis(70));
+        assert_eq!(frame.average, Duration::from_micros(40_500));
+
+        let measures: Vec<Measure> = timings.summaries().into_iter().map(|(m, _)| m).collect();
+        assert_eq!(measures, vec![Measure::Frame, Measure::Stats]);
+    }
+}
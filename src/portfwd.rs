@@ -0,0 +1,209 @@
+// src/portfwd.rs - Port forwarding into the hotspot's network, as DNAT rules in lantern's own nftables table
+use crate::command::TimedOutput;
+use crate::provenance;
+use crate::secure_file;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use tokio::process::Command;
+
+// A table of its own, so the trusted-network firewall and this never replace
+// each other's rules
+const RULESET_PATH: &str = "/run/lantern-portfwd.nft";
+const RESET: &str = "table ip lantern_nat {}\ndelete table ip lantern_nat\n";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+    Both,
+}
+
+impl Protocol {
+    pub fn next(self) -> Self {
+        match self {
+            Protocol::Tcp => Protocol::Udp,
+            Protocol::Udp => Protocol::Both,
+            Protocol::Both => Protocol::Tcp,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "TCP",
+            Protocol::Udp => "UDP",
+            Protocol::Both => "TCP+UDP",
+        }
+    }
+
+    // The nft match for the destination port
+    fn port_match(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp dport",
+            Protocol::Udp => "udp dport",
+            Protocol::Both => "meta l4proto { tcp, udp } th dport",
+        }
+    }
+}
+
+/// A `[[hotspot.port_forwards]]` entry: connections to this host's
+/// `external_port` go to `internal_ip:internal_port` on the hotspot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortForward {
+    #[serde(default)]
+    pub protocol: Protocol,
+    pub external_port: u16,
+    pub internal_ip: String,
+    pub internal_port: u16,
+}
+
+impl PortForward {
+    /// A checked rule from the editor's fields; an empty internal port means
+    /// the same as the external one.
+    pub fn new(
+        protocol: Protocol,
+        external_port: &str,
+        internal_ip: &str,
+        internal_port: &str,
+    ) -> Result<Self> {
+        let port = |value: &str| {
+            value
+                .trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|&port| port > 0)
+                .ok_or_else(|| anyhow!("'{}' is not a port number", value.trim()))
+        };
+        let external_port = port(external_port)?;
+        let internal_port = if internal_port.trim().is_empty() {
+            external_port
+        } else {
+            port(internal_port)?
+        };
+        let internal_ip = internal_ip
+            .trim()
+            .parse::<Ipv4Addr>()
+            .map_err(|_| anyhow!("'{}' is not an IPv4 address", internal_ip.trim()))?
+            .to_string();
+        Ok(Self {
+            protocol,
+            external_port,
+            internal_ip,
+            internal_port,
+        })
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {} → {}:{}",
+            self.protocol.label(),
+            self.external_port,
+            self.internal_ip,
+            self.internal_port
+        )
+    }
+}
+
+/// The nftables ruleset for the forwards into the hotspot's /24 behind
+/// `gateway`; others can't be reached and are left out. Only connections
+/// addressed to this host are redirected, not hotspot clients' own traffic
+/// to the same port elsewhere. None without any forward to load.
+pub fn ruleset(forwards: &[PortForward], gateway: &str) -> Option<String> {
+    let gateway = gateway.parse::<Ipv4Addr>().ok()?;
+    let forwards: Vec<&PortForward> = forwards
+        .iter()
+        .filter(|forward| {
+            forward
+                .internal_ip
+                .parse::<Ipv4Addr>()
+                .is_ok_and(|ip| ip != gateway && ip.octets()[..3] == gateway.octets()[..3])
+        })
+        .collect();
+    if forwards.is_empty() {
+        return None;
+    }
+
+    // Declaring the table first makes the delete succeed on the first load
+    let mut rules = String::from(RESET);
+    rules.push_str("table ip lantern_nat {\n");
+    rules.push_str("\tchain prerouting {\n");
+    rules.push_str("\t\ttype nat hook prerouting priority dstnat; policy accept;\n");
+    for forward in forwards {
+        let _ = writeln!(
+            rules,
+            "\t\tfib daddr type local {} {} dnat to {}:{}",
+            forward.protocol.port_match(),
+            forward.external_port,
+            forward.internal_ip,
+            forward.internal_port
+        );
+    }
+    rules.push_str("\t}\n}\n");
+    Some(rules)
+}
+
+/// Whether the hotspot's forwards are loaded; the file lives in /run like the table
+pub fn installed() -> bool {
+    Path::new(RULESET_PATH).exists()
+}
+
+/// Load the ruleset, or remove lantern's NAT table when there is none.
+pub async fn apply(ruleset: Option<String>) -> Result<()> {
+    if ruleset.is_none() && !Path::new(RULESET_PATH).exists() {
+        return Ok(());
+    }
+    let removing = ruleset.is_none();
+    let ruleset = provenance::stamp(
+        &ruleset.unwrap_or_else(|| RESET.to_string()),
+        if removing {
+            "Remove port forwards"
+        } else {
+            "Port forwards"
+        },
+    );
+    secure_file::write_private(RULESET_PATH, ruleset)?;
+
+    let output = Command::new("/usr/bin/nft")
+        .args(["-f", RULESET_PATH])
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "nft failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    if removing {
+        let _ = std::fs::remove_file(RULESET_PATH);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_dnat_rules_for_the_hotspot_subnet() {
+        let web = PortForward::new(Protocol::Tcp, "8080", " 192.168.4.23", "80").unwrap();
+        let game = PortForward::new(Protocol::Both, "27015", "192.168.4.40", "").unwrap();
+        assert_eq!(game.internal_port, 27015);
+        assert_eq!(web.summary(), "TCP 8080 → 192.168.4.23:80");
+        let elsewhere = PortForward::new(Protocol::Udp, "53", "10.0.0.2", "53").unwrap();
+        assert!(PortForward::new(Protocol::Tcp, "0", "192.168.4.23", "").is_err());
+        assert!(PortForward::new(Protocol::Tcp, "22", "2001:db8::2", "").is_err());
+
+        let rules = ruleset(&[web, game, elsewhere], "192.168.4.1").unwrap();
+        assert!(rules.starts_with(RESET));
+        assert!(rules.contains("\t\tfib daddr type local tcp dport 8080 dnat to 192.168.4.23:80\n"));
+        assert!(rules.contains(
+            "\t\tfib daddr type local meta l4proto { tcp, udp } th dport 27015 dnat to 192.168.4.40:27015\n"
+        ));
+        assert!(!rules.contains("10.0.0.2"));
+        assert_eq!(ruleset(&[], "192.168.4.1"), None);
+    }
+}
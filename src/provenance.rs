@@ -0,0 +1,165 @@
+// src/provenance.rs - Who last wrote a lantern-managed file, when and why, in a comment header
+//
+// Every text file lantern writes or edits is stamped, wherever its format
+// takes `#` comments. Left without a header: JSON state (pending reverts,
+// the scan cache, speed test history), WireGuard key files (wg and networkd
+// read them as the bare key), and checkpoint or revert restores, which put
+// back a file exactly as it was, its old header included.
+use chrono::{DateTime, FixedOffset, Local};
+
+const FIRST: &str = "# Written by lantern ";
+const WHEN: &str = "# When: ";
+const BY: &str = "# By: ";
+const OPERATION: &str = "# Operation: ";
+
+/// The header of a file lantern wrote, read back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub version: String,
+    pub when: Option<DateTime<FixedOffset>>,
+    pub by: String,
+    pub operation: String,
+}
+
+/// The person behind the change: the sudo caller when there is one, since
+/// "root" says nothing on a box several admins share
+pub fn user() -> String {
+    let uid = nix::unistd::getuid();
+    let name = nix::unistd::User::from_uid(uid)
+        .ok()
+        .flatten()
+        .map(|user| user.name)
+        .unwrap_or_else(|| format!("uid {}", uid));
+    match std::env::var("SUDO_USER") {
+        Ok(caller) if !caller.is_empty() && caller != name => {
+            format!("{} (as {} via sudo)", caller, name)
+        }
+        _ => name,
+    }
+}
+
+// One line each: a newline in an operation, or in a crafted SUDO_USER,
+// would otherwise end the comment and add settings to the file
+fn one_line(value: &str) -> String {
+    value.replace(['\n', '\r'], " ")
+}
+
+fn header(operation: &str, when: DateTime<FixedOffset>, by: &str) -> String {
+    format!(
+        "{}{}\n{}{}\n{}{}\n{}{}\n",
+        FIRST,
+        env!("CARGO_PKG_VERSION"),
+        WHEN,
+        when.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        BY,
+        one_line(by),
+        OPERATION,
+        one_line(operation)
+    )
+}
+
+/// `contents` with a fresh header in place of any earlier one
+pub fn stamp(contents: &str, operation: &str) -> String {
+    stamp_at(contents, operation, Local::now().fixed_offset(), &user())
+}
+
+fn stamp_at(contents: &str, operation: &str, when: DateTime<FixedOffset>, by: &str) -> String {
+    let mut stamped = header(operation, when, by);
+    stamped.push_str(strip(contents));
+    stamped
+}
+
+/// The file without lantern's header
+pub fn strip(contents: &str) -> &str {
+    if !contents.starts_with(FIRST) {
+        return contents;
+    }
+    let mut rest = contents;
+    for (i, line) in contents.split_inclusive('\n').enumerate() {
+        let ours = match i {
+            0 => true,
+            _ => [WHEN, BY, OPERATION]
+                .iter()
+                .any(|prefix| line.starts_with(prefix)),
+        };
+        if !ours {
+            break;
+        }
+        rest = &rest[line.len()..];
+    }
+    rest
+}
+
+/// The header at the top of `contents`, if lantern wrote it
+pub fn read(contents: &str) -> Option<Provenance> {
+    let mut lines = contents.lines();
+    let version = lines.next()?.strip_prefix(FIRST)?.trim().to_string();
+    let mut provenance = Provenance {
+        version,
+        when: None,
+        by: String::new(),
+        operation: String::new(),
+    };
+    for line in lines {
+        if let Some(when) = line.strip_prefix(WHEN) {
+            provenance.when = DateTime::parse_from_rfc3339(when.trim()).ok();
+        } else if let Some(by) = line.strip_prefix(BY) {
+            provenance.by = by.trim().to_string();
+        } else if let Some(operation) = line.strip_prefix(OPERATION) {
+            provenance.operation = operation.trim().to_string();
+        } else {
+            break;
+        }
+    }
+    Some(provenance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restamps_and_reads_back() {
+        let when = DateTime::parse_from_rfc3339("2026-03-01T09:30:00+01:00").unwrap();
+        let network = "[Match]\nName=eth0\n";
+        let first = stamp_at(
+            network,
+            "Static address for eth0",
+            when,
+            "alice (as root via sudo)",
+        );
+        assert!(first.ends_with("# Operation: Static address for eth0\n[Match]\nName=eth0\n"));
+        assert!(crate::systemd::validate_network_file(&first).is_ok());
+
+        let second = stamp_at(&first, "DHCP\nfor eth0", when, "bob");
+        assert_eq!(second.matches(FIRST).count(), 1);
+        assert_eq!(strip(&second), network);
+        assert_eq!(
+            read(&second),
+            Some(Provenance {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                when: Some(when),
+                by: "bob".to_string(),
+                operation: "DHCP for eth0".to_string(),
+            })
+        );
+
+        // SUDO_USER comes from the environment and can't add lines
+        let crafted = stamp_at(
+            network,
+            "Static address",
+            when,
+            "eve\n[Network]\nDNS=6.6.6.6",
+        );
+        assert!(crate::systemd::validate_network_file(&crafted).is_ok());
+        assert_eq!(strip(&crafted), network);
+        assert_eq!(
+            read(&crafted).map(|written| written.by),
+            Some("eve [Network] DNS=6.6.6.6".to_string())
+        );
+
+        // A file lantern didn't write, or a comment of the user's own
+        assert_eq!(read(network), None);
+        assert_eq!(strip("# My notes\n[Match]\n"), "# My notes\n[Match]\n");
+    }
+}
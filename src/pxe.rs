@@ -0,0 +1,400 @@
+// src/pxe.rs - A temporary PXE boot server: dnsmasq as proxyDHCP (or DHCP) plus TFTP on one interface
+use crate::command::TimedOutput;
+use anyhow::{anyhow, Context, Result};
+use ipnetwork::Ipv4Network;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Component, Path};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+
+const CONFIG_PATH: &str = "/run/lantern-pxe.conf";
+const LEASE_FILE: &str = "/run/lantern-pxe.leases";
+const LOG_LINES: usize = 200;
+// dnsmasq gives up right away when a port is taken; wait that long for it
+const STARTUP_GRACE: Duration = Duration::from_millis(500);
+
+/// How clients get their address while they boot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PxeMode {
+    /// Next to the LAN's DHCP server, which keeps handing out addresses
+    #[default]
+    Proxy,
+    /// On an isolated link, with lantern handing out addresses too
+    Dhcp,
+}
+
+impl PxeMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            PxeMode::Proxy => "proxyDHCP (the LAN's DHCP server assigns addresses)",
+            PxeMode::Dhcp => "DHCP (isolated link, lantern assigns addresses)",
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            PxeMode::Proxy => PxeMode::Dhcp,
+            PxeMode::Dhcp => PxeMode::Proxy,
+        }
+    }
+}
+
+/// `[pxe]`: what the boot server dialog starts from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PxeSettings {
+    pub mode: PxeMode,
+    pub tftp_root: String,
+    /// Relative to the TFTP root
+    pub bios_boot_file: String,
+    /// Relative to the TFTP root; UEFI clients aren't offered anything when empty
+    pub efi_boot_file: String,
+    /// Put on the interface in DHCP mode when it has no IPv4 address
+    pub address: String,
+}
+
+impl Default for PxeSettings {
+    fn default() -> Self {
+        Self {
+            mode: PxeMode::Proxy,
+            tftp_root: "/srv/tftp".to_string(),
+            bios_boot_file: "pxelinux.0".to_string(),
+            efi_boot_file: "grubx64.efi".to_string(),
+            address: "10.77.0.1/24".to_string(),
+        }
+    }
+}
+
+/// A checked set of settings for one interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct PxeConfig {
+    pub interface: String,
+    pub mode: PxeMode,
+    pub tftp_root: String,
+    pub bios_boot_file: String,
+    pub efi_boot_file: Option<String>,
+    pub network: Ipv4Network, // The interface's address and prefix
+    pub assign_address: bool, // The address is lantern's to add and remove
+}
+
+fn boot_file(name: &str) -> Result<&str> {
+    let name = crate::sanitize::config_value(name.trim())?;
+    let relative = Path::new(name)
+        .components()
+        .all(|part| matches!(part, Component::Normal(_)));
+    if name.is_empty() || !relative || name.contains(',') {
+        return Err(anyhow!(
+            "Boot file must be a path inside the TFTP root, got {:?}",
+            name
+        ));
+    }
+    Ok(name)
+}
+
+impl PxeConfig {
+    /// `addresses` are the interface's IPv4 addresses in CIDR notation
+    pub fn new(interface: &str, addresses: &[String], settings: &PxeSettings) -> Result<Self> {
+        let interface = crate::sanitize::interface_name(interface)?.to_string();
+        let tftp_root = crate::sanitize::config_value(settings.tftp_root.trim())?;
+        if !tftp_root.starts_with('/') {
+            return Err(anyhow!("TFTP root must be an absolute path"));
+        }
+        let bios_boot_file = boot_file(&settings.bios_boot_file)?.to_string();
+        let efi_boot_file = match settings.efi_boot_file.trim() {
+            "" => None,
+            name => Some(boot_file(name)?.to_string()),
+        };
+
+        let current = addresses
+            .iter()
+            .find_map(|address| address.parse::<Ipv4Network>().ok());
+        let (network, assign_address) = match (current, settings.mode) {
+            (Some(network), _) => (network, false),
+            (None, PxeMode::Proxy) => {
+                return Err(anyhow!(
+                    "{} has no IPv4 address; proxyDHCP needs one from the LAN",
+                    interface
+                ))
+            }
+            (None, PxeMode::Dhcp) => {
+                let network = settings
+                    .address
+                    .trim()
+                    .parse::<Ipv4Network>()
+                    .with_context(|| format!("Invalid address {:?}", settings.address))?;
+                (network, true)
+            }
+        };
+        if settings.mode == PxeMode::Dhcp && network.prefix() > 28 {
+            return Err(anyhow!(
+                "{} is too small to hand out addresses from",
+                network
+            ));
+        }
+
+        Ok(Self {
+            interface,
+            mode: settings.mode,
+            tftp_root: tftp_root.to_string(),
+            bios_boot_file,
+            efi_boot_file,
+            network,
+            assign_address,
+        })
+    }
+
+    /// The boot files have to be there before clients ask for them
+    pub fn check_files(&self) -> Result<()> {
+        let root = Path::new(&self.tftp_root);
+        if !root.is_dir() {
+            return Err(anyhow!("TFTP root {} does not exist", self.tftp_root));
+        }
+        for file in std::iter::once(&self.bios_boot_file).chain(&self.efi_boot_file) {
+            if !root.join(file).is_file() {
+                return Err(anyhow!("{} is not in {}", file, self.tftp_root));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The dnsmasq configuration: no DNS, DHCP bound to the one interface,
+/// TFTP from the root, and a boot file per client architecture
+pub fn dnsmasq_config(config: &PxeConfig) -> String {
+    let mut lines = vec![
+        "port=0".to_string(),
+        format!("interface={}", config.interface),
+        "bind-interfaces".to_string(),
+        format!("dhcp-leasefile={}", LEASE_FILE),
+        "enable-tftp".to_string(),
+        format!("tftp-root={}", config.tftp_root),
+        "log-dhcp".to_string(),
+    ];
+
+    match config.mode {
+        PxeMode::Proxy => {
+            lines.push(format!("dhcp-range={},proxy", config.network.network()));
+            lines.push(format!(
+                "pxe-service=x86PC,\"Network boot\",{}",
+                config.bios_boot_file
+            ));
+            if let Some(efi) = &config.efi_boot_file {
+                lines.push(format!("pxe-service=X86-64_EFI,\"Network boot\",{}", efi));
+                lines.push(format!("pxe-service=BC_EFI,\"Network boot\",{}", efi));
+            }
+        }
+        PxeMode::Dhcp => {
+            // Clear of the first addresses, where the server usually sits
+            let size = config.network.size();
+            let first = config.network.nth(size / 4).unwrap_or(config.network.ip());
+            let last = config.network.nth(size - 2).unwrap_or(config.network.ip());
+            lines.push(format!(
+                "dhcp-range={},{},{},1h",
+                first,
+                last,
+                config.network.mask()
+            ));
+            lines.push("dhcp-match=set:efi64,option:client-arch,7".to_string());
+            lines.push("dhcp-match=set:efi64,option:client-arch,9".to_string());
+            lines.push(format!("dhcp-boot=tag:!efi64,{}", config.bios_boot_file));
+            if let Some(efi) = &config.efi_boot_file {
+                lines.push(format!("dhcp-boot=tag:efi64,{}", efi));
+            }
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// A running boot server. dnsmasq is killed and the address lantern added
+/// is left behind if this is dropped without `stop`.
+pub struct PxeServer {
+    pub config: PxeConfig,
+    child: Option<Child>, // None in demo mode
+    log: Arc<Mutex<VecDeque<String>>>,
+    logged: Arc<Mutex<usize>>, // Lines read so far, to notice new ones
+    reader: Option<JoinHandle<()>>,
+}
+
+impl PxeServer {
+    pub async fn start(config: PxeConfig) -> Result<Self> {
+        config.check_files()?;
+        let dnsmasq = crate::provenance::stamp(&dnsmasq_config(&config), "PXE boot server");
+        crate::secure_file::write_public(CONFIG_PATH, dnsmasq)
+            .context("Failed to write the PXE dnsmasq configuration")?;
+
+        if config.assign_address {
+            Command::new("/usr/bin/ip")
+                .args(["addr", "add", &config.network.to_string(), "dev"])
+                .arg(&config.interface)
+                .timed_output()
+                .await
+                .context("Failed to add the boot server's address")?;
+        }
+
+        let child = Command::new("/usr/bin/dnsmasq")
+            .arg(format!("--conf-file={}", CONFIG_PATH))
+            .args(["--keep-in-foreground", "--log-facility=-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                remove_address(&config).await;
+                return Err(e).context("Failed to start dnsmasq");
+            }
+        };
+
+        let log = Arc::new(Mutex::new(VecDeque::new()));
+        let logged = Arc::new(Mutex::new(0));
+        let reader = child.stderr.take().map(|stderr| {
+            let log = Arc::clone(&log);
+            let logged = Arc::clone(&logged);
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let mut log = log.lock().unwrap_or_else(|e| e.into_inner());
+                    if log.len() == LOG_LINES {
+                        log.pop_front();
+                    }
+                    log.push_back(line);
+                    *logged.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+                }
+            })
+        });
+
+        let mut server = Self {
+            config,
+            child: Some(child),
+            log,
+            logged,
+            reader,
+        };
+        tokio::time::sleep(STARTUP_GRACE).await;
+        if let Some(reason) = server.exited() {
+            server.stop().await;
+            return Err(anyhow!("dnsmasq exited: {}", reason));
+        }
+        Ok(server)
+    }
+
+    /// Stands in for a server in demo mode, where nothing is started
+    pub fn simulated(config: PxeConfig) -> Self {
+        let log = VecDeque::from([format!(
+            "demo: a boot server on {} would answer here",
+            config.interface
+        )]);
+        Self {
+            config,
+            child: None,
+            log: Arc::new(Mutex::new(log)),
+            logged: Arc::new(Mutex::new(1)),
+            reader: None,
+        }
+    }
+
+    /// The last lines dnsmasq logged, oldest first
+    pub fn log(&self) -> Vec<String> {
+        self.log
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    pub fn logged(&self) -> usize {
+        *self.logged.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Why dnsmasq stopped, once it has: its last log line
+    pub fn exited(&mut self) -> Option<String> {
+        let status = self.child.as_mut()?.try_wait().ok()??;
+        let last = self
+            .log
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .back()
+            .cloned();
+        Some(last.unwrap_or_else(|| status.to_string()))
+    }
+
+    pub async fn stop(mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill().await;
+            remove_address(&self.config).await;
+        }
+        if let Some(reader) = self.reader.take() {
+            reader.abort();
+        }
+        let _ = std::fs::remove_file(LEASE_FILE);
+    }
+}
+
+async fn remove_address(config: &PxeConfig) {
+    if config.assign_address {
+        Command::new("/usr/bin/ip")
+            .args(["addr", "del", &config.network.to_string(), "dev"])
+            .arg(&config.interface)
+            .timed_output()
+            .await
+            .ok();
+    }
+}
+
+impl Drop for PxeServer {
+    fn drop(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            reader.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offers_boot_files_per_architecture() {
+        let settings = PxeSettings::default();
+        let proxy = PxeConfig::new("eth1", &["192.168.1.20/24".to_string()], &settings).unwrap();
+        assert!(!proxy.assign_address);
+        let conf = dnsmasq_config(&proxy);
+        assert!(conf.contains("port=0\n"));
+        assert!(conf.contains("dhcp-range=192.168.1.0,proxy\n"));
+        assert!(conf.contains("pxe-service=x86PC,\"Network boot\",pxelinux.0\n"));
+        assert!(conf.contains("pxe-service=X86-64_EFI,\"Network boot\",grubx64.efi\n"));
+
+        // Without an address proxyDHCP has no LAN to sit next to
+        assert!(PxeConfig::new("eth1", &[], &settings).is_err());
+
+        let dhcp = PxeSettings {
+            mode: PxeMode::Dhcp,
+            efi_boot_file: String::new(),
+            ..PxeSettings::default()
+        };
+        let dhcp = PxeConfig::new("eth1", &[], &dhcp).unwrap();
+        assert!(dhcp.assign_address);
+        let conf = dnsmasq_config(&dhcp);
+        assert!(conf.contains("dhcp-range=10.77.0.64,10.77.0.254,255.255.255.0,1h\n"));
+        assert!(conf.contains("dhcp-boot=tag:!efi64,pxelinux.0\n"));
+        assert!(!conf.contains("tag:efi64,"));
+
+        for escape in ["../etc/shadow", "/boot/vmlinuz", "a,b"] {
+            let settings = PxeSettings {
+                bios_boot_file: escape.to_string(),
+                ..PxeSettings::default()
+            };
+            assert!(PxeConfig::new("eth1", &["10.0.0.2/24".to_string()], &settings).is_err());
+        }
+    }
+}
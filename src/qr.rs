@@ -0,0 +1,285 @@
+// src/qr.rs - WiFi QR code payloads (WIFI:T:WPA;S:ssid;P:pass;;)
+use crate::command::TimedOutput;
+use crate::network::{WifiBand, WifiCredentials, WifiSecurity};
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::path::Path;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WifiQrPayload {
+    pub ssid: String,
+    pub password: Option<String>,
+    pub security: WifiSecurity,
+    pub hidden: bool,
+}
+
+impl WifiQrPayload {
+    /// Parse the de-facto standard `WIFI:` payload used by phones and zbar.
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        // zbarimg without --raw prefixes every symbol with its type
+        let input = input.strip_prefix("QR-Code:").unwrap_or(input);
+        let body = input
+            .strip_prefix("WIFI:")
+            .ok_or_else(|| anyhow::anyhow!("Not a WiFi QR code (missing WIFI: prefix)"))?;
+
+        let mut ssid = None;
+        let mut password = None;
+        let mut auth_type = String::new();
+        let mut hidden = false;
+
+        for field in split_unescaped(body, ';') {
+            if field.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = field.split_once(':') else {
+                continue;
+            };
+            let value = unescape(value);
+            match key {
+                "S" => ssid = Some(value),
+                "P" => password = Some(value),
+                "T" => auth_type = value,
+                "H" => hidden = value.eq_ignore_ascii_case("true"),
+                _ => {} // Ignore EAP fields and future extensions
+            }
+        }
+
+        let ssid = ssid
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("WiFi QR code does not contain an SSID"))?;
+
+        let security = match auth_type.to_uppercase().as_str() {
+            "" | "NOPASS" => WifiSecurity::Open,
+            "WEP" => WifiSecurity::WEP,
+            "WPA" | "WPA2" => WifiSecurity::WPA2,
+            "SAE" | "WPA3" => WifiSecurity::WPA3,
+            "WPA2-EAP" | "EAP" => WifiSecurity::Enterprise,
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Unsupported WiFi QR authentication type: {}",
+                    other
+                ))
+            }
+        };
+
+        let password = if security == WifiSecurity::Open {
+            None
+        } else {
+            password.filter(|p| !p.is_empty())
+        };
+
+        Ok(Self {
+            ssid,
+            password,
+            security,
+            hidden,
+        })
+    }
+
+    /// Encode as a `WIFI:` payload that phone cameras can join from.
+    pub fn to_payload_string(&self) -> String {
+        let auth_type = match self.security {
+            WifiSecurity::Open => "nopass",
+            WifiSecurity::WEP => "WEP",
+            WifiSecurity::WPA | WifiSecurity::WPA2 => "WPA",
+            WifiSecurity::WPA3 => "SAE",
+            WifiSecurity::Enterprise => "WPA2-EAP",
+        };
+
+        let mut payload = format!("WIFI:T:{};S:{};", auth_type, escape(&self.ssid));
+        if let Some(ref password) = self.password {
+            payload.push_str(&format!("P:{};", escape(password)));
+        }
+        if self.hidden {
+            payload.push_str("H:true;");
+        }
+        payload.push(';');
+        payload
+    }
+
+    pub fn to_credentials(&self) -> WifiCredentials {
+        WifiCredentials {
+            ssid: self.ssid.clone(),
+            password: self.password.clone(),
+            security: self.security.clone(),
+            hidden: self.hidden,
+            enterprise: None,
+            band: WifiBand::Any,
+            tx_power_dbm: None,
+            routes: Vec::new(),
+            domains: Vec::new(),
+        }
+    }
+}
+
+/// Resolve a `--qr` argument to a payload string: `-` reads stdin, an existing
+/// file is decoded with zbarimg, anything else is taken as the payload itself.
+pub async fn read_qr_source(source: &str) -> Result<String> {
+    if source == "-" {
+        let mut payload = String::new();
+        std::io::stdin()
+            .read_to_string(&mut payload)
+            .context("Failed to read QR payload from stdin")?;
+        return Ok(payload);
+    }
+
+    if Path::new(source).is_file() {
+        let output = Command::new("/usr/bin/zbarimg")
+            .args(["--raw", "--quiet", source])
+            .timed_output()
+            .await
+            .context("Failed to run zbarimg (is zbar installed?)")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("No QR code found in image '{}'", source));
+        }
+
+        return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+    }
+
+    Ok(source.to_string())
+}
+
+/// Render a QR code as terminal lines using half-block characters (two modules
+/// per character row). Light modules are drawn, so the result is meant to be
+/// shown as light-on-dark with a quiet zone around it.
+pub fn render_qr_lines(data: &str) -> Result<Vec<String>> {
+    const QUIET_ZONE: usize = 2;
+
+    let code = qrcode::QrCode::new(data.as_bytes()).context("Failed to encode QR code")?;
+    let width = code.width();
+    let colors = code.to_colors();
+    let size = width + QUIET_ZONE * 2;
+
+    let is_light = |x: usize, y: usize| -> bool {
+        if x < QUIET_ZONE || y < QUIET_ZONE || x >= width + QUIET_ZONE || y >= width + QUIET_ZONE {
+            return true;
+        }
+        colors[(y - QUIET_ZONE) * width + (x - QUIET_ZONE)] == qrcode::Color::Light
+    };
+
+    let mut lines = Vec::new();
+    for y in (0..size).step_by(2) {
+        let mut line = String::with_capacity(size);
+        for x in 0..size {
+            let top = is_light(x, y);
+            let bottom = y + 1 < size && is_light(x, y + 1);
+            line.push(match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        lines.push(line);
+    }
+
+    Ok(lines)
+}
+
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+// Split on a delimiter that is not preceded by a backslash escape
+fn split_unescaped(input: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut escaped = false;
+
+    for c in input.chars() {
+        if escaped {
+            current.push('\\');
+            current.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+    }
+
+    if !current.is_empty() {
+        fields.push(current);
+    }
+
+    fields
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                result.push(next);
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_wpa_payload() {
+        let payload = WifiQrPayload::parse("WIFI:T:WPA;S:Home Net;P:secret123;;").unwrap();
+        assert_eq!(payload.ssid, "Home Net");
+        assert_eq!(payload.password.as_deref(), Some("secret123"));
+        assert_eq!(payload.security, WifiSecurity::WPA2);
+        assert!(!payload.hidden);
+    }
+
+    #[test]
+    fn parses_escaped_fields_and_zbar_prefix() {
+        let payload =
+            WifiQrPayload::parse("QR-Code:WIFI:S:My\\;Cafe\\:1;T:SAE;P:p\\\\w\\,d;H:true;;\n")
+                .unwrap();
+        assert_eq!(payload.ssid, "My;Cafe:1");
+        assert_eq!(payload.password.as_deref(), Some("p\\w,d"));
+        assert_eq!(payload.security, WifiSecurity::WPA3);
+        assert!(payload.hidden);
+    }
+
+    #[test]
+    fn open_network_drops_password() {
+        let payload = WifiQrPayload::parse("WIFI:S:Guest;T:nopass;P:;;").unwrap();
+        assert_eq!(payload.security, WifiSecurity::Open);
+        assert_eq!(payload.password, None);
+    }
+
+    #[test]
+    fn payload_round_trips() {
+        let original = WifiQrPayload {
+            ssid: "Cafe;Guest".to_string(),
+            password: Some("pa:ss,\\word".to_string()),
+            security: WifiSecurity::WPA2,
+            hidden: true,
+        };
+        let parsed = WifiQrPayload::parse(&original.to_payload_string()).unwrap();
+        assert_eq!(parsed, original);
+    }
+
+    #[test]
+    fn rejects_non_wifi_payload() {
+        assert!(WifiQrPayload::parse("https://example.com").is_err());
+        assert!(WifiQrPayload::parse("WIFI:T:WPA;P:x;;").is_err());
+    }
+}
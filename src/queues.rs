@@ -0,0 +1,724 @@
+// src/queues.rs - Per-queue counters, IRQ/CPU distribution and queue tuning of multi-queue NICs
+use crate::command::TimedOutput;
+use crate::netlink;
+use crate::provenance;
+use crate::sanitize;
+use crate::secure_file;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Offloads worth showing; `ethtool -k` lists dozens of fixed ones
+const MAIN_OFFLOADS: &[&str] = &[
+    "rx-checksumming",
+    "tx-checksumming",
+    "scatter-gather",
+    "tcp-segmentation-offload",
+    "generic-segmentation-offload",
+    "generic-receive-offload",
+    "large-receive-offload",
+    "rx-gro-hw",
+];
+
+/// Packet counters of one hardware queue, as the driver reports them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueCounters {
+    pub packets: Option<u64>,
+    pub bytes: Option<u64>,
+}
+
+/// An interrupt line serving the NIC, with its count on every CPU
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrqStats {
+    pub irq: u32,
+    pub name: String,
+    pub per_cpu: Vec<u64>,
+    pub affinity: Option<String>, // smp_affinity_list, e.g. "0-3"
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct QueueStats {
+    pub rx_queues: usize, // From /sys, also for drivers without per-queue counters
+    pub tx_queues: usize,
+    pub rx: BTreeMap<u32, QueueCounters>,
+    pub tx: BTreeMap<u32, QueueCounters>,
+    pub irqs: Vec<IrqStats>,
+    pub rings: Option<RingSizes>,
+    pub rss_table: Vec<u32>, // RX queue of every indirection table entry
+    pub rps_cpus: BTreeMap<u32, String>,
+    pub xps_cpus: BTreeMap<u32, String>,
+    pub offloads: Vec<(String, bool)>,
+}
+
+/// Ring buffer sizes, current and the hardware maximum
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RingSizes {
+    pub rx: Option<u32>,
+    pub tx: Option<u32>,
+    pub rx_max: Option<u32>,
+    pub tx_max: Option<u32>,
+}
+
+/// Settings changed by `lantern tune`; unset fields are left alone.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueTuning {
+    pub rx_ring: Option<u32>,
+    pub tx_ring: Option<u32>,
+    pub rss_queues: Option<u32>, // Spread the indirection table evenly over this many queues
+    pub rps_cpus: Option<String>,
+    pub xps_cpus: Option<String>,
+    pub offloads: Vec<(String, bool)>,
+}
+
+impl QueueTuning {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl QueueStats {
+    pub fn is_empty(&self) -> bool {
+        self.rx_queues == 0 && self.tx_queues == 0 && self.irqs.is_empty()
+    }
+}
+
+pub async fn get_queue_stats(interface: &str) -> QueueStats {
+    let (rx_queues, tx_queues) = count_queues(interface);
+    let mut stats = QueueStats {
+        rx_queues,
+        tx_queues,
+        ..Default::default()
+    };
+
+    // Per-queue counters only exist in the driver's ethtool statistics
+    if let Ok(output) = Command::new("/usr/sbin/ethtool")
+        .args(["-S", interface])
+        .timed_output()
+        .await
+    {
+        if output.status.success() {
+            (stats.rx, stats.tx) = parse_ethtool_queues(&String::from_utf8_lossy(&output.stdout));
+        }
+    }
+
+    let ethtool = |flag: &'static str| async move {
+        Command::new("/usr/sbin/ethtool")
+            .args([flag, interface])
+            .timed_output()
+            .await
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    stats.rings = ethtool("-g").await.map(|output| parse_ring_sizes(&output));
+    stats.rss_table = ethtool("-x")
+        .await
+        .map(|output| parse_rss_table(&output))
+        .unwrap_or_default();
+    stats.offloads = ethtool("-k")
+        .await
+        .map(|output| parse_offloads(&output))
+        .unwrap_or_default();
+    stats.rps_cpus = read_queue_masks(interface, "rx", "rps_cpus", rx_queues);
+    stats.xps_cpus = read_queue_masks(interface, "tx", "xps_cpus", tx_queues);
+
+    if let Ok(interrupts) = fs::read_to_string("/proc/interrupts") {
+        let msi_irqs = msi_irqs(interface);
+        stats.irqs = parse_interrupts(&interrupts, interface, &msi_irqs);
+        for irq in &mut stats.irqs {
+            irq.affinity = fs::read_to_string(format!("/proc/irq/{}/smp_affinity_list", irq.irq))
+                .ok()
+                .map(|affinity| affinity.trim().to_string());
+        }
+    }
+    stats
+}
+
+fn count_queues(interface: &str) -> (usize, usize) {
+    let Ok(entries) = fs::read_dir(format!("/sys/class/net/{}/queues", interface)) else {
+        return (0, 0);
+    };
+    let names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    let count = |prefix: &str| names.iter().filter(|name| name.starts_with(prefix)).count();
+    (count("rx-"), count("tx-"))
+}
+
+fn read_queue_masks(
+    interface: &str,
+    direction: &str,
+    attribute: &str,
+    queues: usize,
+) -> BTreeMap<u32, String> {
+    (0..queues as u32)
+        .filter_map(|queue| {
+            let path = format!(
+                "/sys/class/net/{}/queues/{}-{}/{}",
+                interface, direction, queue, attribute
+            );
+            let mask = fs::read_to_string(path).ok()?;
+            Some((queue, mask.trim().to_string()))
+        })
+        .collect()
+}
+
+/// IRQs allocated to the NIC's PCI device; their names in /proc/interrupts
+/// often don't mention the interface (e.g. "mlx5_comp3@pci:...").
+fn msi_irqs(interface: &str) -> Vec<u32> {
+    fs::read_dir(format!("/sys/class/net/{}/device/msi_irqs", interface))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Split a driver statistic like `rx_queue_0_packets`, `tx-3.bytes` or
+/// `rx0_bytes` into direction, queue and counter.
+fn parse_queue_stat(name: &str) -> Option<(bool, u32, bool)> {
+    let (rx, rest) = if let Some(rest) = name.strip_prefix("rx") {
+        (true, rest)
+    } else {
+        (false, name.strip_prefix("tx")?)
+    };
+    let rest = rest.trim_start_matches(['_', '-']);
+    let rest = rest.strip_prefix("queue").unwrap_or(rest);
+    let rest = rest.trim_start_matches(['_', '-']);
+
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let queue = rest[..digits].parse().ok()?;
+    let bytes = match rest[digits..].trim_start_matches(['_', '.']) {
+        "packets" => false,
+        "bytes" => true,
+        _ => return None,
+    };
+    Some((rx, queue, bytes))
+}
+
+type QueueMap = BTreeMap<u32, QueueCounters>;
+
+fn parse_ethtool_queues(output: &str) -> (QueueMap, QueueMap) {
+    let mut rx = QueueMap::new();
+    let mut tx = QueueMap::new();
+    for line in output.lines() {
+        let Some((name, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let (Some((is_rx, queue, is_bytes)), Ok(value)) =
+            (parse_queue_stat(name.trim()), value.trim().parse::<u64>())
+        else {
+            continue;
+        };
+        let map = if is_rx { &mut rx } else { &mut tx };
+        let counters = map.entry(queue).or_default();
+        if is_bytes {
+            counters.bytes = Some(value);
+        } else {
+            counters.packets = Some(value);
+        }
+    }
+    (rx, tx)
+}
+
+/// Interrupt lines that belong to the interface: the device's MSI vectors,
+/// or any line named after the interface (e.g. "eth0-TxRx-0").
+fn parse_interrupts(contents: &str, interface: &str, msi_irqs: &[u32]) -> Vec<IrqStats> {
+    let mut lines = contents.lines();
+    let cpus = lines
+        .next()
+        .map_or(0, |header| header.split_whitespace().count());
+
+    lines
+        .filter_map(|line| {
+            let (irq, rest) = line.trim_start().split_once(':')?;
+            let irq: u32 = irq.parse().ok()?; // Skips NMI, LOC and friends
+            let mut fields = rest.split_whitespace();
+            let per_cpu: Vec<u64> = fields
+                .by_ref()
+                .take(cpus)
+                .map(|count| count.parse().unwrap_or(0))
+                .collect();
+            // Chip, hwirq and trigger come before the action name
+            let name = fields.last().unwrap_or_default().to_string();
+
+            let named = name
+                .split(['-', '@', ':'])
+                .next()
+                .is_some_and(|prefix| prefix == interface);
+            (named || msi_irqs.contains(&irq)).then_some(IrqStats {
+                irq,
+                name,
+                per_cpu,
+                affinity: None,
+            })
+        })
+        .collect()
+}
+
+/// `ethtool -g`: the "Pre-set maximums" section comes before the current settings.
+fn parse_ring_sizes(output: &str) -> RingSizes {
+    let mut rings = RingSizes::default();
+    let mut current = false;
+    for line in output.lines() {
+        if line.starts_with("Current hardware settings") {
+            current = true;
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().parse().ok();
+        match (key.trim(), current) {
+            ("RX", false) => rings.rx_max = value,
+            ("TX", false) => rings.tx_max = value,
+            ("RX", true) => rings.rx = value,
+            ("TX", true) => rings.tx = value,
+            _ => {}
+        }
+    }
+    rings
+}
+
+/// `ethtool -x`: rows of "<first entry>: <queue> <queue> ...", up to the hash key.
+fn parse_rss_table(output: &str) -> Vec<u32> {
+    let mut table = Vec::new();
+    for line in output.lines() {
+        // The key is colon-separated hex and would pass for a table row
+        if line.starts_with("RSS hash key") {
+            break;
+        }
+        let Some((index, queues)) = line.trim().split_once(':') else {
+            continue;
+        };
+        if index.parse::<u32>().is_err() {
+            continue;
+        }
+        table.extend(
+            queues
+                .split_whitespace()
+                .filter_map(|queue| queue.parse::<u32>().ok()),
+        );
+    }
+    table
+}
+
+/// How many indirection table entries point at each queue, e.g.
+/// "128 entries: q0 32, q1 32, q2 32, q3 32".
+pub fn rss_spread(table: &[u32]) -> String {
+    let mut per_queue = BTreeMap::new();
+    for queue in table {
+        *per_queue.entry(queue).or_insert(0) += 1;
+    }
+    let spread: Vec<String> = per_queue
+        .iter()
+        .map(|(queue, entries)| format!("q{} {}", queue, entries))
+        .collect();
+    format!("{} entries: {}", table.len(), spread.join(", "))
+}
+
+fn parse_offloads(output: &str) -> Vec<(String, bool)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, state) = line.split_once(": ")?;
+            MAIN_OFFLOADS
+                .contains(&name)
+                .then(|| (name.to_string(), state.starts_with("on")))
+        })
+        .collect()
+}
+
+/// An offload change as given on the command line, e.g. `gro=off`.
+pub fn parse_offload(setting: &str) -> Result<(String, bool)> {
+    let (name, state) = setting
+        .split_once('=')
+        .with_context(|| format!("Expected NAME=on|off, got '{}'", setting))?;
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid_name {
+        anyhow::bail!("Invalid offload name '{}'", name);
+    }
+    let on = match state {
+        "on" => true,
+        "off" => false,
+        _ => anyhow::bail!("Offload state must be on or off, got '{}'", state),
+    };
+    Ok((name.to_string(), on))
+}
+
+/// CPU masks as sysfs takes them: hex words, comma-separated on big machines.
+fn cpu_mask(mask: &str) -> Result<&str> {
+    let valid = !mask.is_empty()
+        && mask
+            .split(',')
+            .all(|word| !word.is_empty() && word.chars().all(|c| c.is_ascii_hexdigit()));
+    if !valid {
+        anyhow::bail!(
+            "Invalid CPU mask '{}', expected hex like f or ff,ffffffff",
+            mask
+        );
+    }
+    Ok(mask)
+}
+
+/// N when the indirection table is `ethtool -X equal N`'s even spread
+pub fn equal_rss_queues(table: &[u32]) -> Option<u32> {
+    let queues = table.iter().max()? + 1;
+    table
+        .iter()
+        .enumerate()
+        .all(|(entry, queue)| entry as u32 % queues == *queue)
+        .then_some(queues)
+}
+
+/// The tuning the Queues tab form asks for; a blank field or the current
+/// value leaves that setting alone
+pub fn tuning_from_form(
+    rx_ring: &str,
+    tx_ring: &str,
+    rss_queues: &str,
+    current: &QueueStats,
+) -> Result<QueueTuning> {
+    let number = |value: &str, label: &str| -> Result<Option<u32>> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        match value.parse::<u32>() {
+            Ok(number) if number > 0 => Ok(Some(number)),
+            _ => anyhow::bail!("{} must be a number above zero", label),
+        }
+    };
+    let rings = current.rings.clone().unwrap_or_default();
+    let ring = |value: &str, label: &str, now: Option<u32>, max: Option<u32>| {
+        let size = number(value, label)?;
+        if let (Some(size), Some(max)) = (size, max) {
+            if size > max {
+                anyhow::bail!("{} can be at most {}", label, max);
+            }
+        }
+        Ok::<_, anyhow::Error>(size.filter(|size| Some(*size) != now))
+    };
+
+    let rss = number(rss_queues, "RSS queues")?;
+    if let Some(queues) = rss.filter(|_| current.rx_queues > 0) {
+        if queues as usize > current.rx_queues {
+            anyhow::bail!("The NIC has only {} RX queues", current.rx_queues);
+        }
+    }
+    Ok(QueueTuning {
+        rx_ring: ring(rx_ring, "RX ring", rings.rx, rings.rx_max)?,
+        tx_ring: ring(tx_ring, "TX ring", rings.tx, rings.tx_max)?,
+        rss_queues: rss.filter(|queues| Some(*queues) != equal_rss_queues(&current.rss_table)),
+        ..Default::default()
+    })
+}
+
+/// `ethtool -K` arguments for the offload changes, if there are any
+fn offload_args(interface: &str, tuning: &QueueTuning) -> Option<Vec<String>> {
+    if tuning.offloads.is_empty() {
+        return None;
+    }
+    let mut args = vec!["-K".to_string(), interface.to_string()];
+    for (name, on) in &tuning.offloads {
+        args.extend([name.clone(), if *on { "on" } else { "off" }.to_string()]);
+    }
+    Some(args)
+}
+
+/// `lantern tune` arguments that redo the ring, RSS and offload changes
+fn tune_args(interface: &str, tuning: &QueueTuning) -> Vec<String> {
+    let mut args = Vec::new();
+    for (flag, value) in [
+        ("--rx-ring", tuning.rx_ring),
+        ("--tx-ring", tuning.tx_ring),
+        ("--rss-queues", tuning.rss_queues),
+    ] {
+        if let Some(value) = value {
+            args.extend([flag.to_string(), value.to_string()]);
+        }
+    }
+    for (name, on) in &tuning.offloads {
+        args.extend([
+            "--offload".to_string(),
+            format!("{}={}", name, if *on { "on" } else { "off" }),
+        ]);
+    }
+    if !args.is_empty() {
+        args.splice(0..0, ["tune".to_string(), interface.to_string()]);
+    }
+    args
+}
+
+/// Apply the tuning now; the kernel forgets it when the NIC goes away.
+/// Rings and RSS go through ethtool netlink, offloads through ethtool.
+pub async fn apply_tuning(interface: &str, tuning: &QueueTuning) -> Result<()> {
+    sanitize::interface_name(interface)?;
+    if tuning.rx_ring.is_some() || tuning.tx_ring.is_some() {
+        netlink::set_ring_sizes(interface, tuning.rx_ring, tuning.tx_ring).map_err(|e| {
+            anyhow::anyhow!("Failed to change the ring sizes of {}: {}", interface, e)
+        })?;
+    }
+    if let Some(queues) = tuning.rss_queues {
+        netlink::spread_rss(interface, queues)
+            .map_err(|e| anyhow::anyhow!("Failed to spread RSS over {} queues: {}", queues, e))?;
+    }
+    if let Some(args) = offload_args(interface, tuning) {
+        let output = Command::new("/usr/sbin/ethtool")
+            .args(&args)
+            .timed_output()
+            .await?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "ethtool -K failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+    }
+
+    let (rx_queues, tx_queues) = count_queues(interface);
+    for (mask, direction, attribute, queues) in [
+        (&tuning.rps_cpus, "rx", "rps_cpus", rx_queues),
+        (&tuning.xps_cpus, "tx", "xps_cpus", tx_queues),
+    ] {
+        let Some(mask) = mask else {
+            continue;
+        };
+        let mask = cpu_mask(mask)?;
+        for queue in 0..queues {
+            let path = format!(
+                "/sys/class/net/{}/queues/{}-{}/{}",
+                interface, direction, queue, attribute
+            );
+            fs::write(&path, mask).with_context(|| format!("Failed to write {}", path))?;
+        }
+    }
+    Ok(())
+}
+
+fn udev_rule_path(interface: &str) -> PathBuf {
+    PathBuf::from(format!(
+        "/etc/udev/rules.d/70-lantern-tune-{}.rules",
+        interface
+    ))
+}
+
+/// A udev rule that replays the tuning whenever the NIC appears, at boot or on hotplug.
+fn udev_rule(
+    interface: &str,
+    tuning: &QueueTuning,
+    rx_queues: usize,
+    tx_queues: usize,
+    lantern: &Path,
+) -> Result<String> {
+    let mut actions = Vec::new();
+    if let Some(mask) = &tuning.rps_cpus {
+        let mask = cpu_mask(mask)?;
+        for queue in 0..rx_queues {
+            actions.push(format!("ATTR{{queues/rx-{}/rps_cpus}}=\"{}\"", queue, mask));
+        }
+    }
+    if let Some(mask) = &tuning.xps_cpus {
+        let mask = cpu_mask(mask)?;
+        for queue in 0..tx_queues {
+            actions.push(format!("ATTR{{queues/tx-{}/xps_cpus}}=\"{}\"", queue, mask));
+        }
+    }
+    let args = tune_args(interface, tuning);
+    if !args.is_empty() {
+        actions.push(format!("RUN+=\"{} {}\"", lantern.display(), args.join(" ")));
+    }
+
+    let mut rule = format!(
+        "# Queue tuning for {}, replayed by lantern tune when the NIC appears\n",
+        interface
+    );
+    let _ = writeln!(
+        rule,
+        "ACTION==\"add\", SUBSYSTEM==\"net\", KERNEL==\"{}\", {}",
+        interface,
+        actions.join(", ")
+    );
+    Ok(rule)
+}
+
+/// Keep the tuning across reboots and replugs.
+pub fn persist_tuning(interface: &str, tuning: &QueueTuning) -> Result<PathBuf> {
+    sanitize::interface_name(interface)?;
+    let (rx_queues, tx_queues) = count_queues(interface);
+    let path = udev_rule_path(interface);
+    let lantern = std::env::current_exe().context("Failed to locate the lantern binary")?;
+    let rule = udev_rule(interface, tuning, rx_queues, tx_queues, &lantern)?;
+    let operation = format!("Queue tuning for {}", interface);
+    secure_file::write_public(&path, provenance::stamp(&rule, &operation))?;
+    Ok(path)
+}
+
+/// Drop the persisted tuning; what is applied stays until the NIC is reset.
+pub fn remove_persisted_tuning(interface: &str) -> Result<bool> {
+    sanitize::interface_name(interface)?;
+    match fs::remove_file(udev_rule_path(interface)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_driver_queue_counters() {
+        let output = "NIC statistics:
+     rx_packets: 1000
+     tx_queue_0_packets: 40
+     tx_queue_0_bytes: 4000
+     rx_queue_0_packets: 600
+     rx_queue_1_packets: 400
+     rx-1.bytes: 52000
+     rx0_bytes: 78000
+     rx_queue_0_drops: 3
+";
+        let (rx, tx) = parse_ethtool_queues(output);
+        assert_eq!(rx.len(), 2);
+        assert_eq!(
+            rx[&0],
+            QueueCounters {
+                packets: Some(600),
+                bytes: Some(78000)
+            }
+        );
+        assert_eq!(rx[&1].bytes, Some(52000));
+        assert_eq!(tx[&0].packets, Some(40));
+    }
+
+    #[test]
+    fn finds_interface_irqs() {
+        let interrupts = "           CPU0       CPU1
+  0:         20          0   IO-APIC   2-edge      timer
+ 42:       1500        300   PCI-MSIX-0000:03:00.0    1-edge      eth0-TxRx-0
+ 43:         10       2200   PCI-MSIX-0000:03:00.0    2-edge      eth0-TxRx-1
+ 50:          5          0   PCI-MSIX-0000:04:00.0    0-edge      mlx5_comp0@pci:0000:04:00.0
+NMI:          0          0   Non-maskable interrupts
+";
+        let irqs = parse_interrupts(interrupts, "eth0", &[]);
+        assert_eq!(irqs.len(), 2);
+        assert_eq!(irqs[1].irq, 43);
+        assert_eq!(irqs[1].per_cpu, vec![10, 2200]);
+        assert_eq!(irqs[1].name, "eth0-TxRx-1");
+
+        let irqs = parse_interrupts(interrupts, "enp4s0", &[50]);
+        assert_eq!(irqs[0].name, "mlx5_comp0@pci:0000:04:00.0");
+    }
+
+    #[test]
+    fn parses_rings_and_rss_table() {
+        let rings = parse_ring_sizes(
+            "Ring parameters for eth0:
+Pre-set maximums:
+RX:\t\t4096
+RX Mini:\tn/a
+TX:\t\t4096
+Current hardware settings:
+RX:\t\t256
+RX Mini:\tn/a
+TX:\t\t512
+",
+        );
+        assert_eq!(
+            rings,
+            RingSizes {
+                rx: Some(256),
+                tx: Some(512),
+                rx_max: Some(4096),
+                tx_max: Some(4096)
+            }
+        );
+
+        let table = parse_rss_table(
+            "RX flow hash indirection table for eth0 with 2 RX ring(s):
+    0:      0     1     0     1
+    4:      0     1     0     0
+RSS hash key:
+12:34:56:78
+",
+        );
+        assert_eq!(table, vec![0, 1, 0, 1, 0, 1, 0, 0]);
+        assert_eq!(rss_spread(&table), "8 entries: q0 5, q1 3");
+    }
+
+    #[test]
+    fn writes_udev_rule_for_tuning() {
+        let tuning = QueueTuning {
+            rx_ring: Some(4096),
+            rss_queues: Some(2),
+            rps_cpus: Some("f".to_string()),
+            offloads: vec![("generic-receive-offload".to_string(), false)],
+            ..Default::default()
+        };
+        let lantern = Path::new("/usr/bin/lantern");
+        let rule = udev_rule("eth0", &tuning, 2, 2, lantern).unwrap();
+        assert!(rule.contains(
+            "KERNEL==\"eth0\", ATTR{queues/rx-0/rps_cpus}=\"f\", ATTR{queues/rx-1/rps_cpus}=\"f\", \
+             RUN+=\"/usr/bin/lantern tune eth0 --rx-ring 4096 --rss-queues 2 \
+             --offload generic-receive-offload=off\""
+        ));
+
+        let bad = QueueTuning {
+            xps_cpus: Some("f; rm".to_string()),
+            ..Default::default()
+        };
+        assert!(udev_rule("eth0", &bad, 1, 1, lantern).is_err());
+        assert_eq!(
+            parse_offload("gro=off").unwrap(),
+            ("gro".to_string(), false)
+        );
+        assert!(parse_offload("gro off\"=on").is_err());
+    }
+
+    #[test]
+    fn turns_the_form_into_changes() {
+        let current = QueueStats {
+            rx_queues: 4,
+            tx_queues: 4,
+            rings: Some(RingSizes {
+                rx: Some(256),
+                tx: Some(512),
+                rx_max: Some(4096),
+                tx_max: Some(4096),
+            }),
+            rss_table: vec![0, 1, 2, 3, 0, 1, 2, 3],
+            ..Default::default()
+        };
+        assert_eq!(equal_rss_queues(&current.rss_table), Some(4));
+        assert_eq!(equal_rss_queues(&[0, 1, 0, 0]), None);
+        assert_eq!(equal_rss_queues(&[]), None);
+
+        // Unchanged and blank fields change nothing
+        assert!(tuning_from_form("256", "", " 4 ", &current)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            tuning_from_form("2048", "512", "2", &current).unwrap(),
+            QueueTuning {
+                rx_ring: Some(2048),
+                rss_queues: Some(2),
+                ..Default::default()
+            }
+        );
+        assert!(tuning_from_form("8192", "", "", &current).is_err());
+        assert!(tuning_from_form("", "", "8", &current).is_err());
+        assert!(tuning_from_form("0", "", "", &current).is_err());
+        assert!(tuning_from_form("", "lots", "", &current).is_err());
+    }
+}
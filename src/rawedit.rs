@@ -0,0 +1,491 @@
+// src/rawedit.rs - Editing a lantern-written .network or .netdev file as text, for options the dialogs don't cover
+use crate::networkd::FileKind;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::path::PathBuf;
+
+/// Sections and keys from systemd.network(5), as of systemd 255
+const NETWORK_KEYS: &[(&str, &str)] = &[
+    (
+        "Match",
+        "MACAddress PermanentMACAddress Path Driver Type Kind Name Property Host \
+         Virtualization KernelCommandLine KernelVersion Credential Architecture Firmware \
+         WLANInterfaceType SSID BSSID",
+    ),
+    (
+        "Link",
+        "MACAddress MTUBytes ARP Multicast AllMulticast Promiscuous Unmanaged Group \
+         RequiredForOnline RequiredFamilyForOnline ActivationPolicy",
+    ),
+    (
+        "Network",
+        "Description DHCP DHCPServer LinkLocalAddressing \
+         IPv6LinkLocalAddressGenerationMode IPv6StableSecretAddress IPv4LLStartAddress \
+         IPv4LLRoute DefaultRouteOnDevice LLMNR MulticastDNS DNSOverTLS DNSSEC \
+         DNSSECNegativeTrustAnchors LLDP EmitLLDP BindCarrier Address Gateway DNS Domains \
+         DNSDefaultRoute NTP IPForward IPv4Forwarding IPv6Forwarding IPMasquerade \
+         IPv6PrivacyExtensions IPv6AcceptRA IPv6DuplicateAddressDetection IPv6HopLimit \
+         IPv4ReversePathFilter IPv4AcceptLocal IPv4RouteLocalnet IPv4ProxyARP \
+         IPv6ProxyNDP IPv6ProxyNDPAddress IPv6SendRA DHCPPrefixDelegation IPv6MTUBytes \
+         KeepMaster BatmanAdvanced Bond Bridge VRF IPoIB IPVLAN IPVTAP MACsec MACVLAN \
+         MACVTAP Tunnel VLAN VXLAN Xfrm ActiveSlave PrimarySlave ConfigureWithoutCarrier \
+         IgnoreCarrierLoss KeepConfiguration",
+    ),
+    (
+        "Address",
+        "Address Peer Broadcast Label PreferredLifetime Scope RouteMetric HomeAddress \
+         DuplicateAddressDetection ManageTemporaryAddress AddPrefixRoute AutoJoin \
+         NetLabel NFTSet",
+    ),
+    ("Neighbor", "Address LinkLayerAddress"),
+    ("IPv6AddressLabel", "Label Prefix"),
+    (
+        "RoutingPolicyRule",
+        "TypeOfService From To FirewallMark Table Priority IncomingInterface \
+         OutgoingInterface L3MasterDevice SourcePort DestinationPort IPProtocol \
+         InvertRule Family User SuppressPrefixLength SuppressInterfaceGroup Type",
+    ),
+    ("NextHop", "Id Gateway Family OnLink Blackhole Group"),
+    (
+        "Route",
+        "Gateway GatewayOnLink Destination Source Metric IPv6Preference Scope \
+         PreferredSource Table HopLimit Protocol Type InitialCongestionWindow \
+         InitialAdvertisedReceiveWindow QuickAck FastOpenNoCookie MTUBytes \
+         TCPAdvertisedMaximumSegmentSize TCPCongestionControlAlgorithm \
+         TCPRetransmissionTimeoutSec MultiPathRoute NextHop",
+    ),
+    (
+        "DHCPv4",
+        "RequestAddress SendHostname Hostname MUDURL ClientIdentifier \
+         VendorClassIdentifier UserClass DUIDType DUIDRawData IAID RapidCommit Anonymize \
+         RequestOptions SendOption SendVendorOption IPServiceType SocketPriority Label \
+         UseDNS RoutesToDNS UseNTP RoutesToNTP UseSIP UseCaptivePortal UseMTU UseHostname \
+         UseDomains UseRoutes RouteMetric RouteTable RouteMTUBytes QuickAck \
+         InitialCongestionWindow InitialAdvertisedReceiveWindow UseGateway UseTimezone \
+         Use6RD IPv6OnlyMode FallbackLeaseLifetimeSec RequestBroadcast MaxAttempts \
+         ListenPort DenyList AllowList SendRelease SendDecline NetLabel NFTSet",
+    ),
+    (
+        "DHCPv6",
+        "MUDURL IAID DUIDType DUIDRawData RequestOptions SendOption SendVendorOption \
+         UserClass VendorClass PrefixDelegationHint RapidCommit SendHostname Hostname \
+         UseAddress UseCaptivePortal UseDelegatedPrefix UseDNS UseNTP UseHostname \
+         UseDomains NetLabel NFTSet WithoutRA",
+    ),
+    (
+        "DHCPPrefixDelegation",
+        "UplinkInterface SubnetId Announce Assign Token ManageTemporaryAddress \
+         RouteMetric NetLabel NFTSet",
+    ),
+    (
+        "IPv6AcceptRA",
+        "Token UseDNS UseDomains RouteTable RouteMetric QuickAck UseMTU UseHopLimit \
+         UseReachableTime UseRetransmissionTime UseGateway UseRoutePrefix \
+         UseCaptivePortal UsePREF64 UseAutonomousPrefix UseOnLinkPrefix RouterDenyList \
+         RouterAllowList PrefixDenyList PrefixAllowList RouteDenyList RouteAllowList \
+         DHCPv6Client NetLabel NFTSet",
+    ),
+    (
+        "DHCPServer",
+        "ServerAddress PoolOffset PoolSize DefaultLeaseTimeSec MaxLeaseTimeSec \
+         UplinkInterface EmitDNS DNS EmitNTP NTP EmitSIP SIP EmitPOP3 POP3 EmitSMTP SMTP \
+         EmitLPR LPR EmitRouter Router EmitTimezone Timezone BootServerAddress \
+         BootServerName BootFilename SendOption SendVendorOption BindToInterface \
+         RelayTarget RelayAgentCircuitId RelayAgentRemoteId RapidCommit PersistLeases",
+    ),
+    ("DHCPServerStaticLease", "MACAddress Address"),
+    (
+        "IPv6SendRA",
+        "Managed OtherInformation RouterLifetimeSec ReachableTimeSec RetransmitSec \
+         RouterPreference HopLimit UplinkInterface EmitDNS DNS EmitDomains Domains \
+         DNSLifetimeSec HomeAgent HomeAgentLifetimeSec HomeAgentPreference",
+    ),
+    (
+        "IPv6Prefix",
+        "AddressAutoconfiguration OnLink Prefix PreferredLifetimeSec ValidLifetimeSec \
+         Assign Token RouteMetric",
+    ),
+    ("IPv6RoutePrefix", "Route LifetimeSec Preference"),
+    ("IPv6PREF64Prefix", "Prefix LifetimeSec"),
+    (
+        "Bridge",
+        "UnicastFlood MulticastFlood MulticastToUnicast NeighborSuppression Learning \
+         HairPin Isolated UseBPDU FastLeave AllowPortToBeRoot ProxyARP ProxyARPWiFi \
+         MulticastRouter Cost Priority",
+    ),
+    (
+        "BridgeFDB",
+        "MACAddress Destination VLANId VNI AssociatedWith OutgoingInterface",
+    ),
+    ("BridgeMDB", "MulticastGroupAddress VLANId"),
+    ("BridgeVLAN", "VLAN EgressUntagged PVID"),
+    ("LLDP", "MUDURL"),
+    (
+        "CAN",
+        "BitRate SamplePoint RestartSec Termination ListenOnly",
+    ),
+];
+
+/// Sections and keys from systemd.netdev(5) for the kinds lantern creates or
+/// adopts, as of systemd 255
+const NETDEV_KEYS: &[(&str, &str)] = &[
+    (
+        "Match",
+        "Host Virtualization KernelCommandLine KernelVersion Credential Architecture \
+         Firmware",
+    ),
+    ("NetDev", "Description Name Kind MTUBytes MACAddress"),
+    (
+        "WireGuard",
+        "PrivateKey PrivateKeyFile ListenPort FirewallMark RouteTable RouteMetric",
+    ),
+    (
+        "WireGuardPeer",
+        "PublicKey PresharedKey PresharedKeyFile AllowedIPs Endpoint PersistentKeepalive \
+         RouteTable RouteMetric",
+    ),
+    (
+        "VLAN",
+        "Id Protocol GVRP MVRP LooseBinding ReorderHeader EgressQOSMaps IngressQOSMaps",
+    ),
+    (
+        "Bridge",
+        "HelloTimeSec MaxAgeSec ForwardDelaySec AgeingTimeSec Priority GroupForwardMask \
+         DefaultPVID MulticastQuerier MulticastSnooping VLANFiltering VLANProtocol STP \
+         MulticastIGMPVersion",
+    ),
+    (
+        "Bond",
+        "Mode TransmitHashPolicy LACPTransmitRate MIIMonitorSec PeerNotifyDelaySec \
+         UpDelaySec DownDelaySec LearnPacketIntervalSec AdSelect AdActorSystemPriority \
+         AdUserPortKey AdActorSystem FailOverMACPolicy ARPValidate ARPIntervalSec \
+         ARPIPTargets ARPAllTargets PrimaryReselectPolicy ResendIGMP PacketsPerSlave \
+         GratuitousARP AllSlavesActive DynamicTransmitLoadBalancing MinLinks ARPMissedMax",
+    ),
+    (
+        "MACVLAN",
+        "Mode SourceMACAddress BroadcastMulticastQueueLength BroadcastQueueThreshold",
+    ),
+    ("VRF", "Table"),
+    ("Peer", "Name MACAddress"),
+    (
+        "Tun",
+        "MultiQueue PacketInfo VNetHeader User Group KeepCarrier",
+    ),
+    (
+        "Tap",
+        "MultiQueue PacketInfo VNetHeader User Group KeepCarrier",
+    ),
+];
+
+/// What one line of a unit-style file is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax<'a> {
+    Blank,
+    Comment,
+    Section(&'a str),
+    Setting { key: &'a str, value: &'a str },
+    Invalid,
+}
+
+pub fn syntax(line: &str) -> Syntax<'_> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return Syntax::Blank;
+    }
+    if trimmed.starts_with(['#', ';']) {
+        return Syntax::Comment;
+    }
+    if let Some(name) = trimmed.strip_prefix('[') {
+        return match name.strip_suffix(']') {
+            Some(name) if !name.is_empty() && !name.contains(['[', ']']) => Syntax::Section(name),
+            _ => Syntax::Invalid,
+        };
+    }
+    match trimmed.split_once('=') {
+        Some((key, value))
+            if !key.trim().is_empty() && key.trim().chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            Syntax::Setting {
+                key: key.trim(),
+                value: value.trim(),
+            }
+        }
+        _ => Syntax::Invalid,
+    }
+}
+
+/// A problem on one line (0-based; the last line for whole-file problems).
+/// Errors block saving; warnings are keys networkd would skip with a log
+/// line, or ones newer than this list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub line: usize,
+    pub message: String,
+    pub error: bool,
+}
+
+pub fn lint(kind: FileKind, contents: &str) -> Vec<Issue> {
+    let (known, manual) = match kind {
+        FileKind::NetDev => (NETDEV_KEYS, "systemd.netdev(5)"),
+        _ => (NETWORK_KEYS, "systemd.network(5)"),
+    };
+    let mut issues = Vec::new();
+    let mut section: Option<(&str, Option<&str>)> = None;
+    let mut sections = Vec::new();
+    let mut last = 0;
+    for (number, line) in contents.lines().enumerate() {
+        last = number;
+        let mut issue = |message: String, error: bool| {
+            issues.push(Issue {
+                line: number,
+                message,
+                error,
+            })
+        };
+        match syntax(line) {
+            Syntax::Blank | Syntax::Comment => {}
+            Syntax::Invalid if line.trim_start().starts_with('[') => {
+                issue("Malformed section header".to_string(), true)
+            }
+            Syntax::Invalid => issue(format!("Expected Key=value, found '{}'", line.trim()), true),
+            Syntax::Section(name) => {
+                let keys = known
+                    .iter()
+                    .find(|(known, _)| *known == name)
+                    .map(|(_, keys)| *keys);
+                if keys.is_none() {
+                    issue(
+                        format!("[{}] is not a section lantern knows from {}", name, manual),
+                        false,
+                    );
+                }
+                sections.push(name);
+                section = Some((name, keys));
+            }
+            Syntax::Setting { key, .. } => match section {
+                None => issue(format!("'{}' is outside any [Section]", key), true),
+                Some((name, Some(keys))) if !keys.split_whitespace().any(|known| known == key) => {
+                    issue(
+                        format!("{} is not a [{}] key in {}", key, name, manual),
+                        false,
+                    )
+                }
+                Some(_) => {}
+            },
+        }
+    }
+
+    let required: &[&str] = match kind {
+        FileKind::NetDev => &["NetDev"],
+        _ => &["Match"],
+    };
+    for name in required {
+        if !sections.contains(name) {
+            issues.push(Issue {
+                line: last,
+                message: format!("No [{}] section", name),
+                error: true,
+            });
+        }
+    }
+    issues
+}
+
+/// The file being edited, with a cursor. Columns count chars, not bytes.
+#[derive(Debug, Clone)]
+pub struct RawEditor {
+    pub interface: String,
+    pub path: PathBuf,
+    pub kind: FileKind,
+    pub lines: Vec<String>,
+    pub row: usize,
+    pub col: usize,
+    pub issues: Vec<Issue>,
+    pub confirm_discard: bool, // Esc once with unsaved changes
+    original: String,
+}
+
+impl RawEditor {
+    pub fn new(interface: &str, path: PathBuf, kind: FileKind, contents: &str) -> Self {
+        let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        let mut editor = Self {
+            interface: interface.to_string(),
+            path,
+            kind,
+            lines,
+            row: 0,
+            col: 0,
+            issues: Vec::new(),
+            confirm_discard: false,
+            original: String::new(),
+        };
+        editor.original = editor.contents();
+        editor.issues = lint(kind, &editor.original);
+        editor
+    }
+
+    pub fn contents(&self) -> String {
+        let mut contents = self.lines.join("\n");
+        contents.push('\n');
+        contents
+    }
+
+    pub fn modified(&self) -> bool {
+        self.contents() != self.original
+    }
+
+    pub fn first_error(&self) -> Option<&Issue> {
+        self.issues.iter().find(|issue| issue.error)
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    fn byte_at(&self, col: usize) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(col)
+            .map_or(line.len(), |(index, _)| index)
+    }
+
+    /// Moves the cursor or changes the text; Ctrl-S and Esc are the caller's
+    pub fn key(&mut self, key: KeyEvent) {
+        let page = 10;
+        let before = self.lines.clone();
+        match key.code {
+            KeyCode::Left if self.col > 0 => self.col -= 1,
+            KeyCode::Left if self.row > 0 => {
+                self.row -= 1;
+                self.col = self.line_len();
+            }
+            KeyCode::Right if self.col < self.line_len() => self.col += 1,
+            KeyCode::Right if self.row + 1 < self.lines.len() => {
+                self.row += 1;
+                self.col = 0;
+            }
+            KeyCode::Up => self.row = self.row.saturating_sub(1),
+            KeyCode::Down => self.row = (self.row + 1).min(self.lines.len() - 1),
+            KeyCode::PageUp => self.row = self.row.saturating_sub(page),
+            KeyCode::PageDown => self.row = (self.row + page).min(self.lines.len() - 1),
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.line_len(),
+            KeyCode::Enter => {
+                let at = self.byte_at(self.col);
+                let rest = self.lines[self.row].split_off(at);
+                self.lines.insert(self.row + 1, rest);
+                self.row += 1;
+                self.col = 0;
+            }
+            KeyCode::Backspace if self.col > 0 => {
+                self.col -= 1;
+                let at = self.byte_at(self.col);
+                self.lines[self.row].remove(at);
+            }
+            KeyCode::Backspace if self.row > 0 => {
+                let line = self.lines.remove(self.row);
+                self.row -= 1;
+                self.col = self.line_len();
+                self.lines[self.row].push_str(&line);
+            }
+            KeyCode::Delete if self.col < self.line_len() => {
+                let at = self.byte_at(self.col);
+                self.lines[self.row].remove(at);
+            }
+            KeyCode::Delete if self.row + 1 < self.lines.len() => {
+                let next = self.lines.remove(self.row + 1);
+                self.lines[self.row].push_str(&next);
+            }
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                let at = self.byte_at(self.col);
+                self.lines[self.row].insert(at, c);
+                self.col += 1;
+            }
+            _ => {}
+        }
+        self.col = self.col.min(self.line_len());
+        if self.lines != before {
+            self.confirm_discard = false;
+            self.issues = lint(self.kind, &self.contents());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(editor: &mut RawEditor, code: KeyCode) {
+        editor.key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn edits_and_lints_a_netdev() {
+        let netdev = "[NetDev]\nName=wg0\nKind=wireguard\n\n[WireGuard]\nListenPort=51820\n";
+        assert_eq!(lint(FileKind::NetDev, netdev), Vec::new());
+
+        let mut editor = RawEditor::new(
+            "wg0",
+            PathBuf::from("/x/50-wg0.netdev"),
+            FileKind::NetDev,
+            netdev,
+        );
+        assert!(!editor.modified());
+        editor.row = 5;
+        press(&mut editor, KeyCode::End);
+        press(&mut editor, KeyCode::Enter);
+        for c in "FirewalMark=7".chars() {
+            press(&mut editor, KeyCode::Char(c));
+        }
+        assert!(editor.modified());
+        assert_eq!(
+            editor.issues,
+            vec![Issue {
+                line: 6,
+                message: "FirewalMark is not a [WireGuard] key in systemd.netdev(5)".to_string(),
+                error: false,
+            }]
+        );
+        assert!(editor.first_error().is_none());
+
+        // Fix the typo: back to after "Firewal", add the missing l
+        press(&mut editor, KeyCode::Home);
+        for _ in 0.."Firewal".len() {
+            press(&mut editor, KeyCode::Right);
+        }
+        press(&mut editor, KeyCode::Char('l'));
+        assert_eq!(editor.lines[6], "FirewallMark=7");
+        assert!(editor.issues.is_empty());
+
+        // Joining lines breaks the syntax
+        press(&mut editor, KeyCode::Home);
+        press(&mut editor, KeyCode::Backspace);
+        assert_eq!(editor.lines[5], "ListenPort=51820FirewallMark=7");
+        press(&mut editor, KeyCode::Home);
+        press(&mut editor, KeyCode::Backspace);
+        assert_eq!(editor.lines[4], "[WireGuard]ListenPort=51820FirewallMark=7");
+        assert_eq!(editor.first_error().map(|issue| issue.line), Some(4));
+    }
+
+    #[test]
+    fn network_files_need_a_match() {
+        let issues = lint(FileKind::Network, "[Network]\nDHCP=yes\nBogus\n");
+        assert!(issues.iter().any(|i| i.error && i.line == 2));
+        assert!(issues.iter().any(|i| i.message == "No [Match] section"));
+        assert_eq!(syntax(" [Match] "), Syntax::Section("Match"));
+        assert_eq!(
+            syntax("Name = eth0"),
+            Syntax::Setting {
+                key: "Name",
+                value: "eth0"
+            }
+        );
+    }
+}
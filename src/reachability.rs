@@ -0,0 +1,407 @@
+// src/reachability.rs - IPv4 and IPv6 tested apart: address, route, ping, DNS and HTTP per stack
+use crate::command::TimedOutput;
+use crate::network::{parse_ping_rtt, Interface};
+use tokio::process::Command;
+
+/// Dual-stack, so a failure says something about the stack and not the site
+const DNS_NAME: &str = "www.google.com";
+const HTTP_URL: &str = "http://www.google.com/generate_204";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    pub fn label(self) -> &'static str {
+        match self {
+            Family::V4 => "IPv4",
+            Family::V6 => "IPv6",
+        }
+    }
+
+    fn flag(self) -> &'static str {
+        match self {
+            Family::V4 => "-4",
+            Family::V6 => "-6",
+        }
+    }
+
+    /// Cloudflare's resolver answers pings on both
+    fn ping_target(self) -> &'static str {
+        match self {
+            Family::V4 => "1.1.1.1",
+            Family::V6 => "2606:4700:4700::1111",
+        }
+    }
+
+    fn record_type(self) -> &'static str {
+        match self {
+            Family::V4 => "A",
+            Family::V6 => "AAAA",
+        }
+    }
+}
+
+/// The steps, in the order a packet needs them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Address,
+    Route,
+    Ping,
+    Dns,
+    Http,
+}
+
+impl Check {
+    pub fn label(self) -> &'static str {
+        match self {
+            Check::Address => "Address",
+            Check::Route => "Default route",
+            Check::Ping => "Ping",
+            Check::Dns => "DNS",
+            Check::Http => "HTTP",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Pass(String),
+    Fail(String),
+    /// Not tried, because an earlier step it needs failed
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackResult {
+    pub family: Family,
+    pub checks: Vec<(Check, Outcome)>,
+    /// Only meaningful for IPv6: whether the kernel listens to router advertisements
+    pub accept_ra: bool,
+}
+
+impl StackResult {
+    pub fn works(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|(_, outcome)| matches!(outcome, Outcome::Pass(_)))
+    }
+
+    pub fn first_failure(&self) -> Option<Check> {
+        self.checks
+            .iter()
+            .find(|(_, outcome)| matches!(outcome, Outcome::Fail(_)))
+            .map(|(check, _)| *check)
+    }
+
+    /// What the first failure most likely means
+    pub fn diagnosis(&self) -> String {
+        let family = self.family.label();
+        match (self.family, self.first_failure()) {
+            (_, None) => format!("{} works", family),
+            (Family::V6, Some(Check::Address | Check::Route)) if !self.accept_ra => format!(
+                "{} is not set up: router advertisements are ignored on this interface (accept_ra off)",
+                family
+            ),
+            (Family::V6, Some(Check::Address | Check::Route)) => format!(
+                "{} is not set up: no router advertisement arrived, so there is no address or default route",
+                family
+            ),
+            (_, Some(Check::Address)) => format!("{} has no address: DHCP or static setup is missing", family),
+            (_, Some(Check::Route)) => format!("{} has no default route: nothing leaves the local network", family),
+            (Family::V6, Some(Check::Ping)) => format!(
+                "{} is configured but nothing gets through upstream; applications may stall trying it before falling back to IPv4",
+                family
+            ),
+            (_, Some(Check::Ping)) => format!(
+                "{} is configured but nothing gets through: check the gateway and upstream link",
+                family
+            ),
+            (_, Some(Check::Dns)) => format!(
+                "{} packets get through but names don't resolve: check the DNS servers",
+                family
+            ),
+            (_, Some(Check::Http)) => format!(
+                "{} packets get through but web traffic doesn't: a captive portal, proxy or firewall?",
+                family
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReachabilityReport {
+    pub interface: String,
+    pub v4: StackResult,
+    pub v6: StackResult,
+}
+
+impl ReachabilityReport {
+    /// One line for the status bar: which stack is broken, if any
+    pub fn summary(&self) -> String {
+        match (self.v4.works(), self.v6.works()) {
+            (true, true) => format!("{}: IPv4 and IPv6 both work", self.interface),
+            (true, false) => format!("{}: IPv4 works, IPv6 is broken", self.interface),
+            (false, true) => format!("{}: IPv6 works, IPv4 is broken", self.interface),
+            (false, false) => format!("{}: neither IPv4 nor IPv6 works", self.interface),
+        }
+    }
+}
+
+/// Global addresses of the family; link-local IPv6 doesn't get anywhere
+pub fn global_addresses(interface: &Interface, family: Family) -> Vec<String> {
+    match family {
+        Family::V4 => interface.ipv4_addresses.clone(),
+        Family::V6 => interface
+            .ipv6_addresses
+            .iter()
+            .filter(|address| {
+                let address = address.to_lowercase();
+                !address.starts_with("fe80:") && !address.starts_with("::1/")
+            })
+            .cloned()
+            .collect(),
+    }
+}
+
+/// The first address in `resolvectl query -t A|AAAA` output:
+/// "www.google.com IN AAAA 2a00:1450:4014:80d::2004   -- link: eth0"
+pub fn parse_record(output: &str, record_type: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        words.find(|word| *word == record_type)?;
+        words.next().map(str::to_string)
+    })
+}
+
+/// curl's `-w "%{http_code} %{time_total}"`; a status of 000 means no response
+pub fn parse_http(output: &str) -> Option<(u16, f64)> {
+    let mut words = output.split_whitespace();
+    let code: u16 = words.next()?.parse().ok()?;
+    let seconds: f64 = words.next()?.parse().ok()?;
+    (code != 0).then_some((code, seconds))
+}
+
+async fn route(interface: &str, family: Family) -> Outcome {
+    let output = Command::new("/usr/bin/ip")
+        .args([family.flag(), "route", "show", "default", "dev", interface])
+        .timed_output()
+        .await;
+    match output {
+        Ok(output) => {
+            let routes = String::from_utf8_lossy(&output.stdout);
+            match routes.lines().next() {
+                Some(route) => Outcome::Pass(route.trim().to_string()),
+                None => Outcome::Fail(format!("No default route through {}", interface)),
+            }
+        }
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+async fn ping(interface: &str, family: Family) -> Outcome {
+    let target = family.ping_target();
+    let output = Command::new("/usr/bin/ping")
+        .args([
+            family.flag(),
+            "-c",
+            "2",
+            "-W",
+            "2",
+            "-n",
+            "-I",
+            interface,
+            target,
+        ])
+        .timed_output()
+        .await;
+    match output {
+        Ok(output) if output.status.success() => {
+            match parse_ping_rtt(&String::from_utf8_lossy(&output.stdout)) {
+                Some(rtt) => Outcome::Pass(format!(
+                    "{} in {:.1} ms",
+                    target,
+                    rtt.as_secs_f64() * 1000.0
+                )),
+                None => Outcome::Pass(format!("{} answered", target)),
+            }
+        }
+        Ok(_) => Outcome::Fail(format!("No reply from {}", target)),
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+pub(crate) async fn dns(interface: &str, family: Family) -> Outcome {
+    let record_type = family.record_type();
+    let output = Command::new("/usr/bin/resolvectl")
+        .args([
+            "query",
+            "--cache=no",
+            "--legend=no",
+            "-i",
+            interface,
+            "-t",
+            record_type,
+            DNS_NAME,
+        ])
+        .timed_output()
+        .await;
+    match output {
+        Ok(output) if output.status.success() => {
+            match parse_record(&String::from_utf8_lossy(&output.stdout), record_type) {
+                Some(address) => Outcome::Pass(format!("{} {} {}", DNS_NAME, record_type, address)),
+                None => Outcome::Fail(format!("No {} record for {}", record_type, DNS_NAME)),
+            }
+        }
+        Ok(output) => Outcome::Fail(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+pub(crate) async fn http(interface: &str, family: Family) -> Outcome {
+    let output = Command::new("/usr/bin/curl")
+        .args([
+            family.flag(),
+            "--interface",
+            interface,
+            "-s",
+            "-o",
+            "/dev/null",
+            "--max-time",
+            "5",
+            "-w",
+            "%{http_code} %{time_total}",
+            HTTP_URL,
+        ])
+        .timed_output()
+        .await;
+    match output {
+        Ok(output) => match parse_http(&String::from_utf8_lossy(&output.stdout)) {
+            Some((code, seconds)) => {
+                Outcome::Pass(format!("HTTP {} in {:.0} ms", code, seconds * 1000.0))
+            }
+            None => Outcome::Fail(format!("No response from {}", HTTP_URL)),
+        },
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+async fn test_stack(interface: &Interface, family: Family) -> StackResult {
+    let name = interface.name.as_str();
+    let addresses = global_addresses(interface, family);
+    let address = match addresses.first() {
+        Some(address) => Outcome::Pass(address.clone()),
+        None => Outcome::Fail(format!("No global {} address", family.label())),
+    };
+    let route = if matches!(address, Outcome::Pass(_)) {
+        route(name, family).await
+    } else {
+        Outcome::Skipped
+    };
+    let routed = matches!(route, Outcome::Pass(_));
+    // DNS goes through whichever servers the link has, so it's worth asking either way
+    let (ping, dns, http) = tokio::join!(
+        async {
+            if routed {
+                ping(name, family).await
+            } else {
+                Outcome::Skipped
+            }
+        },
+        dns(name, family),
+        async {
+            if routed {
+                http(name, family).await
+            } else {
+                Outcome::Skipped
+            }
+        },
+    );
+    StackResult {
+        family,
+        checks: vec![
+            (Check::Address, address),
+            (Check::Route, route),
+            (Check::Ping, ping),
+            (Check::Dns, dns),
+            (Check::Http, http),
+        ],
+        accept_ra: interface
+            .ipv6_info
+            .as_ref()
+            .is_none_or(|info| info.accept_ra),
+    }
+}
+
+/// Test both stacks of `interface` at once
+pub async fn run(interface: &Interface) -> ReachabilityReport {
+    let (v4, v6) = tokio::join!(
+        test_stack(interface, Family::V4),
+        test_stack(interface, Family::V6)
+    );
+    ReachabilityReport {
+        interface: interface.name.clone(),
+        v4,
+        v6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_which_stack_is_broken() {
+        assert_eq!(
+            parse_record(
+                "www.google.com IN AAAA 2a00:1450:4014:80d::2004         -- link: eth0\n",
+                "AAAA"
+            )
+            .as_deref(),
+            Some("2a00:1450:4014:80d::2004")
+        );
+        assert_eq!(
+            parse_record("www.google.com: resolve call failed", "A"),
+            None
+        );
+        assert_eq!(parse_http("204 0.084512"), Some((204, 0.084512)));
+        assert_eq!(parse_http("000 5.001"), None);
+
+        let pass = || Outcome::Pass(String::new());
+        let stack = |family, checks: Vec<(Check, Outcome)>| StackResult {
+            family,
+            checks,
+            accept_ra: true,
+        };
+        let report = ReachabilityReport {
+            interface: "eth0".to_string(),
+            v4: stack(
+                Family::V4,
+                vec![(Check::Address, pass()), (Check::Http, pass())],
+            ),
+            v6: stack(
+                Family::V6,
+                vec![
+                    (Check::Address, pass()),
+                    (Check::Route, pass()),
+                    (Check::Ping, Outcome::Fail("No reply".to_string())),
+                    (Check::Dns, pass()),
+                    (Check::Http, Outcome::Fail("No response".to_string())),
+                ],
+            ),
+        };
+        assert_eq!(report.summary(), "eth0: IPv4 works, IPv6 is broken");
+        assert_eq!(report.v6.first_failure(), Some(Check::Ping));
+        assert!(report.v6.diagnosis().contains("upstream"));
+
+        let mut unconfigured = stack(
+            Family::V6,
+            vec![(Check::Address, Outcome::Fail(String::new()))],
+        );
+        assert!(unconfigured.diagnosis().contains("no router advertisement"));
+        unconfigured.accept_ra = false;
+        assert!(unconfigured.diagnosis().contains("accept_ra off"));
+    }
+}
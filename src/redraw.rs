@@ -0,0 +1,139 @@
+// src/redraw.rs - Per-region dirty tracking, so a frame only re-renders the panes whose data changed
+use crate::pacing::Pace;
+use ratatui::{buffer::Buffer, layout::Rect, Frame};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// How long a status message stays in the footer
+pub const STATUS_DURATION_SECS: u64 = 3;
+
+/// The parts of the main screen that are rendered separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Region {
+    Header,
+    Interfaces,
+    Tcp,
+    Details,
+    Footer,
+    Overlay, // Dialogs and the perf overlay; redrawn in every frame they show in
+}
+
+impl Region {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Which regions need rendering, and what the clean ones looked like when
+/// they last were. Dialogs sit on top and are always drawn fresh, so the
+/// panes are cached before them.
+#[derive(Debug, Default)]
+pub struct Regions {
+    dirty: u8,
+    buffers: HashMap<Region, Buffer>,
+    // What the header and footer were showing, beyond what marks them dirty
+    pace: Option<Pace>,
+    status: Option<Instant>,
+}
+
+impl Regions {
+    pub fn mark(&mut self, regions: &[Region]) {
+        for region in regions {
+            self.dirty |= region.bit();
+        }
+    }
+
+    pub fn is_dirty(&self, region: Region) -> bool {
+        self.dirty & region.bit() != 0
+    }
+
+    pub fn any_dirty(&self) -> bool {
+        self.dirty != 0
+    }
+
+    pub fn clear(&mut self) {
+        self.dirty = 0;
+    }
+
+    /// The pace shown in the header changes with time alone
+    pub fn pace_changed(&self, pace: Pace) -> bool {
+        self.pace != Some(pace)
+    }
+
+    /// A status message appeared, was replaced or has timed out
+    pub fn status_changed(&self, status: Option<Instant>) -> bool {
+        self.status != status
+    }
+
+    pub fn drew_header(&mut self, pace: Pace) {
+        self.pace = Some(pace);
+    }
+
+    pub fn drew_footer(&mut self, status: Option<Instant>) {
+        self.status = status;
+    }
+
+    /// Render `region` into `area`, or reuse its cells from the last frame
+    /// when it isn't dirty and the layout hasn't moved.
+    pub fn draw(
+        &mut self,
+        f: &mut Frame,
+        region: Region,
+        area: Rect,
+        dirty: bool,
+        render: impl FnOnce(&mut Frame, Rect),
+    ) {
+        let reuse = !dirty
+            && !self.is_dirty(region)
+            && self
+                .buffers
+                .get(&region)
+                .is_some_and(|cached| cached.area == area);
+        if reuse {
+            if let Some(cached) = self.buffers.get(&region) {
+                f.buffer_mut().merge(cached);
+            }
+            return;
+        }
+
+        render(f, area);
+        let mut snapshot = Buffer::empty(area);
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                if let (Some(cell), Some(copy)) =
+                    (f.buffer_mut().cell((x, y)), snapshot.cell_mut((x, y)))
+                {
+                    *copy = cell.clone();
+                }
+            }
+        }
+        self.buffers.insert(region, snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_regions_separately() {
+        let mut regions = Regions::default();
+        assert!(!regions.any_dirty());
+        regions.mark(&[Region::Tcp, Region::Footer]);
+        assert!(regions.is_dirty(Region::Tcp));
+        assert!(regions.is_dirty(Region::Footer));
+        assert!(!regions.is_dirty(Region::Details));
+        regions.clear();
+        assert!(!regions.any_dirty());
+
+        assert!(regions.pace_changed(Pace::Live));
+        regions.drew_header(Pace::Live);
+        assert!(!regions.pace_changed(Pace::Live));
+        assert!(regions.pace_changed(Pace::Idle));
+
+        let shown = Some(Instant::now());
+        regions.drew_footer(shown);
+        assert!(!regions.status_changed(shown));
+        assert!(regions.status_changed(None));
+    }
+}
@@ -0,0 +1,378 @@
+// src/replay.rs - A pcap capture or generated test frames sent out an interface at a set rate
+use anyhow::{anyhow, Context, Result};
+use std::borrow::Cow;
+use std::io;
+use std::time::{Duration, Instant};
+
+const PCAP_HEADER_LEN: usize = 24;
+const RECORD_HEADER_LEN: usize = 16;
+const LINKTYPE_ETHERNET: u32 = 1;
+const PCAPNG_MAGIC: u32 = 0x0a0d_0d0a;
+// IEEE's EtherType for local experiments, so switches and hosts leave the
+// test frames alone
+const TEST_ETHERTYPE: u16 = 0x88b5;
+const VLAN_ETHERTYPE: u16 = 0x8100;
+const FCS_LEN: usize = 4;
+/// Smallest Ethernet frame, FCS included
+pub const MIN_FRAME_SIZE: usize = 64;
+
+/// One captured frame and when it was seen, from the first one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub data: Vec<u8>,
+    pub offset: Duration,
+}
+
+/// The frames of a classic libpcap file of Ethernet captures, and how many
+/// were cut short by the capture's snap length (they go out as captured)
+pub fn parse_pcap(file: &[u8]) -> Result<(Vec<Frame>, usize)> {
+    if file.len() < PCAP_HEADER_LEN {
+        return Err(anyhow!("Not a pcap file: too short"));
+    }
+    let magic = u32::from_le_bytes(file[0..4].try_into()?);
+    let (big_endian, nanos) = match magic {
+        0xa1b2_c3d4 => (false, false),
+        0xa1b2_3c4d => (false, true),
+        0xd4c3_b2a1 => (true, false),
+        0x4d3c_b2a1 => (true, true),
+        PCAPNG_MAGIC => {
+            return Err(anyhow!(
+                "pcapng files aren't supported; convert with `editcap -F pcap in.pcapng out.pcap`"
+            ))
+        }
+        _ => return Err(anyhow!("Not a pcap file")),
+    };
+    let read_u32 = |at: usize| -> u32 {
+        let bytes: [u8; 4] = file[at..at + 4].try_into().unwrap_or_default();
+        if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    };
+    let linktype = read_u32(20) & 0x0fff_ffff; // The upper bits carry FCS flags
+    if linktype != LINKTYPE_ETHERNET {
+        return Err(anyhow!(
+            "Only Ethernet captures can be sent, this one has link type {}",
+            linktype
+        ));
+    }
+
+    let mut frames = Vec::new();
+    let mut truncated = 0;
+    let mut first = None;
+    let mut at = PCAP_HEADER_LEN;
+    while at + RECORD_HEADER_LEN <= file.len() {
+        let (secs, fraction) = (read_u32(at) as u64, read_u32(at + 4) as u64);
+        let (captured, original) = (read_u32(at + 8) as usize, read_u32(at + 12) as usize);
+        at += RECORD_HEADER_LEN;
+        let data = file
+            .get(at..at + captured)
+            .ok_or_else(|| anyhow!("Capture ends in the middle of frame {}", frames.len() + 1))?;
+        at += captured;
+
+        let seen = Duration::from_secs(secs)
+            + if nanos {
+                Duration::from_nanos(fraction)
+            } else {
+                Duration::from_micros(fraction)
+            };
+        let first = *first.get_or_insert(seen);
+        if captured < original {
+            truncated += 1;
+        }
+        frames.push(Frame {
+            data: data.to_vec(),
+            offset: seen.saturating_sub(first),
+        });
+    }
+    Ok((frames, truncated))
+}
+
+pub fn parse_mac(mac: &str) -> Result<[u8; 6]> {
+    let octets: Vec<u8> = mac
+        .split(':')
+        .map(|octet| u8::from_str_radix(octet, 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| anyhow!("Invalid MAC address {:?}", mac))?;
+    octets
+        .try_into()
+        .map_err(|_| anyhow!("Invalid MAC address {:?}", mac))
+}
+
+/// What the generated frames look like
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestFrames {
+    pub destination: [u8; 6],
+    pub source: [u8; 6],
+    pub vlan: Option<u16>,
+    /// On the wire, FCS included
+    pub size: usize,
+}
+
+impl TestFrames {
+    /// Frame number `sequence`: "LANTERN" and the number after the headers,
+    /// zero padded to the size
+    pub fn frame(&self, sequence: u64) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(self.size);
+        frame.extend_from_slice(&self.destination);
+        frame.extend_from_slice(&self.source);
+        if let Some(vlan) = self.vlan {
+            frame.extend_from_slice(&VLAN_ETHERTYPE.to_be_bytes());
+            frame.extend_from_slice(&(vlan & 0x0fff).to_be_bytes());
+        }
+        frame.extend_from_slice(&TEST_ETHERTYPE.to_be_bytes());
+        frame.extend_from_slice(b"LANTERN");
+        frame.extend_from_slice(&sequence.to_be_bytes());
+        // The NIC appends the FCS
+        frame.resize(frame.len().max(self.size.max(MIN_FRAME_SIZE) - FCS_LEN), 0);
+        frame
+    }
+}
+
+/// What goes out
+pub enum Source {
+    Capture { frames: Vec<Frame>, loops: usize },
+    Generated { frames: TestFrames, count: usize },
+}
+
+impl Source {
+    fn len(&self) -> usize {
+        match self {
+            Source::Capture { frames, loops } => frames.len() * loops,
+            Source::Generated { count, .. } => *count,
+        }
+    }
+
+    // The frame and, for captures, when it was seen within its loop
+    fn frame(&self, index: usize) -> (Cow<'_, [u8]>, Duration) {
+        match self {
+            Source::Capture { frames, .. } => {
+                let frame = &frames[index % frames.len()];
+                (Cow::Borrowed(&frame.data), frame.offset)
+            }
+            Source::Generated { frames, .. } => {
+                (Cow::Owned(frames.frame(index as u64)), Duration::ZERO)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rate {
+    PacketsPerSecond(f64),
+    Megabits(f64), // Frame bytes, without preamble and gap
+    /// As the capture was taken, each loop right after the last
+    Recorded,
+}
+
+/// When frame `index` is due, from the start: `sent` bytes went before it
+/// and `offset`/`loop_start` place it in the capture
+fn due(rate: Rate, index: usize, sent: u64, offset: Duration, loop_start: Duration) -> Duration {
+    match rate {
+        Rate::PacketsPerSecond(pps) => Duration::from_secs_f64(index as f64 / pps),
+        Rate::Megabits(mbps) => Duration::from_secs_f64(sent as f64 * 8.0 / (mbps * 1e6)),
+        Rate::Recorded => loop_start + offset,
+    }
+}
+
+/// How a run went
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub sent: u64,
+    pub bytes: u64,
+    pub failed: u64,
+    pub first_error: Option<String>,
+    pub elapsed: Duration,
+}
+
+/// Send every frame of `source` out `interface`, paced by `rate`; `progress`
+/// hears about the run about once a second. Blocks until done.
+pub fn send(
+    interface: &str,
+    source: &Source,
+    rate: Rate,
+    mut progress: impl FnMut(&Summary, usize),
+) -> Result<Summary> {
+    crate::sanitize::interface_name(interface)?;
+    let socket = PacketSocket::open(interface)
+        .with_context(|| format!("Failed to open a packet socket on {}", interface))?;
+
+    let total = source.len();
+    let start = Instant::now();
+    let mut summary = Summary::default();
+    let mut loop_start = Duration::ZERO;
+    let mut last_offset = Duration::ZERO;
+    let mut last_report = start;
+    for index in 0..total {
+        let (frame, offset) = source.frame(index);
+        if offset < last_offset {
+            // The capture starts over; its first frame follows the last one
+            loop_start += last_offset;
+        }
+        last_offset = offset;
+
+        let due = start + due(rate, index, summary.bytes, offset, loop_start);
+        let now = Instant::now();
+        if due > now {
+            std::thread::sleep(due - now);
+        }
+        match socket.send(&frame) {
+            Ok(()) => {
+                summary.sent += 1;
+                summary.bytes += frame.len() as u64;
+            }
+            Err(e) => {
+                summary.failed += 1;
+                summary
+                    .first_error
+                    .get_or_insert_with(|| format!("frame {}: {}", index + 1, e));
+            }
+        }
+
+        if last_report.elapsed() >= Duration::from_secs(1) {
+            last_report = Instant::now();
+            summary.elapsed = start.elapsed();
+            progress(&summary, total);
+        }
+    }
+    summary.elapsed = start.elapsed();
+    Ok(summary)
+}
+
+/// An AF_PACKET socket bound to one interface, for sending whole frames
+struct PacketSocket(libc::c_int);
+
+impl PacketSocket {
+    fn open(interface: &str) -> io::Result<Self> {
+        let name = std::ffi::CString::new(interface)
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        // SAFETY: name is a valid NUL-terminated string for the call
+        let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if index == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Protocol 0: nothing is received on it
+        // SAFETY: plain socket(2) call, the fd is owned and closed by Drop
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = Self(fd);
+
+        // SAFETY: zeroed sockaddr_ll is valid; family and index are set below
+        let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as libc::sa_family_t;
+        addr.sll_ifindex = index as libc::c_int;
+        // SAFETY: addr outlives the call and its length is passed along
+        let bound = unsafe {
+            libc::bind(
+                socket.0,
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(socket)
+    }
+
+    fn send(&self, frame: &[u8]) -> io::Result<()> {
+        // SAFETY: frame is valid for reads of frame.len() bytes
+        let sent = unsafe {
+            libc::send(
+                self.0,
+                frame.as_ptr() as *const libc::c_void,
+                frame.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PacketSocket {
+    fn drop(&mut self) {
+        // SAFETY: the fd came from socket(2) and is closed exactly once
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcap(big_endian: bool, records: &[(u32, u32, &[u8], u32)]) -> Vec<u8> {
+        let word = |value: u32| {
+            if big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        };
+        let mut file = Vec::new();
+        file.extend_from_slice(&word(0xa1b2_c3d4));
+        file.extend_from_slice(&[0, 2, 0, 4]); // Version, unchecked
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&word(65535));
+        file.extend_from_slice(&word(LINKTYPE_ETHERNET));
+        for (secs, micros, data, original) in records {
+            for value in [*secs, *micros, data.len() as u32, *original] {
+                file.extend_from_slice(&word(value));
+            }
+            file.extend_from_slice(data);
+        }
+        file
+    }
+
+    #[test]
+    fn reads_captures_and_builds_test_frames() {
+        for big_endian in [false, true] {
+            let file = pcap(
+                big_endian,
+                &[(100, 500_000, &[1; 60], 60), (101, 0, &[2; 40], 1500)],
+            );
+            let (frames, truncated) = parse_pcap(&file).unwrap();
+            assert_eq!(frames.len(), 2);
+            assert_eq!(frames[1].data, vec![2; 40]);
+            assert_eq!(frames[1].offset, Duration::from_millis(500));
+            assert_eq!(truncated, 1);
+        }
+        let mut cut = pcap(false, &[(0, 0, &[1; 60], 60)]);
+        cut.truncate(cut.len() - 1);
+        assert!(parse_pcap(&cut).is_err());
+        assert!(parse_pcap(&PCAPNG_MAGIC.to_le_bytes().repeat(8))
+            .unwrap_err()
+            .to_string()
+            .contains("pcapng"));
+
+        let frames = TestFrames {
+            destination: parse_mac("ff:ff:ff:ff:ff:ff").unwrap(),
+            source: parse_mac("02:00:00:00:00:01").unwrap(),
+            vlan: Some(100),
+            size: MIN_FRAME_SIZE,
+        };
+        let frame = frames.frame(7);
+        assert_eq!(frame.len(), 60);
+        assert_eq!(&frame[12..18], &[0x81, 0x00, 0x00, 100, 0x88, 0xb5]);
+        assert_eq!(&frame[18..25], b"LANTERN");
+        assert_eq!(frame[32], 7);
+        assert!(parse_mac("02:00:00:00:00").is_err());
+
+        let second = Duration::from_secs(1);
+        let pps = due(Rate::PacketsPerSecond(4.0), 2, 0, second, second);
+        assert_eq!(pps, Duration::from_millis(500));
+        // 125 000 bytes are a second's worth at 1 Mbit/s
+        let mbps = due(Rate::Megabits(1.0), 9, 125_000, second, second);
+        assert_eq!(mbps, second);
+        let recorded = due(Rate::Recorded, 2, 0, second, second * 3);
+        assert_eq!(recorded, second * 4);
+    }
+}
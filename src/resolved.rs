@@ -0,0 +1,441 @@
+// src/resolved.rs - systemd-resolved's global and per-link settings, changed through drop-ins
+use crate::command::TimedOutput;
+use crate::{provenance, sanitize, secure_file};
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+const MAIN_CONFIG: &str = "/etc/systemd/resolved.conf";
+/// Lantern's own; the other files are only read
+pub const DROP_IN: &str = "/etc/systemd/resolved.conf.d/50-lantern.conf";
+// Lowest priority first: a file of the same name further down replaces it
+const DROP_IN_DIRS: [&str; 3] = [
+    "/usr/lib/systemd/resolved.conf.d",
+    "/run/systemd/resolved.conf.d",
+    "/etc/systemd/resolved.conf.d",
+];
+
+// Per-link LLMNR= and MulticastDNS=, next to the interface's .network file
+const LINK_DROP_IN: &str = "60-lantern-resolve.conf";
+
+/// DNSOverTLS= values
+pub const TLS_MODES: [&str; 3] = ["no", "opportunistic", "yes"];
+/// LLMNR= and MulticastDNS= values
+pub const RESPONDER_MODES: [&str; 3] = ["yes", "resolve", "no"];
+
+/// The [Resolve] settings lantern manages. Empty or None: not set, so an
+/// earlier file or resolved's own default applies.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GlobalDns {
+    pub dns: Vec<String>,
+    pub fallback_dns: Vec<String>,
+    pub domains: Vec<String>,
+    pub dns_over_tls: Option<String>,
+    pub llmnr: Option<String>,
+    pub multicast_dns: Option<String>,
+}
+
+impl GlobalDns {
+    /// Apply one file's [Resolve] section over what earlier files set, as
+    /// resolved does: lists grow with each line and an empty one clears them
+    fn apply(&mut self, content: &str) {
+        let mut in_resolve = false;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_resolve = line == "[Resolve]";
+                continue;
+            }
+            if !in_resolve || line.starts_with(['#', ';']) {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            let list = match key.trim() {
+                "DNS" => &mut self.dns,
+                "FallbackDNS" => &mut self.fallback_dns,
+                "Domains" => &mut self.domains,
+                key => {
+                    let setting = match key {
+                        "DNSOverTLS" => &mut self.dns_over_tls,
+                        "LLMNR" => &mut self.llmnr,
+                        "MulticastDNS" => &mut self.multicast_dns,
+                        _ => continue,
+                    };
+                    *setting = (!value.is_empty()).then(|| value.to_string());
+                    continue;
+                }
+            };
+            if value.is_empty() {
+                list.clear();
+            } else {
+                list.extend(value.split_whitespace().map(str::to_string));
+            }
+        }
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut settings = Self::default();
+        settings.apply(content);
+        settings
+    }
+
+    /// The drop-in setting only what is set here
+    pub fn to_drop_in(&self) -> String {
+        let mut content = String::from("[Resolve]\n");
+        for (key, values) in [
+            ("DNS", &self.dns),
+            ("FallbackDNS", &self.fallback_dns),
+            ("Domains", &self.domains),
+        ] {
+            if !values.is_empty() {
+                content.push_str(&format!("{}={}\n", key, values.join(" ")));
+            }
+        }
+        for (key, value) in [
+            ("DNSOverTLS", &self.dns_over_tls),
+            ("LLMNR", &self.llmnr),
+            ("MulticastDNS", &self.multicast_dns),
+        ] {
+            if let Some(value) = value {
+                content.push_str(&format!("{}={}\n", key, value));
+            }
+        }
+        content
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for server in self.dns.iter().chain(&self.fallback_dns) {
+            if !valid_server(server) {
+                return Err(anyhow!("{:?} is not a DNS server address", server));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An address as resolved takes it: optionally with a port, an interface
+/// (`%eth0`) and a name to check the certificate against (`#dns.quad9.net`)
+pub fn valid_server(server: &str) -> bool {
+    let address = server
+        .split_once('#')
+        .map_or(server, |(address, _)| address);
+    let address = address
+        .split_once('%')
+        .map_or(address, |(address, _)| address);
+    if let Some(bracketed) = address.strip_prefix('[') {
+        let Some((ip, rest)) = bracketed.split_once(']') else {
+            return false;
+        };
+        let port_ok = rest.is_empty()
+            || rest
+                .strip_prefix(':')
+                .is_some_and(|port| port.parse::<u16>().is_ok());
+        return ip.parse::<Ipv6Addr>().is_ok() && port_ok;
+    }
+    if address.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+    address
+        .split_once(':')
+        .is_some_and(|(ip, port)| ip.parse::<IpAddr>().is_ok() && port.parse::<u16>().is_ok())
+}
+
+/// The next of `modes` after `value`, and back to unset after the last
+pub fn cycle(value: &Option<String>, modes: &[&str]) -> Option<String> {
+    let next = match value {
+        None => 0,
+        Some(value) => modes
+            .iter()
+            .position(|mode| mode == value)
+            .map_or(0, |i| i + 1),
+    };
+    modes.get(next).map(|mode| mode.to_string())
+}
+
+/// resolved.conf, then the drop-ins in the order resolved reads them
+fn config_files() -> Vec<PathBuf> {
+    let mut drop_ins = BTreeMap::new();
+    for dir in DROP_IN_DIRS {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.ends_with(".conf") {
+                drop_ins.insert(name, entry.path());
+            }
+        }
+    }
+    let mut files = vec![PathBuf::from(MAIN_CONFIG)];
+    files.extend(drop_ins.into_values());
+    files
+}
+
+/// What is in effect once every file is read, and what lantern's drop-in sets
+pub fn load() -> (GlobalDns, GlobalDns) {
+    let mut effective = GlobalDns::default();
+    for file in config_files() {
+        if let Ok(content) = fs::read_to_string(&file) {
+            effective.apply(&content);
+        }
+    }
+    let ours = fs::read_to_string(DROP_IN)
+        .map(|content| GlobalDns::parse(&content))
+        .unwrap_or_default();
+    (effective, ours)
+}
+
+/// Write lantern's drop-in, or remove it when it would set nothing, and
+/// restart resolved if it is running. Returns whether it was restarted.
+pub async fn save(settings: &GlobalDns) -> Result<bool> {
+    settings.validate()?;
+    let path = Path::new(DROP_IN);
+    if *settings == GlobalDns::default() {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Failed to remove {}", DROP_IN));
+            }
+            _ => {}
+        }
+    } else {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let drop_in = provenance::stamp(&settings.to_drop_in(), "Global DNS settings");
+        secure_file::write_public(path, drop_in)?;
+    }
+
+    let running = Command::new("/usr/bin/systemctl")
+        .args(["is-active", "--quiet", "systemd-resolved"])
+        .timed_output()
+        .await
+        .is_ok_and(|output| output.status.success());
+    if !running {
+        return Ok(false);
+    }
+    let output = Command::new("/usr/bin/systemctl")
+        .args(["restart", "systemd-resolved"])
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to restart systemd-resolved: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(true)
+}
+
+/// LLMNR= and MulticastDNS= for one link; None leaves networkd's default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkResponders {
+    pub llmnr: Option<String>,
+    pub multicast_dns: Option<String>,
+}
+
+impl LinkResponders {
+    /// The [Network] settings of a .network file or drop-in
+    pub fn parse(content: &str) -> Self {
+        let mut settings = Self::default();
+        let mut in_network = false;
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_network = line == "[Network]";
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=').filter(|_| in_network) else {
+                continue;
+            };
+            let value = value.trim();
+            let setting = match key.trim() {
+                "LLMNR" => &mut settings.llmnr,
+                "MulticastDNS" => &mut settings.multicast_dns,
+                _ => continue,
+            };
+            *setting = (!value.is_empty()).then(|| value.to_string());
+        }
+        settings
+    }
+
+    pub fn to_drop_in(&self) -> String {
+        let mut content = String::from("[Network]\n");
+        for (key, value) in [
+            ("LLMNR", &self.llmnr),
+            ("MulticastDNS", &self.multicast_dns),
+        ] {
+            if let Some(value) = value {
+                content.push_str(&format!("{}={}\n", key, value));
+            }
+        }
+        content
+    }
+}
+
+/// The mode from `resolvectl llmnr <iface>` or `resolvectl mdns <iface>`:
+/// "Link 2 (eth0): resolve"
+pub fn parse_link_mode(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Link ")?.split_once("): "))
+        .map(|(_, mode)| mode.trim().to_string())
+        .filter(|mode| !mode.is_empty())
+}
+
+async fn resolvectl(args: &[&str]) -> Result<String> {
+    let output = Command::new("/usr/bin/resolvectl")
+        .args(args)
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "resolvectl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// What resolved is doing on the link now; None where it can't say (not
+/// running, or the link unknown to it)
+pub async fn link_state(interface: &str) -> LinkResponders {
+    let mut state = LinkResponders::default();
+    if sanitize::interface_name(interface).is_err() {
+        return state;
+    }
+    for (verb, setting) in [
+        ("llmnr", &mut state.llmnr),
+        ("mdns", &mut state.multicast_dns),
+    ] {
+        *setting = resolvectl(&[verb, interface])
+            .await
+            .ok()
+            .and_then(|output| parse_link_mode(&output));
+    }
+    state
+}
+
+/// What lantern's drop-in for the link sets
+pub async fn link_settings(interface: &str) -> LinkResponders {
+    let Some(path) = crate::networkd::drop_in_path(interface, LINK_DROP_IN).await else {
+        return LinkResponders::default();
+    };
+    fs::read_to_string(path)
+        .map(|content| LinkResponders::parse(&content))
+        .unwrap_or_default()
+}
+
+/// Write the link's drop-in, or remove it when it sets nothing, and tell
+/// resolved now. Returns whether it could be persisted, which needs the
+/// interface to be managed by networkd.
+pub async fn set_link(interface: &str, settings: &LinkResponders) -> Result<bool> {
+    sanitize::interface_name(interface)?;
+    for value in [&settings.llmnr, &settings.multicast_dns]
+        .into_iter()
+        .flatten()
+    {
+        if !RESPONDER_MODES.contains(&value.as_str()) {
+            return Err(anyhow!(
+                "{:?} is not one of {}",
+                value,
+                RESPONDER_MODES.join(", ")
+            ));
+        }
+    }
+
+    let persisted = match crate::networkd::drop_in_path(interface, LINK_DROP_IN).await {
+        Some(path) => {
+            if *settings == LinkResponders::default() {
+                if path.exists() {
+                    fs::remove_file(&path)?;
+                }
+            } else {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                let operation = format!("Name resolution responders on {}", interface);
+                let drop_in = provenance::stamp(&settings.to_drop_in(), &operation);
+                secure_file::write_public(&path, drop_in)?;
+            }
+            crate::systemd::reload_networkd().await?;
+            true
+        }
+        None => false,
+    };
+
+    // Reloading doesn't reach resolved until the link is reconfigured, so
+    // set it directly; unset means networkd's defaults
+    resolvectl(&[
+        "llmnr",
+        interface,
+        settings.llmnr.as_deref().unwrap_or("yes"),
+    ])
+    .await?;
+    resolvectl(&[
+        "mdns",
+        interface,
+        settings.multicast_dns.as_deref().unwrap_or("no"),
+    ])
+    .await?;
+    Ok(persisted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_settings_like_resolved() {
+        let mut settings = GlobalDns::parse(
+            "[Resolve]\n#DNS=\nDNS=1.1.1.1\nDNS=9.9.9.9#dns.quad9.net\nFallbackDNS=8.8.8.8\n\
+             LLMNR=no\n[Other]\nDNS=10.0.0.1\n",
+        );
+        assert_eq!(settings.dns, ["1.1.1.1", "9.9.9.9#dns.quad9.net"]);
+        assert_eq!(settings.llmnr.as_deref(), Some("no"));
+        // A later file: an empty list clears, an empty value unsets
+        settings.apply("[Resolve]\nFallbackDNS=\nLLMNR=\nDNSOverTLS=opportunistic\n");
+        assert!(settings.fallback_dns.is_empty());
+        assert_eq!(settings.llmnr, None);
+        assert_eq!(
+            settings.to_drop_in(),
+            "[Resolve]\nDNS=1.1.1.1 9.9.9.9#dns.quad9.net\n\
+             DNSOverTLS=opportunistic\n"
+        );
+        assert_eq!(GlobalDns::parse(&settings.to_drop_in()), settings);
+
+        assert!(valid_server("2606:4700::1111"));
+        assert!(valid_server("[2606:4700::1111]:853#one.one.one.one"));
+        assert!(valid_server("192.168.1.1:5353%eth0"));
+        assert!(!valid_server("dns.google"));
+        assert!(!valid_server("[::1"));
+
+        let mut mode = None;
+        mode = cycle(&mode, &TLS_MODES);
+        assert_eq!(mode.as_deref(), Some("no"));
+        mode = cycle(&Some("yes".to_string()), &TLS_MODES);
+        assert_eq!(mode, None);
+    }
+
+    #[test]
+    fn reads_per_link_responders() {
+        assert_eq!(
+            parse_link_mode("Link 3 (wlan0): resolve\n").as_deref(),
+            Some("resolve")
+        );
+        assert_eq!(parse_link_mode("Global: yes\n"), None);
+
+        let settings = LinkResponders::parse(
+            "[Match]\nName=eth0\n[Network]\nDHCP=yes\nLLMNR=no\nMulticastDNS=\n[DHCP]\nLLMNR=yes\n",
+        );
+        assert_eq!(settings.llmnr.as_deref(), Some("no"));
+        assert_eq!(settings.multicast_dns, None);
+        assert_eq!(settings.to_drop_in(), "[Network]\nLLMNR=no\n");
+        assert_eq!(LinkResponders::parse(&settings.to_drop_in()), settings);
+    }
+}
@@ -0,0 +1,128 @@
+// src/retry.rs - Exponential backoff for operations that fail transiently
+use anyhow::Result;
+use std::future::Future;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// iwd only knows about networks from its own scans, so a connect right
+    /// after an `iw` scan can fail until iwd catches up
+    pub const IWD_CONNECT: Self = Self::new(3, 500, 2_000);
+    /// networkctl fails while systemd-networkd is restarting
+    pub const NETWORKD_RELOAD: Self = Self::new(4, 200, 1_000);
+    /// Waiting for a DHCP lease after (re)configuring an interface, ~15s total
+    pub const DHCP_LEASE: Self = Self::new(8, 250, 4_000);
+    /// Waiting for the kernel to report a link state change
+    pub const LINK_STATE: Self = Self::new(6, 100, 1_000);
+
+    pub const fn new(attempts: u32, initial_delay_ms: u64, max_delay_ms: u64) -> Self {
+        Self {
+            attempts,
+            initial_delay: Duration::from_millis(initial_delay_ms),
+            max_delay: Duration::from_millis(max_delay_ms),
+        }
+    }
+
+    /// Delay before retry number `retry` (0-based), doubling up to `max_delay`.
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay)
+    }
+}
+
+/// Run `operation` until it succeeds or the policy runs out of attempts,
+/// returning the last error. Command timeouts and missing tools are not
+/// retried; neither recovers on its own and retrying would only add delay.
+pub async fn retry<T, F, Fut>(policy: RetryPolicy, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut retry = 0;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if retry + 1 >= policy.attempts || is_permanent(&e) => return Err(e),
+            Err(_) => {
+                tokio::time::sleep(policy.delay(retry)).await;
+                retry += 1;
+            }
+        }
+    }
+}
+
+fn is_permanent(err: &anyhow::Error) -> bool {
+    crate::command::is_timeout(err)
+        || err.chain().any(|cause| {
+            cause
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+        })
+}
+
+/// Poll `check` with backoff until it yields a value or attempts run out.
+pub async fn poll_until<T, F, Fut>(policy: RetryPolicy, mut check: F) -> Option<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    for retry in 0..policy.attempts {
+        if let Some(value) = check().await {
+            return Some(value);
+        }
+        if retry + 1 < policy.attempts {
+            tokio::time::sleep(policy.delay(retry)).await;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_and_caps() {
+        let policy = RetryPolicy::new(5, 100, 500);
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(3), Duration::from_millis(500));
+        assert_eq!(policy.delay(40), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn retries_until_success_then_stops() {
+        let policy = RetryPolicy::new(4, 1, 1);
+        let mut calls = 0;
+        let result = retry(policy, || {
+            calls += 1;
+            let attempt = calls;
+            async move {
+                if attempt < 3 {
+                    Err(anyhow::anyhow!("transient"))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<()> = retry(policy, || {
+            calls += 1;
+            async { Err(anyhow::anyhow!("permanent")) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 4);
+    }
+}
@@ -0,0 +1,539 @@
+// src/rfmon.rs - Passive RF monitor: probe requests, probe responses and beacons heard on a monitor interface
+use crate::command::TimedOutput;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long the radio stays on a channel while hopping
+const HOP_INTERVAL: Duration = Duration::from_millis(300);
+/// 2.4 GHz and the 5 GHz channels without radar detection, which a monitor
+/// can listen on anywhere
+const HOP_CHANNELS: [u32; 21] = [
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 36, 40, 44, 48, 149, 153, 157, 161,
+];
+
+const ETH_P_ALL: u16 = 0x0003;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    Beacon,
+    ProbeRequest,
+    ProbeResponse,
+}
+
+/// One management frame worth keeping
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sighting {
+    pub kind: FrameKind,
+    /// The transmitter: a client for probe requests, the AP otherwise
+    pub source: String,
+    pub bssid: String,
+    /// None for a hidden beacon or a wildcard probe request
+    pub ssid: Option<String>,
+    pub signal: Option<i8>,
+    pub frequency: Option<u16>,
+}
+
+/// What the radiotap header in front of every captured frame says
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Radiotap {
+    pub length: usize,
+    pub signal: Option<i8>,
+    pub frequency: Option<u16>,
+    /// The frame still ends in its 4-byte checksum
+    pub fcs: bool,
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        buf.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        buf.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// The fields up to the antenna signal, which come first and in this order;
+/// each is aligned to its own (alignment, size)
+const RADIOTAP_FIELDS: [(usize, usize); 6] = [
+    (8, 8), // TSFT
+    (1, 1), // Flags
+    (1, 1), // Rate
+    (2, 4), // Channel: frequency, flags
+    (2, 2), // FHSS
+    (1, 1), // Antenna signal, dBm
+];
+
+pub fn parse_radiotap(packet: &[u8]) -> Option<Radiotap> {
+    if packet.first() != Some(&0) {
+        return None; // Only version 0 exists
+    }
+    let length = read_u16(packet, 2)? as usize;
+    if length > packet.len() {
+        return None;
+    }
+    let present = read_u32(packet, 4)?;
+    // Further present words follow while bit 31 is set; the fields start after them
+    let mut offset = 8;
+    let mut word = present;
+    while word & (1 << 31) != 0 {
+        word = read_u32(packet, offset)?;
+        offset += 4;
+    }
+
+    let mut radiotap = Radiotap {
+        length,
+        signal: None,
+        frequency: None,
+        fcs: false,
+    };
+    for (bit, (alignment, size)) in RADIOTAP_FIELDS.iter().enumerate() {
+        if present & (1 << bit) == 0 {
+            continue;
+        }
+        offset = offset.div_ceil(*alignment) * alignment;
+        if offset + size > length {
+            break;
+        }
+        match bit {
+            1 => radiotap.fcs = packet[offset] & 0x10 != 0,
+            3 => radiotap.frequency = read_u16(packet, offset),
+            5 => radiotap.signal = Some(packet[offset] as i8),
+            _ => {}
+        }
+        offset += size;
+    }
+    Some(radiotap)
+}
+
+fn mac(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// The SSID element; None when it is empty or zeroed out, as hidden
+/// networks and wildcard probes send it
+fn ssid_element(mut elements: &[u8]) -> Option<String> {
+    while let [id, len, rest @ ..] = elements {
+        let len = *len as usize;
+        let value = rest.get(..len)?;
+        if *id == 0 {
+            if value.iter().all(|byte| *byte == 0) {
+                return None;
+            }
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+        elements = &rest[len..];
+    }
+    None
+}
+
+/// An 802.11 frame without radiotap; None for anything but beacons and probes
+pub fn parse_frame(frame: &[u8]) -> Option<Sighting> {
+    let control = *frame.first()?;
+    if (control >> 2) & 0x3 != 0 {
+        return None; // Not a management frame
+    }
+    let kind = match control >> 4 {
+        4 => FrameKind::ProbeRequest,
+        5 => FrameKind::ProbeResponse,
+        8 => FrameKind::Beacon,
+        _ => return None,
+    };
+    let header = frame.get(..24)?;
+    // Beacons and probe responses put a timestamp, interval and capabilities first
+    let elements = match kind {
+        FrameKind::ProbeRequest => frame.get(24..)?,
+        _ => frame.get(36..)?,
+    };
+    Some(Sighting {
+        kind,
+        source: mac(&header[10..16]),
+        bssid: mac(&header[16..22]),
+        ssid: ssid_element(elements),
+        signal: None,
+        frequency: None,
+    })
+}
+
+/// A captured packet: radiotap, then the frame
+pub fn parse_packet(packet: &[u8]) -> Option<Sighting> {
+    let radiotap = parse_radiotap(packet)?;
+    let mut frame = &packet[radiotap.length..];
+    if radiotap.fcs {
+        frame = frame.get(..frame.len().checked_sub(4)?)?;
+    }
+    let mut sighting = parse_frame(frame)?;
+    sighting.signal = radiotap.signal;
+    sighting.frequency = radiotap.frequency;
+    Some(sighting)
+}
+
+/// Locally administered: phones and laptops make these up per network or
+/// per scan, so one device may show up as several
+pub fn is_randomized(mac: &str) -> bool {
+    u8::from_str_radix(mac.get(..2).unwrap_or("00"), 16).is_ok_and(|byte| byte & 0x02 != 0)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HiddenNetwork {
+    pub bssid: String,
+    /// Named by a probe response, once a client asked for it
+    pub ssid: Option<String>,
+    pub signal: Option<i8>,
+    pub frequency: Option<u16>,
+    pub last_seen: Instant,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientDevice {
+    pub mac: String,
+    /// Networks it asked for by name, in the order first heard
+    pub probed: Vec<String>,
+    pub signal: Option<i8>,
+    pub last_seen: Instant,
+}
+
+/// Everything heard since the monitor started
+#[derive(Debug, Clone, Default)]
+pub struct RfSurvey {
+    pub hidden: BTreeMap<String, HiddenNetwork>,
+    pub clients: BTreeMap<String, ClientDevice>,
+    /// Names from probe responses by BSSID, for a hidden beacon heard later
+    named: BTreeMap<String, String>,
+    pub frames: u64,
+}
+
+impl RfSurvey {
+    pub fn record(&mut self, sighting: Sighting, now: Instant) {
+        self.frames += 1;
+        match sighting.kind {
+            FrameKind::Beacon if sighting.ssid.is_none() => {
+                let network = self
+                    .hidden
+                    .entry(sighting.bssid.clone())
+                    .or_insert_with(|| HiddenNetwork {
+                        bssid: sighting.bssid.clone(),
+                        ssid: None,
+                        signal: None,
+                        frequency: None,
+                        last_seen: now,
+                    });
+                if network.ssid.is_none() {
+                    network.ssid = self.named.get(&sighting.bssid).cloned();
+                }
+                network.signal = sighting.signal.or(network.signal);
+                network.frequency = sighting.frequency.or(network.frequency);
+                network.last_seen = now;
+            }
+            FrameKind::Beacon => {}
+            FrameKind::ProbeResponse => {
+                let Some(ssid) = sighting.ssid else {
+                    return;
+                };
+                if let Some(network) = self.hidden.get_mut(&sighting.bssid) {
+                    network.ssid = Some(ssid.clone());
+                }
+                self.named.insert(sighting.bssid, ssid);
+            }
+            FrameKind::ProbeRequest => {
+                let client = self
+                    .clients
+                    .entry(sighting.source.clone())
+                    .or_insert_with(|| ClientDevice {
+                        mac: sighting.source.clone(),
+                        probed: Vec::new(),
+                        signal: None,
+                        last_seen: now,
+                    });
+                if let Some(ssid) = sighting.ssid {
+                    if !client.probed.contains(&ssid) {
+                        client.probed.push(ssid);
+                    }
+                }
+                client.signal = sighting.signal.or(client.signal);
+                client.last_seen = now;
+            }
+        }
+    }
+
+    /// Hidden networks whose name came out
+    pub fn revealed(&self) -> usize {
+        self.hidden
+            .values()
+            .filter(|network| network.ssid.is_some())
+            .count()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Capture {
+    Starting,
+    Listening,
+    /// With the error that ended it, if any
+    Stopped(Option<String>),
+}
+
+/// A monitor lantern listens on until the dialog closes
+#[derive(Debug)]
+pub struct RfSession {
+    /// The managed interface it was started from
+    pub interface: String,
+    pub monitor: String,
+    /// Lantern added the monitor, and removes it again
+    pub created: bool,
+    pub hop: bool,
+    pub stop: Arc<AtomicBool>,
+    pub started: Instant,
+    pub capture: Capture,
+    pub survey: RfSurvey,
+}
+
+impl RfSession {
+    pub fn new(interface: &str, monitor: String, created: bool, hop: bool) -> Self {
+        Self {
+            interface: interface.to_string(),
+            monitor,
+            created,
+            hop,
+            stop: Arc::new(AtomicBool::new(false)),
+            started: Instant::now(),
+            capture: Capture::Starting,
+            survey: RfSurvey::default(),
+        }
+    }
+}
+
+pub(crate) struct PacketSocket(libc::c_int);
+
+impl PacketSocket {
+    /// A packet socket bound to one interface, with a receive timeout so the
+    /// caller gets to check whether to stop. `kind` is SOCK_RAW for whole
+    /// frames, SOCK_DGRAM for what follows the link-layer header.
+    pub(crate) fn open(interface: &str, kind: libc::c_int) -> io::Result<Self> {
+        let index = nix::net::if_::if_nametoindex(interface).map_err(io::Error::from)?;
+        // SAFETY: plain socket(2) call, the fd is owned and closed by Drop
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_PACKET,
+                kind | libc::SOCK_CLOEXEC,
+                ETH_P_ALL.to_be() as libc::c_int,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = Self(fd);
+
+        // SAFETY: zeroed sockaddr_ll is valid; family, protocol and index are set below
+        let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as libc::c_ushort;
+        addr.sll_protocol = ETH_P_ALL.to_be();
+        addr.sll_ifindex = index as libc::c_int;
+        // SAFETY: addr outlives the call and its length is passed along
+        let bound = unsafe {
+            libc::bind(
+                socket.0,
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let timeout = libc::timeval {
+            tv_sec: 0,
+            tv_usec: 200_000,
+        };
+        // SAFETY: timeout outlives the call and its length is passed along
+        let set = unsafe {
+            libc::setsockopt(
+                socket.0,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        if set < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(socket)
+    }
+
+    /// None when the timeout passed without a packet
+    fn recv(&self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        // SAFETY: buf is valid for writes of buf.len() bytes
+        let received =
+            unsafe { libc::recv(self.0, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+        received_or_timeout(received)
+    }
+
+    /// A packet's full length, however little of it fits in `buf`, with its
+    /// link-layer protocol and whether this host sent it
+    pub(crate) fn recv_from(&self, buf: &mut [u8]) -> io::Result<Option<(usize, u16, bool)>> {
+        // SAFETY: zeroed sockaddr_ll is valid; recvfrom fills it in
+        let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        let mut addr_len = std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+        // SAFETY: buf is valid for writes of buf.len() bytes, addr for addr_len
+        let received = unsafe {
+            libc::recvfrom(
+                self.0,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                libc::MSG_TRUNC,
+                &mut addr as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                &mut addr_len,
+            )
+        };
+        Ok(received_or_timeout(received)?.map(|len| {
+            let outgoing = addr.sll_pkttype == libc::PACKET_OUTGOING;
+            (len, u16::from_be(addr.sll_protocol), outgoing)
+        }))
+    }
+}
+
+fn received_or_timeout(received: isize) -> io::Result<Option<usize>> {
+    if received < 0 {
+        let error = io::Error::last_os_error();
+        return match error.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted => {
+                Ok(None)
+            }
+            _ => Err(error),
+        };
+    }
+    Ok(Some(received as usize))
+}
+
+impl Drop for PacketSocket {
+    fn drop(&mut self) {
+        // SAFETY: the fd came from socket(2) and is closed exactly once
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+// The capture runs on a blocking-pool thread, which may wait on the runtime;
+// a radio that ignores the hop must not stall the capture past the timeout
+fn set_channel(monitor: &str, channel: u32) {
+    let mut command = tokio::process::Command::new(crate::command::IW);
+    command.args(["dev", monitor, "set", "channel", &channel.to_string()]);
+    let _ = tokio::runtime::Handle::current().block_on(command.timed_output());
+}
+
+/// Listen on `monitor` until `stop` is set or `on_sighting` returns false.
+/// Only receives: nothing is transmitted. With `hop` the radio walks the
+/// channels; otherwise it stays where the managed interface has it.
+pub fn capture(
+    monitor: &str,
+    hop: bool,
+    stop: &AtomicBool,
+    mut on_sighting: impl FnMut(Sighting) -> bool,
+) -> Result<()> {
+    let socket = PacketSocket::open(monitor, libc::SOCK_RAW)
+        .with_context(|| format!("Failed to listen on {} (needs root)", monitor))?;
+    let mut buf = vec![0u8; 4096];
+    let mut channels = HOP_CHANNELS.iter().cycle();
+    let mut hopped = Instant::now() - HOP_INTERVAL;
+    while !stop.load(Ordering::Relaxed) {
+        if hop && hopped.elapsed() >= HOP_INTERVAL {
+            if let Some(channel) = channels.next() {
+                set_channel(monitor, *channel);
+            }
+            hopped = Instant::now();
+        }
+        let Some(len) = socket.recv(&mut buf)? else {
+            continue;
+        };
+        // Beacons that name their network are in every scan already
+        let sighting = parse_packet(&buf[..len])
+            .filter(|sighting| sighting.kind != FrameKind::Beacon || sighting.ssid.is_none());
+        if let Some(sighting) = sighting {
+            if !on_sighting(sighting) {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Radiotap with flags (FCS present), channel 2412 MHz and -42 dBm
+    fn radiotap() -> Vec<u8> {
+        let mut header = vec![0, 0, 16, 0];
+        header.extend(0b10_1010u32.to_le_bytes()); // Flags, channel, signal
+        header.push(0x10); // Flags: FCS at the end
+        header.push(0); // Padding to align the channel
+        header.extend(2412u16.to_le_bytes());
+        header.extend(0x00a0u16.to_le_bytes());
+        header.push(-42i8 as u8);
+        header.push(0);
+        header
+    }
+
+    fn frame(subtype: u8, source: u8, ssid: &[u8]) -> Vec<u8> {
+        let mut frame = vec![subtype << 4, 0, 0, 0];
+        frame.extend([0xff; 6]);
+        frame.extend([0x02, 0, 0, 0, 0, source]);
+        frame.extend([0xa0, 0, 0, 0, 0, 1]);
+        frame.extend([0, 0]);
+        if subtype != 4 {
+            frame.extend([0; 12]);
+        }
+        frame.push(0);
+        frame.push(ssid.len() as u8);
+        frame.extend(ssid);
+        frame.extend([1, 1, 0x82]); // Supported rates
+        frame
+    }
+
+    #[test]
+    fn reveals_hidden_networks_from_probes() {
+        let mut packet = radiotap();
+        packet.extend(frame(8, 9, &[0, 0, 0, 0]));
+        packet.extend([0xde, 0xad, 0xbe, 0xef]);
+        let beacon = parse_packet(&packet).unwrap();
+        assert_eq!(beacon.kind, FrameKind::Beacon);
+        assert_eq!(beacon.bssid, "a0:00:00:00:00:01");
+        assert_eq!(
+            (beacon.ssid.as_deref(), beacon.signal, beacon.frequency),
+            (None, Some(-42), Some(2412))
+        );
+
+        let probe = parse_frame(&frame(4, 7, b"Office")).unwrap();
+        assert_eq!(probe.kind, FrameKind::ProbeRequest);
+        assert_eq!(probe.source, "02:00:00:00:00:07");
+        assert!(is_randomized(&probe.source));
+        assert!(!is_randomized("a4:c3:f0:85:12:9e"));
+        assert_eq!(parse_frame(&frame(4, 7, b"")).unwrap().ssid, None);
+        assert_eq!(parse_frame(&[0x08, 0x02, 0, 0]), None); // Data frame
+
+        let now = Instant::now();
+        let mut survey = RfSurvey::default();
+        survey.record(beacon, now);
+        survey.record(probe, now);
+        assert_eq!(survey.revealed(), 0);
+        survey.record(parse_frame(&frame(5, 1, b"Lab")).unwrap(), now);
+        assert_eq!(
+            survey.hidden["a0:00:00:00:00:01"].ssid.as_deref(),
+            Some("Lab")
+        );
+        assert_eq!(survey.clients["02:00:00:00:00:07"].probed, vec!["Office"]);
+        assert_eq!(survey.frames, 3);
+    }
+}
@@ -0,0 +1,420 @@
+// src/routing.rs - Read-only BGP/OSPF status from FRR (vtysh) or BIRD (control socket)
+use crate::command::TimedOutput;
+use anyhow::Result;
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::process::Command;
+
+const VTYSH: &str = "/usr/bin/vtysh";
+const BIRD_SOCKETS: &[&str] = &["/run/bird/bird.ctl", "/run/bird.ctl", "/var/run/bird.ctl"];
+const BIRD_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoutingDaemon {
+    Frr,
+    Bird,
+}
+
+impl RoutingDaemon {
+    pub fn name(self) -> &'static str {
+        match self {
+            RoutingDaemon::Frr => "FRR",
+            RoutingDaemon::Bird => "BIRD",
+        }
+    }
+}
+
+/// A BGP session or OSPF adjacency
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingNeighbor {
+    pub protocol: String, // "BGP v4", "OSPF", ...
+    pub name: String,     // Peer address, router ID or BIRD protocol name
+    pub state: String,
+    pub established: bool,
+    pub uptime: Option<String>,
+    pub prefixes: Option<u64>, // Received from the peer
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RoutingStatus {
+    pub daemon: Option<RoutingDaemon>,
+    pub neighbors: Vec<RoutingNeighbor>,
+    pub routes: Vec<(String, u64)>, // Route source or table, and its route count
+    pub error: Option<String>,
+}
+
+/// Ask whichever routing daemon is running; FRR wins if both are.
+pub async fn get_routing_status() -> RoutingStatus {
+    if Path::new(VTYSH).exists() {
+        match frr_status().await {
+            Ok(status) => return status,
+            Err(e) if !bird_socket_exists() => {
+                return RoutingStatus {
+                    daemon: Some(RoutingDaemon::Frr),
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                }
+            }
+            Err(_) => {}
+        }
+    }
+    if let Some(socket) = BIRD_SOCKETS.iter().find(|path| Path::new(path).exists()) {
+        return bird_status(Path::new(socket))
+            .await
+            .unwrap_or_else(|e| RoutingStatus {
+                daemon: Some(RoutingDaemon::Bird),
+                error: Some(e.to_string()),
+                ..Default::default()
+            });
+    }
+    RoutingStatus::default()
+}
+
+fn bird_socket_exists() -> bool {
+    BIRD_SOCKETS.iter().any(|path| Path::new(path).exists())
+}
+
+async fn vtysh_json(command: &str) -> Result<Value> {
+    let output = Command::new(VTYSH)
+        .args(["-c", command])
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "vtysh failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    // Daemons that aren't running answer with an empty object or nothing at all
+    Ok(serde_json::from_slice(&output.stdout).unwrap_or(Value::Null))
+}
+
+async fn frr_status() -> Result<RoutingStatus> {
+    let bgp = vtysh_json("show bgp summary json").await?;
+    let mut neighbors = parse_frr_bgp_summary(&bgp);
+    if let Ok(ospf) = vtysh_json("show ip ospf neighbor json").await {
+        neighbors.extend(parse_frr_ospf_neighbors(&ospf));
+    }
+
+    let mut routes = Vec::new();
+    for (family, command) in [
+        ("v4", "show ip route summary json"),
+        ("v6", "show ipv6 route summary json"),
+    ] {
+        if let Ok(summary) = vtysh_json(command).await {
+            routes.extend(parse_frr_route_summary(&summary, family));
+        }
+    }
+
+    Ok(RoutingStatus {
+        daemon: Some(RoutingDaemon::Frr),
+        neighbors,
+        routes,
+        error: None,
+    })
+}
+
+/// `show bgp summary json`: one object per address family, each with its peers.
+fn parse_frr_bgp_summary(summary: &Value) -> Vec<RoutingNeighbor> {
+    let Some(families) = summary.as_object() else {
+        return Vec::new();
+    };
+    let mut neighbors = Vec::new();
+    for (family, data) in families {
+        let Some(peers) = data["peers"].as_object() else {
+            continue;
+        };
+        let protocol = match family.as_str() {
+            "ipv4Unicast" => "BGP v4".to_string(),
+            "ipv6Unicast" => "BGP v6".to_string(),
+            other => format!("BGP {}", other),
+        };
+        for (peer, info) in peers {
+            let state = info["state"].as_str().unwrap_or("Unknown").to_string();
+            neighbors.push(RoutingNeighbor {
+                protocol: protocol.clone(),
+                name: match info["remoteAs"].as_u64() {
+                    Some(asn) => format!("{} (AS{})", peer, asn),
+                    None => peer.clone(),
+                },
+                established: state == "Established",
+                state,
+                uptime: info["peerUptime"].as_str().map(String::from),
+                prefixes: info["pfxRcd"].as_u64(),
+            });
+        }
+    }
+    neighbors
+}
+
+/// `show ip ospf neighbor json`; newer FRR lists each router ID's adjacencies
+/// in an array, older versions give a single object.
+fn parse_frr_ospf_neighbors(neighbors: &Value) -> Vec<RoutingNeighbor> {
+    let Some(routers) = neighbors["neighbors"].as_object() else {
+        return Vec::new();
+    };
+    routers
+        .iter()
+        .flat_map(|(router_id, entries)| {
+            let entries = match entries {
+                Value::Array(entries) => entries.clone(),
+                entry => vec![entry.clone()],
+            };
+            entries.into_iter().map(move |entry| {
+                let state = entry["nbrState"]
+                    .as_str()
+                    .or_else(|| entry["state"].as_str())
+                    .unwrap_or("Unknown")
+                    .to_string();
+                // "eth0:10.0.0.2" is the interface and the local address
+                let interface = entry["ifaceName"]
+                    .as_str()
+                    .and_then(|name| name.split(':').next())
+                    .unwrap_or_default();
+                RoutingNeighbor {
+                    protocol: "OSPF".to_string(),
+                    name: format!("{} on {}", router_id, interface),
+                    established: state.starts_with("Full"),
+                    state,
+                    uptime: entry["upTime"].as_str().map(String::from),
+                    prefixes: None,
+                }
+            })
+        })
+        .collect()
+}
+
+fn parse_frr_route_summary(summary: &Value, family: &str) -> Vec<(String, u64)> {
+    summary["routes"]
+        .as_array()
+        .map(|routes| {
+            routes
+                .iter()
+                .filter_map(|route| {
+                    let source = route["type"].as_str()?;
+                    Some((format!("{} {}", family, source), route["rib"].as_u64()?))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn bird_status(socket: &Path) -> Result<RoutingStatus> {
+    let stream = tokio::time::timeout(BIRD_TIMEOUT, UnixStream::connect(socket)).await??;
+    let mut stream = BufReader::new(stream);
+    read_bird_reply(&mut stream).await?; // Greeting
+
+    let mut neighbors = Vec::new();
+    for (code, line) in bird_command(&mut stream, "show protocols").await? {
+        if code == 1002 {
+            neighbors.extend(parse_bird_protocol(&line));
+        }
+    }
+    // An error reply when no OSPF protocol is configured
+    if let Ok(reply) = bird_command(&mut stream, "show ospf neighbors").await {
+        for (code, line) in reply {
+            if code == 1013 {
+                neighbors.extend(parse_bird_ospf_neighbor(&line));
+            }
+        }
+    }
+    let routes = bird_command(&mut stream, "show route count")
+        .await?
+        .iter()
+        .filter_map(|(_, line)| parse_bird_route_count(line))
+        .collect();
+
+    Ok(RoutingStatus {
+        daemon: Some(RoutingDaemon::Bird),
+        neighbors,
+        routes,
+        error: None,
+    })
+}
+
+async fn bird_command(
+    stream: &mut BufReader<UnixStream>,
+    command: &str,
+) -> Result<Vec<(u16, String)>> {
+    stream
+        .get_mut()
+        .write_all(format!("{}\n", command).as_bytes())
+        .await?;
+    read_bird_reply(stream).await
+}
+
+/// Read one reply: "NNNN-text" lines continue it, as do lines starting with
+/// a space (same code as before); "NNNN text" ends it. Codes 8000 and up are errors.
+async fn read_bird_reply(stream: &mut BufReader<UnixStream>) -> Result<Vec<(u16, String)>> {
+    let mut reply = Vec::new();
+    let mut code = 0;
+    loop {
+        let mut line = String::new();
+        let read = tokio::time::timeout(BIRD_TIMEOUT, stream.read_line(&mut line)).await??;
+        if read == 0 {
+            return Err(anyhow::anyhow!("BIRD closed the control socket"));
+        }
+        let line = line.trim_end_matches('\n');
+        match parse_bird_line(line) {
+            Some((line_code, last, text)) => {
+                code = line_code;
+                if code >= 8000 {
+                    return Err(anyhow::anyhow!("BIRD: {}", text));
+                }
+                reply.push((code, text.to_string()));
+                if last {
+                    return Ok(reply);
+                }
+            }
+            None => reply.push((code, line.trim_start().to_string())),
+        }
+    }
+}
+
+fn parse_bird_line(line: &str) -> Option<(u16, bool, &str)> {
+    let code = line.get(..4)?.parse().ok()?;
+    match line.as_bytes().get(4) {
+        Some(b'-') => Some((code, false, &line[5..])),
+        Some(b' ') => Some((code, true, &line[5..])),
+        None => Some((code, true, "")),
+        _ => None,
+    }
+}
+
+/// A `show protocols` row: name, protocol, table, state, since (a time, or a
+/// date and a time), then the info column.
+fn parse_bird_protocol(line: &str) -> Option<RoutingNeighbor> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let protocol = *fields.get(1)?;
+    if protocol != "BGP" {
+        // OSPF adjacencies come from `show ospf neighbors` instead
+        return None;
+    }
+    let since_end = if fields.get(5).is_some_and(|field| !field.contains(':')) {
+        5
+    } else {
+        6
+    };
+    let info = fields.get(since_end..).unwrap_or_default().join(" ");
+    let state = if info.is_empty() {
+        fields.get(3)?.to_string()
+    } else {
+        info
+    };
+    Some(RoutingNeighbor {
+        protocol: "BGP".to_string(),
+        name: fields[0].to_string(),
+        established: state.starts_with("Established"),
+        state,
+        uptime: fields.get(4).map(|since| since.to_string()),
+        prefixes: None,
+    })
+}
+
+/// A `show ospf neighbors` row: router ID, priority, state, dead time,
+/// interface, router IP.
+fn parse_bird_ospf_neighbor(line: &str) -> Option<RoutingNeighbor> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 5 || fields[1].parse::<u8>().is_err() {
+        return None; // Header or instance name
+    }
+    Some(RoutingNeighbor {
+        protocol: "OSPF".to_string(),
+        name: format!("{} on {}", fields[0], fields[4]),
+        established: fields[2].starts_with("Full"),
+        state: fields[2].to_string(),
+        uptime: None,
+        prefixes: None,
+    })
+}
+
+/// "12 of 12 routes for 12 networks in table master4"
+fn parse_bird_route_count(line: &str) -> Option<(String, u64)> {
+    let (counts, table) = line.split_once(" in table ")?;
+    let routes = counts.split_whitespace().next()?.parse().ok()?;
+    Some((table.trim().to_string(), routes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_frr_json() {
+        let bgp: Value = serde_json::from_str(
+            r#"{"ipv4Unicast":{"routerId":"10.0.0.1","peers":{
+                "10.0.0.2":{"remoteAs":65002,"state":"Established","peerUptime":"01:02:03","pfxRcd":120},
+                "10.0.0.3":{"remoteAs":65003,"state":"Active","peerUptime":"never"}}}}"#,
+        )
+        .unwrap();
+        let neighbors = parse_frr_bgp_summary(&bgp);
+        assert_eq!(neighbors.len(), 2);
+        assert_eq!(neighbors[0].name, "10.0.0.2 (AS65002)");
+        assert!(neighbors[0].established);
+        assert_eq!(neighbors[0].prefixes, Some(120));
+        assert!(!neighbors[1].established);
+
+        let ospf: Value = serde_json::from_str(
+            r#"{"neighbors":{"10.0.0.9":[{"nbrState":"Full/DR","ifaceName":"eth1:10.1.0.1"}]}}"#,
+        )
+        .unwrap();
+        let ospf = parse_frr_ospf_neighbors(&ospf);
+        assert_eq!(ospf[0].name, "10.0.0.9 on eth1");
+        assert!(ospf[0].established);
+
+        let summary: Value = serde_json::from_str(
+            r#"{"routes":[{"fib":2,"rib":2,"type":"connected"},{"fib":118,"rib":120,"type":"bgp"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parse_frr_route_summary(&summary, "v4"),
+            vec![("v4 connected".to_string(), 2), ("v4 bgp".to_string(), 120)]
+        );
+    }
+
+    #[test]
+    fn parses_bird_replies() {
+        assert_eq!(
+            parse_bird_line("1002-bgp1 BGP --- up 12:00:00.000 Established"),
+            Some((1002, false, "bgp1 BGP --- up 12:00:00.000 Established"))
+        );
+        assert_eq!(parse_bird_line("0000 "), Some((0, true, "")));
+        assert_eq!(parse_bird_line(" continued"), None);
+
+        let bgp = parse_bird_protocol(
+            "bgp1       BGP        ---        up     2024-01-01 12:00:00  Established",
+        )
+        .unwrap();
+        assert_eq!(bgp.name, "bgp1");
+        assert!(bgp.established);
+        assert!(
+            parse_bird_protocol("device1    Device     ---        up     12:00:00.000").is_none()
+        );
+        let down =
+            parse_bird_protocol("bgp2       BGP        ---        start  12:00:00.000  Connect")
+                .unwrap();
+        assert_eq!(down.state, "Connect");
+
+        assert!(parse_bird_ospf_neighbor(
+            "Router ID   \tPri\t     State     \tDTime\tInterface  Router IP"
+        )
+        .is_none());
+        let ospf = parse_bird_ospf_neighbor(
+            "10.0.0.2    \t  1\tFull/DR      \t38.108\teth0       10.0.0.2",
+        )
+        .unwrap();
+        assert_eq!(ospf.name, "10.0.0.2 on eth0");
+
+        assert_eq!(
+            parse_bird_route_count("12 of 14 routes for 12 networks in table master4"),
+            Some(("master4".to_string(), 12))
+        );
+        assert_eq!(
+            parse_bird_route_count("Total: 16 of 16 routes for 16 networks in 2 tables"),
+            None
+        );
+    }
+}
@@ -0,0 +1,117 @@
+// src/sanitize.rs - Validation and escaping for values written to configs or passed to commands
+use anyhow::Result;
+
+// IFNAMSIZ is 16 including the terminating NUL
+const MAX_INTERFACE_NAME_LEN: usize = 15;
+const MAX_SSID_LEN: usize = 32;
+
+/// Validate an interface name before it is used in a file path, a config
+/// section or a command argument.
+pub fn interface_name(name: &str) -> Result<&str> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_INTERFACE_NAME_LEN
+        && name != "."
+        && name != ".."
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'));
+
+    if valid {
+        Ok(name)
+    } else {
+        Err(anyhow::anyhow!("Invalid interface name: {:?}", name))
+    }
+}
+
+/// Validate an SSID length. Any byte is allowed in an SSID, so escaping is
+/// left to the writer of each config format.
+pub fn ssid(ssid: &str) -> Result<&str> {
+    if ssid.is_empty() || ssid.len() > MAX_SSID_LEN {
+        return Err(anyhow::anyhow!(
+            "SSID must be 1-{} bytes, got {}",
+            MAX_SSID_LEN,
+            ssid.len()
+        ));
+    }
+    Ok(ssid)
+}
+
+/// Reject values that could end a line or otherwise break out of a
+/// `Key=value` config entry (systemd-networkd, dnsmasq, hostapd).
+pub fn config_value(value: &str) -> Result<&str> {
+    if value.chars().any(|c| c.is_control()) {
+        return Err(anyhow::anyhow!(
+            "Value contains control characters: {:?}",
+            value
+        ));
+    }
+    Ok(value)
+}
+
+/// Like `config_value`, but also refuses values a command could mistake for
+/// an option.
+pub fn argument(value: &str) -> Result<&str> {
+    if value.starts_with('-') {
+        return Err(anyhow::anyhow!(
+            "Value must not start with '-': {:?}",
+            value
+        ));
+    }
+    config_value(value)
+}
+
+/// Encode a string for wpa_supplicant/hostapd: quoted when that is
+/// unambiguous, otherwise as hex, which both parse for ssid and key fields.
+pub fn wpa_string(value: &str) -> String {
+    let quotable = value.chars().all(|c| c.is_ascii_graphic() || c == ' ') && !value.contains('"');
+
+    if quotable {
+        format!("\"{}\"", value)
+    } else {
+        value.bytes().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// The SSID line for hostapd.conf; `ssid2` is needed for the hex form.
+pub fn hostapd_ssid_line(ssid: &str) -> String {
+    format!("ssid2={}", wpa_string(ssid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interface_names_cannot_escape_paths() {
+        assert!(interface_name("wlan0").is_ok());
+        assert!(interface_name("wg-home.1").is_ok());
+        assert!(interface_name("../../etc/passwd").is_err());
+        assert!(interface_name("..").is_err());
+        assert!(interface_name("eth0\n[Network]").is_err());
+        assert!(interface_name("-all").is_err());
+        assert!(interface_name("averyveryverylongname").is_err());
+    }
+
+    #[test]
+    fn hostile_ssids_are_hex_encoded() {
+        assert_eq!(wpa_string("Home Net"), "\"Home Net\"");
+
+        let hostile = "x\"\n}\nnetwork={\n ssid=\"evil";
+        let encoded = wpa_string(hostile);
+        assert!(!encoded.contains('"') && !encoded.contains('\n'));
+        assert!(encoded.chars().all(|c| c.is_ascii_hexdigit()));
+
+        assert_eq!(wpa_string("Café"), "436166c3a9");
+        assert_eq!(hostapd_ssid_line("a\nb"), "ssid2=610a62");
+    }
+
+    #[test]
+    fn config_values_reject_line_breaks() {
+        assert!(config_value("192.168.1.10/24").is_ok());
+        assert!(config_value("1.1.1.1\nDNS=6.6.6.6").is_err());
+        assert!(config_value("key\r").is_err());
+        assert!(argument("--help").is_err());
+        assert!(ssid(&"a".repeat(33)).is_err());
+    }
+}
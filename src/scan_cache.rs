@@ -0,0 +1,141 @@
+// src/scan_cache.rs - Last WiFi scan per interface, kept across restarts
+use crate::network::WifiNetwork;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Older results are more likely to mislead than help
+const MAX_AGE: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedScan {
+    pub scanned_at: SystemTime,
+    pub networks: Vec<WifiNetwork>,
+}
+
+impl CachedScan {
+    pub fn age(&self) -> Duration {
+        self.age_at(SystemTime::now())
+    }
+
+    fn age_at(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.scanned_at).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanCache {
+    interfaces: HashMap<String, CachedScan>,
+}
+
+impl ScanCache {
+    /// Load the cache; a missing or corrupt file just means an empty cache.
+    pub fn load() -> Self {
+        Self::cache_path()
+            .and_then(|path| Ok(std::fs::read_to_string(path)?))
+            .and_then(|content| Ok(serde_json::from_str(&content)?))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::cache_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        crate::secure_file::write_private(path, serde_json::to_string(self)?)
+    }
+
+    /// Cached networks for an interface if they are recent enough to show.
+    pub fn get(&self, interface: &str) -> Option<&CachedScan> {
+        self.get_at(interface, SystemTime::now())
+    }
+
+    fn get_at(&self, interface: &str, now: SystemTime) -> Option<&CachedScan> {
+        self.interfaces
+            .get(interface)
+            .filter(|scan| scan.age_at(now) <= MAX_AGE)
+    }
+
+    pub fn store(&mut self, interface: &str, networks: &[WifiNetwork]) {
+        self.store_at(interface, networks, SystemTime::now());
+    }
+
+    fn store_at(&mut self, interface: &str, networks: &[WifiNetwork], now: SystemTime) {
+        self.interfaces.insert(
+            interface.to_string(),
+            CachedScan {
+                scanned_at: now,
+                networks: networks.to_vec(),
+            },
+        );
+    }
+
+    fn cache_path() -> Result<PathBuf> {
+        if let Some(dir) = crate::config::sandbox_dir() {
+            return Ok(dir.join("wifi_scan.json"));
+        }
+        let cache_dir =
+            dirs::cache_dir().ok_or_else(|| anyhow::anyhow!("Could not find cache directory"))?;
+        Ok(cache_dir.join("lantern").join("wifi_scan.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::WifiSecurity;
+    use crate::wifi_ies::SecurityDetails;
+
+    fn network(ssid: &str) -> WifiNetwork {
+        WifiNetwork {
+            ssid: ssid.to_string(),
+            bssid: "02:00:00:00:00:01".to_string(),
+            signal_strength: -50,
+            frequency: 2412,
+            channel: 1,
+            security: WifiSecurity::WPA2,
+            encryption: Vec::new(),
+            security_details: SecurityDetails::default(),
+            connected: false,
+            in_history: false,
+            access_points: Vec::new(),
+        }
+    }
+
+    fn ssids(scan: Option<&CachedScan>) -> Vec<String> {
+        scan.map(|scan| scan.networks.iter().map(|n| n.ssid.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn keeps_recent_scans_per_interface() {
+        let scanned = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut cache = ScanCache::default();
+        cache.store_at("wlan0", &[network("home")], scanned);
+        cache.store_at("wlan1", &[network("office"), network("guest")], scanned);
+
+        let later = scanned + Duration::from_secs(60);
+        assert_eq!(ssids(cache.get_at("wlan0", later)), vec!["home"]);
+        assert_eq!(ssids(cache.get_at("wlan1", later)), vec!["office", "guest"]);
+        assert_eq!(
+            cache.get_at("wlan0", later).map(|scan| scan.age_at(later)),
+            Some(Duration::from_secs(60))
+        );
+        assert!(cache.get_at("wlan2", later).is_none());
+
+        // A fresh scan replaces only its own interface's entry
+        cache.store_at("wlan0", &[network("cafe")], later);
+        assert_eq!(ssids(cache.get_at("wlan0", later)), vec!["cafe"]);
+        assert_eq!(ssids(cache.get_at("wlan1", later)), vec!["office", "guest"]);
+
+        // Past MAX_AGE the old scan is hidden while the newer one still shows
+        let expired = scanned + MAX_AGE + Duration::from_secs(1);
+        assert!(cache.get_at("wlan1", expired).is_none());
+        assert_eq!(ssids(cache.get_at("wlan0", expired)), vec!["cafe"]);
+        assert!(cache
+            .get_at("wlan0", later + MAX_AGE + Duration::from_secs(1))
+            .is_none());
+    }
+}
@@ -0,0 +1,145 @@
+// src/screenshot.rs - The screen as drawn, saved as plain or ANSI-colored text
+use anyhow::Result;
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier, Style};
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    /// With the colors and bold, for `less -R` or `cat` in a terminal
+    Ansi,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Plain => "txt",
+            Format::Ansi => "ans",
+        }
+    }
+}
+
+/// SGR parameters for a color; `base` is 30 for foreground, 40 for background
+fn color(color: Color, base: u8) -> String {
+    let basic = |offset: u8| (base + offset).to_string();
+    let bright = |offset: u8| (base + 60 + offset).to_string();
+    match color {
+        Color::Reset => basic(9),
+        Color::Black => basic(0),
+        Color::Red => basic(1),
+        Color::Green => basic(2),
+        Color::Yellow => basic(3),
+        Color::Blue => basic(4),
+        Color::Magenta => basic(5),
+        Color::Cyan => basic(6),
+        Color::Gray => basic(7),
+        Color::DarkGray => bright(0),
+        Color::LightRed => bright(1),
+        Color::LightGreen => bright(2),
+        Color::LightYellow => bright(3),
+        Color::LightBlue => bright(4),
+        Color::LightMagenta => bright(5),
+        Color::LightCyan => bright(6),
+        Color::White => bright(7),
+        Color::Indexed(index) => format!("{};5;{}", base + 8, index),
+        Color::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+    }
+}
+
+/// The escape that switches to `style` from anything
+fn sgr(style: Style) -> String {
+    let mut parameters = vec!["0".to_string()];
+    let modifiers = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::CROSSED_OUT, "9"),
+    ];
+    for (modifier, parameter) in modifiers {
+        if style.add_modifier.contains(modifier) {
+            parameters.push(parameter.to_string());
+        }
+    }
+    if let Some(fg) = style.fg.filter(|&fg| fg != Color::Reset) {
+        parameters.push(color(fg, 30));
+    }
+    if let Some(bg) = style.bg.filter(|&bg| bg != Color::Reset) {
+        parameters.push(color(bg, 40));
+    }
+    format!("\x1b[{}m", parameters.join(";"))
+}
+
+/// Every row of `buffer`, one line each. Cells hidden under a wide character
+/// are left out; plain lines lose their trailing blanks.
+pub fn render(buffer: &Buffer, format: Format) -> String {
+    let area = buffer.area;
+    let mut text = String::new();
+    for y in area.top()..area.bottom() {
+        let mut line = String::new();
+        let mut current: Option<Style> = None;
+        let mut hidden = 0;
+        for x in area.left()..area.right() {
+            if hidden > 0 {
+                hidden -= 1;
+                continue;
+            }
+            let cell = &buffer[(x, y)];
+            let symbol = cell.symbol();
+            hidden = symbol.width().saturating_sub(1);
+            if format == Format::Ansi && current != Some(cell.style()) {
+                line.push_str(&sgr(cell.style()));
+                current = Some(cell.style());
+            }
+            line.push_str(symbol);
+        }
+        match format {
+            Format::Plain => text.push_str(line.trim_end()),
+            Format::Ansi => {
+                text.push_str(&line);
+                text.push_str("\x1b[0m");
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Write `buffer` to `lantern-screen-<date>-<time>.txt` (or `.ans`) in the
+/// working directory; the file name
+pub fn save(buffer: &Buffer, format: Format) -> Result<String> {
+    let path = format!(
+        "lantern-screen-{}.{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        format.extension()
+    );
+    std::fs::write(&path, render(buffer, format))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+
+    #[test]
+    fn renders_rows_with_and_without_color() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 8, 2));
+        buffer.set_string(0, 0, "eth0", Style::default().fg(Color::Green));
+        buffer.set_string(5, 0, "up", Style::default());
+        buffer.set_string(0, 1, "📶 ok", Style::default());
+
+        assert_eq!(render(&buffer, Format::Plain), "eth0 up\n📶 ok\n");
+        let ansi = render(&buffer, Format::Ansi);
+        assert!(ansi.starts_with("\x1b[0;32meth0\x1b[0m up \x1b[0m\n"));
+        assert_eq!(
+            sgr(Style::default()
+                .fg(Color::Rgb(1, 2, 3))
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD)),
+            "\x1b[0;1;38;2;1;2;3;44m"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+// src/secure_file.rs - Atomic config writes with explicit permissions
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{fchown, OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+
+/// Mode for files holding secrets (PSKs, private keys, saved passwords)
+pub const PRIVATE_MODE: u32 = 0o600;
+/// Mode for secret files that a service group must still read
+pub const GROUP_READ_MODE: u32 = 0o640;
+/// Mode for ordinary config files without secrets
+pub const PUBLIC_MODE: u32 = 0o644;
+
+/// Write a file only its owner can read. When running as root the file is
+/// owned by root, otherwise by the invoking user (e.g. ~/.config/lantern).
+pub fn write_private(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    write_atomic(path.as_ref(), contents.as_ref(), PRIVATE_MODE, None)
+}
+
+/// Write a secret file as root:`group` 0640, for configs read by a daemon
+/// running under its own group (systemd-networkd reads .netdev files as
+/// systemd-network). Falls back to 0600 if the group does not exist.
+pub fn write_private_for_group(
+    path: impl AsRef<Path>,
+    contents: impl AsRef<[u8]>,
+    group: &str,
+) -> Result<()> {
+    let gid = nix::unistd::Group::from_name(group)
+        .ok()
+        .flatten()
+        .map(|g| g.gid.as_raw());
+
+    match gid {
+        Some(gid) if nix::unistd::Uid::effective().is_root() => {
+            write_atomic(path.as_ref(), contents.as_ref(), GROUP_READ_MODE, Some(gid))
+        }
+        _ => write_private(path, contents),
+    }
+}
+
+/// Write a world-readable config file, still atomically.
+pub fn write_public(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    write_atomic(path.as_ref(), contents.as_ref(), PUBLIC_MODE, None)
+}
+
+// Write to a temp file in the same directory, fsync, then rename over the
+// target so readers never see a half-written config.
+fn write_atomic(path: &Path, contents: &[u8], mode: u32, gid: Option<u32>) -> Result<()> {
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid file path: {}", path.display()))?;
+    let tmp_path = dir.join(format!(
+        ".{}.tmp-{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    // A crashed earlier run with the same pid could have left this behind
+    let _ = fs::remove_file(&tmp_path);
+
+    let result = (|| -> Result<()> {
+        // The mode applies at creation, so the secret is never readable by others
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(mode)
+            .open(&tmp_path)
+            .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+
+        // Creation mode is masked by umask; set it explicitly
+        file.set_permissions(fs::Permissions::from_mode(mode))?;
+
+        if nix::unistd::Uid::effective().is_root() {
+            fchown(&file, Some(0), Some(gid.unwrap_or(0)))
+                .with_context(|| format!("Failed to set ownership of {}", tmp_path.display()))?;
+        }
+
+        file.write_all(contents)?;
+        file.sync_all()?;
+
+        fs::rename(&tmp_path, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+        return result;
+    }
+
+    // Persist the rename itself
+    if let Ok(dir_handle) = File::open(dir) {
+        let _ = dir_handle.sync_all();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_write_replaces_file_with_strict_mode() {
+        let dir = std::env::temp_dir().join(format!("lantern-secure-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("secret.conf");
+
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private(&path, "psk=abc\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "psk=abc\n");
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, PRIVATE_MODE);
+        assert_eq!(
+            fs::read_dir(&dir).unwrap().count(),
+            1,
+            "temp file left behind"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,320 @@
+// src/snmp.rs - Interfaces, WireGuard tunnels and hotspot clients for SNMP pollers, through an AgentX master
+use crate::agentx::{self, Mib, Oid, Value};
+use crate::hotspot::Station;
+use crate::network::{Interface, NetworkManager, WireGuardStatus};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+
+/// `[snmp]`: answer SNMP polls for lantern's data through snmpd
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnmpSettings {
+    pub enabled: bool,
+    /// snmpd's AgentX socket (`agentXSocket` in snmpd.conf)
+    pub socket: String,
+    /// Where lantern's objects live; net-snmp's playpen unless set
+    pub oid: String,
+    pub refresh_secs: u64,
+}
+
+impl Default for SnmpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            socket: "/var/agentx/master".to_string(),
+            oid: "1.3.6.1.4.1.8072.9999.9999".to_string(),
+            refresh_secs: 10,
+        }
+    }
+}
+
+impl SnmpSettings {
+    pub fn refresh(&self) -> Duration {
+        Duration::from_secs(self.refresh_secs.max(1))
+    }
+}
+
+/// What the objects are built from
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub interfaces: Vec<Interface>,
+    pub tunnels: Vec<WireGuardStatus>,
+    pub hotspot: Option<(String, String, Vec<Station>)>, // Interface, SSID, clients
+}
+
+// TruthValue from SNMPv2-TC
+fn truth(value: bool) -> Value {
+    Value::Integer(if value { 1 } else { 2 })
+}
+
+fn text(value: &str) -> Value {
+    Value::OctetString(value.to_string())
+}
+
+/// One column per (sub-identifier, value) of each row; rows are numbered
+/// from 1 and a None leaves the cell out
+fn table(mib: &mut Mib, entry: Oid, rows: Vec<Vec<(u32, Option<Value>)>>) {
+    for (index, row) in rows.into_iter().enumerate() {
+        for (column, value) in row {
+            if let Some(value) = value {
+                let mut oid = entry.clone();
+                oid.extend([column, index as u32 + 1]);
+                mib.push((oid, value));
+            }
+        }
+    }
+}
+
+/// The objects under `base`:
+/// - `.1.1.<column>.<n>`: interfaces (index, name, state, carrier, IPv4
+///   addresses, gateway, bytes in and out, SSID, signal in dBm)
+/// - `.2.1.<column>.<n>`: WireGuard tunnels (index, name, connected, peers,
+///   seconds since the last handshake, bytes in and out)
+/// - `.3.1.0` to `.3.4.0`: hotspot running, its interface, SSID and client count
+/// - `.3.5.1.<column>.<n>`: hotspot clients (index, MAC, IP, hostname,
+///   signal in dBm, bytes in and out)
+pub fn mib(base: &[u32], snapshot: &Snapshot, now: SystemTime) -> Mib {
+    let oid = |suffix: &[u32]| -> Oid { base.iter().chain(suffix).copied().collect() };
+    let mut mib = Mib::new();
+
+    let rows = snapshot
+        .interfaces
+        .iter()
+        .enumerate()
+        .map(|(index, iface)| {
+            let wifi = iface.wifi_info.as_ref();
+            let network = wifi.and_then(|wifi| wifi.current_network.as_ref());
+            vec![
+                (1, Some(Value::Integer(index as i32 + 1))),
+                (2, Some(text(&iface.name))),
+                (3, Some(text(&iface.state))),
+                (4, iface.carrier.map(truth)),
+                (5, Some(text(&iface.ipv4_addresses.join(",")))),
+                (6, iface.gateway.as_deref().map(text)),
+                (7, Some(Value::Counter64(iface.stats.rx_bytes))),
+                (8, Some(Value::Counter64(iface.stats.tx_bytes))),
+                (9, network.map(|network| text(&network.ssid))),
+                (
+                    10,
+                    wifi.and_then(|wifi| wifi.signal_strength)
+                        .or(network.map(|network| network.signal_strength))
+                        .map(Value::Integer),
+                ),
+            ]
+        })
+        .collect();
+    table(&mut mib, oid(&[1, 1]), rows);
+
+    let rows = snapshot
+        .tunnels
+        .iter()
+        .enumerate()
+        .map(|(index, tunnel)| {
+            let handshake = tunnel
+                .last_handshake
+                .and_then(|at| now.duration_since(at).ok())
+                .map(|age| Value::Gauge32(age.as_secs().min(u32::MAX as u64) as u32));
+            vec![
+                (1, Some(Value::Integer(index as i32 + 1))),
+                (2, Some(text(&tunnel.interface))),
+                (3, Some(truth(tunnel.connected))),
+                (4, Some(Value::Gauge32(tunnel.peers.len() as u32))),
+                (5, handshake),
+                (
+                    6,
+                    Some(Value::Counter64(
+                        tunnel.peers.iter().map(|p| p.transfer_rx).sum(),
+                    )),
+                ),
+                (
+                    7,
+                    Some(Value::Counter64(
+                        tunnel.peers.iter().map(|p| p.transfer_tx).sum(),
+                    )),
+                ),
+            ]
+        })
+        .collect();
+    table(&mut mib, oid(&[2, 1]), rows);
+
+    let (interface, ssid, stations) = match &snapshot.hotspot {
+        Some((interface, ssid, stations)) => (interface.as_str(), ssid.as_str(), &stations[..]),
+        None => ("", "", &[][..]),
+    };
+    mib.push((oid(&[3, 1, 0]), truth(snapshot.hotspot.is_some())));
+    mib.push((oid(&[3, 2, 0]), text(interface)));
+    mib.push((oid(&[3, 3, 0]), text(ssid)));
+    mib.push((oid(&[3, 4, 0]), Value::Gauge32(stations.len() as u32)));
+    let rows = stations
+        .iter()
+        .enumerate()
+        .map(|(index, station)| {
+            vec![
+                (1, Some(Value::Integer(index as i32 + 1))),
+                (2, Some(text(&station.mac))),
+                (3, station.ip.as_deref().map(text)),
+                (4, station.hostname.as_deref().map(text)),
+                (5, station.signal.map(Value::Integer)),
+                (6, Some(Value::Counter64(station.rx_bytes))),
+                (7, Some(Value::Counter64(station.tx_bytes))),
+            ]
+        })
+        .collect();
+    table(&mut mib, oid(&[3, 5, 1]), rows);
+
+    mib.sort_by(|(a, _), (b, _)| a.cmp(b));
+    mib
+}
+
+/// The subagent's session with snmpd and the objects it answers from
+pub struct Agent {
+    base: Oid,
+    mib: Arc<Mutex<Mib>>,
+    task: JoinHandle<()>,
+}
+
+impl Agent {
+    pub fn start(settings: &SnmpSettings) -> Result<Self> {
+        let base = agentx::parse_oid(&settings.oid)?;
+        let mib = Arc::new(Mutex::new(Mib::new()));
+        let task = tokio::spawn(agentx::run(
+            settings.socket.clone(),
+            base.clone(),
+            Arc::clone(&mib),
+        ));
+        Ok(Self { base, mib, task })
+    }
+
+    /// Rebuild the objects from `interfaces` and what the job looks up.
+    /// `hotspot` is the interface and SSID of lantern's own hotspot, if any.
+    pub fn refresh_job(
+        &self,
+        network_manager: NetworkManager,
+        interfaces: Vec<Interface>,
+        hotspot: Option<(String, String)>,
+    ) -> SnmpJob {
+        SnmpJob {
+            network_manager,
+            interfaces,
+            hotspot,
+            base: self.base.clone(),
+            mib: Arc::clone(&self.mib),
+        }
+    }
+}
+
+impl Drop for Agent {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+pub struct SnmpJob {
+    network_manager: NetworkManager,
+    interfaces: Vec<Interface>,
+    hotspot: Option<(String, String)>,
+    base: Oid,
+    mib: Arc<Mutex<Mib>>,
+}
+
+impl SnmpJob {
+    pub async fn run(self) {
+        let mut tunnels = Vec::new();
+        for name in self
+            .network_manager
+            .list_wireguard_interfaces()
+            .await
+            .unwrap_or_default()
+        {
+            if let Ok(Some(status)) = self.network_manager.get_wireguard_status(&name).await {
+                tunnels.push(status);
+            }
+        }
+
+        // A hotspot lantern didn't start here, e.g. from the TUI
+        let hotspot = match self.hotspot {
+            Some(hotspot) => Some(hotspot),
+            None => self
+                .network_manager
+                .running_access_points()
+                .await
+                .into_iter()
+                .next(),
+        };
+        let hotspot = match hotspot {
+            Some((interface, ssid)) => {
+                let stations = self
+                    .network_manager
+                    .get_hotspot_stations(&interface)
+                    .await
+                    .unwrap_or_default();
+                Some((interface, ssid, stations))
+            }
+            None => None,
+        };
+
+        let snapshot = Snapshot {
+            interfaces: self.interfaces,
+            tunnels,
+            hotspot,
+        };
+        let mib = mib(&self.base, &snapshot, SystemTime::now());
+        *self.mib.lock().unwrap_or_else(|e| e.into_inner()) = mib;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_tables_column_by_column() {
+        let eth0: Interface = serde_json::from_value(serde_json::json!({
+            "name": "eth0", "mac_address": "aa:bb:cc:dd:ee:01", "state": "UP", "mtu": 1500,
+            "ipv4_addresses": ["10.0.0.2/24"], "ipv6_addresses": [], "dns_servers": [],
+            "gateway": "10.0.0.1",
+            "stats": {"rx_bytes": 10, "tx_bytes": 20, "rx_packets": 0, "tx_packets": 0,
+                      "rx_errors": 0, "tx_errors": 0},
+        }))
+        .unwrap();
+        let mut wlan0 = eth0.clone();
+        wlan0.name = "wlan0".to_string();
+        let snapshot = Snapshot {
+            interfaces: vec![eth0, wlan0],
+            tunnels: Vec::new(),
+            hotspot: Some((
+                "wlan1".to_string(),
+                "Lantern".to_string(),
+                vec![Station {
+                    mac: "11:22:33:44:55:66".to_string(),
+                    ..Station::default()
+                }],
+            )),
+        };
+
+        let mib = mib(&[1, 3, 6, 1, 4, 1, 9], &snapshot, SystemTime::now());
+        let names: Vec<&Oid> = mib.iter().map(|(oid, _)| oid).collect();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+        let value = |suffix: &[u32]| {
+            let oid: Oid = [1, 3, 6, 1, 4, 1, 9]
+                .iter()
+                .chain(suffix)
+                .copied()
+                .collect();
+            mib.iter()
+                .find(|(name, _)| *name == oid)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(value(&[1, 1, 2, 2]), Some(text("wlan0")));
+        assert_eq!(value(&[1, 1, 7, 1]), Some(Value::Counter64(10)));
+        // No carrier reported, no SSID: the cells are left out
+        assert_eq!(value(&[1, 1, 4, 1]), None);
+        assert_eq!(value(&[1, 1, 9, 1]), None);
+        assert_eq!(value(&[3, 1, 0]), Some(truth(true)));
+        assert_eq!(value(&[3, 4, 0]), Some(Value::Gauge32(1)));
+        assert_eq!(value(&[3, 5, 1, 2, 1]), Some(text("11:22:33:44:55:66")));
+    }
+}
@@ -0,0 +1,338 @@
+// src/speedtest.rs - Download and upload speed through curl, on demand or on the daemon's schedule, kept as a history
+use crate::command::TimedOutput;
+use crate::secure_file;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Longest a single transfer may take; a test on a very slow link ends early
+const TRANSFER_TIMEOUT_SECS: u64 = 60;
+
+// Everything curl reports about a transfer that the result needs
+const WRITE_OUT: &str =
+    "%{http_code} %{speed_download} %{speed_upload} %{time_namelookup} %{time_connect}";
+
+/// `[speedtest]`: what to test against, and how often the daemon does it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeedTestSettings {
+    /// Minutes between tests run by `lantern daemon`; 0 runs none
+    pub interval_mins: u64,
+    pub download_url: String,
+    /// Takes a POST of `upload_bytes` and throws it away
+    pub upload_url: String,
+    pub upload_bytes: u64,
+    /// Results older than this are dropped from the history
+    pub keep_days: u64,
+}
+
+impl Default for SpeedTestSettings {
+    fn default() -> Self {
+        Self {
+            interval_mins: 0,
+            download_url: "https://speed.cloudflare.com/__down?bytes=25000000".to_string(),
+            upload_url: "https://speed.cloudflare.com/__up".to_string(),
+            upload_bytes: 10_000_000,
+            keep_days: 90,
+        }
+    }
+}
+
+impl SpeedTestSettings {
+    /// None when the daemon shouldn't test on its own
+    pub fn interval(&self) -> Option<Duration> {
+        (self.interval_mins > 0).then(|| Duration::from_secs(self.interval_mins * 60))
+    }
+}
+
+/// One test; a failed transfer leaves its speed out and says why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeedResult {
+    pub timestamp: String, // RFC 3339
+    pub download_mbps: Option<f64>,
+    pub upload_mbps: Option<f64>,
+    /// TCP handshake with the download server
+    pub latency_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub const CSV_HEADER: &str = "timestamp,download_mbps,upload_mbps,latency_ms,error";
+
+impl SpeedResult {
+    pub fn time(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|time| time.with_timezone(&Local))
+    }
+
+    pub fn to_csv_row(&self) -> String {
+        let optional = |value: Option<f64>| value.map(|v| format!("{:.1}", v)).unwrap_or_default();
+        let error = self
+            .error
+            .as_deref()
+            .map(|error| format!("\"{}\"", error.replace('"', "\"\"")))
+            .unwrap_or_default();
+        [
+            self.timestamp.clone(),
+            optional(self.download_mbps),
+            optional(self.upload_mbps),
+            optional(self.latency_ms),
+            error,
+        ]
+        .join(",")
+    }
+
+    /// "↓ 412.3 Mbit/s ↑ 38.1 Mbit/s, 14 ms"
+    pub fn summary(&self) -> String {
+        self.summary_in(|mbps| format!("{:.1} Mbit/s", mbps))
+    }
+
+    /// The summary with speeds written by `speed`, e.g. in MiB/s
+    pub fn summary_in(&self, speed: impl Fn(f64) -> String) -> String {
+        let speed = |value: Option<f64>| value.map(&speed).unwrap_or_else(|| "failed".to_string());
+        let mut summary = format!(
+            "↓ {} ↑ {}",
+            speed(self.download_mbps),
+            speed(self.upload_mbps)
+        );
+        if let Some(latency) = self.latency_ms {
+            summary.push_str(&format!(", {:.0} ms", latency));
+        }
+        summary
+    }
+}
+
+/// What curl's WRITE_OUT says about one transfer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transfer {
+    pub download_bytes_per_sec: f64,
+    pub upload_bytes_per_sec: f64,
+    pub handshake_secs: f64,
+}
+
+/// None unless the server answered with a 2xx
+pub fn parse_transfer(output: &str) -> Option<Transfer> {
+    let fields: Vec<&str> = output.split_whitespace().collect();
+    let [code, download, upload, lookup, connect] = fields[..] else {
+        return None;
+    };
+    if !code.starts_with('2') {
+        return None;
+    }
+    let number = |field: &str| field.parse::<f64>().ok();
+    Some(Transfer {
+        download_bytes_per_sec: number(download)?,
+        upload_bytes_per_sec: number(upload)?,
+        handshake_secs: (number(connect)? - number(lookup)?).max(0.0),
+    })
+}
+
+fn mbps(bytes_per_sec: f64) -> f64 {
+    bytes_per_sec * 8.0 / 1_000_000.0
+}
+
+async fn curl(args: &[&str]) -> Result<Transfer> {
+    let max_time = TRANSFER_TIMEOUT_SECS.to_string();
+    let output = Command::new("/usr/bin/curl")
+        .args([
+            "-s",
+            "-o",
+            "/dev/null",
+            "--max-time",
+            &max_time,
+            "-w",
+            WRITE_OUT,
+        ])
+        .args(args)
+        .timed_output_with(Duration::from_secs(TRANSFER_TIMEOUT_SECS + 5))
+        .await
+        .context("Failed to run curl")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_transfer(&stdout).ok_or_else(|| match stdout.split_whitespace().next() {
+        Some("000") | None => anyhow!("No response from the test server"),
+        Some(code) => anyhow!("The test server answered HTTP {}", code),
+    })
+}
+
+/// `bytes` of zeros to upload. Never through a link someone else left in
+/// the shared temporary directory.
+fn write_payload(path: &Path, bytes: u64) -> std::io::Result<()> {
+    let _ = fs::remove_file(path); // Left behind by a run that was killed
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(&vec![0u8; bytes as usize])
+}
+
+/// Download, then upload; each on its own so one failing still reports the other
+pub async fn run(settings: &SpeedTestSettings) -> SpeedResult {
+    let timestamp = Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+    let mut errors = Vec::new();
+
+    let download = curl(&[&settings.download_url]).await;
+    let (download_mbps, latency_ms) = match download {
+        Ok(transfer) => (
+            Some(mbps(transfer.download_bytes_per_sec)),
+            Some(transfer.handshake_secs * 1000.0),
+        ),
+        Err(e) => {
+            errors.push(format!("download: {}", e));
+            (None, None)
+        }
+    };
+
+    // curl streams a file with -T, where a body given on the command line would sit in memory
+    let payload = std::env::temp_dir().join(format!("lantern-upload-{}", std::process::id()));
+    let upload = match write_payload(&payload, settings.upload_bytes) {
+        Ok(()) => {
+            let path = payload.to_string_lossy();
+            let result = curl(&["-X", "POST", "-T", &path, &settings.upload_url]).await;
+            let _ = fs::remove_file(&payload);
+            result
+        }
+        Err(e) => Err(anyhow!("Failed to write {}: {}", payload.display(), e)),
+    };
+    let upload_mbps = match upload {
+        Ok(transfer) => Some(mbps(transfer.upload_bytes_per_sec)),
+        Err(e) => {
+            errors.push(format!("upload: {}", e));
+            None
+        }
+    };
+
+    SpeedResult {
+        timestamp,
+        download_mbps,
+        upload_mbps,
+        latency_ms,
+        error: (!errors.is_empty()).then(|| errors.join("; ")),
+    }
+}
+
+/// A JSON result per line, oldest first
+pub fn history_path() -> Result<PathBuf> {
+    if let Some(dir) = crate::config::sandbox_dir() {
+        return Ok(dir.join("speedtest.jsonl"));
+    }
+    let data_dir = dirs::data_dir().ok_or_else(|| anyhow!("Could not find the data directory"))?;
+    Ok(data_dir.join("lantern").join("speedtest.jsonl"))
+}
+
+/// Oldest first; lines that don't parse are skipped
+pub fn load_history() -> Vec<SpeedResult> {
+    let Ok(path) = history_path() else {
+        return Vec::new();
+    };
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Drop what is older than `keep_days` before `now`
+pub fn prune(history: &mut Vec<SpeedResult>, keep_days: u64, now: DateTime<Local>) {
+    let cutoff = now - chrono::Duration::days(keep_days as i64);
+    history.retain(|result| result.time().is_none_or(|time| time >= cutoff));
+}
+
+/// Add `result` to the history file, pruning it as it goes
+pub fn record(result: &SpeedResult, keep_days: u64) -> Result<()> {
+    let path = history_path()?;
+    let mut history = load_history();
+    history.push(result.clone());
+    prune(&mut history, keep_days, Local::now());
+    let mut content = String::new();
+    for result in &history {
+        content.push_str(&serde_json::to_string(result)?);
+        content.push('\n');
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    secure_file::write_public(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn to_csv(history: &[SpeedResult]) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for result in history {
+        csv.push_str(&result.to_csv_row());
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Average download speed by local hour of day, where slow evenings show up
+pub fn hourly_download(history: &[SpeedResult]) -> [Option<f64>; 24] {
+    let mut sums = [(0.0, 0u32); 24];
+    for result in history {
+        let (Some(time), Some(download)) = (result.time(), result.download_mbps) else {
+            continue;
+        };
+        let (sum, count) = &mut sums[time.hour() as usize];
+        *sum += download;
+        *count += 1;
+    }
+    sums.map(|(sum, count)| (count > 0).then(|| sum / count as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn reads_curl_and_averages_by_hour() {
+        let transfer = parse_transfer("200 52428800.000 0.000 0.004 0.018").unwrap();
+        assert_eq!(mbps(transfer.download_bytes_per_sec), 419.4304);
+        assert!((transfer.handshake_secs - 0.014).abs() < 1e-9);
+        assert_eq!(parse_transfer("000 0 0 0 0"), None);
+        assert_eq!(parse_transfer("404 120.0 0 0.004 0.018"), None);
+
+        let at = |hour, download| SpeedResult {
+            timestamp: Local
+                .with_ymd_and_hms(2026, 10, 16, hour, 0, 0)
+                .unwrap()
+                .to_rfc3339(),
+            download_mbps: download,
+            upload_mbps: Some(40.0),
+            latency_ms: Some(14.0),
+            error: None,
+        };
+        let mut history = vec![
+            at(9, Some(480.0)),
+            at(20, Some(110.0)),
+            at(20, Some(90.0)),
+            at(21, None),
+        ];
+        let hourly = hourly_download(&history);
+        assert_eq!(hourly[9], Some(480.0));
+        assert_eq!(hourly[20], Some(100.0));
+        assert_eq!(hourly[21], None);
+        assert_eq!(hourly[3], None);
+
+        assert_eq!(
+            history[1].to_csv_row(),
+            format!("{},110.0,40.0,14.0,", history[1].timestamp)
+        );
+        assert_eq!(to_csv(&history).lines().count(), 5);
+
+        let now = Local.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+        history.push(SpeedResult {
+            timestamp: "2026-01-01T00:00:00+00:00".to_string(),
+            ..at(9, None)
+        });
+        prune(&mut history, 90, now);
+        assert_eq!(history.len(), 4);
+    }
+}
@@ -0,0 +1,367 @@
+// src/survey.rs - WiFi site survey: every BSS of repeated scans, with a GPS fix when gpsd has one
+use crate::network::WifiNetwork;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// How long to wait for gpsd to report a position before a scan goes without one
+const GPS_WAIT: Duration = Duration::from_secs(2);
+
+/// `[survey]`: how `lantern wifi survey` and the survey panel sample
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurveySettings {
+    /// Seconds between the starts of scans in the survey panel
+    pub interval_secs: u64,
+    /// Tag samples with gpsd's position in the survey panel
+    pub gps: bool,
+    pub gpsd: String,
+}
+
+impl Default for SurveySettings {
+    fn default() -> Self {
+        Self {
+            interval_secs: 10,
+            gps: true,
+            gpsd: "127.0.0.1:2947".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            other => Err(anyhow!("Unknown format {:?}: csv or json", other)),
+        }
+    }
+}
+
+/// Where the receiver was, from a gpsd TPV report with at least a 2D fix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fix {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+/// One access point heard in one scan
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurveyRecord {
+    pub timestamp: String, // RFC 3339
+    pub scan: usize,       // Which scan of the survey, from 1
+    pub ssid: String,
+    pub bssid: String,
+    pub channel: u32,
+    pub frequency: u32,
+    pub signal_dbm: i32,
+    pub security: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<f64>,
+}
+
+pub const CSV_HEADER: &str =
+    "timestamp,scan,ssid,bssid,channel,frequency,signal_dbm,security,latitude,longitude,altitude";
+
+/// A row per BSS, so access points sharing an SSID each get their own
+pub fn records(
+    networks: &[WifiNetwork],
+    scan: usize,
+    timestamp: &str,
+    fix: Option<Fix>,
+) -> Vec<SurveyRecord> {
+    let record = |network: &WifiNetwork, bssid: &str, channel, frequency, signal| SurveyRecord {
+        timestamp: timestamp.to_string(),
+        scan,
+        ssid: network.ssid.clone(),
+        bssid: bssid.to_string(),
+        channel,
+        frequency,
+        signal_dbm: signal,
+        security: format!("{:?}", network.security),
+        latitude: fix.map(|fix| fix.latitude),
+        longitude: fix.map(|fix| fix.longitude),
+        altitude: fix.and_then(|fix| fix.altitude),
+    };
+    networks
+        .iter()
+        .flat_map(|network| {
+            if network.access_points.is_empty() {
+                return vec![record(
+                    network,
+                    &network.bssid,
+                    network.channel,
+                    network.frequency,
+                    network.signal_strength,
+                )];
+            }
+            network
+                .access_points
+                .iter()
+                .map(|ap| {
+                    record(
+                        network,
+                        &ap.bssid,
+                        ap.channel,
+                        ap.frequency,
+                        ap.signal_strength,
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Quoted when it holds a comma, quote or line break, as RFC 4180 has it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl SurveyRecord {
+    pub fn to_csv_row(&self) -> String {
+        let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        [
+            self.timestamp.clone(),
+            self.scan.to_string(),
+            csv_field(&self.ssid),
+            self.bssid.clone(),
+            self.channel.to_string(),
+            self.frequency.to_string(),
+            self.signal_dbm.to_string(),
+            self.security.clone(),
+            optional(self.latitude),
+            optional(self.longitude),
+            optional(self.altitude),
+        ]
+        .join(",")
+    }
+}
+
+/// What gpsd can say about where the receiver is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GpsStatus {
+    /// Not polled yet, or gpsd not reachable
+    Unavailable,
+    /// gpsd is running but its receiver has no fix yet
+    NoFix,
+    Fix(Fix),
+}
+
+impl GpsStatus {
+    pub fn fix(self) -> Option<Fix> {
+        match self {
+            GpsStatus::Fix(fix) => Some(fix),
+            _ => None,
+        }
+    }
+}
+
+/// The position in a gpsd JSON report; None unless it is a TPV
+pub fn parse_report(line: &str) -> Option<GpsStatus> {
+    let report: Value = serde_json::from_str(line).ok()?;
+    if report["class"] != "TPV" {
+        return None;
+    }
+    let mode = report["mode"].as_u64().unwrap_or(0);
+    let position = (report["lat"].as_f64(), report["lon"].as_f64());
+    let (Some(latitude), Some(longitude)) = position else {
+        return Some(GpsStatus::NoFix);
+    };
+    if mode < 2 {
+        return Some(GpsStatus::NoFix);
+    }
+    Some(GpsStatus::Fix(Fix {
+        latitude,
+        longitude,
+        // Only a 3D fix has a meaningful altitude
+        altitude: (mode == 3)
+            .then(|| report["altMSL"].as_f64().or(report["alt"].as_f64()))
+            .flatten(),
+    }))
+}
+
+/// Ask gpsd at `address` for the current position
+pub async fn gps_status(address: &str) -> GpsStatus {
+    let Ok(Ok(mut stream)) = tokio::time::timeout(GPS_WAIT, TcpStream::connect(address)).await
+    else {
+        return GpsStatus::Unavailable;
+    };
+    let watch = stream
+        .write_all(b"?WATCH={\"enable\":true,\"json\":true};\n")
+        .await;
+    if watch.is_err() {
+        return GpsStatus::Unavailable;
+    }
+    // gpsd sends VERSION and DEVICES first; no TPV in time means no fix
+    let mut lines = BufReader::new(stream).lines();
+    let first_tpv = async {
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Some(status) = parse_report(&line) {
+                return status;
+            }
+        }
+        GpsStatus::Unavailable
+    };
+    tokio::time::timeout(GPS_WAIT, first_tpv)
+        .await
+        .unwrap_or(GpsStatus::NoFix)
+}
+
+/// A survey run from the TUI: everything heard so far, tagged with the
+/// latest GPS status
+#[derive(Debug, Clone)]
+pub struct Survey {
+    pub interface: String,
+    pub records: Vec<SurveyRecord>,
+    pub scans: usize,
+    /// The latest scan's rows, strongest first
+    pub last_scan: Vec<SurveyRecord>,
+    pub gps: GpsStatus,
+    pub error: Option<String>,
+}
+
+impl Survey {
+    pub fn new(interface: &str) -> Self {
+        Self {
+            interface: interface.to_string(),
+            records: Vec::new(),
+            scans: 0,
+            last_scan: Vec::new(),
+            gps: GpsStatus::Unavailable,
+            error: None,
+        }
+    }
+
+    pub fn add_scan(&mut self, networks: &[WifiNetwork], timestamp: &str) {
+        self.scans += 1;
+        let mut scan = records(networks, self.scans, timestamp, self.gps.fix());
+        scan.sort_by_key(|record| std::cmp::Reverse(record.signal_dbm));
+        self.records.extend(scan.iter().cloned());
+        self.last_scan = scan;
+        self.error = None;
+    }
+
+    /// Distinct BSSIDs heard over the whole survey
+    pub fn access_points(&self) -> usize {
+        self.records
+            .iter()
+            .map(|record| record.bssid.as_str())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_HEADER);
+        for record in &self.records {
+            csv.push_str(&record.to_csv_row());
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{AccessPoint, WifiSecurity};
+
+    #[test]
+    fn one_row_per_access_point() {
+        let ap = |bssid: &str, signal, channel| AccessPoint {
+            bssid: bssid.to_string(),
+            signal_strength: signal,
+            frequency: 2412,
+            channel,
+            connected: false,
+        };
+        let network = WifiNetwork {
+            ssid: "Cafe, \"Free\"".to_string(),
+            bssid: "aa:aa:aa:aa:aa:01".to_string(),
+            signal_strength: -40,
+            frequency: 2412,
+            channel: 1,
+            security: WifiSecurity::WPA2,
+            encryption: Vec::new(),
+            security_details: Default::default(),
+            connected: false,
+            in_history: false,
+            access_points: vec![
+                ap("aa:aa:aa:aa:aa:01", -40, 1),
+                ap("aa:aa:aa:aa:aa:02", -71, 1),
+            ],
+        };
+        let fix = Fix {
+            latitude: 50.08,
+            longitude: 14.42,
+            altitude: None,
+        };
+        let rows = records(
+            std::slice::from_ref(&network),
+            3,
+            "2026-10-17T12:00:00+02:00",
+            Some(fix),
+        );
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].signal_dbm, -71);
+        assert_eq!(
+            rows[0].to_csv_row(),
+            "2026-10-17T12:00:00+02:00,3,\"Cafe, \"\"Free\"\"\",aa:aa:aa:aa:aa:01,1,2412,-40,WPA2,50.08,14.42,"
+        );
+        assert_eq!(
+            CSV_HEADER.split(',').count(),
+            rows[0].to_csv_row().split(',').count() - 1 // The comma in the SSID
+        );
+
+        // A TUI survey counts each BSSID once however often it is heard
+        let mut survey = Survey::new("wlan0");
+        survey.gps = GpsStatus::Fix(fix);
+        survey.add_scan(std::slice::from_ref(&network), "2026-10-17T12:00:00+02:00");
+        survey.add_scan(&[network], "2026-10-17T12:00:10+02:00");
+        assert_eq!(survey.scans, 2);
+        assert_eq!(survey.records.len(), 4);
+        assert_eq!(survey.access_points(), 2);
+        assert_eq!(survey.last_scan[0].scan, 2);
+        assert_eq!(survey.to_csv().lines().count(), 5);
+    }
+
+    #[test]
+    fn reads_position_from_gpsd() {
+        let status = parse_report(
+            r#"{"class":"TPV","device":"/dev/ttyACM0","mode":3,"lat":50.087,"lon":14.421,"altMSL":235.1}"#,
+        );
+        assert_eq!(
+            status,
+            Some(GpsStatus::Fix(Fix {
+                latitude: 50.087,
+                longitude: 14.421,
+                altitude: Some(235.1),
+            }))
+        );
+        // No fix yet, and reports that aren't positions
+        assert_eq!(
+            parse_report(r#"{"class":"TPV","mode":1}"#),
+            Some(GpsStatus::NoFix)
+        );
+        assert_eq!(
+            parse_report(r#"{"class":"VERSION","release":"3.25"}"#),
+            None
+        );
+        assert_eq!(parse_report("garbage"), None);
+    }
+}
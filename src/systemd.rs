@@ -1,15 +1,33 @@
 // src/systemd.rs
 #![allow(dead_code)] // Many methods are for future features or CLI mode
 #![allow(clippy::needless_borrows_for_generic_args)] // Command args are clearer with explicit borrows
-use crate::network::{Ipv6Config, WifiCredentials, WifiSecurity, WireGuardConfig};
-use anyhow::Result;
+use crate::command::TimedOutput;
+use crate::config::NetworkExtras;
+use crate::network::{Ipv6Config, VpnAutoConnect, WifiCredentials, WifiSecurity, WireGuardConfig};
+use crate::provenance;
+use crate::retry::{retry, RetryPolicy};
+use crate::sanitize;
+use crate::secure_file;
+use anyhow::{Context, Result};
 use std::fs;
-use std::path::Path;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct SystemdNetworkConfig;
 
+// USB sharing, wired, IPv6, WiFi, dummy and WireGuard configs, in networkd's order
+const GENERATED_PREFIXES: [&str; 6] = [crate::usbshare::PREFIX, "10", "20", "25", "40", "50"];
+
+/// A peer's `Endpoint=` from a WireGuard netdev. When it names a host,
+/// networkd resolves it once when the tunnel comes up and never again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerEndpoint {
+    pub interface: String,
+    pub peer_public_key: String,
+    pub endpoint: String, // host:port as written in the netdev
+}
+
 impl SystemdNetworkConfig {
     pub fn new() -> Self {
         Self
@@ -22,13 +40,17 @@ impl SystemdNetworkConfig {
         ip: Option<String>,
         gateway: Option<String>,
         dns: Option<Vec<String>>,
+        extras: &NetworkExtras,
     ) -> Result<()> {
+        check_network_values(interface, &ip, &gateway, &dns)?;
+        check_extras(extras)?;
+
         let config_dir = Path::new("/etc/systemd/network");
         if !config_dir.exists() {
             fs::create_dir_all(config_dir)?;
         }
 
-        let config_file = config_dir.join(format!("10-{}.network", interface));
+        let config_file = network_file_path(interface);
 
         let mut config = String::new();
         config.push_str(&format!("[Match]\nName={}\n\n", interface));
@@ -49,29 +71,83 @@ impl SystemdNetworkConfig {
                 }
             }
         }
+        push_domains(&mut config, extras);
 
         config.push_str("\n[Link]\n");
         config.push_str("RequiredForOnline=yes\n");
+        push_routes(&mut config, extras);
+
+        secure_file::write_public(
+            config_file,
+            provenance::stamp(
+                &config,
+                &format!(
+                    "{} for {}",
+                    if dhcp { "DHCP" } else { "Static address" },
+                    interface
+                ),
+            ),
+        )?;
+
+        reload_networkd().await?;
+
+        Command::new("/usr/bin/networkctl")
+            .args(&["reconfigure", interface])
+            .timed_output()
+            .await?;
 
-        fs::write(config_file, config)?;
+        Ok(())
+    }
 
-        // Reload systemd-networkd
-        Command::new("/usr/bin/networkctl").arg("reload").output()?;
+    /// The .network file lantern generated for `interface`, if there is one.
+    pub fn generated_network_file(&self, interface: &str) -> Option<PathBuf> {
+        sanitize::interface_name(interface).ok()?;
+        GENERATED_PREFIXES
+            .iter()
+            .map(|prefix| {
+                Path::new("/etc/systemd/network").join(format!("{}-{}.network", prefix, interface))
+            })
+            .find(|path| path.exists())
+    }
+
+    /// Whether `path` is one of the files lantern writes for `interface`.
+    pub fn written_by_lantern(&self, path: &Path, interface: &str) -> bool {
+        if path.parent() != Some(Path::new("/etc/systemd/network")) {
+            return false;
+        }
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        GENERATED_PREFIXES.iter().any(|prefix| {
+            ["network", "netdev"]
+                .iter()
+                .any(|extension| name == format!("{}-{}.{}", prefix, interface, extension))
+        })
+    }
 
+    /// Pick up a .network file edited by hand.
+    pub async fn apply_edited_network(&self, interface: &str) -> Result<()> {
+        reload_networkd().await?;
         Command::new("/usr/bin/networkctl")
             .args(&["reconfigure", interface])
-            .output()?;
-
+            .timed_output()
+            .await?;
         Ok(())
     }
 
+    /// After editing a file that may match several interfaces, or none yet
+    pub async fn reload(&self) -> Result<()> {
+        reload_networkd().await
+    }
+
     pub async fn remove_config(&self, interface: &str) -> Result<()> {
+        sanitize::interface_name(interface)?;
         let config_file =
             Path::new("/etc/systemd/network").join(format!("10-{}.network", interface));
         if config_file.exists() {
             fs::remove_file(config_file)?;
 
-            Command::new("/usr/bin/networkctl").arg("reload").output()?;
+            reload_networkd().await?;
         }
         Ok(())
     }
@@ -85,6 +161,10 @@ impl SystemdNetworkConfig {
         gateway: Option<String>,
         dns: Option<Vec<String>>,
     ) -> Result<()> {
+        check_network_values(interface, &ip, &gateway, &dns)?;
+        let extras = &credentials.extras();
+        check_extras(extras)?;
+
         // Create wpa_supplicant configuration
         self.create_wpa_supplicant_config(interface, credentials)
             .await?;
@@ -116,15 +196,22 @@ impl SystemdNetworkConfig {
                 }
             }
         }
+        push_domains(&mut config, extras);
 
         // Add WiFi-specific configuration
         config.push_str("\n[Link]\n");
         config.push_str("RequiredForOnline=yes\n");
+        push_routes(&mut config, extras);
 
-        fs::write(config_file, config)?;
+        secure_file::write_public(
+            config_file,
+            provenance::stamp(
+                &config,
+                &format!("WiFi {} on {}", credentials.ssid, interface),
+            ),
+        )?;
 
-        // Reload systemd-networkd
-        Command::new("/usr/bin/networkctl").arg("reload").output()?;
+        reload_networkd().await?;
 
         Ok(())
     }
@@ -132,12 +219,16 @@ impl SystemdNetworkConfig {
     pub async fn create_enterprise_wifi_config(
         &self,
         interface: &str,
-        _credentials: &WifiCredentials,
+        credentials: &WifiCredentials,
         dhcp: bool,
         ip: Option<String>,
         gateway: Option<String>,
         dns: Option<Vec<String>>,
     ) -> Result<()> {
+        check_network_values(interface, &ip, &gateway, &dns)?;
+        let extras = &credentials.extras();
+        check_extras(extras)?;
+
         // Create systemd-networkd configuration (same as regular WiFi)
         let config_dir = Path::new("/etc/systemd/network");
         if !config_dir.exists() {
@@ -165,15 +256,22 @@ impl SystemdNetworkConfig {
                 }
             }
         }
+        push_domains(&mut config, extras);
 
         // Add WiFi-specific configuration for Enterprise
         config.push_str("\n[Link]\n");
         config.push_str("RequiredForOnline=yes\n");
+        push_routes(&mut config, extras);
 
-        fs::write(config_file, config)?;
+        secure_file::write_public(
+            config_file,
+            provenance::stamp(
+                &config,
+                &format!("Enterprise WiFi {} on {}", credentials.ssid, interface),
+            ),
+        )?;
 
-        // Reload systemd-networkd
-        Command::new("/usr/bin/networkctl").arg("reload").output()?;
+        reload_networkd().await?;
 
         Ok(())
     }
@@ -183,6 +281,9 @@ impl SystemdNetworkConfig {
         interface: &str,
         credentials: &WifiCredentials,
     ) -> Result<()> {
+        sanitize::interface_name(interface)?;
+        sanitize::ssid(&credentials.ssid)?;
+
         let wpa_dir = Path::new("/etc/wpa_supplicant");
         if !wpa_dir.exists() {
             fs::create_dir_all(wpa_dir)?;
@@ -197,11 +298,17 @@ impl SystemdNetworkConfig {
 
         // Add network configuration
         wpa_config.push_str("network={\n");
-        wpa_config.push_str(&format!("    ssid=\"{}\"\n", credentials.ssid));
+        wpa_config.push_str(&format!(
+            "    ssid={}\n",
+            sanitize::wpa_string(&credentials.ssid)
+        ));
 
         if credentials.hidden {
             wpa_config.push_str("    scan_ssid=1\n");
         }
+        if let Some(frequencies) = credentials.band.freq_list() {
+            wpa_config.push_str(&format!("    freq_list={}\n", frequencies));
+        }
 
         match &credentials.security {
             WifiSecurity::Open => {
@@ -209,20 +316,29 @@ impl SystemdNetworkConfig {
             }
             WifiSecurity::WEP => {
                 if let Some(ref password) = credentials.password {
-                    wpa_config.push_str(&format!("    wep_key0=\"{}\"\n", password));
+                    wpa_config.push_str(&format!(
+                        "    wep_key0={}\n",
+                        sanitize::wpa_string(password)
+                    ));
                     wpa_config.push_str("    key_mgmt=NONE\n");
                     wpa_config.push_str("    wep_tx_keyidx=0\n");
                 }
             }
             WifiSecurity::WPA | WifiSecurity::WPA2 => {
                 if let Some(ref password) = credentials.password {
-                    wpa_config.push_str(&format!("    psk=\"{}\"\n", password));
+                    // Store the derived PSK so the passphrase never lands in /etc
+                    let psk = crate::password::derive_wpa_psk(&credentials.ssid, password)?;
+                    wpa_config.push_str(&format!("    psk={}\n", psk));
                 }
                 wpa_config.push_str("    key_mgmt=WPA-PSK\n");
             }
             WifiSecurity::WPA3 => {
+                // SAE needs the actual password, it cannot be pre-hashed like WPA-PSK
                 if let Some(ref password) = credentials.password {
-                    wpa_config.push_str(&format!("    psk=\"{}\"\n", password));
+                    wpa_config.push_str(&format!(
+                        "    sae_password={}\n",
+                        sanitize::wpa_string(password)
+                    ));
                 }
                 wpa_config.push_str("    key_mgmt=SAE\n");
                 wpa_config.push_str("    ieee80211w=2\n");
@@ -237,16 +353,19 @@ impl SystemdNetworkConfig {
 
         wpa_config.push_str("}\n");
 
-        fs::write(wpa_config_file, wpa_config)?;
+        let operation = format!("WiFi credentials for {} on {}", credentials.ssid, interface);
+        secure_file::write_private(wpa_config_file, provenance::stamp(&wpa_config, &operation))?;
 
         // Enable and start wpa_supplicant for this interface
         Command::new("/usr/bin/systemctl")
             .args(&["enable", &format!("wpa_supplicant@{}.service", interface)])
-            .output()?;
+            .timed_output()
+            .await?;
 
         Command::new("/usr/bin/systemctl")
             .args(&["restart", &format!("wpa_supplicant@{}.service", interface)])
-            .output()?;
+            .timed_output()
+            .await?;
 
         Ok(())
     }
@@ -255,11 +374,13 @@ impl SystemdNetworkConfig {
         // Stop wpa_supplicant
         Command::new("/usr/bin/systemctl")
             .args(&["stop", &format!("wpa_supplicant@{}.service", interface)])
-            .output()?;
+            .timed_output()
+            .await?;
 
         Command::new("/usr/bin/systemctl")
             .args(&["disable", &format!("wpa_supplicant@{}.service", interface)])
-            .output()?;
+            .timed_output()
+            .await?;
 
         // Remove wpa_supplicant config
         let wpa_config_file =
@@ -283,6 +404,16 @@ impl SystemdNetworkConfig {
         gateway: Option<String>,
         dns: Option<Vec<String>>,
     ) -> Result<()> {
+        check_network_values(interface, &ip, &gateway, &dns)?;
+        for value in ipv6_config
+            .addresses
+            .iter()
+            .chain(&ipv6_config.dns_servers)
+            .chain(&ipv6_config.gateway)
+        {
+            sanitize::config_value(value)?;
+        }
+
         let config_dir = Path::new("/etc/systemd/network");
         if !config_dir.exists() {
             fs::create_dir_all(config_dir)?;
@@ -346,14 +477,17 @@ impl SystemdNetworkConfig {
         config.push_str("\n[Link]\n");
         config.push_str("RequiredForOnline=yes\n");
 
-        fs::write(config_file, config)?;
+        secure_file::write_public(
+            config_file,
+            provenance::stamp(&config, &format!("IPv6 settings for {}", interface)),
+        )?;
 
-        // Reload systemd-networkd
-        Command::new("/usr/bin/networkctl").arg("reload").output()?;
+        reload_networkd().await?;
 
         Command::new("/usr/bin/networkctl")
             .args(&["reconfigure", interface])
-            .output()?;
+            .timed_output()
+            .await?;
 
         Ok(())
     }
@@ -368,7 +502,8 @@ impl SystemdNetworkConfig {
             // Enable IPv6 on interface
             Command::new("/usr/bin/sysctl")
                 .args(&["-w", &format!("net.ipv6.conf.{}.disable_ipv6=0", interface)])
-                .output()?;
+                .timed_output()
+                .await?;
 
             // Configure Router Advertisement acceptance
             Command::new("/usr/bin/sysctl")
@@ -380,7 +515,8 @@ impl SystemdNetworkConfig {
                         if ipv6_config.accept_ra { "1" } else { "0" }
                     ),
                 ])
-                .output()?;
+                .timed_output()
+                .await?;
 
             // Configure privacy extensions
             Command::new("/usr/bin/sysctl")
@@ -396,28 +532,38 @@ impl SystemdNetworkConfig {
                         }
                     ),
                 ])
-                .output()?;
+                .timed_output()
+                .await?;
         } else {
             // Disable IPv6 on interface
             Command::new("/usr/bin/sysctl")
                 .args(&["-w", &format!("net.ipv6.conf.{}.disable_ipv6=1", interface)])
-                .output()?;
+                .timed_output()
+                .await?;
         }
 
         Ok(())
     }
 
     pub async fn add_ipv6_address(&self, interface: &str, address: &str) -> Result<()> {
+        sanitize::interface_name(interface)?;
+        sanitize::argument(address)?;
+
         Command::new("/usr/bin/ip")
             .args(&["-6", "addr", "add", address, "dev", interface])
-            .output()?;
+            .timed_output()
+            .await?;
         Ok(())
     }
 
     pub async fn remove_ipv6_address(&self, interface: &str, address: &str) -> Result<()> {
+        sanitize::interface_name(interface)?;
+        sanitize::argument(address)?;
+
         Command::new("/usr/bin/ip")
             .args(&["-6", "addr", "del", address, "dev", interface])
-            .output()?;
+            .timed_output()
+            .await?;
         Ok(())
     }
 
@@ -433,19 +579,25 @@ impl SystemdNetworkConfig {
             args.extend(&["via", gw]);
         }
 
-        Command::new("/usr/bin/ip").args(&args).output()?;
+        Command::new("/usr/bin/ip")
+            .args(&args)
+            .timed_output()
+            .await?;
         Ok(())
     }
 
     pub async fn remove_ipv6_route(&self, interface: &str, destination: &str) -> Result<()> {
         Command::new("/usr/bin/ip")
             .args(&["-6", "route", "del", destination, "dev", interface])
-            .output()?;
+            .timed_output()
+            .await?;
         Ok(())
     }
 
     // WireGuard methods
     pub async fn create_wireguard_config(&self, config: &WireGuardConfig) -> Result<()> {
+        check_wireguard_values(config)?;
+
         // Create the .netdev file for WireGuard interface
         self.create_wireguard_netdev(config).await?;
 
@@ -455,7 +607,8 @@ impl SystemdNetworkConfig {
         // Reload systemd-networkd
         Command::new("/usr/bin/systemctl")
             .args(&["reload", "systemd-networkd"])
-            .output()?;
+            .timed_output()
+            .await?;
 
         Ok(())
     }
@@ -502,7 +655,12 @@ impl SystemdNetworkConfig {
             }
         }
 
-        fs::write(netdev_file, netdev_config)?;
+        // The netdev holds the private key; networkd reads it as systemd-network
+        let netdev_config = provenance::stamp(
+            &netdev_config,
+            &format!("WireGuard tunnel {}", config.interface_name),
+        );
+        secure_file::write_private_for_group(netdev_file, netdev_config, "systemd-network")?;
         Ok(())
     }
 
@@ -540,11 +698,13 @@ impl SystemdNetworkConfig {
             network_config.push_str(&format!("MTUBytes={}\n", mtu));
         }
 
-        fs::write(network_file, network_config)?;
+        let operation = format!("WireGuard tunnel {}", config.interface_name);
+        secure_file::write_public(network_file, provenance::stamp(&network_config, &operation))?;
         Ok(())
     }
 
     pub async fn remove_wireguard_config(&self, interface_name: &str) -> Result<()> {
+        sanitize::interface_name(interface_name)?;
         let network_dir = Path::new("/etc/systemd/network");
 
         // Remove .netdev file
@@ -562,11 +722,278 @@ impl SystemdNetworkConfig {
         // Reload systemd-networkd
         Command::new("/usr/bin/systemctl")
             .args(&["reload", "systemd-networkd"])
-            .output()?;
+            .timed_output()
+            .await?;
 
         Ok(())
     }
 
+    /// Create a dummy interface carrying service addresses (VIPs, anycast
+    /// prefixes announced by a BGP daemon). It comes back on every boot.
+    pub async fn create_dummy_interface(&self, name: &str, addresses: &[String]) -> Result<()> {
+        sanitize::interface_name(name)?;
+        let addresses = addresses
+            .iter()
+            .map(|address| service_address(address))
+            .collect::<Result<Vec<_>>>()?;
+        let netdev_file = dummy_path(name, "netdev");
+        if !netdev_file.exists() && Path::new("/sys/class/net").join(name).exists() {
+            return Err(anyhow::anyhow!("Interface {} already exists", name));
+        }
+
+        fs::create_dir_all("/etc/systemd/network")?;
+        let operation = format!("Service addresses on {}", name);
+        secure_file::write_public(
+            &netdev_file,
+            provenance::stamp(
+                &format!(
+                    "[NetDev]\nName={}\nKind=dummy\nDescription=Service addresses\n",
+                    name
+                ),
+                &operation,
+            ),
+        )?;
+        secure_file::write_public(
+            dummy_path(name, "network"),
+            provenance::stamp(&address_network(name, &addresses), &operation),
+        )?;
+        reload_networkd().await
+    }
+
+    pub async fn remove_dummy_interface(&self, name: &str) -> Result<()> {
+        sanitize::interface_name(name)?;
+        let netdev_file = dummy_path(name, "netdev");
+        if !netdev_file.exists() {
+            return Err(anyhow::anyhow!(
+                "{} is not a dummy interface made by lantern",
+                name
+            ));
+        }
+        fs::remove_file(netdev_file)?;
+        let network_file = dummy_path(name, "network");
+        if network_file.exists() {
+            fs::remove_file(network_file)?;
+        }
+        reload_networkd().await?;
+
+        // networkd leaves netdevs in place when their file goes away
+        Command::new("/usr/bin/ip")
+            .args(["link", "delete", name, "type", "dummy"])
+            .timed_output()
+            .await?;
+        Ok(())
+    }
+
+    /// Extra addresses on lo, kept in lantern's own .network for it.
+    pub fn loopback_addresses(&self) -> Vec<String> {
+        fs::read_to_string(loopback_network_path())
+            .map(|network| parse_addresses(&network))
+            .unwrap_or_default()
+    }
+
+    pub async fn add_loopback_address(&self, address: &str) -> Result<String> {
+        let address = service_address(address)?;
+        let mut addresses = self.loopback_addresses();
+        if !addresses.contains(&address) {
+            addresses.push(address.clone());
+        }
+        self.write_loopback_addresses(&addresses).await?;
+        Ok(address)
+    }
+
+    /// Returns false when the address wasn't one of lantern's.
+    pub async fn remove_loopback_address(&self, address: &str) -> Result<bool> {
+        let address = service_address(address)?;
+        let mut addresses = self.loopback_addresses();
+        let before = addresses.len();
+        addresses.retain(|existing| *existing != address);
+        if addresses.len() == before {
+            return Ok(false);
+        }
+        self.write_loopback_addresses(&addresses).await?;
+
+        // Reconfiguring keeps addresses networkd no longer knows about
+        Command::new("/usr/bin/ip")
+            .args(["addr", "del", &address, "dev", "lo"])
+            .timed_output()
+            .await?;
+        Ok(true)
+    }
+
+    async fn write_loopback_addresses(&self, addresses: &[String]) -> Result<()> {
+        let path = loopback_network_path();
+        if addresses.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
+        } else {
+            fs::create_dir_all("/etc/systemd/network")?;
+            // lo keeps 127.0.0.1/8 and ::1 whatever the file says
+            let network = address_network("lo", addresses);
+            let network = provenance::stamp(&network, "Loopback service addresses");
+            secure_file::write_public(&path, network)?;
+        }
+        reload_networkd().await?;
+        Command::new("/usr/bin/networkctl")
+            .args(["reconfigure", "lo"])
+            .timed_output()
+            .await?;
+        Ok(())
+    }
+
+    /// First half of a key rotation: generate a new keypair and park the
+    /// private key next to the netdev without touching the running tunnel,
+    /// so the old key keeps working until the remote peer has the new public
+    /// key. Staging again reuses the pending key. Returns the new public key.
+    pub fn stage_wireguard_key_rotation(&self, interface_name: &str) -> Result<String> {
+        sanitize::interface_name(interface_name)?;
+        let netdev =
+            fs::read_to_string(wireguard_netdev_path(interface_name)).with_context(|| {
+                format!("No lantern-managed WireGuard netdev for {}", interface_name)
+            })?;
+        // Fail before generating anything if confirming could never succeed
+        replace_private_key(&netdev, "")?;
+
+        let pending_file = pending_key_path(interface_name);
+        let private_key = match fs::read_to_string(&pending_file) {
+            Ok(key) => key.trim().to_string(),
+            Err(_) => {
+                let key = crate::wgkey::generate_private_key()?;
+                secure_file::write_private(&pending_file, format!("{}\n", key))?;
+                key
+            }
+        };
+
+        crate::wgkey::public_key_from_private(&private_key)
+    }
+
+    /// Second half of a key rotation: write the pending key into the netdev,
+    /// switch the live interface over with `wg set` and drop the pending file.
+    pub async fn confirm_wireguard_key_rotation(&self, interface_name: &str) -> Result<()> {
+        sanitize::interface_name(interface_name)?;
+        let pending_file = pending_key_path(interface_name);
+        let private_key = fs::read_to_string(&pending_file)
+            .with_context(|| format!("No key rotation pending for {}", interface_name))?;
+        let private_key = private_key.trim();
+        crate::wgkey::public_key_from_private(private_key)?;
+
+        let netdev_file = wireguard_netdev_path(interface_name);
+        let netdev = fs::read_to_string(&netdev_file)?;
+        let updated = replace_private_key(&netdev, private_key)?;
+        let operation = format!("WireGuard key rotation for {}", interface_name);
+        let updated = provenance::stamp(&updated, &operation);
+        secure_file::write_private_for_group(&netdev_file, updated, "systemd-network")?;
+
+        // networkd does not re-key an existing interface on reload, so
+        // apply the key directly; an interface that is down picks it up
+        // from the netdev when it next comes up
+        let output = Command::new("/usr/bin/wg")
+            .args(["set", interface_name, "private-key"])
+            .arg(&pending_file)
+            .timed_output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Updated {} but wg set failed: {}",
+                netdev_file.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        fs::remove_file(&pending_file)?;
+        reload_networkd().await
+    }
+
+    /// Discard a staged key rotation, leaving the current key in place.
+    pub fn abort_wireguard_key_rotation(&self, interface_name: &str) -> Result<bool> {
+        sanitize::interface_name(interface_name)?;
+        match fs::remove_file(pending_key_path(interface_name)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Attach a preshared key to one peer of a lantern-managed tunnel,
+    /// generating one like `wg genpsk` when none is given. The key is stored
+    /// in the netdev (private to systemd-network) and applied to the running
+    /// interface. Returns the key so it can be handed to the other side.
+    pub async fn set_wireguard_preshared_key(
+        &self,
+        interface_name: &str,
+        peer_public_key: &str,
+        preshared_key: Option<&str>,
+    ) -> Result<String> {
+        sanitize::interface_name(interface_name)?;
+        crate::wgkey::validate_key(peer_public_key)?;
+        let preshared_key = match preshared_key {
+            Some(key) => {
+                crate::wgkey::validate_key(key)?;
+                key.trim().to_string()
+            }
+            None => crate::wgkey::generate_preshared_key()?,
+        };
+
+        let netdev_file = wireguard_netdev_path(interface_name);
+        let netdev = fs::read_to_string(&netdev_file).with_context(|| {
+            format!("No lantern-managed WireGuard netdev for {}", interface_name)
+        })?;
+        let updated = set_peer_preshared_key(&netdev, peer_public_key.trim(), &preshared_key)?;
+        let operation = format!("Preshared key for a peer of {}", interface_name);
+        let updated = provenance::stamp(&updated, &operation);
+        secure_file::write_private_for_group(&netdev_file, updated, "systemd-network")?;
+
+        // wg only takes keys from files; keep the copy private and short-lived
+        let key_file =
+            Path::new("/etc/systemd/network").join(format!("50-{}.netdev.psk", interface_name));
+        secure_file::write_private(&key_file, format!("{}\n", preshared_key))?;
+        let output = Command::new("/usr/bin/wg")
+            .args([
+                "set",
+                interface_name,
+                "peer",
+                peer_public_key.trim(),
+                "preshared-key",
+            ])
+            .arg(&key_file)
+            .timed_output()
+            .await;
+        let _ = fs::remove_file(&key_file);
+
+        // A tunnel that is down gets the key from the netdev when it comes up
+        let output = output?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Updated {} but wg set failed: {}",
+                netdev_file.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(preshared_key)
+    }
+
+    /// Endpoints of all peers of the WireGuard netdevs.
+    pub fn wireguard_endpoints(&self) -> Vec<PeerEndpoint> {
+        let Ok(entries) = fs::read_dir("/etc/systemd/network") else {
+            return vec![];
+        };
+        entries
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "netdev"))
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .flat_map(|netdev| parse_peer_endpoints(&netdev))
+            .collect()
+    }
+
+    /// Peers whose endpoint is a hostname rather than an address.
+    pub fn wireguard_hostname_endpoints(&self) -> Vec<PeerEndpoint> {
+        self.wireguard_endpoints()
+            .into_iter()
+            .filter(|peer| !peer.is_address())
+            .collect()
+    }
+
     pub async fn create_wireguard_from_config_file(
         &self,
         config_path: &str,
@@ -592,7 +1019,7 @@ impl SystemdNetworkConfig {
             dns: vec![],
             mtu: None,
             peers: vec![],
-            auto_connect: false,
+            auto_connect: VpnAutoConnect::Never,
         };
 
         let mut current_section = "";
@@ -665,12 +1092,466 @@ impl SystemdNetworkConfig {
 
         // Generate public key from private key if not set
         if config.public_key.is_empty() && !config.private_key.is_empty() {
-            let output = Command::new("/bin/sh")
-                .args(&["-c", &format!("echo '{}' | wg pubkey", config.private_key)])
-                .output()?;
-            config.public_key = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            config.public_key = crate::wgkey::public_key_from_private(&config.private_key)?;
         }
 
         Ok(config)
     }
 }
+
+/// Catch the mistakes networkd would silently skip over in a hand-edited
+/// .network file: lines that are neither a `[Section]` nor `Key=value`,
+/// settings outside a section, and a missing [Match] section.
+pub fn validate_network_file(contents: &str) -> Result<()> {
+    let mut section: Option<&str> = None;
+    let mut has_match = false;
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        let number = number + 1;
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .filter(|name| !name.is_empty() && !name.contains(['[', ']']))
+                .with_context(|| format!("Line {}: malformed section header", number))?;
+            has_match |= name == "Match";
+            section = Some(name);
+            continue;
+        }
+        let Some((key, _)) = line.split_once('=') else {
+            anyhow::bail!("Line {}: expected Key=value, found '{}'", number, line);
+        };
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+            anyhow::bail!("Line {}: invalid setting name '{}'", number, key);
+        }
+        if section.is_none() {
+            anyhow::bail!("Line {}: '{}' is outside any [Section]", number, key);
+        }
+    }
+
+    if !has_match {
+        anyhow::bail!("No [Match] section; the file would apply to every interface");
+    }
+    Ok(())
+}
+
+// Everything below ends up as `Key=value` lines in a .network file
+fn check_network_values(
+    interface: &str,
+    ip: &Option<String>,
+    gateway: &Option<String>,
+    dns: &Option<Vec<String>>,
+) -> Result<()> {
+    sanitize::interface_name(interface)?;
+    for value in ip.iter().chain(gateway.iter()).chain(dns.iter().flatten()) {
+        sanitize::config_value(value)?;
+    }
+    Ok(())
+}
+
+fn check_extras(extras: &NetworkExtras) -> Result<()> {
+    for route in &extras.routes {
+        sanitize::config_value(&route.destination)?;
+        if route.destination.parse::<ipnetwork::IpNetwork>().is_err() {
+            anyhow::bail!(
+                "Route destination {:?} is not an address/prefix",
+                route.destination
+            );
+        }
+        if let Some(gateway) = &route.gateway {
+            if gateway.parse::<std::net::IpAddr>().is_err() {
+                anyhow::bail!("Route gateway {:?} is not an address", gateway);
+            }
+        }
+    }
+    for domain in &extras.domains {
+        sanitize::config_value(domain)?;
+        if domain.is_empty() || domain.contains(char::is_whitespace) {
+            anyhow::bail!("{:?} is not a search domain", domain);
+        }
+    }
+    Ok(())
+}
+
+/// Domains= goes in [Network], whether addresses come from DHCP or not
+fn push_domains(config: &mut String, extras: &NetworkExtras) {
+    if !extras.domains.is_empty() {
+        config.push_str(&format!("Domains={}\n", extras.domains.join(" ")));
+    }
+}
+
+/// One [Route] section per route, after everything else
+fn push_routes(config: &mut String, extras: &NetworkExtras) {
+    for route in &extras.routes {
+        config.push_str(&format!("\n[Route]\nDestination={}\n", route.destination));
+        match &route.gateway {
+            Some(gateway) => config.push_str(&format!("Gateway={}\n", gateway)),
+            None => config.push_str("Scope=link\n"),
+        }
+        if let Some(metric) = route.metric {
+            config.push_str(&format!("Metric={}\n", metric));
+        }
+    }
+}
+
+fn check_wireguard_values(config: &WireGuardConfig) -> Result<()> {
+    sanitize::interface_name(&config.interface_name)?;
+    sanitize::config_value(&config.private_key)?;
+    for value in config.addresses.iter().chain(&config.dns) {
+        sanitize::config_value(value)?;
+    }
+    for peer in &config.peers {
+        sanitize::config_value(&peer.public_key)?;
+        for value in peer
+            .preshared_key
+            .iter()
+            .chain(&peer.endpoint)
+            .chain(&peer.allowed_ips)
+        {
+            sanitize::config_value(value)?;
+        }
+    }
+    Ok(())
+}
+
+fn dummy_path(name: &str, extension: &str) -> PathBuf {
+    Path::new("/etc/systemd/network").join(format!("40-{}.{}", name, extension))
+}
+
+fn loopback_network_path() -> PathBuf {
+    Path::new("/etc/systemd/network").join("40-lo.network")
+}
+
+/// An address with its prefix; a bare address is a single host (/32 or /128).
+fn service_address(address: &str) -> Result<String> {
+    let (ip, prefix) = match address.trim().split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (address.trim(), None),
+    };
+    let ip: std::net::IpAddr = ip
+        .parse()
+        .with_context(|| format!("Invalid address '{}'", address))?;
+    let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|prefix| *prefix <= max_prefix)
+            .with_context(|| format!("Invalid prefix length in '{}'", address))?,
+        None => max_prefix,
+    };
+    Ok(format!("{}/{}", ip, prefix))
+}
+
+fn address_network(name: &str, addresses: &[String]) -> String {
+    let mut network = format!("[Match]\nName={}\n\n[Network]\n", name);
+    for address in addresses {
+        network.push_str(&format!("Address={}\n", address));
+    }
+    // Service addresses shouldn't hold up network-online.target
+    network.push_str("\n[Link]\nRequiredForOnline=no\n");
+    network
+}
+
+fn parse_addresses(network: &str) -> Vec<String> {
+    network
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("Address="))
+        .map(str::to_string)
+        .collect()
+}
+
+fn wireguard_netdev_path(interface_name: &str) -> PathBuf {
+    Path::new("/etc/systemd/network").join(format!("50-{}.netdev", interface_name))
+}
+
+// networkd only loads *.netdev/*.network, so the pending key can sit beside them
+fn pending_key_path(interface_name: &str) -> PathBuf {
+    Path::new("/etc/systemd/network").join(format!("50-{}.netdev.pending-key", interface_name))
+}
+
+/// Swap the `PrivateKey=` line of the [WireGuard] section, keeping the rest
+/// of the netdev as written.
+fn replace_private_key(netdev: &str, private_key: &str) -> Result<String> {
+    let mut section = "";
+    let mut replaced = false;
+    let mut output = String::with_capacity(netdev.len());
+
+    for line in netdev.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            section = trimmed;
+        }
+        if section == "[WireGuard]" {
+            if trimmed.starts_with("PrivateKeyFile=") {
+                return Err(anyhow::anyhow!(
+                    "Netdev reads its key from PrivateKeyFile=; rotate that file instead"
+                ));
+            }
+            if trimmed.starts_with("PrivateKey=") {
+                output.push_str(&format!("PrivateKey={}\n", private_key));
+                replaced = true;
+                continue;
+            }
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+
+    if !replaced {
+        return Err(anyhow::anyhow!(
+            "Netdev has no PrivateKey= in its [WireGuard] section"
+        ));
+    }
+    Ok(output)
+}
+
+/// Set `PresharedKey=` in the [WireGuardPeer] section for `peer_public_key`,
+/// replacing any key it already had.
+fn set_peer_preshared_key(
+    netdev: &str,
+    peer_public_key: &str,
+    preshared_key: &str,
+) -> Result<String> {
+    let mut sections: Vec<Vec<&str>> = vec![vec![]];
+    for line in netdev.lines() {
+        if line.trim().starts_with('[') {
+            sections.push(vec![]);
+        }
+        sections.last_mut().unwrap().push(line);
+    }
+
+    let public_key_line = format!("PublicKey={}", peer_public_key);
+    let preshared_key_line = format!("PresharedKey={}", preshared_key);
+    let mut found = false;
+    let mut output = String::with_capacity(netdev.len() + preshared_key_line.len());
+
+    for section in sections {
+        let is_peer = section.first().map(|l| l.trim()) == Some("[WireGuardPeer]")
+            && section.iter().any(|l| l.trim() == public_key_line);
+        for line in section {
+            let trimmed = line.trim();
+            if is_peer && trimmed.starts_with("PresharedKey=") {
+                continue;
+            }
+            output.push_str(line);
+            output.push('\n');
+            if is_peer && trimmed == public_key_line {
+                output.push_str(&preshared_key_line);
+                output.push('\n');
+            }
+        }
+        found |= is_peer;
+    }
+
+    if !found {
+        return Err(anyhow::anyhow!(
+            "No peer with public key {} in the netdev",
+            peer_public_key
+        ));
+    }
+    Ok(output)
+}
+
+impl PeerEndpoint {
+    /// Whether the endpoint is already an address (including [v6]:port)
+    pub fn is_address(&self) -> bool {
+        self.endpoint.parse::<std::net::SocketAddr>().is_ok()
+    }
+}
+
+fn parse_peer_endpoints(netdev: &str) -> Vec<PeerEndpoint> {
+    let mut interface = None;
+    let mut wireguard = false;
+    let mut peers: Vec<(Option<String>, Option<String>)> = vec![];
+    let mut section = "";
+
+    for line in netdev.lines().map(str::trim) {
+        if line.starts_with('[') {
+            section = line;
+            if section == "[WireGuardPeer]" {
+                peers.push((None, None));
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match (section, key.trim()) {
+            ("[NetDev]", "Name") => interface = Some(value),
+            ("[NetDev]", "Kind") => wireguard = value == "wireguard",
+            ("[WireGuardPeer]", "PublicKey") => peers.last_mut().unwrap().0 = Some(value),
+            ("[WireGuardPeer]", "Endpoint") => peers.last_mut().unwrap().1 = Some(value),
+            _ => {}
+        }
+    }
+
+    let Some(interface) = interface.filter(|_| wireguard) else {
+        return vec![];
+    };
+    peers
+        .into_iter()
+        .filter_map(|(public_key, endpoint)| {
+            Some(PeerEndpoint {
+                interface: interface.clone(),
+                peer_public_key: public_key?,
+                endpoint: endpoint?,
+            })
+        })
+        .collect()
+}
+
+/// Where `create_config` writes the .network file for `interface`
+pub fn network_file_path(interface: &str) -> PathBuf {
+    Path::new("/etc/systemd/network").join(format!("10-{}.network", interface))
+}
+
+// networkctl fails while systemd-networkd is (re)starting, so retry briefly.
+// Only a failure to run networkctl at all is an error; a reload that keeps
+// failing is left for networkd to pick up on its next start.
+pub(crate) async fn reload_networkd() -> Result<()> {
+    let result = retry(RetryPolicy::NETWORKD_RELOAD, || async {
+        let output = Command::new("/usr/bin/networkctl")
+            .arg("reload")
+            .timed_output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "networkctl reload failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    })
+    .await;
+
+    match result {
+        Err(e) if e.downcast_ref::<std::io::Error>().is_some() => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_hand_edited_network_files() {
+        let network = "# Edited\n[Match]\nName=eth0\n\n[Network]\nDHCP=yes\nDNS = 1.1.1.1\n";
+        assert!(validate_network_file(network).is_ok());
+
+        assert!(validate_network_file("[Network]\nDHCP=yes\n").is_err());
+        assert!(validate_network_file("Name=eth0\n[Match]\n").is_err());
+        assert!(validate_network_file("[Match]\nName=eth0\nDHCP yes\n").is_err());
+        assert!(validate_network_file("[Match\nName=eth0\n").is_err());
+        assert!(validate_network_file("[Match]\nName-x=eth0\n").is_err());
+    }
+
+    #[test]
+    fn writes_service_addresses() {
+        assert_eq!(service_address("192.0.2.10").unwrap(), "192.0.2.10/32");
+        assert_eq!(service_address("2001:db8::1/64").unwrap(), "2001:db8::1/64");
+        assert!(service_address("192.0.2.10/33").is_err());
+        assert!(service_address("192.0.2.10\nDNS=1.1.1.1").is_err());
+
+        let addresses = vec!["192.0.2.10/32".to_string(), "2001:db8::1/128".to_string()];
+        let network = address_network("vip0", &addresses);
+        assert!(network.starts_with("[Match]\nName=vip0\n"));
+        assert_eq!(parse_addresses(&network), addresses);
+    }
+
+    #[test]
+    fn writes_profile_routes_and_domains() {
+        use crate::config::ProfileRoute;
+        let mut extras = NetworkExtras {
+            routes: vec![
+                ProfileRoute {
+                    destination: "10.20.0.0/16".to_string(),
+                    gateway: Some("192.168.50.1".to_string()),
+                    metric: Some(50),
+                },
+                ProfileRoute {
+                    destination: "172.16.5.0/24".to_string(),
+                    gateway: None,
+                    metric: None,
+                },
+            ],
+            domains: vec!["corp.example".to_string(), "lab.example".to_string()],
+        };
+        assert!(check_extras(&extras).is_ok());
+
+        let mut config = "[Network]\nDHCP=yes\n".to_string();
+        push_domains(&mut config, &extras);
+        push_routes(&mut config, &extras);
+        assert_eq!(
+            config,
+            "[Network]\nDHCP=yes\nDomains=corp.example lab.example\n\
+             \n[Route]\nDestination=10.20.0.0/16\nGateway=192.168.50.1\nMetric=50\n\
+             \n[Route]\nDestination=172.16.5.0/24\nScope=link\n"
+        );
+        assert!(validate_network_file(&format!("[Match]\nName=eth0\n{}", config)).is_ok());
+
+        extras.domains.push("corp.example\nDNS=6.6.6.6".to_string());
+        assert!(check_extras(&extras).is_err());
+        extras.domains.pop();
+        extras.routes[0].gateway = Some("gateway".to_string());
+        assert!(check_extras(&extras).is_err());
+    }
+
+    #[test]
+    fn replaces_only_the_interface_private_key() {
+        let netdev = "[NetDev]\nName=wg0\nKind=wireguard\n\n\
+                      [WireGuard]\nPrivateKey=old\nListenPort=51820\n\n\
+                      [WireGuardPeer]\nPublicKey=peer\nAllowedIPs=10.0.0.2/32\n";
+
+        let updated = replace_private_key(netdev, "new").unwrap();
+        assert_eq!(updated, netdev.replace("PrivateKey=old", "PrivateKey=new"));
+
+        assert!(replace_private_key("[NetDev]\nName=wg0\n", "new").is_err());
+        assert!(replace_private_key("[WireGuard]\nPrivateKeyFile=/etc/wg.key\n", "new").is_err());
+    }
+
+    #[test]
+    fn sets_preshared_key_on_matching_peer() {
+        let netdev = "[WireGuard]\nPrivateKey=priv\n\n\
+                      [WireGuardPeer]\nPresharedKey=stale\nPublicKey=a\nAllowedIPs=10.0.0.2/32\n\n\
+                      [WireGuardPeer]\nPublicKey=b\nAllowedIPs=10.0.0.3/32\n";
+
+        let updated = set_peer_preshared_key(netdev, "a", "psk").unwrap();
+        assert_eq!(
+            updated,
+            "[WireGuard]\nPrivateKey=priv\n\n\
+             [WireGuardPeer]\nPublicKey=a\nPresharedKey=psk\nAllowedIPs=10.0.0.2/32\n\n\
+             [WireGuardPeer]\nPublicKey=b\nAllowedIPs=10.0.0.3/32\n"
+        );
+        assert!(set_peer_preshared_key(netdev, "c", "psk").is_err());
+    }
+
+    #[test]
+    fn finds_peer_endpoints() {
+        let netdev = "[NetDev]\nName=wg0\nKind=wireguard\n\n\
+                      [WireGuard]\nPrivateKey=priv\n\n\
+                      [WireGuardPeer]\nPublicKey=a\nEndpoint=vpn.example.org:51820\n\n\
+                      [WireGuardPeer]\nPublicKey=b\nEndpoint=203.0.113.7:51820\n\n\
+                      [WireGuardPeer]\nPublicKey=c\nEndpoint=[2001:db8::1]:51820\n\n\
+                      [WireGuardPeer]\nPublicKey=d\n";
+
+        let endpoints = parse_peer_endpoints(netdev);
+        assert_eq!(
+            endpoints[0],
+            PeerEndpoint {
+                interface: "wg0".to_string(),
+                peer_public_key: "a".to_string(),
+                endpoint: "vpn.example.org:51820".to_string(),
+            }
+        );
+        let addresses: Vec<bool> = endpoints.iter().map(PeerEndpoint::is_address).collect();
+        assert_eq!(addresses, vec![false, true, true]);
+        assert!(parse_peer_endpoints("[NetDev]\nName=br0\nKind=bridge\n").is_empty());
+    }
+}
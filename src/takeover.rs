@@ -0,0 +1,600 @@
+// src/takeover.rs - Interfaces run by NetworkManager, dhcpcd, netctl or wicked, and adopting them into networkd
+use crate::command::TimedOutput;
+use crate::{provenance, sanitize, secure_file};
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tokio::process::Command;
+
+const NM_UNMANAGED: &str = "/etc/NetworkManager/conf.d/90-lantern-unmanaged.conf";
+const DHCPCD_CONF: &str = "/etc/dhcpcd.conf";
+const NETCTL_DIR: &str = "/etc/netctl";
+const WICKED_DIR: &str = "/etc/sysconfig/network";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Manager {
+    NetworkManager,
+    Dhcpcd,
+    Netctl,
+    Wicked,
+}
+
+impl Manager {
+    pub fn label(self) -> &'static str {
+        match self {
+            Manager::NetworkManager => "NetworkManager",
+            Manager::Dhcpcd => "dhcpcd",
+            Manager::Netctl => "netctl",
+            Manager::Wicked => "wicked",
+        }
+    }
+}
+
+/// IPv4 settings read from the other manager's config, to write as networkd's
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Imported {
+    pub dhcp: bool,
+    pub address: Option<String>, // CIDR
+    pub gateway: Option<String>,
+    pub dns: Vec<String>,
+}
+
+impl Imported {
+    fn dhcp() -> Self {
+        Self {
+            dhcp: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        if self.dhcp {
+            return "DHCP".to_string();
+        }
+        let mut parts = vec![self
+            .address
+            .clone()
+            .unwrap_or_else(|| "no address".to_string())];
+        if let Some(gateway) = &self.gateway {
+            parts.push(format!("via {}", gateway));
+        }
+        if !self.dns.is_empty() {
+            parts.push(format!("DNS {}", self.dns.join(", ")));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Another manager in charge of an interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct Foreign {
+    pub manager: Manager,
+    /// What holds the interface: NetworkManager connection, netctl profile,
+    /// systemd unit or ifcfg file
+    pub source: String,
+    pub imported: Imported,
+}
+
+impl Foreign {
+    /// What adopting does to the other manager, for the confirmation
+    pub fn release_steps(&self, interface: &str) -> Vec<String> {
+        match self.manager {
+            Manager::NetworkManager => vec![
+                format!(
+                    "Add interface-name:{} to unmanaged-devices in {}",
+                    interface, NM_UNMANAGED
+                ),
+                format!("nmcli device set {} managed no", interface),
+            ],
+            Manager::Dhcpcd if self.source == "dhcpcd.service" => vec![
+                format!("Add 'denyinterfaces {}' to {}", interface, DHCPCD_CONF),
+                format!("dhcpcd -k {}", interface),
+            ],
+            Manager::Dhcpcd => vec![format!("systemctl disable --now {}", self.source)],
+            Manager::Netctl if self.source.starts_with("netctl@") => {
+                let profile = unit_instance(&self.source).unwrap_or_default();
+                vec![format!("netctl disable {0} && netctl stop {0}", profile)]
+            }
+            Manager::Netctl => vec![format!("systemctl disable --now {}", self.source)],
+            Manager::Wicked => vec![
+                format!("Set STARTMODE='off' in {}", self.source),
+                format!("wicked ifdown {}", interface),
+            ],
+        }
+    }
+}
+
+// "netctl@home\x2dwifi.service" -> "home-wifi"
+fn unit_instance(unit: &str) -> Option<String> {
+    let instance = unit.split_once('@')?.1.strip_suffix(".service")?;
+    Some(instance.replace("\\x2d", "-"))
+}
+
+// nmcli -t escapes ':' and '\' inside fields
+fn split_terse(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => fields.last_mut().unwrap().extend(chars.next()),
+            ':' => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Device and connection name of every device NetworkManager manages, from
+/// `nmcli -t -f DEVICE,STATE,CONNECTION device`.
+pub fn parse_nmcli_devices(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .map(split_terse)
+        .filter(|fields| fields.len() == 3)
+        .filter(|fields| !fields[1].starts_with("unmanaged") && fields[1] != "unavailable")
+        .map(|fields| (fields[0].clone(), fields[2].clone()))
+        .collect()
+}
+
+/// A connection's IPv4 settings from `nmcli -t -f ipv4.method,ipv4.addresses,
+/// ipv4.gateway,ipv4.dns connection show <name>`.
+pub fn parse_nm_connection(output: &str) -> Imported {
+    let mut settings = HashMap::new();
+    for line in output.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            settings.insert(key.trim(), value.trim());
+        }
+    }
+    if settings.get("ipv4.method") != Some(&"manual") {
+        return Imported::dhcp();
+    }
+    let list = |key: &str| -> Vec<String> {
+        settings
+            .get(key)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty() && *v != "--")
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    Imported {
+        dhcp: false,
+        address: list("ipv4.addresses").into_iter().next(),
+        gateway: list("ipv4.gateway").into_iter().next(),
+        dns: list("ipv4.dns"),
+    }
+}
+
+/// Names of the running units from `systemctl list-units --plain --no-legend`.
+pub fn parse_units(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+fn matches_pattern(pattern: &str, interface: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => interface.starts_with(prefix),
+        None => pattern == interface,
+    }
+}
+
+/// Whether a global dhcpcd leaves `interface` alone, and its static settings
+/// from an `interface <name>` block of dhcpcd.conf (DHCP without one).
+pub fn parse_dhcpcd_conf(conf: &str, interface: &str) -> (bool, Imported) {
+    let mut denied = false;
+    let mut allowed: Option<bool> = None; // allowinterfaces makes everything else denied
+    let mut in_block = false;
+    let mut imported = Imported::dhcp();
+    for line in conf.lines().map(str::trim) {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("denyinterfaces") => denied |= words.any(|p| matches_pattern(p, interface)),
+            Some("allowinterfaces") => {
+                let listed = words.any(|p| matches_pattern(p, interface));
+                allowed = Some(allowed.unwrap_or(false) || listed);
+            }
+            Some("interface") => in_block = words.next() == Some(interface),
+            Some("static") if in_block => {
+                let Some((key, value)) = line["static".len()..].trim().split_once('=') else {
+                    continue;
+                };
+                let values: Vec<String> = value.split_whitespace().map(str::to_string).collect();
+                match key.trim() {
+                    "ip_address" => {
+                        imported.dhcp = false;
+                        imported.address = values.into_iter().next();
+                    }
+                    "routers" => imported.gateway = values.into_iter().next(),
+                    "domain_name_servers" => imported.dns = values,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    (denied || allowed == Some(false), imported)
+}
+
+// A shell value, with one level of quotes; arrays come back element by element
+fn shell_values(value: &str) -> Vec<String> {
+    let value = value.trim();
+    let inner = value
+        .strip_prefix('(')
+        .and_then(|v| v.strip_suffix(')'))
+        .unwrap_or(value);
+    inner
+        .split_whitespace()
+        .map(|v| v.trim_matches(['\'', '"']).to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+fn shell_assignments(contents: &str) -> HashMap<String, Vec<String>> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), shell_values(value)))
+        .collect()
+}
+
+/// The interface and IPv4 settings of a netctl profile.
+pub fn parse_netctl_profile(contents: &str) -> (Option<String>, Imported) {
+    let settings = shell_assignments(contents);
+    let first = |key: &str| settings.get(key).and_then(|v| v.first().cloned());
+    let interface = first("Interface");
+    if first("IP").as_deref() != Some("static") {
+        return (interface, Imported::dhcp());
+    }
+    let imported = Imported {
+        dhcp: false,
+        address: first("Address"),
+        gateway: first("Gateway"),
+        dns: settings.get("DNS").cloned().unwrap_or_default(),
+    };
+    (interface, imported)
+}
+
+/// Whether wicked brings up an ifcfg file's interface, and its IPv4
+/// settings. The gateway lives in a routes file, DNS in netconfig's global
+/// settings.
+pub fn parse_ifcfg(contents: &str, routes: &str) -> (bool, Imported) {
+    let settings = shell_assignments(contents);
+    let first = |key: &str| settings.get(key).and_then(|v| v.first().cloned());
+    let started = first("STARTMODE").as_deref() != Some("off");
+    if first("BOOTPROTO").as_deref() != Some("static") {
+        return (started, Imported::dhcp());
+    }
+    let address = first("IPADDR").map(|address| match first("PREFIXLEN") {
+        Some(prefix) if !address.contains('/') => format!("{}/{}", address, prefix),
+        _ => address,
+    });
+    let gateway = routes.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        (words.next() == Some("default"))
+            .then(|| words.next())
+            .flatten()
+            .map(str::to_string)
+    });
+    let imported = Imported {
+        dhcp: false,
+        address,
+        gateway,
+        dns: Vec::new(),
+    };
+    (started, imported)
+}
+
+/// NetworkManager's keyfile settings with `interface` added to
+/// `unmanaged-devices`.
+pub fn with_nm_unmanaged(conf: &str, interface: &str) -> String {
+    let device = format!("interface-name:{}", interface);
+    let mut devices: Vec<String> = conf
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("unmanaged-devices="))
+        .flat_map(|value| value.split(';'))
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !devices.contains(&device) {
+        devices.push(device);
+    }
+    format!(
+        "# Interfaces adopted by lantern; networkd configures them\n[keyfile]\nunmanaged-devices={}\n",
+        devices.join(";")
+    )
+}
+
+/// An ifcfg file with wicked told to leave the interface down.
+pub fn with_startmode_off(contents: &str) -> String {
+    let mut lines: Vec<String> = contents
+        .lines()
+        .filter(|line| !line.trim().starts_with("STARTMODE="))
+        .map(str::to_string)
+        .collect();
+    lines.push("STARTMODE='off' # Adopted by lantern".to_string());
+    lines.join("\n") + "\n"
+}
+
+async fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program).args(args).timed_output().await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Which of `interfaces` another manager runs. NetworkManager wins when
+/// several claim one, since it grabs every device it isn't told to leave.
+pub async fn detect(interfaces: &[String]) -> HashMap<String, Foreign> {
+    let mut found = HashMap::new();
+    let units = run(
+        "/usr/bin/systemctl",
+        &[
+            "list-units",
+            "--type=service",
+            "--state=active",
+            "--plain",
+            "--no-legend",
+            "NetworkManager.service",
+            "dhcpcd*",
+            "netctl*",
+            "wicked.service",
+        ],
+    )
+    .await
+    .map(|output| parse_units(&output))
+    .unwrap_or_default();
+    let mut claim = |interface: &str, foreign: Foreign| {
+        if interfaces.iter().any(|name| name == interface) {
+            found.entry(interface.to_string()).or_insert(foreign);
+        }
+    };
+
+    if units.iter().any(|unit| unit == "NetworkManager.service") {
+        let devices = run(
+            "/usr/bin/nmcli",
+            &["-t", "-f", "DEVICE,STATE,CONNECTION", "device"],
+        )
+        .await
+        .unwrap_or_default();
+        for (device, connection) in parse_nmcli_devices(&devices) {
+            let imported = match connection.as_str() {
+                "" | "--" => Imported::dhcp(),
+                name => run(
+                    "/usr/bin/nmcli",
+                    &[
+                        "-t",
+                        "-f",
+                        "ipv4.method,ipv4.addresses,ipv4.gateway,ipv4.dns",
+                        "connection",
+                        "show",
+                        name,
+                    ],
+                )
+                .await
+                .map(|output| parse_nm_connection(&output))
+                .unwrap_or_else(|_| Imported::dhcp()),
+            };
+            claim(
+                &device,
+                Foreign {
+                    manager: Manager::NetworkManager,
+                    source: connection,
+                    imported,
+                },
+            );
+        }
+    }
+
+    let dhcpcd_conf = fs::read_to_string(DHCPCD_CONF).unwrap_or_default();
+    for unit in &units {
+        if unit == "dhcpcd.service" {
+            // One daemon for every interface but the denied ones
+            for interface in interfaces.iter().filter(|name| *name != "lo") {
+                let (denied, imported) = parse_dhcpcd_conf(&dhcpcd_conf, interface);
+                if !denied {
+                    claim(
+                        interface,
+                        Foreign {
+                            manager: Manager::Dhcpcd,
+                            source: unit.clone(),
+                            imported,
+                        },
+                    );
+                }
+            }
+        } else if let Some(instance) = unit_instance(unit) {
+            if unit.starts_with("dhcpcd@") {
+                let (_, imported) = parse_dhcpcd_conf(&dhcpcd_conf, &instance);
+                let foreign = Foreign {
+                    manager: Manager::Dhcpcd,
+                    source: unit.clone(),
+                    imported,
+                };
+                claim(&instance, foreign);
+            } else if unit.starts_with("netctl@") {
+                let profile =
+                    fs::read_to_string(Path::new(NETCTL_DIR).join(&instance)).unwrap_or_default();
+                if let (Some(interface), imported) = parse_netctl_profile(&profile) {
+                    let foreign = Foreign {
+                        manager: Manager::Netctl,
+                        source: unit.clone(),
+                        imported,
+                    };
+                    claim(&interface, foreign);
+                }
+            } else if unit.starts_with("netctl-") {
+                // netctl-auto@ and netctl-ifplugd@ are per interface
+                let foreign = Foreign {
+                    manager: Manager::Netctl,
+                    source: unit.clone(),
+                    imported: Imported::dhcp(),
+                };
+                claim(&instance, foreign);
+            }
+        }
+    }
+
+    if units.iter().any(|unit| unit == "wicked.service") {
+        let routes = fs::read_to_string(Path::new(WICKED_DIR).join("routes")).unwrap_or_default();
+        for interface in interfaces {
+            let path = Path::new(WICKED_DIR).join(format!("ifcfg-{}", interface));
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let own_routes =
+                fs::read_to_string(Path::new(WICKED_DIR).join(format!("ifroute-{}", interface)));
+            let (started, imported) =
+                parse_ifcfg(&contents, own_routes.as_deref().unwrap_or(&routes));
+            if started {
+                claim(
+                    interface,
+                    Foreign {
+                        manager: Manager::Wicked,
+                        source: path.display().to_string(),
+                        imported,
+                    },
+                );
+            }
+        }
+    }
+    found
+}
+
+/// Make the other manager let go of `interface`, for good: only that
+/// interface, the manager itself keeps running for the rest.
+pub async fn release(interface: &str, foreign: &Foreign) -> Result<()> {
+    sanitize::interface_name(interface)?;
+    match foreign.manager {
+        Manager::NetworkManager => {
+            let conf = fs::read_to_string(NM_UNMANAGED).unwrap_or_default();
+            if let Some(dir) = Path::new(NM_UNMANAGED).parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let operation = format!("Adopt {} from NetworkManager", interface);
+            let conf = provenance::stamp(&with_nm_unmanaged(&conf, interface), &operation);
+            secure_file::write_public(NM_UNMANAGED, conf)
+                .context("Failed to mark the interface unmanaged")?;
+            run(
+                "/usr/bin/nmcli",
+                &["device", "set", interface, "managed", "no"],
+            )
+            .await?;
+        }
+        Manager::Dhcpcd if foreign.source == "dhcpcd.service" => {
+            let mut conf = fs::read_to_string(DHCPCD_CONF).unwrap_or_default();
+            if !conf.is_empty() && !conf.ends_with('\n') {
+                conf.push('\n');
+            }
+            conf.push_str(&format!(
+                "# Adopted by lantern\ndenyinterfaces {}\n",
+                interface
+            ));
+            let conf = provenance::stamp(&conf, &format!("Adopt {} from dhcpcd", interface));
+            secure_file::write_public(DHCPCD_CONF, conf).context("Failed to update dhcpcd.conf")?;
+            run("/usr/bin/dhcpcd", &["-k", interface]).await?;
+        }
+        Manager::Netctl if foreign.source.starts_with("netctl@") => {
+            let profile = unit_instance(&foreign.source)
+                .ok_or_else(|| anyhow!("Unexpected unit {}", foreign.source))?;
+            sanitize::argument(&profile)?;
+            run("/usr/bin/netctl", &["disable", &profile]).await?;
+            run("/usr/bin/netctl", &["stop", &profile]).await?;
+        }
+        Manager::Dhcpcd | Manager::Netctl => {
+            sanitize::argument(&foreign.source)?;
+            run("/usr/bin/systemctl", &["disable", "--now", &foreign.source]).await?;
+        }
+        Manager::Wicked => {
+            let contents = fs::read_to_string(&foreign.source)
+                .with_context(|| format!("Failed to read {}", foreign.source))?;
+            let operation = format!("Adopt {} from wicked", interface);
+            let contents = provenance::stamp(&with_startmode_off(&contents), &operation);
+            secure_file::write_public(&foreign.source, contents)?;
+            run("/usr/sbin/wicked", &["ifdown", interface]).await?;
+        }
+    }
+    Ok(())
+}
+
+/// networkd has to be running to take over, which it often isn't on a
+/// machine another manager ran.
+pub async fn start_networkd() -> Result<()> {
+    run(
+        "/usr/bin/systemctl",
+        &["enable", "--now", "systemd-networkd.service"],
+    )
+    .await
+    .map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_other_managers_config() {
+        let devices = "eth0:connected:Wired connection 1\nwlan0:connected:Caf\\:e\nlo:unmanaged:\nwg0:unmanaged:--\n";
+        assert_eq!(
+            parse_nmcli_devices(devices),
+            vec![
+                ("eth0".to_string(), "Wired connection 1".to_string()),
+                ("wlan0".to_string(), "Caf:e".to_string()),
+            ]
+        );
+        let manual = "ipv4.method:manual\nipv4.addresses:192.168.1.5/24\nipv4.gateway:192.168.1.1\nipv4.dns:1.1.1.1,9.9.9.9\n";
+        assert_eq!(
+            parse_nm_connection(manual).summary(),
+            "192.168.1.5/24, via 192.168.1.1, DNS 1.1.1.1, 9.9.9.9"
+        );
+        assert!(parse_nm_connection("ipv4.method:auto\n").dhcp);
+
+        let conf = "hostname\ndenyinterfaces veth* docker0\ninterface eth0\nstatic ip_address=10.0.0.5/24\nstatic routers=10.0.0.1\nstatic domain_name_servers=10.0.0.1 1.1.1.1\ninterface wlan0\n";
+        let (denied, eth0) = parse_dhcpcd_conf(conf, "eth0");
+        assert!(!denied);
+        assert_eq!(eth0.address.as_deref(), Some("10.0.0.5/24"));
+        assert_eq!(eth0.dns, vec!["10.0.0.1", "1.1.1.1"]);
+        assert!(parse_dhcpcd_conf(conf, "wlan0").1.dhcp);
+        assert!(parse_dhcpcd_conf(conf, "veth12ab").0);
+
+        let profile = "Description='Office'\nInterface=enp3s0\nConnection=ethernet\nIP=static\nAddress=('192.168.7.20/24')\nGateway='192.168.7.1'\nDNS=('192.168.7.1' \"8.8.8.8\")\n";
+        let (interface, imported) = parse_netctl_profile(profile);
+        assert_eq!(interface.as_deref(), Some("enp3s0"));
+        assert_eq!(
+            imported.summary(),
+            "192.168.7.20/24, via 192.168.7.1, DNS 192.168.7.1, 8.8.8.8"
+        );
+
+        let ifcfg = "BOOTPROTO='static'\nSTARTMODE='auto'\nIPADDR='192.168.3.4'\nPREFIXLEN='24'\n";
+        let (started, imported) = parse_ifcfg(ifcfg, "default 192.168.3.1 - -\n");
+        assert!(started);
+        assert_eq!(imported.summary(), "192.168.3.4/24, via 192.168.3.1");
+        let off = with_startmode_off(ifcfg);
+        assert!(off.contains("STARTMODE='off'") && !off.contains("'auto'"));
+        assert!(!parse_ifcfg(&off, "").0);
+
+        let nm = with_nm_unmanaged("[keyfile]\nunmanaged-devices=interface-name:eth1\n", "eth0");
+        assert!(nm.ends_with("unmanaged-devices=interface-name:eth1;interface-name:eth0\n"));
+        assert_eq!(with_nm_unmanaged(&nm, "eth0"), nm);
+        assert_eq!(
+            unit_instance("netctl@home\\x2dwifi.service").as_deref(),
+            Some("home-wifi")
+        );
+    }
+}
@@ -0,0 +1,213 @@
+// src/tcp.rs - System-wide TCP health: retransmits, socket states and memory pressure
+use std::collections::BTreeMap;
+use std::time::Instant;
+use tokio::fs;
+
+// Kernel TCP states as numbered in /proc/net/tcp (include/net/tcp_states.h)
+const TCP_STATES: [&str; 11] = [
+    "established",
+    "syn-sent",
+    "syn-recv",
+    "fin-wait1",
+    "fin-wait2",
+    "time-wait",
+    "close",
+    "close-wait",
+    "last-ack",
+    "listen",
+    "closing",
+];
+
+/// One reading of the kernel's TCP counters
+#[derive(Debug, Clone)]
+pub struct TcpSnapshot {
+    pub taken_at: Instant,
+    pub out_segments: u64,
+    pub retransmitted: u64,
+    pub states: BTreeMap<&'static str, usize>,
+    pub orphans: u64,
+    pub memory_pages: u64,
+    pub memory_limits: Option<(u64, u64, u64)>, // tcp_mem: low, pressure, high (pages)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemoryPressure {
+    Normal,
+    Pressure,  // Above tcp_mem[1]: the kernel trims socket buffers
+    Exhausted, // At tcp_mem[2]: new allocations fail
+}
+
+/// The latest snapshot and the retransmit rate since the one before it.
+#[derive(Debug, Clone, Default)]
+pub struct TcpHealth {
+    pub latest: Option<TcpSnapshot>,
+    pub retransmit_percent: Option<f64>,
+    pub retransmits_per_sec: Option<f64>,
+}
+
+impl TcpHealth {
+    pub fn record(&mut self, snapshot: TcpSnapshot) {
+        if let Some(previous) = &self.latest {
+            let seconds = snapshot
+                .taken_at
+                .duration_since(previous.taken_at)
+                .as_secs_f64();
+            let sent = snapshot.out_segments.saturating_sub(previous.out_segments);
+            let retransmitted = snapshot
+                .retransmitted
+                .saturating_sub(previous.retransmitted);
+            self.retransmit_percent =
+                (sent > 0).then(|| retransmitted as f64 * 100.0 / sent as f64);
+            self.retransmits_per_sec = (seconds > 0.0).then(|| retransmitted as f64 / seconds);
+        }
+        self.latest = Some(snapshot);
+    }
+
+    pub fn memory_pressure(&self) -> MemoryPressure {
+        let Some(snapshot) = &self.latest else {
+            return MemoryPressure::Normal;
+        };
+        match snapshot.memory_limits {
+            Some((_, _, high)) if snapshot.memory_pages >= high => MemoryPressure::Exhausted,
+            Some((_, pressure, _)) if snapshot.memory_pages >= pressure => MemoryPressure::Pressure,
+            _ => MemoryPressure::Normal,
+        }
+    }
+}
+
+pub async fn read_snapshot() -> TcpSnapshot {
+    let snmp = fs::read_to_string("/proc/net/snmp")
+        .await
+        .unwrap_or_default();
+    let (out_segments, retransmitted) = parse_snmp_tcp(&snmp).unwrap_or_default();
+
+    let mut states = BTreeMap::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        if let Ok(contents) = fs::read_to_string(table).await {
+            count_socket_states(&contents, &mut states);
+        }
+    }
+
+    let sockstat = fs::read_to_string("/proc/net/sockstat")
+        .await
+        .unwrap_or_default();
+    let (orphans, memory_pages) = parse_sockstat(&sockstat);
+    let memory_limits = fs::read_to_string("/proc/sys/net/ipv4/tcp_mem")
+        .await
+        .ok()
+        .and_then(|limits| parse_tcp_mem(&limits));
+
+    TcpSnapshot {
+        taken_at: Instant::now(),
+        out_segments,
+        retransmitted,
+        states,
+        orphans,
+        memory_pages,
+        memory_limits,
+    }
+}
+
+/// OutSegs and RetransSegs from the "Tcp:" header/value line pair.
+fn parse_snmp_tcp(snmp: &str) -> Option<(u64, u64)> {
+    let mut tcp_lines = snmp.lines().filter(|line| line.starts_with("Tcp:"));
+    let header: Vec<&str> = tcp_lines.next()?.split_whitespace().collect();
+    let values: Vec<&str> = tcp_lines.next()?.split_whitespace().collect();
+    let field = |name: &str| -> Option<u64> {
+        let index = header.iter().position(|field| *field == name)?;
+        values.get(index)?.parse().ok()
+    };
+    Some((field("OutSegs")?, field("RetransSegs")?))
+}
+
+fn count_socket_states(table: &str, states: &mut BTreeMap<&'static str, usize>) {
+    for line in table.lines().skip(1) {
+        // sl local_address rem_address st ...
+        let Some(state) = line.split_whitespace().nth(3) else {
+            continue;
+        };
+        let Ok(state) = usize::from_str_radix(state, 16) else {
+            continue;
+        };
+        if let Some(name) = state.checked_sub(1).and_then(|index| TCP_STATES.get(index)) {
+            *states.entry(*name).or_insert(0) += 1;
+        }
+    }
+}
+
+/// Orphaned sockets and memory pages from "TCP: inuse 5 orphan 0 tw 2 alloc 7 mem 1".
+fn parse_sockstat(sockstat: &str) -> (u64, u64) {
+    let Some(line) = sockstat.lines().find(|line| line.starts_with("TCP:")) else {
+        return (0, 0);
+    };
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let value = |name: &str| {
+        fields
+            .iter()
+            .position(|field| *field == name)
+            .and_then(|index| fields.get(index + 1)?.parse().ok())
+            .unwrap_or(0)
+    };
+    (value("orphan"), value("mem"))
+}
+
+fn parse_tcp_mem(limits: &str) -> Option<(u64, u64, u64)> {
+    let mut values = limits.split_whitespace().map(|value| value.parse().ok());
+    Some((values.next()??, values.next()??, values.next()??))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn parses_proc_files() {
+        let snmp = "Ip: Forwarding DefaultTTL\nIp: 1 64\n\
+            Tcp: RtoAlgorithm RtoMin RtoMax MaxConn ActiveOpens PassiveOpens AttemptFails EstabResets CurrEstab InSegs OutSegs RetransSegs InErrs OutRsts InCsumErrors\n\
+            Tcp: 1 200 120000 -1 100 20 3 4 12 50000 48000 96 0 10 0\n";
+        assert_eq!(parse_snmp_tcp(snmp), Some((48000, 96)));
+
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue\n\
+               0: 0100007F:0277 00000000:0000 0A 00000000:00000000\n\
+               1: 0200A8C0:D2F0 0101A8C0:01BB 01 00000000:00000000\n\
+               2: 0200A8C0:D2F2 0101A8C0:01BB 01 00000000:00000000\n\
+               3: 0200A8C0:D2F4 0101A8C0:01BB 06 00000000:00000000\n";
+        let mut states = BTreeMap::new();
+        count_socket_states(table, &mut states);
+        assert_eq!(states["established"], 2);
+        assert_eq!(states["listen"], 1);
+        assert_eq!(states["time-wait"], 1);
+
+        assert_eq!(
+            parse_sockstat("sockets: used 300\nTCP: inuse 5 orphan 2 tw 2 alloc 7 mem 41\n"),
+            (2, 41)
+        );
+        assert_eq!(
+            parse_tcp_mem("45000\t60000\t90000\n"),
+            Some((45000, 60000, 90000))
+        );
+    }
+
+    #[test]
+    fn computes_retransmit_rate_and_pressure() {
+        let start = Instant::now();
+        let snapshot = |at: u64, out, retransmitted, memory_pages| TcpSnapshot {
+            taken_at: start + Duration::from_secs(at),
+            out_segments: out,
+            retransmitted,
+            states: BTreeMap::new(),
+            orphans: 0,
+            memory_pages,
+            memory_limits: Some((100, 200, 300)),
+        };
+
+        let mut health = TcpHealth::default();
+        health.record(snapshot(0, 1000, 10, 50));
+        assert_eq!(health.retransmit_percent, None);
+        health.record(snapshot(2, 3000, 50, 250));
+        assert_eq!(health.retransmit_percent, Some(2.0));
+        assert_eq!(health.retransmits_per_sec, Some(20.0));
+        assert_eq!(health.memory_pressure(), MemoryPressure::Pressure);
+    }
+}
@@ -0,0 +1,517 @@
+// src/throughput.rs - TCP and UDP throughput and jitter between two lantern instances (`lantern perf`)
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+
+pub const DEFAULT_PORT: u16 = 5202;
+/// UDP tests send this fast unless told otherwise, in bits per second
+pub const DEFAULT_BITRATE: u64 = 10_000_000;
+/// Longest test a server agrees to run
+const MAX_DURATION: Duration = Duration::from_secs(600);
+/// Written at once in TCP tests
+const BLOCK: usize = 128 * 1024;
+/// UDP payload; fits a 1500-byte MTU with IPv6 and UDP headers
+const DATAGRAM: usize = 1400;
+/// Sequence number and send time at the start of every datagram
+const HEADER: usize = 16;
+/// How long datagrams still in flight are waited for once the sender is done
+const GRACE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// What the client asks the server for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub protocol: Protocol,
+    pub millis: u64,
+    /// The server sends and the client receives
+    pub reverse: bool,
+    /// UDP only, in bits per second
+    pub bitrate: u64,
+}
+
+impl Request {
+    fn duration(&self) -> Duration {
+        Duration::from_millis(self.millis)
+    }
+
+    pub fn describe(&self) -> String {
+        let protocol = match self.protocol {
+            Protocol::Tcp => "TCP",
+            Protocol::Udp => "UDP",
+        };
+        let direction = if self.reverse { "download" } else { "upload" };
+        format!("{} {}", protocol, direction)
+    }
+}
+
+/// What the receiving side measured; the sender gets a copy
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub bytes: u64,
+    pub millis: u64,
+    /// UDP only from here on
+    pub datagrams: u64,
+    pub lost: u64,
+    pub out_of_order: u64,
+    pub jitter_micros: u64,
+}
+
+impl Report {
+    pub fn mbps(&self) -> f64 {
+        if self.millis == 0 {
+            return 0.0;
+        }
+        self.bytes as f64 * 8.0 / (self.millis as f64 * 1000.0)
+    }
+
+    pub fn summary(&self, protocol: Protocol) -> String {
+        let rate = match self.mbps() {
+            mbps if mbps >= 1000.0 => format!("{:.2} Gbit/s", mbps / 1000.0),
+            mbps => format!("{:.1} Mbit/s", mbps),
+        };
+        let mut summary = format!(
+            "{} over {:.1} s ({:.1} MB)",
+            rate,
+            self.millis as f64 / 1000.0,
+            self.bytes as f64 / 1_000_000.0
+        );
+        if protocol == Protocol::Udp {
+            let sent = self.datagrams + self.lost;
+            let loss = if sent == 0 {
+                0.0
+            } else {
+                self.lost as f64 * 100.0 / sent as f64
+            };
+            summary.push_str(&format!(
+                ", jitter {:.2} ms, {}/{} lost ({:.2}%)",
+                self.jitter_micros as f64 / 1000.0,
+                self.lost,
+                sent,
+                loss
+            ));
+            if self.out_of_order > 0 {
+                summary.push_str(&format!(", {} out of order", self.out_of_order));
+            }
+        }
+        summary
+    }
+}
+
+/// The control connection: one JSON line per message. In TCP tests the data
+/// follows on the same connection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Message {
+    Request(Request),
+    Ready {
+        udp_port: Option<u16>,
+    },
+    /// The UDP sender is done, after this many datagrams
+    Done {
+        sent: u64,
+    },
+    Report(Report),
+    Refused(String),
+}
+
+type Control = BufReader<TcpStream>;
+
+async fn send(control: &mut Control, message: &Message) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    control.get_mut().write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+async fn receive(control: &mut Control) -> Result<Message> {
+    let mut line = String::new();
+    if control.read_line(&mut line).await? == 0 {
+        bail!("The other side closed the connection");
+    }
+    serde_json::from_str(&line).context("Unexpected message from the other side")
+}
+
+/// Interarrival jitter as RTP computes it (RFC 3550 6.4.1): a running
+/// average of how much the transit time changes from one datagram to the
+/// next. The two clocks never need to agree, only to tick at the same rate.
+#[derive(Debug, Default)]
+struct Jitter {
+    last_transit: Option<i64>,
+    micros: f64,
+}
+
+impl Jitter {
+    fn add(&mut self, transit: i64) {
+        if let Some(last) = self.last_transit {
+            let change = (transit - last).abs() as f64;
+            self.micros += (change - self.micros) / 16.0;
+        }
+        self.last_transit = Some(transit);
+    }
+}
+
+/// Counts datagrams as they arrive
+#[derive(Debug)]
+struct Datagrams {
+    start: Instant,
+    first: Option<Instant>,
+    last: Option<Instant>,
+    next_sequence: u64,
+    received: u64,
+    bytes: u64,
+    out_of_order: u64,
+    jitter: Jitter,
+}
+
+impl Datagrams {
+    fn new(start: Instant) -> Self {
+        Self {
+            start,
+            first: None,
+            last: None,
+            next_sequence: 0,
+            received: 0,
+            bytes: 0,
+            out_of_order: 0,
+            jitter: Jitter::default(),
+        }
+    }
+
+    fn record(&mut self, datagram: &[u8], now: Instant) {
+        let Some((sequence, sent_micros)) = parse_datagram(datagram) else {
+            return;
+        };
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+        self.received += 1;
+        self.bytes += datagram.len() as u64;
+        if sequence < self.next_sequence {
+            self.out_of_order += 1;
+        } else {
+            self.next_sequence = sequence + 1;
+        }
+        let arrived = now.duration_since(self.start).as_micros() as i64;
+        self.jitter.add(arrived - sent_micros as i64);
+    }
+
+    fn report(&self, sent: u64) -> Report {
+        let millis = match (self.first, self.last) {
+            (Some(first), Some(last)) => last.duration_since(first).as_millis() as u64,
+            _ => 0,
+        };
+        Report {
+            bytes: self.bytes,
+            millis,
+            datagrams: self.received,
+            lost: sent.saturating_sub(self.received),
+            out_of_order: self.out_of_order,
+            jitter_micros: self.jitter.micros as u64,
+        }
+    }
+}
+
+fn datagram(buf: &mut [u8], sequence: u64, sent_micros: u64) {
+    buf[..8].copy_from_slice(&sequence.to_be_bytes());
+    buf[8..HEADER].copy_from_slice(&sent_micros.to_be_bytes());
+}
+
+fn parse_datagram(buf: &[u8]) -> Option<(u64, u64)> {
+    let sequence = u64::from_be_bytes(buf.get(..8)?.try_into().ok()?);
+    let sent_micros = u64::from_be_bytes(buf.get(8..HEADER)?.try_into().ok()?);
+    Some((sequence, sent_micros))
+}
+
+/// Write as fast as TCP lets us for `duration`, then close our half so the
+/// receiver sees the end
+async fn send_tcp(control: &mut Control, duration: Duration) -> Result<()> {
+    let block = vec![0u8; BLOCK];
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        control.get_mut().write_all(&block).await?;
+    }
+    control.get_mut().shutdown().await?;
+    Ok(())
+}
+
+async fn receive_tcp(control: &mut Control) -> Result<Report> {
+    let mut buf = vec![0u8; BLOCK];
+    let mut bytes = 0u64;
+    let mut first = None;
+    loop {
+        let read = control.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        first.get_or_insert_with(Instant::now);
+        bytes += read as u64;
+    }
+    Ok(Report {
+        bytes,
+        millis: first.map_or(0, |first| first.elapsed().as_millis() as u64),
+        ..Report::default()
+    })
+}
+
+/// Send datagrams at `bitrate` for `duration` and say how many went out
+async fn send_udp(socket: &UdpSocket, duration: Duration, bitrate: u64) -> Result<u64> {
+    let mut buf = vec![0u8; DATAGRAM];
+    let start = Instant::now();
+    let mut sent = 0u64;
+    while start.elapsed() < duration {
+        // As many as the rate allows by now; a short nap when ahead
+        let due = start.elapsed().as_secs_f64() * bitrate as f64 / (DATAGRAM * 8) as f64;
+        if sent as f64 >= due {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            continue;
+        }
+        datagram(&mut buf, sent, start.elapsed().as_micros() as u64);
+        socket.send(&buf).await?;
+        sent += 1;
+    }
+    Ok(sent)
+}
+
+/// Count datagrams until the sender says it's done, then wait out the ones
+/// still in flight
+async fn receive_udp(socket: &UdpSocket, control: &mut Control) -> Result<Report> {
+    let mut buf = vec![0u8; DATAGRAM * 2];
+    let mut datagrams = Datagrams::new(Instant::now());
+    let done = receive(control);
+    tokio::pin!(done);
+    let sent = loop {
+        tokio::select! {
+            message = &mut done => match message? {
+                Message::Done { sent } => break sent,
+                other => bail!("Expected the end of the test, got {:?}", other),
+            },
+            received = socket.recv(&mut buf) => {
+                let len = received?;
+                datagrams.record(&buf[..len], Instant::now());
+            }
+        }
+    };
+    while let Ok(received) = tokio::time::timeout(GRACE, socket.recv(&mut buf)).await {
+        let len = received?;
+        datagrams.record(&buf[..len], Instant::now());
+    }
+    Ok(datagrams.report(sent))
+}
+
+/// Send or receive over UDP; the receiver measures and both end up with
+/// its report
+async fn run_udp(
+    socket: &UdpSocket,
+    control: &mut Control,
+    request: &Request,
+    sending: bool,
+) -> Result<Report> {
+    if sending {
+        let sent = send_udp(socket, request.duration(), request.bitrate).await?;
+        send(control, &Message::Done { sent }).await?;
+        match receive(control).await? {
+            Message::Report(report) => Ok(report),
+            other => bail!("Expected a report, got {:?}", other),
+        }
+    } else {
+        let report = receive_udp(socket, control).await?;
+        send(control, &Message::Report(report.clone())).await?;
+        Ok(report)
+    }
+}
+
+async fn run_tcp(control: &mut Control, request: &Request, sending: bool) -> Result<Report> {
+    if sending {
+        send_tcp(control, request.duration()).await?;
+        match receive(control).await? {
+            Message::Report(report) => Ok(report),
+            other => bail!("Expected a report, got {:?}", other),
+        }
+    } else {
+        let report = receive_tcp(control).await?;
+        send(control, &Message::Report(report.clone())).await?;
+        Ok(report)
+    }
+}
+
+/// Run one test against a `lantern perf serve` on `host`
+pub async fn connect(host: &str, port: u16, request: Request) -> Result<Report> {
+    let stream = TcpStream::connect((host, port))
+        .await
+        .with_context(|| format!("Failed to reach lantern perf serve on {}:{}", host, port))?;
+    let server = stream.peer_addr()?;
+    let local = stream.local_addr()?.ip();
+    let mut control = BufReader::new(stream);
+    send(&mut control, &Message::Request(request.clone())).await?;
+    let udp_port = match receive(&mut control).await? {
+        Message::Ready { udp_port } => udp_port,
+        Message::Refused(reason) => bail!("The server refused the test: {}", reason),
+        other => bail!("Expected the server to be ready, got {:?}", other),
+    };
+
+    match request.protocol {
+        Protocol::Tcp => run_tcp(&mut control, &request, !request.reverse).await,
+        Protocol::Udp => {
+            let udp_port = udp_port.ok_or_else(|| anyhow!("The server opened no UDP port"))?;
+            let socket = UdpSocket::bind(SocketAddr::new(local, 0)).await?;
+            socket
+                .connect(SocketAddr::new(server.ip(), udp_port))
+                .await?;
+            if request.reverse {
+                // The server learns where to send from these; one is enough
+                for _ in 0..3 {
+                    socket.send(&[]).await?;
+                }
+            }
+            run_udp(&socket, &mut control, &request, !request.reverse).await
+        }
+    }
+}
+
+/// Answer one client: the other half of `connect`
+async fn serve_one(stream: TcpStream) -> Result<(Request, Report)> {
+    let peer = stream.peer_addr()?;
+    let local = stream.local_addr()?.ip();
+    let mut control = BufReader::new(stream);
+    let request = match receive(&mut control).await? {
+        Message::Request(request) => request,
+        other => bail!("Expected a test request, got {:?}", other),
+    };
+    if request.duration() > MAX_DURATION || request.millis == 0 || request.bitrate == 0 {
+        let reason = format!("tests run 1 ms to {} s", MAX_DURATION.as_secs());
+        send(&mut control, &Message::Refused(reason.clone())).await?;
+        bail!("Refused {}: {}", request.describe(), reason);
+    }
+
+    let report = match request.protocol {
+        Protocol::Tcp => {
+            send(&mut control, &Message::Ready { udp_port: None }).await?;
+            run_tcp(&mut control, &request, request.reverse).await?
+        }
+        Protocol::Udp => {
+            let socket = UdpSocket::bind(SocketAddr::new(local, 0)).await?;
+            let udp_port = Some(socket.local_addr()?.port());
+            send(&mut control, &Message::Ready { udp_port }).await?;
+            if request.reverse {
+                // Only ever back to the client's own address, so the server
+                // can't be pointed at someone else
+                let mut hello = [0u8; 1];
+                let client = loop {
+                    let (_, from) =
+                        tokio::time::timeout(MAX_DURATION, socket.recv_from(&mut hello))
+                            .await
+                            .context("The client never sent its UDP address")??;
+                    if from.ip() == peer.ip() {
+                        break from;
+                    }
+                };
+                socket.connect(client).await?;
+            }
+            run_udp(&socket, &mut control, &request, request.reverse).await?
+        }
+    };
+    Ok((request, report))
+}
+
+/// Accept tests one at a time until interrupted, printing each result
+pub async fn serve(address: IpAddr, port: u16, mut on_result: impl FnMut(String)) -> Result<()> {
+    let listener = TcpListener::bind((address, port))
+        .await
+        .with_context(|| format!("Failed to listen on port {}", port))?;
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let line = match serve_one(stream).await {
+            Ok((request, report)) => format!(
+                "{}: {}, {}",
+                peer.ip(),
+                request.describe(),
+                report.summary(request.protocol)
+            ),
+            Err(e) => format!("{}: {:#}", peer.ip(), e),
+        };
+        on_result(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_loss_reordering_and_jitter() {
+        let start = Instant::now();
+        let mut datagrams = Datagrams::new(start);
+        let mut buf = vec![0u8; DATAGRAM];
+        // Sent every 1 ms; 2 arrives late, after 3, and 4 never does
+        for (sequence, arrived) in [(0, 10), (1, 11), (3, 13), (2, 15), (5, 15)] {
+            datagram(&mut buf, sequence, sequence * 1000);
+            datagrams.record(&buf, start + Duration::from_millis(arrived));
+        }
+        datagrams.record(&[1, 2], start); // Too short to be a datagram
+        let report = datagrams.report(6);
+        assert_eq!(
+            (report.datagrams, report.lost, report.out_of_order),
+            (5, 1, 1)
+        );
+        assert_eq!(report.millis, 5);
+        assert!(report.jitter_micros > 0);
+        assert_eq!(report.bytes, 5 * DATAGRAM as u64);
+
+        let steady = Report {
+            bytes: 125_000_000,
+            millis: 10_000,
+            datagrams: 998,
+            lost: 2,
+            ..Report::default()
+        };
+        assert_eq!(
+            steady.summary(Protocol::Udp),
+            "100.0 Mbit/s over 10.0 s (125.0 MB), jitter 0.00 ms, 2/1000 lost (0.20%)"
+        );
+    }
+
+    #[tokio::test]
+    async fn client_and_server_agree() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let _ = serve_one(stream).await;
+            }
+        });
+
+        for (protocol, reverse) in [
+            (Protocol::Tcp, false),
+            (Protocol::Tcp, true),
+            (Protocol::Udp, false),
+            (Protocol::Udp, true),
+        ] {
+            let request = Request {
+                protocol,
+                millis: 200,
+                reverse,
+                bitrate: DEFAULT_BITRATE,
+            };
+            let report = connect("127.0.0.1", port, request).await.unwrap();
+            assert!(report.bytes > 0, "{:?} reverse={}", protocol, reverse);
+        }
+
+        let refused = Request {
+            protocol: Protocol::Tcp,
+            millis: 0,
+            reverse: false,
+            bitrate: DEFAULT_BITRATE,
+        };
+        let error = connect("127.0.0.1", port, refused).await.unwrap_err();
+        assert!(error.to_string().contains("refused"));
+    }
+}
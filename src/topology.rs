@@ -0,0 +1,213 @@
+// src/topology.rs - How interfaces hang together: bridge ports, bond slaves, VLANs on their parents, veths into namespaces
+use crate::containers::ContainerLink;
+use crate::link_kind::LinkKind;
+use crate::network::Interface;
+use std::collections::{HashMap, HashSet};
+
+/// One row of the tree, ready to draw
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub prefix: String, // "├─ ", "│  └─ ", ...
+    pub name: String,
+    pub kind: LinkKind,
+    pub wireless: bool,
+    pub up: bool,
+    pub relation: Option<&'static str>, // How it hangs off the row above
+    pub notes: Vec<String>,
+}
+
+/// Where a link goes in the tree: under its master (bridge, bond) first, as
+/// that is what carries its traffic, else under its lower link
+fn tree_parent(interface: &Interface) -> Option<&str> {
+    interface.master.as_deref().or(interface.lower.as_deref())
+}
+
+fn relation(interface: &Interface, parent: &Interface) -> &'static str {
+    if interface.master.as_deref() == Some(parent.name.as_str()) {
+        return match parent.kind {
+            LinkKind::Bridge => "port",
+            LinkKind::Bond => "slave",
+            _ => "member",
+        };
+    }
+    match interface.kind {
+        LinkKind::Vlan => "VLAN",
+        LinkKind::Veth => "peer",
+        _ => "on top",
+    }
+}
+
+/// The interfaces as a forest, in list order. Links whose parent isn't listed
+/// are roots; a veth pair in one namespace (each the other's lower link)
+/// hangs under whichever comes first.
+pub fn build(interfaces: &[Interface], containers: &HashMap<String, ContainerLink>) -> Vec<Node> {
+    let listed: HashSet<&str> = interfaces.iter().map(|i| i.name.as_str()).collect();
+    let mut children: HashMap<&str, Vec<&Interface>> = HashMap::new();
+    for interface in interfaces {
+        if let Some(parent) = tree_parent(interface).filter(|p| listed.contains(p)) {
+            children.entry(parent).or_default().push(interface);
+        }
+    }
+
+    let mut forest = Forest {
+        children,
+        containers,
+        placed: HashSet::new(),
+        nodes: Vec::new(),
+    };
+    let roots = interfaces
+        .iter()
+        .filter(|i| !tree_parent(i).is_some_and(|p| listed.contains(p)));
+    for root in roots {
+        forest.place(root, None, "", "");
+    }
+    // Only cycles are left
+    for interface in interfaces {
+        forest.place(interface, None, "", "");
+    }
+    forest.nodes
+}
+
+struct Forest<'a> {
+    children: HashMap<&'a str, Vec<&'a Interface>>,
+    containers: &'a HashMap<String, ContainerLink>,
+    placed: HashSet<&'a str>,
+    nodes: Vec<Node>,
+}
+
+impl<'a> Forest<'a> {
+    /// Add `interface` and everything below it, unless already placed
+    fn place(
+        &mut self,
+        interface: &'a Interface,
+        parent: Option<&Interface>,
+        prefix: &str,
+        continuation: &str,
+    ) {
+        if !self.placed.insert(interface.name.as_str()) {
+            return;
+        }
+
+        let mut notes = Vec::new();
+        // Under its master, a VLAN or macvlan still says what it sits on
+        if let (Some(lower), Some(_)) = (&interface.lower, &interface.master) {
+            if interface.kind != LinkKind::Veth {
+                notes.push(format!("on {}", lower));
+            }
+        }
+        if let Some(container) = self.containers.get(&interface.name) {
+            notes.push(format!("⇄ {} ({})", container.name, container.runtime));
+        } else if let Some(netns) = interface.peer_netns {
+            notes.push(format!("⇄ netns {}", netns));
+        }
+        self.nodes.push(Node {
+            prefix: prefix.to_string(),
+            name: interface.name.clone(),
+            kind: interface.kind,
+            wireless: interface.wifi_info.is_some(),
+            up: interface.admin_up && interface.state != "DOWN",
+            relation: parent.map(|parent| relation(interface, parent)),
+            notes,
+        });
+
+        let below: Vec<&'a Interface> = self
+            .children
+            .get(interface.name.as_str())
+            .into_iter()
+            .flatten()
+            .filter(|child| !self.placed.contains(child.name.as_str()))
+            .copied()
+            .collect();
+        let last = below.len().saturating_sub(1);
+        for (i, child) in below.into_iter().enumerate() {
+            let (branch, next) = if i == last {
+                ("└─ ", "   ")
+            } else {
+                ("├─ ", "│  ")
+            };
+            self.place(
+                child,
+                Some(interface),
+                &format!("{}{}", continuation, branch),
+                &format!("{}{}", continuation, next),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::InterfaceStats;
+
+    fn link(name: &str, kind: LinkKind, master: Option<&str>, lower: Option<&str>) -> Interface {
+        Interface {
+            name: name.to_string(),
+            mac_address: String::new(),
+            state: "UP".to_string(),
+            mtu: 1500,
+            ipv4_addresses: Vec::new(),
+            ipv6_addresses: Vec::new(),
+            ipv6_info: None,
+            gateway: None,
+            ipv6_gateway: None,
+            dns_servers: Vec::new(),
+            stats: InterfaceStats::default(),
+            wifi_info: None,
+            hardware: None,
+            admin_up: true,
+            carrier: Some(true),
+            carrier_changes: None,
+            promiscuous: false,
+            monitor: false,
+            kind,
+            master: master.map(str::to_string),
+            lower: lower.map(str::to_string),
+            peer_netns: None,
+        }
+    }
+
+    #[test]
+    fn nests_ports_vlans_and_veth_pairs() {
+        let mut container_side = link("veth9", LinkKind::Veth, Some("br0"), None);
+        container_side.peer_netns = Some(1);
+        let interfaces = vec![
+            link("eth0", LinkKind::Physical, Some("bond0"), None),
+            link("eth1", LinkKind::Physical, Some("bond0"), None),
+            link("bond0", LinkKind::Bond, None, None),
+            link("bond0.10", LinkKind::Vlan, Some("br0"), Some("bond0")),
+            link("br0", LinkKind::Bridge, None, None),
+            container_side,
+            link("vetha", LinkKind::Veth, None, Some("vethb")),
+            link("vethb", LinkKind::Veth, None, Some("vetha")),
+            link("wg0", LinkKind::WireGuard, None, None),
+        ];
+
+        let rows: Vec<(String, Option<&str>, Vec<String>)> = build(&interfaces, &HashMap::new())
+            .into_iter()
+            .map(|node| {
+                (
+                    format!("{}{}", node.prefix, node.name),
+                    node.relation,
+                    node.notes,
+                )
+            })
+            .collect();
+        let expected = [
+            ("bond0", None, vec![]),
+            ("├─ eth0", Some("slave"), vec![]),
+            ("└─ eth1", Some("slave"), vec![]),
+            ("br0", None, vec![]),
+            ("├─ bond0.10", Some("port"), vec!["on bond0".to_string()]),
+            ("└─ veth9", Some("port"), vec!["⇄ netns 1".to_string()]),
+            ("wg0", None, vec![]),
+            ("vetha", None, vec![]),
+            ("└─ vethb", Some("peer"), vec![]),
+        ];
+        let expected: Vec<(String, Option<&str>, Vec<String>)> = expected
+            .into_iter()
+            .map(|(row, relation, notes)| (row.to_string(), relation, notes))
+            .collect();
+        assert_eq!(rows, expected);
+    }
+}
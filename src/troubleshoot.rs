@@ -0,0 +1,267 @@
+// src/troubleshoot.rs - "Why is my network broken?": layers checked bottom up, stopping at the first that fails
+use crate::command::TimedOutput;
+use crate::network::Interface;
+use crate::reachability::{self, Family, Outcome};
+use tokio::process::Command;
+
+/// The layers, in the order each needs the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Link,
+    Address,
+    Gateway,
+    Dns,
+    Http,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 5] = [
+        Layer::Link,
+        Layer::Address,
+        Layer::Gateway,
+        Layer::Dns,
+        Layer::Http,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Layer::Link => "Link",
+            Layer::Address => "Address",
+            Layer::Gateway => "Gateway",
+            Layer::Dns => "DNS",
+            Layer::Http => "HTTP",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis {
+    pub interface: String,
+    /// Every layer; the ones above a failure are Skipped
+    pub steps: Vec<(Layer, Outcome)>,
+    /// What to try about the first failure
+    pub fix: Option<String>,
+}
+
+impl Diagnosis {
+    /// From the outcomes of the layers tried, bottom up and ending at the
+    /// first failure; the layers above it are Skipped
+    pub fn new(interface: &Interface, tried: Vec<Outcome>) -> Self {
+        let mut steps: Vec<(Layer, Outcome)> = Layer::ALL.into_iter().zip(tried).collect();
+        if let Some(at) = steps
+            .iter()
+            .position(|(_, outcome)| matches!(outcome, Outcome::Fail(_)))
+        {
+            steps.truncate(at + 1);
+        }
+        steps.extend(
+            Layer::ALL[steps.len()..]
+                .iter()
+                .map(|layer| (*layer, Outcome::Skipped)),
+        );
+        let mut diagnosis = Diagnosis {
+            interface: interface.name.clone(),
+            steps,
+            fix: None,
+        };
+        diagnosis.fix = diagnosis.first_failure().map(|layer| fix(layer, interface));
+        diagnosis
+    }
+
+    pub fn first_failure(&self) -> Option<Layer> {
+        self.steps
+            .iter()
+            .find(|(_, outcome)| matches!(outcome, Outcome::Fail(_)))
+            .map(|(layer, _)| *layer)
+    }
+
+    /// One line for the status bar
+    pub fn summary(&self) -> String {
+        match self.first_failure() {
+            Some(layer) => format!("{}: broken at {}", self.interface, layer.label()),
+            None => format!("{}: every layer works", self.interface),
+        }
+    }
+}
+
+pub fn link(interface: &Interface) -> Outcome {
+    if !interface.admin_up {
+        Outcome::Fail("Interface is down".to_string())
+    } else if interface.no_carrier() {
+        Outcome::Fail(match interface.wifi_info {
+            Some(_) => "Not associated with any network".to_string(),
+            None => "No carrier".to_string(),
+        })
+    } else {
+        Outcome::Pass(match &interface.wifi_info {
+            Some(info) => match &info.current_network {
+                Some(network) => format!("Associated with '{}'", network.ssid),
+                None => "Up".to_string(),
+            },
+            None => "Carrier".to_string(),
+        })
+    }
+}
+
+pub fn address(interface: &Interface) -> Outcome {
+    match interface.ipv4_addresses.first() {
+        None => Outcome::Fail("No IPv4 address".to_string()),
+        Some(_) if crate::linklocal::only_link_local(&interface.ipv4_addresses) => {
+            Outcome::Fail(format!("Only link-local {}", interface.ipv4_addresses[0]))
+        }
+        Some(address) => Outcome::Pass(address.clone()),
+    }
+}
+
+/// The neighbor's MAC from `ip neigh show ADDRESS dev IFACE`, unless the
+/// kernel gave up resolving it: "192.168.1.1 lladdr 00:11:22:33:44:55 REACHABLE"
+pub fn parse_neighbor(output: &str) -> Option<String> {
+    let line = output.lines().next()?;
+    let mut words = line.split_whitespace();
+    words.find(|word| *word == "lladdr")?;
+    let mac = words.next()?;
+    (!line.contains("FAILED") && !line.contains("INCOMPLETE")).then(|| mac.to_string())
+}
+
+/// Ping the gateway so the kernel resolves it, then ask for the ARP entry;
+/// a router that drops pings still has to answer ARP
+async fn gateway(interface: &Interface) -> Outcome {
+    let Some(gateway) = &interface.gateway else {
+        return Outcome::Fail("No default gateway".to_string());
+    };
+    let name = interface.name.as_str();
+    let _ = Command::new("/usr/bin/ping")
+        .args(["-4", "-c", "1", "-W", "1", "-n", "-I", name, gateway])
+        .timed_output()
+        .await;
+    let output = Command::new("/usr/bin/ip")
+        .args(["neigh", "show", gateway, "dev", name])
+        .timed_output()
+        .await;
+    match output {
+        Ok(output) => match parse_neighbor(&String::from_utf8_lossy(&output.stdout)) {
+            Some(mac) => Outcome::Pass(format!("{} is at {}", gateway, mac)),
+            None => Outcome::Fail(format!("{} doesn't answer ARP", gateway)),
+        },
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+/// What to try when `layer` is the first to fail
+pub fn fix(layer: Layer, interface: &Interface) -> String {
+    let wifi = interface.wifi_info.is_some();
+    match layer {
+        Layer::Link if !interface.admin_up => "Bring the interface up with u".to_string(),
+        Layer::Link if wifi => {
+            "Connect to a network from the WiFi dialog (w); check the password if it drops again"
+                .to_string()
+        }
+        Layer::Link => {
+            "Check the cable and the switch port; try another cable or port".to_string()
+        }
+        Layer::Address if !interface.ipv4_addresses.is_empty() => {
+            "No DHCP server answered: retry DHCP with N, look for DHCP servers with O, or set a static address with e"
+                .to_string()
+        }
+        Layer::Address => {
+            "Retry DHCP with N, or set a static address with e; the .network file may not match this interface (F)"
+                .to_string()
+        }
+        Layer::Gateway if interface.gateway.is_none() => {
+            "Set a gateway with e, or check that the DHCP server hands out a router".to_string()
+        }
+        Layer::Gateway => {
+            "The gateway is unreachable: check its address and subnet (e), the VLAN, and that the router is on"
+                .to_string()
+        }
+        Layer::Dns if interface.dns_servers.is_empty() => {
+            "No DNS servers on this link: add some with e, or set global ones with D".to_string()
+        }
+        Layer::Dns => {
+            "The DNS servers don't answer: try others with e or D, and check `resolvectl status`"
+                .to_string()
+        }
+        Layer::Http => {
+            "Names resolve but web traffic fails: a captive portal (open a browser), a proxy or a firewall? H shows the details"
+                .to_string()
+        }
+    }
+}
+
+pub async fn run(interface: &Interface) -> Diagnosis {
+    let name = interface.name.as_str();
+    let mut tried = Vec::new();
+    for layer in Layer::ALL {
+        let outcome = match layer {
+            Layer::Link => link(interface),
+            Layer::Address => address(interface),
+            Layer::Gateway => gateway(interface).await,
+            Layer::Dns => reachability::dns(name, Family::V4).await,
+            Layer::Http => reachability::http(name, Family::V4).await,
+        };
+        let failed = matches!(outcome, Outcome::Fail(_));
+        tried.push(outcome);
+        if failed {
+            break;
+        }
+    }
+    Diagnosis::new(interface, tried)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_at_the_first_broken_layer() {
+        assert_eq!(
+            parse_neighbor("192.168.1.1 lladdr 00:11:22:33:44:55 REACHABLE\n").as_deref(),
+            Some("00:11:22:33:44:55")
+        );
+        assert_eq!(parse_neighbor("192.168.1.1  FAILED\n"), None);
+        assert_eq!(parse_neighbor(""), None);
+
+        let mut interface = Interface {
+            name: "eth0".to_string(),
+            mac_address: String::new(),
+            state: "UP".to_string(),
+            mtu: 1500,
+            ipv4_addresses: vec!["169.254.17.3/16".to_string()],
+            ipv6_addresses: Vec::new(),
+            ipv6_info: None,
+            gateway: None,
+            ipv6_gateway: None,
+            dns_servers: Vec::new(),
+            stats: Default::default(),
+            wifi_info: None,
+            hardware: None,
+            admin_up: true,
+            carrier: Some(true),
+            carrier_changes: None,
+            promiscuous: false,
+            monitor: false,
+            kind: Default::default(),
+            master: None,
+            lower: None,
+            peer_netns: None,
+        };
+        let diagnosis = Diagnosis::new(
+            &interface,
+            vec![
+                link(&interface),
+                address(&interface),
+                Outcome::Pass(String::new()),
+            ],
+        );
+        assert_eq!(diagnosis.first_failure(), Some(Layer::Address));
+        assert_eq!(diagnosis.steps[2], (Layer::Gateway, Outcome::Skipped));
+        assert!(diagnosis.fix.as_deref().unwrap().contains("No DHCP server"));
+        assert_eq!(diagnosis.summary(), "eth0: broken at Address");
+
+        interface.carrier = Some(false);
+        assert!(matches!(link(&interface), Outcome::Fail(_)));
+        assert!(fix(Layer::Link, &interface).contains("cable"));
+        interface.admin_up = false;
+        assert!(fix(Layer::Link, &interface).contains("up with u"));
+    }
+}
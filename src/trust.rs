@@ -0,0 +1,202 @@
+// src/trust.rs - Trusted networks and the firewall lantern applies on untrusted ones
+use crate::command::TimedOutput;
+use crate::config::TrustedNetworks;
+use crate::provenance;
+use crate::secure_file;
+use anyhow::Result;
+use std::fmt::Write;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use tokio::process::Command;
+
+// Rules are loaded from a file because nft takes atomic rulesets with -f
+const RULESET_PATH: &str = "/run/lantern-firewall.nft";
+
+/// The network an interface with a default route is attached to.
+#[derive(Debug, Clone, Default)]
+pub struct Uplink {
+    pub interface: String,
+    pub ssid: Option<String>,
+    pub gateway: Option<String>,
+    pub gateway_mac: Option<String>,
+}
+
+impl TrustedNetworks {
+    /// WiFi is untrusted unless its SSID or gateway is listed. Wired links
+    /// are trusted: the cable is usually under the user's control, and a
+    /// listed gateway cannot make them any more trusted than that.
+    pub fn trusts(&self, uplink: &Uplink) -> bool {
+        let Some(ssid) = &uplink.ssid else {
+            return true;
+        };
+        let gateway_listed = |value: &Option<String>| {
+            value.as_ref().is_some_and(|value| {
+                self.gateways
+                    .iter()
+                    .any(|gateway| gateway.eq_ignore_ascii_case(value))
+            })
+        };
+        self.ssids.contains(ssid)
+            || gateway_listed(&uplink.gateway)
+            || gateway_listed(&uplink.gateway_mac)
+    }
+
+    pub fn enforces_firewall(&self) -> bool {
+        self.firewall || self.kill_switch
+    }
+}
+
+/// nftables rules for the untrusted interfaces: with `firewall`, only replies
+/// and DHCP/neighbour discovery come in; with `kill_switch`, nothing but
+/// DHCP, neighbour discovery and WireGuard to the given endpoints goes out,
+/// so traffic can only leave through a tunnel. None when nothing applies.
+pub fn firewall_ruleset(
+    settings: &TrustedNetworks,
+    untrusted: &[String],
+    endpoints: &[SocketAddr],
+) -> Option<String> {
+    if untrusted.is_empty() || !settings.enforces_firewall() {
+        return None;
+    }
+    let names = untrusted
+        .iter()
+        .map(|name| format!("\"{}\"", name))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // Declaring the table first makes the delete succeed on the first load
+    let mut rules = String::from("table inet lantern {}\ndelete table inet lantern\n");
+    rules.push_str("table inet lantern {\n");
+
+    if settings.firewall {
+        let iif = format!("\t\tiifname {{ {} }}", names);
+        rules.push_str("\tchain input {\n");
+        rules.push_str("\t\ttype filter hook input priority filter; policy accept;\n");
+        let _ = writeln!(rules, "{} ct state established,related accept", iif);
+        let _ = writeln!(rules, "{} udp dport {{ 68, 546 }} accept", iif);
+        let _ = writeln!(
+            rules,
+            "{} icmpv6 type {{ nd-router-advert, nd-neighbor-solicit, nd-neighbor-advert }} accept",
+            iif
+        );
+        let _ = writeln!(rules, "{} drop", iif);
+        rules.push_str("\t}\n");
+    }
+
+    if settings.kill_switch {
+        let oif = format!("\t\toifname {{ {} }}", names);
+        rules.push_str("\tchain output {\n");
+        rules.push_str("\t\ttype filter hook output priority filter; policy accept;\n");
+        let _ = writeln!(rules, "{} udp dport {{ 67, 547 }} accept", oif);
+        let _ = writeln!(
+            rules,
+            "{} icmpv6 type {{ nd-router-solicit, nd-neighbor-solicit, nd-neighbor-advert }} accept",
+            oif
+        );
+        for endpoint in endpoints {
+            let family = if endpoint.is_ipv4() { "ip" } else { "ip6" };
+            let _ = writeln!(
+                rules,
+                "{} {} daddr {} udp dport {} accept",
+                oif,
+                family,
+                endpoint.ip(),
+                endpoint.port()
+            );
+        }
+        let _ = writeln!(rules, "{} drop", oif);
+        rules.push_str("\t}\n");
+    }
+
+    rules.push_str("}\n");
+    Some(rules)
+}
+
+/// Whether lantern has loaded a ruleset that may still be active; the file
+/// lives in /run, so it goes away with the table on reboot.
+pub fn firewall_installed() -> bool {
+    Path::new(RULESET_PATH).exists()
+}
+
+/// Load the ruleset, or remove lantern's table when there is none.
+pub async fn apply_firewall(ruleset: Option<String>) -> Result<()> {
+    let removing = ruleset.is_none();
+    if removing && !firewall_installed() {
+        return Ok(());
+    }
+    let rules =
+        ruleset.unwrap_or_else(|| "table inet lantern {}\ndelete table inet lantern\n".to_string());
+    let operation = if removing {
+        "Remove the untrusted-network firewall"
+    } else {
+        "Untrusted-network firewall"
+    };
+    secure_file::write_private(RULESET_PATH, provenance::stamp(&rules, operation))?;
+
+    let output = Command::new("/usr/bin/nft")
+        .args(["-f", RULESET_PATH])
+        .timed_output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "nft failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    if removing {
+        fs::remove_file(RULESET_PATH)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wifi(ssid: &str, gateway: &str, mac: &str) -> Uplink {
+        Uplink {
+            interface: "wlan0".to_string(),
+            ssid: Some(ssid.to_string()),
+            gateway: Some(gateway.to_string()),
+            gateway_mac: Some(mac.to_string()),
+        }
+    }
+
+    #[test]
+    fn trusts_listed_ssids_and_gateways() {
+        let settings = TrustedNetworks {
+            ssids: vec!["Home".to_string()],
+            gateways: vec!["AA:BB:CC:00:11:22".to_string()],
+            ..Default::default()
+        };
+
+        assert!(settings.trusts(&wifi("Home", "192.168.1.1", "00:00:00:00:00:01")));
+        assert!(settings.trusts(&wifi("Office", "10.0.0.1", "aa:bb:cc:00:11:22")));
+        assert!(!settings.trusts(&wifi("Cafe", "192.168.1.1", "00:00:00:00:00:01")));
+        assert!(settings.trusts(&Uplink {
+            interface: "eth0".to_string(),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn builds_kill_switch_ruleset() {
+        let mut settings = TrustedNetworks::default();
+        let untrusted = vec!["wlan0".to_string()];
+        let endpoints = vec!["198.51.100.7:51820".parse().unwrap()];
+        assert_eq!(firewall_ruleset(&settings, &untrusted, &endpoints), None);
+
+        settings.kill_switch = true;
+        assert_eq!(firewall_ruleset(&settings, &[], &endpoints), None);
+
+        let rules = firewall_ruleset(&settings, &untrusted, &endpoints).unwrap();
+        assert!(
+            rules.contains("oifname { \"wlan0\" } ip daddr 198.51.100.7 udp dport 51820 accept")
+        );
+        assert!(rules
+            .trim_end()
+            .ends_with("oifname { \"wlan0\" } drop\n\t}\n}"));
+        assert!(!rules.contains("chain input"));
+    }
+}
@@ -2,16 +2,44 @@
 #![allow(clippy::map_clone)] // .map(|x| x.clone()) is clearer than .cloned() in some contexts
 #![allow(clippy::option_as_ref_deref)] // Code clarity over micro-optimizations
 #![allow(clippy::useless_format)] // Format strings may contain dynamic content in future
-use crate::app::App;
+use crate::app::{App, DetailTab};
+use crate::console;
+use crate::dhcpprobe::Basis;
+use crate::dnsleak::{Probe, Verdict};
+use crate::doctor::{self, Severity};
+use crate::events::{EventKind, FLAP_WINDOW};
+use crate::gai::Preference;
 use crate::icons;
+use crate::jobs::JobState;
+use crate::link_kind::LinkKind;
+use crate::locale;
+use crate::modal::Modal;
+use crate::pacing::Pace;
+use crate::palette;
+use crate::provenance;
+use crate::queues;
+use crate::rawedit::{self, Syntax};
+use crate::reachability::{Outcome, StackResult};
+use crate::redraw::Region;
+use crate::rfmon::Capture;
+use crate::survey::GpsStatus;
+use crate::tcp;
+use crate::topology;
+use crate::wifi_rate;
 use byte_unit::Byte;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols,
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
+    widgets::{
+        Axis, BarChart, Block, Borders, Chart, Clear, Dataset, GraphType, List, ListItem,
+        Paragraph, Tabs, Wrap,
+    },
     Frame,
 };
+use std::time::Instant;
+use unicode_width::UnicodeWidthStr;
 
 pub fn draw(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
@@ -23,85 +51,184 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         ])
         .split(f.area());
 
-    // Header
-    let header = Paragraph::new(Line::from(vec![
-        Span::styled(
-            format!("{} Lantern", icons::LANTERN),
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::raw(format!(" - {} Network Interface Manager", icons::NETWORK)),
-    ]))
-    .block(Block::default().borders(Borders::ALL))
-    .alignment(Alignment::Center);
-    f.render_widget(header, chunks[0]);
-
     // Main content area
     let main_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
         .split(chunks[1]);
 
-    // Interface list
-    draw_interface_list(f, app, main_chunks[0]);
-
-    // Details or stats
-    if app.show_details {
-        draw_interface_details(f, app, main_chunks[1]);
-    } else {
-        draw_interface_stats(f, app, main_chunks[1]);
-    }
-
-    // Footer
-    draw_footer(f, app, chunks[2]);
+    // Interface list, with system-wide TCP health below it
+    let left_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(5)])
+        .split(main_chunks[0]);
 
-    // Edit dialog
-    if app.show_edit_dialog {
-        draw_edit_dialog(f, app);
-    }
+    // Panes whose data didn't change keep last frame's cells; a key press
+    // redraws everything
+    let mut regions = std::mem::take(&mut app.redraw);
+    let all = app.needs_redraw;
+    let pace = app.pacer.pace(Instant::now());
+    let status = app.visible_status().map(|(_, time)| *time);
+    regions.draw(
+        f,
+        Region::Header,
+        chunks[0],
+        all || regions.pace_changed(pace),
+        |f, area| draw_header(f, app, pace, area),
+    );
+    regions.drew_header(pace);
+    // Change highlights fade without an event of their own
+    let flashing = app.events.needs_redraw();
+    regions.draw(
+        f,
+        Region::Interfaces,
+        left_chunks[0],
+        all || flashing,
+        |f, area| draw_interface_list(f, app, area),
+    );
+    regions.draw(f, Region::Tcp, left_chunks[1], all, |f, area| {
+        draw_tcp_health(f, app, area)
+    });
+    regions.draw(f, Region::Details, main_chunks[1], all, |f, area| {
+        draw_detail_pane(f, app, area)
+    });
+    regions.draw(
+        f,
+        Region::Footer,
+        chunks[2],
+        all || regions.status_changed(status),
+        |f, area| draw_footer(f, app, area),
+    );
+    regions.drew_footer(status);
+    app.redraw = regions;
 
-    // WiFi loading dialog
-    if app.show_wifi_loading_dialog {
-        draw_wifi_loading_dialog(f, app);
+    // Dialogs in the order they were opened, so each covers the one it came from
+    let app: &App = app;
+    for modal in app.modals.iter() {
+        match modal {
+            Modal::Edit => draw_edit_dialog(f, app),
+            Modal::WifiLoading => draw_wifi_loading_dialog(f, app),
+            Modal::Wifi => draw_wifi_dialog(f, app),
+            Modal::WifiConnect => draw_wifi_connect_dialog(f, app),
+            Modal::WifiEnterprise => draw_wifi_enterprise_dialog(f, app),
+            Modal::WifiDiagnostics => draw_wifi_diagnostics_dialog(f, app),
+            Modal::Hotspot => draw_hotspot_dialog(f, app),
+            Modal::HotspotSummary => draw_hotspot_summary(f, app),
+            Modal::HotspotClients => draw_hotspot_clients_dialog(f, app),
+            Modal::Reservations => draw_reservations_dialog(f, app),
+            Modal::PortForwards => draw_port_forward_dialog(f, app),
+            Modal::Pxe => draw_pxe_dialog(f, app),
+            Modal::GlobalDns => draw_global_dns_dialog(f, app),
+            Modal::DnsLeak => draw_dns_leak_dialog(f, app),
+            Modal::DhcpProbe => draw_dhcp_probe_dialog(f, app),
+            Modal::Reachability => draw_reachability_dialog(f, app),
+            Modal::Troubleshoot => draw_troubleshoot_dialog(f, app),
+            Modal::HttpCheck => draw_http_check_dialog(f, app),
+            Modal::AddressPreference => draw_address_preference_dialog(f, app),
+            Modal::SpeedTest => draw_speed_test_panel(f, app),
+            Modal::Neighbors => draw_neighbor_dialog(f, app),
+            Modal::NetworkdFiles => draw_networkd_files_dialog(f, app),
+            Modal::RawEdit => draw_raw_edit_dialog(f, app),
+            Modal::QueueTuning => draw_queue_tuning_dialog(f, app),
+            Modal::Share => draw_share_dialog(f, app),
+            Modal::Survey => draw_survey_panel(f, app),
+            Modal::RfMonitor => draw_rf_monitor_panel(f, app),
+            Modal::Packets => draw_packet_panel(f, app),
+            Modal::Routing => draw_routing_dialog(f, app),
+            Modal::Doctor => draw_doctor_dialog(f, app),
+            Modal::Topology => draw_topology_dialog(f, app),
+            Modal::Adopt => draw_adopt_dialog(f, app),
+            Modal::Copy => draw_copy_dialog(f, app),
+            Modal::Jobs => draw_jobs_panel(f, app),
+            Modal::Palette => draw_palette(f, app),
+            Modal::Hotplug => draw_hotplug_dialog(f, app),
+            // Forms are drawn within the dialog they belong to
+            Modal::ReservationForm | Modal::PortForwardForm | Modal::NeighborForm => {}
+        }
     }
 
-    // WiFi dialog
-    if app.show_wifi_dialog {
-        draw_wifi_dialog(f, app);
+    // Debug overlay goes on top of everything, dialogs included
+    if app.show_perf_overlay {
+        draw_perf_overlay(f, app);
     }
 
-    // WiFi connect dialog
-    if app.show_wifi_connect_dialog {
-        draw_wifi_connect_dialog(f, app);
+    // Serial consoles get the same frame in ASCII
+    if console::is_minimal() {
+        console::to_ascii(f.buffer_mut());
     }
+}
 
-    // Enterprise WiFi dialog
-    if app.show_wifi_enterprise_dialog {
-        draw_wifi_enterprise_dialog(f, app);
+fn draw_header(f: &mut Frame, app: &App, pace: Pace, area: Rect) {
+    let mut title = vec![
+        Span::styled(
+            format!("{} Lantern", icons::LANTERN),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(" - {} Network Interface Manager", icons::NETWORK)),
+    ];
+    let (pace_icon, pace_color) = match pace {
+        Pace::Live => (icons::LIVE, Color::Green),
+        Pace::Idle => (icons::LIVE, Color::DarkGray),
+        Pace::Churn => (icons::LIVE, Color::Yellow),
+        Pace::Paused => (icons::PAUSED, Color::Yellow),
+    };
+    title.push(Span::styled(
+        format!("  {} {}", pace_icon, pace.label()),
+        Style::default().fg(pace_color),
+    ));
+    // The soonest revert; K keeps them all
+    if let Some(change) = app.pending.first() {
+        let remaining = change.remaining(std::time::SystemTime::now());
+        title.push(Span::styled(
+            format!(
+                "  {} {} temporary, reverting in {} (K: keep)",
+                icons::WARNING,
+                app.pending.len(),
+                crate::pending::format_remaining(remaining)
+            ),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
     }
-
-    // Hotspot dialog
-    if app.show_hotspot_dialog {
-        draw_hotspot_dialog(f, app);
+    if app.network_manager.is_demo() {
+        title.push(Span::styled(
+            "  [DEMO: simulated network]",
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ));
     }
-
-    // WiFi diagnostics dialog
-    if app.show_wifi_diagnostics_dialog {
-        draw_wifi_diagnostics_dialog(f, app);
+    if app.safe_mode {
+        title.push(Span::styled(
+            "  [SAFE: no automatic actions]",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
     }
+    let header = Paragraph::new(Line::from(title))
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+    f.render_widget(header, area);
 }
 
 fn draw_interface_list(f: &mut Frame, app: &App, area: Rect) {
-    let interfaces: Vec<ListItem> = app
+    let mut interfaces: Vec<ListItem> = app
         .interfaces
         .iter()
         .enumerate()
         .map(|(i, iface)| {
-            let (state_icon, state_color) = match iface.state.as_str() {
-                "UP" => (icons::UP, Color::Green),
-                "DOWN" => (icons::DOWN, Color::Red),
-                _ => (icons::UNKNOWN, Color::Yellow),
+            let (state_icon, state_label, state_color) = match iface.state.as_str() {
+                // Wired and set up, but nothing at the other end of the cable
+                _ if iface.no_carrier() && iface.wifi_info.is_none() => {
+                    (icons::NO_CARRIER, "UNPLUG", Color::Magenta)
+                }
+                "UP" => (icons::UP, "UP", Color::Green),
+                "DOWN" => (icons::DOWN, "DOWN", Color::Red),
+                state => (icons::UNKNOWN, state, Color::Yellow),
             };
 
             let ip = iface
@@ -112,6 +239,10 @@ fn draw_interface_list(f: &mut Frame, app: &App, area: Rect) {
 
             // Build WiFi info if available
             let mut content_spans = vec![
+                Span::styled(
+                    format!("{} ", iface.kind.icon(iface.wifi_info.is_some())),
+                    Style::default().fg(kind_color(iface.kind)),
+                ),
                 Span::styled(
                     format!("{:<12}", iface.name),
                     if i == app.selected_index {
@@ -124,12 +255,35 @@ fn draw_interface_list(f: &mut Frame, app: &App, area: Rect) {
                 ),
                 Span::raw(" "),
                 Span::styled(
-                    format!("{} {:<6}", state_icon, iface.state),
+                    format!("{} {:<6}", state_icon, state_label),
                     Style::default().fg(state_color),
                 ),
                 Span::raw(" "),
                 Span::raw(format!("{:<15}", ip)),
             ];
+            if app.config.interfaces.is_ignored(&iface.name) {
+                content_spans.push(Span::styled(" ignored", Style::default().fg(Color::Gray)));
+            }
+            if crate::linklocal::only_link_local(&iface.ipv4_addresses) {
+                content_spans.push(Span::styled(
+                    " link-local only (N: retry DHCP)",
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+
+            // Name the container at the other end of a veth
+            if let Some(container) = app.containers.get(&iface.name) {
+                content_spans.push(Span::styled(
+                    format!(" {} {}", icons::CONTAINER, container.name),
+                    Style::default().fg(Color::LightBlue),
+                ));
+                if let Some(address) = container.addresses.first() {
+                    content_spans.push(Span::styled(
+                        format!(" {}", address),
+                        Style::default().fg(Color::Gray),
+                    ));
+                }
+            }
 
             // Add WiFi info if this is a wireless interface
             if let Some(wifi_info) = &iface.wifi_info {
@@ -148,7 +302,7 @@ fn draw_interface_list(f: &mut Frame, app: &App, area: Rect) {
                             _ => Color::Red,                // Poor
                         };
                         content_spans.push(Span::styled(
-                            format!(" ({}dBm)", signal),
+                            format!(" ({})", app.config.display.signal.format(signal)),
                             Style::default().fg(signal_color),
                         ));
                     }
@@ -160,21 +314,262 @@ fn draw_interface_list(f: &mut Frame, app: &App, area: Rect) {
                 }
             }
 
+            // Capture modes see traffic not meant for this host; keep them obvious
+            if iface.promiscuous {
+                content_spans.push(Span::styled(
+                    format!(" {} PROMISC", icons::CAPTURE),
+                    Style::default()
+                        .fg(Color::LightYellow)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+            if iface.monitor {
+                content_spans.push(Span::styled(
+                    format!(" {} MONITOR", icons::CAPTURE),
+                    Style::default()
+                        .fg(Color::LightYellow)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+
+            if app.identifying.as_deref() == Some(iface.name.as_str()) {
+                content_spans.push(Span::styled(
+                    format!(" {} BLINKING", icons::IDENTIFY),
+                    Style::default()
+                        .fg(Color::LightMagenta)
+                        .add_modifier(Modifier::BOLD | Modifier::SLOW_BLINK),
+                ));
+            }
+
+            let flaps = app.events.flaps();
+            if flaps.is_flapping(&iface.name) {
+                content_spans.push(Span::styled(
+                    format!(" FLAP×{}", flaps.recent(&iface.name)),
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ));
+            }
+
             let content = Line::from(content_spans);
 
-            ListItem::new(content)
+            // Briefly highlight rows that just changed
+            match app.events.flash(&iface.name) {
+                Some(kind) => ListItem::new(content)
+                    .style(Style::default().fg(Color::Black).bg(event_color(kind))),
+                None => ListItem::new(content),
+            }
         })
         .collect();
 
+    // Aggregate groups as synthetic rows below the interfaces; not selectable
+    let groups = app.group_totals();
+    if !groups.is_empty() {
+        interfaces.push(ListItem::new(Line::from(Span::styled(
+            "─".repeat(area.width.saturating_sub(2) as usize),
+            Style::default().fg(Color::DarkGray),
+        ))));
+    }
+    for group in groups {
+        let members = match group.members.len() {
+            1 => "1 link".to_string(),
+            n => format!("{} links", n),
+        };
+        interfaces.push(ListItem::new(Line::from(vec![
+            Span::styled(
+                format!("Σ {:<10}", group.name),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" "),
+            Span::styled(format!("{:<8}", members), Style::default().fg(Color::Gray)),
+            Span::raw(" "),
+            Span::styled(
+                format!(
+                    "{} {:>11}",
+                    icons::RX,
+                    app.config.display.rate(group.rx_rate)
+                ),
+                Style::default().fg(Color::Green),
+            ),
+            Span::raw(" "),
+            Span::styled(
+                format!(
+                    "{} {:>11}",
+                    icons::TX,
+                    app.config.display.rate(group.tx_rate)
+                ),
+                Style::default().fg(Color::Blue),
+            ),
+            Span::styled(
+                format!(
+                    "  ({:.1} / {:.1})",
+                    Byte::from_u64(group.stats.rx_bytes)
+                        .get_appropriate_unit(app.config.display.unit_type()),
+                    Byte::from_u64(group.stats.tx_bytes)
+                        .get_appropriate_unit(app.config.display.unit_type())
+                ),
+                Style::default().fg(Color::Gray),
+            ),
+        ])));
+    }
+
     let interfaces_list = List::new(interfaces)
+        .block(Block::default().borders(Borders::ALL).title(
+            match (app.hidden_interfaces, app.show_ignored) {
+                (0, _) => format!("{} Interfaces [↑/↓ to navigate]", icons::ETHERNET),
+                (_, true) => format!(
+                    "{} Interfaces [↑/↓ to navigate, . hides ignored]",
+                    icons::ETHERNET
+                ),
+                (hidden, false) => format!(
+                    "{} Interfaces [↑/↓ to navigate, {} ignored hidden, . shows]",
+                    icons::ETHERNET,
+                    hidden
+                ),
+            },
+        ))
+        .highlight_style(Style::default().bg(Color::DarkGray));
+
+    f.render_widget(interfaces_list, area);
+}
+
+fn draw_detail_pane(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
+
+    let tabs = Tabs::new(DetailTab::ALL.iter().map(|tab| tab.title()))
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(format!("{} Interfaces [↑/↓ to navigate]", icons::ETHERNET)),
+                .title("Details [←/→ or Enter to switch]"),
         )
-        .highlight_style(Style::default().bg(Color::DarkGray));
+        .select(
+            DetailTab::ALL
+                .iter()
+                .position(|&tab| tab == app.detail_tab)
+                .unwrap_or(0),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_widget(tabs, chunks[0]);
 
-    f.render_widget(interfaces_list, area);
+    match app.detail_tab {
+        DetailTab::Overview => {
+            let overview = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .split(chunks[1]);
+            draw_interface_details(f, app, overview[0]);
+            draw_interface_stats(f, app, overview[1]);
+        }
+        DetailTab::Ipv6 => draw_ipv6_tab(f, app, chunks[1]),
+        DetailTab::Wifi => draw_wifi_tab(f, app, chunks[1]),
+        DetailTab::WireGuard => draw_wireguard_tab(f, app, chunks[1]),
+        DetailTab::Routes => draw_routes_tab(f, app, chunks[1]),
+        DetailTab::Queues => draw_queues_tab(f, app, chunks[1]),
+        DetailTab::Logs => draw_logs_tab(f, app, chunks[1]),
+    }
+}
+
+fn draw_tcp_health(f: &mut Frame, app: &App, area: Rect) {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let health = &app.tcp;
+
+    let lines = match &health.latest {
+        None => vec![Line::from("Collecting...")],
+        Some(snapshot) => {
+            let retransmits = match (health.retransmit_percent, health.retransmits_per_sec) {
+                (Some(percent), Some(per_sec)) => Span::styled(
+                    format!("{:.2}% ({:.0}/s)", percent, per_sec),
+                    Style::default().fg(match percent {
+                        p if p < 1.0 => Color::Green,
+                        p if p < 3.0 => Color::Yellow,
+                        _ => Color::Red,
+                    }),
+                ),
+                (None, Some(per_sec)) => Span::raw(format!("{:.0}/s, nothing sent", per_sec)),
+                _ => Span::raw("measuring..."),
+            };
+
+            // Busiest states first
+            let mut states: Vec<(&str, usize)> = snapshot
+                .states
+                .iter()
+                .map(|(state, count)| (*state, *count))
+                .collect();
+            states.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            let states = states
+                .iter()
+                .map(|(state, count)| format!("{} {}", count, state))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let (pressure, pressure_color) = match health.memory_pressure() {
+                tcp::MemoryPressure::Normal => ("", Color::Reset),
+                tcp::MemoryPressure::Pressure => (" UNDER PRESSURE", Color::Yellow),
+                tcp::MemoryPressure::Exhausted => (" EXHAUSTED", Color::Red),
+            };
+            let limit = snapshot
+                .memory_limits
+                .map(|(_, pressure, _)| format!(" of {} before pressure", pressure))
+                .unwrap_or_default();
+
+            vec![
+                Line::from(vec![Span::styled("Retransmits: ", bold), retransmits]),
+                Line::from(vec![Span::styled("Sockets: ", bold), Span::raw(states)]),
+                Line::from(vec![
+                    Span::styled("Memory: ", bold),
+                    Span::raw(format!(
+                        "{} pages{} | {} orphans",
+                        snapshot.memory_pages, limit, snapshot.orphans
+                    )),
+                    Span::styled(
+                        pressure,
+                        Style::default()
+                            .fg(pressure_color)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                ]),
+            ]
+        }
+    };
+
+    let widget = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("TCP Health"))
+        .wrap(Wrap { trim: true });
+    f.render_widget(widget, area);
+}
+
+fn flap_line(app: &App, name: &str) -> Line<'static> {
+    let flaps = app.events.flaps();
+    let recent = flaps.recent(name);
+    let color = if flaps.is_flapping(name) {
+        Color::Red
+    } else if recent > 0 {
+        Color::Yellow
+    } else {
+        Color::Reset
+    };
+    Line::from(vec![
+        Span::styled(
+            "Carrier changes: ",
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!(
+                "{} in last {} min ({} since start)",
+                recent,
+                FLAP_WINDOW.as_secs() / 60,
+                flaps.total(name)
+            ),
+            Style::default().fg(color),
+        ),
+    ])
 }
 
 fn draw_interface_details(f: &mut Frame, app: &App, area: Rect) {
@@ -188,6 +583,78 @@ fn draw_interface_details(f: &mut Frame, app: &App, area: Rect) {
                 Span::styled("MAC: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(&interface.mac_address),
             ]),
+        ];
+        if interface.kind != LinkKind::Physical {
+            lines.push(Line::from(vec![
+                Span::styled("Type: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    format!("{} {}", interface.kind.icon(false), interface.kind.label()),
+                    Style::default().fg(kind_color(interface.kind)),
+                ),
+            ]));
+        }
+        if let Some(hardware) = &interface.hardware {
+            lines.push(Line::from(vec![
+                Span::styled("Hardware: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(hardware.summary()),
+            ]));
+        }
+        for conflict in app
+            .mac_conflicts
+            .iter()
+            .filter(|conflict| conflict.interface() == interface.name)
+        {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{} MAC conflict: ", icons::WARNING),
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(conflict.describe(), Style::default().fg(Color::Red)),
+            ]));
+        }
+        if interface.kind == LinkKind::Gadget {
+            let sharing = match app.usb_shares.get(&interface.name) {
+                Some(address) => Span::styled(
+                    format!("{} with DHCP and NAT (X: stop)", address),
+                    Style::default().fg(Color::Green),
+                ),
+                None => Span::styled(
+                    "off (X: share this machine's connection)",
+                    Style::default().fg(Color::Gray),
+                ),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(
+                    "USB sharing: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                sharing,
+            ]));
+        }
+        if let Some(container) = app.containers.get(&interface.name) {
+            let mut container_line = format!("{} ({})", container.name, container.runtime);
+            if !container.addresses.is_empty() {
+                container_line.push_str(&format!(" {}", container.addresses.join(", ")));
+            }
+            lines.push(Line::from(vec![
+                Span::styled("Container: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(container_line),
+            ]));
+        }
+        if let Some(foreign) = app.foreign_managers.get(&interface.name) {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    "Managed by: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("{} ({})", foreign.manager.label(), foreign.source),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled("  T: Adopt", Style::default().fg(Color::Gray)),
+            ]));
+        }
+        lines.extend([
             Line::from(vec![
                 Span::styled("State: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::styled(
@@ -199,6 +666,38 @@ fn draw_interface_details(f: &mut Frame, app: &App, area: Rect) {
                     }),
                 ),
             ]),
+            Line::from(vec![
+                Span::styled("Admin: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(if interface.admin_up { "Up" } else { "Down" }),
+                Span::styled("  Carrier: ", Style::default().add_modifier(Modifier::BOLD)),
+                match interface.carrier {
+                    Some(true) => Span::styled("Yes", Style::default().fg(Color::Green)),
+                    // The kernel reports no carrier for any interface that is down
+                    Some(false) if !interface.admin_up => Span::raw("No"),
+                    Some(false) if interface.wifi_info.is_some() => {
+                        Span::styled("No (not associated)", Style::default().fg(Color::Magenta))
+                    }
+                    Some(false) => Span::styled(
+                        format!("{} No (cable unplugged)", icons::NO_CARRIER),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                    None => Span::raw("Unknown"),
+                },
+            ]),
+            flap_line(app, &interface.name),
+            Line::from(vec![
+                Span::styled("Capture: ", Style::default().add_modifier(Modifier::BOLD)),
+                match (interface.monitor, interface.promiscuous) {
+                    (true, _) => Span::styled(
+                        "Monitor mode (802.11 frames)",
+                        Style::default().fg(Color::LightYellow),
+                    ),
+                    (false, true) => {
+                        Span::styled("Promiscuous", Style::default().fg(Color::LightYellow))
+                    }
+                    (false, false) => Span::raw("Off"),
+                },
+            ]),
             Line::from(vec![
                 Span::styled("MTU: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(interface.mtu.to_string()),
@@ -208,7 +707,7 @@ fn draw_interface_details(f: &mut Frame, app: &App, area: Rect) {
                 "IPv4 Addresses:",
                 Style::default().add_modifier(Modifier::BOLD),
             )),
-        ];
+        ]);
 
         for addr in &interface.ipv4_addresses {
             lines.push(Line::from(format!("  • {}", addr)));
@@ -216,12 +715,36 @@ fn draw_interface_details(f: &mut Frame, app: &App, area: Rect) {
         if interface.ipv4_addresses.is_empty() {
             lines.push(Line::from("  None"));
         }
+        if crate::linklocal::only_link_local(&interface.ipv4_addresses) {
+            lines.push(Line::from(Span::styled(
+                "  Link-local only: DHCP got no answer. N asks again",
+                Style::default().fg(Color::Yellow),
+            )));
+        }
 
         lines.push(Line::from(""));
         lines.push(Line::from(vec![
             Span::styled("Gateway: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(interface.gateway.as_deref().unwrap_or("None")),
         ]));
+        if let Some(&trusted) = app.network_trust.get(&interface.name) {
+            let (label, color) = if trusted {
+                ("Trusted", Color::Green)
+            } else {
+                ("Untrusted", Color::Yellow)
+            };
+            let mut spans = vec![
+                Span::styled("Network: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(label, Style::default().fg(color)),
+            ];
+            if !trusted && app.firewall_failed.is_some() {
+                spans.push(Span::styled(
+                    " (firewall not enforced)",
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
 
         lines.push(Line::from(""));
         lines.push(Line::from(Span::styled(
@@ -241,7 +764,7 @@ fn draw_interface_details(f: &mut Frame, app: &App, area: Rect) {
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Interface Details [Enter to toggle]"),
+                    .title("Interface Details"),
             )
             .wrap(Wrap { trim: true });
 
@@ -251,16 +774,29 @@ fn draw_interface_details(f: &mut Frame, app: &App, area: Rect) {
 
 fn draw_interface_stats(f: &mut Frame, app: &App, area: Rect) {
     if let Some(interface) = app.get_selected_interface() {
-        let rx_bytes =
-            Byte::from_u128(interface.stats.rx_bytes as u128).unwrap_or(Byte::from_u64(0));
-        let tx_bytes =
-            Byte::from_u128(interface.stats.tx_bytes as u128).unwrap_or(Byte::from_u64(0));
-
-        let stats_text = vec![
-            Line::from(Span::styled(
+        // Relative to the last z, if any
+        let counters = app.baselines.relative(interface);
+        let locale = app.config.display.locale();
+        let rx_bytes = Byte::from_u128(counters.rx_bytes as u128).unwrap_or(Byte::from_u64(0));
+        let tx_bytes = Byte::from_u128(counters.tx_bytes as u128).unwrap_or(Byte::from_u64(0));
+        let heading = match app.baselines.get(&interface.name) {
+            Some(baseline) => Line::from(vec![
+                Span::styled(
+                    format!("Since {}", locale.time(&baseline.taken)),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(" (Z: totals)", Style::default().fg(Color::Gray)),
+            ]),
+            None => Line::from(Span::styled(
                 "Network Statistics",
                 Style::default().add_modifier(Modifier::BOLD),
             )),
+        };
+
+        let stats_text = vec![
+            heading,
             Line::from(""),
             Line::from(vec![
                 Span::styled(
@@ -269,16 +805,16 @@ fn draw_interface_stats(f: &mut Frame, app: &App, area: Rect) {
                 ),
                 Span::raw(format!(
                     "{:.2}",
-                    rx_bytes.get_appropriate_unit(byte_unit::UnitType::Binary)
+                    rx_bytes.get_appropriate_unit(app.config.display.unit_type())
                 )),
             ]),
             Line::from(vec![
                 Span::raw("  Packets: "),
-                Span::raw(interface.stats.rx_packets.to_string()),
+                Span::raw(locale.count(counters.rx_packets)),
             ]),
             Line::from(vec![
                 Span::raw("  Errors: "),
-                Span::raw(interface.stats.rx_errors.to_string()),
+                Span::raw(locale.count(counters.rx_errors)),
             ]),
             Line::from(""),
             Line::from(vec![
@@ -288,92 +824,530 @@ fn draw_interface_stats(f: &mut Frame, app: &App, area: Rect) {
                 ),
                 Span::raw(format!(
                     "{:.2}",
-                    tx_bytes.get_appropriate_unit(byte_unit::UnitType::Binary)
+                    tx_bytes.get_appropriate_unit(app.config.display.unit_type())
                 )),
             ]),
             Line::from(vec![
                 Span::raw("  Packets: "),
-                Span::raw(interface.stats.tx_packets.to_string()),
+                Span::raw(locale.count(counters.tx_packets)),
             ]),
             Line::from(vec![
                 Span::raw("  Errors: "),
-                Span::raw(interface.stats.tx_errors.to_string()),
+                Span::raw(locale.count(counters.tx_errors)),
             ]),
         ];
 
-        let stats = Paragraph::new(stats_text).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Statistics [Enter for details]"),
-        );
+        let stats = Paragraph::new(stats_text)
+            .block(Block::default().borders(Borders::ALL).title("Statistics"));
 
         f.render_widget(stats, area);
     }
 }
 
-fn draw_footer(f: &mut Frame, app: &App, area: Rect) {
-    let mut footer_text = vec![Span::raw(
-        "q: Quit | r: Refresh | e: Edit | u: Up/Down iface | w: WiFi | h: Hotspot | Enter: Details",
-    )];
+fn draw_ipv6_tab(f: &mut Frame, app: &App, area: Rect) {
+    let Some(interface) = app.get_selected_interface() else {
+        return;
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+
+    match &interface.ipv6_info {
+        Some(info) => {
+            lines.push(Line::from(Span::styled("Addresses:", bold)));
+            for addr in &info.addresses {
+                let mut text = format!(
+                    "  • {}/{} ({:?})",
+                    addr.address, addr.prefix_length, addr.scope
+                );
+                if !addr.flags.is_empty() {
+                    text.push_str(&format!(" [{}]", addr.flags.join(", ")));
+                }
+                lines.push(Line::from(text));
+            }
+            if info.addresses.is_empty() {
+                lines.push(Line::from("  None"));
+            }
 
-    if let Some((msg, time)) = &app.status_message {
-        if time.elapsed().as_secs() < 3 {
-            footer_text.push(Span::raw(" | "));
-            footer_text.push(Span::styled(msg, Style::default().fg(Color::Yellow)));
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![
+                Span::styled("Default Route: ", bold),
+                Span::raw(
+                    info.default_route
+                        .as_deref()
+                        .or(interface.ipv6_gateway.as_deref())
+                        .unwrap_or("None"),
+                ),
+            ]));
+
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("DNS Servers:", bold)));
+            for dns in &info.dns_servers {
+                lines.push(Line::from(format!("  • {}", dns)));
+            }
+            if info.dns_servers.is_empty() {
+                lines.push(Line::from("  None"));
+            }
+
+            lines.push(Line::from(""));
+            let flag = |label: &'static str, enabled: bool| {
+                Line::from(vec![
+                    Span::styled(label, bold),
+                    Span::styled(
+                        if enabled { "yes" } else { "no" },
+                        Style::default().fg(if enabled { Color::Green } else { Color::Gray }),
+                    ),
+                ])
+            };
+            lines.push(flag("Router Advertisements: ", info.accept_ra));
+            lines.push(flag("Privacy Extensions: ", info.privacy_extensions));
+            lines.push(flag("DHCPv6: ", info.dhcpv6_enabled));
+        }
+        None => {
+            lines.push(Line::from(Span::styled("Addresses:", bold)));
+            for addr in &interface.ipv6_addresses {
+                lines.push(Line::from(format!("  • {}", addr)));
+            }
+            if interface.ipv6_addresses.is_empty() {
+                lines.push(Line::from("  IPv6 is not configured on this interface"));
+            }
         }
     }
 
-    let footer = Paragraph::new(Line::from(footer_text))
-        .block(Block::default().borders(Borders::ALL))
-        .alignment(Alignment::Center);
+    let ipv6 = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("IPv6"))
+        .wrap(Wrap { trim: true });
+    f.render_widget(ipv6, area);
+}
 
-    f.render_widget(footer, area);
+fn draw_wifi_tab(f: &mut Frame, app: &App, area: Rect) {
+    let Some(interface) = app.get_selected_interface() else {
+        return;
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let lines = match &interface.wifi_info {
+        None => vec![Line::from("Not a wireless interface")],
+        Some(wifi) => {
+            let mut lines = Vec::new();
+            match &wifi.current_network {
+                Some(network) => {
+                    lines.push(Line::from(vec![
+                        Span::styled("Network: ", bold),
+                        Span::styled(&network.ssid, Style::default().fg(Color::Green)),
+                    ]));
+                    lines.push(Line::from(vec![
+                        Span::styled("BSSID: ", bold),
+                        Span::raw(&network.bssid),
+                    ]));
+                    lines.push(Line::from(vec![
+                        Span::styled("Security: ", bold),
+                        Span::raw(format!("{:?}", network.security)),
+                    ]));
+                }
+                None => lines.push(Line::from(vec![
+                    Span::styled("Network: ", bold),
+                    Span::styled("Not connected", Style::default().fg(Color::Red)),
+                ])),
+            }
+            if let Some(signal) = wifi.signal_strength {
+                lines.push(Line::from(vec![
+                    Span::styled("Signal: ", bold),
+                    Span::raw(app.config.display.signal.format(signal)),
+                ]));
+            }
+            if let Some(frequency) = wifi.frequency {
+                lines.push(Line::from(vec![
+                    Span::styled("Frequency: ", bold),
+                    Span::raw(format!("{} MHz", frequency)),
+                ]));
+            }
+            if let Some(channel) = wifi.channel {
+                lines.push(Line::from(vec![
+                    Span::styled("Channel: ", bold),
+                    Span::raw(channel.to_string()),
+                ]));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                "w: Scan networks | s: Share",
+                Style::default().fg(Color::Gray),
+            )));
+            lines
+        }
+    };
+
+    let wifi = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("WiFi"))
+        .wrap(Wrap { trim: true });
+    f.render_widget(wifi, area);
 }
 
-fn draw_edit_dialog(f: &mut Frame, app: &App) {
-    let area = centered_rect(60, 50, f.area());
-    f.render_widget(Clear, area);
+fn draw_wireguard_tab(f: &mut Frame, app: &App, area: Rect) {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
 
-    let block = Block::default()
-        .title(format!(
-            "Edit {}",
-            app.edit_interface.as_ref().unwrap().name
-        ))
-        .borders(Borders::ALL)
-        .style(Style::default().bg(Color::Black));
+    let lines: Vec<Line> = match &app.wireguard.value {
+        None if app.wireguard.loading => vec![Line::from("Loading WireGuard status...")],
+        None => vec![Line::from("Not a WireGuard interface")],
+        Some(status) => {
+            let mut lines = vec![
+                Line::from(vec![
+                    Span::styled("Public Key: ", bold),
+                    Span::raw(&status.public_key),
+                ]),
+                Line::from(vec![
+                    Span::styled("Listen Port: ", bold),
+                    Span::raw(
+                        status
+                            .listen_port
+                            .map(|port| port.to_string())
+                            .unwrap_or_else(|| "None".to_string()),
+                    ),
+                ]),
+                Line::from(""),
+                Line::from(Span::styled(
+                    format!("Peers ({}):", status.peers.len()),
+                    bold,
+                )),
+            ];
 
-    let inner = block.inner(area);
-    f.render_widget(block, area);
+            for peer in &status.peers {
+                let key: String = peer.public_key.chars().take(12).collect();
+                let handshake = match peer.latest_handshake.and_then(|at| at.elapsed().ok()) {
+                    Some(age) => format!("handshake {} ago", locale::duration(age)),
+                    None => "no handshake".to_string(),
+                };
+                let mut spans = vec![
+                    Span::styled(format!("  • {}… ", key), bold),
+                    Span::raw(handshake),
+                ];
+                if let Some(rtt) = peer.rtt {
+                    spans.push(Span::styled(
+                        format!(" | RTT {:.1} ms", rtt.as_secs_f64() * 1000.0),
+                        Style::default().fg(Color::Green),
+                    ));
+                }
+                lines.push(Line::from(spans));
 
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Min(1),
-            Constraint::Length(2),
-        ])
-        .split(inner);
+                let rx = Byte::from_u64(peer.transfer_rx)
+                    .get_appropriate_unit(app.config.display.unit_type());
+                let tx = Byte::from_u64(peer.transfer_tx)
+                    .get_appropriate_unit(app.config.display.unit_type());
+                lines.push(Line::from(Span::styled(
+                    format!(
+                        "    {} | {} {:.1} {} {:.1}",
+                        peer.endpoint.as_deref().unwrap_or("no endpoint"),
+                        icons::RX,
+                        rx,
+                        icons::TX,
+                        tx
+                    ),
+                    Style::default().fg(Color::Gray),
+                )));
+            }
+            lines
+        }
+    };
 
-    // DHCP toggle
-    let dhcp_text = if app.use_dhcp {
-        format!(
-            "DHCP: [{}] Enabled (press Space to toggle)",
-            icons::CONNECTED
-        )
+    let wireguard = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("WireGuard"))
+        .wrap(Wrap { trim: true });
+    f.render_widget(wireguard, area);
+}
+
+fn draw_routes_tab(f: &mut Frame, app: &App, area: Rect) {
+    let lines: Vec<Line> = if app.routes.value.is_empty() {
+        vec![Line::from(if app.routes.loading {
+            "Loading routes..."
+        } else {
+            "No routes through this interface"
+        })]
     } else {
-        "DHCP: [ ] Disabled (press Space to toggle)".to_string()
+        app.routes
+            .value
+            .iter()
+            .map(|route| {
+                let mut spans = vec![
+                    Span::styled(
+                        if route.ipv6 { "v6 " } else { "v4 " },
+                        Style::default().fg(Color::Gray),
+                    ),
+                    Span::styled(
+                        route.destination.clone(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                ];
+                if let Some(gateway) = &route.gateway {
+                    spans.push(Span::raw(format!(" via {}", gateway)));
+                }
+                if let Some(protocol) = &route.protocol {
+                    spans.push(Span::styled(
+                        format!(" proto {}", protocol),
+                        Style::default().fg(Color::Gray),
+                    ));
+                }
+                if let Some(metric) = route.metric {
+                    spans.push(Span::styled(
+                        format!(" metric {}", metric),
+                        Style::default().fg(Color::Gray),
+                    ));
+                }
+                Line::from(spans)
+            })
+            .collect()
     };
-    let dhcp = Paragraph::new(dhcp_text);
-    f.render_widget(dhcp, chunks[0]);
 
-    if !app.use_dhcp {
-        // IP input
-        let ip_style = if app.active_input == 0 {
-            Style::default().fg(Color::Yellow)
+    let routes = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Routes"))
+        .wrap(Wrap { trim: true });
+    f.render_widget(routes, area);
+}
+
+fn draw_queues_tab(f: &mut Frame, app: &App, area: Rect) {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let stats = &app.queues.value;
+
+    let lines: Vec<Line> = if stats.is_empty() {
+        vec![Line::from(if app.queues.loading {
+            "Loading queue statistics..."
+        } else {
+            "No hardware queues or IRQs found for this interface"
+        })]
+    } else {
+        let mut lines = vec![Line::from(vec![
+            Span::styled("Queues: ", bold),
+            Span::raw(format!("{} rx / {} tx", stats.rx_queues, stats.tx_queues)),
+        ])];
+
+        for (direction, queues) in [("rx", &stats.rx), ("tx", &stats.tx)] {
+            if queues.is_empty() {
+                continue;
+            }
+            // Share of packets shows how evenly RSS spreads the load
+            let total: u64 = queues.values().filter_map(|queue| queue.packets).sum();
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!("{} per queue:", direction.to_uppercase()),
+                bold,
+            )));
+            for (index, queue) in queues {
+                let mut spans = vec![Span::raw(format!("  {}-{:<3}", direction, index))];
+                if let Some(packets) = queue.packets {
+                    spans.push(Span::raw(format!(" {:>12} pkts", packets)));
+                    if total > 0 {
+                        spans.push(Span::styled(
+                            format!(" {:>5.1}%", packets as f64 * 100.0 / total as f64),
+                            Style::default().fg(Color::Cyan),
+                        ));
+                    }
+                }
+                if let Some(bytes) = queue.bytes {
+                    spans.push(Span::styled(
+                        format!(" {:>10.1} MB", bytes as f64 / 1024.0 / 1024.0),
+                        Style::default().fg(Color::Gray),
+                    ));
+                }
+                lines.push(Line::from(spans));
+            }
+        }
+
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Tuning (t to change):", bold)));
+        if let Some(rings) = &stats.rings {
+            let size = |value: Option<u32>| value.map_or("n/a".to_string(), |v| v.to_string());
+            lines.push(Line::from(format!(
+                "  Rings: rx {} / {}  tx {} / {} max",
+                size(rings.rx),
+                size(rings.rx_max),
+                size(rings.tx),
+                size(rings.tx_max)
+            )));
+        }
+        if !stats.rss_table.is_empty() {
+            lines.push(Line::from(format!(
+                "  RSS: {}",
+                queues::rss_spread(&stats.rss_table)
+            )));
+        }
+        for (label, masks) in [("RPS", &stats.rps_cpus), ("XPS", &stats.xps_cpus)] {
+            if masks.is_empty() {
+                continue;
+            }
+            // Usually the same mask everywhere; list queues only when they differ
+            let mut distinct: Vec<&String> = masks.values().collect();
+            distinct.dedup();
+            let text = if distinct.len() == 1 {
+                format!("{} on all queues", distinct[0])
+            } else {
+                masks
+                    .iter()
+                    .map(|(queue, mask)| format!("{}:{}", queue, mask))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            lines.push(Line::from(format!("  {} CPUs: {}", label, text)));
+        }
+        if !stats.offloads.is_empty() {
+            let mut spans = vec![Span::raw("  Offloads:")];
+            for (name, on) in &stats.offloads {
+                spans.push(Span::styled(
+                    format!(" {}", name),
+                    Style::default().fg(if *on { Color::Green } else { Color::Gray }),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+
+        if !stats.irqs.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("IRQs (count per CPU):", bold)));
+        }
+        for irq in &stats.irqs {
+            // Idle CPUs are left out; machines with many cores would not fit
+            let cpus: Vec<String> = irq
+                .per_cpu
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(cpu, count)| format!("CPU{} {}", cpu, count))
+                .collect();
+            let mut spans = vec![
+                Span::styled(format!("  {:>4} ", irq.irq), bold),
+                Span::raw(format!("{} ", irq.name)),
+                Span::styled(
+                    if cpus.is_empty() {
+                        "no interrupts".to_string()
+                    } else {
+                        cpus.join(", ")
+                    },
+                    Style::default().fg(Color::Cyan),
+                ),
+            ];
+            if let Some(affinity) = &irq.affinity {
+                spans.push(Span::styled(
+                    format!(" | affinity {}", affinity),
+                    Style::default().fg(Color::Gray),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+        lines
+    };
+
+    let queues = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Queues"))
+        .wrap(Wrap { trim: true });
+    f.render_widget(queues, area);
+}
+
+fn draw_logs_tab(f: &mut Frame, app: &App, area: Rect) {
+    let locale = app.config.display.locale();
+    let Some(interface) = app.get_selected_interface() else {
+        return;
+    };
+
+    // Newest first, only for the selected interface
+    let lines: Vec<Line> = app
+        .events
+        .recent()
+        .filter(|event| event.interface == interface.name)
+        .take(area.height.saturating_sub(2) as usize)
+        .map(|event| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{} ", locale.time(&event.timestamp)),
+                    Style::default().fg(Color::Gray),
+                ),
+                Span::styled(
+                    event.message.clone(),
+                    Style::default().fg(event_color(event.kind)),
+                ),
+            ])
+        })
+        .collect();
+
+    let logs = if lines.is_empty() {
+        Paragraph::new("No changes recorded for this interface yet")
+    } else {
+        Paragraph::new(lines)
+    };
+    f.render_widget(
+        logs.block(Block::default().borders(Borders::ALL).title("Event Log")),
+        area,
+    );
+}
+
+fn draw_footer(f: &mut Frame, app: &App, area: Rect) {
+    let mut footer_text = vec![Span::raw(
+        "q: Quit | r: Refresh | e: Edit | u: Up/Down iface | p: Promisc | m: Monitor | b: Routing | w: WiFi | h: Hotspot | s: Share | ←/→: Tabs",
+    )];
+
+    let active = app.jobs.active();
+    if active > 0 {
+        footer_text.push(Span::raw(" | "));
+        footer_text.push(Span::styled(
+            format!("J: {} job{}", active, if active == 1 { "" } else { "s" }),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    if let Some((msg, _)) = app.visible_status() {
+        footer_text.push(Span::raw(" | "));
+        footer_text.push(Span::styled(msg, Style::default().fg(Color::Yellow)));
+    }
+
+    let footer = Paragraph::new(Line::from(footer_text))
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    f.render_widget(footer, area);
+}
+
+fn draw_edit_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(
+            "Edit {}",
+            app.edit_interface.as_ref().unwrap().name
+        ))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(2),
+        ])
+        .split(inner);
+
+    // DHCP toggle
+    let dhcp_text = if app.use_dhcp {
+        format!(
+            "DHCP: [{}] Enabled (press Space to toggle)",
+            icons::CONNECTED
+        )
+    } else {
+        "DHCP: [ ] Disabled (press Space to toggle)".to_string()
+    };
+    let dhcp = Paragraph::new(dhcp_text);
+    f.render_widget(dhcp, chunks[0]);
+
+    if !app.use_dhcp {
+        // IP input
+        let ip_style = if app.active_input == 0 {
+            Style::default().fg(Color::Yellow)
         } else {
             Style::default()
         };
@@ -414,11 +1388,79 @@ fn draw_edit_dialog(f: &mut Frame, app: &App) {
         f.render_widget(dns, chunks[3]);
     }
 
+    // Revert time, for changes that might cut off the session making them
+    let revert_style = if app.active_input == 3 {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    let revert = Paragraph::new(app.revert_input.value()).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Revert After (minutes, empty: keep)")
+            .border_style(revert_style),
+    );
+    f.render_widget(revert, chunks[4]);
+
+    // Multicast name resolution on this link, next to what resolved does now
+    let responder_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(34),
+            Constraint::Percentage(33),
+            Constraint::Percentage(33),
+        ])
+        .split(chunks[5]);
+    let responders = [
+        (
+            "LLMNR",
+            &app.link_responders.llmnr,
+            &app.link_responders_now.llmnr,
+        ),
+        (
+            "Multicast DNS",
+            &app.link_responders.multicast_dns,
+            &app.link_responders_now.multicast_dns,
+        ),
+    ];
+    for (index, (name, value, now)) in responders.into_iter().enumerate() {
+        let style = if app.active_input == index + 4 {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        let field = Paragraph::new(value.as_deref().unwrap_or("not set")).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(
+                    "{} (now: {})",
+                    name,
+                    now.as_deref().unwrap_or("unknown")
+                ))
+                .border_style(style),
+        );
+        f.render_widget(field, responder_chunks[index]);
+    }
+
+    // A 169.254 address when DHCP gets no answer
+    let link_local_style = if app.active_input == 6 {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    let link_local = Paragraph::new(if app.link_local_fallback { "on" } else { "off" }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("IPv4 Link-Local Fallback")
+            .border_style(link_local_style),
+    );
+    f.render_widget(link_local, responder_chunks[2]);
+
     // Instructions
     let instructions =
-        Paragraph::new("Tab: Next field | Space: Toggle DHCP | s: Save | Esc: Cancel")
+        Paragraph::new("Tab: Next field | Space: Toggle DHCP or mode | s: Save | Esc: Cancel")
             .alignment(Alignment::Center);
-    f.render_widget(instructions, chunks[5]);
+    f.render_widget(instructions, chunks[7]);
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -445,9 +1487,10 @@ fn draw_wifi_dialog(f: &mut Frame, app: &App) {
     let area = centered_rect(85, 75, f.area());
     f.render_widget(Clear, area);
 
+    let signal = app.config.display.signal;
     let mut networks: Vec<ListItem> = Vec::new();
 
-    if app.wifi_scanning {
+    if app.wifi_scanning && app.wifi_networks.is_empty() {
         networks.push(ListItem::new(format!(
             "{} Scanning for networks...",
             icons::SCANNING
@@ -479,19 +1522,12 @@ fn draw_wifi_dialog(f: &mut Frame, app: &App) {
             // Check if this network is in connection history (optimized)
             let in_history = network.in_history;
 
-            // Check auto-connect status for saved networks
-            let auto_connect = if in_history {
-                if let Some(interface) = app.get_selected_interface() {
-                    app.config
-                        .get_wifi_profile(&network.ssid, &interface.name)
-                        .map(|profile| profile.auto_connect)
-                        .unwrap_or(false)
-                } else {
-                    false
-                }
-            } else {
-                false
-            };
+            // Auto-connect and band lock of saved networks
+            let profile = app
+                .get_selected_interface()
+                .filter(|_| in_history)
+                .and_then(|interface| app.config.get_wifi_profile(&network.ssid, &interface.name));
+            let auto_connect = profile.is_some_and(|profile| profile.auto_connect);
 
             // Show connected status, history, auto-connect, and selection
             let prefix = if network.connected {
@@ -521,11 +1557,51 @@ fn draw_wifi_dialog(f: &mut Frame, app: &App) {
                 "  ".to_string() // Normal network
             };
 
-            let line = format!(
-                "{}{} {} {} ({}dBm)",
-                prefix, security_icon, network.ssid, signal_bars, network.signal_strength
+            let mut line = format!(
+                "{}{} {} {} ({})",
+                prefix,
+                security_icon,
+                network.ssid,
+                signal_bars,
+                signal.format(network.signal_strength)
             );
 
+            // Several access points share this SSID: best signal shown, expand for each
+            let expanded = app.wifi_expanded.contains(&network.ssid);
+            if network.access_points.len() > 1 {
+                let mut bands: Vec<&str> =
+                    network.access_points.iter().map(|ap| ap.band()).collect();
+                bands.sort();
+                bands.dedup();
+                line.push_str(&format!(
+                    " {} {} APs, {}",
+                    if expanded { "▾" } else { "▸" },
+                    network.access_points.len(),
+                    bands.join(" + ")
+                ));
+            }
+            if let Some(expiry) = profile.and_then(|_| app.cert_expiry(&network.ssid)) {
+                let now = chrono::Utc::now();
+                if expiry.not_after <= now {
+                    line.push_str(" | certificate expired");
+                } else {
+                    let days = expiry.days_left(now);
+                    line.push_str(&format!(" | certificate expires in {}d", days));
+                }
+            }
+            if let Some(band) = profile
+                .map(|profile| profile.band)
+                .filter(|&band| band != crate::network::WifiBand::Any)
+            {
+                line.push_str(&format!(" | {} only", band.label()));
+            } else if let Some(better) = network.band_steering_hint() {
+                line.push_str(&format!(
+                    " | {} available ({})",
+                    better.band(),
+                    signal.format(better.signal_strength)
+                ));
+            }
+
             let style = if network.connected {
                 Style::default()
                     .fg(Color::Green)
@@ -539,13 +1615,58 @@ fn draw_wifi_dialog(f: &mut Frame, app: &App) {
             };
 
             networks.push(ListItem::new(line).style(style));
+
+            if expanded && network.access_points.len() > 1 {
+                for ap in &network.access_points {
+                    let ap_line = format!(
+                        "      {} {}  {} ch {}  {}{}",
+                        if ap.connected { icons::CONNECTED } else { "·" },
+                        ap.bssid,
+                        ap.band(),
+                        ap.channel,
+                        signal.format(ap.signal_strength),
+                        if ap.connected { "  (associated)" } else { "" }
+                    );
+                    let ap_style = if ap.connected {
+                        Style::default().fg(Color::Green)
+                    } else {
+                        Style::default().fg(Color::Gray)
+                    };
+                    networks.push(ListItem::new(ap_line).style(ap_style));
+                }
+            }
+
+            if app.wifi_show_details && i == app.selected_wifi_index {
+                networks.extend(
+                    wifi_security_lines(network)
+                        .into_iter()
+                        .map(|line| ListItem::new(line).style(Style::default().fg(Color::Gray))),
+                );
+            }
         }
     }
 
+    // Cached results are shown until the background scan replaces them
+    let cache_note = app
+        .wifi_cached_since
+        .and_then(|since| since.elapsed().ok())
+        .map(|age| {
+            if app.wifi_scanning {
+                format!(
+                    " (cached {} ago, {} scanning...)",
+                    locale::duration(age),
+                    icons::SCANNING
+                )
+            } else {
+                format!(" (cached {} ago)", locale::duration(age))
+            }
+        })
+        .unwrap_or_default();
+
     let block = Block::default()
         .borders(Borders::ALL)
-        .title(format!("{} WiFi Networks [{} = Saved, {} = Auto | a: Auto | e: Enterprise | d: Diagnostics | ↑/↓: Navigate | Enter: Connect | r: Scan | Esc: Close]", 
-            icons::WIFI, icons::HISTORY, icons::AUTO_CONNECT))
+        .title(format!("{} WiFi Networks{} [{} = Saved, {} = Auto | a: Auto | e: Enterprise | i: Details | ←/→: APs | d: Diagnostics | ↑/↓: Navigate | Enter: Connect | r: Scan | Esc: Close]", 
+            icons::WIFI, cache_note, icons::HISTORY, icons::AUTO_CONNECT))
         .border_style(Style::default().fg(Color::Cyan));
 
     let wifi_list = List::new(networks)
@@ -555,6 +1676,42 @@ fn draw_wifi_dialog(f: &mut Frame, app: &App) {
     f.render_widget(wifi_list, area);
 }
 
+/// The expanded details row under a network in the WiFi list.
+fn wifi_security_lines(network: &crate::network::WifiNetwork) -> Vec<String> {
+    use crate::wifi_ies::ManagementFrameProtection;
+
+    let details = &network.security_details;
+    let list = |items: &[String]| {
+        if items.is_empty() {
+            "none".to_string()
+        } else {
+            items.join(" ")
+        }
+    };
+
+    vec![
+        format!(
+            "      BSSID {} | {} MHz, channel {}",
+            network.bssid, network.frequency, network.channel
+        ),
+        format!(
+            "      Ciphers: {} (group {}) | AKM: {}",
+            list(&network.encryption),
+            details.group_cipher.as_deref().unwrap_or("none"),
+            list(&details.akm_suites)
+        ),
+        format!(
+            "      802.11w: {} | WPS: {}",
+            match details.mfp {
+                ManagementFrameProtection::Required => "required",
+                ManagementFrameProtection::Capable => "capable",
+                ManagementFrameProtection::Disabled => "off",
+            },
+            if details.wps { "available" } else { "no" }
+        ),
+    ]
+}
+
 fn draw_wifi_connect_dialog(f: &mut Frame, app: &App) {
     let area = centered_rect(60, 50, f.area());
     f.render_widget(Clear, area);
@@ -969,11 +2126,24 @@ fn draw_hotspot_dialog(f: &mut Frame, app: &App) {
         Style::default()
     };
     let password_text = "*".repeat(app.hotspot_password_input.value().len());
+    let entropy_bits = crate::password::estimate_entropy_bits(app.hotspot_password_input.value());
+    let strength = crate::password::Strength::from_bits(entropy_bits);
+    let strength_color = match strength {
+        crate::password::Strength::VeryWeak | crate::password::Strength::Weak => Color::Red,
+        crate::password::Strength::Fair => Color::Yellow,
+        _ => Color::Green,
+    };
     let password_input = Paragraph::new(password_text)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Password (min 8 chars)"),
+                .title(Line::from(vec![
+                    Span::raw("Password (min 8 chars) "),
+                    Span::styled(
+                        format!("[{} ~{:.0} bits]", strength.label(), entropy_bits),
+                        Style::default().fg(strength_color),
+                    ),
+                ])),
         )
         .style(password_style);
     f.render_widget(password_input, chunks[1]);
@@ -984,7 +2154,11 @@ fn draw_hotspot_dialog(f: &mut Frame, app: &App) {
     } else {
         Style::default()
     };
-    let channel_text = format!("Channel {}", app.hotspot_channel);
+    let channel_text = if app.hotspot_channel == crate::hotspot::AUTO_CHANNEL {
+        "Auto (least congested of 1, 6 and 11, surveyed on start)".to_string()
+    } else {
+        format!("Channel {}", app.hotspot_channel)
+    };
     let channel_input = Paragraph::new(channel_text)
         .block(
             Block::default()
@@ -996,7 +2170,7 @@ fn draw_hotspot_dialog(f: &mut Frame, app: &App) {
 
     // Instructions
     let instructions = Paragraph::new(
-        "Tab: Next field | Space: Cycle Channel | Enter: Create Hotspot | Esc: Cancel",
+        "Tab: Next field | Space: Cycle Channel | Ctrl+G: Generate Password | Enter: Create Hotspot | Esc: Cancel",
     )
     .wrap(ratatui::widgets::Wrap { trim: true })
     .style(Style::default().fg(Color::Yellow));
@@ -1004,6 +2178,7 @@ fn draw_hotspot_dialog(f: &mut Frame, app: &App) {
 }
 
 fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
+    let locale = app.config.display.locale();
     let area = centered_rect(85, 85, f.area());
     f.render_widget(Clear, area);
 
@@ -1022,11 +2197,12 @@ fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
             .direction(Direction::Vertical)
             .margin(1)
             .constraints([
-                Constraint::Length(8), // Connection Info
-                Constraint::Length(6), // Signal & Performance
-                Constraint::Length(8), // Network Statistics
-                Constraint::Min(1),    // Advanced Details
-                Constraint::Length(2), // Instructions
+                Constraint::Length(8),  // Connection Info
+                Constraint::Length(11), // Signal & Performance
+                Constraint::Length(8),  // Network Statistics
+                Constraint::Length(7),  // Advanced Details
+                Constraint::Min(3),     // Recent 802.11 events
+                Constraint::Length(2),  // Instructions
             ])
             .split(inner);
 
@@ -1064,7 +2240,7 @@ fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
                     Style::default().add_modifier(Modifier::BOLD),
                 ),
                 Span::raw(if let Some(uptime) = &diagnostics.connected_time {
-                    format!("{} minutes", uptime.as_secs() / 60)
+                    locale::duration(*uptime)
                 } else {
                     "Unknown".to_string()
                 }),
@@ -1097,7 +2273,10 @@ fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
                     Style::default().add_modifier(Modifier::BOLD),
                 ),
                 Span::styled(
-                    format!("{} dBm", diagnostics.signal_strength),
+                    app.config
+                        .display
+                        .signal
+                        .format(diagnostics.signal_strength),
                     Style::default().fg(signal_color),
                 ),
             ]),
@@ -1130,6 +2309,34 @@ fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
                     "Unknown".to_string()
                 }),
             ]),
+            Line::from(vec![
+                Span::styled("Mode: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(
+                    wifi_rate::mode_summary(
+                        diagnostics.tx_rate.as_ref(),
+                        diagnostics.rx_rate.as_ref(),
+                    )
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("TX Rate: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(
+                    diagnostics
+                        .tx_rate
+                        .as_ref()
+                        .map_or_else(|| "Unknown".to_string(), |rate| rate.summary()),
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("RX Rate: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(
+                    diagnostics
+                        .rx_rate
+                        .as_ref()
+                        .map_or_else(|| "Unknown".to_string(), |rate| rate.summary()),
+                ),
+            ]),
         ];
 
         let signal_widget = Paragraph::new(signal_info).block(
@@ -1157,16 +2364,16 @@ fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
                 Span::styled("📥 RX: ", Style::default().fg(Color::Green)),
                 Span::raw(format!(
                     "{:.2} ({} packets)",
-                    rx_bytes.get_appropriate_unit(byte_unit::UnitType::Binary),
-                    diagnostics.rx_packets
+                    rx_bytes.get_appropriate_unit(app.config.display.unit_type()),
+                    locale.count(diagnostics.rx_packets)
                 )),
             ]),
             Line::from(vec![
                 Span::styled("📤 TX: ", Style::default().fg(Color::Blue)),
                 Span::raw(format!(
                     "{:.2} ({} packets)",
-                    tx_bytes.get_appropriate_unit(byte_unit::UnitType::Binary),
-                    diagnostics.tx_packets
+                    tx_bytes.get_appropriate_unit(app.config.display.unit_type()),
+                    locale.count(diagnostics.tx_packets)
                 )),
             ]),
             Line::from(vec![
@@ -1208,6 +2415,17 @@ fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
                 } else {
                     "Unknown".to_string()
                 }),
+                Span::styled(
+                    match app.get_selected_interface().and_then(|interface| {
+                        app.config
+                            .get_wifi_profile(&diagnostics.ssid, &interface.name)?
+                            .tx_power_dbm
+                    }) {
+                        Some(_) => " (fixed for this network)",
+                        None => " (driver's choice)",
+                    },
+                    Style::default().fg(Color::Gray),
+                ),
             ]),
             Line::from(vec![
                 Span::styled(
@@ -1260,12 +2478,15 @@ fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
                 .title("Advanced Details"),
         );
         f.render_widget(advanced_widget, chunks[3]);
+        draw_frame_events(f, app, chunks[4]);
 
         // Instructions
-        let instructions = Paragraph::new("Press Esc to close | r: Refresh diagnostics")
-            .style(Style::default().fg(Color::Gray))
-            .alignment(Alignment::Center);
-        f.render_widget(instructions, chunks[4]);
+        let instructions = Paragraph::new(
+            "Press Esc to close | r: Refresh diagnostics | +/-: TX power | a: Automatic TX power",
+        )
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center);
+        f.render_widget(instructions, chunks[5]);
     } else {
         // No WiFi connection or data available
         let no_data = vec![
@@ -1284,6 +2505,12 @@ fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
             Line::from("• Ensure the interface is UP"),
         ];
 
+        // Right after a drop is when the reason matters most
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(13), Constraint::Min(3)])
+            .split(inner);
+
         let no_data_widget = Paragraph::new(no_data)
             .block(
                 Block::default()
@@ -1291,6 +2518,2839 @@ fn draw_wifi_diagnostics_dialog(f: &mut Frame, app: &App) {
                     .title("No Data Available"),
             )
             .alignment(Alignment::Center);
-        f.render_widget(no_data_widget, inner);
+        f.render_widget(no_data_widget, chunks[0]);
+        draw_frame_events(f, app, chunks[1]);
+    }
+}
+
+fn draw_frame_events(f: &mut Frame, app: &App, area: Rect) {
+    let locale = app.config.display.locale();
+    let events = app.selected_frame_events();
+    let lines: Vec<Line> = if events.is_empty() {
+        vec![Line::from(Span::styled(
+            "No disconnects, deauths or beacon loss seen since Lantern started",
+            Style::default().fg(Color::Gray),
+        ))]
+    } else {
+        events
+            .iter()
+            .map(|seen| {
+                let color = if seen.event.is_drop() {
+                    Color::Red
+                } else {
+                    Color::Yellow
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{} ", locale.time(&seen.timestamp)),
+                        Style::default().fg(Color::Gray),
+                    ),
+                    Span::styled(seen.event.summary(), Style::default().fg(color)),
+                ])
+            })
+            .collect()
+    };
+
+    let widget = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Recent 802.11 Events"),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(widget, area);
+}
+
+fn draw_hotplug_dialog(f: &mut Frame, app: &App) {
+    let Some(offer) = &app.hotplug_offer else {
+        return;
+    };
+
+    let width = 56.min(f.area().width);
+    let height = 9.min(f.area().height);
+    let area = Rect {
+        x: f.area().x + (f.area().width.saturating_sub(width)) / 2,
+        y: f.area().y + (f.area().height.saturating_sub(height)) / 2,
+        width,
+        height,
+    };
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!("{} Interface Plugged In", icons::ETHERNET))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let addressing = if offer.profile.dhcp {
+        "DHCP".to_string()
+    } else {
+        offer
+            .profile
+            .ip
+            .clone()
+            .unwrap_or_else(|| "static".to_string())
+    };
+    let lines = vec![
+        Line::from(vec![
+            Span::styled(
+                &offer.interface,
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(" {}", offer.hardware)),
+        ]),
+        Line::from(""),
+        Line::from(format!(
+            "Apply saved profile '{}' ({})?",
+            offer.profile.name, addressing
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            "y/Enter: Apply | n/Esc: Ignore",
+            Style::default().fg(Color::Gray),
+        )),
+    ];
+
+    let dialog = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(dialog, area);
+}
+
+fn draw_perf_overlay(f: &mut Frame, app: &App) {
+    let summaries = app.timings.summaries();
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(Span::styled(
+        format!("{:<11}{:>9}{:>9}{:>9}", "", "last", "avg", "max"),
+        bold,
+    ))];
+    if summaries.is_empty() {
+        lines.push(Line::from("No samples yet"));
+    }
+    for (measure, summary) in &summaries {
+        let ms = |d: std::time::Duration| format!("{:.1}ms", d.as_secs_f64() * 1000.0);
+        lines.push(Line::from(format!(
+            "{:<11}{:>9}{:>9}{:>9}",
+            measure.label(),
+            ms(summary.last),
+            ms(summary.average),
+            ms(summary.max)
+        )));
+    }
+
+    // Top-right corner, clear of the header's title
+    let screen = f.area();
+    let width = 42.min(screen.width);
+    let height = (lines.len() as u16 + 2).min(screen.height.saturating_sub(1));
+    let area = Rect::new(screen.x + screen.width - width, screen.y + 1, width, height);
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .title(" Timings (F12) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Magenta)),
+        ),
+        area,
+    );
+}
+
+fn draw_routing_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 70, f.area());
+    f.render_widget(Clear, area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let mut lines = Vec::new();
+    match &app.routing {
+        None => lines.push(Line::from("Querying routing daemon...")),
+        Some(status) if status.daemon.is_none() => {
+            lines.push(Line::from("No routing daemon found"));
+            lines.push(Line::from(Span::styled(
+                "Looked for FRR's vtysh and BIRD's control socket",
+                Style::default().fg(Color::Gray),
+            )));
+        }
+        Some(status) => {
+            if let Some(error) = &status.error {
+                lines.push(Line::from(Span::styled(
+                    error.clone(),
+                    Style::default().fg(Color::Red),
+                )));
+            }
+
+            let established = status.neighbors.iter().filter(|n| n.established).count();
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "Neighbors ({} of {} up):",
+                    established,
+                    status.neighbors.len()
+                ),
+                bold,
+            )));
+            if status.neighbors.is_empty() {
+                lines.push(Line::from("  None configured"));
+            }
+            for neighbor in &status.neighbors {
+                let mut spans = vec![
+                    Span::styled(
+                        format!("  {:<7}", neighbor.protocol),
+                        Style::default().fg(Color::Gray),
+                    ),
+                    Span::raw(format!("{:<32} ", neighbor.name)),
+                    Span::styled(
+                        neighbor.state.clone(),
+                        Style::default().fg(if neighbor.established {
+                            Color::Green
+                        } else {
+                            Color::Red
+                        }),
+                    ),
+                ];
+                if let Some(uptime) = &neighbor.uptime {
+                    spans.push(Span::styled(
+                        format!(" | {}", uptime),
+                        Style::default().fg(Color::Gray),
+                    ));
+                }
+                if let Some(prefixes) = neighbor.prefixes {
+                    spans.push(Span::raw(format!(" | {} prefixes", prefixes)));
+                }
+                lines.push(Line::from(spans));
+            }
+
+            if !status.routes.is_empty() {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled("Routes:", bold)));
+                for (source, count) in &status.routes {
+                    lines.push(Line::from(format!("  {:<16} {}", source, count)));
+                }
+            }
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Read-only, refreshed every 5s | Esc: Close",
+        Style::default().fg(Color::Gray),
+    )));
+
+    let daemon = app
+        .routing
+        .as_ref()
+        .and_then(|status| status.daemon)
+        .map(|daemon| format!(" ({})", daemon.name()))
+        .unwrap_or_default();
+    let dialog = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(format!("Routing Daemon{}", daemon))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(dialog, area);
+}
+
+fn draw_topology_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 70, f.area());
+    f.render_widget(Clear, area);
+    let gray = Style::default().fg(Color::Gray);
+
+    let nodes = topology::build(&app.interfaces, &app.containers);
+    let mut lines = Vec::new();
+    if nodes.is_empty() {
+        lines.push(Line::from("No interfaces"));
+    }
+    for node in &nodes {
+        let mut spans = vec![
+            Span::styled(node.prefix.clone(), Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                format!("{} ", node.kind.icon(node.wireless)),
+                Style::default().fg(kind_color(node.kind)),
+            ),
+            Span::styled(
+                node.name.clone(),
+                if node.up {
+                    Style::default().add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::Red)
+                },
+            ),
+        ];
+        if node.kind != LinkKind::Physical {
+            spans.push(Span::styled(format!(" {}", node.kind.label()), gray));
+        }
+        if let Some(relation) = node.relation {
+            spans.push(Span::styled(
+                format!(" ({})", relation),
+                Style::default().fg(Color::Cyan),
+            ));
+        }
+        for note in &node.notes {
+            spans.push(Span::styled(
+                format!("  {}", note),
+                Style::default().fg(Color::LightBlue),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Ports under their bridge or bond, VLANs under their parent | Red: down | Esc: Close",
+        gray,
+    )));
+
+    let dialog = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(format!("{} Topology", icons::NETWORK))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(dialog, area);
+}
+
+fn draw_survey_panel(f: &mut Frame, app: &App) {
+    let Some(survey) = &app.survey else {
+        return;
+    };
+    let area = centered_rect(80, 70, f.area());
+    f.render_widget(Clear, area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let gray = Style::default().fg(Color::Gray);
+
+    let settings = &app.config.survey;
+    let gps = if !settings.gps {
+        Span::styled("off ([survey] gps = false)", gray)
+    } else {
+        match survey.gps {
+            GpsStatus::Fix(fix) => Span::styled(
+                format!(
+                    "{} fix {:.5}, {:.5}{}",
+                    if fix.altitude.is_some() { "3D" } else { "2D" },
+                    fix.latitude,
+                    fix.longitude,
+                    fix.altitude
+                        .map(|altitude| format!(", {:.0} m", altitude))
+                        .unwrap_or_default()
+                ),
+                Style::default().fg(Color::Green),
+            ),
+            GpsStatus::NoFix => Span::styled(
+                "no fix yet; samples are recorded without a position",
+                Style::default().fg(Color::Yellow),
+            ),
+            GpsStatus::Unavailable => {
+                Span::styled(format!("gpsd not reachable at {}", settings.gpsd), gray)
+            }
+        }
+    };
+    let mut lines = vec![
+        Line::from(vec![Span::styled("GPS: ", bold), gps]),
+        Line::from(vec![
+            Span::styled("Recorded: ", bold),
+            Span::raw(format!(
+                "{} scans, {} samples, {} access points | scanning every {}s",
+                survey.scans,
+                survey.records.len(),
+                survey.access_points(),
+                settings.interval_secs.max(1)
+            )),
+        ]),
+    ];
+    if let Some(error) = &survey.error {
+        lines.push(Line::from(Span::styled(
+            format!("Last scan failed: {}", error),
+            Style::default().fg(Color::Red),
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!(
+            "{:<28} {:<17} {:>4} {:>7}  {}",
+            "SSID", "BSSID", "Ch", "Signal", "Security"
+        ),
+        bold,
+    )));
+    if survey.scans == 0 {
+        lines.push(Line::from(Span::styled(
+            "Waiting for the first scan...",
+            gray,
+        )));
+    }
+    // What fits between the header lines and the footer
+    let room = (area.height as usize).saturating_sub(lines.len() + 4);
+    for record in survey.last_scan.iter().take(room) {
+        let signal_color = match record.signal_dbm {
+            s if s > -60 => Color::Green,
+            s if s > -70 => Color::Yellow,
+            _ => Color::Red,
+        };
+        let ssid: String = if record.ssid.is_empty() {
+            "<hidden>".to_string()
+        } else {
+            record.ssid.chars().take(28).collect()
+        };
+        lines.push(Line::from(vec![
+            Span::raw(format!(
+                "{:<28} {:<17} {:>4} ",
+                ssid, record.bssid, record.channel
+            )),
+            Span::styled(
+                format!("{:>7}", app.config.display.signal.format(record.signal_dbm)),
+                Style::default().fg(signal_color),
+            ),
+            Span::styled(format!("  {}", record.security), gray),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Latest scan, strongest first | x: Save CSV now | Esc: Stop and save",
+        gray,
+    )));
+
+    let dialog = Paragraph::new(lines).block(
+        Block::default()
+            .title(format!(
+                "{} WiFi Survey from {}",
+                icons::WIFI,
+                survey.interface
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(dialog, area);
+}
+
+fn draw_rf_monitor_panel(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 70, f.area());
+    f.render_widget(Clear, area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let gray = Style::default().fg(Color::Gray);
+    let block = Block::default()
+        .title(format!(
+            "{} RF Monitor on {}",
+            icons::WIFI,
+            app.rf_interface
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let Some(session) = &app.rf_session else {
+        // Nothing listens before this is confirmed
+        let lines = vec![
+            Line::from(Span::styled("Listen passively to the radio?", bold)),
+            Line::from(""),
+            Line::from(format!(
+                "A monitor interface is added to {}'s radio and receives management frames:",
+                app.rf_interface
+            )),
+            Line::from("  - beacons of hidden networks, and the probe responses that name them"),
+            Line::from("  - probe requests, with the networks nearby devices remember"),
+            Line::from(""),
+            Line::from("Nothing is transmitted. While connected, the monitor stays on the"),
+            Line::from(
+                "current channel; otherwise it walks the 2.4 GHz and non-DFS 5 GHz channels.",
+            ),
+            Line::from(""),
+            Line::from(Span::styled(
+                "Only listen where you are allowed to: probe requests identify people's devices.",
+                Style::default().fg(Color::Yellow),
+            )),
+            Line::from(""),
+            Line::from(Span::styled("Enter: Start listening | Esc: Cancel", gray)),
+        ];
+        f.render_widget(Paragraph::new(lines).block(block), area);
+        return;
+    };
+
+    let survey = &session.survey;
+    let elapsed = session.started.elapsed().as_secs();
+    let (state, state_style) = match &session.capture {
+        Capture::Starting | Capture::Listening => (
+            format!(
+                "listening on {} for {}m {:02}s",
+                session.monitor,
+                elapsed / 60,
+                elapsed % 60
+            ),
+            Style::default().fg(Color::Green),
+        ),
+        Capture::Stopped(None) => ("stopped".to_string(), gray),
+        Capture::Stopped(Some(error)) => (error.clone(), Style::default().fg(Color::Red)),
+    };
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Capture: ", bold),
+            Span::styled(state, state_style),
+        ]),
+        Line::from(vec![
+            Span::styled("Channels: ", bold),
+            Span::raw(if session.hop {
+                "hopping".to_string()
+            } else {
+                format!("{}'s current channel", session.interface)
+            }),
+            Span::styled(format!(" | {} frames kept", survey.frames), gray),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!(
+                "Hidden networks: {} ({} named)",
+                survey.hidden.len(),
+                survey.revealed()
+            ),
+            bold,
+        )),
+    ];
+    let signal = |signal: Option<i8>| {
+        signal
+            .map(|dbm| app.config.display.signal.format(dbm as i32))
+            .unwrap_or_default()
+    };
+    let channel = |frequency: Option<u16>| {
+        frequency
+            .map(|mhz| crate::network::frequency_to_channel(mhz as u32).to_string())
+            .unwrap_or_default()
+    };
+    // Split what fits between the two lists
+    let room = (area.height as usize).saturating_sub(lines.len() + 7) / 2;
+    for network in survey.hidden.values().take(room) {
+        let (name, style) = match &network.ssid {
+            Some(ssid) => (ssid.clone(), Style::default().fg(Color::Green)),
+            None => ("<not named yet>".to_string(), gray),
+        };
+        lines.push(Line::from(vec![
+            Span::raw(format!("  {:<17} ", network.bssid)),
+            Span::styled(format!("{:<28}", name), style),
+            Span::raw(format!(
+                " {:>4} {:>7}",
+                channel(network.frequency),
+                signal(network.signal)
+            )),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("Devices probing: {}", survey.clients.len()),
+        bold,
+    )));
+    for client in survey.clients.values().take(room) {
+        let probed = if client.probed.is_empty() {
+            "any network".to_string()
+        } else {
+            client.probed.join(", ")
+        };
+        lines.push(Line::from(vec![
+            Span::raw(format!("  {:<17} ", client.mac)),
+            Span::styled(
+                if crate::rfmon::is_randomized(&client.mac) {
+                    "random "
+                } else {
+                    "       "
+                },
+                gray,
+            ),
+            Span::raw(format!("{:>7}  ", signal(client.signal))),
+            Span::styled(probed, Style::default().fg(Color::Cyan)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Esc: Stop listening and close",
+        gray,
+    )));
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_packet_panel(f: &mut Frame, app: &App) {
+    let area = centered_rect(90, 70, f.area());
+    f.render_widget(Clear, area);
+    let Some(view) = &app.packet_view else {
+        return;
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let gray = Style::default().fg(Color::Gray);
+    let block = Block::default()
+        .title(format!("{} Packets on {}", icons::NETWORK, view.interface))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let elapsed = view.started.elapsed().as_secs();
+    let (state, state_style) = match &view.capture {
+        Capture::Starting | Capture::Listening => (
+            format!("listening for {}m {:02}s", elapsed / 60, elapsed % 60),
+            Style::default().fg(Color::Green),
+        ),
+        Capture::Stopped(None) => ("stopped".to_string(), gray),
+        Capture::Stopped(Some(error)) => (error.clone(), Style::default().fg(Color::Red)),
+    };
+    let bytes = Byte::from_u64(view.bytes).get_appropriate_unit(app.config.display.unit_type());
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Capture: ", bold),
+            Span::styled(state, state_style),
+        ]),
+        Line::from(vec![
+            Span::styled("Seen: ", bold),
+            Span::raw(format!("{} packets, {:.1}", view.seen, bytes)),
+            Span::styled(
+                format!(
+                    " | up to {}/s listed, {} only counted",
+                    crate::packets::SAMPLES_PER_SECOND,
+                    view.skipped()
+                ),
+                gray,
+            ),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!(
+                "  {:<3} {:<7} {:<40} {:<40} {:>6}  {}",
+                "", "Proto", "Source", "Destination", "Length", "Info"
+            ),
+            bold,
+        )),
+    ];
+    if view.recent.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  Nothing has arrived or left yet",
+            gray,
+        )));
+    }
+    // Newest at the bottom, as they scroll in
+    let room = (area.height as usize).saturating_sub(lines.len() + 4);
+    let skip = view.recent.len().saturating_sub(room);
+    for packet in view.recent.iter().skip(skip) {
+        let (arrow, color) = match packet.direction {
+            crate::packets::Direction::In => ("<-", Color::Green),
+            crate::packets::Direction::Out => ("->", Color::Blue),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<3} ", arrow), Style::default().fg(color)),
+            Span::styled(
+                format!("{:<7} ", packet.protocol),
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::raw(format!(
+                "{:<40} {:<40} {:>6}  ",
+                packet.source, packet.destination, packet.length
+            )),
+            Span::styled(packet.info.clone(), gray),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("v/Esc: Stop and close", gray)));
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_hotspot_clients_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 60, f.area());
+    f.render_widget(Clear, area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let clients = &app.hotspot_clients.clients;
+
+    let mut lines = Vec::new();
+    if let Some(error) = &app.hotspot_clients_error {
+        lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+    lines.push(Line::from(Span::styled(
+        format!(
+            "  {:<20} {:<15} {:>7} {:>12} {:>12} {:>10} {:>10}",
+            "Client", "Address", "Signal", "Down", "Up", "Received", "Sent"
+        ),
+        bold,
+    )));
+    if clients.is_empty() {
+        lines.push(Line::from("  No clients connected"));
+    }
+    for (i, client) in clients.iter().enumerate() {
+        let station = &client.station;
+        let reserved = app
+            .config
+            .hotspot
+            .reservations
+            .iter()
+            .any(|r| r.mac == station.mac);
+        let row = if i == app.selected_hotspot_client {
+            Style::default().bg(Color::Blue)
+        } else {
+            Style::default()
+        };
+        let signal_color = match station.signal {
+            Some(s) if s > -60 => Color::Green,
+            Some(s) if s > -70 => Color::Yellow,
+            Some(_) => Color::Red,
+            None => Color::Gray,
+        };
+        let total = |bytes: u64| {
+            format!(
+                "{:.1}",
+                Byte::from_u64(bytes).get_appropriate_unit(app.config.display.unit_type())
+            )
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} ", if reserved { icons::PIN } else { " " }),
+                row.fg(Color::Cyan),
+            ),
+            Span::styled(format!("{:<20} ", client.label()), row),
+            Span::styled(
+                format!("{:<15} ", station.ip.as_deref().unwrap_or(&station.mac)),
+                row.fg(Color::Gray),
+            ),
+            Span::styled(
+                format!(
+                    "{:>7} ",
+                    station
+                        .signal
+                        .map(|s| app.config.display.signal.format(s))
+                        .unwrap_or_default()
+                ),
+                row.fg(signal_color),
+            ),
+            // Down is what the AP sends the client, up what it receives from it
+            Span::styled(
+                format!("{:>12} ", app.config.display.rate(client.tx_rate)),
+                row.fg(Color::Green),
+            ),
+            Span::styled(
+                format!("{:>12} ", app.config.display.rate(client.rx_rate)),
+                row.fg(Color::LightBlue),
+            ),
+            Span::styled(
+                format!(
+                    "{:>10} {:>10}",
+                    total(station.tx_bytes),
+                    total(station.rx_bytes)
+                ),
+                row,
+            ),
+        ]));
+    }
+    let down: u64 = clients.iter().map(|c| c.tx_rate).sum();
+    let up: u64 = clients.iter().map(|c| c.rx_rate).sum();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!(
+            "{} clients, {} down / {} up | busiest first, every 2s | p: Pin address | r: Reservations | f: Port forwards | h/Esc: Close",
+            clients.len(),
+            app.config.display.rate(down),
+            app.config.display.rate(up)
+        ),
+        Style::default().fg(Color::Gray),
+    )));
+
+    let ssid = app
+        .active_hotspot
+        .as_ref()
+        .map(|hotspot| format!(" '{}'", hotspot.ssid))
+        .unwrap_or_default();
+    let dialog = Paragraph::new(lines).block(
+        Block::default()
+            .title(format!("{} Hotspot{} Clients", icons::HOTSPOT, ssid))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(dialog, area);
+}
+
+fn draw_reservations_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 60, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!("{} Hotspot DHCP Reservations", icons::PIN))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(if app.modals.is_open(Modal::ReservationForm) {
+            [
+                Constraint::Min(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ]
+        } else {
+            [
+                Constraint::Min(3),
+                Constraint::Length(0),
+                Constraint::Length(0),
+                Constraint::Length(0),
+            ]
+        })
+        .split(inner);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let reservations = &app.config.hotspot.reservations;
+    let mut lines = vec![Line::from(Span::styled(
+        format!("  {:<18} {:<15} {}", "MAC", "Address", "Hostname"),
+        bold,
+    ))];
+    if reservations.is_empty() {
+        lines.push(Line::from(
+            "  No reservations; pin a client with p in the clients view",
+        ));
+    }
+    for (i, reservation) in reservations.iter().enumerate() {
+        let style = if i == app.selected_reservation && !app.modals.is_open(Modal::ReservationForm)
+        {
+            Style::default().bg(Color::Blue).fg(Color::White)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  {:<18} {:<15} {}",
+                reservation.mac,
+                reservation.ip,
+                reservation.hostname.as_deref().unwrap_or("-")
+            ),
+            style,
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        if app.modals.is_open(Modal::ReservationForm) {
+            "Tab: Next field | Enter: Save | Esc: Cancel"
+        } else {
+            "a: Add | e/Enter: Edit | d: Remove | r/Esc: Back to clients"
+        },
+        Style::default().fg(Color::Gray),
+    )));
+    lines.push(Line::from(Span::styled(
+        "Clients get their reserved address when they next renew their lease",
+        Style::default().fg(Color::Gray),
+    )));
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    if app.modals.is_open(Modal::ReservationForm) {
+        let fields = [
+            (&app.reservation_mac_input, "MAC Address"),
+            (&app.reservation_ip_input, "IP Address (in 192.168.4.0/24)"),
+            (&app.reservation_hostname_input, "Hostname (optional)"),
+        ];
+        for (index, (input, title)) in fields.into_iter().enumerate() {
+            let style = if app.reservation_active_input == index {
+                Style::default().bg(Color::Blue).fg(Color::White)
+            } else {
+                Style::default()
+            };
+            let field = Paragraph::new(input.value())
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .style(style);
+            f.render_widget(field, chunks[index + 1]);
+        }
+    }
+}
+
+fn draw_port_forward_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 80, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!("{} Hotspot Port Forwarding", icons::HOTSPOT))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let field_height = if app.modals.is_open(Modal::PortForwardForm) {
+        3
+    } else {
+        0
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length(field_height),
+            Constraint::Length(field_height),
+            Constraint::Length(field_height),
+            Constraint::Length(field_height),
+            Constraint::Length(field_height),
+        ])
+        .split(inner);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let forwards = &app.config.hotspot.port_forwards;
+    let mut lines = vec![Line::from(Span::styled(
+        format!("  {:<8} {:>8}   {}", "Protocol", "Port", "Forwarded to"),
+        bold,
+    ))];
+    if forwards.is_empty() {
+        lines.push(Line::from("  No ports forwarded"));
+    }
+    for (i, forward) in forwards.iter().enumerate() {
+        let style = if i == app.selected_port_forward && !app.modals.is_open(Modal::PortForwardForm)
+        {
+            Style::default().bg(Color::Blue).fg(Color::White)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  {:<8} {:>8} → {}:{}",
+                forward.protocol.label(),
+                forward.external_port,
+                forward.internal_ip,
+                forward.internal_port
+            ),
+            style,
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        if app.modals.is_open(Modal::PortForwardForm) {
+            "Space: Change protocol | Tab: Next field | Enter: Save | Esc: Cancel"
+        } else {
+            "a: Add for the selected client | e/Enter: Edit | d: Remove | f/Esc: Back to clients"
+        },
+        Style::default().fg(Color::Gray),
+    )));
+    lines.push(Line::from(Span::styled(
+        "Connections to this machine's port are passed on while the hotspot runs",
+        Style::default().fg(Color::Gray),
+    )));
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    if app.modals.is_open(Modal::PortForwardForm) {
+        let fields = [
+            (app.port_forward_protocol.label(), "Protocol"),
+            (app.port_forward_external_input.value(), "External Port"),
+            (
+                app.port_forward_ip_input.value(),
+                "Client IP Address (in 192.168.4.0/24)",
+            ),
+            (
+                app.port_forward_internal_input.value(),
+                "Client Port (empty: same as external)",
+            ),
+            (
+                app.port_forward_revert_input.value(),
+                "Revert After (minutes, empty: keep)",
+            ),
+        ];
+        for (index, (value, title)) in fields.into_iter().enumerate() {
+            let style = if app.port_forward_active_input == index {
+                Style::default().bg(Color::Blue).fg(Color::White)
+            } else {
+                Style::default()
+            };
+            let field = Paragraph::new(value)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .style(style);
+            f.render_widget(field, chunks[index + 1]);
+        }
+    }
+}
+
+fn draw_pxe_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(75, 75, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!("{} PXE Boot Server", icons::NETWORK))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let gray = Style::default().fg(Color::Gray);
+
+    if let Some(server) = &app.pxe_server {
+        let config = &server.config;
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(6), Constraint::Min(3)])
+            .split(inner);
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let mut boot_files = config.bios_boot_file.clone();
+        if let Some(efi) = &config.efi_boot_file {
+            boot_files = format!("{} (BIOS), {} (UEFI)", boot_files, efi);
+        }
+        let lines = vec![
+            Line::from(vec![
+                Span::styled("Serving on: ", bold),
+                Span::styled(
+                    format!("{} ({})", config.interface, config.network),
+                    Style::default().fg(Color::Green),
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Mode: ", bold),
+                Span::raw(config.mode.label()),
+            ]),
+            Line::from(vec![
+                Span::styled("TFTP root: ", bold),
+                Span::raw(&config.tftp_root),
+            ]),
+            Line::from(vec![
+                Span::styled("Boot files: ", bold),
+                Span::raw(boot_files),
+            ]),
+            Line::from(Span::styled(
+                "s: Stop the server | Esc: Close (the server keeps running)",
+                gray,
+            )),
+        ];
+        f.render_widget(Paragraph::new(lines), chunks[0]);
+
+        // The newest lines that fit
+        let log = server.log();
+        let height = chunks[1].height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = log
+            .iter()
+            .skip(log.len().saturating_sub(height))
+            .map(|line| Line::from(line.as_str()))
+            .collect();
+        let log = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("dnsmasq log"));
+        f.render_widget(log, chunks[1]);
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    let interface = app
+        .get_selected_interface()
+        .map(|iface| iface.name.as_str())
+        .unwrap_or("none");
+    let intro = Paragraph::new(vec![
+        Line::from(format!("Boot machines on {} over the network", interface)),
+        Line::from(Span::styled(
+            "Space: Change mode | Tab: Next field | Enter: Start | Esc: Cancel",
+            gray,
+        )),
+    ]);
+    f.render_widget(intro, chunks[0]);
+
+    let fields = [
+        (app.pxe_mode.label(), "Mode"),
+        (app.pxe_root_input.value(), "TFTP Root"),
+        (
+            app.pxe_bios_input.value(),
+            "BIOS Boot File (relative to the root)",
+        ),
+        (
+            app.pxe_efi_input.value(),
+            "UEFI Boot File (empty: BIOS clients only)",
+        ),
+    ];
+    for (index, (value, title)) in fields.into_iter().enumerate() {
+        let style = if app.pxe_active_input == index {
+            Style::default().bg(Color::Blue).fg(Color::White)
+        } else {
+            Style::default()
+        };
+        let field = Paragraph::new(value)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(style);
+        f.render_widget(field, chunks[index + 1]);
+    }
+
+    let note = Paragraph::new(Span::styled(
+        "proxyDHCP only adds boot options to the LAN's own DHCP offers. DHCP mode \
+         hands out addresses itself; use it on a cable to the machine alone.",
+        Style::default().fg(Color::Yellow),
+    ))
+    .wrap(Wrap { trim: false });
+    f.render_widget(note, chunks[5]);
+}
+
+fn draw_queue_tuning_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!(
+            "{} Tune queues on {}",
+            icons::NETWORK,
+            app.queue_tuning_interface
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let gray = Style::default().fg(Color::Gray);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            "Tab: Next field | Enter: Apply | Ctrl-S: Apply and keep | Esc: Cancel",
+            gray,
+        ))
+        .wrap(Wrap { trim: true }),
+        chunks[0],
+    );
+
+    let stats = &app.queues.value;
+    let rings = stats.rings.clone().unwrap_or_default();
+    let max = |value: Option<u32>| value.map_or(String::new(), |max| format!(" (max {})", max));
+    let fields = [
+        (
+            &app.queue_rx_ring_input,
+            format!("RX ring{}", max(rings.rx_max)),
+        ),
+        (
+            &app.queue_tx_ring_input,
+            format!("TX ring{}", max(rings.tx_max)),
+        ),
+        (
+            &app.queue_rss_input,
+            format!("Spread RSS over queues (of {})", stats.rx_queues),
+        ),
+    ];
+    for (index, (input, title)) in fields.into_iter().enumerate() {
+        let style = if app.queue_tuning_active_input == index {
+            Style::default().bg(Color::Blue).fg(Color::White)
+        } else {
+            Style::default()
+        };
+        let field = Paragraph::new(input.value())
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(style);
+        f.render_widget(field, chunks[index + 1]);
+    }
+
+    let note = Paragraph::new(Span::styled(
+        "Changes go to the driver over ethtool netlink and last until the NIC \
+         is reset. Kept settings are replayed by a udev rule whenever the NIC \
+         appears; lantern tune IFACE --forget removes it. A blank field or the \
+         current value leaves that setting alone.",
+        gray,
+    ))
+    .wrap(Wrap { trim: false });
+    f.render_widget(note, chunks[4]);
+}
+
+fn draw_global_dns_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(75, 80, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!("{} Global DNS (systemd-resolved)", icons::NETWORK))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let gray = Style::default().fg(Color::Gray);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(8),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    // What all of resolved.conf and its drop-ins add up to
+    let effective = &app.global_dns_effective;
+    let list = |values: &[String]| {
+        if values.is_empty() {
+            "default".to_string()
+        } else {
+            values.join(" ")
+        }
+    };
+    let mode = |value: &Option<String>| value.clone().unwrap_or_else(|| "default".to_string());
+    let in_effect = [
+        ("DNS", list(&effective.dns)),
+        ("Fallback DNS", list(&effective.fallback_dns)),
+        ("Domains", list(&effective.domains)),
+        ("DNS over TLS", mode(&effective.dns_over_tls)),
+        ("LLMNR", mode(&effective.llmnr)),
+        ("Multicast DNS", mode(&effective.multicast_dns)),
+    ];
+    let mut lines = vec![Line::from(Span::styled("In effect:", bold))];
+    for (label, value) in in_effect {
+        lines.push(Line::from(vec![
+            Span::raw(format!("  {}: ", label)),
+            Span::styled(value, Style::default().fg(Color::Green)),
+        ]));
+    }
+    lines.push(Line::from(Span::styled(
+        "Tab: Next field | Space: Change | Enter: Save and restart resolved | Esc: Cancel",
+        gray,
+    )));
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let mode_value = |value: &Option<String>| value.as_deref().unwrap_or("not set").to_string();
+    let fields = [
+        (app.global_dns_input.value().to_string(), "DNS Servers"),
+        (
+            app.global_fallback_dns_input.value().to_string(),
+            "Fallback DNS Servers (used when no other servers are known)",
+        ),
+        (
+            app.global_domains_input.value().to_string(),
+            "Search Domains (~example.com: route only)",
+        ),
+        (mode_value(&app.global_dns_over_tls), "DNS over TLS"),
+        (mode_value(&app.global_llmnr), "LLMNR"),
+        (mode_value(&app.global_multicast_dns), "Multicast DNS"),
+    ];
+    for (index, (value, title)) in fields.into_iter().enumerate() {
+        let style = if app.global_dns_active_input == index {
+            Style::default().bg(Color::Blue).fg(Color::White)
+        } else {
+            Style::default()
+        };
+        let field = Paragraph::new(value)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(style);
+        f.render_widget(field, chunks[index + 1]);
+    }
+
+    let note = Paragraph::new(Span::styled(
+        format!(
+            "Saved to {}; an empty field or \"not set\" leaves the setting to \
+             resolved.conf and resolved's defaults.",
+            crate::resolved::DROP_IN
+        ),
+        gray,
+    ))
+    .wrap(Wrap { trim: false });
+    f.render_widget(note, chunks[7]);
+}
+
+fn draw_dns_leak_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 50, f.area());
+    f.render_widget(Clear, area);
+    let gray = Style::default().fg(Color::Gray);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let interface = &app.dns_leak_interface;
+
+    let probe_line = |probe: &Probe| match (&probe.resolver, &probe.error) {
+        (Some(resolver), _) => Line::from(vec![
+            Span::raw("   resolver "),
+            Span::styled(resolver.clone(), Style::default().fg(Color::Cyan)),
+            Span::raw(
+                probe
+                    .link
+                    .as_ref()
+                    .map(|link| format!(" via {}", link))
+                    .unwrap_or_default(),
+            ),
+        ]),
+        (None, Some(error)) => Line::from(Span::styled(
+            format!("   {}", error),
+            Style::default().fg(Color::Red),
+        )),
+        (None, None) => Line::from(Span::styled("   no answer", gray)),
+    };
+
+    let mut lines = Vec::new();
+    match &app.dns_leak {
+        None => lines.push(Line::from(Span::styled(
+            format!(
+                "Looking up {} through {} and as applications would...",
+                crate::dnsleak::PROBE_NAME,
+                interface
+            ),
+            gray,
+        ))),
+        Some(report) => {
+            lines.push(Line::from(Span::styled(
+                format!("Through {}'s DNS servers:", interface),
+                bold,
+            )));
+            lines.push(probe_line(&report.direct));
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "As applications resolve ({} lookups):",
+                    report.default_path.len()
+                ),
+                bold,
+            )));
+            lines.extend(report.default_path.iter().map(probe_line));
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![
+                Span::styled("Tunnels up: ", bold),
+                Span::raw(if report.vpns.is_empty() {
+                    "none".to_string()
+                } else {
+                    report.vpns.join(", ")
+                }),
+            ]));
+            let (text, color) = match &report.verdict {
+                Verdict::NoVpn => (
+                    "No VPN is up, so there is nothing for lookups to leak around".to_string(),
+                    Color::Gray,
+                ),
+                Verdict::NoLeak => (
+                    format!(
+                        "{} No leak: every lookup went through {}",
+                        icons::SUCCESS,
+                        report.vpns.join(", ")
+                    ),
+                    Color::Green,
+                ),
+                Verdict::Leak(links) => (
+                    format!(
+                        "{}  DNS leak: lookups went out of {} while {} is up",
+                        icons::WARNING,
+                        links.join(", "),
+                        report.vpns.join(", ")
+                    ),
+                    Color::Red,
+                ),
+                Verdict::Unknown => (
+                    "Couldn't tell which link the lookups used".to_string(),
+                    Color::Yellow,
+                ),
+            };
+            lines.push(Line::from(Span::styled(
+                text,
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            )));
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "The resolver is where lookups reach the internet from; compare it with your VPN provider's.",
+        gray,
+    )));
+    lines.push(Line::from(Span::styled("r: Run again | Esc: Close", gray)));
+
+    let dialog = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .title(format!("{} DNS Leak Test: {}", icons::NETWORK, interface))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(dialog, area);
+}
+
+fn draw_dhcp_probe_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 50, f.area());
+    f.render_widget(Clear, area);
+    let gray = Style::default().fg(Color::Gray);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let interface = &app.dhcp_probe_interface;
+
+    let mut lines = Vec::new();
+    match &app.dhcp_probe {
+        None => lines.push(Line::from(Span::styled(
+            format!(
+                "Broadcasting DHCPDISCOVER on {} and listening for offers...",
+                interface
+            ),
+            gray,
+        ))),
+        Some(report) if report.error.is_some() => lines.push(Line::from(Span::styled(
+            report.summary(),
+            Style::default().fg(Color::Red),
+        ))),
+        Some(report) => {
+            let basis = match &report.basis {
+                Basis::Configured => "Expected: the servers in [dhcp] servers".to_string(),
+                Basis::Lease(server) => {
+                    format!("Expected: {}, which gave the current lease", server)
+                }
+                Basis::Unknown => {
+                    "No lease or [dhcp] servers to go by: more than one server is a conflict"
+                        .to_string()
+                }
+            };
+            lines.push(Line::from(Span::styled(basis, gray)));
+            lines.push(Line::from(""));
+            for offer in &report.offers {
+                let (mark, color) = if offer.expected {
+                    (icons::SUCCESS.to_string(), Color::Green)
+                } else {
+                    (format!("{} ", icons::WARNING), Color::Red)
+                };
+                let mut server = vec![
+                    Span::styled(format!("{} ", mark), Style::default().fg(color)),
+                    Span::styled(offer.server.to_string(), bold.fg(color)),
+                ];
+                if offer.from != offer.server {
+                    server.push(Span::styled(format!(" via relay {}", offer.from), gray));
+                }
+                if !offer.expected {
+                    server.push(Span::styled(
+                        "  unexpected",
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                lines.push(Line::from(server));
+                lines.push(Line::from(format!("   offers {}", offer.describe())));
+            }
+            let color = match (report.offers.len(), report.unexpected()) {
+                (0, _) => Color::Yellow,
+                (_, 0) => Color::Green,
+                _ => Color::Red,
+            };
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                report.summary(),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            )));
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Offers are never accepted; the current lease is left alone.",
+        gray,
+    )));
+    lines.push(Line::from(Span::styled("r: Run again | Esc: Close", gray)));
+
+    let dialog = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .title(format!("{} DHCP Servers: {}", icons::NETWORK, interface))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(dialog, area);
+}
+
+fn draw_reachability_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 50, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!(
+            "{} IPv4 / IPv6 Reachability: {}",
+            icons::NETWORK,
+            app.reachability_interface
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let gray = Style::default().fg(Color::Gray);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(7),
+            Constraint::Length(4),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+    let footer = Paragraph::new(Span::styled("r: Run again | Esc: Close", gray));
+    f.render_widget(footer, chunks[2]);
+
+    let report = match &app.reachability {
+        None => {
+            let waiting = Paragraph::new(Span::styled(
+                "Testing address, default route, ping, DNS and HTTP over each stack...",
+                gray,
+            ));
+            f.render_widget(waiting, chunks[0]);
+            return;
+        }
+        Some(Err(e)) => {
+            let error = Paragraph::new(Span::styled(e.clone(), Style::default().fg(Color::Red)));
+            f.render_widget(error, chunks[0]);
+            return;
+        }
+        Some(Ok(report)) => report,
+    };
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[0]);
+    let stack_lines = |stack: &StackResult| -> Vec<Line> {
+        stack
+            .checks
+            .iter()
+            .map(|(check, outcome)| {
+                let (icon, color, detail) = match outcome {
+                    Outcome::Pass(detail) => (icons::SUCCESS, Color::Green, detail.as_str()),
+                    Outcome::Fail(detail) => (icons::ERROR, Color::Red, detail.as_str()),
+                    Outcome::Skipped => ("-", Color::DarkGray, "skipped"),
+                };
+                Line::from(vec![
+                    Span::styled(format!("{} ", icon), Style::default().fg(color)),
+                    Span::styled(
+                        format!("{:<14}", check.label()),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(detail.to_string(), gray),
+                ])
+            })
+            .collect()
+    };
+    for (column, stack) in columns.iter().zip([&report.v4, &report.v6]) {
+        let color = if stack.works() {
+            Color::Green
+        } else {
+            Color::Red
+        };
+        let panel = Paragraph::new(stack_lines(stack))
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(stack.family.label())
+                    .border_style(Style::default().fg(color)),
+            );
+        f.render_widget(panel, *column);
+    }
+
+    let diagnosis: Vec<Line> = [&report.v4, &report.v6]
+        .into_iter()
+        .map(|stack| {
+            let color = if stack.works() {
+                Color::Green
+            } else {
+                Color::Yellow
+            };
+            Line::from(Span::styled(stack.diagnosis(), Style::default().fg(color)))
+        })
+        .collect();
+    f.render_widget(
+        Paragraph::new(diagnosis).wrap(Wrap { trim: true }),
+        chunks[1],
+    );
+}
+
+fn draw_troubleshoot_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(75, 50, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!(
+            "{} Why Is My Network Broken? {}",
+            icons::NETWORK,
+            app.troubleshoot_interface
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let gray = Style::default().fg(Color::Gray);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(6),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+    let footer = Paragraph::new(Span::styled("r: Run again | Esc: Close", gray));
+    f.render_widget(footer, chunks[2]);
+
+    let diagnosis = match &app.troubleshoot {
+        None => {
+            let waiting = Paragraph::new(Span::styled(
+                "Checking link, address, gateway, DNS and HTTP in turn...",
+                gray,
+            ));
+            f.render_widget(waiting, chunks[0]);
+            return;
+        }
+        Some(Err(e)) => {
+            let error = Paragraph::new(Span::styled(e.clone(), Style::default().fg(Color::Red)));
+            f.render_widget(error, chunks[0]);
+            return;
+        }
+        Some(Ok(diagnosis)) => diagnosis,
+    };
+
+    let steps: Vec<Line> = diagnosis
+        .steps
+        .iter()
+        .map(|(layer, outcome)| {
+            let (icon, color, detail) = match outcome {
+                Outcome::Pass(detail) => (icons::SUCCESS, Color::Green, detail.as_str()),
+                Outcome::Fail(detail) => (icons::ERROR, Color::Red, detail.as_str()),
+                Outcome::Skipped => ("-", Color::DarkGray, "not checked"),
+            };
+            Line::from(vec![
+                Span::styled(format!("{} ", icon), Style::default().fg(color)),
+                Span::styled(
+                    format!("{:<10}", layer.label()),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(detail.to_string(), gray),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(steps), chunks[0]);
+
+    let verdict = match (diagnosis.first_failure(), &diagnosis.fix) {
+        (Some(layer), Some(fix)) => vec![
+            Line::from(Span::styled(
+                format!("Broken at: {}", layer.label()),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )),
+            Line::from(Span::styled(
+                fix.clone(),
+                Style::default().fg(Color::Yellow),
+            )),
+        ],
+        _ => vec![Line::from(Span::styled(
+            "Every layer works: what's broken is a particular site or application",
+            Style::default().fg(Color::Green),
+        ))],
+    };
+    f.render_widget(Paragraph::new(verdict).wrap(Wrap { trim: true }), chunks[1]);
+}
+
+fn draw_http_check_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(75, 60, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!("{} HTTP Check", icons::NETWORK))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let gray = Style::default().fg(Color::Gray);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Min(6),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    let url = Paragraph::new(app.http_check_input.value()).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("URL")
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+    f.render_widget(url, chunks[0]);
+
+    let binding = app
+        .http_check_bindings
+        .get(app.http_check_binding)
+        .map(|binding| binding.label())
+        .unwrap_or_default();
+    f.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("From: ", gray),
+            Span::styled(binding, bold),
+        ])),
+        chunks[1],
+    );
+    let footer = Paragraph::new(Span::styled(
+        "Enter: Fetch | Tab: Interface / source address | Esc: Close",
+        gray,
+    ));
+    f.render_widget(footer, chunks[3]);
+
+    let check = match &app.http_check {
+        None => {
+            let hint = if app.http_check_running() {
+                "Fetching..."
+            } else {
+                "Enter fetches the URL once; the body is thrown away."
+            };
+            f.render_widget(Paragraph::new(Span::styled(hint, gray)), chunks[2]);
+            return;
+        }
+        Some(Err(e)) => {
+            let error = Paragraph::new(Span::styled(e.clone(), Style::default().fg(Color::Red)));
+            f.render_widget(error, chunks[2]);
+            return;
+        }
+        Some(Ok(check)) => check,
+    };
+
+    let row = |label: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<13}", label), bold),
+            Span::styled(value, gray),
+        ])
+    };
+    let ms = |seconds: f64| format!("{:.1} ms", seconds * 1000.0);
+    let (icon, color) = if check.passed() {
+        (icons::SUCCESS, Color::Green)
+    } else {
+        (icons::ERROR, Color::Red)
+    };
+    let mut lines = vec![Line::from(Span::styled(
+        format!("{} {}", icon, check.summary()),
+        Style::default().fg(color),
+    ))];
+    if let Some(status) = check.status {
+        lines.push(row(
+            "Response",
+            format!(
+                "{} over HTTP/{}, {} bytes from {}",
+                status, check.http_version, check.bytes, check.remote_ip
+            ),
+        ));
+        let timings = &check.timings;
+        lines.push(row("DNS", ms(timings.dns)));
+        lines.push(row("Connect", ms(timings.connect)));
+        if let Some(tls) = timings.tls {
+            lines.push(row("TLS", ms(tls)));
+        }
+        lines.push(row("First byte", ms(timings.first_byte)));
+        lines.push(row("Total", ms(timings.total)));
+    }
+    if let Some(tls) = &check.tls {
+        lines.push(Line::from(""));
+        lines.push(row("Protocol", format!("{} {}", tls.version, tls.cipher)));
+        lines.push(row("Subject", tls.subject.clone()));
+        lines.push(row("Issuer", tls.issuer.clone()));
+        lines.push(row("Expires", tls.expires.clone()));
+        let (verified, color) = if tls.verified {
+            ("Chain verified", Color::Green)
+        } else {
+            ("Chain NOT verified", Color::Red)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<13}", "Certificate"), bold),
+            Span::styled(verified, Style::default().fg(color)),
+        ]));
+    }
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), chunks[2]);
+}
+
+fn draw_address_preference_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(75, 70, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!(
+            "{} IPv4 / IPv6 Preference ({})",
+            icons::NETWORK,
+            crate::gai::GAI_CONF
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let gray = Style::default().fg(Color::Gray);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(7),
+            Constraint::Length(4),
+            Constraint::Min(4),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    let explanation = Paragraph::new(vec![
+        Line::from(Span::styled(
+            "getaddrinfo() sorts a name's addresses, and most programs try them in that \
+             order. By default IPv6 comes first whenever this host has a global IPv6 address.",
+            gray,
+        )),
+        Line::from(Span::styled(
+            "Browsers race both families (Happy Eyeballs) and barely notice broken IPv6; \
+             ssh, apt, curl and most other tools wait for each IPv6 attempt to time out \
+             before trying IPv4. Preferring IPv4 fixes the wait without turning IPv6 off: \
+             IPv6-only names still work.",
+            gray,
+        )),
+    ])
+    .wrap(Wrap { trim: true });
+    f.render_widget(explanation, chunks[0]);
+
+    let saved = app.address_preference.preference;
+    let choices: Vec<Line> = [Preference::Ipv6, Preference::Ipv4]
+        .into_iter()
+        .map(|preference| {
+            let chosen = preference == app.address_preference_choice;
+            let style = if chosen {
+                Style::default().bg(Color::Blue).fg(Color::White)
+            } else {
+                Style::default()
+            };
+            let mut spans = vec![Span::styled(
+                format!(
+                    " ({}) {} ",
+                    if chosen { "*" } else { " " },
+                    preference.label()
+                ),
+                style,
+            )];
+            if preference == saved {
+                spans.push(Span::styled(
+                    "  in effect",
+                    Style::default().fg(Color::Green),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let choice = Paragraph::new(choices).block(
+        Block::default()
+            .borders(Borders::TOP)
+            .title(Span::styled("Addresses first", bold)),
+    );
+    f.render_widget(choice, chunks[1]);
+
+    let mut lines = vec![Line::from(Span::styled(
+        format!(
+            "{} resolves to, in the order programs try:",
+            crate::gai::SAMPLE_NAME
+        ),
+        bold,
+    ))];
+    match &app.address_order {
+        None => lines.push(Line::from(Span::styled("  Looking up...", gray))),
+        Some(Err(e)) => lines.push(Line::from(Span::styled(
+            format!("  {}", e),
+            Style::default().fg(Color::Red),
+        ))),
+        Some(Ok(order)) => {
+            for (index, address) in order.iter().take(4).enumerate() {
+                let family = if address.contains(':') {
+                    "IPv6"
+                } else {
+                    "IPv4"
+                };
+                lines.push(Line::from(vec![
+                    Span::raw(format!("  {}. ", index + 1)),
+                    Span::styled(address.clone(), Style::default().fg(Color::Cyan)),
+                    Span::styled(format!("  {}", family), gray),
+                ]));
+            }
+        }
+    }
+    if !app.address_preference.foreign.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{} also has precedence lines of its own, which apply together with these: {}",
+                crate::gai::GAI_CONF,
+                app.address_preference.foreign.join("; ")
+            ),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), chunks[2]);
+
+    let footer = Paragraph::new(Span::styled(
+        "Space: Change | Enter: Save | Esc: Close",
+        gray,
+    ));
+    f.render_widget(footer, chunks[3]);
+}
+
+fn draw_speed_test_panel(f: &mut Frame, app: &App) {
+    let locale = app.config.display.locale();
+    let area = centered_rect(85, 80, f.area());
+    f.render_widget(Clear, area);
+    let history = &app.speed_history;
+    let block = Block::default()
+        .title(format!(
+            "{} Speed Tests ({} recorded)",
+            icons::NETWORK,
+            history.len()
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let gray = Style::default().fg(Color::Gray);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Min(8),
+            Constraint::Length(9),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    let latest = if app.speed_test_running {
+        Line::from(Span::styled(
+            "Testing...",
+            Style::default().fg(Color::Yellow),
+        ))
+    } else {
+        match history.last() {
+            Some(result) => Line::from(vec![
+                Span::styled("Latest: ", bold),
+                Span::raw(result.summary_in(|mbps| app.config.display.mbps(mbps))),
+                Span::styled(
+                    result
+                        .time()
+                        .map(|time| format!("  {}", locale.date_time(&time)))
+                        .unwrap_or_default(),
+                    gray,
+                ),
+            ]),
+            None => Line::from(Span::styled("No tests recorded yet", gray)),
+        }
+    };
+    let schedule = match app.config.speedtest.interval() {
+        Some(_) => format!(
+            "lantern daemon tests every {} minutes",
+            app.config.speedtest.interval_mins
+        ),
+        None => "Set interval_mins under [speedtest] for lantern daemon to test on a schedule"
+            .to_string(),
+    };
+    f.render_widget(
+        Paragraph::new(vec![latest, Line::from(Span::styled(schedule, gray))]),
+        chunks[0],
+    );
+
+    // Speed over time, the tests placed where they happened, in the unit shown
+    let (factor, unit) = app.config.display.mega_rate();
+    let points = |speed: fn(&crate::speedtest::SpeedResult) -> Option<f64>| -> Vec<(f64, f64)> {
+        history
+            .iter()
+            .filter_map(|result| Some((result.time()?.timestamp() as f64, speed(result)? * factor)))
+            .collect()
+    };
+    let download = points(|result| result.download_mbps);
+    let upload = points(|result| result.upload_mbps);
+    let all = || download.iter().chain(&upload);
+    let first = all().map(|(x, _)| *x).fold(f64::INFINITY, f64::min);
+    let last = all().map(|(x, _)| *x).fold(f64::NEG_INFINITY, f64::max);
+    let top = all().map(|(_, y)| *y).fold(0.0, f64::max).max(1.0) * 1.1;
+    if first.is_finite() && last > first {
+        let date = |x: f64| {
+            chrono::DateTime::from_timestamp(x as i64, 0)
+                .map(|time| locale.short_date_time(&time.with_timezone(&chrono::Local)))
+                .unwrap_or_default()
+        };
+        let datasets = vec![
+            Dataset::default()
+                .name("Download")
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Green))
+                .data(&download),
+            Dataset::default()
+                .name("Upload")
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Cyan))
+                .data(&upload),
+        ];
+        let chart = Chart::new(datasets)
+            .block(
+                Block::default()
+                    .borders(Borders::TOP)
+                    .title(Span::styled(format!("{} over time", unit), bold)),
+            )
+            .x_axis(
+                Axis::default()
+                    .bounds([first, last])
+                    .labels(vec![
+                        Span::styled(date(first), gray),
+                        Span::styled(date(last), gray),
+                    ])
+                    .style(gray),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds([0.0, top])
+                    .labels(vec![
+                        Span::styled("0", gray),
+                        Span::styled(format!("{:.0}", top / 2.0), gray),
+                        Span::styled(format!("{:.0}", top), gray),
+                    ])
+                    .style(gray),
+            );
+        f.render_widget(chart, chunks[1]);
+    } else {
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                "The chart needs at least two tests; t runs one now",
+                gray,
+            ))
+            .block(Block::default().borders(Borders::TOP)),
+            chunks[1],
+        );
+    }
+
+    // Slow evenings stand out against the rest of the day
+    let hourly = crate::speedtest::hourly_download(history);
+    let labels: Vec<String> = (0..24).map(|hour| format!("{:02}", hour)).collect();
+    let bars: Vec<(&str, u64)> = labels
+        .iter()
+        .zip(hourly)
+        .map(|(label, average)| {
+            let average = average.unwrap_or(0.0) * factor;
+            (label.as_str(), average.round() as u64)
+        })
+        .collect();
+    let by_hour = BarChart::default()
+        .block(Block::default().borders(Borders::TOP).title(Span::styled(
+            format!("Average download by hour of day ({})", unit),
+            bold,
+        )))
+        .data(&bars)
+        .bar_width(3)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Green))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Green))
+        .label_style(gray);
+    f.render_widget(by_hour, chunks[2]);
+
+    let footer = Paragraph::new(Span::styled(
+        "t: Test now | x: Export CSV | Esc: Close",
+        gray,
+    ));
+    f.render_widget(footer, chunks[3]);
+}
+
+fn draw_palette(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!("{} Run an Action", icons::LANTERN))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    let input = Paragraph::new(app.palette_input.value())
+        .block(Block::default().borders(Borders::ALL).title("Search"));
+    f.render_widget(input, chunks[0]);
+
+    let matches = palette::matches(app.palette_input.value());
+    let height = chunks[1].height as usize;
+    // Scroll just enough to keep the selection in view
+    let skip = (app.palette_selected + 1).saturating_sub(height);
+    let width = chunks[1].width as usize;
+    let lines: Vec<Line> = if matches.is_empty() {
+        vec![Line::from(Span::styled(
+            "  No matching action",
+            Style::default().fg(Color::Gray),
+        ))]
+    } else {
+        matches
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .take(height)
+            .map(|(index, entry)| {
+                let hint = entry.key_hint().unwrap_or_default();
+                let label = format!("  {}", entry.label);
+                let padding =
+                    width.saturating_sub(label.chars().count() + hint.chars().count() + 2);
+                let style = if index == app.palette_selected {
+                    Style::default().bg(Color::Blue).fg(Color::White)
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(format!("{}{}", label, " ".repeat(padding)), style),
+                    Span::styled(format!("{}  ", hint), style.fg(Color::Yellow)),
+                ])
+            })
+            .collect()
+    };
+    f.render_widget(Paragraph::new(lines), chunks[1]);
+
+    let help = Paragraph::new(Span::styled(
+        "Type to search | ↑↓: Select | Enter: Run | Esc: Cancel",
+        Style::default().fg(Color::Gray),
+    ));
+    f.render_widget(help, chunks[2]);
+}
+
+fn draw_adopt_dialog(f: &mut Frame, app: &App) {
+    let Some(foreign) = app.foreign_managers.get(&app.adopt_interface) else {
+        return;
+    };
+    let area = centered_rect(70, 50, f.area());
+    f.render_widget(Clear, area);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(&app.adopt_interface, bold),
+            Span::raw(format!(
+                " is managed by {} ({})",
+                foreign.manager.label(),
+                foreign.source
+            )),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled("Adopting it into lantern will:", bold)),
+    ];
+    for step in foreign.release_steps(&app.adopt_interface) {
+        lines.push(Line::from(format!("  • {}", step)));
+    }
+    lines.push(Line::from(
+        "  • Enable systemd-networkd if it isn't running",
+    ));
+    lines.push(Line::from(format!(
+        "  • Write /etc/systemd/network/10-{}.network with {}",
+        app.adopt_interface,
+        foreign.imported.summary()
+    )));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!(
+            "{} keeps running for its other interfaces. The link may drop briefly.",
+            foreign.manager.label()
+        ),
+        Style::default().fg(Color::Yellow),
+    )));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "y/Enter: Adopt | any other key: Cancel",
+        Style::default().fg(Color::Gray),
+    )));
+
+    let dialog = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(format!("{} Adopt Interface", icons::NETWORK))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(dialog, area);
+}
+
+fn draw_doctor_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 70, f.area());
+    f.render_widget(Clear, area);
+    let gray = Style::default().fg(Color::Gray);
+
+    let mut lines = Vec::new();
+    match &app.doctor {
+        None => lines.push(Line::from("Reading networkd files...")),
+        Some(Err(error)) => lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        ))),
+        Some(Ok(findings)) if findings.is_empty() => {
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "{} No problems found in {}",
+                    icons::SUCCESS,
+                    doctor::NETWORKD_DIR
+                ),
+                Style::default().fg(Color::Green),
+            )));
+        }
+        Some(Ok(findings)) => {
+            let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "{}: {} errors, {} warnings, {} hints",
+                    doctor::NETWORKD_DIR,
+                    count(Severity::Error),
+                    count(Severity::Warning),
+                    count(Severity::Hint)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            lines.push(Line::from(""));
+            for (i, finding) in findings.iter().enumerate() {
+                let (icon, color) = match finding.severity {
+                    Severity::Error => (icons::ERROR, Color::Red),
+                    Severity::Warning => (icons::WARNING, Color::Yellow),
+                    Severity::Hint => (icons::INFO, Color::Cyan),
+                };
+                let name = finding
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let place = match finding.line {
+                    Some(line) => format!("{}:{}", name, line),
+                    None => name,
+                };
+                let selected = i == app.selected_finding;
+                let text_style = if selected {
+                    Style::default().bg(Color::Blue).fg(Color::White)
+                } else {
+                    Style::default()
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("{} {:<7} ", icon, finding.severity.label()),
+                        Style::default().fg(color),
+                    ),
+                    Span::styled(place, text_style.add_modifier(Modifier::BOLD)),
+                ]));
+                lines.push(Line::from(Span::styled(
+                    format!("    {}", finding.message),
+                    if selected { Style::default() } else { gray },
+                )));
+            }
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "↑/↓: Select | Enter/E: Edit the file | r: Check again | Esc: Close",
+        gray,
+    )));
+
+    let dialog = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(format!("{} Doctor", icons::SETTINGS))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(dialog, area);
+}
+
+fn draw_networkd_files_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 80, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!(
+            "{} networkd Files for {}",
+            icons::NETWORK,
+            app.networkd_files_interface
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(9), Constraint::Min(3)])
+        .split(inner);
+
+    let gray = Style::default().fg(Color::Gray);
+    let mut lines = Vec::new();
+    if let Some(error) = &app.networkd_files_error {
+        lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+    let Some(matched) = &app.networkd_files else {
+        lines.push(Line::from("  Asking networkd..."));
+        f.render_widget(Paragraph::new(lines), chunks[0]);
+        return;
+    };
+
+    let (state, color) = match matched.setup_state.as_deref() {
+        Some("unmanaged") | None => ("unmanaged".to_string(), Color::Gray),
+        Some(state @ ("failed" | "linger")) => (state.to_string(), Color::Red),
+        Some(state) => (format!("managed, {}", state), Color::Green),
+    };
+    lines.push(Line::from(vec![
+        Span::raw("  State: "),
+        Span::styled(state, Style::default().fg(color)),
+    ]));
+    if let Some(shadowed) = &matched.shadowed {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  {} Lantern's {} is not in use; networkd applies {}",
+                icons::WARNING,
+                shadowed.display(),
+                matched
+                    .network_file()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|| "no .network file".to_string())
+            ),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    if matched.files.is_empty() {
+        lines.push(Line::from("  No files match this interface"));
+    }
+    for (i, file) in matched.files.iter().enumerate() {
+        let style = if i == app.selected_networkd_file {
+            Style::default().bg(Color::Blue).fg(Color::White)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  {:<9} {}{}",
+                file.kind.label(),
+                file.path.display(),
+                if file.lantern { "  (lantern)" } else { "" }
+            ),
+            style,
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "E/Enter: Edit | i: Edit here (lantern's files) | r: Refresh | F/Esc: Close",
+        gray,
+    )));
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let preview: Vec<Line> = matched
+        .files
+        .get(app.selected_networkd_file)
+        .map(|file| match &file.contents {
+            Some(contents) => {
+                let mut lines = Vec::new();
+                if let Some(written) = provenance::read(contents) {
+                    lines.push(provenance_line(&written, app));
+                    lines.push(Line::from(""));
+                }
+                lines.extend(provenance::strip(contents).lines().map(unit_file_line));
+                lines
+            }
+            None => vec![Line::from(format!("Cannot read {}", file.path.display()))],
+        })
+        .unwrap_or_default();
+    let title = matched
+        .files
+        .get(app.selected_networkd_file)
+        .and_then(|file| file.path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let preview = Paragraph::new(preview)
+        .block(Block::default().borders(Borders::TOP).title(title))
+        .wrap(Wrap { trim: false });
+    f.render_widget(preview, chunks[1]);
+}
+
+/// Who last wrote a file of lantern's, from its header
+fn provenance_line(written: &provenance::Provenance, app: &App) -> Line<'static> {
+    let when = written
+        .when
+        .map(|when| app.config.display.locale().date_time(&when))
+        .unwrap_or_else(|| "at an unknown time".to_string());
+    Line::from(vec![
+        Span::styled(
+            format!("Last written {} by {}", when, written.by),
+            Style::default().fg(Color::Green),
+        ),
+        Span::styled(
+            format!(" (lantern {}): {}", written.version, written.operation),
+            Style::default().fg(Color::Gray),
+        ),
+    ])
+}
+
+/// One line of a .network/.netdev file, colored by what it is
+fn unit_file_line(line: &str) -> Line<'static> {
+    match rawedit::syntax(line) {
+        Syntax::Blank => Line::from(line.to_string()),
+        Syntax::Comment => Line::from(Span::styled(
+            line.to_string(),
+            Style::default().fg(Color::DarkGray),
+        )),
+        Syntax::Section(_) => Line::from(Span::styled(
+            line.to_string(),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Syntax::Setting { .. } => {
+            let (key, value) = line.split_once('=').unwrap_or((line, ""));
+            Line::from(vec![
+                Span::styled(key.to_string(), Style::default().fg(Color::Yellow)),
+                Span::styled("=", Style::default().fg(Color::DarkGray)),
+                Span::raw(value.to_string()),
+            ])
+        }
+        Syntax::Invalid => Line::from(Span::styled(
+            line.to_string(),
+            Style::default().fg(Color::Red),
+        )),
+    }
+}
+
+fn draw_raw_edit_dialog(f: &mut Frame, app: &App) {
+    let Some(editor) = &app.raw_editor else {
+        return;
+    };
+    let area = centered_rect(80, 80, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!(
+            "{} {}{}",
+            icons::NETWORK,
+            editor.path.display(),
+            if editor.modified() { " [modified]" } else { "" }
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let issue_rows = editor.issues.len().min(5) as u16;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length(issue_rows),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    // Keep the cursor's line in view; line numbers take 5 columns
+    let height = chunks[0].height as usize;
+    let scroll = (editor.row + 1).saturating_sub(height);
+    let lines: Vec<Line> = editor
+        .lines
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(height)
+        .map(|(number, text)| {
+            let marker = match editor.issues.iter().find(|issue| issue.line == number) {
+                Some(issue) if issue.error => Color::Red,
+                Some(_) => Color::Yellow,
+                None => Color::DarkGray,
+            };
+            let mut line = unit_file_line(text);
+            line.spans.insert(
+                0,
+                Span::styled(format!("{:>4} ", number + 1), Style::default().fg(marker)),
+            );
+            line
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+    let column = editor.lines[editor.row]
+        .chars()
+        .take(editor.col)
+        .collect::<String>()
+        .width() as u16;
+    f.set_cursor_position((
+        (chunks[0].x + 5 + column).min(chunks[0].right().saturating_sub(1)),
+        chunks[0].y + (editor.row - scroll) as u16,
+    ));
+
+    let issues: Vec<Line> = editor
+        .issues
+        .iter()
+        .take(issue_rows as usize)
+        .map(|issue| {
+            Line::from(Span::styled(
+                format!("  Line {}: {}", issue.line + 1, issue.message),
+                Style::default().fg(if issue.error {
+                    Color::Red
+                } else {
+                    Color::Yellow
+                }),
+            ))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(issues), chunks[1]);
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            "Ctrl-S: Save and apply | Esc: Close",
+            Style::default().fg(Color::Gray),
+        )),
+        chunks[2],
+    );
+}
+
+fn draw_copy_dialog(f: &mut Frame, app: &App) {
+    let height = app.copy_choices.len() as u16 + 4;
+    let area = centered_rect(60, 50, f.area());
+    let area = Rect {
+        height: height.min(area.height),
+        ..area
+    };
+    f.render_widget(Clear, area);
+    let mut lines: Vec<Line> = app
+        .copy_choices
+        .iter()
+        .enumerate()
+        .map(|(i, (label, value))| {
+            let style = if i == app.selected_copy {
+                Style::default().bg(Color::Blue).fg(Color::White)
+            } else {
+                Style::default()
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:<11}", label),
+                    style.add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(value.clone(), style),
+            ])
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "y/Enter: Copy | Esc: Cancel",
+        Style::default().fg(Color::Gray),
+    )));
+    let dialog = Paragraph::new(lines).block(
+        Block::default()
+            .title(format!("{} Copy to Clipboard", icons::NETWORK))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(dialog, area);
+}
+
+fn draw_jobs_panel(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 60, f.area());
+    f.render_widget(Clear, area);
+    let mut lines = Vec::new();
+    if app.jobs.jobs().is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No jobs yet. Hotspots and hotplug profiles are queued here.",
+            Style::default().fg(Color::Gray),
+        )));
+    }
+    for (i, queued) in app.jobs.jobs().iter().enumerate() {
+        let color = match queued.state {
+            JobState::Pending => Color::Gray,
+            JobState::Running => Color::Yellow,
+            JobState::Done => Color::Green,
+            JobState::Failed(_) => Color::Red,
+            JobState::Cancelled => Color::DarkGray,
+        };
+        let style = if i == app.selected_job {
+            Style::default().bg(Color::Blue).fg(Color::White)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(" {:<10}", queued.state.label()),
+                style.fg(color).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(queued.job.describe(), style),
+            Span::styled(
+                format!("  {}", locale::duration(queued.since.elapsed())),
+                Style::default().fg(Color::Gray),
+            ),
+        ]));
+        if let JobState::Failed(error) = &queued.state {
+            lines.push(Line::from(Span::styled(
+                format!("            {}", error),
+                Style::default().fg(Color::Red),
+            )));
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "c: Cancel | r: Retry | J/Esc: Close",
+        Style::default().fg(Color::Gray),
+    )));
+    let panel = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(format!("{} Jobs", icons::NETWORK))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(panel, area);
+}
+
+fn draw_neighbor_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 60, f.area());
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!(
+            "{} Static Neighbors on {}",
+            icons::NETWORK,
+            app.neighbor_interface
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(if app.modals.is_open(Modal::NeighborForm) {
+            [
+                Constraint::Min(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ]
+        } else {
+            [
+                Constraint::Min(3),
+                Constraint::Length(0),
+                Constraint::Length(0),
+            ]
+        })
+        .split(inner);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+    if let Some(error) = &app.static_neighbors_error {
+        lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+    lines.push(Line::from(Span::styled(
+        format!("  {:<40} {:<18} {}", "Address", "MAC", "State"),
+        bold,
+    )));
+    match &app.static_neighbors {
+        None => lines.push(Line::from("  Reading neighbor table...")),
+        Some(neighbors) if neighbors.is_empty() => {
+            lines.push(Line::from("  No permanent entries"));
+        }
+        Some(neighbors) => {
+            for (i, neighbor) in neighbors.iter().enumerate() {
+                let (state, color) = match (neighbor.active, neighbor.persisted) {
+                    (true, true) => ("permanent", Color::Green),
+                    (true, false) => ("until reboot", Color::Yellow),
+                    (false, _) => ("saved, not applied", Color::Gray),
+                };
+                let style =
+                    if i == app.selected_neighbor && !app.modals.is_open(Modal::NeighborForm) {
+                        Style::default().bg(Color::Blue).fg(Color::White)
+                    } else {
+                        Style::default()
+                    };
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("  {:<40} {:<18} ", neighbor.address, neighbor.mac),
+                        style,
+                    ),
+                    Span::styled(state, style.fg(color)),
+                ]));
+            }
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        if app.modals.is_open(Modal::NeighborForm) {
+            "Tab: Next field | Enter: Save | Esc: Cancel"
+        } else {
+            "a: Add | e/Enter: Edit | d: Remove | A/Esc: Close"
+        },
+        Style::default().fg(Color::Gray),
+    )));
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    if app.modals.is_open(Modal::NeighborForm) {
+        let field_style = |index: usize| {
+            if app.neighbor_active_input == index {
+                Style::default().bg(Color::Blue).fg(Color::White)
+            } else {
+                Style::default()
+            }
+        };
+        let address = Paragraph::new(app.neighbor_address_input.value())
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("IP Address (IPv4 or IPv6)"),
+            )
+            .style(field_style(0));
+        f.render_widget(address, chunks[1]);
+        let mac = Paragraph::new(app.neighbor_mac_input.value())
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("MAC Address (aa:bb:cc:dd:ee:ff)"),
+            )
+            .style(field_style(1));
+        f.render_widget(mac, chunks[2]);
+    }
+}
+
+fn draw_share_dialog(f: &mut Frame, app: &App) {
+    let Some(payload) = &app.share_payload else {
+        return;
+    };
+
+    let qr_lines = crate::qr::render_qr_lines(&payload.to_payload_string()).unwrap_or_default();
+
+    // Size the dialog to the QR code instead of a percentage of the screen
+    let qr_width = qr_lines
+        .first()
+        .map(|l| l.chars().count() as u16)
+        .unwrap_or(0);
+    let width = (qr_width + 4).max(40).min(f.area().width);
+    let height = (qr_lines.len() as u16 + 7).min(f.area().height);
+    let area = Rect {
+        x: f.area().x + (f.area().width.saturating_sub(width)) / 2,
+        y: f.area().y + (f.area().height.saturating_sub(height)) / 2,
+        width,
+        height,
+    };
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!("{} Share Network: {}", icons::WIFI, payload.ssid))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Green));
+
+    let mut lines: Vec<Line> = qr_lines
+        .into_iter()
+        .map(|l| {
+            Line::from(Span::styled(
+                l,
+                Style::default().fg(Color::White).bg(Color::Black),
+            ))
+        })
+        .collect();
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Security: ", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(format!("{:?}", payload.security)),
+    ]));
+    if payload.password.is_none() && payload.security != crate::network::WifiSecurity::Open {
+        lines.push(Line::from(Span::styled(
+            "Password unknown - connect from Lantern to save it",
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    lines.push(Line::from(Span::styled(
+        "Scan with a phone camera to join | Esc: Close",
+        Style::default().fg(Color::Gray),
+    )));
+
+    let share = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center);
+    f.render_widget(share, area);
+}
+
+fn draw_hotspot_summary(f: &mut Frame, app: &App) {
+    let (Some(hotspot), Some(payload)) = (&app.active_hotspot, app.hotspot_qr_payload()) else {
+        return;
+    };
+    let qr_lines = crate::qr::render_qr_lines(&payload.to_payload_string()).unwrap_or_default();
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{}: ", name), bold),
+            Span::raw(value),
+        ])
+    };
+    let clients = match &app.hotspot_clients_error {
+        Some(e) => Span::styled(format!("unknown ({})", e), Style::default().fg(Color::Red)),
+        None => Span::styled(
+            app.hotspot_clients.clients.len().to_string(),
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ),
+    };
+    let boot = match (hotspot.persistent, app.hotspot_unit_state.as_deref()) {
+        (false, _) => Span::styled("not started", Style::default().fg(Color::Gray)),
+        (true, Some("active") | None) => {
+            Span::styled("started (systemd)", Style::default().fg(Color::Green))
+        }
+        (true, Some(state)) => Span::styled(
+            format!("started (systemd: {})", state),
+            Style::default().fg(Color::Red),
+        ),
+    };
+    let mut lines = vec![
+        field("SSID", hotspot.ssid.clone()),
+        field("Password", hotspot.password.clone()),
+        field("Gateway", hotspot.gateway.clone()),
+        field("Channel", hotspot.channel.to_string()),
+        Line::from(vec![Span::styled("Connected: ", bold), clients]),
+        Line::from(vec![Span::styled("At boot: ", bold), boot]),
+        Line::from(""),
+    ];
+    let qr_width = qr_lines
+        .first()
+        .map(|l| l.chars().count() as u16)
+        .unwrap_or(0);
+    lines.extend(qr_lines.into_iter().map(|l| {
+        Line::from(Span::styled(
+            l,
+            Style::default().fg(Color::White).bg(Color::Black),
+        ))
+    }));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        if hotspot.persistent {
+            "Scan to join | c: Clients | p: Stop & remove from boot | Esc: Close"
+        } else {
+            "Scan to join | c: Clients | p: Keep across reboots | Esc: Close"
+        },
+        Style::default().fg(Color::Gray),
+    )));
+
+    // Sized to the QR code, like the share dialog
+    let width = (qr_width + 4).max(44).min(f.area().width);
+    let height = (lines.len() as u16 + 2).min(f.area().height);
+    let area = Rect {
+        x: f.area().x + (f.area().width.saturating_sub(width)) / 2,
+        y: f.area().y + (f.area().height.saturating_sub(height)) / 2,
+        width,
+        height,
+    };
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(format!(
+            "{} Hotspot Running on {}",
+            icons::WIFI,
+            hotspot.interface
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Green));
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .alignment(Alignment::Center),
+        area,
+    );
+}
+
+/// Physical links stay plain so the virtual ones stand out
+fn kind_color(kind: LinkKind) -> Color {
+    match kind {
+        LinkKind::Physical | LinkKind::Gadget => Color::Gray,
+        LinkKind::Bridge | LinkKind::Bond | LinkKind::Vlan => Color::LightCyan,
+        LinkKind::WireGuard | LinkKind::Tun => Color::LightGreen,
+        LinkKind::Veth => Color::LightBlue,
+        LinkKind::Dummy | LinkKind::Virtual => Color::DarkGray,
+    }
+}
+
+fn event_color(kind: EventKind) -> Color {
+    match kind {
+        EventKind::Added | EventKind::AddressAdded => Color::Green,
+        EventKind::Removed
+        | EventKind::AddressRemoved
+        | EventKind::LinkFlapping
+        | EventKind::WifiDropped
+        | EventKind::MacConflict => Color::Red,
+        EventKind::StateChanged | EventKind::WifiChanged | EventKind::EndpointChanged => {
+            Color::Yellow
+        }
     }
 }
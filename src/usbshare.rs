@@ -0,0 +1,165 @@
+// src/usbshare.rs - This machine's connection shared with the host at the other end of a USB gadget link
+use crate::command::TimedOutput;
+use crate::provenance;
+use crate::sanitize;
+use crate::secure_file;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+const NETWORK_DIR: &str = "/etc/systemd/network";
+/// Ahead of the "10-" file lantern writes for wired links, so sharing wins
+/// while it's on and the interface's own config is back once it's off
+pub const PREFIX: &str = "05";
+
+/// `[usb_share]`: the gadget side's address; the host gets one from the same subnet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsbShareSettings {
+    pub address: String,
+}
+
+impl Default for UsbShareSettings {
+    fn default() -> Self {
+        Self {
+            // What the Raspberry Pi gadget guides use
+            address: "192.168.7.1/24".to_string(),
+        }
+    }
+}
+
+pub fn network_path(interface: &str) -> PathBuf {
+    Path::new(NETWORK_DIR).join(format!("{}-{}.network", PREFIX, interface))
+}
+
+/// An IPv4 address with a prefix that leaves room for a host: 192.168.7.1/24
+fn check_address(address: &str) -> Result<()> {
+    let (ip, prefix) = address
+        .split_once('/')
+        .ok_or_else(|| anyhow!("USB share address '{}' needs a prefix, e.g. /24", address))?;
+    ip.parse::<Ipv4Addr>()
+        .with_context(|| format!("Invalid USB share address '{}'", address))?;
+    match prefix.parse::<u8>() {
+        Ok(8..=30) => Ok(()),
+        _ => Err(anyhow!(
+            "USB share prefix in '{}' must be /8 to /30",
+            address
+        )),
+    }
+}
+
+/// networkd does all of it: the address, a DHCP server handing out the rest
+/// of the subnet with this side as router and DNS from the uplink, and
+/// masquerading (which turns on forwarding) for what the host sends out
+pub fn network_file(interface: &str, address: &str) -> String {
+    format!(
+        "[Match]
+Name={interface}
+
+[Network]
+Address={address}
+DHCPServer=yes
+IPMasquerade=ipv4
+ConfigureWithoutCarrier=yes
+
+[DHCPServer]
+PoolOffset=10
+EmitRouter=yes
+EmitDNS=yes
+
+[Link]
+RequiredForOnline=no
+"
+    )
+}
+
+/// The shared address, if `network` is one of these files
+pub fn shared_address(network: &str) -> Option<String> {
+    if !network.lines().any(|line| line.trim() == "DHCPServer=yes") {
+        return None;
+    }
+    network
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Address="))
+        .map(str::to_string)
+}
+
+/// Interfaces being shared right now, with the address each has
+pub fn shares() -> HashMap<String, String> {
+    let Ok(entries) = fs::read_dir(NETWORK_DIR) else {
+        return HashMap::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let interface = name
+                .strip_prefix(&format!("{}-", PREFIX))?
+                .strip_suffix(".network")?;
+            let address = shared_address(&fs::read_to_string(entry.path()).ok()?)?;
+            Some((interface.to_string(), address))
+        })
+        .collect()
+}
+
+pub async fn share(interface: &str, settings: &UsbShareSettings) -> Result<()> {
+    sanitize::interface_name(interface)?;
+    let address = settings.address.trim();
+    check_address(address)?;
+    fs::create_dir_all(NETWORK_DIR)?;
+    let network = provenance::stamp(
+        &network_file(interface, address),
+        &format!("USB sharing on {}", interface),
+    );
+    secure_file::write_public(network_path(interface), network)
+        .context("Failed to write the USB share's .network file")?;
+    crate::systemd::reload_networkd().await?;
+    reconfigure(interface).await
+}
+
+pub async fn unshare(interface: &str) -> Result<()> {
+    sanitize::interface_name(interface)?;
+    let path = network_path(interface);
+    if path.exists() {
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    crate::systemd::reload_networkd().await?;
+    reconfigure(interface).await
+}
+
+// Drops the old address and DHCP server straight away rather than at the next carrier change
+async fn reconfigure(interface: &str) -> Result<()> {
+    Command::new("/usr/bin/networkctl")
+        .args(["reconfigure", interface])
+        .timed_output()
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_with_dhcp_and_masquerading() {
+        let network = network_file("usb0", "192.168.7.1/24");
+        assert!(network.contains("Name=usb0\n"));
+        assert!(network.contains("DHCPServer=yes\nIPMasquerade=ipv4\n"));
+        assert!(crate::systemd::validate_network_file(&network).is_ok());
+        assert_eq!(shared_address(&network).as_deref(), Some("192.168.7.1/24"));
+        assert_eq!(shared_address("[Network]\nAddress=10.0.0.1/24\n"), None);
+
+        assert!(check_address("192.168.7.1/24").is_ok());
+        assert!(check_address("192.168.7.1").is_err());
+        assert!(check_address("192.168.7.1/31").is_err());
+        assert!(check_address("fd00::1/64").is_err());
+        assert_eq!(
+            network_path("usb0"),
+            Path::new("/etc/systemd/network/05-usb0.network")
+        );
+    }
+}
@@ -0,0 +1,94 @@
+// src/wgkey.rs - Native WireGuard (Curve25519) key handling
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fs::File;
+use std::io::Read;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Generate a base64 private key, equivalent to `wg genkey`.
+pub fn generate_private_key() -> Result<String> {
+    let mut bytes = random_key()?;
+
+    // Clamp like wg genkey so the stored key is already a valid scalar
+    bytes[0] &= 248;
+    bytes[31] = (bytes[31] & 127) | 64;
+
+    Ok(STANDARD.encode(bytes))
+}
+
+/// Generate a base64 preshared key, equivalent to `wg genpsk`.
+pub fn generate_preshared_key() -> Result<String> {
+    Ok(STANDARD.encode(random_key()?))
+}
+
+/// Check that a key pasted by the user is 32 bytes of base64, as `wg` expects.
+pub fn validate_key(key: &str) -> Result<()> {
+    let decoded = STANDARD
+        .decode(key.trim())
+        .context("WireGuard key is not valid base64")?;
+    if decoded.len() != 32 {
+        return Err(anyhow::anyhow!("WireGuard key must be 32 bytes"));
+    }
+    Ok(())
+}
+
+fn random_key() -> Result<[u8; 32]> {
+    let mut bytes = [0u8; 32];
+    File::open("/dev/urandom")
+        .and_then(|mut urandom| urandom.read_exact(&mut bytes))
+        .context("Failed to read from /dev/urandom")?;
+    Ok(bytes)
+}
+
+/// Derive the base64 public key from a base64 private key, equivalent to
+/// `wg pubkey` but without the private key ever leaving this process.
+pub fn public_key_from_private(private_key: &str) -> Result<String> {
+    let decoded = STANDARD
+        .decode(private_key.trim())
+        .context("WireGuard private key is not valid base64")?;
+    let bytes: [u8; 32] = decoded
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("WireGuard private key must be 32 bytes"))?;
+
+    let secret = StaticSecret::from(bytes);
+    Ok(STANDARD.encode(PublicKey::from(&secret).as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex_to_base64(hex: &str) -> String {
+        let bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        STANDARD.encode(bytes)
+    }
+
+    #[test]
+    fn derives_rfc7748_public_key() {
+        let private =
+            hex_to_base64("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let public =
+            hex_to_base64("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a");
+        assert_eq!(public_key_from_private(&private).unwrap(), public);
+    }
+
+    #[test]
+    fn generated_key_round_trips() {
+        let private = generate_private_key().unwrap();
+        assert_eq!(STANDARD.decode(&private).unwrap().len(), 32);
+        assert!(public_key_from_private(&private).is_ok());
+        assert!(public_key_from_private("not a key").is_err());
+    }
+
+    #[test]
+    fn generated_preshared_keys_are_distinct() {
+        let first = generate_preshared_key().unwrap();
+        assert!(validate_key(&first).is_ok());
+        assert_ne!(first, generate_preshared_key().unwrap());
+        assert!(validate_key("c2hvcnQ=").is_err());
+    }
+}
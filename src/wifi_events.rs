@@ -0,0 +1,204 @@
+// src/wifi_events.rs - 802.11 disconnects, deauths and beacon loss from nl80211 (`iw event`)
+use chrono::{DateTime, Local};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+/// What the driver reported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameEventKind {
+    Disconnected { by_ap: bool, reason: Option<u16> },
+    Deauthenticated { reason: u16 },
+    Disassociated { reason: u16 },
+    BeaconLoss,
+    ConnectFailed { status: u16 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameEvent {
+    pub interface: String,
+    pub kind: FrameEventKind,
+    pub detail: String, // iw's text for the reason or status code
+}
+
+impl FrameEvent {
+    /// For the event timeline and the diagnostics dialog
+    pub fn summary(&self) -> String {
+        let what = match &self.kind {
+            FrameEventKind::Disconnected {
+                by_ap: true,
+                reason,
+            } => {
+                format!("disconnected by the AP{}", reason_suffix(*reason))
+            }
+            FrameEventKind::Disconnected {
+                by_ap: false,
+                reason,
+            } => {
+                format!("disconnected locally{}", reason_suffix(*reason))
+            }
+            FrameEventKind::Deauthenticated { reason } => {
+                format!("deauthenticated{}", reason_suffix(Some(*reason)))
+            }
+            FrameEventKind::Disassociated { reason } => {
+                format!("disassociated{}", reason_suffix(Some(*reason)))
+            }
+            FrameEventKind::BeaconLoss => "lost the AP's beacons".to_string(),
+            FrameEventKind::ConnectFailed { status } => {
+                format!("failed to connect, status {}", status)
+            }
+        };
+        if self.detail.is_empty() {
+            format!("{} {}", self.interface, what)
+        } else {
+            format!("{} {}: {}", self.interface, what, self.detail)
+        }
+    }
+
+    /// Drops worth a line in the timeline; deauth and disassoc frames come
+    /// with a disconnect carrying the same reason.
+    pub fn is_drop(&self) -> bool {
+        matches!(
+            self.kind,
+            FrameEventKind::Disconnected { .. }
+                | FrameEventKind::BeaconLoss
+                | FrameEventKind::ConnectFailed { .. }
+        )
+    }
+}
+
+/// A frame event with when it was seen, kept for the diagnostics dialog
+#[derive(Debug, Clone)]
+pub struct SeenFrameEvent {
+    pub timestamp: DateTime<Local>,
+    pub event: FrameEvent,
+}
+
+fn reason_suffix(reason: Option<u16>) -> String {
+    reason
+        .map(|r| format!(", reason {}", r))
+        .unwrap_or_default()
+}
+
+// "reason 3: Deauthenticated because ..." or "reason: 3: ...", as iw prints them
+fn parse_code(text: &str, keyword: &str) -> Option<(u16, String)> {
+    let rest = text.split_once(keyword)?.1.trim_start_matches(':').trim();
+    let (code, detail) = rest.split_once(':').unwrap_or((rest, ""));
+    Some((code.trim().parse().ok()?, detail.trim().to_string()))
+}
+
+/// Parse one line of `iw event`. Lines look like
+/// `wlan0 (phy #0): disconnected (by AP) reason: 2: Previous authentication no longer valid`;
+/// everything lantern doesn't report on is None.
+pub fn parse_iw_event(line: &str) -> Option<FrameEvent> {
+    let (interface, message) = line.split_once(" (phy #")?;
+    let message = message.split_once("): ")?.1;
+
+    let (kind, detail) = if let Some(rest) = message.strip_prefix("disconnected") {
+        let reason = parse_code(rest, "reason");
+        (
+            FrameEventKind::Disconnected {
+                by_ap: rest.contains("(by AP)"),
+                reason: reason.as_ref().map(|(code, _)| *code),
+            },
+            reason.map(|(_, detail)| detail).unwrap_or_default(),
+        )
+    } else if let Some(rest) = message.strip_prefix("deauth") {
+        let (reason, detail) = parse_code(rest, "reason")?;
+        (FrameEventKind::Deauthenticated { reason }, detail)
+    } else if let Some(rest) = message.strip_prefix("disassoc") {
+        let (reason, detail) = parse_code(rest, "reason")?;
+        (FrameEventKind::Disassociated { reason }, detail)
+    } else if message.contains("beacon loss") {
+        (FrameEventKind::BeaconLoss, String::new())
+    } else if let Some(rest) = message.strip_prefix("failed to connect") {
+        let (status, detail) = parse_code(rest, "status")?;
+        (FrameEventKind::ConnectFailed { status }, detail)
+    } else {
+        return None;
+    };
+
+    Some(FrameEvent {
+        interface: interface.trim().to_string(),
+        kind,
+        detail,
+    })
+}
+
+/// Follow `iw event` until it exits or `on_event` returns false. Without iw,
+/// or without the privileges to listen, there is simply nothing to report.
+pub async fn watch(mut on_event: impl FnMut(FrameEvent) -> bool) {
+    let Ok(mut child) = Command::new(crate::command::IW)
+        .arg("event")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+    else {
+        return;
+    };
+    let Some(stdout) = child.stdout.take() else {
+        return;
+    };
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(event) = parse_iw_event(&line) {
+            if !on_event(event) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_drops_and_their_reasons() {
+        let event = parse_iw_event(
+            "wlan0 (phy #0): disconnected (by AP) reason: 2: Previous authentication no longer valid",
+        )
+        .unwrap();
+        assert_eq!(
+            event.kind,
+            FrameEventKind::Disconnected {
+                by_ap: true,
+                reason: Some(2)
+            }
+        );
+        assert_eq!(
+            event.summary(),
+            "wlan0 disconnected by the AP, reason 2: Previous authentication no longer valid"
+        );
+        assert!(event.is_drop());
+
+        let event = parse_iw_event(
+            "wlan0 (phy #0): deauth: 00:11:22:33:44:55 -> aa:bb:cc:dd:ee:ff reason 7: Class 3 frame received from nonassociated STA",
+        )
+        .unwrap();
+        assert_eq!(event.kind, FrameEventKind::Deauthenticated { reason: 7 });
+        assert_eq!(
+            event.detail,
+            "Class 3 frame received from nonassociated STA"
+        );
+        assert!(!event.is_drop());
+
+        let event = parse_iw_event("wlan0 (phy #0): connection quality monitor event: beacon loss")
+            .unwrap();
+        assert_eq!(event.kind, FrameEventKind::BeaconLoss);
+
+        let event = parse_iw_event(
+            "wlp3s0 (phy #1): failed to connect to 00:11:22:33:44:55, status: 17: AP unable to handle additional associated STAs",
+        )
+        .unwrap();
+        assert_eq!(event.interface, "wlp3s0");
+        assert_eq!(event.kind, FrameEventKind::ConnectFailed { status: 17 });
+
+        let event = parse_iw_event("wlan0 (phy #0): disconnected (local request)").unwrap();
+        assert_eq!(event.summary(), "wlan0 disconnected locally");
+
+        assert_eq!(parse_iw_event("wlan0 (phy #0): scan started"), None);
+        assert_eq!(parse_iw_event("phy #0: reg_change"), None);
+    }
+}
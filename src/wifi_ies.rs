@@ -0,0 +1,241 @@
+// src/wifi_ies.rs - Security details from the RSN/WPA/WPS elements in iw scan output
+use crate::network::WifiSecurity;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ManagementFrameProtection {
+    #[default]
+    Disabled,
+    Capable,
+    Required,
+}
+
+/// What a BSS advertises in its beacon: cipher and key management suites
+/// from the RSN element (or the legacy WPA vendor element), WPS and 802.11w.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecurityDetails {
+    pub rsn: bool,
+    pub wpa: bool, // Legacy WPA vendor element
+    pub privacy: bool,
+    pub group_cipher: Option<String>,
+    pub pairwise_ciphers: Vec<String>,
+    pub akm_suites: Vec<String>,
+    pub mfp: ManagementFrameProtection,
+    pub wps: bool,
+}
+
+impl SecurityDetails {
+    /// The coarse security class used to pick how to connect.
+    pub fn security(&self) -> WifiSecurity {
+        let has_akm = |akm: &str| self.akm_suites.iter().any(|a| a == akm);
+        let enterprise = self
+            .akm_suites
+            .iter()
+            .any(|a| a.contains("802.1X") || a.starts_with("SUITE-B"));
+
+        if enterprise {
+            WifiSecurity::Enterprise
+        } else if has_akm("SAE") && !has_akm("PSK") && !has_akm("PSK/SHA-256") {
+            WifiSecurity::WPA3
+        } else if self.rsn {
+            // Also WPA2/WPA3 transition networks, which accept both
+            WifiSecurity::WPA2
+        } else if self.wpa {
+            WifiSecurity::WPA
+        } else if self.privacy {
+            WifiSecurity::WEP
+        } else {
+            WifiSecurity::Open
+        }
+    }
+
+    pub fn ciphers(&self) -> Vec<String> {
+        if self.pairwise_ciphers.is_empty() && self.privacy && !self.rsn && !self.wpa {
+            return vec!["WEP".to_string()];
+        }
+        self.pairwise_ciphers.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Rsn,
+    Wpa,
+    Wps,
+    Other,
+}
+
+/// Feeds the lines of one BSS entry from `iw dev <iface> scan`. Elements
+/// start with a "Name:" line one tab in; their fields follow as "* Key: value"
+/// items, the first one sometimes on the header line itself:
+///
+/// ```text
+///     RSN:     * Version: 1
+///              * Group cipher: CCMP
+///              * Pairwise ciphers: CCMP
+///              * Authentication suites: PSK SAE
+///              * Capabilities: 1-PTKSA-RC 1-GTKSA-RC MFP-capable (0x0080)
+/// ```
+#[derive(Debug, Clone)]
+pub struct ElementParser {
+    details: SecurityDetails,
+    section: Section,
+}
+
+impl Default for ElementParser {
+    fn default() -> Self {
+        Self {
+            details: SecurityDetails::default(),
+            section: Section::Other,
+        }
+    }
+}
+
+impl ElementParser {
+    pub fn feed(&mut self, line: &str) {
+        let line = line.trim();
+
+        if let Some(item) = line.strip_prefix("* ") {
+            self.item(item);
+            return;
+        }
+
+        let Some((name, rest)) = line.split_once(':') else {
+            return;
+        };
+        self.section = match name {
+            "RSN" => Section::Rsn,
+            "WPA" => Section::Wpa,
+            "WPS" => Section::Wps,
+            _ => Section::Other,
+        };
+        match self.section {
+            Section::Rsn => self.details.rsn = true,
+            Section::Wpa => self.details.wpa = true,
+            Section::Wps => self.details.wps = true,
+            Section::Other => {}
+        }
+
+        // "capability: ESS Privacy ShortSlotTime (0x0411)"
+        if name == "capability" && rest.split_whitespace().any(|flag| flag == "Privacy") {
+            self.details.privacy = true;
+        }
+
+        if let Some(item) = rest.trim().strip_prefix("* ") {
+            self.item(item);
+        }
+    }
+
+    pub fn finish(self) -> SecurityDetails {
+        self.details
+    }
+
+    fn item(&mut self, item: &str) {
+        if !matches!(self.section, Section::Rsn | Section::Wpa) {
+            return;
+        }
+        let Some((key, value)) = item.split_once(':') else {
+            return;
+        };
+        let words = || value.split_whitespace().map(String::from);
+
+        // RSN wins over the WPA element when a network advertises both
+        let primary = self.section == Section::Rsn || !self.details.rsn;
+        match key.trim() {
+            "Group cipher" if primary => self.details.group_cipher = words().next(),
+            "Pairwise ciphers" if primary => self.details.pairwise_ciphers = words().collect(),
+            "Authentication suites" => {
+                for akm in words() {
+                    if !self.details.akm_suites.contains(&akm) {
+                        self.details.akm_suites.push(akm);
+                    }
+                }
+            }
+            "Capabilities" if self.section == Section::Rsn => {
+                self.details.mfp = if value.contains("MFP-required") {
+                    ManagementFrameProtection::Required
+                } else if value.contains("MFP-capable") {
+                    ManagementFrameProtection::Capable
+                } else {
+                    ManagementFrameProtection::Disabled
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(entry: &str) -> SecurityDetails {
+        let mut parser = ElementParser::default();
+        entry.lines().for_each(|line| parser.feed(line));
+        parser.finish()
+    }
+
+    #[test]
+    fn parses_wpa3_transition_network() {
+        let details = parse(
+            "\tcapability: ESS Privacy ShortSlotTime (0x0411)\n\
+             \tRSN:\t * Version: 1\n\
+             \t\t * Group cipher: CCMP\n\
+             \t\t * Pairwise ciphers: CCMP GCMP-256\n\
+             \t\t * Authentication suites: PSK SAE\n\
+             \t\t * Capabilities: 1-PTKSA-RC 1-GTKSA-RC MFP-capable (0x0080)\n\
+             \tWPS:\t * Version: 1.0\n\
+             \t\t * Wi-Fi Protected Setup State: 2 (Configured)\n",
+        );
+
+        assert!(details.rsn && details.privacy && details.wps);
+        assert_eq!(details.group_cipher.as_deref(), Some("CCMP"));
+        assert_eq!(details.pairwise_ciphers, vec!["CCMP", "GCMP-256"]);
+        assert_eq!(details.akm_suites, vec!["PSK", "SAE"]);
+        assert_eq!(details.mfp, ManagementFrameProtection::Capable);
+        assert_eq!(details.security(), WifiSecurity::WPA2);
+    }
+
+    #[test]
+    fn classifies_security() {
+        let sae_only = parse(
+            "\tRSN:\t * Version: 1\n\
+             \t\t * Authentication suites: SAE\n\
+             \t\t * Capabilities: MFP-required MFP-capable (0x00c0)\n",
+        );
+        assert_eq!(sae_only.security(), WifiSecurity::WPA3);
+        assert_eq!(sae_only.mfp, ManagementFrameProtection::Required);
+
+        let enterprise = parse("\tRSN:\t * Authentication suites: IEEE 802.1X\n");
+        assert_eq!(enterprise.security(), WifiSecurity::Enterprise);
+
+        let wpa = parse(
+            "\tWPA:\t * Version: 1\n\
+             \t\t * Group cipher: TKIP\n\
+             \t\t * Pairwise ciphers: TKIP\n\
+             \t\t * Authentication suites: PSK\n",
+        );
+        assert_eq!(wpa.security(), WifiSecurity::WPA);
+        assert_eq!(wpa.group_cipher.as_deref(), Some("TKIP"));
+
+        let wep = parse("\tcapability: ESS Privacy (0x0011)\n");
+        assert_eq!(wep.security(), WifiSecurity::WEP);
+        assert_eq!(wep.ciphers(), vec!["WEP"]);
+
+        assert_eq!(
+            parse("\tcapability: ESS (0x0001)\n").security(),
+            WifiSecurity::Open
+        );
+    }
+
+    #[test]
+    fn other_elements_do_not_leak_into_rsn() {
+        let details = parse(
+            "\tRSN:\t * Version: 1\n\
+             \t\t * Pairwise ciphers: CCMP\n\
+             \tHT capabilities:\n\
+             \t\t * Pairwise ciphers: TKIP\n",
+        );
+        assert_eq!(details.pairwise_ciphers, vec!["CCMP"]);
+    }
+}
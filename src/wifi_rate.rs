@@ -0,0 +1,216 @@
+// src/wifi_rate.rs - Negotiated 802.11 mode from nl80211's bitrate info: standard, width, MCS, streams, guard interval
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WifiStandard {
+    /// 802.11a/b/g rates, no MCS
+    Legacy,
+    /// 802.11n
+    Ht,
+    /// 802.11ac
+    Vht,
+    /// 802.11ax
+    He,
+    /// 802.11be
+    Eht,
+}
+
+impl WifiStandard {
+    pub fn label(self) -> &'static str {
+        match self {
+            WifiStandard::Legacy => "802.11a/b/g",
+            WifiStandard::Ht => "802.11n (Wi-Fi 4)",
+            WifiStandard::Vht => "802.11ac (Wi-Fi 5)",
+            WifiStandard::He => "802.11ax (Wi-Fi 6)",
+            WifiStandard::Eht => "802.11be (Wi-Fi 7)",
+        }
+    }
+
+    /// What iw puts before "MCS", "NSS" and "GI"
+    fn prefix(self) -> &'static str {
+        match self {
+            WifiStandard::Legacy | WifiStandard::Ht => "",
+            WifiStandard::Vht => "VHT-",
+            WifiStandard::He => "HE-",
+            WifiStandard::Eht => "EHT-",
+        }
+    }
+}
+
+/// One direction's rate, as in iw's "tx bitrate:" and "rx bitrate:" lines
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkRate {
+    pub standard: WifiStandard,
+    pub bitrate_mbps: f64,
+    pub width_mhz: u32,
+    pub mcs: Option<u8>,
+    /// Spatial streams
+    pub nss: Option<u8>,
+    /// Guard interval in nanoseconds
+    pub guard_interval_ns: Option<u32>,
+}
+
+impl LinkRate {
+    /// "HE-MCS 11, 2 streams, GI 0.8 µs, 1200.9 Mbit/s"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(mcs) = self.mcs {
+            parts.push(format!("{}MCS {}", self.standard.prefix(), mcs));
+        }
+        if let Some(nss) = self.nss {
+            parts.push(format!("{} stream{}", nss, if nss == 1 { "" } else { "s" }));
+        }
+        if let Some(gi) = self.guard_interval_ns {
+            let short = match self.standard {
+                WifiStandard::Ht | WifiStandard::Vht if gi == 400 => " (short)",
+                _ => "",
+            };
+            parts.push(format!("GI {} µs{}", gi as f64 / 1000.0, short));
+        }
+        parts.push(format!("{:.1} Mbit/s", self.bitrate_mbps));
+        parts.join(", ")
+    }
+}
+
+/// The part after "tx bitrate:", e.g.
+/// "1200.9 MBit/s 80MHz HE-MCS 11 HE-NSS 2 HE-GI 0 HE-DCM 0" or
+/// "300.0 MBit/s MCS 15 40MHz short GI"
+pub fn parse_bitrate(text: &str) -> Option<LinkRate> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let bitrate_mbps = words.first()?.parse().ok()?;
+    // The number after `key`
+    let after = |key: &str| -> Option<u8> {
+        let at = words.iter().position(|word| *word == key)?;
+        words.get(at + 1)?.parse().ok()
+    };
+
+    let standard = if words.contains(&"EHT-MCS") {
+        WifiStandard::Eht
+    } else if words.contains(&"HE-MCS") {
+        WifiStandard::He
+    } else if words.contains(&"VHT-MCS") {
+        WifiStandard::Vht
+    } else if words.contains(&"MCS") {
+        WifiStandard::Ht
+    } else {
+        WifiStandard::Legacy
+    };
+    let prefix = standard.prefix();
+    let mcs = after(&format!("{}MCS", prefix));
+    let nss = match standard {
+        WifiStandard::Legacy => None,
+        // HT MCS 0-7 is one stream, 8-15 two and so on
+        WifiStandard::Ht => mcs.map(|mcs| mcs / 8 + 1),
+        _ => after(&format!("{}NSS", prefix)),
+    };
+    let guard_interval_ns = match standard {
+        WifiStandard::Legacy => None,
+        WifiStandard::Ht | WifiStandard::Vht => {
+            Some(if words.contains(&"short") { 400 } else { 800 })
+        }
+        // nl80211's HE and EHT GI: 0 = 0.8 µs, 1 = 1.6 µs, 2 = 3.2 µs
+        _ => after(&format!("{}GI", prefix)).and_then(|gi| match gi {
+            0 => Some(800),
+            1 => Some(1600),
+            2 => Some(3200),
+            _ => None,
+        }),
+    };
+    // Absent for 20 MHz; "80P80MHz" is 160 MHz of spectrum in two pieces
+    let width_mhz = words
+        .iter()
+        .filter_map(|word| word.strip_suffix("MHz"))
+        .find_map(|width| match width {
+            "80P80" => Some(160),
+            width => width.parse().ok(),
+        })
+        .unwrap_or(20);
+
+    Some(LinkRate {
+        standard,
+        bitrate_mbps,
+        width_mhz,
+        mcs,
+        nss,
+        guard_interval_ns,
+    })
+}
+
+/// The tx and rx rates in `iw dev <if> link` or `station get` output
+pub fn parse_link_rates(output: &str) -> (Option<LinkRate>, Option<LinkRate>) {
+    let mut tx = None;
+    let mut rx = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("tx bitrate:") {
+            tx = parse_bitrate(rest);
+        } else if let Some(rest) = line.strip_prefix("rx bitrate:") {
+            rx = parse_bitrate(rest);
+        }
+    }
+    (tx, rx)
+}
+
+/// "802.11ax (Wi-Fi 6), 80 MHz": the better of the two directions, since a
+/// link idle one way often reports a basic rate there
+pub fn mode_summary(tx: Option<&LinkRate>, rx: Option<&LinkRate>) -> Option<String> {
+    let best = [tx, rx]
+        .into_iter()
+        .flatten()
+        .max_by_key(|rate| (rate.standard as u8, rate.width_mhz))?;
+    Some(format!("{}, {} MHz", best.standard.label(), best.width_mhz))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_each_standard() {
+        let he = parse_bitrate(" 1200.9 MBit/s 80MHz HE-MCS 11 HE-NSS 2 HE-GI 0 HE-DCM 0").unwrap();
+        assert_eq!(
+            he,
+            LinkRate {
+                standard: WifiStandard::He,
+                bitrate_mbps: 1200.9,
+                width_mhz: 80,
+                mcs: Some(11),
+                nss: Some(2),
+                guard_interval_ns: Some(800),
+            }
+        );
+        assert_eq!(
+            he.summary(),
+            "HE-MCS 11, 2 streams, GI 0.8 µs, 1200.9 Mbit/s"
+        );
+
+        let vht = parse_bitrate("866.7 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 2").unwrap();
+        assert_eq!(vht.standard, WifiStandard::Vht);
+        assert_eq!((vht.mcs, vht.nss, vht.width_mhz), (Some(9), Some(2), 80));
+        assert_eq!(vht.guard_interval_ns, Some(400));
+
+        let ht = parse_bitrate("300.0 MBit/s MCS 15 40MHz short GI").unwrap();
+        assert_eq!(ht.standard, WifiStandard::Ht);
+        assert_eq!((ht.mcs, ht.nss, ht.width_mhz), (Some(15), Some(2), 40));
+
+        let legacy = parse_bitrate("6.0 MBit/s").unwrap();
+        assert_eq!(legacy.standard, WifiStandard::Legacy);
+        assert_eq!((legacy.mcs, legacy.width_mhz), (None, 20));
+        assert_eq!(legacy.summary(), "6.0 Mbit/s");
+
+        let eht = parse_bitrate("2882.4 MBit/s 320MHz EHT-MCS 13 EHT-NSS 2 EHT-GI 1").unwrap();
+        assert_eq!((eht.standard, eht.width_mhz), (WifiStandard::Eht, 320));
+        assert_eq!(eht.guard_interval_ns, Some(1600));
+
+        let (tx, rx) = parse_link_rates(
+            "Connected to aa:bb:cc:dd:ee:ff (on wlan0)\n\
+             \trx bitrate: 6.0 MBit/s\n\
+             \ttx bitrate: 1200.9 MBit/s 80MHz HE-MCS 11 HE-NSS 2 HE-GI 0 HE-DCM 0\n",
+        );
+        assert_eq!(
+            mode_summary(tx.as_ref(), rx.as_ref()).as_deref(),
+            Some("802.11ax (Wi-Fi 6), 80 MHz")
+        );
+        assert_eq!(mode_summary(None, None), None);
+    }
+}
@@ -5,7 +5,7 @@ use std::process::Command;
 fn test_binary_exists() {
     // Test that the binary can be built
     let output = Command::new("cargo")
-        .args(&["build", "--release"])
+        .args(["build", "--release"])
         .output()
         .expect("Failed to run cargo build");
 
@@ -15,7 +15,7 @@ fn test_binary_exists() {
 #[test]
 fn test_version_output() {
     // Try to build first, then test --version flag
-    let build_output = Command::new("cargo").args(&["build", "--release"]).output();
+    let build_output = Command::new("cargo").args(["build", "--release"]).output();
 
     if build_output.is_err() {
         println!("Skipping version test - cargo build failed in CI");
@@ -44,7 +44,7 @@ fn test_version_output() {
 #[test]
 fn test_help_output() {
     // Try to build first, then test --help flag
-    let build_output = Command::new("cargo").args(&["build", "--release"]).output();
+    let build_output = Command::new("cargo").args(["build", "--release"]).output();
 
     if build_output.is_err() {
         println!("Skipping help test - cargo build failed in CI");
@@ -73,7 +73,7 @@ fn test_help_output() {
 #[test]
 fn test_cli_mode_without_root() {
     // Try to build first, then test CLI mode without root
-    let build_output = Command::new("cargo").args(&["build", "--release"]).output();
+    let build_output = Command::new("cargo").args(["build", "--release"]).output();
 
     if build_output.is_err() {
         println!("Skipping CLI test - cargo build failed in CI");
@@ -81,7 +81,7 @@ fn test_cli_mode_without_root() {
     }
 
     let output = Command::new("./target/release/lantern")
-        .args(&["--cli"])
+        .args(["--cli"])
         .output();
 
     if let Ok(output) = output {
@@ -0,0 +1,238 @@
+// Integration tests against real interfaces in throwaway network namespaces.
+// They need root (CAP_NET_ADMIN) and skip themselves otherwise, so a plain
+// `cargo test` stays green on developer machines; CI runs them under sudo.
+use lantern::network::{Interface, NetworkManager};
+use nix::mount::{mount, umount2, MntFlags, MsFlags};
+use nix::sched::{setns, unshare, CloneFlags};
+use std::future::Future;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A network namespace deleted, with everything in it, on drop.
+struct NetNs {
+    name: String,
+}
+
+impl NetNs {
+    fn create() -> Option<Self> {
+        let name = format!(
+            "lantern-test-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::SeqCst)
+        );
+        if !run("/usr/bin/ip", &["netns", "add", &name])
+            .status
+            .success()
+        {
+            return None;
+        }
+        let netns = NetNs { name };
+        netns.ip(&["link", "set", "lo", "up"]);
+        Some(netns)
+    }
+
+    /// Run `ip` inside the namespace and panic if it fails.
+    fn ip(&self, args: &[&str]) {
+        let mut full = vec!["-n", self.name.as_str()];
+        full.extend_from_slice(args);
+        let output = run("/usr/bin/ip", &full);
+        assert!(
+            output.status.success(),
+            "ip {:?} failed: {}",
+            full,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    fn exec(&self, program: &str, args: &[&str]) -> Output {
+        let mut full = vec!["netns", "exec", self.name.as_str(), program];
+        full.extend_from_slice(args);
+        run("/usr/bin/ip", &full)
+    }
+
+    /// Run lantern's library code inside the namespace, on a thread of its
+    /// own: the `ip` it spawns, its netlink sockets and its /sys reads all
+    /// see the namespace's interfaces, like under `ip netns exec`.
+    fn run<T, Fut>(&self, work: impl FnOnce(NetworkManager) -> Fut + Send + 'static) -> T
+    where
+        Fut: Future<Output = T>,
+        T: Send + 'static,
+    {
+        let path = format!("/run/netns/{}", self.name);
+        std::thread::spawn(move || {
+            let netns = std::fs::File::open(&path).expect("namespace should exist");
+            setns(netns, CloneFlags::CLONE_NEWNET).expect("setns");
+            // /sys shows the namespace that mounted it; mount a fresh one
+            // in a mount namespace private to this thread
+            unshare(CloneFlags::CLONE_NEWNS | CloneFlags::CLONE_FS).expect("unshare");
+            mount(
+                None::<&str>,
+                "/",
+                None::<&str>,
+                MsFlags::MS_REC | MsFlags::MS_PRIVATE,
+                None::<&str>,
+            )
+            .expect("make / private");
+            umount2("/sys", MntFlags::MNT_DETACH).expect("unmount /sys");
+            mount(
+                Some("sysfs"),
+                "/sys",
+                Some("sysfs"),
+                MsFlags::empty(),
+                None::<&str>,
+            )
+            .expect("mount sysfs");
+
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("runtime")
+                .block_on(work(NetworkManager::new()))
+        })
+        .join()
+        .expect("namespace thread panicked")
+    }
+
+    fn interface(&self, name: &'static str) -> Interface {
+        let interfaces = self
+            .run(|manager| async move { manager.get_interfaces().await })
+            .expect("get_interfaces should work in a bare namespace");
+        interfaces
+            .into_iter()
+            .find(|iface| iface.name == name)
+            .unwrap_or_else(|| panic!("{} missing", name))
+    }
+}
+
+impl Drop for NetNs {
+    fn drop(&mut self) {
+        let _ = run("/usr/bin/ip", &["netns", "delete", &self.name]);
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Output {
+    Command::new(program)
+        .args(args)
+        .output()
+        .unwrap_or_else(|e| panic!("Failed to run {}: {}", program, e))
+}
+
+/// Two namespaces joined by a veth pair: veth0 in `left`, veth1 in `right`.
+/// None when namespaces can't be created here (not root, no iproute2).
+fn veth_pair() -> Option<(NetNs, NetNs)> {
+    if !nix::unistd::Uid::effective().is_root() {
+        println!("Skipping: network namespace tests need root");
+        return None;
+    }
+    let (Some(left), Some(right)) = (NetNs::create(), NetNs::create()) else {
+        println!("Skipping: could not create network namespaces");
+        return None;
+    };
+    left.ip(&[
+        "link",
+        "add",
+        "veth0",
+        "type",
+        "veth",
+        "peer",
+        "name",
+        "veth1",
+        "netns",
+        &right.name,
+    ]);
+    left.ip(&["link", "set", "veth0", "up"]);
+    right.ip(&["link", "set", "veth1", "up"]);
+    Some((left, right))
+}
+
+fn has_address(interface: &Interface, address: &str) -> bool {
+    interface.ipv4_addresses.iter().any(|a| a == address)
+}
+
+#[test]
+fn assigns_and_removes_addresses() {
+    let Some((left, _right)) = veth_pair() else {
+        return;
+    };
+    let add = |address: &'static str| {
+        left.run(move |manager| async move { manager.add_ip_address("veth0", address).await })
+    };
+
+    add("10.200.0.1/24").expect("adding a fresh address");
+    let veth0 = left.interface("veth0");
+    assert!(has_address(&veth0, "10.200.0.1/24"), "{:?}", veth0);
+    assert!(veth0.admin_up);
+    assert_eq!(veth0.carrier, Some(true));
+
+    // The kernel refuses duplicates and garbage; lantern must report it
+    assert!(add("10.200.0.1/24").is_err());
+    assert!(add("10.200.0.300/24").is_err());
+
+    left.run(|manager| async move { manager.remove_ip_address("veth0", "10.200.0.1/24").await })
+        .expect("removing the address");
+    assert!(!has_address(&left.interface("veth0"), "10.200.0.1/24"));
+}
+
+#[test]
+fn lists_routes_through_an_interface() {
+    let Some((left, _right)) = veth_pair() else {
+        return;
+    };
+    left.ip(&["address", "add", "10.200.0.1/24", "dev", "veth0"]);
+    left.ip(&[
+        "route",
+        "add",
+        "10.201.0.0/24",
+        "via",
+        "10.200.0.2",
+        "metric",
+        "50",
+    ]);
+
+    let routes = left
+        .run(|manager| async move { manager.get_routes("veth0").await })
+        .expect("listing routes");
+    let static_route = routes
+        .iter()
+        .find(|route| route.destination == "10.201.0.0/24")
+        .unwrap_or_else(|| panic!("10.201.0.0/24 missing from {:?}", routes));
+    assert_eq!(static_route.gateway.as_deref(), Some("10.200.0.2"));
+    assert_eq!(static_route.metric, Some(50));
+    assert!(!static_route.ipv6);
+    assert!(routes
+        .iter()
+        .any(|route| route.destination == "10.200.0.0/24" && route.gateway.is_none()));
+}
+
+#[test]
+fn counts_traffic_across_a_veth_pair() {
+    let Some((left, right)) = veth_pair() else {
+        return;
+    };
+    left.ip(&["address", "add", "10.200.0.1/24", "dev", "veth0"]);
+    right.ip(&["address", "add", "10.200.0.2/24", "dev", "veth1"]);
+
+    // Three UDP datagrams to the discard port; bash's /dev/udp avoids
+    // depending on ping or netcat being installed
+    let sent = left.exec(
+        "/bin/bash",
+        &[
+            "-c",
+            "for _ in 1 2 3; do echo lantern > /dev/udp/10.200.0.2/9; done",
+        ],
+    );
+    assert!(
+        sent.status.success(),
+        "{}",
+        String::from_utf8_lossy(&sent.stderr)
+    );
+
+    let sender = left.interface("veth0").stats;
+    let receiver = right.interface("veth1").stats;
+    assert!(sender.tx_packets >= 3, "{:?}", sender);
+    assert!(receiver.rx_packets >= 3, "{:?}", receiver);
+    assert!(receiver.rx_bytes > 0);
+    assert_eq!(sender.tx_errors, 0);
+}